@@ -99,7 +99,64 @@ fn compile_shaders() {
     );
 }
 
+/// Compile the shaders used by the built-in IBL passes. These are written to `OUT_DIR` and embedded into the library.
+#[cfg(feature = "shaderc")]
+fn compile_ibl_shaders() {
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    for name in ["equirect_to_cubemap", "irradiance", "prefilter"] {
+        let source = format!("src/ibl/shaders/{name}.comp");
+        println!("cargo:rerun-if-changed={source}");
+        compile_shader(
+            Path::new(&source),
+            shaderc::ShaderKind::Compute,
+            &Path::new(&out_dir).join(format!("{name}.spv")),
+        );
+    }
+}
+
+/// Compile the shaders used by the egui renderer. These are written to `OUT_DIR` and embedded into the library.
+#[cfg(feature = "egui")]
+fn compile_egui_shaders() {
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    for (name, kind) in [
+        ("egui.vert", shaderc::ShaderKind::Vertex),
+        ("egui.frag", shaderc::ShaderKind::Fragment),
+    ] {
+        let source = format!("src/egui/shaders/{name}");
+        println!("cargo:rerun-if-changed={source}");
+        compile_shader(
+            Path::new(&source),
+            kind,
+            &Path::new(&out_dir).join(format!("{name}.spv")),
+        );
+    }
+}
+
+/// Compile the shaders used by the Dear ImGui renderer. These are written to `OUT_DIR` and embedded into the library.
+#[cfg(feature = "imgui")]
+fn compile_imgui_shaders() {
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    for (name, kind) in [
+        ("imgui.vert", shaderc::ShaderKind::Vertex),
+        ("imgui.frag", shaderc::ShaderKind::Fragment),
+    ] {
+        let source = format!("src/imgui/shaders/{name}");
+        println!("cargo:rerun-if-changed={source}");
+        compile_shader(
+            Path::new(&source),
+            kind,
+            &Path::new(&out_dir).join(format!("{name}.spv")),
+        );
+    }
+}
+
 fn main() {
     #[cfg(feature = "shaderc")]
     compile_shaders();
+    #[cfg(feature = "shaderc")]
+    compile_ibl_shaders();
+    #[cfg(feature = "egui")]
+    compile_egui_shaders();
+    #[cfg(feature = "imgui")]
+    compile_imgui_shaders();
 }
@@ -72,7 +72,7 @@ impl ExampleApp for Compute {
             .begin_query(&stats, query)
             .bind_compute_pipeline("compute")?
             .bind_storage_buffer(0, 0, &self.buffer.view_full())?
-            .push_constant(vk::ShaderStageFlags::COMPUTE, 0, &multiplier)
+            .push_constant(vk::ShaderStageFlags::COMPUTE, 0, &multiplier)?
             .dispatch(1024, 1, 1)?
             .end_query(&stats, query)
             .write_timestamp(&mut timestamps, PipelineStage::COMPUTE_SHADER)?
@@ -39,7 +39,7 @@ struct RaytracingSample {
     sampler: Sampler,
 }
 
-fn make_input_buffer<T: Copy>(
+fn make_input_buffer<T: bytemuck::Pod>(
     ctx: &mut Context,
     data: &[T],
     alignment: Option<u64>,
@@ -293,6 +293,7 @@ impl ExampleApp for RaytracingSample {
                 samples: vk::SampleCountFlags::TYPE_1,
                 mip_levels: 1,
                 layers: 1,
+                flags: vk::ImageCreateFlags::empty(),
                 memory_type: phobos::MemoryType::GpuOnly
             },
         )?;
@@ -335,8 +336,8 @@ impl ExampleApp for RaytracingSample {
                 let projection =
                     Mat4::perspective_rh(90.0_f32.to_radians(), 800.0 / 600.0, 0.001, 100.0);
                 cmd.bind_ray_tracing_pipeline("rt")?
-                    .push_constant(vk::ShaderStageFlags::RAYGEN_KHR, 0, &view)
-                    .push_constant(vk::ShaderStageFlags::RAYGEN_KHR, 64, &projection)
+                    .push_constant(vk::ShaderStageFlags::RAYGEN_KHR, 0, &view)?
+                    .push_constant(vk::ShaderStageFlags::RAYGEN_KHR, 64, &projection)?
                     .bind_acceleration_structure(0, 0, &self.tlas.accel)?
                     .resolve_and_bind_storage_image(0, 1, &rt_image, bindings)?
                     .trace_rays(800, 600, 1)
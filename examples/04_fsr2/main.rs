@@ -61,6 +61,7 @@ mod fsr2_example {
                     samples: vk::SampleCountFlags::TYPE_1,
                     mip_levels: 1,
                     layers: 1,
+                    flags: vk::ImageCreateFlags::empty(),
                     memory_type: phobos::MemoryType::GpuOnly
                 },
             )?;
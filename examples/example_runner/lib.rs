@@ -176,7 +176,8 @@ macro_rules! ubo_struct_assign {
             }
 
             let mut buffer_name = $pool.allocate_scratch_buffer(std::mem::size_of::<$name>() as vk::DeviceSize)?;
-            let $var = buffer_name.mapped_slice::<$name>()?;
+            // SAFETY: All fields are assigned below before the buffer is used.
+            let $var = unsafe { buffer_name.mapped_slice_unchecked::<$name>()? };
             let mut $var = $var.get_mut(0).unwrap();
 
             $(
@@ -203,35 +204,11 @@ pub fn create_shader(path: &str, stage: vk::ShaderStageFlags) -> ShaderCreateInf
 }
 
 #[allow(dead_code)]
-pub fn staged_buffer_upload<T: Copy>(
+pub fn staged_buffer_upload<T: bytemuck::Pod>(
     mut ctx: Context,
     data: &[T],
 ) -> Result<Buffer> {
-    let staging = Buffer::new(
-        ctx.device.clone(),
-        &mut ctx.allocator,
-        data.len() as u64 * std::mem::size_of::<T>() as u64,
-        MemoryType::CpuToGpu,
-    )?;
-
-    let mut staging_view = staging.view_full();
-    staging_view.mapped_slice()?.copy_from_slice(data);
-
-    let buffer = Buffer::new_device_local(
-        ctx.device,
-        &mut ctx.allocator,
-        staging.size(),
-    )?;
-    let view = buffer.view_full();
-
-    let cmd = ctx
-        .exec
-        .on_domain::<domain::Transfer>()?
-        .copy_buffer(&staging_view, &view)?
-        .finish()?;
-
-    ctx.exec.submit(cmd)?.wait()?;
-    Ok(buffer)
+    Buffer::from_data(&mut ctx.allocator, &ctx.exec, data)?.wait()
 }
 
 #[derive(Debug)]
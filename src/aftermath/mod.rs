@@ -0,0 +1,317 @@
+//! Integration with [Nsight Aftermath](https://developer.nvidia.com/nsight-aftermath) GPU crash dumps. This requires the `aftermath` feature.
+//!
+//! The Aftermath library is loaded at runtime, so it must be available next to the executable or in the library search path
+//! (`GFSDK_Aftermath_Lib.x64.dll` on Windows, `libGFSDK_Aftermath_Lib.x64.so` on Linux). To collect crash dumps:
+//! - Create a [`GpuCrashTracker`] *before* the device is created, since Aftermath only watches devices created after it is enabled.
+//! - Enable device diagnostics with [`AppBuilder::device_diagnostics()`](crate::AppBuilder::device_diagnostics), so crash dumps
+//!   contain shader debug information and checkpoints.
+//! - After device creation, call [`GpuCrashTracker::watch()`]. When device loss is observed, phobos waits until the crash dump
+//!   was written before returning the error.
+//! - Register shader binaries with [`GpuCrashTracker::register_pipeline_cache()`] after registering pipelines, so Nsight Graphics
+//!   can map the crash to shader source.
+//!
+//! Crash dumps (`.nv-gpudmp`), shader debug information (`.nvdbg`) and shader binaries (`.spv`) are all written to the dump directory,
+//! which can be opened in Nsight Graphics.
+//! # Example
+//! ```
+//! # use anyhow::Result;
+//! # use phobos::prelude::*;
+//! # use phobos::aftermath::GpuCrashTracker;
+//! fn enable_crash_dumps(device: &Device, cache: &PipelineCache) -> Result<GpuCrashTracker> {
+//!     // This should be created before the device
+//!     let tracker = GpuCrashTracker::new("crash_dumps")?;
+//!     tracker.watch(device);
+//!     tracker.register_pipeline_cache(cache)?;
+//!     Ok(tracker)
+//! }
+//! ```
+
+use std::ffi::c_void;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Result};
+use libloading::Library;
+
+use crate::{Allocator, Device, PipelineCache, ShaderCreateInfo};
+
+/// Version of the Aftermath API these bindings are written against. The loaded library must support this version.
+pub const AFTERMATH_API_VERSION: u32 = 0x0000215;
+
+/// How long to wait for Aftermath to finish writing a crash dump after device loss.
+const CRASH_DUMP_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[cfg(windows)]
+const LIBRARY_NAME: &str = "GFSDK_Aftermath_Lib.x64.dll";
+#[cfg(not(windows))]
+const LIBRARY_NAME: &str = "libGFSDK_Aftermath_Lib.x64.so";
+
+const WATCHED_API_VULKAN: u32 = 0x2;
+
+type GpuCrashDumpCallback = unsafe extern "C" fn(dump: *const c_void, size: u32, user_data: *mut c_void);
+type ShaderDebugInfoCallback = unsafe extern "C" fn(debug_info: *const c_void, size: u32, user_data: *mut c_void);
+
+#[repr(C)]
+struct SpirvCode {
+    data: *const c_void,
+    size: u32,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct ShaderBinaryHash {
+    hash: u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct ShaderDebugInfoIdentifier {
+    id: [u64; 2],
+}
+
+// Values of `GFSDK_Aftermath_CrashDump_Status`.
+const STATUS_NOT_STARTED: u32 = 0;
+const STATUS_COLLECTING_DATA_FAILED: u32 = 2;
+const STATUS_FINISHED: u32 = 4;
+const STATUS_UNKNOWN: u32 = 5;
+
+/// Function pointers loaded from the Aftermath library. These stay valid as long as the library is loaded.
+#[derive(Copy, Clone)]
+struct Functions {
+    enable_gpu_crash_dumps: unsafe extern "C" fn(
+        version: u32,
+        watched_apis: u32,
+        flags: u32,
+        crash_dump_cb: Option<GpuCrashDumpCallback>,
+        shader_debug_info_cb: Option<ShaderDebugInfoCallback>,
+        description_cb: *const c_void,
+        resolve_marker_cb: *const c_void,
+        user_data: *mut c_void,
+    ) -> u32,
+    disable_gpu_crash_dumps: unsafe extern "C" fn() -> u32,
+    get_crash_dump_status: unsafe extern "C" fn(status: *mut u32) -> u32,
+    get_shader_hash_spirv: unsafe extern "C" fn(version: u32, shader: *const SpirvCode, hash: *mut ShaderBinaryHash) -> u32,
+    get_shader_debug_info_identifier: unsafe extern "C" fn(
+        version: u32,
+        debug_info: *const c_void,
+        size: u32,
+        identifier: *mut ShaderDebugInfoIdentifier,
+    ) -> u32,
+}
+
+/// Aftermath results are failures if their upper bits are `0xBAD`.
+fn check(result: u32, function: &str) -> Result<()> {
+    if result & 0xFFF00000 == 0xBAD00000 {
+        Err(anyhow!("Aftermath call {function} failed with result {result:#x}"))
+    } else {
+        Ok(())
+    }
+}
+
+/// State shared with the Aftermath callbacks, which are called from a thread owned by Aftermath, and with device lost handlers.
+struct TrackerState {
+    // Keeps the function pointers valid for as long as the state is alive.
+    _library: Library,
+    functions: Functions,
+    dump_dir: PathBuf,
+    crash_dumps: Mutex<Vec<PathBuf>>,
+}
+
+impl TrackerState {
+    fn write(&self, name: &str, data: &[u8]) -> Option<PathBuf> {
+        let path = self.dump_dir.join(name);
+        match fs::write(&path, data) {
+            Ok(()) => Some(path),
+            Err(e) => {
+                error!("Failed to write {}: {e}", path.display());
+                None
+            }
+        }
+    }
+
+    fn crash_dump_status(&self) -> Result<u32> {
+        let mut status = STATUS_UNKNOWN;
+        // SAFETY: The status pointer is valid for writes.
+        check(unsafe { (self.functions.get_crash_dump_status)(&mut status) }, "GFSDK_Aftermath_GetCrashDumpStatus")?;
+        Ok(status)
+    }
+
+    fn wait_for_crash_dump(&self, timeout: Duration) -> Result<Option<PathBuf>> {
+        let start = Instant::now();
+        loop {
+            match self.crash_dump_status()? {
+                STATUS_FINISHED => return Ok(self.crash_dumps.lock().unwrap().last().cloned()),
+                STATUS_COLLECTING_DATA_FAILED => return Err(anyhow!("Aftermath failed to collect GPU crash dump data")),
+                STATUS_UNKNOWN => return Ok(None),
+                // Nothing is collected when the device was lost for a reason Aftermath does not detect.
+                STATUS_NOT_STARTED if start.elapsed() > timeout => return Ok(None),
+                _ if start.elapsed() > timeout => return Err(anyhow!("Timed out waiting for GPU crash dump")),
+                _ => std::thread::sleep(Duration::from_millis(50)),
+            }
+        }
+    }
+}
+
+unsafe extern "C" fn on_gpu_crash_dump(dump: *const c_void, size: u32, user_data: *mut c_void) {
+    // SAFETY: The user data is the tracker state, which outlives the registration of this callback.
+    let state = &*(user_data as *const TrackerState);
+    let data = std::slice::from_raw_parts(dump as *const u8, size as usize);
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    if let Some(path) = state.write(&format!("crash-{timestamp}.nv-gpudmp"), data) {
+        error!("GPU crash dump written to {}", path.display());
+        state.crash_dumps.lock().unwrap().push(path);
+    }
+}
+
+unsafe extern "C" fn on_shader_debug_info(debug_info: *const c_void, size: u32, user_data: *mut c_void) {
+    // SAFETY: The user data is the tracker state, which outlives the registration of this callback.
+    let state = &*(user_data as *const TrackerState);
+    let mut identifier = ShaderDebugInfoIdentifier::default();
+    let result = (state.functions.get_shader_debug_info_identifier)(AFTERMATH_API_VERSION, debug_info, size, &mut identifier);
+    if let Err(e) = check(result, "GFSDK_Aftermath_GetShaderDebugInfoIdentifier") {
+        error!("{e}");
+        return;
+    }
+    let data = std::slice::from_raw_parts(debug_info as *const u8, size as usize);
+    state.write(&format!("shader-{:016x}{:016x}.nvdbg", identifier.id[0], identifier.id[1]), data);
+}
+
+/// Collects GPU crash dumps through Nsight Aftermath. See the [module-level documentation](self) for more information.
+///
+/// Crash dump collection stays enabled until this is dropped.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct GpuCrashTracker {
+    #[derivative(Debug = "ignore")]
+    state: Arc<TrackerState>,
+}
+
+impl GpuCrashTracker {
+    /// Load the Aftermath library and enable GPU crash dumps for Vulkan devices created after this call.
+    /// All files are written to `dump_dir`, which is created if it does not exist.
+    /// # Errors
+    /// * Fails if the dump directory could not be created.
+    /// * Fails if the Aftermath library could not be loaded.
+    /// * Fails if GPU crash dumps could not be enabled, for example because the driver is not supported.
+    pub fn new(dump_dir: impl Into<PathBuf>) -> Result<Self> {
+        let dump_dir = dump_dir.into();
+        fs::create_dir_all(&dump_dir)?;
+        // SAFETY: Loading the Aftermath library does not run any initialization with preconditions.
+        let library = unsafe { Library::new(LIBRARY_NAME)? };
+        // SAFETY: These symbols are declared with the signatures from the Aftermath headers.
+        let functions = unsafe {
+            Functions {
+                enable_gpu_crash_dumps: *library.get(b"GFSDK_Aftermath_EnableGpuCrashDumps\0")?,
+                disable_gpu_crash_dumps: *library.get(b"GFSDK_Aftermath_DisableGpuCrashDumps\0")?,
+                get_crash_dump_status: *library.get(b"GFSDK_Aftermath_GetCrashDumpStatus\0")?,
+                get_shader_hash_spirv: *library.get(b"GFSDK_Aftermath_GetShaderHashSpirv\0")?,
+                get_shader_debug_info_identifier: *library.get(b"GFSDK_Aftermath_GetShaderDebugInfoIdentifier\0")?,
+            }
+        };
+        let state = Arc::new(TrackerState {
+            _library: library,
+            functions,
+            dump_dir,
+            crash_dumps: Mutex::new(Vec::new()),
+        });
+        // SAFETY: The state pointer stays valid until crash dumps are disabled when the tracker is dropped.
+        let result = unsafe {
+            (functions.enable_gpu_crash_dumps)(
+                AFTERMATH_API_VERSION,
+                WATCHED_API_VULKAN,
+                0,
+                Some(on_gpu_crash_dump),
+                Some(on_shader_debug_info),
+                std::ptr::null(),
+                std::ptr::null(),
+                Arc::as_ptr(&state) as *mut c_void,
+            )
+        };
+        check(result, "GFSDK_Aftermath_EnableGpuCrashDumps")?;
+        Ok(Self {
+            state,
+        })
+    }
+
+    /// Wait for the crash dump to be written when device loss is observed on `device`, before the error is returned.
+    /// See [`Device::on_device_lost()`].
+    pub fn watch(&self, device: &Device) {
+        let state = self.state.clone();
+        device.on_device_lost(move || match state.wait_for_crash_dump(CRASH_DUMP_TIMEOUT) {
+            Ok(Some(_)) => {}
+            Ok(None) => warn!("Device lost, but Aftermath did not write a GPU crash dump."),
+            Err(e) => error!("Failed to write GPU crash dump: {e}"),
+        });
+    }
+
+    /// Wait until Aftermath finished writing the crash dump for a lost device, and return its path. Returns `None`
+    /// if no crash dump was collected within `timeout`, for example because the device was not lost.
+    /// # Errors
+    /// * Fails if Aftermath failed to collect the crash dump.
+    /// * Fails if collecting the crash dump did not finish within `timeout`.
+    pub fn wait_for_crash_dump(&self, timeout: Duration) -> Result<Option<PathBuf>> {
+        self.state.wait_for_crash_dump(timeout)
+    }
+
+    /// Get the paths of all crash dumps written so far.
+    pub fn crash_dumps(&self) -> Vec<PathBuf> {
+        self.state.crash_dumps.lock().unwrap().clone()
+    }
+
+    /// Get the directory crash dumps and shader files are written to.
+    pub fn dump_dir(&self) -> &Path {
+        &self.state.dump_dir
+    }
+
+    /// Write a shader binary to the dump directory, named after its Aftermath shader hash, so Nsight Graphics can find it
+    /// when inspecting a crash dump. Shaders that were already written are skipped.
+    /// # Errors
+    /// * Fails if the shader hash could not be computed.
+    /// * Fails if the shader binary could not be written.
+    pub fn register_shader(&self, shader: &ShaderCreateInfo) -> Result<()> {
+        let code = SpirvCode {
+            data: shader.code().as_ptr() as *const c_void,
+            size: std::mem::size_of_val(shader.code()) as u32,
+        };
+        let mut hash = ShaderBinaryHash::default();
+        // SAFETY: The code pointer is valid for `size` bytes, and the hash pointer is valid for writes.
+        let result = unsafe { (self.state.functions.get_shader_hash_spirv)(AFTERMATH_API_VERSION, &code, &mut hash) };
+        check(result, "GFSDK_Aftermath_GetShaderHashSpirv")?;
+        let path = self.state.dump_dir.join(format!("shader-{:016x}.spv", hash.hash));
+        if !path.exists() {
+            let bytes = shader
+                .code()
+                .iter()
+                .flat_map(|word| word.to_ne_bytes())
+                .collect::<Vec<_>>();
+            fs::write(path, bytes)?;
+        }
+        Ok(())
+    }
+
+    /// Register the shaders of every pipeline in a pipeline cache. See [`GpuCrashTracker::register_shader()`].
+    /// Pipelines registered afterwards must be registered again.
+    /// # Errors
+    /// * Fails if registering any shader fails.
+    pub fn register_pipeline_cache<A: Allocator>(&self, cache: &PipelineCache<A>) -> Result<()> {
+        cache
+            .shaders()
+            .iter()
+            .try_for_each(|shader| self.register_shader(shader))
+    }
+}
+
+impl Drop for GpuCrashTracker {
+    fn drop(&mut self) {
+        // SAFETY: Crash dumps were enabled in `new()`. After this call the callbacks no longer use the state pointer.
+        let result = unsafe { (self.state.functions.disable_gpu_crash_dumps)() };
+        if let Err(e) = check(result, "GFSDK_Aftermath_DisableGpuCrashDumps") {
+            error!("{e}");
+        }
+    }
+}
@@ -97,6 +97,16 @@ impl DefaultAllocator {
             )?)),
         })
     }
+
+    /// Log every live allocation of this allocator with its name, size and memory block at the given log level. Together with
+    /// [`Device::allocation_statistics()`](crate::Device::allocation_statistics), this can be used to find out where memory is going.
+    /// # Errors
+    /// * Fails if the internal mutex was poisoned.
+    pub fn log_allocations(&self, level: log::Level) -> Result<()> {
+        let alloc = self.alloc.lock().map_err(|_| Error::PoisonError)?;
+        alloc.report_memory_leaks(level);
+        Ok(())
+    }
 }
 
 impl DefaultAllocator {
@@ -7,8 +7,11 @@
 //! # Scratch allocator
 //! A linear allocator used for making temporary, short lived allocations. For more information check the [`scratch_allocator`]
 //! module documentation.
+//! # Statistics
+//! Allocation statistics per category and per-heap memory budgets are available through the [`statistics`] module.
 
 pub mod default_allocator;
 pub mod memory_type;
 pub mod scratch_allocator;
+pub mod statistics;
 pub mod traits;
@@ -31,8 +31,9 @@
 use anyhow::Result;
 use ash::vk;
 
-use crate::{Allocator, Buffer, BufferView, DefaultAllocator, Device, Error, MemoryType};
+use crate::{Allocator, Buffer, BufferView, DefaultAllocator, Device, Error, MemoryCategory, MemoryType};
 use crate::pool::Poolable;
+use crate::util::device_size::DeviceSize;
 
 /// A linear allocator used for short-lived resources. A good example of such a resource is a buffer
 /// that needs to be updated every frame, like a uniform buffer for transform data.
@@ -116,7 +117,8 @@ impl<A: Allocator> ScratchAllocator<A> {
             anyhow::bail!("Chunk size must be a multiple of alignment");
         }
 
-        let buffer = Buffer::new(device.clone(), allocator, chunk_size, MemoryType::CpuToGpu)?;
+        let mut buffer = Buffer::new(device.clone(), allocator, chunk_size, MemoryType::CpuToGpu)?;
+        buffer.set_memory_category(MemoryCategory::Scratch);
         if !buffer.is_mapped() {
             anyhow::bail!(Error::UnmappableBuffer);
         }
@@ -151,11 +153,14 @@ impl<A: Allocator> ScratchAllocator<A> {
         let size: u64 = size.into();
 
         // Round up to the preferred alignment value
-        let padded_size = ((size as f32) / (self.alignment as f32)).ceil() as u64 * self.alignment;
+        let padded_size = DeviceSize::new(size).align_up(self.alignment)?.get();
         
         // Check if we can use the current (last) buffer
         let current_buffer = self.buffers.get(self.current_buffer);
-        let use_current_buffer = current_buffer.map(|buffer| self.local_offset + padded_size <= buffer.size()).unwrap_or_default();
+        let use_current_buffer = match current_buffer {
+            Some(buffer) => DeviceSize::new(buffer.size()).contains_range(self.local_offset, padded_size)?,
+            None => false,
+        };
 
         // Get a buffer view to return
         let view = if use_current_buffer {
@@ -163,10 +168,11 @@ impl<A: Allocator> ScratchAllocator<A> {
         } else {
             // In case we want to allocate something larger than the chunk size
             let whole_buffer_size = size.max(self.chunk_size);
-            let whole_buffer_size = ((whole_buffer_size as f32) / (self.alignment as f32)).ceil() as u64 * self.alignment;
+            let whole_buffer_size = DeviceSize::new(whole_buffer_size).align_up(self.alignment)?.get();
             
             // Create a new chunked buffer with the chunk size 
-            let buffer = Buffer::new(self.device.clone(), &mut self.allocator, whole_buffer_size, MemoryType::CpuToGpu)?;
+            let mut buffer = Buffer::new(self.device.clone(), &mut self.allocator, whole_buffer_size, MemoryType::CpuToGpu)?;
+            buffer.set_memory_category(MemoryCategory::Scratch);
             if !buffer.is_mapped() {
                 anyhow::bail!(Error::UnmappableBuffer);
             }
@@ -224,7 +230,8 @@ impl<A: Allocator> ScratchAllocator<A> {
             });
 
             // Create a new buffer that should contain *all* allocations during the frame 
-            let buffer = Buffer::new(self.device.clone(), &mut self.allocator, compressed_size, MemoryType::CpuToGpu)?;
+            let mut buffer = Buffer::new(self.device.clone(), &mut self.allocator, compressed_size, MemoryType::CpuToGpu)?;
+            buffer.set_memory_category(MemoryCategory::Scratch);
             if !buffer.is_mapped() {
                 anyhow::bail!(Error::UnmappableBuffer);
             }
@@ -0,0 +1,230 @@
+//! Memory budget tracking and allocation statistics.
+//!
+//! Every [`Buffer`](crate::Buffer) and [`Image`](crate::Image) records the size of its memory allocation in a
+//! [`MemoryCategory`] on its [`Device`], regardless of the allocator used. [`Device::allocation_statistics()`] returns the
+//! current totals per category.
+//!
+//! If [`ExtensionID::MemoryBudget`](crate::ExtensionID::MemoryBudget) is enabled, [`Device::memory_budget()`] returns the usage and budget
+//! of each memory heap as reported by the driver. This includes memory allocated by other processes, and the budget is the amount of
+//! memory the application can use before allocations are likely to fail or cause performance issues.
+//!
+//! [`ResourcePool::memory_report()`](crate::ResourcePool::memory_report) combines both into a [`MemoryReport`], which can be printed.
+//! # Example
+//! ```
+//! # use anyhow::Result;
+//! # use phobos::prelude::*;
+//! fn print_memory_usage(pool: &ResourcePool) -> Result<()> {
+//!     let report = pool.memory_report()?;
+//!     let images = report.allocations.get(MemoryCategory::Image);
+//!     println!("{} images use {} bytes", images.allocation_count, images.allocated_bytes);
+//!     // Print the full report, including the budget of every heap.
+//!     println!("{report}");
+//!     Ok(())
+//! }
+//! ```
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use ash::vk;
+
+use crate::Device;
+
+/// Category of a memory allocation, used to group [`AllocationStatistics`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum MemoryCategory {
+    /// Buffers that do not belong to any other category.
+    Buffer,
+    /// Images.
+    Image,
+    /// Buffers used for scratch memory, such as the chunks of a [`ScratchAllocator`](crate::ScratchAllocator)
+    /// and acceleration structure build scratch buffers.
+    Scratch,
+    /// Buffers backing acceleration structures, and acceleration structure instance buffers.
+    AccelerationStructure,
+    /// Memory shared by resources placed in an [`AliasedMemory`](crate::AliasedMemory). Placed resources are not counted
+    /// separately.
+    Aliased,
+    /// Memory pages bound to sparse buffers and images, allocated through a [`SparseMemory`](crate::SparseMemory). Sparse
+    /// resources themselves are not counted.
+    Sparse,
+}
+
+impl MemoryCategory {
+    /// All memory categories.
+    pub const ALL: [MemoryCategory; 6] = [
+        MemoryCategory::Buffer,
+        MemoryCategory::Image,
+        MemoryCategory::Scratch,
+        MemoryCategory::AccelerationStructure,
+        MemoryCategory::Aliased,
+        MemoryCategory::Sparse,
+    ];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+impl fmt::Display for MemoryCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            MemoryCategory::Buffer => "Buffers",
+            MemoryCategory::Image => "Images",
+            MemoryCategory::Scratch => "Scratch",
+            MemoryCategory::AccelerationStructure => "Acceleration structures",
+            MemoryCategory::Aliased => "Aliased memory",
+            MemoryCategory::Sparse => "Sparse memory",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Allocation statistics of a single [`MemoryCategory`].
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct CategoryStatistics {
+    /// Number of live allocations.
+    pub allocation_count: u64,
+    /// Total size of all live allocations in bytes.
+    pub allocated_bytes: u64,
+}
+
+/// Allocation statistics of all live buffers and images on a device, per [`MemoryCategory`].
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct AllocationStatistics {
+    categories: [CategoryStatistics; MemoryCategory::ALL.len()],
+}
+
+impl AllocationStatistics {
+    /// Get the statistics of a single category.
+    pub fn get(&self, category: MemoryCategory) -> CategoryStatistics {
+        self.categories[category.index()]
+    }
+
+    /// Get the statistics of all categories combined.
+    pub fn total(&self) -> CategoryStatistics {
+        self.categories
+            .iter()
+            .fold(CategoryStatistics::default(), |total, category| CategoryStatistics {
+                allocation_count: total.allocation_count + category.allocation_count,
+                allocated_bytes: total.allocated_bytes + category.allocated_bytes,
+            })
+    }
+}
+
+/// Usage and budget of a single memory heap, as reported by `VK_EXT_memory_budget`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct HeapBudget {
+    /// Index of the memory heap.
+    pub heap_index: u32,
+    /// Flags of the memory heap. Device local heaps have [`vk::MemoryHeapFlags::DEVICE_LOCAL`] set.
+    pub flags: vk::MemoryHeapFlags,
+    /// Total size of the heap in bytes.
+    pub size: u64,
+    /// Memory currently used from this heap by all processes, in bytes.
+    pub usage: u64,
+    /// Estimate of the memory this process can use from this heap, in bytes.
+    pub budget: u64,
+}
+
+/// Formats a size in bytes as mebibytes.
+struct Mebibytes(u64);
+
+impl fmt::Display for Mebibytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.1} MiB", self.0 as f64 / (1024.0 * 1024.0))
+    }
+}
+
+/// Report of the memory usage of an application. Implements [`Display`](fmt::Display) to print a readable summary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryReport {
+    /// Usage and budget of every memory heap. This is `None` if [`ExtensionID::MemoryBudget`](crate::ExtensionID::MemoryBudget)
+    /// is not enabled.
+    pub heaps: Option<Vec<HeapBudget>>,
+    /// Allocation statistics per category.
+    pub allocations: AllocationStatistics,
+}
+
+impl fmt::Display for MemoryReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Memory report")?;
+        match &self.heaps {
+            Some(heaps) => {
+                for heap in heaps {
+                    let kind = if heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL) {
+                        "device local"
+                    } else {
+                        "host"
+                    };
+                    writeln!(
+                        f,
+                        "  Heap {} ({kind}, {}): {} used of {} budget",
+                        heap.heap_index,
+                        Mebibytes(heap.size),
+                        Mebibytes(heap.usage),
+                        Mebibytes(heap.budget)
+                    )?;
+                }
+            }
+            None => writeln!(f, "  Heap budgets unavailable, VK_EXT_memory_budget is not enabled")?,
+        }
+        for category in MemoryCategory::ALL {
+            let stats = self.allocations.get(category);
+            writeln!(f, "  {category}: {} in {} allocations", Mebibytes(stats.allocated_bytes), stats.allocation_count)?;
+        }
+        let total = self.allocations.total();
+        write!(f, "  Total: {} in {} allocations", Mebibytes(total.allocated_bytes), total.allocation_count)
+    }
+}
+
+/// Counts live allocations per category. Owned by the device, and updated by buffers and images when they are created and dropped.
+#[derive(Debug, Default)]
+pub(crate) struct MemoryTracker {
+    counts: [AtomicU64; MemoryCategory::ALL.len()],
+    bytes: [AtomicU64; MemoryCategory::ALL.len()],
+}
+
+impl MemoryTracker {
+    /// Record a new allocation of `size` bytes.
+    pub fn allocate(&self, category: MemoryCategory, size: u64) {
+        self.counts[category.index()].fetch_add(1, Ordering::Relaxed);
+        self.bytes[category.index()].fetch_add(size, Ordering::Relaxed);
+    }
+
+    /// Record that an allocation of `size` bytes was freed.
+    pub fn free(&self, category: MemoryCategory, size: u64) {
+        self.counts[category.index()].fetch_sub(1, Ordering::Relaxed);
+        self.bytes[category.index()].fetch_sub(size, Ordering::Relaxed);
+    }
+
+    /// Move an allocation of `size` bytes to a different category.
+    pub fn recategorize(&self, from: MemoryCategory, to: MemoryCategory, size: u64) {
+        self.free(from, size);
+        self.allocate(to, size);
+    }
+
+    pub fn statistics(&self) -> AllocationStatistics {
+        let mut statistics = AllocationStatistics::default();
+        for category in MemoryCategory::ALL {
+            statistics.categories[category.index()] = CategoryStatistics {
+                allocation_count: self.counts[category.index()].load(Ordering::Relaxed),
+                allocated_bytes: self.bytes[category.index()].load(Ordering::Relaxed),
+            };
+        }
+        statistics
+    }
+}
+
+/// Build a memory report for a device. Heap budgets are only included if `VK_EXT_memory_budget` is enabled.
+pub(crate) fn memory_report(device: &Device) -> anyhow::Result<MemoryReport> {
+    let heaps = if device.is_extension_enabled(crate::ExtensionID::MemoryBudget) {
+        Some(device.memory_budget()?)
+    } else {
+        None
+    };
+    Ok(MemoryReport {
+        heaps,
+        allocations: device.allocation_statistics(),
+    })
+}
@@ -1,14 +1,16 @@
 //! Contains implementations of the compute domain for command buffers
 
-use anyhow::Result;
+use anyhow::{bail, ensure, Result};
 use ash::vk;
 
-use crate::{Allocator, ComputeCmdBuffer, ComputeSupport};
+use crate::{Allocator, BufferView, ComputeCmdBuffer, ComputeSupport};
+use crate::command_buffer::graphics::validate_indirect_buffer;
 use crate::command_buffer::IncompleteCommandBuffer;
 use crate::core::device::ExtensionID;
 use crate::query_pool::{AccelerationStructurePropertyQuery, QueryPool};
 use crate::raytracing::*;
 use crate::sync::domain::ExecutionDomain;
+use crate::util::to_vk::AsVulkanType;
 
 impl<D: ComputeSupport + ExecutionDomain, A: Allocator> ComputeCmdBuffer
     for IncompleteCommandBuffer<'_, D, A>
@@ -35,6 +37,7 @@ impl<D: ComputeSupport + ExecutionDomain, A: Allocator> ComputeCmdBuffer
                 pipeline.handle,
                 pipeline.layout,
                 pipeline.set_layouts.clone(),
+                &pipeline.push_constants,
                 vk::PipelineBindPoint::COMPUTE,
             )
         })?;
@@ -73,6 +76,40 @@ impl<D: ComputeSupport + ExecutionDomain, A: Allocator> ComputeCmdBuffer
         Ok(self)
     }
 
+    /// Dispatch compute invocations, reading the amount of workgroups in each dimension from a buffer. The buffer must contain a
+    /// [`VkDispatchIndirectCommand`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/VkDispatchIndirectCommand.html)
+    /// at `offset` bytes into the buffer view.
+    ///
+    /// This function also flushes the current descriptor set state, just like [`ComputeCmdBuffer::dispatch()`].
+    ///
+    /// See also: [`vkCmdDispatchIndirect`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdDispatchIndirect.html)
+    ///
+    /// # Errors
+    /// * Fails if `offset` is not a multiple of 4, or if the dispatch parameters do not fit inside `buffer`.
+    /// * Fails if updating the descriptor state fails.
+    /// # Example
+    /// ```
+    /// # use phobos::*;
+    /// # use phobos::sync::domain::ExecutionDomain;
+    /// # use anyhow::Result;
+    /// // Assumes "my_pipeline" was previously added to the pipeline cache with `PipelineCache::create_named_compute_pipeline()`,
+    /// // and that cmd was created with this cache.
+    /// fn compute_pipeline<'q, D: ExecutionDomain + ComputeSupport>(cmd: IncompleteCommandBuffer<'q, D>, args: &BufferView) -> Result<IncompleteCommandBuffer<'q, D>> {
+    ///     cmd.bind_compute_pipeline("my_pipeline")?
+    ///        .dispatch_indirect(args, 0)
+    /// }
+    /// ```
+    fn dispatch_indirect(mut self, buffer: &BufferView, offset: vk::DeviceSize) -> Result<Self> {
+        let command_size = std::mem::size_of::<vk::DispatchIndirectCommand>() as u64;
+        validate_indirect_buffer(buffer, offset, 1, 0, command_size)?;
+        self = self.ensure_descriptor_state()?;
+        unsafe {
+            self.device
+                .cmd_dispatch_indirect(self.handle, buffer.handle(), buffer.offset() + offset);
+        }
+        Ok(self)
+    }
+
     /// Build a single acceleration structure. This is a write operation to the acceleration structure, so
     /// it must be synchronized.
     fn build_acceleration_structure(self, info: &AccelerationStructureBuildInfo) -> Result<Self>
@@ -112,6 +149,30 @@ impl<D: ComputeSupport + ExecutionDomain, A: Allocator> ComputeCmdBuffer
         Ok(self)
     }
 
+    /// Build a single opacity micromap. This is a write operation to the micromap, so it must be synchronized before the
+    /// micromap is used in an acceleration structure build.
+    fn build_micromap(self, info: &MicromapBuildInfo) -> Result<Self>
+    where
+        Self: Sized, {
+        self.build_micromaps(std::slice::from_ref(info))
+    }
+
+    /// Build multiple opacity micromaps in a single Vulkan command.
+    /// This is a write operation to the micromaps, so it must be synchronized.
+    /// # Errors
+    /// * Fails if [`ExtensionID::OpacityMicromap`] is not enabled.
+    fn build_micromaps(self, info: &[MicromapBuildInfo]) -> Result<Self>
+    where
+        Self: Sized, {
+        self.device.require_extension(ExtensionID::OpacityMicromap)?;
+        let infos = info.iter().map(|info| info.as_vulkan()).collect::<Vec<_>>();
+        unsafe {
+            let fns = self.device.opacity_micromap().unwrap();
+            (fns.cmd_build_micromaps_ext)(self.handle, infos.len() as u32, infos.as_ptr());
+        }
+        Ok(self)
+    }
+
     /// Compact an acceleration structure. This is read operation on `src`, and a write operation on `dst`, which must both be
     /// externally synchronized.
     fn compact_acceleration_structure(
@@ -119,19 +180,94 @@ impl<D: ComputeSupport + ExecutionDomain, A: Allocator> ComputeCmdBuffer
         src: &AccelerationStructure,
         dst: &AccelerationStructure,
     ) -> Result<Self> {
+        self.compact_acceleration_structures(std::slice::from_ref(src), std::slice::from_ref(dst))
+    }
+
+    /// Compact multiple acceleration structures. Each structure in `src` is compacted into the structure at the same index in `dst`.
+    /// This is a read operation on each structure in `src`, and a write operation on each structure in `dst`, which must all be
+    /// externally synchronized.
+    ///
+    /// This is typically used together with [`ComputeCmdBuffer::write_acceleration_structures_properties()`] to first
+    /// query the compacted sizes of many acceleration structures at once.
+    /// # Errors
+    /// * Fails if [`ExtensionID::AccelerationStructure`] is not enabled.
+    /// * Fails if `src` and `dst` do not have the same length.
+    fn compact_acceleration_structures(
+        self,
+        src: &[AccelerationStructure],
+        dst: &[AccelerationStructure],
+    ) -> Result<Self> {
+        self.device
+            .require_extension(ExtensionID::AccelerationStructure)?;
+        ensure!(
+            src.len() == dst.len(),
+            "Number of source and destination acceleration structures for compaction must match"
+        );
+        let fns = self.device.acceleration_structure().unwrap();
+        for (src, dst) in src.iter().zip(dst.iter()) {
+            let info = vk::CopyAccelerationStructureInfoKHR {
+                s_type: vk::StructureType::COPY_ACCELERATION_STRUCTURE_INFO_KHR,
+                p_next: std::ptr::null(),
+                src: unsafe { src.handle() },
+                dst: unsafe { dst.handle() },
+                mode: vk::CopyAccelerationStructureModeKHR::COMPACT,
+            };
+            unsafe {
+                fns.cmd_copy_acceleration_structure(self.handle, &info);
+            };
+        }
+        Ok(self)
+    }
+
+    /// Serialize an acceleration structure into a buffer. The serialized data can be copied to the host and stored on disk, so
+    /// the acceleration structure does not need to be rebuilt the next time the application runs. This is a read operation on
+    /// `src` and a write operation on `dst`, which must both be externally synchronized.
+    ///
+    /// The size needed for `dst` can be queried with an [`AccelerationStructureSerializationSizeQuery`](crate::query_pool::AccelerationStructureSerializationSizeQuery).
+    /// # Errors
+    /// * Fails if [`ExtensionID::AccelerationStructure`] is not enabled.
+    fn serialize_acceleration_structure(self, src: &AccelerationStructure, dst: &BufferView) -> Result<Self> {
         self.device
             .require_extension(ExtensionID::AccelerationStructure)?;
         let fns = self.device.acceleration_structure().unwrap();
-        let info = vk::CopyAccelerationStructureInfoKHR {
-            s_type: vk::StructureType::COPY_ACCELERATION_STRUCTURE_INFO_KHR,
+        let info = vk::CopyAccelerationStructureToMemoryInfoKHR {
+            s_type: vk::StructureType::COPY_ACCELERATION_STRUCTURE_TO_MEMORY_INFO_KHR,
             p_next: std::ptr::null(),
             src: unsafe { src.handle() },
-            dst: unsafe { dst.handle() },
-            mode: vk::CopyAccelerationStructureModeKHR::COMPACT,
+            dst: vk::DeviceOrHostAddressKHR {
+                device_address: dst.address(),
+            },
+            mode: vk::CopyAccelerationStructureModeKHR::SERIALIZE,
         };
         unsafe {
-            fns.cmd_copy_acceleration_structure(self.handle, &info);
+            fns.cmd_copy_acceleration_structure_to_memory(self.handle, &info);
+        }
+        Ok(self)
+    }
+
+    /// Deserialize an acceleration structure from a buffer with serialized data. This is a read operation on `src` and a write
+    /// operation on `dst`, which must both be externally synchronized.
+    ///
+    /// The data must be compatible with this device, which can be checked with [`AccelerationStructure::is_compatible()`]. The
+    /// size of the buffer backing `dst` can be obtained with [`AccelerationStructure::deserialized_size()`].
+    /// # Errors
+    /// * Fails if [`ExtensionID::AccelerationStructure`] is not enabled.
+    fn deserialize_acceleration_structure(self, src: &BufferView, dst: &AccelerationStructure) -> Result<Self> {
+        self.device
+            .require_extension(ExtensionID::AccelerationStructure)?;
+        let fns = self.device.acceleration_structure().unwrap();
+        let info = vk::CopyMemoryToAccelerationStructureInfoKHR {
+            s_type: vk::StructureType::COPY_MEMORY_TO_ACCELERATION_STRUCTURE_INFO_KHR,
+            p_next: std::ptr::null(),
+            src: vk::DeviceOrHostAddressConstKHR {
+                device_address: src.address(),
+            },
+            dst: unsafe { dst.handle() },
+            mode: vk::CopyAccelerationStructureModeKHR::DESERIALIZE,
         };
+        unsafe {
+            fns.cmd_copy_memory_to_acceleration_structure(self.handle, &info);
+        }
         Ok(self)
     }
 
@@ -139,7 +275,12 @@ impl<D: ComputeSupport + ExecutionDomain, A: Allocator> ComputeCmdBuffer
     /// the type of the query pool passed in and is automatically inferred to be
     /// [`Q::QUERY_TYPE`].
     ///
+    /// The properties of `src[i]` are written to query `first + i`, where `first` is the next available query in the pool.
+    ///
     /// This is a read operation on the acceleration structures, so it must be externally synchronized.
+    /// # Errors
+    /// * Fails if [`ExtensionID::AccelerationStructure`] is not enabled.
+    /// * Fails if the query pool does not have enough queries left for all acceleration structures.
     fn write_acceleration_structures_properties<Q: AccelerationStructurePropertyQuery>(
         self,
         src: &[AccelerationStructure],
@@ -153,7 +294,10 @@ impl<D: ComputeSupport + ExecutionDomain, A: Allocator> ComputeCmdBuffer
             .iter()
             .map(|a| unsafe { a.handle() })
             .collect::<Vec<_>>();
-        let first = query_pool.current();
+        // Reserve a consecutive range of queries, one for each acceleration structure.
+        let Some(first) = query_pool.next_range(handles.len() as u32)? else {
+            bail!("Query pool does not have enough queries left to write {} acceleration structure properties", handles.len());
+        };
         unsafe {
             fns.cmd_write_acceleration_structures_properties(
                 self.handle,
@@ -163,10 +307,6 @@ impl<D: ComputeSupport + ExecutionDomain, A: Allocator> ComputeCmdBuffer
                 first,
             );
         }
-        // Update the query pool to point to the next available query
-        handles.iter().for_each(|_| {
-            query_pool.next();
-        });
         Ok(self)
     }
 
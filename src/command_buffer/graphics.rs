@@ -1,13 +1,124 @@
 //! Contains implementations of the graphics domain for command buffers
 
-use anyhow::{bail, Result};
+use anyhow::{bail, ensure, Result};
 use ash::vk;
 
-use crate::{Allocator, BufferView, Error, GfxSupport, GraphicsCmdBuffer, ImageView};
+use crate::{Allocator, BufferView, Device, Error, GfxSupport, GraphicsCmdBuffer, ImageView};
 use crate::command_buffer::IncompleteCommandBuffer;
+use crate::command_buffer::transfer::{subresource_layers, ImageRegion};
 use crate::core::device::ExtensionID;
 use crate::sync::domain::ExecutionDomain;
 
+/// Parameters for a single non-indexed indirect drawcall. The memory layout of this structure matches
+/// [`VkDrawIndirectCommand`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/VkDrawIndirectCommand.html),
+/// so an array of these can be written directly into a buffer used with [`GraphicsCmdBuffer::draw_indirect()`].
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(C)]
+pub struct DrawIndirectCommand {
+    /// Number of vertices to draw.
+    pub vertex_count: u32,
+    /// Number of instances to draw.
+    pub instance_count: u32,
+    /// Index of the first vertex to draw.
+    pub first_vertex: u32,
+    /// Instance ID of the first instance to draw.
+    pub first_instance: u32,
+}
+
+/// Parameters for a single indexed indirect drawcall. The memory layout of this structure matches
+/// [`VkDrawIndexedIndirectCommand`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/VkDrawIndexedIndirectCommand.html),
+/// so an array of these can be written directly into a buffer used with [`GraphicsCmdBuffer::draw_indexed_indirect()`].
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(C)]
+pub struct DrawIndexedIndirectCommand {
+    /// Number of indices to draw.
+    pub index_count: u32,
+    /// Number of instances to draw.
+    pub instance_count: u32,
+    /// Base index within the index buffer.
+    pub first_index: u32,
+    /// Value added to the vertex index before indexing into the vertex buffer.
+    pub vertex_offset: i32,
+    /// Instance ID of the first instance to draw.
+    pub first_instance: u32,
+}
+
+/// Parameters for a single indirect mesh shading drawcall. The memory layout of this structure matches
+/// [`VkDrawMeshTasksIndirectCommandEXT`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/VkDrawMeshTasksIndirectCommandEXT.html),
+/// so an array of these can be written directly into a buffer used with [`GraphicsCmdBuffer::draw_mesh_tasks_indirect()`].
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(C)]
+pub struct DrawMeshTasksIndirectCommand {
+    /// Number of local workgroups to dispatch in the X dimension.
+    pub group_count_x: u32,
+    /// Number of local workgroups to dispatch in the Y dimension.
+    pub group_count_y: u32,
+    /// Number of local workgroups to dispatch in the Z dimension.
+    pub group_count_z: u32,
+}
+
+/// Parameters for a single indirect trace rays command. The memory layout of this structure matches
+/// [`VkTraceRaysIndirectCommandKHR`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/VkTraceRaysIndirectCommandKHR.html),
+/// so it can be written directly into a buffer used with [`GraphicsCmdBuffer::trace_rays_indirect()`].
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(C)]
+pub struct TraceRaysIndirectCommand {
+    /// Width of the ray trace query dimensions.
+    pub width: u32,
+    /// Height of the ray trace query dimensions.
+    pub height: u32,
+    /// Depth of the ray trace query dimensions.
+    pub depth: u32,
+}
+
+assert_eq_size!(DrawIndirectCommand, vk::DrawIndirectCommand);
+assert_eq_size!(DrawIndexedIndirectCommand, vk::DrawIndexedIndirectCommand);
+assert_eq_size!(DrawMeshTasksIndirectCommand, vk::DrawMeshTasksIndirectCommandEXT);
+assert_eq_size!(TraceRaysIndirectCommand, vk::TraceRaysIndirectCommandKHR);
+
+/// Validates that `count` indirect commands of `command_size` bytes, starting at `offset` and each `stride` bytes apart,
+/// are correctly aligned and fit inside `buffer`. The stride is only checked when more than one command is read.
+pub(crate) fn validate_indirect_buffer(
+    buffer: &BufferView,
+    offset: vk::DeviceSize,
+    count: u32,
+    stride: u32,
+    command_size: u64,
+) -> Result<()> {
+    let error = || Error::InvalidIndirectBuffer {
+        offset,
+        stride,
+        count,
+        command_size,
+        buffer_size: buffer.size(),
+    };
+    let stride_valid = count <= 1 || (stride % 4 == 0 && stride as u64 >= command_size);
+    // The offset passed to Vulkan includes the offset of the view, so both must be aligned together.
+    if (buffer.offset() + offset) % 4 != 0 || !stride_valid {
+        return Err(error().into());
+    }
+    if count == 0 {
+        return Ok(());
+    }
+    let end = (count as u64 - 1)
+        .checked_mul(stride as u64)
+        .and_then(|range| range.checked_add(offset))
+        .and_then(|range| range.checked_add(command_size));
+    match end {
+        Some(end) if end <= buffer.size() => Ok(()),
+        _ => Err(error().into()),
+    }
+}
+
+/// The extended dynamic state commands are core in Vulkan 1.3, so their function pointers are only available on devices
+/// that support this version.
+fn require_extended_dynamic_state(device: &Device) -> Result<()> {
+    if device.properties().api_version < vk::API_VERSION_1_3 {
+        return Err(Error::FeatureNotSupported("extendedDynamicState").into());
+    }
+    Ok(())
+}
+
 impl<D: GfxSupport + ExecutionDomain, A: Allocator> GraphicsCmdBuffer
     for IncompleteCommandBuffer<'_, D, A>
 {
@@ -159,22 +270,366 @@ impl<D: GfxSupport + ExecutionDomain, A: Allocator> GraphicsCmdBuffer
         Ok(self)
     }
 
+    /// Issue multiple drawcalls with their parameters sourced from a buffer. This will flush the current descriptor state
+    /// and actually bind the descriptor sets. The buffer must contain `draw_count` [`DrawIndirectCommand`] structures, starting at `offset`
+    /// bytes into the buffer view and each `stride` bytes apart.
+    /// Directly translates to [`vkCmdDrawIndirect`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdDrawIndirect.html).
+    /// # Errors
+    /// * Fails if `offset` or `stride` are misaligned, or if the commands do not fit inside `buffer`.
+    /// * Fails if flushing the descriptor state fails.
+    /// # Example
+    /// ```
+    /// # use anyhow::Result;
+    /// # use phobos::*;
+    /// # use phobos::sync::domain::*;
+    /// fn draw_indirect<C: GraphicsCmdBuffer>(cmd: C, vertex_buffer: &BufferView, draws: &BufferView) -> Result<C> {
+    ///     let stride = std::mem::size_of::<DrawIndirectCommand>() as u32;
+    ///     let count = (draws.size() / stride as u64) as u32;
+    ///     cmd.full_viewport_scissor()
+    ///        .bind_graphics_pipeline("my_pipeline")?
+    ///        .bind_vertex_buffer(0, vertex_buffer)
+    ///        .draw_indirect(draws, 0, count, stride)
+    /// }
+    /// ```
+    fn draw_indirect(
+        mut self,
+        buffer: &BufferView,
+        offset: vk::DeviceSize,
+        draw_count: u32,
+        stride: u32,
+    ) -> Result<Self> {
+        validate_indirect_buffer(
+            buffer,
+            offset,
+            draw_count,
+            stride,
+            std::mem::size_of::<DrawIndirectCommand>() as u64,
+        )?;
+        self = self.ensure_descriptor_state()?;
+        unsafe {
+            self.device.cmd_draw_indirect(
+                self.handle,
+                buffer.handle(),
+                buffer.offset() + offset,
+                draw_count,
+                stride,
+            );
+        }
+        Ok(self)
+    }
+
+    /// Issue multiple indexed drawcalls with their parameters sourced from a buffer. This will flush the current descriptor state
+    /// and actually bind the descriptor sets. The buffer must contain `draw_count` [`DrawIndexedIndirectCommand`] structures, starting at `offset`
+    /// bytes into the buffer view and each `stride` bytes apart.
+    /// Directly translates to [`vkCmdDrawIndexedIndirect`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdDrawIndexedIndirect.html).
+    /// # Errors
+    /// * Fails if `offset` or `stride` are misaligned, or if the commands do not fit inside `buffer`.
+    /// * Fails if flushing the descriptor state fails.
+    /// # Example
+    /// ```
+    /// # use anyhow::Result;
+    /// # use phobos::*;
+    /// # use phobos::sync::domain::*;
+    /// fn draw_indexed_indirect<C: GraphicsCmdBuffer>(cmd: C, vertex_buffer: &BufferView, index_buffer: &BufferView, draws: &BufferView) -> Result<C> {
+    ///     let stride = std::mem::size_of::<DrawIndexedIndirectCommand>() as u32;
+    ///     let count = (draws.size() / stride as u64) as u32;
+    ///     cmd.full_viewport_scissor()
+    ///        .bind_graphics_pipeline("my_pipeline")?
+    ///        .bind_vertex_buffer(0, vertex_buffer)
+    ///        .bind_index_buffer(index_buffer, vk::IndexType::UINT32)
+    ///        .draw_indexed_indirect(draws, 0, count, stride)
+    /// }
+    /// ```
+    fn draw_indexed_indirect(
+        mut self,
+        buffer: &BufferView,
+        offset: vk::DeviceSize,
+        draw_count: u32,
+        stride: u32,
+    ) -> Result<Self> {
+        validate_indirect_buffer(
+            buffer,
+            offset,
+            draw_count,
+            stride,
+            std::mem::size_of::<DrawIndexedIndirectCommand>() as u64,
+        )?;
+        self = self.ensure_descriptor_state()?;
+        unsafe {
+            self.device.cmd_draw_indexed_indirect(
+                self.handle,
+                buffer.handle(),
+                buffer.offset() + offset,
+                draw_count,
+                stride,
+            );
+        }
+        Ok(self)
+    }
+
+    /// Issue indirect drawcalls where the number of drawcalls is read from `count_buffer` at `count_offset` on the GPU.
+    /// At most `max_draw_count` [`DrawIndirectCommand`] structures are read from `buffer`, starting at `offset` and each `stride` bytes apart.
+    /// This will flush the current descriptor state and actually bind the descriptor sets.
+    /// Directly translates to [`vkCmdDrawIndirectCount`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdDrawIndirectCount.html).
+    ///
+    /// The `drawIndirectCount` feature is automatically enabled when it is supported. Support can be queried through
+    /// [`Device::features_1_2()`](crate::Device::features_1_2).
+    /// # Errors
+    /// * Fails if the `drawIndirectCount` feature is not enabled.
+    /// * Fails if `offset` or `stride` are misaligned, or if the commands do not fit inside `buffer`.
+    /// * Fails if `count_offset` is misaligned, or if the draw count does not fit inside `count_buffer`.
+    /// * Fails if flushing the descriptor state fails.
+    fn draw_indirect_count(
+        mut self,
+        buffer: &BufferView,
+        offset: vk::DeviceSize,
+        count_buffer: &BufferView,
+        count_offset: vk::DeviceSize,
+        max_draw_count: u32,
+        stride: u32,
+    ) -> Result<Self> {
+        if self.device.features_1_2().draw_indirect_count != vk::TRUE {
+            return Err(Error::FeatureNotSupported("drawIndirectCount").into());
+        }
+        validate_indirect_buffer(
+            buffer,
+            offset,
+            max_draw_count,
+            stride,
+            std::mem::size_of::<DrawIndirectCommand>() as u64,
+        )?;
+        validate_indirect_buffer(count_buffer, count_offset, 1, 0, 4)?;
+        self = self.ensure_descriptor_state()?;
+        unsafe {
+            self.device.cmd_draw_indirect_count(
+                self.handle,
+                buffer.handle(),
+                buffer.offset() + offset,
+                count_buffer.handle(),
+                count_buffer.offset() + count_offset,
+                max_draw_count,
+                stride,
+            );
+        }
+        Ok(self)
+    }
+
+    /// Issue indexed indirect drawcalls where the number of drawcalls is read from `count_buffer` at `count_offset` on the GPU.
+    /// At most `max_draw_count` [`DrawIndexedIndirectCommand`] structures are read from `buffer`, starting at `offset` and each `stride` bytes apart.
+    /// This will flush the current descriptor state and actually bind the descriptor sets.
+    /// Directly translates to [`vkCmdDrawIndexedIndirectCount`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdDrawIndexedIndirectCount.html).
+    ///
+    /// The `drawIndirectCount` feature is automatically enabled when it is supported. Support can be queried through
+    /// [`Device::features_1_2()`](crate::Device::features_1_2).
+    /// # Errors
+    /// * Fails if the `drawIndirectCount` feature is not enabled.
+    /// * Fails if `offset` or `stride` are misaligned, or if the commands do not fit inside `buffer`.
+    /// * Fails if `count_offset` is misaligned, or if the draw count does not fit inside `count_buffer`.
+    /// * Fails if flushing the descriptor state fails.
+    /// # Example
+    /// ```
+    /// # use anyhow::Result;
+    /// # use phobos::*;
+    /// # use phobos::sync::domain::*;
+    /// // `draws` and `count` were filled by a GPU culling pass.
+    /// fn draw_culled<C: GraphicsCmdBuffer>(cmd: C, index_buffer: &BufferView, draws: &BufferView, count: &BufferView, max_draws: u32) -> Result<C> {
+    ///     let stride = std::mem::size_of::<DrawIndexedIndirectCommand>() as u32;
+    ///     cmd.full_viewport_scissor()
+    ///        .bind_graphics_pipeline("my_pipeline")?
+    ///        .bind_index_buffer(index_buffer, vk::IndexType::UINT32)
+    ///        .draw_indexed_indirect_count(draws, 0, count, 0, max_draws, stride)
+    /// }
+    /// ```
+    fn draw_indexed_indirect_count(
+        mut self,
+        buffer: &BufferView,
+        offset: vk::DeviceSize,
+        count_buffer: &BufferView,
+        count_offset: vk::DeviceSize,
+        max_draw_count: u32,
+        stride: u32,
+    ) -> Result<Self> {
+        if self.device.features_1_2().draw_indirect_count != vk::TRUE {
+            return Err(Error::FeatureNotSupported("drawIndirectCount").into());
+        }
+        validate_indirect_buffer(
+            buffer,
+            offset,
+            max_draw_count,
+            stride,
+            std::mem::size_of::<DrawIndexedIndirectCommand>() as u64,
+        )?;
+        validate_indirect_buffer(count_buffer, count_offset, 1, 0, 4)?;
+        self = self.ensure_descriptor_state()?;
+        unsafe {
+            self.device.cmd_draw_indexed_indirect_count(
+                self.handle,
+                buffer.handle(),
+                buffer.offset() + offset,
+                count_buffer.handle(),
+                count_buffer.offset() + count_offset,
+                max_draw_count,
+                stride,
+            );
+        }
+        Ok(self)
+    }
+
+    /// Issue a mesh shading drawcall, dispatching `x * y * z` task shader workgroups (or mesh shader workgroups if the
+    /// pipeline has no task shader). This will flush the current descriptor state and actually bind the descriptor sets.
+    /// Requires [`ExtensionID::MeshShader`] to be enabled.
+    /// Directly translates to [`vkCmdDrawMeshTasksEXT`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdDrawMeshTasksEXT.html).
+    /// # Errors
+    /// * Fails if [`ExtensionID::MeshShader`] is not enabled.
+    /// * Fails if flushing the descriptor state fails.
+    /// # Example
+    /// ```
+    /// # use anyhow::Result;
+    /// # use phobos::*;
+    /// # use phobos::sync::domain::*;
+    /// // Assumes "my_mesh_pipeline" was created with a task and mesh shader.
+    /// fn draw_meshlets<C: GraphicsCmdBuffer>(cmd: C, meshlet_count: u32) -> Result<C> {
+    ///     cmd.full_viewport_scissor()
+    ///        .bind_graphics_pipeline("my_mesh_pipeline")?
+    ///        .draw_mesh_tasks(meshlet_count, 1, 1)
+    /// }
+    /// ```
+    fn draw_mesh_tasks(mut self, x: u32, y: u32, z: u32) -> Result<Self> {
+        self.device.require_extension(ExtensionID::MeshShader)?;
+        self = self.ensure_descriptor_state()?;
+        let fns = self.device.mesh_shader().unwrap();
+        // SAFETY: Vulkan API call. This function pointer is not null because we just verified its availability.
+        unsafe {
+            fns.cmd_draw_mesh_tasks(self.handle, x, y, z);
+        }
+        Ok(self)
+    }
+
+    /// Issue multiple mesh shading drawcalls with their parameters sourced from a buffer. The buffer must contain `draw_count`
+    /// [`DrawMeshTasksIndirectCommand`] structures, starting at `offset` bytes into the buffer view and each `stride` bytes apart.
+    /// This will flush the current descriptor state and actually bind the descriptor sets.
+    /// Requires [`ExtensionID::MeshShader`] to be enabled.
+    /// Directly translates to [`vkCmdDrawMeshTasksIndirectEXT`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdDrawMeshTasksIndirectEXT.html).
+    /// # Errors
+    /// * Fails if [`ExtensionID::MeshShader`] is not enabled.
+    /// * Fails if `offset` or `stride` are misaligned, or if the commands do not fit inside `buffer`.
+    /// * Fails if flushing the descriptor state fails.
+    fn draw_mesh_tasks_indirect(
+        mut self,
+        buffer: &BufferView,
+        offset: vk::DeviceSize,
+        draw_count: u32,
+        stride: u32,
+    ) -> Result<Self> {
+        self.device.require_extension(ExtensionID::MeshShader)?;
+        validate_indirect_buffer(
+            buffer,
+            offset,
+            draw_count,
+            stride,
+            std::mem::size_of::<DrawMeshTasksIndirectCommand>() as u64,
+        )?;
+        self = self.ensure_descriptor_state()?;
+        let fns = self.device.mesh_shader().unwrap();
+        // SAFETY: Vulkan API call. This function pointer is not null because we just verified its availability.
+        unsafe {
+            fns.cmd_draw_mesh_tasks_indirect(
+                self.handle,
+                buffer.handle(),
+                buffer.offset() + offset,
+                draw_count,
+                stride,
+            );
+        }
+        Ok(self)
+    }
+
+    /// Issue mesh shading drawcalls where the number of drawcalls is read from `count_buffer` at `count_offset` on the GPU.
+    /// At most `max_draw_count` [`DrawMeshTasksIndirectCommand`] structures are read from `buffer`, starting at `offset` and each `stride` bytes apart.
+    /// This will flush the current descriptor state and actually bind the descriptor sets.
+    /// Requires [`ExtensionID::MeshShader`] and the `drawIndirectCount` feature to be enabled.
+    /// Directly translates to [`vkCmdDrawMeshTasksIndirectCountEXT`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdDrawMeshTasksIndirectCountEXT.html).
+    /// # Errors
+    /// * Fails if [`ExtensionID::MeshShader`] is not enabled.
+    /// * Fails if the `drawIndirectCount` feature is not enabled.
+    /// * Fails if `offset` or `stride` are misaligned, or if the commands do not fit inside `buffer`.
+    /// * Fails if `count_offset` is misaligned, or if the draw count does not fit inside `count_buffer`.
+    /// * Fails if flushing the descriptor state fails.
+    fn draw_mesh_tasks_indirect_count(
+        mut self,
+        buffer: &BufferView,
+        offset: vk::DeviceSize,
+        count_buffer: &BufferView,
+        count_offset: vk::DeviceSize,
+        max_draw_count: u32,
+        stride: u32,
+    ) -> Result<Self> {
+        self.device.require_extension(ExtensionID::MeshShader)?;
+        if self.device.features_1_2().draw_indirect_count != vk::TRUE {
+            return Err(Error::FeatureNotSupported("drawIndirectCount").into());
+        }
+        validate_indirect_buffer(
+            buffer,
+            offset,
+            max_draw_count,
+            stride,
+            std::mem::size_of::<DrawMeshTasksIndirectCommand>() as u64,
+        )?;
+        validate_indirect_buffer(count_buffer, count_offset, 1, 0, 4)?;
+        self = self.ensure_descriptor_state()?;
+        let fns = self.device.mesh_shader().unwrap();
+        // SAFETY: Vulkan API call. This function pointer is not null because we just verified its availability.
+        unsafe {
+            fns.cmd_draw_mesh_tasks_indirect_count(
+                self.handle,
+                buffer.handle(),
+                buffer.offset() + offset,
+                count_buffer.handle(),
+                count_buffer.offset() + count_offset,
+                max_draw_count,
+                stride,
+            );
+        }
+        Ok(self)
+    }
+
     /// Issue a `vkCmdTraceRaysKHR` command. Requires [`ExtensionID::RayTracingPipeline`] to be enabled.
-    fn trace_rays(mut self, width: u32, height: u32, depth: u32) -> Result<Self>
+    fn trace_rays(self, width: u32, height: u32, depth: u32) -> Result<Self>
+    where
+        Self: Sized, {
+        let Some(regions) = self.current_sbt_regions else { bail!("called trace_rays() without a valid raytracing pipeline build"); };
+        self.trace_rays_with_regions(&regions, width, height, depth)
+    }
+
+    /// Start raytracing using a custom shader binding table. The regions are given in the order ray generation, miss, hit and
+    /// callable regions. The shader group handles needed to fill in a shader binding table can be obtained with
+    /// [`PipelineCache::shader_group_handles()`](crate::PipelineCache::shader_group_handles).
+    /// # Errors
+    /// * Fails if no raytracing pipeline is bound.
+    fn trace_rays_with_regions(
+        mut self,
+        regions: &[vk::StridedDeviceAddressRegionKHR; 4],
+        width: u32,
+        height: u32,
+        depth: u32,
+    ) -> Result<Self>
     where
         Self: Sized, {
         self.device
             .require_extension(ExtensionID::RayTracingPipeline)?;
+        ensure!(
+            self.current_bindpoint == vk::PipelineBindPoint::RAY_TRACING_KHR,
+            "called trace_rays_with_regions() without a raytracing pipeline bound"
+        );
         self = self.ensure_descriptor_state()?;
         let fns = self.device.raytracing_pipeline().unwrap();
-        let Some(regions) = self.current_sbt_regions else { bail!("called trace_rays() without a valid raytracing pipeline build"); };
         unsafe {
             fns.cmd_trace_rays(
                 self.handle,
-                regions.get(0).unwrap(),
-                regions.get(1).unwrap(),
-                regions.get(2).unwrap(),
-                regions.get(3).unwrap(),
+                &regions[0],
+                &regions[1],
+                &regions[2],
+                &regions[3],
                 width,
                 height,
                 depth,
@@ -183,7 +638,78 @@ impl<D: GfxSupport + ExecutionDomain, A: Allocator> GraphicsCmdBuffer
         Ok(self)
     }
 
-    /// Bind a graphics pipeline by name.
+    /// Start raytracing with the dimensions read from a buffer, using the shader binding table of the bound raytracing pipeline.
+    /// The buffer must contain a [`TraceRaysIndirectCommand`] at `offset` bytes into the buffer view.
+    /// Directly translates to [`vkCmdTraceRaysIndirectKHR`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdTraceRaysIndirectKHR.html).
+    /// # Errors
+    /// * Fails if the `rayTracingPipelineTraceRaysIndirect` feature is not supported.
+    /// * Fails if no raytracing pipeline is bound.
+    fn trace_rays_indirect(mut self, buffer: &BufferView, offset: vk::DeviceSize) -> Result<Self>
+    where
+        Self: Sized, {
+        self.device
+            .require_extension(ExtensionID::RayTracingPipeline)?;
+        ensure!(
+            self.device.raytracing_pipeline_features().ray_tracing_pipeline_trace_rays_indirect == vk::TRUE,
+            "trace_rays_indirect() requires the rayTracingPipelineTraceRaysIndirect feature"
+        );
+        ensure!(
+            self.current_bindpoint == vk::PipelineBindPoint::RAY_TRACING_KHR,
+            "called trace_rays_indirect() without a raytracing pipeline bound"
+        );
+        let Some(regions) = self.current_sbt_regions else { bail!("called trace_rays_indirect() without a valid raytracing pipeline build"); };
+        let address = buffer.address() + offset;
+        self = self.ensure_descriptor_state()?;
+        let fns = self.device.raytracing_pipeline().unwrap();
+        unsafe {
+            fns.cmd_trace_rays_indirect(
+                self.handle,
+                std::slice::from_ref(&regions[0]),
+                std::slice::from_ref(&regions[1]),
+                std::slice::from_ref(&regions[2]),
+                std::slice::from_ref(&regions[3]),
+                address,
+            );
+        }
+        Ok(self)
+    }
+
+    /// Start raytracing with both the dimensions and the shader binding table read from a buffer. The buffer must contain a
+    /// [`vk::TraceRaysIndirectCommand2KHR`] at `offset` bytes into the buffer view. This allows the GPU to select the shader
+    /// binding table, for example with a table built from [`PipelineCache::shader_group_handles()`](crate::PipelineCache::shader_group_handles).
+    /// A raytracing pipeline must still be bound.
+    /// Directly translates to [`vkCmdTraceRaysIndirect2KHR`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdTraceRaysIndirect2KHR.html).
+    /// # Errors
+    /// * Fails if [`ExtensionID::RayTracingMaintenance1`] is not enabled, or the `rayTracingPipelineTraceRaysIndirect2` feature
+    ///   is not supported.
+    /// * Fails if no raytracing pipeline is bound.
+    fn trace_rays_indirect2(mut self, buffer: &BufferView, offset: vk::DeviceSize) -> Result<Self>
+    where
+        Self: Sized, {
+        self.device
+            .require_extension(ExtensionID::RayTracingMaintenance1)?;
+        ensure!(
+            self.device
+                .raytracing_maintenance1_features()
+                .ray_tracing_pipeline_trace_rays_indirect2
+                == vk::TRUE,
+            "trace_rays_indirect2() requires the rayTracingPipelineTraceRaysIndirect2 feature"
+        );
+        ensure!(
+            self.current_bindpoint == vk::PipelineBindPoint::RAY_TRACING_KHR,
+            "called trace_rays_indirect2() without a raytracing pipeline bound"
+        );
+        let address = buffer.address() + offset;
+        self = self.ensure_descriptor_state()?;
+        let fns = self.device.raytracing_maintenance1().unwrap();
+        unsafe {
+            fns.cmd_trace_rays_indirect2(self.handle, address);
+        }
+        Ok(self)
+    }
+
+    /// Bind a graphics pipeline by name. If the pipeline uses shader objects, this binds its shaders and sets all pipeline state
+    /// that is not dynamic instead.
     /// # Errors
     /// * Fails if the pipeline was not previously registered in the pipeline cache.
     /// # Example
@@ -200,11 +726,16 @@ impl<D: GfxSupport + ExecutionDomain, A: Allocator> GraphicsCmdBuffer
     fn bind_graphics_pipeline(mut self, name: &str) -> Result<Self> {
         let Some(rendering_state) = self.current_rendering_state.clone() else { return Err(Error::NoRenderpass.into()) };
         let cache = self.pipeline_cache.clone();
+        if cache.uses_shader_objects(name) {
+            cache.with_shader_objects(name, rendering_state, |binding| self.bind_shader_objects_impl(binding))?;
+            return Ok(self);
+        }
         cache.with_pipeline(name, rendering_state, |pipeline| {
             self.bind_pipeline_impl(
                 pipeline.handle,
                 pipeline.layout,
                 pipeline.set_layouts.clone(),
+                &pipeline.push_constants,
                 vk::PipelineBindPoint::GRAPHICS,
             )
         })?;
@@ -212,6 +743,28 @@ impl<D: GfxSupport + ExecutionDomain, A: Allocator> GraphicsCmdBuffer
         Ok(self)
     }
 
+    /// Check whether a graphics pipeline is ready to be bound in the current render pass, and compile it on a worker thread if it
+    /// is not.
+    /// # Errors
+    /// * Fails if the pipeline was not previously registered in the pipeline cache.
+    /// * Fails if this is called outside of a render pass.
+    /// # Example
+    /// ```
+    /// # use phobos::*;
+    /// # use anyhow::Result;
+    /// fn draw_if_ready<C: GraphicsCmdBuffer>(cmd: C) -> Result<C> {
+    ///     if !cmd.is_graphics_pipeline_ready("my_pipeline")? {
+    ///         // The pipeline is still compiling, skip this draw for now.
+    ///         return Ok(cmd);
+    ///     }
+    ///     cmd.bind_graphics_pipeline("my_pipeline")?.draw(3, 1, 0, 0)
+    /// }
+    /// ```
+    fn is_graphics_pipeline_ready(&self, name: &str) -> Result<bool> {
+        let Some(rendering_state) = self.current_rendering_state.clone() else { return Err(Error::NoRenderpass.into()) };
+        self.pipeline_cache.request_pipeline(name, rendering_state)
+    }
+
     /// Bind a ray tracing pipeline by name.
     /// # Errors
     /// * Fails if the pipeline was not previously registered in the pipeline cache.
@@ -225,6 +778,7 @@ impl<D: GfxSupport + ExecutionDomain, A: Allocator> GraphicsCmdBuffer
                 pipeline.handle,
                 pipeline.layout,
                 pipeline.set_layouts.clone(),
+                &pipeline.push_constants,
                 vk::PipelineBindPoint::RAY_TRACING_KHR,
             )
         })?;
@@ -283,33 +837,36 @@ impl<D: GfxSupport + ExecutionDomain, A: Allocator> GraphicsCmdBuffer
         self
     }
 
-    /// Blit a source image to a destination image, using the specified offsets into the images and a filter. Direct and thin wrapper around
-    /// [`vkCmdBlitImage`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdBlitImage.html)
+    /// Blit a region of the base mip level of `src` to a region of the base mip level of `dst`. If the regions have different sizes,
+    /// the image is scaled using the given filter. `src` must be in `VK_IMAGE_LAYOUT_TRANSFER_SRC_OPTIMAL` and `dst` must be in
+    /// `VK_IMAGE_LAYOUT_TRANSFER_DST_OPTIMAL`.
+    /// See also [`vkCmdBlitImage`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdBlitImage.html)
+    /// # Errors
+    /// * Fails if either region is out of range of its image.
+    /// # Example
+    /// ```
+    /// # use anyhow::Result;
+    /// # use phobos::*;
+    /// # use phobos::sync::domain::*;
+    /// fn downscale<C: GraphicsCmdBuffer>(cmd: C, src: &ImageView, dst: &ImageView) -> Result<C> {
+    ///     cmd.blit_image(src, &ImageRegion::full(src), dst, &ImageRegion::full(dst), vk::Filter::LINEAR)
+    /// }
+    /// ```
     fn blit_image(
         self,
         src: &ImageView,
+        src_region: &ImageRegion,
         dst: &ImageView,
-        src_offsets: &[vk::Offset3D; 2],
-        dst_offsets: &[vk::Offset3D; 2],
+        dst_region: &ImageRegion,
         filter: vk::Filter,
-    ) -> Self
-    where
-        Self: Sized, {
+    ) -> Result<Self> {
+        src_region.validate(src)?;
+        dst_region.validate(dst)?;
         let blit = vk::ImageBlit {
-            src_subresource: vk::ImageSubresourceLayers {
-                aspect_mask: src.aspect(),
-                mip_level: src.base_level(),
-                base_array_layer: src.base_layer(),
-                layer_count: src.layer_count(),
-            },
-            src_offsets: *src_offsets,
-            dst_subresource: vk::ImageSubresourceLayers {
-                aspect_mask: dst.aspect(),
-                mip_level: dst.base_level(),
-                base_array_layer: dst.base_layer(),
-                layer_count: dst.layer_count(),
-            },
-            dst_offsets: *dst_offsets,
+            src_subresource: subresource_layers(src),
+            src_offsets: src_region.corners(),
+            dst_subresource: subresource_layers(dst),
+            dst_offsets: dst_region.corners(),
         };
 
         unsafe {
@@ -323,7 +880,73 @@ impl<D: GfxSupport + ExecutionDomain, A: Allocator> GraphicsCmdBuffer
                 filter,
             );
         }
-        self
+
+        Ok(self)
+    }
+
+    /// Resolve the base mip level of a multisampled image into a single-sampled image of the same size.
+    /// `src` must be in `VK_IMAGE_LAYOUT_TRANSFER_SRC_OPTIMAL` and `dst` must be in `VK_IMAGE_LAYOUT_TRANSFER_DST_OPTIMAL`.
+    /// See also [`vkCmdResolveImage`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdResolveImage.html)
+    /// # Errors
+    /// * Fails if the base mip levels of both images do not have the same size.
+    /// # Example
+    /// ```
+    /// # use anyhow::Result;
+    /// # use phobos::*;
+    /// # use phobos::sync::domain::*;
+    /// fn resolve<C: GraphicsCmdBuffer>(cmd: C, msaa: &ImageView, resolved: &ImageView) -> Result<C> {
+    ///     cmd.resolve_image(msaa, resolved)
+    /// }
+    /// ```
+    fn resolve_image(self, src: &ImageView, dst: &ImageView) -> Result<Self> {
+        let extent = src.base_level_size();
+        if extent != dst.base_level_size() {
+            return Err(Error::ImageRegionOutOfRange.into());
+        }
+        let resolve = vk::ImageResolve {
+            src_subresource: subresource_layers(src),
+            src_offset: Default::default(),
+            dst_subresource: subresource_layers(dst),
+            dst_offset: Default::default(),
+            extent,
+        };
+
+        unsafe {
+            self.device.cmd_resolve_image(
+                self.handle,
+                src.image(),
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                dst.image(),
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                std::slice::from_ref(&resolve),
+            );
+        }
+
+        Ok(self)
+    }
+
+    /// Set the line width for subsequent drawcalls. The bound pipeline must have been created with [`vk::DynamicState::LINE_WIDTH`].
+    /// Equivalent to [`vkCmdSetLineWidth`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdSetLineWidth.html)
+    /// # Errors
+    /// * Fails with [`Error::FeatureNotSupported`] if `width` is not `1.0` and the `wideLines` feature is not enabled.
+    /// # Example
+    /// ```
+    /// # use anyhow::Result;
+    /// # use phobos::*;
+    /// fn draw_thick_lines<C: GraphicsCmdBuffer>(cmd: C) -> Result<C> {
+    ///     cmd.bind_graphics_pipeline("debug_lines")?
+    ///         .set_line_width(4.0)?
+    ///         .draw(2, 1, 0, 0)
+    /// }
+    /// ```
+    fn set_line_width(self, width: f32) -> Result<Self> {
+        if width != 1.0 && self.device.features().wide_lines != vk::TRUE {
+            return Err(Error::FeatureNotSupported("wideLines").into());
+        }
+        unsafe {
+            self.device.cmd_set_line_width(self.handle, width);
+        }
+        Ok(self)
     }
 
     /// Set the polygon mode. Only available if `VK_EXT_extended_dynamic_state3` was enabled on device creation.
@@ -352,4 +975,218 @@ impl<D: GfxSupport + ExecutionDomain, A: Allocator> GraphicsCmdBuffer
         }
         Ok(self)
     }
+
+    /// Set the face culling mode for subsequent drawcalls. The bound pipeline must have been created with
+    /// [`vk::DynamicState::CULL_MODE`].
+    /// Equivalent to [`vkCmdSetCullMode`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdSetCullMode.html)
+    /// # Errors
+    /// * Fails if the device does not support Vulkan 1.3 extended dynamic state.
+    /// # Example
+    /// ```
+    /// # use anyhow::Result;
+    /// # use phobos::*;
+    /// // One pipeline for both single and double sided materials.
+    /// fn draw_material<C: GraphicsCmdBuffer>(cmd: C, double_sided: bool) -> Result<C> {
+    ///     let cull = if double_sided { vk::CullModeFlags::NONE } else { vk::CullModeFlags::BACK };
+    ///     cmd.bind_graphics_pipeline("material")?
+    ///         .set_cull_mode(cull)?
+    ///         .draw(3, 1, 0, 0)
+    /// }
+    /// ```
+    fn set_cull_mode(self, cull: vk::CullModeFlags) -> Result<Self> {
+        require_extended_dynamic_state(&self.device)?;
+        unsafe {
+            self.device.cmd_set_cull_mode(self.handle, cull);
+        }
+        Ok(self)
+    }
+
+    /// Set the front face orientation for subsequent drawcalls. The bound pipeline must have been created with
+    /// [`vk::DynamicState::FRONT_FACE`].
+    /// Equivalent to [`vkCmdSetFrontFace`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdSetFrontFace.html)
+    /// # Errors
+    /// * Fails if the device does not support Vulkan 1.3 extended dynamic state.
+    fn set_front_face(self, face: vk::FrontFace) -> Result<Self> {
+        require_extended_dynamic_state(&self.device)?;
+        unsafe {
+            self.device.cmd_set_front_face(self.handle, face);
+        }
+        Ok(self)
+    }
+
+    /// Set the primitive topology for subsequent drawcalls. The bound pipeline must have been created with
+    /// [`vk::DynamicState::PRIMITIVE_TOPOLOGY`], and the new topology must be of the same class (points, lines, triangles or patches)
+    /// as the topology of the pipeline.
+    /// Equivalent to [`vkCmdSetPrimitiveTopology`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdSetPrimitiveTopology.html)
+    /// # Errors
+    /// * Fails if the device does not support Vulkan 1.3 extended dynamic state.
+    fn set_primitive_topology(self, topology: vk::PrimitiveTopology) -> Result<Self> {
+        require_extended_dynamic_state(&self.device)?;
+        unsafe {
+            self.device.cmd_set_primitive_topology(self.handle, topology);
+        }
+        Ok(self)
+    }
+
+    /// Enable or disable depth testing for subsequent drawcalls. The bound pipeline must have been created with
+    /// [`vk::DynamicState::DEPTH_TEST_ENABLE`].
+    /// Equivalent to [`vkCmdSetDepthTestEnable`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdSetDepthTestEnable.html)
+    /// # Errors
+    /// * Fails if the device does not support Vulkan 1.3 extended dynamic state.
+    fn set_depth_test_enable(self, enable: bool) -> Result<Self> {
+        require_extended_dynamic_state(&self.device)?;
+        unsafe {
+            self.device.cmd_set_depth_test_enable(self.handle, enable);
+        }
+        Ok(self)
+    }
+
+    /// Enable or disable depth writes for subsequent drawcalls. The bound pipeline must have been created with
+    /// [`vk::DynamicState::DEPTH_WRITE_ENABLE`].
+    /// Equivalent to [`vkCmdSetDepthWriteEnable`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdSetDepthWriteEnable.html)
+    /// # Errors
+    /// * Fails if the device does not support Vulkan 1.3 extended dynamic state.
+    fn set_depth_write_enable(self, enable: bool) -> Result<Self> {
+        require_extended_dynamic_state(&self.device)?;
+        unsafe {
+            self.device.cmd_set_depth_write_enable(self.handle, enable);
+        }
+        Ok(self)
+    }
+
+    /// Set the depth compare operation for subsequent drawcalls. The bound pipeline must have been created with
+    /// [`vk::DynamicState::DEPTH_COMPARE_OP`].
+    /// Equivalent to [`vkCmdSetDepthCompareOp`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdSetDepthCompareOp.html)
+    /// # Errors
+    /// * Fails if the device does not support Vulkan 1.3 extended dynamic state.
+    fn set_depth_compare_op(self, op: vk::CompareOp) -> Result<Self> {
+        require_extended_dynamic_state(&self.device)?;
+        unsafe {
+            self.device.cmd_set_depth_compare_op(self.handle, op);
+        }
+        Ok(self)
+    }
+
+    /// Enable or disable blending for the color attachments starting at `first_attachment`. The bound pipeline must have been
+    /// created with [`vk::DynamicState::COLOR_BLEND_ENABLE_EXT`]. Only available if `VK_EXT_extended_dynamic_state3` was enabled
+    /// on device creation, and the device supports this dynamic state.
+    /// Equivalent to [`vkCmdSetColorBlendEnableEXT`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdSetColorBlendEnableEXT.html)
+    /// # Errors
+    /// * Fails if `VK_EXT_extended_dynamic_state3` is not enabled.
+    /// * Fails if the `extendedDynamicState3ColorBlendEnable` feature is not supported.
+    fn set_color_blend_enable(self, first_attachment: u32, enable: &[bool]) -> Result<Self> {
+        let funcs = self
+            .device
+            .dynamic_state3()
+            .ok_or_else::<anyhow::Error, _>(|| {
+                Error::ExtensionNotSupported(ExtensionID::ExtendedDynamicState3).into()
+            })?;
+        if self.device.dynamic_state3_features().extended_dynamic_state3_color_blend_enable != vk::TRUE {
+            return Err(Error::FeatureNotSupported("extendedDynamicState3ColorBlendEnable").into());
+        }
+        let enable = enable.iter().map(|enable| vk::Bool32::from(*enable)).collect::<Vec<_>>();
+        // SAFETY: Vulkan API call. This function pointer is not null because we just verified its availability.
+        unsafe {
+            funcs.cmd_set_color_blend_enable(self.handle, first_attachment, &enable);
+        }
+        Ok(self)
+    }
+
+    /// Set the fragment shading rate for subsequent drawcalls. The bound pipeline must have been created with the
+    /// [`vk::DynamicState::FRAGMENT_SHADING_RATE_KHR`] dynamic state. The combiner operations determine how this rate is combined
+    /// with the per-primitive rate and the rate from the shading rate attachment.
+    /// Requires [`ExtensionID::FragmentShadingRate`] to be enabled.
+    /// Directly translates to [`vkCmdSetFragmentShadingRateKHR`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdSetFragmentShadingRateKHR.html).
+    /// # Errors
+    /// * Fails if [`ExtensionID::FragmentShadingRate`] is not enabled.
+    /// # Example
+    /// ```
+    /// # use anyhow::Result;
+    /// # use phobos::*;
+    /// # use phobos::sync::domain::*;
+    /// fn draw_coarse<C: GraphicsCmdBuffer>(cmd: C) -> Result<C> {
+    ///     // Shade once per 2x2 pixel block, ignoring other shading rate sources.
+    ///     cmd.set_fragment_shading_rate(
+    ///         vk::Extent2D { width: 2, height: 2 },
+    ///         [vk::FragmentShadingRateCombinerOpKHR::KEEP; 2],
+    ///     )?
+    ///     .draw(6, 1, 0, 0)
+    /// }
+    /// ```
+    fn set_fragment_shading_rate(
+        self,
+        fragment_size: vk::Extent2D,
+        combiner_ops: [vk::FragmentShadingRateCombinerOpKHR; 2],
+    ) -> Result<Self> {
+        self.device.require_extension(ExtensionID::FragmentShadingRate)?;
+        let fns = self.device.fragment_shading_rate().unwrap();
+        // SAFETY: Vulkan API call. This function pointer is not null because we just verified its availability.
+        unsafe {
+            (fns.cmd_set_fragment_shading_rate_khr)(self.handle, &fragment_size, &combiner_ops);
+        }
+        Ok(self)
+    }
+
+    /// Begin a conditional rendering block. All drawcalls and dispatches recorded until the matching
+    /// [`end_conditional_rendering`](GraphicsCmdBuffer::end_conditional_rendering) are discarded by the GPU if the 32-bit value
+    /// at the start of `buffer` is zero. If `inverted` is true, they are instead discarded if the value is non-zero.
+    /// This allows skipping work based on GPU-side results (such as occlusion queries) without a readback to the CPU.
+    /// The buffer must be created with `VK_BUFFER_USAGE_CONDITIONAL_RENDERING_BIT_EXT`, and its offset must be a multiple of 4.
+    /// Requires [`ExtensionID::ConditionalRendering`] to be enabled.
+    /// Directly translates to [`vkCmdBeginConditionalRenderingEXT`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdBeginConditionalRenderingEXT.html).
+    /// # Errors
+    /// * Fails if [`ExtensionID::ConditionalRendering`] is not enabled.
+    /// * Fails if the offset of `buffer` is not a multiple of 4.
+    /// # Example
+    /// ```
+    /// # use anyhow::Result;
+    /// # use phobos::*;
+    /// # use phobos::sync::domain::*;
+    /// // `visible` holds a value written by an earlier occlusion culling pass.
+    /// fn draw_if_visible<C: GraphicsCmdBuffer>(cmd: C, visible: &BufferView) -> Result<C> {
+    ///     cmd.begin_conditional_rendering(visible, false)?
+    ///        .draw(6, 1, 0, 0)?
+    ///        .end_conditional_rendering()
+    /// }
+    /// ```
+    fn begin_conditional_rendering(self, buffer: &BufferView, inverted: bool) -> Result<Self> {
+        self.device.require_extension(ExtensionID::ConditionalRendering)?;
+        ensure!(
+            buffer.offset() % 4 == 0,
+            "Conditional rendering buffer offset {} is not a multiple of 4",
+            buffer.offset()
+        );
+        let fns = self.device.conditional_rendering().unwrap();
+        let flags = if inverted {
+            vk::ConditionalRenderingFlagsEXT::INVERTED
+        } else {
+            vk::ConditionalRenderingFlagsEXT::empty()
+        };
+        // SAFETY: Vulkan API call. This function pointer is not null because we just verified its availability.
+        unsafe {
+            let info = vk::ConditionalRenderingBeginInfoEXT {
+                buffer: buffer.handle(),
+                offset: buffer.offset(),
+                flags,
+                ..Default::default()
+            };
+            (fns.cmd_begin_conditional_rendering_ext)(self.handle, &info);
+        }
+        Ok(self)
+    }
+
+    /// End the current conditional rendering block started with [`begin_conditional_rendering`](GraphicsCmdBuffer::begin_conditional_rendering).
+    /// Requires [`ExtensionID::ConditionalRendering`] to be enabled.
+    /// Directly translates to [`vkCmdEndConditionalRenderingEXT`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdEndConditionalRenderingEXT.html).
+    /// # Errors
+    /// * Fails if [`ExtensionID::ConditionalRendering`] is not enabled.
+    fn end_conditional_rendering(self) -> Result<Self> {
+        self.device.require_extension(ExtensionID::ConditionalRendering)?;
+        let fns = self.device.conditional_rendering().unwrap();
+        // SAFETY: Vulkan API call. This function pointer is not null because we just verified its availability.
+        unsafe {
+            (fns.cmd_end_conditional_rendering_ext)(self.handle);
+        }
+        Ok(self)
+    }
 }
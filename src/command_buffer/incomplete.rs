@@ -3,23 +3,27 @@
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::marker::PhantomData;
-use std::sync::{Arc, MutexGuard};
+use std::ops::Range;
+use std::sync::{Arc, Mutex, MutexGuard};
 
 use anyhow::{anyhow, ensure, Result};
 use ash::vk;
 
 use crate::command_buffer::state::{RenderingAttachmentInfo, RenderingInfo};
 use crate::command_buffer::{CommandBuffer, IncompleteCommandBuffer};
-use crate::core::queue::Queue;
+use crate::core::queue::{DeviceQueue, Queue};
 use crate::descriptor::builder::DescriptorSetBuilder;
+use crate::graph::pass::PassConstants;
 use crate::pipeline::create_info::PipelineRenderingInfo;
-use crate::query_pool::{QueryPool, ScopedQuery, TimestampQuery};
+use crate::pipeline::pipeline_layout::{is_valid_push_constant_update, PushConstantRange};
+use crate::pipeline::shader_object::ShaderObjectBinding;
+use crate::query_pool::{Query, QueryPool, ScopedQuery, TimestampQuery};
 use crate::raytracing::acceleration_structure::AccelerationStructure;
 use crate::sync::domain::ExecutionDomain;
 use crate::{
-    Allocator, BufferView, DebugMessenger, DescriptorCache, DescriptorSet, Device, ImageView,
-    IncompleteCmdBuffer, PhysicalResourceBindings, PipelineCache, PipelineStage, Sampler,
-    VirtualResource,
+    AliasedResource, Allocator, BufferView, DebugMessenger, DescriptorCache, Device, Error, ImageView,
+    IncompleteCmdBuffer, PersistentDescriptorSet, PhysicalResourceBindings, PipelineCache,
+    PipelineStage, Sampler, TexelBufferView, VirtualResource,
 };
 
 impl<'q, D: ExecutionDomain, A: Allocator> IncompleteCmdBuffer<'q, A>
@@ -53,16 +57,19 @@ impl<'q, D: ExecutionDomain, A: Allocator> IncompleteCmdBuffer<'q, A>
         Ok(IncompleteCommandBuffer {
             device,
             handle,
+            usage: flags,
             timestamp_valid_bits: queue_lock.family_properties().timestamp_valid_bits,
             queue_lock,
             current_pipeline_layout: vk::PipelineLayout::null(),
             current_set_layouts: vec![],
+            current_push_constants: vec![],
             current_bindpoint: vk::PipelineBindPoint::default(),
             current_rendering_state: None,
             current_render_area: Default::default(),
             current_descriptor_sets: None,
             descriptor_state_needs_update: false,
             current_sbt_regions: None,
+            current_pass_constants: None,
             descriptor_cache: descriptors,
             pipeline_cache: pipelines,
             _domain: PhantomData,
@@ -91,6 +98,10 @@ impl<'q, D: ExecutionDomain, A: Allocator> IncompleteCmdBuffer<'q, A>
         unsafe { self.device.end_command_buffer(self.handle)? }
         Ok(CommandBuffer {
             handle: self.handle,
+            usage: self.usage,
+            queue_family: self.queue_lock.info().family_index,
+            submitted: false,
+            pending: Default::default(),
             _domain: PhantomData,
         })
     }
@@ -100,7 +111,7 @@ impl<D: ExecutionDomain, A: Allocator> IncompleteCommandBuffer<'_, D, A> {
     /// Bind a descriptor set to the command buffer.
     /// # Errors
     /// - Fails if no pipeline was bound.
-    pub(super) fn bind_descriptor_set(&self, index: u32, set: &DescriptorSet) -> Result<()> {
+    pub(super) fn bind_descriptor_set(&self, index: u32, set: vk::DescriptorSet) -> Result<()> {
         ensure!(
             self.current_pipeline_layout != vk::PipelineLayout::null(),
             "cannot bind descriptor set at index {index} without binding a pipeline first."
@@ -116,7 +127,7 @@ impl<D: ExecutionDomain, A: Allocator> IncompleteCommandBuffer<'_, D, A> {
                 self.current_bindpoint,
                 self.current_pipeline_layout,
                 index,
-                std::slice::from_ref(&set.handle),
+                std::slice::from_ref(&set),
                 &[],
             );
         }
@@ -164,7 +175,7 @@ impl<D: ExecutionDomain, A: Allocator> IncompleteCommandBuffer<'_, D, A> {
             let mut info = builder.build();
             info.layout = *self.current_set_layouts.get(index as usize).unwrap();
             cache.with_descriptor_set(info, |set| {
-                self.bind_descriptor_set(index, set)?;
+                self.bind_descriptor_set(index, set.handle)?;
                 Ok(())
             })?;
         }
@@ -182,6 +193,7 @@ impl<D: ExecutionDomain, A: Allocator> IncompleteCommandBuffer<'_, D, A> {
         handle: vk::Pipeline,
         layout: vk::PipelineLayout,
         set_layouts: Vec<vk::DescriptorSetLayout>,
+        push_constants: &[PushConstantRange],
         bind_point: vk::PipelineBindPoint,
     ) -> Result<()> {
         unsafe {
@@ -194,9 +206,54 @@ impl<D: ExecutionDomain, A: Allocator> IncompleteCommandBuffer<'_, D, A> {
         self.current_bindpoint = bind_point;
         self.current_pipeline_layout = layout;
         self.current_set_layouts = set_layouts.clone();
+        self.current_push_constants = push_constants.to_vec();
         Ok(())
     }
 
+    pub(super) fn bind_shader_objects_impl(&mut self, binding: &ShaderObjectBinding) -> Result<()> {
+        unsafe {
+            // SAFETY:
+            // * `self` is valid, so `self.device` and `self.handle` are valid vulkan objects.
+            // * The shaders and layout in `binding` are valid entries from the pipeline cache.
+            binding.record(&self.device, self.handle, self.current_render_area)?;
+        }
+        self.current_bindpoint = vk::PipelineBindPoint::GRAPHICS;
+        self.current_pipeline_layout = binding.layout;
+        self.current_set_layouts = binding.set_layouts.clone();
+        self.current_push_constants = binding.info.layout.push_constants.clone();
+        Ok(())
+    }
+
+    /// Bind a [`PersistentDescriptorSet`] at the given set index. Unlike the other descriptor binding functions, this binds the set
+    /// immediately and does not go through the descriptor cache, so a pipeline must already be bound. Any pending descriptors
+    /// recorded for this set index through the other `bind_xxx` functions are discarded.
+    ///
+    /// The set must have been created with a layout compatible with the one of the bound pipeline at this set index.
+    /// # Errors
+    /// * Fails if no pipeline was bound.
+    /// # Example
+    /// ```
+    /// # use anyhow::Result;
+    /// # use phobos::sync::domain::ExecutionDomain;
+    /// # use phobos::*;
+    /// fn draw_scene<'q, D: ExecutionDomain + GfxSupport>(cmd: IncompleteCommandBuffer<'q, D>, scene: &PersistentDescriptorSet, material: &BufferView) -> Result<IncompleteCommandBuffer<'q, D>> {
+    ///     cmd.bind_graphics_pipeline("scene")?
+    ///         // Static scene data is written once and bound directly
+    ///        .bind_persistent_descriptor_set(0, scene)?
+    ///         // Per-draw data can still go through the descriptor cache
+    ///        .bind_uniform_buffer(1, 0, material)?
+    ///        .draw(6, 1, 0, 0)
+    /// }
+    /// ```
+    pub fn bind_persistent_descriptor_set(mut self, index: u32, set: &PersistentDescriptorSet) -> Result<Self> {
+        if let Some(sets) = self.current_descriptor_sets.as_mut() {
+            sets.remove(&index);
+        }
+        // SAFETY: The handle is only used to bind the set, which is kept alive by the caller.
+        self.bind_descriptor_set(index, unsafe { set.handle() })?;
+        Ok(self)
+    }
+
     /// Clear descriptor set state. Calling this will reset the current descriptor state to nothing being bound.
     /// It does not explicitly unbind descriptor sets, but the next `draw()` or `dispatch()` call will
     /// reflect this change. This function is not extremely useful at the moment.
@@ -284,7 +341,15 @@ impl<D: ExecutionDomain, A: Allocator> IncompleteCommandBuffer<'_, D, A> {
         Ok(self)
     }
 
-    /// Bind an entire array of sampled images using the same sampler.
+    /// Bind an entire array of sampled images using the same sampler, with descriptor type [`vk::DescriptorType::COMBINED_IMAGE_SAMPLER`].
+    /// This binding is not actually flushed to the command buffer until the next draw or dispatch call.
+    ///
+    /// Unsized arrays in the shader (e.g. `sampler2D textures[]`) are reflected as partially bound arrays with a variable descriptor count
+    /// if the device supports it, so only `images.len()` descriptors are allocated and written.
+    ///
+    /// Expects the images to be in [`vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL`]
+    /// # Errors
+    /// None
     pub fn bind_sampled_image_array(
         mut self,
         set: u32,
@@ -299,6 +364,84 @@ impl<D: ExecutionDomain, A: Allocator> IncompleteCommandBuffer<'_, D, A> {
         Ok(self)
     }
 
+    /// Bind an entire array of images with descriptor type [`vk::DescriptorType::SAMPLED_IMAGE`]. Unlike
+    /// [`IncompleteCommandBuffer::bind_sampled_image_array()`], no sampler is bound with the images, so a material system can
+    /// bind all its textures once and sample them with a few samplers bound through [`IncompleteCommandBuffer::bind_sampler()`].
+    /// This binding is not actually flushed to the command buffer until the next draw or dispatch call.
+    ///
+    /// Expects the images to be in [`vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL`]
+    /// # Errors
+    /// None
+    /// # Example
+    /// ```
+    /// # use anyhow::Result;
+    /// # use phobos::sync::domain::ExecutionDomain;
+    /// # use phobos::*;
+    /// fn bind_materials<'q, D: ExecutionDomain + GfxSupport>(cmd: IncompleteCommandBuffer<'q, D>, textures: &[ImageView], sampler: &Sampler) -> Result<IncompleteCommandBuffer<'q, D>> {
+    ///     // In GLSL:
+    ///     // layout(set = 0, binding = 0) uniform sampler linear_sampler;
+    ///     // layout(set = 0, binding = 1) uniform texture2D textures[];
+    ///     cmd.bind_sampler(0, 0, sampler)?
+    ///        .bind_image_array(0, 1, textures)?
+    ///        .draw(6, 1, 0, 0)
+    /// }
+    /// ```
+    pub fn bind_image_array(mut self, set: u32, binding: u32, images: &[ImageView]) -> Result<Self> {
+        self.modify_descriptor_set(set, |builder| {
+            builder.bind_image_array(binding, images);
+            Ok(())
+        })?;
+        Ok(self)
+    }
+
+    /// Binds a new descriptor with type [`vk::DescriptorType::SAMPLER`].
+    /// This binding is not actually flushed to the command buffer until the next draw or dispatch call.
+    /// # Errors
+    /// None
+    pub fn bind_sampler(mut self, set: u32, binding: u32, sampler: &Sampler) -> Result<Self> {
+        self.modify_descriptor_set(set, |builder| {
+            builder.bind_sampler(binding, sampler);
+            Ok(())
+        })?;
+        Ok(self)
+    }
+
+    /// Binds a single element of a descriptor array with type [`vk::DescriptorType::COMBINED_IMAGE_SAMPLER`].
+    /// This binding is not actually flushed to the command buffer until the next draw or dispatch call.
+    /// When the descriptor set is flushed, only array elements that changed compared to the previous descriptor set with the
+    /// same layout are written, which makes this suitable for updating a few slots of a large material table every frame.
+    ///
+    /// Expects the image to be in [`vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL`]
+    /// # Errors
+    /// None
+    /// # Example
+    /// ```
+    /// # use anyhow::Result;
+    /// # use phobos::sync::domain::ExecutionDomain;
+    /// # use phobos::*;
+    /// fn update_material<'q, D: ExecutionDomain + GfxSupport>(cmd: IncompleteCommandBuffer<'q, D>, textures: &[ImageView], changed: &ImageView, sampler: &Sampler) -> Result<IncompleteCommandBuffer<'q, D>> {
+    ///     // In GLSL: layout(set = 0, binding = 0) uniform sampler2D textures[];
+    ///     cmd.bind_sampled_image_array(0, 0, textures, sampler)?
+    ///        // Replace only the texture at index 3
+    ///        .bind_sampled_image_at_index(0, 0, 3, changed, sampler)?
+    ///        .draw(6, 1, 0, 0)
+    /// }
+    /// ```
+    pub fn bind_sampled_image_at_index(
+        mut self,
+        set: u32,
+        binding: u32,
+        index: u32,
+        image: &ImageView,
+        sampler: &Sampler,
+    ) -> Result<Self> {
+        self.modify_descriptor_set(set, |builder| {
+            builder.bind_sampled_image_at_index(binding, index, image, sampler);
+            Ok(())
+        })?;
+        Ok(self)
+    }
+
     /// Binds a new descriptor with type [`vk::DescriptorType::UNIFORM_BUFFER`].
     /// This binding is not actually flushed to the command buffer until the next draw or dispatch call.
     /// # Errors
@@ -355,6 +498,62 @@ impl<D: ExecutionDomain, A: Allocator> IncompleteCommandBuffer<'_, D, A> {
         Ok(self)
     }
 
+    /// Binds a new descriptor with type [`vk::DescriptorType::UNIFORM_TEXEL_BUFFER`].
+    /// This binding is not actually flushed to the command buffer until the next draw or dispatch call.
+    /// # Errors
+    /// None
+    /// # Example
+    /// ```
+    /// # use anyhow::Result;
+    /// # use phobos::sync::domain::ExecutionDomain;
+    /// # use phobos::*;
+    /// fn use_bind_uniform_texel_buffer<'q, D: ExecutionDomain + GfxSupport>(cmd: IncompleteCommandBuffer<'q, D>, view: &TexelBufferView) -> Result<IncompleteCommandBuffer<'q, D>> {
+    ///     cmd.bind_uniform_texel_buffer(0, 0, view)?
+    ///         // This drawcall will flush the descriptor state and bind proper descriptor sets.
+    ///        .draw(6, 1, 0, 0)
+    /// }
+    /// ```
+    pub fn bind_uniform_texel_buffer(
+        mut self,
+        set: u32,
+        binding: u32,
+        view: &TexelBufferView,
+    ) -> Result<Self> {
+        self.modify_descriptor_set(set, |builder| {
+            builder.bind_uniform_texel_buffer(binding, view);
+            Ok(())
+        })?;
+        Ok(self)
+    }
+
+    /// Binds a new descriptor with type [`vk::DescriptorType::STORAGE_TEXEL_BUFFER`].
+    /// This binding is not actually flushed to the command buffer until the next draw or dispatch call.
+    /// # Errors
+    /// None
+    /// # Example
+    /// ```
+    /// # use anyhow::Result;
+    /// # use phobos::sync::domain::ExecutionDomain;
+    /// # use phobos::*;
+    /// fn use_bind_storage_texel_buffer<'q, D: ExecutionDomain + ComputeSupport>(cmd: IncompleteCommandBuffer<'q, D>, view: &TexelBufferView) -> Result<IncompleteCommandBuffer<'q, D>> {
+    ///     cmd.bind_storage_texel_buffer(0, 0, view)?
+    ///         // This dispatch will flush the descriptor state and bind proper descriptor sets.
+    ///        .dispatch(64, 1, 1)
+    /// }
+    /// ```
+    pub fn bind_storage_texel_buffer(
+        mut self,
+        set: u32,
+        binding: u32,
+        view: &TexelBufferView,
+    ) -> Result<Self> {
+        self.modify_descriptor_set(set, |builder| {
+            builder.bind_storage_texel_buffer(binding, view);
+            Ok(())
+        })?;
+        Ok(self)
+    }
+
     /// Binds a new descriptor with type [`vk::DescriptorType::STORAGE_IMAGE`].
     /// This binding is not actually flushed to the command buffer until the next draw or dispatch call.
     ///
@@ -413,6 +612,53 @@ impl<D: ExecutionDomain, A: Allocator> IncompleteCommandBuffer<'_, D, A> {
         Ok(self)
     }
 
+    /// Binds a new descriptor with type [`vk::DescriptorType::INPUT_ATTACHMENT`]. Input attachments can only be read in
+    /// fragment shaders, at the current fragment's location. Declare the read in the pass graph using
+    /// [`PassBuilder::input_attachment()`](crate::PassBuilder::input_attachment) so the image is in the right layout.
+    /// This binding is not actually flushed to the command buffer until the next draw call.
+    ///
+    /// Expects the image to be in [`vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL`]
+    /// # Errors
+    /// None
+    /// # Example
+    /// ```
+    /// # use anyhow::Result;
+    /// # use phobos::sync::domain::ExecutionDomain;
+    /// # use phobos::*;
+    /// fn use_bind_input_attachment<'q, D: ExecutionDomain + GfxSupport>(cmd: IncompleteCommandBuffer<'q, D>, gbuffer: &ImageView) -> Result<IncompleteCommandBuffer<'q, D>> {
+    ///     cmd.bind_input_attachment(0, 0, gbuffer)?
+    ///         // This drawcall will flush the descriptor state and bind proper descriptor sets.
+    ///        .draw(6, 1, 0, 0)
+    /// }
+    /// ```
+    pub fn bind_input_attachment(mut self, set: u32, binding: u32, image: &ImageView) -> Result<Self> {
+        self.modify_descriptor_set(set, |builder| {
+            builder.bind_input_attachment(binding, image);
+            Ok(())
+        })?;
+        Ok(self)
+    }
+
+    /// Binds a new descriptor with descriptor type [`vk::DescriptorType::INPUT_ATTACHMENT`]. The image bound to this is
+    /// the image obtained by resolving the input resource from the given resource bindings.
+    /// This binding is not actually flushed to the command buffer until the next draw call.
+    ///
+    /// Expects the image to be in [`vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL`]
+    /// # Errors
+    /// * Fails if the virtual resource has no physical binding associated to it.
+    pub fn resolve_and_bind_input_attachment(
+        mut self,
+        set: u32,
+        binding: u32,
+        resource: &VirtualResource,
+        bindings: &PhysicalResourceBindings,
+    ) -> Result<Self> {
+        self.modify_descriptor_set(set, |builder| {
+            builder.resolve_and_bind_input_attachment(binding, resource, bindings)
+        })?;
+        Ok(self)
+    }
+
     /// Binds a new descriptor with descriptor type [`vk::DescriptorType::ACCELERATION_STRUCTURE_KHR`]. The
     /// `VK_KHR_acceleration_structure` extension must be enabled for this (use [`AppBuilder::raytracing()`](crate::AppBuilder::raytracing() to enable).
     /// # Example
@@ -439,6 +685,26 @@ impl<D: ExecutionDomain, A: Allocator> IncompleteCommandBuffer<'_, D, A> {
         Ok(self)
     }
 
+    /// Binds a new descriptor with descriptor type [`vk::DescriptorType::ACCELERATION_STRUCTURE_KHR`]. The acceleration
+    /// structure bound to this is obtained by resolving the input resource from the given resource bindings.
+    /// Declare the read in the pass graph using [`PassBuilder::read_acceleration_structure()`](crate::PassBuilder::read_acceleration_structure)
+    /// so the pass waits for the acceleration structure to be built.
+    /// This binding is not actually flushed to the command buffer until the next draw, dispatch or trace rays call.
+    /// # Errors
+    /// * Fails if the virtual resource has no acceleration structure bound to it.
+    pub fn resolve_and_bind_acceleration_structure(
+        mut self,
+        set: u32,
+        binding: u32,
+        resource: &VirtualResource,
+        bindings: &PhysicalResourceBindings,
+    ) -> Result<Self> {
+        self.modify_descriptor_set(set, |builder| {
+            builder.resolve_and_bind_acceleration_structure(binding, resource, bindings)
+        })?;
+        Ok(self)
+    }
+
     /// Transitions an image layout manually. For attachment layouts and other
     /// resources used in the pass graph, this can be done automatically.
     pub fn transition_image(
@@ -480,6 +746,166 @@ impl<D: ExecutionDomain, A: Allocator> IncompleteCommandBuffer<'_, D, A> {
         self.pipeline_barrier(&dependency)
     }
 
+    /// Get the index of the queue family this command buffer is recorded for.
+    pub fn queue_family(&self) -> u32 {
+        self.queue_lock.info().family_index
+    }
+
+    /// Get the device queue this command buffer will be submitted to.
+    pub(crate) fn device_queue(&self) -> Arc<Mutex<DeviceQueue>> {
+        self.queue_lock.device_queue()
+    }
+
+    /// Release ownership of an image to the queue family `dst_family`, and transition it from layout `from` to `to`.
+    /// This waits for `src_stage` and `src_access` of previous commands.
+    ///
+    /// Images with [`vk::SharingMode::EXCLUSIVE`] (see [`Image::sharing_mode()`](crate::Image::sharing_mode)) are owned by a
+    /// single queue family. Before such an image can be used on a queue of another family, it must be released on the
+    /// old queue and acquired on the new queue with [`IncompleteCommandBuffer::acquire_image()`], using the same layouts.
+    /// The acquiring submission must wait for the releasing submission, for example with a semaphore. Buffers are always
+    /// created with [`vk::SharingMode::CONCURRENT`] on devices with multiple queue families, so they never need this.
+    ///
+    /// If no ownership transfer is needed, because the image is concurrent or `dst_family` is the family of this command buffer,
+    /// this records an ordinary layout transition that makes the image available to all later commands, and the matching
+    /// acquire records nothing. This makes it safe to always release and acquire resources that move between queues.
+    /// # Example
+    /// ```
+    /// # use phobos::*;
+    /// # use phobos::sync::domain::{Graphics, Transfer};
+    /// fn hand_over<'q>(
+    ///     transfer: IncompleteCommandBuffer<'q, Transfer>,
+    ///     graphics: IncompleteCommandBuffer<'q, Graphics>,
+    ///     image: &ImageView,
+    /// ) -> (IncompleteCommandBuffer<'q, Transfer>, IncompleteCommandBuffer<'q, Graphics>) {
+    ///     let layout = vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL;
+    ///     let transfer = transfer.release_image(
+    ///         image,
+    ///         graphics.queue_family(),
+    ///         PipelineStage::TRANSFER,
+    ///         vk::AccessFlags2::TRANSFER_WRITE,
+    ///         vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+    ///         layout,
+    ///     );
+    ///     // Submit the graphics command buffer after the transfer command buffer finished.
+    ///     let graphics = graphics.acquire_image(
+    ///         image,
+    ///         transfer.queue_family(),
+    ///         PipelineStage::FRAGMENT_SHADER,
+    ///         vk::AccessFlags2::SHADER_SAMPLED_READ,
+    ///         vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+    ///         layout,
+    ///     );
+    ///     (transfer, graphics)
+    /// }
+    /// ```
+    pub fn release_image(
+        self,
+        image: &ImageView,
+        dst_family: u32,
+        src_stage: PipelineStage,
+        src_access: vk::AccessFlags2,
+        from: vk::ImageLayout,
+        to: vk::ImageLayout,
+    ) -> Self {
+        let src_family = self.queue_family();
+        if !needs_ownership_transfer(image, src_family, dst_family) {
+            return self.transition_image(
+                image,
+                src_stage,
+                PipelineStage::ALL_COMMANDS,
+                from,
+                to,
+                src_access,
+                vk::AccessFlags2::MEMORY_READ | vk::AccessFlags2::MEMORY_WRITE,
+            );
+        }
+        // The destination scope of a release is ignored, the acquire on the other queue defines it.
+        self.ownership_barrier(
+            image,
+            src_family,
+            dst_family,
+            src_stage,
+            src_access,
+            PipelineStage::NONE,
+            vk::AccessFlags2::NONE,
+            from,
+            to,
+        )
+    }
+
+    /// Acquire ownership of an image that was released by the queue family `src_family` with
+    /// [`IncompleteCommandBuffer::release_image()`]. `from` and `to` must be the same layouts that were given to the release.
+    /// Later commands in `dst_stage` can access the image with `dst_access`. Records nothing if no ownership transfer is needed,
+    /// see [`IncompleteCommandBuffer::release_image()`].
+    pub fn acquire_image(
+        self,
+        image: &ImageView,
+        src_family: u32,
+        dst_stage: PipelineStage,
+        dst_access: vk::AccessFlags2,
+        from: vk::ImageLayout,
+        to: vk::ImageLayout,
+    ) -> Self {
+        let dst_family = self.queue_family();
+        if !needs_ownership_transfer(image, src_family, dst_family) {
+            return self;
+        }
+        // The source scope of an acquire is ignored, the semaphore wait on the release covers it.
+        self.ownership_barrier(
+            image,
+            src_family,
+            dst_family,
+            PipelineStage::NONE,
+            vk::AccessFlags2::NONE,
+            dst_stage,
+            dst_access,
+            from,
+            to,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn ownership_barrier(
+        self,
+        image: &ImageView,
+        src_family: u32,
+        dst_family: u32,
+        src_stage: PipelineStage,
+        src_access: vk::AccessFlags2,
+        dst_stage: PipelineStage,
+        dst_access: vk::AccessFlags2,
+        from: vk::ImageLayout,
+        to: vk::ImageLayout,
+    ) -> Self {
+        let barrier = vk::ImageMemoryBarrier2 {
+            s_type: vk::StructureType::IMAGE_MEMORY_BARRIER_2,
+            p_next: std::ptr::null(),
+            src_stage_mask: src_stage,
+            src_access_mask: src_access,
+            dst_stage_mask: dst_stage,
+            dst_access_mask: dst_access,
+            old_layout: from,
+            new_layout: to,
+            src_queue_family_index: src_family,
+            dst_queue_family_index: dst_family,
+            // SAFETY: A valid image view object has a valid `VkImage` handle.
+            image: unsafe { image.image() },
+            subresource_range: image.subresource_range(),
+        };
+        let dependency = vk::DependencyInfo {
+            s_type: vk::StructureType::DEPENDENCY_INFO,
+            p_next: std::ptr::null(),
+            dependency_flags: vk::DependencyFlags::empty(),
+            memory_barrier_count: 0,
+            p_memory_barriers: std::ptr::null(),
+            buffer_memory_barrier_count: 0,
+            p_buffer_memory_barriers: std::ptr::null(),
+            image_memory_barrier_count: 1,
+            p_image_memory_barriers: &barrier,
+        };
+        self.pipeline_barrier(&dependency)
+    }
+
     /// Insert a global memory barrier. If you want to create a barrier for a buffer, prefer using this as every driver
     /// implements buffer barriers as global memory barriers anyway.
     /// Uses [`vkCmdPipelineBarrier2`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdPipelineBarrier2KHR.html) directly.
@@ -514,6 +940,62 @@ impl<D: ExecutionDomain, A: Allocator> IncompleteCommandBuffer<'_, D, A> {
         self.pipeline_barrier(&dependency)
     }
 
+    /// Activate a resource placed in [`AliasedMemory`](crate::AliasedMemory) before using it. This waits for `src_stage` and
+    /// `src_access` of the previous use of any resource overlapping it, and makes the memory available to `dst_stage` and `dst_access`.
+    /// Images are transitioned from `VK_IMAGE_LAYOUT_UNDEFINED` to the requested layout. The contents of the resource are undefined
+    /// after activation.
+    pub fn activate_aliased(
+        self,
+        resource: AliasedResource,
+        src_stage: PipelineStage,
+        src_access: vk::AccessFlags2,
+        dst_stage: PipelineStage,
+        dst_access: vk::AccessFlags2,
+    ) -> Self {
+        match resource {
+            AliasedResource::Buffer(buffer) => {
+                let barrier = vk::BufferMemoryBarrier2 {
+                    s_type: vk::StructureType::BUFFER_MEMORY_BARRIER_2,
+                    p_next: std::ptr::null(),
+                    src_stage_mask: src_stage,
+                    src_access_mask: src_access,
+                    dst_stage_mask: dst_stage,
+                    dst_access_mask: dst_access,
+                    src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+                    dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+                    // SAFETY: A valid buffer view object has a valid `VkBuffer` handle.
+                    buffer: unsafe { buffer.handle() },
+                    offset: buffer.offset(),
+                    size: buffer.size(),
+                };
+                let dependency = vk::DependencyInfo {
+                    s_type: vk::StructureType::DEPENDENCY_INFO,
+                    p_next: std::ptr::null(),
+                    dependency_flags: vk::DependencyFlags::empty(),
+                    memory_barrier_count: 0,
+                    p_memory_barriers: std::ptr::null(),
+                    buffer_memory_barrier_count: 1,
+                    p_buffer_memory_barriers: &barrier,
+                    image_memory_barrier_count: 0,
+                    p_image_memory_barriers: std::ptr::null(),
+                };
+                self.pipeline_barrier(&dependency)
+            }
+            AliasedResource::Image {
+                view,
+                layout,
+            } => self.transition_image(
+                view,
+                src_stage,
+                dst_stage,
+                vk::ImageLayout::UNDEFINED,
+                layout,
+                src_access,
+                dst_access,
+            ),
+        }
+    }
+
     /// The direct equivalent of a raw [`vkCmdPipelineBarrier2`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdPipelineBarrier2KHR.html) call.
     /// Before calling this, make sure there is not an automatic way to insert this barrier, for example
     /// using the pass graph or using [`IncompleteCommandBuffer::transition_image()`].
@@ -526,11 +1008,16 @@ impl<D: ExecutionDomain, A: Allocator> IncompleteCommandBuffer<'_, D, A> {
 
     /// Upload a single value of push constants. These are small packets of data stored inside the command buffer, so their state is tracked while recording and executing.
     /// Direct translation of [`vkCmdPushConstants`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdPushConstants.html).
+    /// # Errors
+    /// * Fails if no pipeline was bound.
+    /// * Fails with [`Error::InvalidPushConstantRange`] if the update does not match the push constant ranges of the bound pipeline,
+    ///   see [`IncompleteCommandBuffer::push_constants()`].
     /// # Example
     /// ```
     /// # use phobos::*;
     /// # use phobos::sync::domain::ExecutionDomain;
-    /// fn use_push_constant<D: ExecutionDomain>(cmd: IncompleteCommandBuffer<D>) -> IncompleteCommandBuffer<D> {
+    /// # use anyhow::Result;
+    /// fn use_push_constant<D: ExecutionDomain>(cmd: IncompleteCommandBuffer<D>) -> Result<IncompleteCommandBuffer<D>> {
     ///     // Assumes a pipeline is bound, and that this pipeline has a vertex shader with the specified push constant range.
     ///     let data: f32 = 1.0;
     ///     cmd.push_constant(vk::ShaderStageFlags::VERTEX, 0, &data)
@@ -540,17 +1027,24 @@ impl<D: ExecutionDomain, A: Allocator> IncompleteCommandBuffer<'_, D, A> {
         stage: vk::ShaderStageFlags,
         offset: u32,
         data: &T,
-    ) -> Self {
+    ) -> Result<Self> {
         self.push_constants(stage, offset, std::slice::from_ref(data))
     }
 
     /// Upload push constants. These are small packets of data stored inside the command buffer, so their state is tracked while recording and executing.
     /// Direct translation of [`vkCmdPushConstants`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdPushConstants.html).
+    ///
+    /// The update is validated against the push constant ranges of the bound pipeline. Every byte that is written must be part
+    /// of a push constant range for each stage in `stage`, and `stage` must include all stages of every range that is written to.
+    /// # Errors
+    /// * Fails if no pipeline was bound.
+    /// * Fails with [`Error::InvalidPushConstantRange`] if the update does not match the push constant ranges of the bound pipeline.
     /// # Example
     /// ```
     /// # use phobos::*;
     /// # use phobos::sync::domain::ExecutionDomain;
-    /// fn use_push_constants<D: ExecutionDomain>(cmd: IncompleteCommandBuffer<D>) -> IncompleteCommandBuffer<D> {
+    /// # use anyhow::Result;
+    /// fn use_push_constants<D: ExecutionDomain>(cmd: IncompleteCommandBuffer<D>) -> Result<IncompleteCommandBuffer<D>> {
     ///     // Assumes a pipeline is bound, and that this pipeline has a vertex shader with the specified push constant range.
     ///     let data: [f32; 2] = [64.0, 32.0];
     ///     cmd.push_constants(vk::ShaderStageFlags::VERTEX, 0, &data)
@@ -561,12 +1055,26 @@ impl<D: ExecutionDomain, A: Allocator> IncompleteCommandBuffer<'_, D, A> {
         stage: vk::ShaderStageFlags,
         offset: u32,
         data: &[T],
-    ) -> Self {
-        // TODO: Validate push constant ranges with current pipeline layout to prevent crashes.
+    ) -> Result<Self> {
+        ensure!(
+            self.current_pipeline_layout != vk::PipelineLayout::null(),
+            "cannot push constants without binding a pipeline first."
+        );
+        // SAFETY: every data structure can be aligned to a byte slice.
+        let (_, data, _) = unsafe { data.align_to::<u8>() };
+        let size = data.len() as u32;
+        if !is_valid_push_constant_update(&self.current_push_constants, stage, offset, size) {
+            return Err(Error::InvalidPushConstantRange {
+                stage,
+                offset,
+                size,
+            }
+            .into());
+        }
         unsafe {
-            // SAFETY: every data structure can be aligned to a byte slice.
-            let (_, data, _) = data.align_to::<u8>();
-            // SAFETY: self is valid, everything else is up to validation layers.
+            // SAFETY:
+            // * self is valid, so self.handle is valid.
+            // * We verified above that a pipeline is bound, and that the update is within its push constant ranges.
             self.device.cmd_push_constants(
                 self.handle,
                 self.current_pipeline_layout,
@@ -575,7 +1083,38 @@ impl<D: ExecutionDomain, A: Allocator> IncompleteCommandBuffer<'_, D, A> {
                 data,
             );
         }
-        self
+        Ok(self)
+    }
+
+    /// Get the constants of the pass currently being recorded. This is only available inside a pass executor, and only if
+    /// the graph has pass constants enabled with
+    /// [`BuiltPassGraph::set_frame_index()`](crate::graph::pass_graph::BuiltPassGraph::set_frame_index).
+    pub fn pass_constants(&self) -> Option<PassConstants> {
+        self.current_pass_constants
+    }
+
+    /// Upload the constants of the pass currently being recorded as push constants. The bound pipeline must have a push constant
+    /// range of at least 16 bytes at `offset`, see [`PassConstants`] for the expected layout.
+    /// # Errors
+    /// * Fails with [`Error::PassConstantsNotEnabled`] if no pass constants are available, see [`IncompleteCommandBuffer::pass_constants()`].
+    /// # Example
+    /// ```
+    /// # use phobos::*;
+    /// # use phobos::sync::domain::ExecutionDomain;
+    /// # use anyhow::Result;
+    /// fn jittered_draw<D: ExecutionDomain + GfxSupport>(cmd: IncompleteCommandBuffer<D>) -> Result<IncompleteCommandBuffer<D>> {
+    ///     // Assumes the bound pipeline has a PassConstants push constant block at offset 0 in the fragment shader.
+    ///     cmd.push_pass_constants(vk::ShaderStageFlags::FRAGMENT, 0)?
+    ///         .draw(3, 1, 0, 0)
+    /// }
+    /// ```
+    pub fn push_pass_constants(self, stage: vk::ShaderStageFlags, offset: u32) -> Result<Self> {
+        let constants = self.current_pass_constants.ok_or(Error::PassConstantsNotEnabled)?;
+        self.push_constant(stage, offset, &constants)
+    }
+
+    pub(crate) fn set_pass_constants(&mut self, constants: Option<PassConstants>) {
+        self.current_pass_constants = constants;
     }
 
     /// Begin a scoped query. Not all query types are scoped, so the query type must implement
@@ -601,6 +1140,19 @@ impl<D: ExecutionDomain, A: Allocator> IncompleteCommandBuffer<'_, D, A> {
         self
     }
 
+    /// Reset all queries in a query pool on the GPU, and rewind the query pool so the next query index is the first query again.
+    /// This allows reusing the same query pool every frame. Note that the queries are only reset once this command executes, so
+    /// results of the previous usage must be read before this command buffer is submitted.
+    /// Directly translates to [`vkCmdResetQueryPool`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdResetQueryPool.html).
+    pub fn reset_query_pool<Q: Query>(self, query_pool: &mut QueryPool<Q>) -> Self {
+        unsafe {
+            self.device
+                .cmd_reset_query_pool(self.handle, query_pool.handle(), 0, query_pool.count());
+        }
+        query_pool.rewind();
+        self
+    }
+
     /// Write a timestamp to the next entry in a query pool.
     /// # Errors
     /// * Fails if the query pool is out of entries.
@@ -616,6 +1168,77 @@ impl<D: ExecutionDomain, A: Allocator> IncompleteCommandBuffer<'_, D, A> {
         Ok(self)
     }
 
+    /// Copy the results of a range of queries to a buffer, so they can be consumed on the GPU without a round trip to the host.
+    /// The results of each query are written tightly packed, as 64-bit values if `flags` contains [`vk::QueryResultFlags::TYPE_64`]
+    /// and as 32-bit values otherwise. If `flags` contains [`vk::QueryResultFlags::WITH_AVAILABILITY`], an availability value
+    /// is written after the results of each query. This is a transfer write to `dst`, so it must be synchronized with later reads.
+    /// Directly translates to [`vkCmdCopyQueryPoolResults`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdCopyQueryPoolResults.html).
+    /// # Errors
+    /// * Fails if `range` is out of bounds of the query pool.
+    /// * Fails if the offset of `dst` is not a multiple of the result size (8 bytes with [`vk::QueryResultFlags::TYPE_64`], 4 bytes otherwise).
+    /// * Fails if `dst` is too small to hold all results.
+    /// # Example
+    /// ```
+    /// # use phobos::prelude::*;
+    /// # use phobos::sync::domain::ExecutionDomain;
+    /// # use anyhow::Result;
+    /// fn copy_statistics<'q, D: ExecutionDomain>(
+    ///     cmd: IncompleteCommandBuffer<'q, D>,
+    ///     pool: &QueryPool<PipelineStatisticsQuery>,
+    ///     dst: &BufferView,
+    /// ) -> Result<IncompleteCommandBuffer<'q, D>> {
+    ///     cmd.copy_query_pool_results(pool, 0..pool.count(), dst, vk::QueryResultFlags::TYPE_64 | vk::QueryResultFlags::WAIT)
+    /// }
+    /// ```
+    pub fn copy_query_pool_results<Q: Query>(
+        self,
+        query_pool: &QueryPool<Q>,
+        range: Range<u32>,
+        dst: &BufferView,
+        flags: vk::QueryResultFlags,
+    ) -> Result<Self> {
+        ensure!(
+            range.start <= range.end && range.end <= query_pool.count(),
+            "Query range {range:?} out of range of query pool with {} queries",
+            query_pool.count()
+        );
+        let item_size = if flags.contains(vk::QueryResultFlags::TYPE_64) {
+            std::mem::size_of::<u64>()
+        } else {
+            std::mem::size_of::<u32>()
+        };
+        let mut items = query_pool.items_per_query();
+        if flags.contains(vk::QueryResultFlags::WITH_AVAILABILITY) {
+            items += 1;
+        }
+        let stride = (items * item_size) as vk::DeviceSize;
+        let alignment = item_size as vk::DeviceSize;
+        ensure!(
+            dst.offset() % alignment == 0 && stride % alignment == 0,
+            "Query results must be copied to an offset and with a stride that are multiples of {alignment}, got offset {} and stride {stride}",
+            dst.offset()
+        );
+        let size = stride * range.len() as vk::DeviceSize;
+        ensure!(
+            size <= dst.size(),
+            "Buffer of size {} is too small to hold {size} bytes of query results",
+            dst.size()
+        );
+        unsafe {
+            self.device.cmd_copy_query_pool_results(
+                self.handle,
+                query_pool.handle(),
+                range.start,
+                range.len() as u32,
+                dst.handle(),
+                dst.offset(),
+                stride,
+                flags,
+            );
+        }
+        Ok(self)
+    }
+
     /// Begins a dynamic renderpass. This must be called before binding any pipelines.
     pub(crate) fn begin_rendering(mut self, info: &RenderingInfo) -> Self {
         let map_attachment = |attachment: &RenderingAttachmentInfo| vk::RenderingAttachmentInfo {
@@ -647,9 +1270,21 @@ impl<D: ExecutionDomain, A: Allocator> IncompleteCommandBuffer<'_, D, A> {
             .collect::<Vec<_>>();
         let depth_attachment = info.depth_attachment.as_ref().map(map_attachment);
         let stencil_attachment = info.stencil_attachment.as_ref().map(map_attachment);
+        let shading_rate_attachment = info.shading_rate_attachment.as_ref().map(|attachment| {
+            vk::RenderingFragmentShadingRateAttachmentInfoKHR {
+                // SAFETY: A valid ShadingRateAttachmentInfo always stores a valid image view
+                image_view: unsafe { attachment.image_view.handle() },
+                image_layout: vk::ImageLayout::FRAGMENT_SHADING_RATE_ATTACHMENT_OPTIMAL_KHR,
+                shading_rate_attachment_texel_size: attachment.texel_size,
+                ..Default::default()
+            }
+        });
         let vk_info = vk::RenderingInfo {
             s_type: vk::StructureType::RENDERING_INFO,
-            p_next: std::ptr::null(),
+            p_next: match &shading_rate_attachment {
+                Some(attachment) => (attachment as *const _) as *const std::ffi::c_void,
+                None => std::ptr::null(),
+            },
             flags: info.flags,
             render_area: info.render_area,
             layer_count: info.layer_count,
@@ -686,6 +1321,7 @@ impl<D: ExecutionDomain, A: Allocator> IncompleteCommandBuffer<'_, D, A> {
                 .stencil_attachment
                 .as_ref()
                 .map(|attachment| attachment.image_view.format()),
+            shading_rate_attachment: info.shading_rate_attachment.is_some(),
         });
         self.current_render_area = info.render_area;
 
@@ -732,4 +1368,14 @@ impl<D: ExecutionDomain, A: Allocator> IncompleteCommandBuffer<'_, D, A> {
     pub unsafe fn handle(&self) -> vk::CommandBuffer {
         self.handle
     }
+
+    /// Get the device this command buffer was allocated from.
+    pub(crate) fn device(&self) -> &Device {
+        &self.device
+    }
+}
+
+/// Whether moving `image` from a queue of `src_family` to a queue of `dst_family` requires a queue family ownership transfer.
+fn needs_ownership_transfer(image: &ImageView, src_family: u32, dst_family: u32) -> bool {
+    image.sharing_mode() == vk::SharingMode::EXCLUSIVE && src_family != dst_family
 }
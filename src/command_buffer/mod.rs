@@ -14,7 +14,8 @@
 
 use std::collections::HashMap;
 use std::marker::PhantomData;
-use std::sync::MutexGuard;
+use std::sync::{Arc, MutexGuard};
+use std::sync::atomic::{AtomicU32, Ordering};
 
 use anyhow::Result;
 use ash::vk;
@@ -25,7 +26,9 @@ use crate::{
 };
 use crate::core::queue::Queue;
 use crate::descriptor::builder::DescriptorSetBuilder;
+use crate::graph::pass::PassConstants;
 use crate::pipeline::create_info::PipelineRenderingInfo;
+use crate::pipeline::pipeline_layout::PushConstantRange;
 use crate::sync::domain::ExecutionDomain;
 
 pub mod compute;
@@ -33,6 +36,7 @@ pub mod graphics;
 pub mod incomplete;
 pub mod traits;
 pub mod transfer;
+pub mod video;
 
 pub(crate) mod command_pool;
 pub(crate) mod state;
@@ -54,6 +58,11 @@ pub(crate) mod state;
 #[derive(Debug)]
 pub struct CommandBuffer<D: ExecutionDomain> {
     handle: vk::CommandBuffer,
+    usage: vk::CommandBufferUsageFlags,
+    // Family of the queue this command buffer was allocated from. It must be submitted to and freed on this queue.
+    queue_family: u32,
+    submitted: bool,
+    pending: Arc<AtomicU32>,
     _domain: PhantomData<D>,
 }
 
@@ -88,10 +97,12 @@ pub struct IncompleteCommandBuffer<'q, D: ExecutionDomain, A: Allocator = Defaul
     #[derivative(Debug = "ignore")]
     device: Device,
     handle: vk::CommandBuffer,
+    usage: vk::CommandBufferUsageFlags,
     queue_lock: MutexGuard<'q, Queue>,
     timestamp_valid_bits: u32,
     current_pipeline_layout: vk::PipelineLayout,
     current_set_layouts: Vec<vk::DescriptorSetLayout>,
+    current_push_constants: Vec<PushConstantRange>,
     // TODO: Note: technically not correct
     current_bindpoint: vk::PipelineBindPoint,
     current_rendering_state: Option<PipelineRenderingInfo>,
@@ -99,6 +110,7 @@ pub struct IncompleteCommandBuffer<'q, D: ExecutionDomain, A: Allocator = Defaul
     current_descriptor_sets: Option<HashMap<u32, DescriptorSetBuilder<'static>>>,
     descriptor_state_needs_update: bool,
     current_sbt_regions: Option<[vk::StridedDeviceAddressRegionKHR; 4]>,
+    current_pass_constants: Option<PassConstants>,
     // TODO: Only update disturbed descriptor sets
     descriptor_cache: DescriptorCache,
     pipeline_cache: PipelineCache<A>,
@@ -111,7 +123,9 @@ impl<D: ExecutionDomain, A: Allocator> CmdBuffer<A> for CommandBuffer<D> {
     /// # Safety
     /// * This command buffer must not currently be executing on the GPU.
     unsafe fn delete(&mut self, exec: ExecutionManager<A>) -> Result<()> {
-        let queue = exec.get_queue::<D>().ok_or_else(|| Error::NoCapableQueue)?;
+        let queue = exec
+            .get_queue_for_family::<D>(self.queue_family)
+            .ok_or_else(|| Error::NoCapableQueue)?;
         let handle = self.handle;
         self.handle = vk::CommandBuffer::null();
         queue.free_command_buffer::<Self, A>(handle)
@@ -126,4 +140,36 @@ impl<D: ExecutionDomain> CommandBuffer<D> {
     pub unsafe fn handle(&self) -> vk::CommandBuffer {
         self.handle
     }
+
+    /// Get the usage flags this command buffer was recorded with.
+    pub fn usage(&self) -> vk::CommandBufferUsageFlags {
+        self.usage
+    }
+
+    /// Get the queue family index of the queue this command buffer was allocated from.
+    pub fn queue_family(&self) -> u32 {
+        self.queue_family
+    }
+
+    /// Whether this command buffer has been submitted and has not finished executing yet.
+    pub fn is_pending(&self) -> bool {
+        self.pending.load(Ordering::Acquire) > 0
+    }
+
+    /// Validate that this command buffer may be submitted, and mark it as pending. The returned counter must be
+    /// decremented once the submission has finished executing.
+    /// # Errors
+    /// * Fails with [`Error::CommandBufferResubmitted`] if this is a one-time submit command buffer that was already submitted.
+    /// * Fails with [`Error::CommandBufferPending`] if this command buffer is still pending and does not allow simultaneous use.
+    pub(crate) fn begin_submit(&mut self) -> Result<Arc<AtomicU32>> {
+        if self.submitted && self.usage.contains(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT) {
+            return Err(Error::CommandBufferResubmitted.into());
+        }
+        if self.is_pending() && !self.usage.contains(vk::CommandBufferUsageFlags::SIMULTANEOUS_USE) {
+            return Err(Error::CommandBufferPending.into());
+        }
+        self.submitted = true;
+        self.pending.fetch_add(1, Ordering::AcqRel);
+        Ok(self.pending.clone())
+    }
 }
@@ -15,6 +15,11 @@ pub(crate) struct RenderingAttachmentInfo {
     pub clear_value: vk::ClearValue,
 }
 
+pub(crate) struct ShadingRateAttachmentInfo {
+    pub image_view: ImageView,
+    pub texel_size: vk::Extent2D,
+}
+
 pub(crate) struct RenderingInfo {
     pub flags: vk::RenderingFlags,
     pub render_area: vk::Rect2D,
@@ -23,4 +28,5 @@ pub(crate) struct RenderingInfo {
     pub color_attachments: Vec<RenderingAttachmentInfo>,
     pub depth_attachment: Option<RenderingAttachmentInfo>,
     pub stencil_attachment: Option<RenderingAttachmentInfo>,
+    pub shading_rate_attachment: Option<ShadingRateAttachmentInfo>,
 }
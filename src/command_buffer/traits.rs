@@ -9,11 +9,13 @@ use crate::{
     Allocator, BufferView, DescriptorCache, Device, ExecutionManager, ImageView, PipelineCache,
 };
 use crate::command_buffer::CommandBuffer;
+use crate::command_buffer::transfer::ImageRegion;
 use crate::core::queue::Queue;
 use crate::query_pool::{AccelerationStructurePropertyQuery, QueryPool};
 use crate::raytracing::*;
 use crate::sync::domain;
 use crate::sync::domain::ExecutionDomain;
+use crate::video::{H264DecodeInfo, VideoReferenceSlot, VideoSession, VideoSessionParameters};
 
 /// Trait representing a command buffer that supports transfer commands.
 pub trait TransferCmdBuffer {
@@ -26,6 +28,39 @@ pub trait TransferCmdBuffer {
     fn copy_buffer_to_image(self, src: &BufferView, dst: &ImageView) -> Result<Self>
     where
         Self: Sized;
+    /// Copy a buffer to a region of an image.
+    fn copy_buffer_to_image_region(self, src: &BufferView, dst: &ImageView, region: &ImageRegion) -> Result<Self>
+    where
+        Self: Sized;
+    /// Copy an image to a buffer.
+    fn copy_image_to_buffer(self, src: &ImageView, dst: &BufferView) -> Result<Self>
+    where
+        Self: Sized;
+    /// Copy a region of an image to a buffer.
+    fn copy_image_region_to_buffer(self, src: &ImageView, region: &ImageRegion, dst: &BufferView) -> Result<Self>
+    where
+        Self: Sized;
+    /// Fill a buffer view with a repeated 32-bit value. Equivalent of `vkCmdFillBuffer`.
+    fn fill_buffer(self, dst: &BufferView, value: u32) -> Result<Self>
+    where
+        Self: Sized;
+    /// Write a small amount of data directly into a buffer view. Equivalent of `vkCmdUpdateBuffer`.
+    /// The data is copied into the command buffer, so this is only suitable for small updates of at most 65536 bytes.
+    fn update_buffer<T: Copy>(self, dst: &BufferView, data: &[T]) -> Result<Self>
+    where
+        Self: Sized;
+    /// Clear a color image to a fixed value. Equivalent of `vkCmdClearColorImage`.
+    fn clear_color_image(self, dst: &ImageView, value: vk::ClearColorValue) -> Result<Self>
+    where
+        Self: Sized;
+    /// Clear a depth-stencil image to a fixed value. Equivalent of `vkCmdClearDepthStencilImage`.
+    fn clear_depth_stencil_image(
+        self,
+        dst: &ImageView,
+        value: vk::ClearDepthStencilValue,
+    ) -> Result<Self>
+    where
+        Self: Sized;
 }
 
 /// Trait representing a command buffer that supports graphics commands.
@@ -55,10 +90,105 @@ pub trait GraphicsCmdBuffer: TransferCmdBuffer {
         vertex_offset: i32,
         first_instance: u32,
     ) -> Result<Self>
+    where
+        Self: Sized;
+    /// Record multiple non-indexed drawcalls with parameters sourced from a buffer. Equivalent of `vkCmdDrawIndirect`.
+    /// The buffer must contain `draw_count` [`DrawIndirectCommand`](crate::DrawIndirectCommand) structures,
+    /// each `stride` bytes apart.
+    fn draw_indirect(
+        self,
+        buffer: &BufferView,
+        offset: vk::DeviceSize,
+        draw_count: u32,
+        stride: u32,
+    ) -> Result<Self>
+    where
+        Self: Sized;
+    /// Record multiple indexed drawcalls with parameters sourced from a buffer. Equivalent of `vkCmdDrawIndexedIndirect`.
+    /// The buffer must contain `draw_count` [`DrawIndexedIndirectCommand`](crate::DrawIndexedIndirectCommand) structures,
+    /// each `stride` bytes apart.
+    fn draw_indexed_indirect(
+        self,
+        buffer: &BufferView,
+        offset: vk::DeviceSize,
+        draw_count: u32,
+        stride: u32,
+    ) -> Result<Self>
+    where
+        Self: Sized;
+    /// Record indirect drawcalls where the number of draws is read from a buffer. Equivalent of `vkCmdDrawIndirectCount`.
+    /// Requires the `drawIndirectCount` feature.
+    fn draw_indirect_count(
+        self,
+        buffer: &BufferView,
+        offset: vk::DeviceSize,
+        count_buffer: &BufferView,
+        count_offset: vk::DeviceSize,
+        max_draw_count: u32,
+        stride: u32,
+    ) -> Result<Self>
+    where
+        Self: Sized;
+    /// Record indexed indirect drawcalls where the number of draws is read from a buffer. Equivalent of `vkCmdDrawIndexedIndirectCount`.
+    /// Requires the `drawIndirectCount` feature.
+    fn draw_indexed_indirect_count(
+        self,
+        buffer: &BufferView,
+        offset: vk::DeviceSize,
+        count_buffer: &BufferView,
+        count_offset: vk::DeviceSize,
+        max_draw_count: u32,
+        stride: u32,
+    ) -> Result<Self>
+    where
+        Self: Sized;
+    /// Record a mesh shading drawcall. Equivalent of `vkCmdDrawMeshTasksEXT`.
+    fn draw_mesh_tasks(self, x: u32, y: u32, z: u32) -> Result<Self>
+    where
+        Self: Sized;
+    /// Record multiple mesh shading drawcalls with parameters sourced from a buffer. Equivalent of `vkCmdDrawMeshTasksIndirectEXT`.
+    fn draw_mesh_tasks_indirect(
+        self,
+        buffer: &BufferView,
+        offset: vk::DeviceSize,
+        draw_count: u32,
+        stride: u32,
+    ) -> Result<Self>
+    where
+        Self: Sized;
+    /// Record mesh shading drawcalls where the number of draws is read from a buffer. Equivalent of `vkCmdDrawMeshTasksIndirectCountEXT`.
+    fn draw_mesh_tasks_indirect_count(
+        self,
+        buffer: &BufferView,
+        offset: vk::DeviceSize,
+        count_buffer: &BufferView,
+        count_offset: vk::DeviceSize,
+        max_draw_count: u32,
+        stride: u32,
+    ) -> Result<Self>
     where
         Self: Sized;
     /// Start raytracing. Equivalent of `vkCmdTraceRays`.
     fn trace_rays(self, width: u32, height: u32, depth: u32) -> Result<Self>
+    where
+        Self: Sized;
+    /// Start raytracing using a custom shader binding table, given by its ray generation, miss, hit and callable regions.
+    /// Equivalent of `vkCmdTraceRays`.
+    fn trace_rays_with_regions(
+        self,
+        regions: &[vk::StridedDeviceAddressRegionKHR; 4],
+        width: u32,
+        height: u32,
+        depth: u32,
+    ) -> Result<Self>
+    where
+        Self: Sized;
+    /// Start raytracing with the dimensions read from a buffer. Equivalent of `vkCmdTraceRaysIndirectKHR`.
+    fn trace_rays_indirect(self, buffer: &BufferView, offset: vk::DeviceSize) -> Result<Self>
+    where
+        Self: Sized;
+    /// Start raytracing with the dimensions and the shader binding table read from a buffer. Equivalent of `vkCmdTraceRaysIndirect2KHR`.
+    fn trace_rays_indirect2(self, buffer: &BufferView, offset: vk::DeviceSize) -> Result<Self>
     where
         Self: Sized;
     /// Bind a graphics pipeline with a given name.
@@ -67,6 +197,13 @@ pub trait GraphicsCmdBuffer: TransferCmdBuffer {
     fn bind_graphics_pipeline(self, name: &str) -> Result<Self>
     where
         Self: Sized;
+    /// Check whether a graphics pipeline can be bound in the current render pass without compiling it first. If it cannot,
+    /// it starts compiling on a worker thread, and this returns `false` until it is done. Skipping draws with this avoids
+    /// stalls when a pipeline is first used.
+    /// # Errors
+    /// This function can report an error in case the pipeline name is not registered in the cache, or when called
+    /// outside of a render pass.
+    fn is_graphics_pipeline_ready(&self, name: &str) -> Result<bool>;
 
     /// Bind a ray tracing pipeline with a given name.
     fn bind_ray_tracing_pipeline(self, name: &str) -> Result<Self>
@@ -81,15 +218,26 @@ pub trait GraphicsCmdBuffer: TransferCmdBuffer {
     fn bind_index_buffer(self, buffer: &BufferView, ty: vk::IndexType) -> Self
     where
         Self: Sized;
-    /// Blit an image. Equivalent to `vkCmdBlitImage`
+    /// Copy a region of one image to a region of another image, applying scaling and format conversion.
+    /// Equivalent of `vkCmdBlitImage`.
     fn blit_image(
         self,
         src: &ImageView,
+        src_region: &ImageRegion,
         dst: &ImageView,
-        src_offsets: &[vk::Offset3D; 2],
-        dst_offsets: &[vk::Offset3D; 2],
+        dst_region: &ImageRegion,
         filter: vk::Filter,
-    ) -> Self
+    ) -> Result<Self>
+    where
+        Self: Sized;
+    /// Resolve a multisampled image into a single-sampled image. Equivalent of `vkCmdResolveImage`.
+    fn resolve_image(self, src: &ImageView, dst: &ImageView) -> Result<Self>
+    where
+        Self: Sized;
+
+    /// Set the line width for subsequent drawcalls. The bound pipeline must have been created with [`vk::DynamicState::LINE_WIDTH`].
+    /// Equivalent to `vkCmdSetLineWidth`
+    fn set_line_width(self, width: f32) -> Result<Self>
     where
         Self: Sized;
 
@@ -97,6 +245,69 @@ pub trait GraphicsCmdBuffer: TransferCmdBuffer {
     fn set_polygon_mode(self, mode: vk::PolygonMode) -> Result<Self>
     where
         Self: Sized;
+
+    /// Set the face culling mode. The bound pipeline must have been created with [`vk::DynamicState::CULL_MODE`].
+    /// Equivalent to `vkCmdSetCullMode`
+    fn set_cull_mode(self, cull: vk::CullModeFlags) -> Result<Self>
+    where
+        Self: Sized;
+
+    /// Set the front face orientation. The bound pipeline must have been created with [`vk::DynamicState::FRONT_FACE`].
+    /// Equivalent to `vkCmdSetFrontFace`
+    fn set_front_face(self, face: vk::FrontFace) -> Result<Self>
+    where
+        Self: Sized;
+
+    /// Set the primitive topology. The bound pipeline must have been created with [`vk::DynamicState::PRIMITIVE_TOPOLOGY`].
+    /// Equivalent to `vkCmdSetPrimitiveTopology`
+    fn set_primitive_topology(self, topology: vk::PrimitiveTopology) -> Result<Self>
+    where
+        Self: Sized;
+
+    /// Enable or disable depth testing. The bound pipeline must have been created with [`vk::DynamicState::DEPTH_TEST_ENABLE`].
+    /// Equivalent to `vkCmdSetDepthTestEnable`
+    fn set_depth_test_enable(self, enable: bool) -> Result<Self>
+    where
+        Self: Sized;
+
+    /// Enable or disable depth writes. The bound pipeline must have been created with [`vk::DynamicState::DEPTH_WRITE_ENABLE`].
+    /// Equivalent to `vkCmdSetDepthWriteEnable`
+    fn set_depth_write_enable(self, enable: bool) -> Result<Self>
+    where
+        Self: Sized;
+
+    /// Set the depth compare operation. The bound pipeline must have been created with [`vk::DynamicState::DEPTH_COMPARE_OP`].
+    /// Equivalent to `vkCmdSetDepthCompareOp`
+    fn set_depth_compare_op(self, op: vk::CompareOp) -> Result<Self>
+    where
+        Self: Sized;
+
+    /// Enable or disable blending for a range of color attachments. Only available if VK_EXT_extended_dynamic_state3 was enabled.
+    /// Equivalent to `vkCmdSetColorBlendEnableEXT`
+    fn set_color_blend_enable(self, first_attachment: u32, enable: &[bool]) -> Result<Self>
+    where
+        Self: Sized;
+
+    /// Set the fragment shading rate for subsequent drawcalls. Requires `VK_KHR_fragment_shading_rate` and the
+    /// `VK_DYNAMIC_STATE_FRAGMENT_SHADING_RATE_KHR` dynamic state. Equivalent to `vkCmdSetFragmentShadingRateKHR`
+    fn set_fragment_shading_rate(
+        self,
+        fragment_size: vk::Extent2D,
+        combiner_ops: [vk::FragmentShadingRateCombinerOpKHR; 2],
+    ) -> Result<Self>
+    where
+        Self: Sized;
+
+    /// Begin a conditional rendering block. Subsequent drawcalls and dispatches are discarded if the 32-bit value at the start of `buffer`
+    /// is zero (or non-zero if `inverted` is set). Equivalent to `vkCmdBeginConditionalRenderingEXT`
+    fn begin_conditional_rendering(self, buffer: &BufferView, inverted: bool) -> Result<Self>
+    where
+        Self: Sized;
+
+    /// End the current conditional rendering block. Equivalent to `vkCmdEndConditionalRenderingEXT`
+    fn end_conditional_rendering(self) -> Result<Self>
+    where
+        Self: Sized;
 }
 
 /// Trait representing a command buffer that supports compute commands.
@@ -113,6 +324,11 @@ pub trait ComputeCmdBuffer: TransferCmdBuffer {
     where
         Self: Sized;
 
+    /// Dispatch a compute invocation with the workgroup counts read from a buffer. See `vkCmdDispatchIndirect`
+    fn dispatch_indirect(self, buffer: &BufferView, offset: vk::DeviceSize) -> Result<Self>
+    where
+        Self: Sized;
+
     /// Build an acceleration structure
     fn build_acceleration_structure(self, info: &AccelerationStructureBuildInfo) -> Result<Self>
     where
@@ -132,7 +348,38 @@ pub trait ComputeCmdBuffer: TransferCmdBuffer {
     where
         Self: Sized;
 
+    /// Compact multiple acceleration structures. Each structure in `src` is compacted into the structure at the same index in `dst`.
+    fn compact_acceleration_structures(
+        self,
+        src: &[AccelerationStructure],
+        dst: &[AccelerationStructure],
+    ) -> Result<Self>
+    where
+        Self: Sized;
+
+    /// Serialize an acceleration structure into a buffer, so it can be stored and loaded again later.
+    fn serialize_acceleration_structure(self, src: &AccelerationStructure, dst: &BufferView) -> Result<Self>
+    where
+        Self: Sized;
+
+    /// Deserialize an acceleration structure from a buffer that was filled with
+    /// [`serialize_acceleration_structure()`](ComputeCmdBuffer::serialize_acceleration_structure).
+    fn deserialize_acceleration_structure(self, src: &BufferView, dst: &AccelerationStructure) -> Result<Self>
+    where
+        Self: Sized;
+
+    /// Build an opacity micromap
+    fn build_micromap(self, info: &MicromapBuildInfo) -> Result<Self>
+    where
+        Self: Sized;
+
+    /// Build multiple opacity micromaps in a single command
+    fn build_micromaps(self, info: &[MicromapBuildInfo]) -> Result<Self>
+    where
+        Self: Sized;
+
     /// Write acceleration structure properties of multiple acceleration structures in a single command.
+    /// The properties are written to consecutive queries in the query pool.
     fn write_acceleration_structures_properties<Q: AccelerationStructurePropertyQuery>(
         self,
         src: &[AccelerationStructure],
@@ -151,6 +398,36 @@ pub trait ComputeCmdBuffer: TransferCmdBuffer {
         Self: Sized;
 }
 
+/// Trait representing a command buffer that supports video decode commands. See the [`video`](crate::video) module.
+pub trait VideoDecodeCmdBuffer {
+    /// Begin a video coding scope for a session. All DPB slots used as references or set up as new references inside the
+    /// scope must be listed in `reference_slots`. See `vkCmdBeginVideoCodingKHR`
+    fn begin_video_coding<VA: Allocator>(
+        self,
+        session: &VideoSession<VA>,
+        parameters: Option<&VideoSessionParameters>,
+        reference_slots: &[VideoReferenceSlot],
+    ) -> Result<Self>
+    where
+        Self: Sized;
+
+    /// Reset the state of the bound video session. This must be done once before the first decode with a new session.
+    /// See `vkCmdControlVideoCodingKHR`
+    fn reset_video_coding(self) -> Result<Self>
+    where
+        Self: Sized;
+
+    /// Decode a single H.264 picture. See `vkCmdDecodeVideoKHR`
+    fn decode_h264(self, info: &H264DecodeInfo) -> Result<Self>
+    where
+        Self: Sized;
+
+    /// End the current video coding scope. See `vkCmdEndVideoCodingKHR`
+    fn end_video_coding(self) -> Result<Self>
+    where
+        Self: Sized;
+}
+
 /// Completed command buffer
 pub trait CmdBuffer<A: Allocator> {
     /// Delete the command buffer immediately.
@@ -192,6 +469,9 @@ pub trait TransferSupport {}
 /// also implies it supports transfer operations.
 pub trait ComputeSupport: TransferSupport {}
 
+/// Whether this domain supports video decode operations.
+pub trait DecodeSupport {}
+
 impl GfxSupport for domain::Graphics {}
 impl GfxSupport for domain::All {}
 impl TransferSupport for domain::Graphics {}
@@ -200,3 +480,4 @@ impl TransferSupport for domain::Compute {}
 impl TransferSupport for domain::All {}
 impl ComputeSupport for domain::Compute {}
 impl ComputeSupport for domain::All {}
+impl DecodeSupport for domain::VideoDecode {}
@@ -5,8 +5,123 @@ use ash::vk;
 
 use crate::command_buffer::IncompleteCommandBuffer;
 use crate::sync::domain::ExecutionDomain;
+use crate::util::compressed::compressed_block;
+use crate::util::format::{texel_region_size, texel_size};
 use crate::{Allocator, BufferView, Error, ImageView, TransferCmdBuffer, TransferSupport};
 
+/// A box-shaped region inside the base mip level of an [`ImageView`], used for blits and copies.
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ImageRegion {
+    /// Offset of the region in texels.
+    pub offset: vk::Offset3D,
+    /// Size of the region in texels.
+    pub extent: vk::Extent3D,
+}
+
+impl ImageRegion {
+    /// A region covering the entire base mip level of an image view.
+    pub fn full(view: &ImageView) -> Self {
+        Self {
+            offset: vk::Offset3D::default(),
+            extent: view.base_level_size(),
+        }
+    }
+
+    /// Validate that this region lies inside the base mip level of an image view.
+    /// # Errors
+    /// * Fails with [`Error::ImageRegionOutOfRange`] if any part of the region is outside of the image.
+    pub fn validate(&self, view: &ImageView) -> Result<()> {
+        let size = view.base_level_size();
+        let fits = |offset: i32, extent: u32, size: u32| {
+            offset >= 0 && (offset as u64) + (extent as u64) <= size as u64
+        };
+        if fits(self.offset.x, self.extent.width, size.width)
+            && fits(self.offset.y, self.extent.height, size.height)
+            && fits(self.offset.z, self.extent.depth, size.depth)
+        {
+            Ok(())
+        } else {
+            Err(Error::ImageRegionOutOfRange.into())
+        }
+    }
+
+    /// Get the two corners of this region, as used by [`VkImageBlit`](vk::ImageBlit).
+    pub(crate) fn corners(&self) -> [vk::Offset3D; 2] {
+        [
+            self.offset,
+            vk::Offset3D {
+                x: self.offset.x + self.extent.width as i32,
+                y: self.offset.y + self.extent.height as i32,
+                z: self.offset.z + self.extent.depth as i32,
+            },
+        ]
+    }
+}
+
+/// Get the subresource layers of the base mip level of an image view.
+pub(crate) fn subresource_layers(view: &ImageView) -> vk::ImageSubresourceLayers {
+    let range = view.subresource_range();
+    vk::ImageSubresourceLayers {
+        aspect_mask: range.aspect_mask,
+        mip_level: range.base_mip_level,
+        base_array_layer: range.base_array_layer,
+        layer_count: range.layer_count,
+    }
+}
+
+/// Size in bytes of the tightly packed buffer data for a region of an image view, taking block compression and the aspect of
+/// depth/stencil views into account. Returns `None` for formats with an unknown texel size, such as multi-planar formats.
+fn buffer_image_copy_size(view: &ImageView, extent: vk::Extent3D) -> Result<Option<u64>> {
+    let format = view.format();
+    let layers = view.layer_count();
+    if let Some(block) = compressed_block(format) {
+        return block.region_size(extent, layers).map(Some);
+    }
+    let aspect = view.aspect();
+    let texel = if aspect.contains(vk::ImageAspectFlags::DEPTH) {
+        match format {
+            vk::Format::D16_UNORM | vk::Format::D16_UNORM_S8_UINT => Some(2),
+            vk::Format::X8_D24_UNORM_PACK32
+            | vk::Format::D24_UNORM_S8_UINT
+            | vk::Format::D32_SFLOAT
+            | vk::Format::D32_SFLOAT_S8_UINT => Some(4),
+            _ => None,
+        }
+    } else if aspect.contains(vk::ImageAspectFlags::STENCIL) {
+        Some(1)
+    } else {
+        texel_size(format)
+    };
+    texel
+        .map(|texel| {
+            texel_region_size(extent, layers, texel)
+                .ok_or_else(|| anyhow::anyhow!("Image region with extent {extent:?} and {layers} layers is too large"))
+        })
+        .transpose()
+}
+
+/// Check that a buffer view holds the entire region of an image view it is copied to or from.
+/// # Errors
+/// * Fails with [`Error::InvalidBufferCopy`] if the region does not fit in the buffer view.
+fn validate_buffer_image_copy(image: &ImageView, extent: vk::Extent3D, buffer: &BufferView, to_image: bool) -> Result<()> {
+    let Some(size) = buffer_image_copy_size(image, extent)? else {
+        return Ok(());
+    };
+    if size > buffer.size() {
+        let (src_size, dst_size) = if to_image {
+            (buffer.size(), size)
+        } else {
+            (size, buffer.size())
+        };
+        return Err(Error::InvalidBufferCopy {
+            src_size,
+            dst_size,
+        }
+        .into());
+    }
+    Ok(())
+}
+
 impl<D: TransferSupport + ExecutionDomain, A: Allocator> TransferCmdBuffer
     for IncompleteCommandBuffer<'_, D, A>
 {
@@ -24,7 +139,11 @@ impl<D: TransferSupport + ExecutionDomain, A: Allocator> TransferCmdBuffer
     /// ```
     fn copy_buffer(self, src: &BufferView, dst: &BufferView) -> Result<Self> {
         if src.size() != dst.size() {
-            return Err(Error::InvalidBufferCopy.into());
+            return Err(Error::InvalidBufferCopy {
+                src_size: src.size(),
+                dst_size: dst.size(),
+            }
+            .into());
         }
 
         let copy = vk::BufferCopy {
@@ -46,6 +165,8 @@ impl<D: TransferSupport + ExecutionDomain, A: Allocator> TransferCmdBuffer
     }
 
     /// Copy a buffer to the base mip level of the specified image.
+    /// # Errors
+    /// * Fails with [`Error::InvalidBufferCopy`] if the buffer view is too small for the image.
     /// # Example
     /// ```
     /// # use anyhow::Result;
@@ -58,18 +179,27 @@ impl<D: TransferSupport + ExecutionDomain, A: Allocator> TransferCmdBuffer
     fn copy_buffer_to_image(self, src: &BufferView, dst: &ImageView) -> Result<Self>
     where
         Self: Sized, {
+        self.copy_buffer_to_image_region(src, dst, &ImageRegion::full(dst))
+    }
+
+    /// Copy a buffer to a region of the base mip level of the specified image. The image must be in
+    /// `VK_IMAGE_LAYOUT_TRANSFER_DST_OPTIMAL`, and texels must be tightly packed in the buffer.
+    /// This is useful for updating part of a texture, such as a glyph atlas.
+    /// # Errors
+    /// * Fails with [`Error::ImageRegionOutOfRange`] if the region is not inside the image.
+    /// * Fails with [`Error::InvalidBufferCopy`] if the buffer view is too small for the region.
+    fn copy_buffer_to_image_region(self, src: &BufferView, dst: &ImageView, region: &ImageRegion) -> Result<Self>
+    where
+        Self: Sized, {
+        region.validate(dst)?;
+        validate_buffer_image_copy(dst, region.extent, src, true)?;
         let copy = vk::BufferImageCopy {
             buffer_offset: src.offset(),
-            buffer_row_length: dst.width(),
-            buffer_image_height: dst.height(),
-            image_subresource: vk::ImageSubresourceLayers {
-                aspect_mask: dst.aspect(),
-                mip_level: dst.base_level(),
-                base_array_layer: dst.base_layer(),
-                layer_count: dst.layer_count(),
-            },
-            image_offset: Default::default(),
-            image_extent: dst.size(),
+            buffer_row_length: region.extent.width,
+            buffer_image_height: region.extent.height,
+            image_subresource: subresource_layers(dst),
+            image_offset: region.offset,
+            image_extent: region.extent,
         };
 
         unsafe {
@@ -84,4 +214,200 @@ impl<D: TransferSupport + ExecutionDomain, A: Allocator> TransferCmdBuffer
 
         Ok(self)
     }
+
+    /// Copy the base mip level of an image to a buffer. The image must be in `VK_IMAGE_LAYOUT_TRANSFER_SRC_OPTIMAL`.
+    /// Texels are tightly packed in the buffer. This is useful for taking screenshots or reading back render targets.
+    /// See also [`vkCmdCopyImageToBuffer`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdCopyImageToBuffer.html)
+    /// # Errors
+    /// * Fails with [`Error::InvalidBufferCopy`] if the buffer view is too small for the image.
+    /// # Example
+    /// ```
+    /// # use anyhow::Result;
+    /// # use phobos::*;
+    /// # use phobos::sync::domain::*;
+    /// fn screenshot<C: TransferCmdBuffer>(cmd: C, image: &ImageView, dst: &BufferView) -> Result<C> {
+    ///     cmd.copy_image_to_buffer(image, dst)
+    /// }
+    /// ```
+    fn copy_image_to_buffer(self, src: &ImageView, dst: &BufferView) -> Result<Self> {
+        let extent = src.base_level_size();
+        validate_buffer_image_copy(src, extent, dst, false)?;
+        let copy = vk::BufferImageCopy {
+            buffer_offset: dst.offset(),
+            buffer_row_length: extent.width,
+            buffer_image_height: extent.height,
+            image_subresource: subresource_layers(src),
+            image_offset: Default::default(),
+            image_extent: extent,
+        };
+
+        unsafe {
+            self.device.cmd_copy_image_to_buffer(
+                self.handle,
+                src.image(),
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                dst.handle(),
+                std::slice::from_ref(&copy),
+            );
+        }
+
+        Ok(self)
+    }
+
+    /// Copy a region of the base mip level of an image to a buffer. The image must be in `VK_IMAGE_LAYOUT_TRANSFER_SRC_OPTIMAL`.
+    /// Texels are tightly packed in the buffer. This is useful for reading back part of a render target, such as the pixels
+    /// around the cursor for GPU picking.
+    /// See also [`vkCmdCopyImageToBuffer`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdCopyImageToBuffer.html)
+    /// # Errors
+    /// * Fails with [`Error::ImageRegionOutOfRange`] if the region is not inside the image.
+    /// * Fails with [`Error::InvalidBufferCopy`] if the buffer view is too small for the region.
+    fn copy_image_region_to_buffer(self, src: &ImageView, region: &ImageRegion, dst: &BufferView) -> Result<Self> {
+        region.validate(src)?;
+        validate_buffer_image_copy(src, region.extent, dst, false)?;
+        let copy = vk::BufferImageCopy {
+            buffer_offset: dst.offset(),
+            buffer_row_length: region.extent.width,
+            buffer_image_height: region.extent.height,
+            image_subresource: subresource_layers(src),
+            image_offset: region.offset,
+            image_extent: region.extent,
+        };
+
+        unsafe {
+            self.device.cmd_copy_image_to_buffer(
+                self.handle,
+                src.image(),
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                dst.handle(),
+                std::slice::from_ref(&copy),
+            );
+        }
+
+        Ok(self)
+    }
+
+    /// Fill the entire range of a buffer view with a repeated 32-bit value.
+    /// See also [`vkCmdFillBuffer`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdFillBuffer.html)
+    /// # Errors
+    /// * Fails if the offset or size of the buffer view is not a multiple of 4.
+    /// # Example
+    /// ```
+    /// # use anyhow::Result;
+    /// # use phobos::*;
+    /// # use phobos::sync::domain::*;
+    /// fn zero_buffer<C: TransferCmdBuffer>(cmd: C, buffer: &BufferView) -> Result<C> {
+    ///     cmd.fill_buffer(buffer, 0)
+    /// }
+    /// ```
+    fn fill_buffer(self, dst: &BufferView, value: u32) -> Result<Self> {
+        if dst.offset() % 4 != 0 || dst.size() % 4 != 0 {
+            return Err(Error::InvalidBufferUpdate.into());
+        }
+
+        unsafe {
+            self.device
+                .cmd_fill_buffer(self.handle, dst.handle(), dst.offset(), dst.size(), value);
+        }
+
+        Ok(self)
+    }
+
+    /// Write data into a buffer view, starting at the beginning of the view. The data is stored inside the command buffer,
+    /// so this avoids the need for a staging buffer for small updates.
+    /// See also [`vkCmdUpdateBuffer`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdUpdateBuffer.html)
+    /// # Errors
+    /// * Fails if the size of the data is larger than the buffer view.
+    /// * Fails if the offset of the buffer view or the size of the data is not a multiple of 4.
+    /// * Fails if the size of the data is larger than 65536 bytes.
+    /// # Example
+    /// ```
+    /// # use anyhow::Result;
+    /// # use phobos::*;
+    /// # use phobos::sync::domain::*;
+    /// fn write_indices<C: TransferCmdBuffer>(cmd: C, buffer: &BufferView) -> Result<C> {
+    ///     cmd.update_buffer(buffer, &[0u32, 1, 2, 2, 3, 0])
+    /// }
+    /// ```
+    fn update_buffer<T: Copy>(self, dst: &BufferView, data: &[T]) -> Result<Self> {
+        let size = std::mem::size_of_val(data) as vk::DeviceSize;
+        if size > dst.size() {
+            return Err(Error::BufferViewOutOfRange {
+                offset: 0,
+                size,
+                buffer_size: dst.size(),
+            }
+            .into());
+        }
+        if dst.offset() % 4 != 0 || size % 4 != 0 || size > 65536 {
+            return Err(Error::InvalidBufferUpdate.into());
+        }
+
+        // SAFETY: T is Copy, so reinterpreting it as raw bytes is fine. The slice covers exactly the memory of data.
+        let bytes = unsafe {
+            std::slice::from_raw_parts(data.as_ptr() as *const u8, size as usize)
+        };
+
+        unsafe {
+            self.device
+                .cmd_update_buffer(self.handle, dst.handle(), dst.offset(), bytes);
+        }
+
+        Ok(self)
+    }
+
+    /// Clear all subresources of a color image view. The image must be in `VK_IMAGE_LAYOUT_TRANSFER_DST_OPTIMAL`.
+    /// See also [`vkCmdClearColorImage`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdClearColorImage.html)
+    /// # Example
+    /// ```
+    /// # use anyhow::Result;
+    /// # use phobos::*;
+    /// # use phobos::sync::domain::*;
+    /// fn clear_to_black<C: TransferCmdBuffer>(cmd: C, image: &ImageView) -> Result<C> {
+    ///     cmd.clear_color_image(image, vk::ClearColorValue { float32: [0.0, 0.0, 0.0, 1.0] })
+    /// }
+    /// ```
+    fn clear_color_image(self, dst: &ImageView, value: vk::ClearColorValue) -> Result<Self> {
+        let range = dst.subresource_range();
+        unsafe {
+            self.device.cmd_clear_color_image(
+                self.handle,
+                dst.image(),
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &value,
+                std::slice::from_ref(&range),
+            );
+        }
+
+        Ok(self)
+    }
+
+    /// Clear all subresources of a depth-stencil image view. The image must be in `VK_IMAGE_LAYOUT_TRANSFER_DST_OPTIMAL`.
+    /// See also [`vkCmdClearDepthStencilImage`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdClearDepthStencilImage.html)
+    /// # Example
+    /// ```
+    /// # use anyhow::Result;
+    /// # use phobos::*;
+    /// # use phobos::sync::domain::*;
+    /// fn clear_depth<C: TransferCmdBuffer>(cmd: C, image: &ImageView) -> Result<C> {
+    ///     cmd.clear_depth_stencil_image(image, vk::ClearDepthStencilValue { depth: 1.0, stencil: 0 })
+    /// }
+    /// ```
+    fn clear_depth_stencil_image(
+        self,
+        dst: &ImageView,
+        value: vk::ClearDepthStencilValue,
+    ) -> Result<Self> {
+        let range = dst.subresource_range();
+        unsafe {
+            self.device.cmd_clear_depth_stencil_image(
+                self.handle,
+                dst.image(),
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &value,
+                std::slice::from_ref(&range),
+            );
+        }
+
+        Ok(self)
+    }
 }
@@ -0,0 +1,180 @@
+//! Contains implementations of the video decode domain for command buffers
+
+use std::ffi::c_void;
+
+use anyhow::Result;
+use ash::vk;
+
+use crate::{Allocator, DecodeSupport, VideoDecodeCmdBuffer};
+use crate::command_buffer::IncompleteCommandBuffer;
+use crate::core::device::ExtensionID;
+use crate::sync::domain::ExecutionDomain;
+use crate::video::{H264DecodeInfo, VideoReferenceSlot, VideoSession, VideoSessionParameters};
+
+impl<D: DecodeSupport + ExecutionDomain, A: Allocator> VideoDecodeCmdBuffer for IncompleteCommandBuffer<'_, D, A> {
+    /// Begin a video coding scope. All video decode commands must be recorded inside a video coding scope, which binds a
+    /// video session, its parameters and the DPB slots that can be used inside the scope.
+    ///
+    /// See also: [`vkCmdBeginVideoCodingKHR`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdBeginVideoCodingKHR.html)
+    /// # Errors
+    /// * Fails if [`ExtensionID::VideoQueue`] is not enabled.
+    fn begin_video_coding<VA: Allocator>(
+        self,
+        session: &VideoSession<VA>,
+        parameters: Option<&VideoSessionParameters>,
+        reference_slots: &[VideoReferenceSlot],
+    ) -> Result<Self>
+    where
+        Self: Sized, {
+        self.device.require_extension(ExtensionID::VideoQueue)?;
+        // Build the picture resources first, so the pointers to them stay valid.
+        let pictures = reference_slots
+            .iter()
+            .map(|slot| slot.picture.to_vk())
+            .collect::<Vec<_>>();
+        let slots = reference_slots
+            .iter()
+            .zip(pictures.iter())
+            .map(|(slot, picture)| vk::VideoReferenceSlotInfoKHR {
+                slot_index: slot.slot_index,
+                p_picture_resource: picture,
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        let info = vk::VideoBeginCodingInfoKHR {
+            video_session: unsafe { session.handle() },
+            video_session_parameters: parameters
+                .map(|parameters| unsafe { parameters.handle() })
+                .unwrap_or_default(),
+            reference_slot_count: slots.len() as u32,
+            p_reference_slots: slots.as_ptr(),
+            ..Default::default()
+        };
+        unsafe {
+            let fns = self.device.video_queue().unwrap();
+            (fns.cmd_begin_video_coding_khr)(self.handle, &info);
+        }
+        Ok(self)
+    }
+
+    /// Reset the state of the bound video session, including the state of all DPB slots. This must be done once before
+    /// the first decode with a new session, and is only valid inside a video coding scope.
+    ///
+    /// See also: [`vkCmdControlVideoCodingKHR`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdControlVideoCodingKHR.html)
+    /// # Errors
+    /// * Fails if [`ExtensionID::VideoQueue`] is not enabled.
+    fn reset_video_coding(self) -> Result<Self>
+    where
+        Self: Sized, {
+        self.device.require_extension(ExtensionID::VideoQueue)?;
+        let info = vk::VideoCodingControlInfoKHR {
+            flags: vk::VideoCodingControlFlagsKHR::RESET,
+            ..Default::default()
+        };
+        unsafe {
+            let fns = self.device.video_queue().unwrap();
+            (fns.cmd_control_video_coding_khr)(self.handle, &info);
+        }
+        Ok(self)
+    }
+
+    /// Decode a single H.264 picture into `info.output`. This reads from the bitstream and reference pictures, and writes
+    /// to the output picture and the setup reference slot, which must be synchronized with [`PipelineStage::VIDEO_DECODE_KHR`](crate::PipelineStage::VIDEO_DECODE_KHR).
+    /// The output must be in [`vk::ImageLayout::VIDEO_DECODE_DST_KHR`], and all reference pictures in
+    /// [`vk::ImageLayout::VIDEO_DECODE_DPB_KHR`].
+    ///
+    /// See also: [`vkCmdDecodeVideoKHR`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdDecodeVideoKHR.html)
+    /// # Errors
+    /// * Fails if [`ExtensionID::VideoDecodeH264`] is not enabled.
+    fn decode_h264(self, info: &H264DecodeInfo) -> Result<Self>
+    where
+        Self: Sized, {
+        self.device.require_extension(ExtensionID::VideoDecodeH264)?;
+        let setup_picture = info.setup_reference.as_ref().map(|setup| setup.picture.to_vk());
+        let setup_dpb_info = info
+            .setup_reference
+            .as_ref()
+            .map(|setup| vk::VideoDecodeH264DpbSlotInfoKHR {
+                p_std_reference_info: &setup.std_reference_info,
+                ..Default::default()
+            });
+        let setup_slot = info
+            .setup_reference
+            .as_ref()
+            .zip(setup_picture.as_ref())
+            .zip(setup_dpb_info.as_ref())
+            .map(|((setup, picture), dpb_info)| vk::VideoReferenceSlotInfoKHR {
+                p_next: dpb_info as *const _ as *const c_void,
+                slot_index: setup.slot_index,
+                p_picture_resource: picture,
+                ..Default::default()
+            });
+
+        let pictures = info
+            .references
+            .iter()
+            .map(|reference| reference.picture.to_vk())
+            .collect::<Vec<_>>();
+        let dpb_infos = info
+            .references
+            .iter()
+            .map(|reference| vk::VideoDecodeH264DpbSlotInfoKHR {
+                p_std_reference_info: &reference.std_reference_info,
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        let slots = info
+            .references
+            .iter()
+            .zip(pictures.iter().zip(dpb_infos.iter()))
+            .map(|(reference, (picture, dpb_info))| vk::VideoReferenceSlotInfoKHR {
+                p_next: dpb_info as *const _ as *const c_void,
+                slot_index: reference.slot_index,
+                p_picture_resource: picture,
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+
+        let picture_info = vk::VideoDecodeH264PictureInfoKHR {
+            p_std_picture_info: &info.std_picture_info,
+            slice_count: info.slice_offsets.len() as u32,
+            p_slice_offsets: info.slice_offsets.as_ptr(),
+            ..Default::default()
+        };
+        let decode_info = vk::VideoDecodeInfoKHR {
+            p_next: &picture_info as *const _ as *const c_void,
+            src_buffer: unsafe { info.bitstream.handle() },
+            src_buffer_offset: info.bitstream.offset(),
+            src_buffer_range: info.bitstream.size(),
+            dst_picture_resource: info.output.to_vk(),
+            p_setup_reference_slot: setup_slot
+                .as_ref()
+                .map_or(std::ptr::null(), |slot| slot as *const _),
+            reference_slot_count: slots.len() as u32,
+            p_reference_slots: slots.as_ptr(),
+            ..Default::default()
+        };
+        unsafe {
+            let fns = self.device.video_decode_queue().unwrap();
+            (fns.cmd_decode_video_khr)(self.handle, &decode_info);
+        }
+        Ok(self)
+    }
+
+    /// End the current video coding scope.
+    ///
+    /// See also: [`vkCmdEndVideoCodingKHR`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdEndVideoCodingKHR.html)
+    /// # Errors
+    /// * Fails if [`ExtensionID::VideoQueue`] is not enabled.
+    fn end_video_coding(self) -> Result<Self>
+    where
+        Self: Sized, {
+        self.device.require_extension(ExtensionID::VideoQueue)?;
+        let info = vk::VideoEndCodingInfoKHR::default();
+        unsafe {
+            let fns = self.device.video_queue().unwrap();
+            (fns.cmd_end_video_coding_khr)(self.handle, &info);
+        }
+        Ok(self)
+    }
+}
@@ -1,5 +1,7 @@
 //! Exposes all structs needed to store initialization parameters.
 
+use std::path::PathBuf;
+
 use ash::vk;
 #[cfg(feature = "fsr2")]
 use fsr2_sys::FfxFsr2InitializationFlagBits;
@@ -87,6 +89,10 @@ pub struct GPURequirements {
     pub features_1_3: vk::PhysicalDeviceVulkan13Features,
     /// Vulkan device extensions that should be present and enabled.
     pub device_extensions: Vec<String>,
+    /// Require `VK_KHR_ray_query`, so shaders in any stage can trace rays against an acceleration structure.
+    /// Physical devices without support for ray queries and acceleration structures are rejected. This also enables
+    /// `VK_KHR_acceleration_structure`, even if [`AppBuilder::raytracing()`](crate::AppBuilder::raytracing) is not set.
+    pub ray_query: bool,
 }
 
 /// Holds context settings for the FSR2 library
@@ -114,8 +120,24 @@ impl Default for Fsr2Settings {
     }
 }
 
+/// Device simulation profile loaded through the `VK_LAYER_KHRONOS_profiles` layer. This layer is part of the Vulkan SDK and
+/// clamps the reported device properties, features and formats to those in the profile, which makes it possible to test
+/// min-spec behavior on a more capable development machine.
+#[derive(Debug, Clone, Default)]
+pub struct DeviceProfile {
+    /// Path to the JSON profile file, for example one of the profiles shipped with the Vulkan SDK.
+    pub file: PathBuf,
+    /// Name of the profile to simulate inside the profile file. If this is `None`, the layer picks the first profile in the file.
+    pub name: Option<String>,
+}
+
+/// Callback that ranks the surface formats a surface supports. It receives all supported formats and returns the formats
+/// the application accepts, best first. The first returned format that is supported is used for the swapchain.
+pub type SurfaceFormatSelector = Box<dyn Fn(&[vk::SurfaceFormatKHR]) -> Vec<vk::SurfaceFormatKHR> + Send>;
+
 /// Application settings used to initialize the phobos context.
-#[derive(Debug)]
+#[derive(Derivative)]
+#[derivative(Debug)]
 pub struct AppSettings<'a, Window: WindowInterface> {
     /// Application name. Possibly displayed in debugging tools, task manager, etc.
     pub name: String,
@@ -129,10 +151,20 @@ pub struct AppSettings<'a, Window: WindowInterface> {
     /// Optionally a preferred surface format. This is ignored for a headless context. If set to None, a fallback surface format will be chosen.
     /// This format is `{BGRA8_SRGB, NONLINEAR_SRGB}` if it is available. Otherwise, the format is implementation-defined.
     pub surface_format: Option<vk::SurfaceFormatKHR>,
+    /// Optionally a callback to rank the supported surface formats. This is ignored for a headless context. If a format
+    /// returned by the callback is supported, it takes priority over [`AppSettings::surface_format`] and the fallback format.
+    #[derivative(Debug = "ignore")]
+    pub surface_format_selector: Option<SurfaceFormatSelector>,
     /// Optionally a preferred present mode. This is ignored for a headless context. If set to None, this will fall back to
     /// [`VK_PRESENT_MODE_FIFO_KHR`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/VkPresentModeKHR.html),
     /// as this is guaranteed to always be supported.
     pub present_mode: Option<vk::PresentModeKHR>,
+    /// Optionally the minimum number of swapchain images to request. This is ignored for a headless context, and clamped to
+    /// the limits of the surface. If set to None, one more than the minimum the surface supports is requested.
+    pub min_image_count: Option<u32>,
+    /// Optionally a preferred composite alpha mode. This is ignored for a headless context. If set to None, or if the mode
+    /// is not supported, this falls back to [`vk::CompositeAlphaFlagsKHR::OPAQUE`], or any supported mode if that is unavailable.
+    pub composite_alpha: Option<vk::CompositeAlphaFlagsKHR>,
     /// Minimum requirements the selected physical device should have.
     pub gpu_requirements: GPURequirements,
     /// Minimum size of scratch allocator chunks. This is the minimum size of [`ScratchAllocator`](crate::ScratchAllocator) chunks
@@ -140,6 +172,44 @@ pub struct AppSettings<'a, Window: WindowInterface> {
     pub scratch_chunk_size: u64,
     /// Whether to enable raytracing extensions.
     pub raytracing: bool,
+    /// Whether to enable mesh shading extensions.
+    pub mesh_shading: bool,
+    /// Whether to enable variable rate shading through `VK_KHR_fragment_shading_rate`.
+    pub fragment_shading_rate: bool,
+    /// Whether to enable memory residency priorities through `VK_EXT_memory_priority` and `VK_EXT_pageable_device_local_memory`.
+    pub memory_priority: bool,
+    /// Whether to enable querying per-heap memory usage and budgets through `VK_EXT_memory_budget`.
+    pub memory_budget: bool,
+    /// Whether to enable custom sampler border colors through `VK_EXT_custom_border_color`.
+    pub custom_border_color: bool,
+    /// Whether to create graphics pipelines from separately compiled parts through `VK_EXT_graphics_pipeline_library`.
+    pub graphics_pipeline_library: bool,
+    /// Whether to enable conservative rasterization through `VK_EXT_conservative_rasterization`.
+    pub conservative_rasterization: bool,
+    /// Whether to enable an OpenGL style `[-1, 1]` clip space depth range through `VK_EXT_depth_clip_control`.
+    pub depth_clip_control: bool,
+    /// Whether to enable binding shader objects instead of pipelines through `VK_EXT_shader_object`.
+    pub shader_object: bool,
+    /// Whether to enable opacity micromaps through `VK_EXT_opacity_micromap`. Only has an effect if acceleration structures
+    /// are enabled.
+    pub opacity_micromap: bool,
+    /// Whether to enable hardware performance counter queries through `VK_KHR_performance_query`.
+    pub performance_query: bool,
+    /// Whether to enable correlating GPU timestamps with host clocks through `VK_EXT_calibrated_timestamps`.
+    pub calibrated_timestamps: bool,
+    /// Whether to enable extra GPU crash diagnostics through `VK_NV_device_diagnostics_config`, used by Nsight Aftermath.
+    pub device_diagnostics: bool,
+    /// Whether to enable video decoding through `VK_KHR_video_queue`, `VK_KHR_video_decode_queue` and `VK_KHR_video_decode_h264`.
+    pub video_decode: bool,
+    /// Whether to enable present timing through `VK_KHR_present_id`, `VK_KHR_present_wait` and `VK_GOOGLE_display_timing`.
+    pub present_timing: bool,
+    /// Whether to enable copying between host memory and images without staging buffers through `VK_EXT_host_image_copy`.
+    pub host_image_copy: bool,
+    /// Exclusive fullscreen behaviour to request at swapchain creation through `VK_EXT_full_screen_exclusive`. If `None`, the
+    /// extension is not enabled.
+    pub full_screen_exclusive: Option<vk::FullScreenExclusiveEXT>,
+    /// Optionally a device profile to simulate through `VK_LAYER_KHRONOS_profiles`. Requires the layer to be installed.
+    pub device_profile: Option<DeviceProfile>,
     /// FSR2 context settings.
     #[cfg(feature = "fsr2")]
     pub fsr2_settings: Fsr2Settings,
@@ -156,10 +226,31 @@ impl<'a, Window: WindowInterface> Default for AppSettings<'a, Window> {
             enable_validation: false,
             window: None,
             surface_format: None,
+            surface_format_selector: None,
             present_mode: None,
+            min_image_count: None,
+            composite_alpha: None,
             gpu_requirements: GPURequirements::default(),
             scratch_chunk_size: 32768,
             raytracing: false,
+            mesh_shading: false,
+            fragment_shading_rate: false,
+            memory_priority: false,
+            memory_budget: false,
+            custom_border_color: false,
+            graphics_pipeline_library: false,
+            conservative_rasterization: false,
+            depth_clip_control: false,
+            shader_object: false,
+            opacity_micromap: false,
+            performance_query: false,
+            calibrated_timestamps: false,
+            device_diagnostics: false,
+            video_decode: false,
+            present_timing: false,
+            host_image_copy: false,
+            full_screen_exclusive: None,
+            device_profile: None,
             #[cfg(feature = "fsr2")]
             fsr2_settings: Fsr2Settings::default(),
         }
@@ -233,12 +324,39 @@ impl<'a, Window: WindowInterface> AppBuilder<'a, Window> {
         self
     }
 
+    /// Rank the supported surface formats with a callback (if using a window context). See [`SurfaceFormatSelector`].
+    pub fn surface_format_selector(
+        mut self,
+        selector: impl Fn(&[vk::SurfaceFormatKHR]) -> Vec<vk::SurfaceFormatKHR> + Send + 'static,
+    ) -> Self {
+        self.inner.surface_format_selector = Some(Box::new(selector));
+        self
+    }
+
+    /// A list of acceptable surface formats, best first (if using a window context). For example, this can be used to require
+    /// a non-sRGB swapchain by listing only `UNORM` formats.
+    pub fn surface_formats(self, formats: Vec<vk::SurfaceFormatKHR>) -> Self {
+        self.surface_format_selector(move |_| formats.clone())
+    }
+
     /// The present mode to use (if using a window context).
     pub fn present_mode(mut self, mode: vk::PresentModeKHR) -> Self {
         self.inner.present_mode = Some(mode);
         self
     }
 
+    /// The minimum number of swapchain images to request (if using a window context).
+    pub fn min_image_count(mut self, count: u32) -> Self {
+        self.inner.min_image_count = Some(count);
+        self
+    }
+
+    /// The composite alpha mode to use (if using a window context).
+    pub fn composite_alpha(mut self, mode: vk::CompositeAlphaFlagsKHR) -> Self {
+        self.inner.composite_alpha = Some(mode);
+        self
+    }
+
     /// The gpu requirements that the physical device must satisfy.
     pub fn gpu(mut self, gpu: GPURequirements) -> Self {
         self.inner.gpu_requirements = gpu;
@@ -262,6 +380,142 @@ impl<'a, Window: WindowInterface> AppBuilder<'a, Window> {
         self
     }
 
+    /// Enable mesh and task shaders. Will try to enable `VK_EXT_mesh_shader` if it is available and mesh shaders are supported.
+    /// Task shaders are only enabled if the device supports them, check [`Device::mesh_shader_features()`](crate::Device::mesh_shader_features).
+    pub fn mesh_shading(mut self, enabled: bool) -> Self {
+        self.inner.mesh_shading = enabled;
+        self
+    }
+
+    /// Enable variable rate shading. Will try to enable `VK_KHR_fragment_shading_rate` if it is available.
+    pub fn fragment_shading_rate(mut self, enabled: bool) -> Self {
+        self.inner.fragment_shading_rate = enabled;
+        self
+    }
+
+    /// Enable memory residency priorities. Will try to enable `VK_EXT_memory_priority` and `VK_EXT_pageable_device_local_memory`
+    /// if they are available.
+    pub fn memory_priority(mut self, enabled: bool) -> Self {
+        self.inner.memory_priority = enabled;
+        self
+    }
+
+    /// Enable memory budget queries. Will try to enable `VK_EXT_memory_budget` if it is available.
+    /// See [`Device::memory_budget()`](crate::Device::memory_budget).
+    pub fn memory_budget(mut self, enabled: bool) -> Self {
+        self.inner.memory_budget = enabled;
+        self
+    }
+
+    /// Enable custom sampler border colors. Will try to enable `VK_EXT_custom_border_color` if it is available.
+    pub fn custom_border_color(mut self, enabled: bool) -> Self {
+        self.inner.custom_border_color = enabled;
+        self
+    }
+
+    /// Enable graphics pipeline libraries. Will try to enable `VK_EXT_graphics_pipeline_library` if it is available. When enabled,
+    /// the [`PipelineCache`](crate::PipelineCache) compiles graphics pipelines in separate parts that are shared between pipelines,
+    /// and links them when a pipeline is first used.
+    pub fn graphics_pipeline_library(mut self, enabled: bool) -> Self {
+        self.inner.graphics_pipeline_library = enabled;
+        self
+    }
+
+    /// Enable conservative rasterization. Will try to enable `VK_EXT_conservative_rasterization` if it is available.
+    /// See [`PipelineBuilder::conservative_rasterization()`](crate::PipelineBuilder::conservative_rasterization).
+    pub fn conservative_rasterization(mut self, enabled: bool) -> Self {
+        self.inner.conservative_rasterization = enabled;
+        self
+    }
+
+    /// Enable depth clip control. Will try to enable `VK_EXT_depth_clip_control` if it is available.
+    /// See [`PipelineBuilder::depth_clip_negative_one_to_one()`](crate::PipelineBuilder::depth_clip_negative_one_to_one).
+    pub fn depth_clip_control(mut self, enabled: bool) -> Self {
+        self.inner.depth_clip_control = enabled;
+        self
+    }
+
+    /// Enable shader objects. Will try to enable `VK_EXT_shader_object` if it is available.
+    /// See [`PipelineBuilder::shader_objects()`](crate::PipelineBuilder::shader_objects).
+    pub fn shader_object(mut self, enabled: bool) -> Self {
+        self.inner.shader_object = enabled;
+        self
+    }
+
+    /// Enable opacity micromaps. Will try to enable `VK_EXT_opacity_micromap` if it is available and raytracing is enabled.
+    /// See [`Micromap`](crate::Micromap).
+    pub fn opacity_micromap(mut self, enabled: bool) -> Self {
+        self.inner.opacity_micromap = enabled;
+        self
+    }
+
+    /// Enable performance counter queries. Will try to enable `VK_KHR_performance_query` if it is available.
+    /// See [`PerformanceQuery`](crate::query_pool::PerformanceQuery).
+    pub fn performance_query(mut self, enabled: bool) -> Self {
+        self.inner.performance_query = enabled;
+        self
+    }
+
+    /// Enable calibrated timestamps. Will try to enable `VK_EXT_calibrated_timestamps` if it is available.
+    /// See [`TimestampCalibration`](crate::query_pool::TimestampCalibration).
+    pub fn calibrated_timestamps(mut self, enabled: bool) -> Self {
+        self.inner.calibrated_timestamps = enabled;
+        self
+    }
+
+    /// Enable GPU crash diagnostics. Will try to enable `VK_NV_device_diagnostics_config` if it is available, so GPU crash dumps
+    /// contain shader debug information, resource tracking and automatic checkpoints. This has a runtime cost, so only enable it
+    /// when collecting crash dumps. See [`GpuCrashTracker`](crate::aftermath::GpuCrashTracker).
+    pub fn device_diagnostics(mut self, enabled: bool) -> Self {
+        self.inner.device_diagnostics = enabled;
+        self
+    }
+
+    /// Enable video decoding. Will try to enable `VK_KHR_video_queue`, `VK_KHR_video_decode_queue` and `VK_KHR_video_decode_h264`
+    /// if they are available. Decode commands can only be submitted to a queue requested with
+    /// [`QueueType::VideoDecode`](crate::QueueType::VideoDecode). See the [`video`](crate::video) module.
+    pub fn video_decode(mut self, enabled: bool) -> Self {
+        self.inner.video_decode = enabled;
+        self
+    }
+
+    /// Enable present timing. Will try to enable `VK_KHR_present_id`, `VK_KHR_present_wait` and `VK_GOOGLE_display_timing`
+    /// if they are available. These are used by [`FramePacer`](crate::FramePacer) to wait for presentation and measure
+    /// present latency.
+    pub fn present_timing(mut self, enabled: bool) -> Self {
+        self.inner.present_timing = enabled;
+        self
+    }
+
+    /// Enable host image copies. Will try to enable `VK_EXT_host_image_copy` if it is available.
+    /// See [`Image::upload_host()`](crate::Image::upload_host) and [`Image::download_host()`](crate::Image::download_host).
+    pub fn host_image_copy(mut self, enabled: bool) -> Self {
+        self.inner.host_image_copy = enabled;
+        self
+    }
+
+    /// Request exclusive fullscreen behaviour for swapchains. Will try to enable `VK_EXT_full_screen_exclusive` and
+    /// `VK_KHR_get_surface_capabilities2` if they are available, which is generally only the case on Windows. With
+    /// [`vk::FullScreenExclusiveEXT::APPLICATION_CONTROLLED`], exclusive mode is entered and left with
+    /// [`FrameManager::acquire_full_screen_exclusive()`](crate::FrameManager::acquire_full_screen_exclusive) and
+    /// [`FrameManager::release_full_screen_exclusive()`](crate::FrameManager::release_full_screen_exclusive).
+    pub fn full_screen_exclusive(mut self, mode: vk::FullScreenExclusiveEXT) -> Self {
+        self.inner.full_screen_exclusive = Some(mode);
+        self
+    }
+
+    /// Simulate a device profile through `VK_LAYER_KHRONOS_profiles`. `name` selects a profile inside the file, if it contains multiple.
+    /// Note that instance creation will fail if the layer is not installed. The profile is passed to the layer with
+    /// `VK_EXT_layer_settings`. Versions of the layer without support for it ignore these settings, with those the profile must be
+    /// selected through the `VK_KHRONOS_PROFILES_PROFILE_FILE` and `VK_KHRONOS_PROFILES_PROFILE_NAME` environment variables instead.
+    pub fn device_profile(mut self, file: impl Into<PathBuf>, name: Option<&str>) -> Self {
+        self.inner.device_profile = Some(DeviceProfile {
+            file: file.into(),
+            name: name.map(|name| name.to_owned()),
+        });
+        self
+    }
+
     /// Set the initial FSR2 display size
     #[cfg(feature = "fsr2")]
     pub fn fsr2_display_size(mut self, width: u32, height: u32) -> Self {
@@ -0,0 +1,162 @@
+//! Programmatic frame captures through the RenderDoc in-application API. This requires the `renderdoc` feature.
+//!
+//! A [`Capture`] can only be created when the application was launched from RenderDoc (or RenderDoc was injected into it),
+//! so it is safe to create one unconditionally and ignore the error in regular runs. It can be used to:
+//! - Capture the next frame(s) presented, like the capture hotkey does, with [`Capture::trigger_capture()`].
+//! - Capture exactly the commands submitted between two points with [`Capture::start_frame_capture()`] and
+//!   [`Capture::end_frame_capture()`], or with a [`CaptureScope`] that ends the capture when dropped.
+//!   This also works in headless applications, which never present.
+//! # Example
+//! ```
+//! # use anyhow::Result;
+//! # use phobos::prelude::*;
+//! fn capture_frame(instance: &Instance, render: impl FnOnce() -> Result<()>) -> Result<()> {
+//!     // Not launched from RenderDoc, nothing to capture
+//!     let Ok(mut capture) = Capture::new(instance) else {
+//!         return render();
+//!     };
+//!     let scope = capture.scope();
+//!     render()?;
+//!     scope.end();
+//!     Ok(())
+//! }
+//! ```
+
+use std::ffi::c_void;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::Result;
+use ash::vk;
+use ash::vk::Handle;
+use renderdoc::{RenderDoc, V141};
+
+pub use renderdoc::InputButton;
+
+use crate::Instance;
+
+/// Handle to the RenderDoc in-application API. See the [module-level documentation](self) for more information.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct Capture {
+    #[derivative(Debug = "ignore")]
+    renderdoc: RenderDoc<V141>,
+    instance: vk::Instance,
+}
+
+impl Capture {
+    /// Connect to the RenderDoc in-application API. All captures made through this object are restricted to `instance`.
+    /// # Errors
+    /// * Fails if the application was not launched through RenderDoc.
+    pub fn new(instance: &Instance) -> Result<Self> {
+        Ok(Self {
+            renderdoc: RenderDoc::new()?,
+            instance: instance.handle(),
+        })
+    }
+
+    /// RenderDoc identifies Vulkan devices by the dispatch table pointer of the instance, which is stored at the start of
+    /// every dispatchable handle (`RENDERDOC_DEVICEPOINTER_FROM_VKINSTANCE`).
+    fn device_pointer(&self) -> *const c_void {
+        // SAFETY: The instance handle is a valid dispatchable handle, so it points to the loader's dispatch table pointer.
+        unsafe { *(self.instance.as_raw() as *const *const c_void) }
+    }
+
+    /// Capture the next frame presented to any window, as if the capture hotkey was pressed.
+    pub fn trigger_capture(&mut self) {
+        self.renderdoc.trigger_capture();
+    }
+
+    /// Capture the next `frames` frames presented to any window.
+    pub fn trigger_multi_frame_capture(&mut self, frames: u32) {
+        self.renderdoc.trigger_multi_frame_capture(frames);
+    }
+
+    /// Start capturing all commands submitted on this instance, until [`Capture::end_frame_capture()`] is called. Captures are
+    /// not tied to presentation, so the captured range can span less or more than a single frame.
+    pub fn start_frame_capture(&mut self) {
+        let device = self.device_pointer();
+        self.renderdoc.start_frame_capture(device, std::ptr::null());
+    }
+
+    /// End a capture started with [`Capture::start_frame_capture()`], and save it to disk.
+    pub fn end_frame_capture(&mut self) {
+        let device = self.device_pointer();
+        self.renderdoc.end_frame_capture(device, std::ptr::null());
+    }
+
+    /// End a capture started with [`Capture::start_frame_capture()`], without saving it to disk.
+    /// Returns `false` if there was no capture to discard.
+    pub fn discard_frame_capture(&mut self) -> bool {
+        let device = self.device_pointer();
+        self.renderdoc.discard_frame_capture(device, std::ptr::null())
+    }
+
+    /// Whether a frame capture is currently in progress.
+    pub fn is_frame_capturing(&self) -> bool {
+        self.renderdoc.is_frame_capturing()
+    }
+
+    /// Start a frame capture that ends when the returned scope is dropped. See [`CaptureScope`].
+    pub fn scope(&mut self) -> CaptureScope<'_> {
+        self.start_frame_capture();
+        CaptureScope {
+            capture: self,
+            discard: false,
+        }
+    }
+
+    /// Set the keys that trigger a capture in-application. An empty slice disables capture hotkeys,
+    /// which is useful when captures are only triggered programmatically.
+    pub fn set_capture_keys(&mut self, keys: &[InputButton]) {
+        self.renderdoc.set_capture_keys(keys);
+    }
+
+    /// Set the path template for capture files. RenderDoc appends a frame number and the `.rdc` extension to this path.
+    pub fn set_capture_file_path_template(&mut self, path: impl Into<PathBuf>) {
+        self.renderdoc.set_capture_file_path_template(path);
+    }
+
+    /// Get the path template for capture files.
+    pub fn capture_file_path_template(&self) -> &Path {
+        self.renderdoc.get_capture_file_path_template()
+    }
+
+    /// Get the number of captures made so far.
+    pub fn num_captures(&self) -> u32 {
+        self.renderdoc.get_num_captures()
+    }
+
+    /// Get the path and creation time of a capture. Returns `None` if the index is out of range.
+    pub fn capture(&self, index: u32) -> Option<(PathBuf, SystemTime)> {
+        self.renderdoc.get_capture(index)
+    }
+}
+
+/// A frame capture in progress, created with [`Capture::scope()`]. The capture is saved when this is dropped,
+/// unless [`CaptureScope::discard()`] was called.
+#[derive(Debug)]
+pub struct CaptureScope<'a> {
+    capture: &'a mut Capture,
+    discard: bool,
+}
+
+impl CaptureScope<'_> {
+    /// End the capture and save it to disk. This is equivalent to dropping the scope.
+    pub fn end(self) {}
+
+    /// End the capture without saving it to disk, for example because the frame turned out not to be interesting.
+    pub fn discard(mut self) {
+        self.discard = true;
+    }
+}
+
+impl Drop for CaptureScope<'_> {
+    fn drop(&mut self) {
+        if self.discard {
+            self.capture.discard_frame_capture();
+        } else {
+            self.capture.end_frame_capture();
+        }
+    }
+}
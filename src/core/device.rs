@@ -6,21 +6,27 @@ use std::fmt::Formatter;
 #[allow(unused_imports)]
 use std::mem::ManuallyDrop;
 use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, Ordering};
 #[allow(unused_imports)]
-use std::sync::{Arc, Mutex, MutexGuard};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
 
 use anyhow::Result;
 use ash::extensions::{ext, khr};
+use ash::prelude::VkResult;
 use ash::vk;
 #[cfg(feature = "fsr2")]
 use fsr2_sys::FfxDimensions2D;
 
-use crate::{AppSettings, Error, Instance, PhysicalDevice, WindowInterface};
+use crate::{AppSettings, Error, Instance, PhysicalDevice, QueueType, WindowInterface};
+use crate::allocator::statistics::{AllocationStatistics, HeapBudget, MemoryTracker};
+use crate::core::physical_device::is_software_implementation;
+use crate::core::queue::DeviceQueue;
 use crate::core::traits::Nameable;
 #[cfg(feature = "fsr2")]
 use crate::fsr2::Fsr2Context;
 #[cfg(feature = "fsr2")]
 use crate::fsr2::Fsr2ContextCreateInfo;
+use crate::util::host_image_copy::{query_copy_dst_layouts, HostImageCopyFn, PhysicalDeviceHostImageCopyFeatures};
 use crate::util::string::unwrap_to_raw_strings;
 
 /// Device extensions that phobos requests but might not be available.
@@ -41,6 +47,59 @@ pub enum ExtensionID {
     AccelerationStructure,
     /// `VK_KHR_ray_tracing_pipeline` provides raytracing pipelines and ray query objects in shaders.
     RayTracingPipeline,
+    /// `VK_EXT_mesh_shader` provides mesh and task shader stages in graphics pipelines.
+    MeshShader,
+    /// `VK_EXT_conditional_rendering` allows discarding drawcalls and dispatches based on a value in a buffer.
+    /// This extension is only enabled when it is listed in [`GPURequirements::device_extensions`](crate::GPURequirements::device_extensions).
+    ConditionalRendering,
+    /// `VK_KHR_fragment_shading_rate` provides variable rate shading per pipeline, per draw and through a shading rate attachment.
+    FragmentShadingRate,
+    /// `VK_EXT_memory_priority` allows assigning a residency priority to device memory allocations.
+    MemoryPriority,
+    /// `VK_EXT_pageable_device_local_memory` allows changing the residency priority of device memory after it was allocated.
+    /// This extension is only enabled if [`ExtensionID::MemoryPriority`] is also enabled.
+    PageableDeviceLocalMemory,
+    /// `VK_EXT_memory_budget` allows querying the memory usage and budget of each memory heap.
+    MemoryBudget,
+    /// `VK_EXT_custom_border_color` allows samplers to use an arbitrary border color.
+    CustomBorderColor,
+    /// `VK_EXT_graphics_pipeline_library` allows compiling parts of a graphics pipeline separately and linking them later.
+    GraphicsPipelineLibrary,
+    /// `VK_EXT_conservative_rasterization` allows rasterizing every pixel that is partially covered by a primitive.
+    ConservativeRasterization,
+    /// `VK_EXT_depth_clip_control` allows using a `[-1, 1]` depth range in clip space.
+    DepthClipControl,
+    /// `VK_EXT_shader_object` allows binding shaders directly, with all pipeline state set dynamically.
+    ShaderObject,
+    /// `VK_KHR_ray_query` allows tracing rays against an acceleration structure from any shader stage.
+    RayQuery,
+    /// `VK_KHR_ray_tracing_maintenance1` provides indirect trace rays commands that also read the shader binding table from a buffer.
+    RayTracingMaintenance1,
+    /// `VK_EXT_opacity_micromap` allows attaching micromaps with per-triangle opacity data to acceleration structure geometry.
+    OpacityMicromap,
+    /// `VK_KHR_performance_query` allows reading hardware performance counters through query pools.
+    PerformanceQuery,
+    /// `VK_EXT_calibrated_timestamps` allows correlating GPU timestamps with host clocks.
+    CalibratedTimestamps,
+    /// `VK_NV_device_diagnostics_config` enables extra GPU crash diagnostics, such as shader debug info and automatic checkpoints.
+    DeviceDiagnosticsConfig,
+    /// `VK_KHR_video_queue` provides video sessions and video coding scopes in command buffers.
+    VideoQueue,
+    /// `VK_KHR_video_decode_queue` provides video decode commands. This extension is only enabled if [`ExtensionID::VideoQueue`] is also enabled.
+    VideoDecodeQueue,
+    /// `VK_KHR_video_decode_h264` allows decoding H.264 streams. This extension is only enabled if [`ExtensionID::VideoDecodeQueue`] is also enabled.
+    VideoDecodeH264,
+    /// `VK_KHR_present_id` allows attaching an identifier to each present operation.
+    PresentId,
+    /// `VK_KHR_present_wait` allows waiting on the host until a present operation completes. This extension is only enabled
+    /// if [`ExtensionID::PresentId`] is also enabled.
+    PresentWait,
+    /// `VK_GOOGLE_display_timing` allows querying the refresh cycle duration and past presentation timings, and scheduling presents.
+    DisplayTiming,
+    /// `VK_EXT_full_screen_exclusive` allows controlling exclusive fullscreen behaviour of swapchains on Windows.
+    FullScreenExclusive,
+    /// `VK_EXT_host_image_copy` allows copying between host memory and images on the CPU, without staging buffers.
+    HostImageCopy,
 }
 
 impl std::fmt::Display for ExtensionID {
@@ -56,19 +115,72 @@ struct DeviceInner {
     fsr2_context: Mutex<ManuallyDrop<Fsr2Context>>,
     #[derivative(Debug = "ignore")]
     handle: ash::Device,
+    physical_device: vk::PhysicalDevice,
+    #[derivative(Debug = "ignore")]
+    instance_fns_1_1: vk::InstanceFnV1_1,
     queue_families: Vec<u32>,
     properties: vk::PhysicalDeviceProperties,
     accel_structure_properties: Option<vk::PhysicalDeviceAccelerationStructurePropertiesKHR>,
     rt_properties: Option<vk::PhysicalDeviceRayTracingPipelinePropertiesKHR>,
     extensions: HashSet<ExtensionID>,
+    features: vk::PhysicalDeviceFeatures,
+    features_1_1: vk::PhysicalDeviceVulkan11Features,
+    features_1_2: vk::PhysicalDeviceVulkan12Features,
     #[derivative(Debug = "ignore")]
     dynamic_state3: Option<ext::ExtendedDynamicState3>,
+    dynamic_state3_features: vk::PhysicalDeviceExtendedDynamicState3FeaturesEXT,
     #[derivative(Debug = "ignore")]
     acceleration_structure: Option<khr::AccelerationStructure>,
     #[derivative(Debug = "ignore")]
     rt_pipeline: Option<khr::RayTracingPipeline>,
+    rt_pipeline_features: vk::PhysicalDeviceRayTracingPipelineFeaturesKHR,
+    #[derivative(Debug = "ignore")]
+    rt_maintenance1: Option<khr::RayTracingMaintenance1>,
+    rt_maintenance1_features: vk::PhysicalDeviceRayTracingMaintenance1FeaturesKHR,
+    #[derivative(Debug = "ignore")]
+    opacity_micromap: Option<vk::ExtOpacityMicromapFn>,
+    #[derivative(Debug = "ignore")]
+    mesh_shader: Option<ext::MeshShader>,
+    mesh_shader_features: vk::PhysicalDeviceMeshShaderFeaturesEXT,
+    #[derivative(Debug = "ignore")]
+    shader_object: Option<ext::ShaderObject>,
+    #[derivative(Debug = "ignore")]
+    conditional_rendering: Option<vk::ExtConditionalRenderingFn>,
+    #[derivative(Debug = "ignore")]
+    fragment_shading_rate: Option<vk::KhrFragmentShadingRateFn>,
+    fragment_shading_rate_features: vk::PhysicalDeviceFragmentShadingRateFeaturesKHR,
+    custom_border_color_features: vk::PhysicalDeviceCustomBorderColorFeaturesEXT,
+    graphics_pipeline_library_properties: Option<vk::PhysicalDeviceGraphicsPipelineLibraryPropertiesEXT>,
+    conservative_rasterization_properties: Option<vk::PhysicalDeviceConservativeRasterizationPropertiesEXT>,
+    #[derivative(Debug = "ignore")]
+    pageable_device_local_memory: Option<vk::ExtPageableDeviceLocalMemoryFn>,
+    #[derivative(Debug = "ignore")]
+    performance_query: Option<vk::KhrPerformanceQueryFn>,
+    #[derivative(Debug = "ignore")]
+    calibrated_timestamps: Option<ext::CalibratedTimestamps>,
+    calibrateable_time_domains: Vec<vk::TimeDomainEXT>,
+    #[derivative(Debug = "ignore")]
+    video_queue: Option<vk::KhrVideoQueueFn>,
+    #[derivative(Debug = "ignore")]
+    video_decode_queue: Option<vk::KhrVideoDecodeQueueFn>,
+    #[derivative(Debug = "ignore")]
+    present_wait: Option<khr::PresentWait>,
+    #[derivative(Debug = "ignore")]
+    display_timing: Option<vk::GoogleDisplayTimingFn>,
+    #[derivative(Debug = "ignore")]
+    full_screen_exclusive: Option<ext::FullScreenExclusive>,
+    #[derivative(Debug = "ignore")]
+    host_image_copy: Option<HostImageCopyFn>,
+    host_image_copy_dst_layouts: Vec<vk::ImageLayout>,
     #[derivative(Debug = "ignore")]
     debug_utils: Option<ext::DebugUtils>,
+    memory_tracker: MemoryTracker,
+    // Set once device loss is observed, so the device lost handlers only run once.
+    lost: AtomicBool,
+    #[derivative(Debug = "ignore")]
+    device_lost_handlers: Mutex<Vec<Arc<dyn Fn() + Send + Sync>>>,
+    // Every queue that work can be submitted to, used to retire objects once the work submitted before they were dropped completed.
+    submission_queues: Mutex<Vec<Weak<Mutex<DeviceQueue>>>>,
 }
 
 /// Wrapper around a `VkDevice`. The device provides access to almost the entire
@@ -109,6 +221,23 @@ fn add_if_supported(
     }
 }
 
+/// Remove an extension that was added with [`add_if_supported()`], because the device does not support the features it
+/// needs. Always returns false, so the result can be assigned to the `*_supported` flag of the extension.
+fn remove_unsupported(
+    ext: ExtensionID,
+    name: &CStr,
+    enabled_set: &mut HashSet<ExtensionID>,
+    names: &mut Vec<CString>,
+) -> bool {
+    info!(
+        "Extension {} is available, but its features are not supported. Some features might be missing.",
+        name.to_bytes().escape_ascii()
+    );
+    enabled_set.remove(&ext);
+    names.retain(|enabled| enabled.as_c_str() != name);
+    false
+}
+
 impl Device {
     /// Create a new Vulkan device. This is the main interface point with the Vulkan API.
     /// # Errors
@@ -127,6 +256,7 @@ impl Device {
                 let count = physical_device
                     .queues()
                     .iter()
+                    .chain(physical_device.graphics_family_compute_queue().iter())
                     .filter(|queue| queue.family_index == index as u32)
                     .count();
                 if count == 0 {
@@ -162,7 +292,8 @@ impl Device {
             available_extensions.as_slice(),
         );
 
-        let accel_supported = if settings.raytracing {
+        let ray_query_requested = settings.raytracing || settings.gpu_requirements.ray_query;
+        let accel_supported = if ray_query_requested {
             add_if_supported(
                 ExtensionID::AccelerationStructure,
                 khr::AccelerationStructure::name(),
@@ -186,21 +317,317 @@ impl Device {
             false
         };
 
-        let ray_query_name = CStr::from_bytes_with_nul(b"VK_KHR_ray_query\0")?;
-        let ray_query_supported = settings.raytracing
+        let rt_maintenance1_supported = if rt_pipeline_supported {
+            add_if_supported(
+                ExtensionID::RayTracingMaintenance1,
+                khr::RayTracingMaintenance1::name(),
+                &mut enabled_extensions,
+                &mut extension_names,
+                available_extensions.as_slice(),
+            )
+        } else {
+            false
+        };
+
+        // Opacity micromaps are attached to acceleration structure geometry, so they require acceleration structure support.
+        let opacity_micromap_supported = if settings.opacity_micromap && accel_supported {
+            add_if_supported(
+                ExtensionID::OpacityMicromap,
+                vk::ExtOpacityMicromapFn::name(),
+                &mut enabled_extensions,
+                &mut extension_names,
+                available_extensions.as_slice(),
+            )
+        } else {
+            false
+        };
+
+        let performance_query_supported = if settings.performance_query {
+            add_if_supported(
+                ExtensionID::PerformanceQuery,
+                vk::KhrPerformanceQueryFn::name(),
+                &mut enabled_extensions,
+                &mut extension_names,
+                available_extensions.as_slice(),
+            )
+        } else {
+            false
+        };
+
+        let calibrated_timestamps_supported = if settings.calibrated_timestamps {
+            add_if_supported(
+                ExtensionID::CalibratedTimestamps,
+                ext::CalibratedTimestamps::name(),
+                &mut enabled_extensions,
+                &mut extension_names,
+                available_extensions.as_slice(),
+            )
+        } else {
+            false
+        };
+
+        let device_diagnostics_supported = if settings.device_diagnostics {
+            add_if_supported(
+                ExtensionID::DeviceDiagnosticsConfig,
+                vk::NvDeviceDiagnosticsConfigFn::name(),
+                &mut enabled_extensions,
+                &mut extension_names,
+                available_extensions.as_slice(),
+            )
+        } else {
+            false
+        };
+
+        let video_queue_supported = if settings.video_decode {
+            add_if_supported(
+                ExtensionID::VideoQueue,
+                vk::KhrVideoQueueFn::name(),
+                &mut enabled_extensions,
+                &mut extension_names,
+                available_extensions.as_slice(),
+            )
+        } else {
+            false
+        };
+
+        let video_decode_queue_supported = if video_queue_supported {
+            add_if_supported(
+                ExtensionID::VideoDecodeQueue,
+                vk::KhrVideoDecodeQueueFn::name(),
+                &mut enabled_extensions,
+                &mut extension_names,
+                available_extensions.as_slice(),
+            )
+        } else {
+            false
+        };
+
+        if video_decode_queue_supported {
+            add_if_supported(
+                ExtensionID::VideoDecodeH264,
+                vk::KhrVideoDecodeH264Fn::name(),
+                &mut enabled_extensions,
+                &mut extension_names,
+                available_extensions.as_slice(),
+            );
+        }
+
+        let present_id_supported = if settings.present_timing {
+            add_if_supported(
+                ExtensionID::PresentId,
+                vk::KhrPresentIdFn::name(),
+                &mut enabled_extensions,
+                &mut extension_names,
+                available_extensions.as_slice(),
+            )
+        } else {
+            false
+        };
+
+        let present_wait_supported = if present_id_supported {
+            add_if_supported(
+                ExtensionID::PresentWait,
+                khr::PresentWait::name(),
+                &mut enabled_extensions,
+                &mut extension_names,
+                available_extensions.as_slice(),
+            )
+        } else {
+            false
+        };
+
+        let display_timing_supported = if settings.present_timing {
+            add_if_supported(
+                ExtensionID::DisplayTiming,
+                vk::GoogleDisplayTimingFn::name(),
+                &mut enabled_extensions,
+                &mut extension_names,
+                available_extensions.as_slice(),
+            )
+        } else {
+            false
+        };
+
+        // `VK_EXT_full_screen_exclusive` depends on the instance extension `VK_KHR_get_surface_capabilities2`.
+        let full_screen_exclusive_supported = if settings.window.is_some()
+            && settings.full_screen_exclusive.is_some()
+            && instance.surface_capabilities2_enabled()
+        {
+            add_if_supported(
+                ExtensionID::FullScreenExclusive,
+                ext::FullScreenExclusive::name(),
+                &mut enabled_extensions,
+                &mut extension_names,
+                available_extensions.as_slice(),
+            )
+        } else {
+            false
+        };
+
+        let host_image_copy_supported = if settings.host_image_copy {
+            add_if_supported(
+                ExtensionID::HostImageCopy,
+                HostImageCopyFn::name(),
+                &mut enabled_extensions,
+                &mut extension_names,
+                available_extensions.as_slice(),
+            )
+        } else {
+            false
+        };
+
+        let mesh_shader_supported = if settings.mesh_shading {
+            add_if_supported(
+                ExtensionID::MeshShader,
+                ext::MeshShader::name(),
+                &mut enabled_extensions,
+                &mut extension_names,
+                available_extensions.as_slice(),
+            )
+        } else {
+            false
+        };
+
+        let fragment_shading_rate_supported = if settings.fragment_shading_rate {
+            add_if_supported(
+                ExtensionID::FragmentShadingRate,
+                vk::KhrFragmentShadingRateFn::name(),
+                &mut enabled_extensions,
+                &mut extension_names,
+                available_extensions.as_slice(),
+            )
+        } else {
+            false
+        };
+
+        let memory_priority_supported = if settings.memory_priority {
+            add_if_supported(
+                ExtensionID::MemoryPriority,
+                vk::ExtMemoryPriorityFn::name(),
+                &mut enabled_extensions,
+                &mut extension_names,
+                available_extensions.as_slice(),
+            )
+        } else {
+            false
+        };
+
+        if settings.memory_budget {
+            add_if_supported(
+                ExtensionID::MemoryBudget,
+                vk::ExtMemoryBudgetFn::name(),
+                &mut enabled_extensions,
+                &mut extension_names,
+                available_extensions.as_slice(),
+            );
+        }
+
+        let custom_border_color_supported = if settings.custom_border_color {
+            add_if_supported(
+                ExtensionID::CustomBorderColor,
+                vk::ExtCustomBorderColorFn::name(),
+                &mut enabled_extensions,
+                &mut extension_names,
+                available_extensions.as_slice(),
+            )
+        } else {
+            false
+        };
+
+        let conservative_rasterization_supported = if settings.conservative_rasterization {
+            add_if_supported(
+                ExtensionID::ConservativeRasterization,
+                vk::ExtConservativeRasterizationFn::name(),
+                &mut enabled_extensions,
+                &mut extension_names,
+                available_extensions.as_slice(),
+            )
+        } else {
+            false
+        };
+
+        let depth_clip_control_supported = if settings.depth_clip_control {
+            add_if_supported(
+                ExtensionID::DepthClipControl,
+                vk::ExtDepthClipControlFn::name(),
+                &mut enabled_extensions,
+                &mut extension_names,
+                available_extensions.as_slice(),
+            )
+        } else {
+            false
+        };
+
+        let shader_object_supported = if settings.shader_object {
+            add_if_supported(
+                ExtensionID::ShaderObject,
+                ext::ShaderObject::name(),
+                &mut enabled_extensions,
+                &mut extension_names,
+                available_extensions.as_slice(),
+            )
+        } else {
+            false
+        };
+
+        // Graphics pipeline libraries are built on top of VK_KHR_pipeline_library, so both need to be available.
+        let graphics_pipeline_library_supported = settings.graphics_pipeline_library
             && available_extensions.iter().any(|ext| {
-                ray_query_name == unsafe { CStr::from_ptr(ext.extension_name.as_ptr()) }
-            });
-        if ray_query_supported {
-            extension_names.push(CString::from(ray_query_name));
+                vk::KhrPipelineLibraryFn::name() == unsafe { CStr::from_ptr(ext.extension_name.as_ptr()) }
+            })
+            && add_if_supported(
+                ExtensionID::GraphicsPipelineLibrary,
+                vk::ExtGraphicsPipelineLibraryFn::name(),
+                &mut enabled_extensions,
+                &mut extension_names,
+                available_extensions.as_slice(),
+            );
+        if graphics_pipeline_library_supported {
+            extension_names.push(CString::from(vk::KhrPipelineLibraryFn::name()));
         }
 
+        // Pageable device local memory depends on memory priority, so only try to enable it if that succeeded.
+        let pageable_memory_supported = if memory_priority_supported {
+            add_if_supported(
+                ExtensionID::PageableDeviceLocalMemory,
+                vk::ExtPageableDeviceLocalMemoryFn::name(),
+                &mut enabled_extensions,
+                &mut extension_names,
+                available_extensions.as_slice(),
+            )
+        } else {
+            false
+        };
+
+        // Conditional rendering is opt-in through the requested device extensions. Since these were already verified to be
+        // present during physical device selection, we only need to record that it is enabled.
+        let conditional_rendering_enabled = settings
+            .gpu_requirements
+            .device_extensions
+            .iter()
+            .any(|ext| ext.as_bytes() == vk::ExtConditionalRenderingFn::name().to_bytes());
+        if conditional_rendering_enabled {
+            enabled_extensions.insert(ExtensionID::ConditionalRendering);
+        }
+
+        let ray_query_supported = if ray_query_requested {
+            add_if_supported(
+                ExtensionID::RayQuery,
+                vk::KhrRayQueryFn::name(),
+                &mut enabled_extensions,
+                &mut extension_names,
+                available_extensions.as_slice(),
+            )
+        } else {
+            false
+        };
+
         // Add required extensions
         if settings.window.is_some() {
             extension_names.push(CString::from(khr::Swapchain::name()));
         }
 
-        if settings.raytracing {
+        if ray_query_requested {
             extension_names.push(CString::from(khr::DeferredHostOperations::name()));
         }
 
@@ -209,6 +636,259 @@ impl Device {
             info!("{:?}", ext);
         }
 
+        // Query support for optional features, so we can enable them if they are available.
+        let mut supported_features_1_1 = vk::PhysicalDeviceVulkan11Features::default();
+        let mut supported_features_1_2 = vk::PhysicalDeviceVulkan12Features::default();
+        let mut supported_fragment_shading_rate =
+            vk::PhysicalDeviceFragmentShadingRateFeaturesKHR::default();
+        let mut supported_features = vk::PhysicalDeviceFeatures2::builder()
+            .push_next(&mut supported_features_1_1)
+            .push_next(&mut supported_features_1_2);
+        if fragment_shading_rate_supported {
+            supported_features = supported_features.push_next(&mut supported_fragment_shading_rate);
+        }
+        let mut supported_custom_border_color = vk::PhysicalDeviceCustomBorderColorFeaturesEXT::default();
+        if custom_border_color_supported {
+            supported_features = supported_features.push_next(&mut supported_custom_border_color);
+        }
+        let mut supported_dynamic_state3 = vk::PhysicalDeviceExtendedDynamicState3FeaturesEXT::default();
+        if dynamic_state3_supported {
+            supported_features = supported_features.push_next(&mut supported_dynamic_state3);
+        }
+        let mut supported_rt_pipeline = vk::PhysicalDeviceRayTracingPipelineFeaturesKHR::default();
+        if rt_pipeline_supported {
+            supported_features = supported_features.push_next(&mut supported_rt_pipeline);
+        }
+        let mut supported_rt_maintenance1 = vk::PhysicalDeviceRayTracingMaintenance1FeaturesKHR::default();
+        if rt_maintenance1_supported {
+            supported_features = supported_features.push_next(&mut supported_rt_maintenance1);
+        }
+        let mut supported_mesh_shader = vk::PhysicalDeviceMeshShaderFeaturesEXT::default();
+        if mesh_shader_supported {
+            supported_features = supported_features.push_next(&mut supported_mesh_shader);
+        }
+        let mut supported_host_image_copy = PhysicalDeviceHostImageCopyFeatures::default();
+        if host_image_copy_supported {
+            supported_features = supported_features.push_next(&mut supported_host_image_copy);
+        }
+        let mut supported_present_id = vk::PhysicalDevicePresentIdFeaturesKHR::default();
+        if present_id_supported {
+            supported_features = supported_features.push_next(&mut supported_present_id);
+        }
+        let mut supported_present_wait = vk::PhysicalDevicePresentWaitFeaturesKHR::default();
+        if present_wait_supported {
+            supported_features = supported_features.push_next(&mut supported_present_wait);
+        }
+        let mut supported_shader_object = vk::PhysicalDeviceShaderObjectFeaturesEXT::default();
+        if shader_object_supported {
+            supported_features = supported_features.push_next(&mut supported_shader_object);
+        }
+        let mut supported_graphics_pipeline_library = vk::PhysicalDeviceGraphicsPipelineLibraryFeaturesEXT::default();
+        if graphics_pipeline_library_supported {
+            supported_features = supported_features.push_next(&mut supported_graphics_pipeline_library);
+        }
+        let mut supported_depth_clip_control = vk::PhysicalDeviceDepthClipControlFeaturesEXT::default();
+        if depth_clip_control_supported {
+            supported_features = supported_features.push_next(&mut supported_depth_clip_control);
+        }
+        let mut supported_opacity_micromap = vk::PhysicalDeviceOpacityMicromapFeaturesEXT::default();
+        if opacity_micromap_supported {
+            supported_features = supported_features.push_next(&mut supported_opacity_micromap);
+        }
+        let mut supported_performance_query = vk::PhysicalDevicePerformanceQueryFeaturesKHR::default();
+        if performance_query_supported {
+            supported_features = supported_features.push_next(&mut supported_performance_query);
+        }
+        let mut supported_memory_priority = vk::PhysicalDeviceMemoryPriorityFeaturesEXT::default();
+        if memory_priority_supported {
+            supported_features = supported_features.push_next(&mut supported_memory_priority);
+        }
+        let mut supported_pageable_memory = vk::PhysicalDevicePageableDeviceLocalMemoryFeaturesEXT::default();
+        if pageable_memory_supported {
+            supported_features = supported_features.push_next(&mut supported_pageable_memory);
+        }
+        let mut supported_diagnostics_config = vk::PhysicalDeviceDiagnosticsConfigFeaturesNV::default();
+        if device_diagnostics_supported {
+            supported_features = supported_features.push_next(&mut supported_diagnostics_config);
+        }
+        let mut supported_conditional_rendering = vk::PhysicalDeviceConditionalRenderingFeaturesEXT::default();
+        if conditional_rendering_enabled {
+            supported_features = supported_features.push_next(&mut supported_conditional_rendering);
+        }
+        // SAFETY: Vulkan API call. We have a valid reference to a PhysicalDevice, so handle() is valid.
+        unsafe {
+            instance.get_physical_device_features2(
+                physical_device.handle(),
+                &mut supported_features,
+            );
+        }
+        let tessellation_supported = supported_features.features.tessellation_shader == vk::TRUE;
+        let wide_lines_supported = supported_features.features.wide_lines == vk::TRUE;
+        let fill_mode_non_solid_supported = supported_features.features.fill_mode_non_solid == vk::TRUE;
+        let depth_clamp_supported = supported_features.features.depth_clamp == vk::TRUE;
+        let supported_core_features = supported_features.features;
+
+        // Some extensions can be available without supporting their main feature, only keep them if it is supported.
+        let host_image_copy_supported = if host_image_copy_supported && supported_host_image_copy.host_image_copy != vk::TRUE {
+            remove_unsupported(
+                ExtensionID::HostImageCopy,
+                HostImageCopyFn::name(),
+                &mut enabled_extensions,
+                &mut extension_names,
+            )
+        } else {
+            host_image_copy_supported
+        };
+        let mesh_shader_supported = if mesh_shader_supported && supported_mesh_shader.mesh_shader != vk::TRUE {
+            remove_unsupported(
+                ExtensionID::MeshShader,
+                ext::MeshShader::name(),
+                &mut enabled_extensions,
+                &mut extension_names,
+            )
+        } else {
+            mesh_shader_supported
+        };
+        // Present wait needs present identifiers to know what to wait for, so it is removed together with present id.
+        let present_wait_supported = if present_wait_supported
+            && (supported_present_id.present_id != vk::TRUE || supported_present_wait.present_wait != vk::TRUE)
+        {
+            remove_unsupported(
+                ExtensionID::PresentWait,
+                khr::PresentWait::name(),
+                &mut enabled_extensions,
+                &mut extension_names,
+            )
+        } else {
+            present_wait_supported
+        };
+        let present_id_supported = if present_id_supported && supported_present_id.present_id != vk::TRUE {
+            remove_unsupported(
+                ExtensionID::PresentId,
+                vk::KhrPresentIdFn::name(),
+                &mut enabled_extensions,
+                &mut extension_names,
+            )
+        } else {
+            present_id_supported
+        };
+        let shader_object_supported = if shader_object_supported && supported_shader_object.shader_object != vk::TRUE {
+            remove_unsupported(
+                ExtensionID::ShaderObject,
+                ext::ShaderObject::name(),
+                &mut enabled_extensions,
+                &mut extension_names,
+            )
+        } else {
+            shader_object_supported
+        };
+        let graphics_pipeline_library_supported = if graphics_pipeline_library_supported
+            && supported_graphics_pipeline_library.graphics_pipeline_library != vk::TRUE
+        {
+            // VK_KHR_pipeline_library was only enabled for graphics pipeline libraries, so it is removed as well.
+            extension_names.retain(|enabled| enabled.as_c_str() != vk::KhrPipelineLibraryFn::name());
+            remove_unsupported(
+                ExtensionID::GraphicsPipelineLibrary,
+                vk::ExtGraphicsPipelineLibraryFn::name(),
+                &mut enabled_extensions,
+                &mut extension_names,
+            )
+        } else {
+            graphics_pipeline_library_supported
+        };
+        let depth_clip_control_supported =
+            if depth_clip_control_supported && supported_depth_clip_control.depth_clip_control != vk::TRUE {
+                remove_unsupported(
+                    ExtensionID::DepthClipControl,
+                    vk::ExtDepthClipControlFn::name(),
+                    &mut enabled_extensions,
+                    &mut extension_names,
+                )
+            } else {
+                depth_clip_control_supported
+            };
+        let opacity_micromap_supported = if opacity_micromap_supported && supported_opacity_micromap.micromap != vk::TRUE {
+            remove_unsupported(
+                ExtensionID::OpacityMicromap,
+                vk::ExtOpacityMicromapFn::name(),
+                &mut enabled_extensions,
+                &mut extension_names,
+            )
+        } else {
+            opacity_micromap_supported
+        };
+        let performance_query_supported = if performance_query_supported
+            && supported_performance_query.performance_counter_query_pools != vk::TRUE
+        {
+            remove_unsupported(
+                ExtensionID::PerformanceQuery,
+                vk::KhrPerformanceQueryFn::name(),
+                &mut enabled_extensions,
+                &mut extension_names,
+            )
+        } else {
+            performance_query_supported
+        };
+        // Pageable device local memory depends on memory priority, so it is removed together with memory priority.
+        let pageable_memory_supported = if pageable_memory_supported
+            && (supported_memory_priority.memory_priority != vk::TRUE
+                || supported_pageable_memory.pageable_device_local_memory != vk::TRUE)
+        {
+            remove_unsupported(
+                ExtensionID::PageableDeviceLocalMemory,
+                vk::ExtPageableDeviceLocalMemoryFn::name(),
+                &mut enabled_extensions,
+                &mut extension_names,
+            )
+        } else {
+            pageable_memory_supported
+        };
+        let memory_priority_supported =
+            if memory_priority_supported && supported_memory_priority.memory_priority != vk::TRUE {
+                remove_unsupported(
+                    ExtensionID::MemoryPriority,
+                    vk::ExtMemoryPriorityFn::name(),
+                    &mut enabled_extensions,
+                    &mut extension_names,
+                )
+            } else {
+                memory_priority_supported
+            };
+        let device_diagnostics_supported =
+            if device_diagnostics_supported && supported_diagnostics_config.diagnostics_config != vk::TRUE {
+                remove_unsupported(
+                    ExtensionID::DeviceDiagnosticsConfig,
+                    vk::NvDeviceDiagnosticsConfigFn::name(),
+                    &mut enabled_extensions,
+                    &mut extension_names,
+                )
+            } else {
+                device_diagnostics_supported
+            };
+        let rt_maintenance1_supported =
+            if rt_maintenance1_supported && supported_rt_maintenance1.ray_tracing_maintenance1 != vk::TRUE {
+                remove_unsupported(
+                    ExtensionID::RayTracingMaintenance1,
+                    khr::RayTracingMaintenance1::name(),
+                    &mut enabled_extensions,
+                    &mut extension_names,
+                )
+            } else {
+                rt_maintenance1_supported
+            };
+        let conditional_rendering_enabled = if conditional_rendering_enabled
+            && supported_conditional_rendering.conditional_rendering != vk::TRUE
+        {
+            remove_unsupported(
+                ExtensionID::ConditionalRendering,
+                vk::ExtConditionalRenderingFn::name(),
+                &mut enabled_extensions,
+                &mut extension_names,
+            )
+        } else {
+            conditional_rendering_enabled
+        };
+
         let mut features = settings.gpu_requirements.features;
         let mut features_1_1 = settings.gpu_requirements.features_1_1;
         let mut features_1_2 = settings.gpu_requirements.features_1_2;
@@ -220,6 +900,80 @@ impl Device {
         features_1_2.runtime_descriptor_array = vk::TRUE;
         features_1_2.descriptor_binding_partially_bound = vk::TRUE;
         features_1_2.shader_sampled_image_array_non_uniform_indexing = vk::TRUE;
+        if supported_features_1_2.draw_indirect_count == vk::TRUE {
+            features_1_2.draw_indirect_count = vk::TRUE;
+        }
+        // Bindless descriptor arrays: variable-sized, update-after-bind combined image sampler arrays.
+        if supported_features_1_2.descriptor_binding_variable_descriptor_count == vk::TRUE {
+            features_1_2.descriptor_binding_variable_descriptor_count = vk::TRUE;
+        }
+        if supported_features_1_2.descriptor_binding_sampled_image_update_after_bind == vk::TRUE {
+            features_1_2.descriptor_binding_sampled_image_update_after_bind = vk::TRUE;
+        }
+        if supported_features_1_2.descriptor_binding_update_unused_while_pending == vk::TRUE {
+            features_1_2.descriptor_binding_update_unused_while_pending = vk::TRUE;
+        }
+        // Min/max reduction samplers, see SamplerCreateInfo::reduction_mode()
+        if supported_features_1_2.sampler_filter_minmax == vk::TRUE {
+            features_1_2.sampler_filter_minmax = vk::TRUE;
+        }
+        // Allows depth and stencil aspects to be in different layouts, see PassBuilder::stencil_attachment()
+        if supported_features_1_2.separate_depth_stencil_layouts == vk::TRUE {
+            features_1_2.separate_depth_stencil_layouts = vk::TRUE;
+        }
+        // Allows tessellation control and evaluation shaders, see PipelineBuilder::tessellation()
+        if tessellation_supported {
+            features.tessellation_shader = vk::TRUE;
+        }
+        // Allows line widths other than 1.0 and line or point polygon modes, see PipelineBuilder::line_width() and
+        // PipelineBuilder::polygon_mode()
+        if wide_lines_supported {
+            features.wide_lines = vk::TRUE;
+        }
+        if fill_mode_non_solid_supported {
+            features.fill_mode_non_solid = vk::TRUE;
+        }
+        // Allows clamping depth instead of clipping, see PipelineBuilder::depth_clamp()
+        if depth_clamp_supported {
+            features.depth_clamp = vk::TRUE;
+        }
+        // Allows sampling block-compressed images, see the compressed module
+        if supported_core_features.texture_compression_bc == vk::TRUE {
+            features.texture_compression_bc = vk::TRUE;
+        }
+        if supported_core_features.texture_compression_etc2 == vk::TRUE {
+            features.texture_compression_etc2 = vk::TRUE;
+        }
+        if supported_core_features.texture_compression_astc_ldr == vk::TRUE {
+            features.texture_compression_astc_ldr = vk::TRUE;
+        }
+        // Sparse resources are only usable with a sparse binding queue, see the sparse module.
+        let sparse_requested = settings
+            .gpu_requirements
+            .queues
+            .iter()
+            .any(|queue| queue.queue_type == QueueType::SparseBinding);
+        if sparse_requested && supported_core_features.sparse_binding == vk::TRUE {
+            features.sparse_binding = vk::TRUE;
+            features.sparse_residency_buffer = supported_core_features.sparse_residency_buffer;
+            features.sparse_residency_image2_d = supported_core_features.sparse_residency_image2_d;
+            features.sparse_residency_image3_d = supported_core_features.sparse_residency_image3_d;
+            features.sparse_residency_aliased = supported_core_features.sparse_residency_aliased;
+        }
+        // Allows rendering to multiple views in a single pass, see PassBuilder::view_mask()
+        if supported_features_1_1.multiview == vk::TRUE {
+            features_1_1.multiview = vk::TRUE;
+            if supported_features_1_1.multiview_geometry_shader == vk::TRUE {
+                features_1_1.multiview_geometry_shader = vk::TRUE;
+            }
+            if supported_features_1_1.multiview_tessellation_shader == vk::TRUE {
+                features_1_1.multiview_tessellation_shader = vk::TRUE;
+            }
+        }
+        // Allows sampling multi-planar images such as video frames, see the ycbcr module
+        if supported_features_1_1.sampler_ycbcr_conversion == vk::TRUE {
+            features_1_1.sampler_ycbcr_conversion = vk::TRUE;
+        }
         features_1_3.synchronization2 = vk::TRUE;
         features_1_3.dynamic_rendering = vk::TRUE;
         features_1_3.maintenance4 = vk::TRUE;
@@ -241,7 +995,9 @@ impl Device {
             .push_next(&mut features_1_3);
 
         let mut features_dynamic_state3 = vk::PhysicalDeviceExtendedDynamicState3FeaturesEXT {
-            extended_dynamic_state3_polygon_mode: vk::TRUE,
+            extended_dynamic_state3_polygon_mode: supported_dynamic_state3.extended_dynamic_state3_polygon_mode,
+            extended_dynamic_state3_color_blend_enable: supported_dynamic_state3
+                .extended_dynamic_state3_color_blend_enable,
             ..Default::default()
         };
         if dynamic_state3_supported {
@@ -279,15 +1035,233 @@ impl Device {
             ray_tracing_pipeline: vk::TRUE,
             ray_tracing_pipeline_shader_group_handle_capture_replay: vk::FALSE,
             ray_tracing_pipeline_shader_group_handle_capture_replay_mixed: vk::FALSE,
-            ray_tracing_pipeline_trace_rays_indirect: vk::FALSE,
+            // Allows GPU-driven dispatch sizes, see GraphicsCmdBuffer::trace_rays_indirect()
+            ray_tracing_pipeline_trace_rays_indirect: supported_rt_pipeline.ray_tracing_pipeline_trace_rays_indirect,
             ray_traversal_primitive_culling: vk::FALSE,
         };
 
-        if rt_pipeline_supported {
-            info = info.push_next(&mut features_ray_tracing_pipeline);
-        }
+        if rt_pipeline_supported {
+            info = info.push_next(&mut features_ray_tracing_pipeline);
+        }
+
+        let mut features_rt_maintenance1 = vk::PhysicalDeviceRayTracingMaintenance1FeaturesKHR {
+            ray_tracing_maintenance1: vk::TRUE,
+            // Allows reading the shader binding table from a buffer, see GraphicsCmdBuffer::trace_rays_indirect2()
+            ray_tracing_pipeline_trace_rays_indirect2: supported_rt_maintenance1.ray_tracing_pipeline_trace_rays_indirect2,
+            ..Default::default()
+        };
+
+        if rt_maintenance1_supported {
+            info = info.push_next(&mut features_rt_maintenance1);
+        }
+
+        let mut features_opacity_micromap = vk::PhysicalDeviceOpacityMicromapFeaturesEXT {
+            micromap: vk::TRUE,
+            ..Default::default()
+        };
+
+        if opacity_micromap_supported {
+            info = info.push_next(&mut features_opacity_micromap);
+        }
+
+        let mut features_performance_query = vk::PhysicalDevicePerformanceQueryFeaturesKHR {
+            performance_counter_query_pools: vk::TRUE,
+            ..Default::default()
+        };
+
+        if performance_query_supported {
+            info = info.push_next(&mut features_performance_query);
+        }
+
+        let mut features_present_id = vk::PhysicalDevicePresentIdFeaturesKHR {
+            present_id: vk::TRUE,
+            ..Default::default()
+        };
+
+        if present_id_supported {
+            info = info.push_next(&mut features_present_id);
+        }
+
+        let mut features_present_wait = vk::PhysicalDevicePresentWaitFeaturesKHR {
+            present_wait: vk::TRUE,
+            ..Default::default()
+        };
+
+        if present_wait_supported {
+            info = info.push_next(&mut features_present_wait);
+        }
+
+        // Task shaders are optional, only mesh shaders are required for the extension to be enabled.
+        let mut features_mesh_shader = vk::PhysicalDeviceMeshShaderFeaturesEXT {
+            task_shader: supported_mesh_shader.task_shader,
+            mesh_shader: vk::TRUE,
+            // Allows profiling mesh pipelines, see MeshPrimitivesGeneratedQuery and PipelineStatisticsQuery
+            mesh_shader_queries: supported_mesh_shader.mesh_shader_queries,
+            ..Default::default()
+        };
+
+        if mesh_shader_supported {
+            info = info.push_next(&mut features_mesh_shader);
+        }
+
+        let mut features_conditional_rendering = vk::PhysicalDeviceConditionalRenderingFeaturesEXT {
+            conditional_rendering: vk::TRUE,
+            ..Default::default()
+        };
+
+        if conditional_rendering_enabled {
+            info = info.push_next(&mut features_conditional_rendering);
+        }
+
+        // Enable every shading rate source the device supports.
+        let mut features_fragment_shading_rate = vk::PhysicalDeviceFragmentShadingRateFeaturesKHR {
+            pipeline_fragment_shading_rate: supported_fragment_shading_rate
+                .pipeline_fragment_shading_rate,
+            primitive_fragment_shading_rate: supported_fragment_shading_rate
+                .primitive_fragment_shading_rate,
+            attachment_fragment_shading_rate: supported_fragment_shading_rate
+                .attachment_fragment_shading_rate,
+            ..Default::default()
+        };
+
+        if fragment_shading_rate_supported {
+            info = info.push_next(&mut features_fragment_shading_rate);
+        }
+
+        let mut features_custom_border_color = vk::PhysicalDeviceCustomBorderColorFeaturesEXT {
+            custom_border_colors: supported_custom_border_color.custom_border_colors,
+            custom_border_color_without_format: supported_custom_border_color
+                .custom_border_color_without_format,
+            ..Default::default()
+        };
+
+        if custom_border_color_supported {
+            info = info.push_next(&mut features_custom_border_color);
+        }
+
+        let mut features_memory_priority = vk::PhysicalDeviceMemoryPriorityFeaturesEXT {
+            memory_priority: vk::TRUE,
+            ..Default::default()
+        };
+
+        if memory_priority_supported {
+            info = info.push_next(&mut features_memory_priority);
+        }
+
+        let mut features_pageable_memory = vk::PhysicalDevicePageableDeviceLocalMemoryFeaturesEXT {
+            pageable_device_local_memory: vk::TRUE,
+            ..Default::default()
+        };
+
+        if pageable_memory_supported {
+            info = info.push_next(&mut features_pageable_memory);
+        }
+
+        let mut features_host_image_copy = PhysicalDeviceHostImageCopyFeatures {
+            host_image_copy: vk::TRUE,
+            ..Default::default()
+        };
+
+        if host_image_copy_supported {
+            info = info.push_next(&mut features_host_image_copy);
+        }
+
+        let mut features_graphics_pipeline_library = vk::PhysicalDeviceGraphicsPipelineLibraryFeaturesEXT {
+            graphics_pipeline_library: vk::TRUE,
+            ..Default::default()
+        };
+
+        if graphics_pipeline_library_supported {
+            info = info.push_next(&mut features_graphics_pipeline_library);
+        }
+
+        let mut features_depth_clip_control = vk::PhysicalDeviceDepthClipControlFeaturesEXT {
+            depth_clip_control: vk::TRUE,
+            ..Default::default()
+        };
+
+        if depth_clip_control_supported {
+            info = info.push_next(&mut features_depth_clip_control);
+        }
+
+        let mut features_shader_object = vk::PhysicalDeviceShaderObjectFeaturesEXT {
+            shader_object: vk::TRUE,
+            ..Default::default()
+        };
+
+        if shader_object_supported {
+            info = info.push_next(&mut features_shader_object);
+        }
+
+        let mut features_diagnostics_config = vk::PhysicalDeviceDiagnosticsConfigFeaturesNV {
+            diagnostics_config: vk::TRUE,
+            ..Default::default()
+        };
+        let mut diagnostics_config = vk::DeviceDiagnosticsConfigCreateInfoNV {
+            flags: vk::DeviceDiagnosticsConfigFlagsNV::ENABLE_SHADER_DEBUG_INFO
+                | vk::DeviceDiagnosticsConfigFlagsNV::ENABLE_RESOURCE_TRACKING
+                | vk::DeviceDiagnosticsConfigFlagsNV::ENABLE_AUTOMATIC_CHECKPOINTS,
+            ..Default::default()
+        };
+
+        if device_diagnostics_supported {
+            info = info
+                .push_next(&mut features_diagnostics_config)
+                .push_next(&mut diagnostics_config);
+        }
+
+        let info = info.build();
+        // Keep a copy of the enabled features around, but make sure to not store dangling pointers to the rest of the chain.
+        let enabled_features_1_1 = vk::PhysicalDeviceVulkan11Features {
+            p_next: std::ptr::null_mut(),
+            ..features_1_1
+        };
+        let enabled_features_1_2 = vk::PhysicalDeviceVulkan12Features {
+            p_next: std::ptr::null_mut(),
+            ..features_1_2
+        };
+
+        let enabled_fragment_shading_rate = vk::PhysicalDeviceFragmentShadingRateFeaturesKHR {
+            p_next: std::ptr::null_mut(),
+            ..features_fragment_shading_rate
+        };
+
+        let enabled_custom_border_color = vk::PhysicalDeviceCustomBorderColorFeaturesEXT {
+            p_next: std::ptr::null_mut(),
+            ..features_custom_border_color
+        };
+
+        let enabled_dynamic_state3 = vk::PhysicalDeviceExtendedDynamicState3FeaturesEXT {
+            p_next: std::ptr::null_mut(),
+            ..features_dynamic_state3
+        };
+
+        let enabled_rt_pipeline = if rt_pipeline_supported {
+            vk::PhysicalDeviceRayTracingPipelineFeaturesKHR {
+                p_next: std::ptr::null_mut(),
+                ..features_ray_tracing_pipeline
+            }
+        } else {
+            Default::default()
+        };
 
-        let info = info.build();
+        let enabled_rt_maintenance1 = if rt_maintenance1_supported {
+            vk::PhysicalDeviceRayTracingMaintenance1FeaturesKHR {
+                p_next: std::ptr::null_mut(),
+                ..features_rt_maintenance1
+            }
+        } else {
+            Default::default()
+        };
+
+        let enabled_mesh_shader = if mesh_shader_supported {
+            vk::PhysicalDeviceMeshShaderFeaturesEXT {
+                p_next: std::ptr::null_mut(),
+                ..features_mesh_shader
+            }
+        } else {
+            Default::default()
+        };
 
         let handle = unsafe { instance.create_device(physical_device.handle(), &info, None)? };
         #[cfg(feature = "log-objects")]
@@ -311,6 +1285,149 @@ impl Device {
             None
         };
 
+        let rt_maintenance1 = if rt_maintenance1_supported {
+            Some(khr::RayTracingMaintenance1::new(instance, &handle))
+        } else {
+            None
+        };
+
+        // ash does not provide a wrapper for this extension, so we load the function pointers ourselves.
+        let opacity_micromap = if opacity_micromap_supported {
+            Some(vk::ExtOpacityMicromapFn::load(|name| unsafe {
+                // SAFETY: Vulkan API call. The device handle was just created and is valid.
+                std::mem::transmute(instance.get_device_proc_addr(handle.handle(), name.as_ptr()))
+            }))
+        } else {
+            None
+        };
+
+        let mesh_shader = if mesh_shader_supported {
+            Some(ext::MeshShader::new(instance, &handle))
+        } else {
+            None
+        };
+
+        let shader_object = if shader_object_supported {
+            Some(ext::ShaderObject::new(instance, &handle))
+        } else {
+            None
+        };
+
+        // ash does not provide a wrapper for this extension, so we load the function pointers ourselves.
+        let conditional_rendering = if conditional_rendering_enabled {
+            Some(vk::ExtConditionalRenderingFn::load(|name| unsafe {
+                // SAFETY: Vulkan API call. The device handle was just created and is valid.
+                std::mem::transmute(instance.get_device_proc_addr(handle.handle(), name.as_ptr()))
+            }))
+        } else {
+            None
+        };
+
+        let fragment_shading_rate = if fragment_shading_rate_supported {
+            Some(vk::KhrFragmentShadingRateFn::load(|name| unsafe {
+                // SAFETY: Vulkan API call. The device handle was just created and is valid.
+                std::mem::transmute(instance.get_device_proc_addr(handle.handle(), name.as_ptr()))
+            }))
+        } else {
+            None
+        };
+
+        let pageable_device_local_memory = if pageable_memory_supported {
+            Some(vk::ExtPageableDeviceLocalMemoryFn::load(|name| unsafe {
+                // SAFETY: Vulkan API call. The device handle was just created and is valid.
+                std::mem::transmute(instance.get_device_proc_addr(handle.handle(), name.as_ptr()))
+            }))
+        } else {
+            None
+        };
+
+        let performance_query = if performance_query_supported {
+            Some(vk::KhrPerformanceQueryFn::load(|name| unsafe {
+                // SAFETY: Vulkan API call. The device handle was just created and is valid.
+                let function = instance.get_device_proc_addr(handle.handle(), name.as_ptr());
+                // Counter enumeration are physical device functions, which can only be loaded through the instance.
+                let function = function
+                    .or_else(|| instance.loader().get_instance_proc_addr(instance.handle(), name.as_ptr()));
+                std::mem::transmute(function)
+            }))
+        } else {
+            None
+        };
+
+        let video_queue = if video_queue_supported {
+            Some(vk::KhrVideoQueueFn::load(|name| unsafe {
+                // SAFETY: Vulkan API call. The device handle was just created and is valid.
+                let function = instance.get_device_proc_addr(handle.handle(), name.as_ptr());
+                // Capability and format queries are physical device functions, which can only be loaded through the instance.
+                let function = function
+                    .or_else(|| instance.loader().get_instance_proc_addr(instance.handle(), name.as_ptr()));
+                std::mem::transmute(function)
+            }))
+        } else {
+            None
+        };
+
+        let video_decode_queue = if video_decode_queue_supported {
+            Some(vk::KhrVideoDecodeQueueFn::load(|name| unsafe {
+                // SAFETY: Vulkan API call. The device handle was just created and is valid.
+                std::mem::transmute(instance.get_device_proc_addr(handle.handle(), name.as_ptr()))
+            }))
+        } else {
+            None
+        };
+
+        let present_wait = if present_wait_supported {
+            Some(khr::PresentWait::new(instance, &handle))
+        } else {
+            None
+        };
+
+        let display_timing = if display_timing_supported {
+            Some(vk::GoogleDisplayTimingFn::load(|name| unsafe {
+                // SAFETY: Vulkan API call. The device handle was just created and is valid.
+                std::mem::transmute(instance.get_device_proc_addr(handle.handle(), name.as_ptr()))
+            }))
+        } else {
+            None
+        };
+
+        let full_screen_exclusive = if full_screen_exclusive_supported {
+            Some(ext::FullScreenExclusive::new(instance, &handle))
+        } else {
+            None
+        };
+
+        // ash does not provide definitions for this extension, so we load the function pointers ourselves.
+        let host_image_copy = if host_image_copy_supported {
+            HostImageCopyFn::load(|name| unsafe {
+                // SAFETY: Vulkan API call. The device handle was just created and is valid.
+                std::mem::transmute(instance.get_device_proc_addr(handle.handle(), name.as_ptr()))
+            })
+        } else {
+            None
+        };
+        if host_image_copy.is_none() {
+            enabled_extensions.remove(&ExtensionID::HostImageCopy);
+        }
+        let host_image_copy_dst_layouts = if host_image_copy.is_some() {
+            // SAFETY: We have a valid reference to a PhysicalDevice, so handle() is valid.
+            query_copy_dst_layouts(instance, unsafe { physical_device.handle() })
+        } else {
+            vec![]
+        };
+
+        let calibrated_timestamps = if calibrated_timestamps_supported {
+            Some(ext::CalibratedTimestamps::new(unsafe { instance.loader() }, instance))
+        } else {
+            None
+        };
+
+        let calibrateable_time_domains = match &calibrated_timestamps {
+            // SAFETY: Vulkan API call. We have a valid reference to a PhysicalDevice, so handle() is valid.
+            Some(fns) => unsafe { fns.get_physical_device_calibrateable_time_domains(physical_device.handle())? },
+            None => vec![],
+        };
+
         let mut properties2 = vk::PhysicalDeviceProperties2::builder();
 
         let mut accel_properties = if accel_supported {
@@ -331,6 +1448,12 @@ impl Device {
             None
         };
 
+        let mut graphics_pipeline_library_properties = if graphics_pipeline_library_supported {
+            Some(vk::PhysicalDeviceGraphicsPipelineLibraryPropertiesEXT::default())
+        } else {
+            None
+        };
+
         match &mut accel_properties {
             None => {}
             Some(properties) => {
@@ -345,10 +1468,38 @@ impl Device {
             }
         };
 
+        match &mut graphics_pipeline_library_properties {
+            None => {}
+            Some(properties) => {
+                properties2 = properties2.push_next(properties);
+            }
+        };
+
+        let mut conservative_rasterization_properties = if conservative_rasterization_supported {
+            Some(vk::PhysicalDeviceConservativeRasterizationPropertiesEXT::default())
+        } else {
+            None
+        };
+
+        match &mut conservative_rasterization_properties {
+            None => {}
+            Some(properties) => {
+                properties2 = properties2.push_next(properties);
+            }
+        };
+
         unsafe {
             instance.get_physical_device_properties2(physical_device.handle(), &mut properties2)
         };
 
+        // Do not keep pointers into the properties chain around.
+        if let Some(properties) = &mut graphics_pipeline_library_properties {
+            properties.p_next = std::ptr::null_mut();
+        }
+        if let Some(properties) = &mut conservative_rasterization_properties {
+            properties.p_next = std::ptr::null_mut();
+        }
+
         // Create FSR2 context
         #[cfg(feature = "fsr2")]
         let fsr2 = unsafe {
@@ -377,6 +1528,9 @@ impl Device {
 
         let inner = DeviceInner {
             handle,
+            // SAFETY: We have a valid reference to a PhysicalDevice, so handle() is valid.
+            physical_device: unsafe { physical_device.handle() },
+            instance_fns_1_1: instance.fp_v1_1().clone(),
             queue_families: queue_create_infos
                 .iter()
                 .map(|info| info.queue_family_index)
@@ -385,10 +1539,42 @@ impl Device {
             accel_structure_properties: accel_properties,
             rt_properties,
             extensions: enabled_extensions,
+            features,
+            features_1_1: enabled_features_1_1,
+            features_1_2: enabled_features_1_2,
             dynamic_state3,
+            dynamic_state3_features: enabled_dynamic_state3,
             acceleration_structure,
             rt_pipeline,
+            rt_pipeline_features: enabled_rt_pipeline,
+            rt_maintenance1,
+            rt_maintenance1_features: enabled_rt_maintenance1,
+            opacity_micromap,
+            mesh_shader,
+            mesh_shader_features: enabled_mesh_shader,
+            shader_object,
+            conditional_rendering,
+            fragment_shading_rate,
+            fragment_shading_rate_features: enabled_fragment_shading_rate,
+            custom_border_color_features: enabled_custom_border_color,
+            graphics_pipeline_library_properties,
+            conservative_rasterization_properties,
+            pageable_device_local_memory,
+            performance_query,
+            calibrated_timestamps,
+            calibrateable_time_domains,
+            video_queue,
+            video_decode_queue,
+            present_wait,
+            display_timing,
+            full_screen_exclusive,
+            host_image_copy,
+            host_image_copy_dst_layouts,
             debug_utils,
+            memory_tracker: MemoryTracker::default(),
+            lost: AtomicBool::new(false),
+            device_lost_handlers: Mutex::new(Vec::new()),
+            submission_queues: Mutex::new(Vec::new()),
             #[cfg(feature = "fsr2")]
             fsr2_context: Mutex::new(fsr2),
         };
@@ -420,7 +1606,73 @@ impl Device {
     /// }
     /// ```
     pub fn wait_idle(&self) -> Result<()> {
-        unsafe { Ok(self.inner.handle.device_wait_idle()?) }
+        unsafe { Ok(self.check_device_lost(self.inner.handle.device_wait_idle())?) }
+    }
+
+    /// Register a queue that work can be submitted to, see [`Device::wait_for_submitted_work()`].
+    pub(crate) fn register_queue(&self, queue: &Arc<Mutex<DeviceQueue>>) {
+        let mut queues = self.inner.submission_queues.lock().unwrap();
+        queues.retain(|registered| registered.strong_count() > 0);
+        if !queues.iter().any(|registered| registered.as_ptr() == Arc::as_ptr(queue)) {
+            queues.push(Arc::downgrade(queue));
+        }
+    }
+
+    /// Wait for all work that was submitted to the queues of this device so far, before destroying objects that might still be
+    /// in use by it. Unlike [`Device::wait_idle()`], work submitted after this call is not waited on.
+    /// Since this is called from `Drop` implementations, errors are logged instead of returned.
+    pub(crate) fn wait_for_submitted_work(&self) {
+        let queues = self
+            .inner
+            .submission_queues
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(Weak::upgrade)
+            .collect::<Vec<_>>();
+        // Submit all fences first, so the queues are waited on in parallel.
+        let fences = queues
+            .iter()
+            .map(|queue| queue.lock().map_err(|_| Error::PoisonError)?.submitted_work_fence(self))
+            .collect::<Result<Vec<_>>>();
+        let result = fences.and_then(|fences| {
+            fences
+                .into_iter()
+                .try_for_each(|mut fence| fence.wait().map(|_| ()))
+        });
+        if let Err(e) = result {
+            error!("Failed to wait for submitted work during teardown: {e}");
+        }
+    }
+
+    /// Register a function that is called when device loss is first observed, before the error is returned to the caller.
+    /// This can be used to collect diagnostics such as GPU crash dumps. Device loss is detected on queue submission, fence waits,
+    /// presentation and [`Device::wait_idle()`].
+    pub fn on_device_lost(&self, handler: impl Fn() + Send + Sync + 'static) {
+        self.inner
+            .device_lost_handlers
+            .lock()
+            .unwrap()
+            .push(Arc::new(handler));
+    }
+
+    /// Whether this device was lost. A lost device cannot be recovered, all objects created from it must be recreated
+    /// on a new device.
+    pub fn is_lost(&self) -> bool {
+        self.inner.lost.load(Ordering::Acquire)
+    }
+
+    /// Check the result of a Vulkan call for device loss, and run the device lost handlers the first time it is observed.
+    pub(crate) fn check_device_lost<T>(&self, result: VkResult<T>) -> VkResult<T> {
+        if matches!(result, Err(vk::Result::ERROR_DEVICE_LOST)) && !self.inner.lost.swap(true, Ordering::AcqRel) {
+            // Handlers are called without holding the lock, so they can register new handlers.
+            let handlers = self.inner.device_lost_handlers.lock().unwrap().clone();
+            error!("Device lost, running {} device lost handlers.", handlers.len());
+            for handler in handlers.iter() {
+                handler();
+            }
+        }
+        result
     }
 
     /// Get unsafe access to the underlying `VkDevice` handle
@@ -460,6 +1712,12 @@ impl Device {
         &self.inner.properties
     }
 
+    /// Whether this device is a software implementation such as llvmpipe or SwiftShader. Can be used to
+    /// scale down workloads or skip expensive features when running on a CPU implementation.
+    pub fn is_software_implementation(&self) -> bool {
+        is_software_implementation(&self.inner.properties)
+    }
+
     /// Get the physical device properties related to acceleration structures.
     ///
     /// # Errors
@@ -481,6 +1739,51 @@ impl Device {
         Ok(self.inner.rt_properties.as_ref().unwrap())
     }
 
+    /// Get the physical device properties related to graphics pipeline libraries.
+    /// # Errors
+    /// - Fails if [`ExtensionID::GraphicsPipelineLibrary`] is not enabled.
+    pub fn graphics_pipeline_library_properties(
+        &self,
+    ) -> Result<&vk::PhysicalDeviceGraphicsPipelineLibraryPropertiesEXT> {
+        self.require_extension(ExtensionID::GraphicsPipelineLibrary)?;
+        Ok(self.inner.graphics_pipeline_library_properties.as_ref().unwrap())
+    }
+
+    /// Get the physical device properties related to conservative rasterization.
+    /// # Errors
+    /// - Fails if [`ExtensionID::ConservativeRasterization`] is not enabled.
+    pub fn conservative_rasterization_properties(
+        &self,
+    ) -> Result<&vk::PhysicalDeviceConservativeRasterizationPropertiesEXT> {
+        self.require_extension(ExtensionID::ConservativeRasterization)?;
+        Ok(self.inner.conservative_rasterization_properties.as_ref().unwrap())
+    }
+
+    /// Get the Vulkan 1.0 features that were enabled on this device. Some of these are enabled automatically
+    /// when they are supported, such as `tessellationShader`.
+    pub fn features(&self) -> &vk::PhysicalDeviceFeatures {
+        &self.inner.features
+    }
+
+    /// Get the Vulkan 1.1 features that were enabled on this device. Some of these are enabled automatically
+    /// when they are supported, such as `multiview`.
+    pub fn features_1_1(&self) -> &vk::PhysicalDeviceVulkan11Features {
+        &self.inner.features_1_1
+    }
+
+    /// Get the Vulkan 1.2 features that were enabled on this device. Some of these are enabled automatically
+    /// when they are supported, such as `drawIndirectCount`.
+    /// # Example
+    /// ```
+    /// # use phobos::*;
+    /// fn supports_indirect_count(device: Device) -> bool {
+    ///     device.features_1_2().draw_indirect_count == vk::TRUE
+    /// }
+    /// ```
+    pub fn features_1_2(&self) -> &vk::PhysicalDeviceVulkan12Features {
+        &self.inner.features_1_2
+    }
+
     /// Get access to the functions of VK_EXT_debug_utils
     /// # Errors
     /// - Fails if validation layers are disabled
@@ -539,6 +1842,12 @@ impl Device {
         self.inner.dynamic_state3.as_ref()
     }
 
+    /// Get the extended dynamic state 3 features that were enabled on this device. Only the polygon mode and color blend
+    /// enable states are requested. All features are `VK_FALSE` if [`ExtensionID::ExtendedDynamicState3`] is not enabled.
+    pub fn dynamic_state3_features(&self) -> &vk::PhysicalDeviceExtendedDynamicState3FeaturesEXT {
+        &self.inner.dynamic_state3_features
+    }
+
     /// Access to the function pointers for `VK_KHR_acceleration_structure`
     ///
     /// Returns `None` if the extension is not enabled
@@ -553,6 +1862,256 @@ impl Device {
         self.inner.rt_pipeline.as_ref()
     }
 
+    /// Get the raytracing pipeline features that were enabled on this device. All features are `VK_FALSE` if
+    /// [`ExtensionID::RayTracingPipeline`] is not enabled.
+    pub fn raytracing_pipeline_features(&self) -> &vk::PhysicalDeviceRayTracingPipelineFeaturesKHR {
+        &self.inner.rt_pipeline_features
+    }
+
+    /// Access to the function pointers for `VK_KHR_ray_tracing_maintenance1`
+    ///
+    /// Returns `None` if the extension is not enabled
+    pub fn raytracing_maintenance1(&self) -> Option<&khr::RayTracingMaintenance1> {
+        self.inner.rt_maintenance1.as_ref()
+    }
+
+    /// Get the `VK_KHR_ray_tracing_maintenance1` features that were enabled on this device. All features are `VK_FALSE` if
+    /// [`ExtensionID::RayTracingMaintenance1`] is not enabled.
+    pub fn raytracing_maintenance1_features(&self) -> &vk::PhysicalDeviceRayTracingMaintenance1FeaturesKHR {
+        &self.inner.rt_maintenance1_features
+    }
+
+    /// Access to the function pointers for `VK_EXT_mesh_shader`
+    ///
+    /// Returns `None` if the extension is not enabled
+    pub fn mesh_shader(&self) -> Option<&ext::MeshShader> {
+        self.inner.mesh_shader.as_ref()
+    }
+
+    /// Get the mesh shader features that were enabled on this device. All features are `VK_FALSE` if
+    /// [`ExtensionID::MeshShader`] is not enabled.
+    pub fn mesh_shader_features(&self) -> &vk::PhysicalDeviceMeshShaderFeaturesEXT {
+        &self.inner.mesh_shader_features
+    }
+
+    /// Access to the function pointers for `VK_EXT_shader_object`
+    ///
+    /// Returns `None` if the extension is not enabled
+    pub fn shader_object(&self) -> Option<&ext::ShaderObject> {
+        self.inner.shader_object.as_ref()
+    }
+
+    /// Access to the function pointers for `VK_EXT_opacity_micromap`
+    ///
+    /// Returns `None` if the extension is not enabled
+    pub fn opacity_micromap(&self) -> Option<&vk::ExtOpacityMicromapFn> {
+        self.inner.opacity_micromap.as_ref()
+    }
+
+    /// Access to the function pointers for `VK_EXT_conditional_rendering`
+    ///
+    /// Returns `None` if the extension is not enabled
+    pub fn conditional_rendering(&self) -> Option<&vk::ExtConditionalRenderingFn> {
+        self.inner.conditional_rendering.as_ref()
+    }
+
+    /// Access to the function pointers for `VK_KHR_fragment_shading_rate`
+    ///
+    /// Returns `None` if the extension is not enabled
+    pub fn fragment_shading_rate(&self) -> Option<&vk::KhrFragmentShadingRateFn> {
+        self.inner.fragment_shading_rate.as_ref()
+    }
+
+    /// Get the fragment shading rate features that were enabled on this device. All features are `VK_FALSE` if
+    /// [`ExtensionID::FragmentShadingRate`] is not enabled.
+    pub fn fragment_shading_rate_features(&self) -> &vk::PhysicalDeviceFragmentShadingRateFeaturesKHR {
+        &self.inner.fragment_shading_rate_features
+    }
+
+    /// Get the custom border color features that were enabled on this device. All features are `VK_FALSE` if
+    /// [`ExtensionID::CustomBorderColor`] is not enabled.
+    pub fn custom_border_color_features(&self) -> &vk::PhysicalDeviceCustomBorderColorFeaturesEXT {
+        &self.inner.custom_border_color_features
+    }
+
+    /// Access to the function pointers for `VK_KHR_performance_query`
+    ///
+    /// Returns `None` if the extension is not enabled
+    pub fn performance_query(&self) -> Option<&vk::KhrPerformanceQueryFn> {
+        self.inner.performance_query.as_ref()
+    }
+
+    /// Access to the function pointers for `VK_EXT_calibrated_timestamps`
+    ///
+    /// Returns `None` if the extension is not enabled
+    pub fn calibrated_timestamps(&self) -> Option<&ext::CalibratedTimestamps> {
+        self.inner.calibrated_timestamps.as_ref()
+    }
+
+    /// Access to the function pointers for `VK_KHR_video_queue`
+    ///
+    /// Returns `None` if the extension is not enabled
+    pub fn video_queue(&self) -> Option<&vk::KhrVideoQueueFn> {
+        self.inner.video_queue.as_ref()
+    }
+
+    /// Access to the function pointers for `VK_KHR_video_decode_queue`
+    ///
+    /// Returns `None` if the extension is not enabled
+    pub fn video_decode_queue(&self) -> Option<&vk::KhrVideoDecodeQueueFn> {
+        self.inner.video_decode_queue.as_ref()
+    }
+
+    /// Access to the function pointers for `VK_KHR_present_wait`
+    ///
+    /// Returns `None` if the extension is not enabled
+    pub fn present_wait(&self) -> Option<&khr::PresentWait> {
+        self.inner.present_wait.as_ref()
+    }
+
+    /// Access to the function pointers for `VK_GOOGLE_display_timing`
+    ///
+    /// Returns `None` if the extension is not enabled
+    pub fn display_timing(&self) -> Option<&vk::GoogleDisplayTimingFn> {
+        self.inner.display_timing.as_ref()
+    }
+
+    /// Access to the function pointers for `VK_EXT_full_screen_exclusive`
+    ///
+    /// Returns `None` if the extension is not enabled
+    pub fn full_screen_exclusive(&self) -> Option<&ext::FullScreenExclusive> {
+        self.inner.full_screen_exclusive.as_ref()
+    }
+
+    /// Access to the function pointers for `VK_EXT_host_image_copy`
+    ///
+    /// Returns `None` if the extension is not enabled
+    pub(crate) fn host_image_copy(&self) -> Option<&HostImageCopyFn> {
+        self.inner.host_image_copy.as_ref()
+    }
+
+    /// Get the image layouts that can be used as the destination of host image copies. Empty if
+    /// [`ExtensionID::HostImageCopy`] is not enabled.
+    pub(crate) fn host_image_copy_dst_layouts(&self) -> &[vk::ImageLayout] {
+        &self.inner.host_image_copy_dst_layouts
+    }
+
+    /// Get the time domains that can be used to calibrate timestamps on this device. Empty if
+    /// [`ExtensionID::CalibratedTimestamps`] is not enabled.
+    pub fn calibrateable_time_domains(&self) -> &[vk::TimeDomainEXT] {
+        &self.inner.calibrateable_time_domains
+    }
+
+    /// Access to the function pointers for `VK_EXT_pageable_device_local_memory`
+    ///
+    /// Returns `None` if the extension is not enabled
+    pub fn pageable_device_local_memory(&self) -> Option<&vk::ExtPageableDeviceLocalMemoryFn> {
+        self.inner.pageable_device_local_memory.as_ref()
+    }
+
+    /// Suggest a new residency priority for a block of device memory. Memory with a higher priority is less likely to be
+    /// demoted to system memory when device local memory is oversubscribed. The priority must be in the range `[0.0, 1.0]`,
+    /// with `0.5` being the default priority. See also [`vkSetDeviceMemoryPriorityEXT`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkSetDeviceMemoryPriorityEXT.html)
+    ///
+    /// Note that allocators may place multiple resources inside the same `VkDeviceMemory` block, so this changes the priority of
+    /// every resource sharing this memory.
+    /// # Errors
+    /// * Fails if [`ExtensionID::PageableDeviceLocalMemory`] is not enabled.
+    /// * Fails if the priority is outside the `[0.0, 1.0]` range.
+    /// # Safety
+    /// * `memory` must be a valid `VkDeviceMemory` handle allocated from this device.
+    pub unsafe fn set_memory_priority(&self, memory: vk::DeviceMemory, priority: f32) -> Result<()> {
+        self.require_extension(ExtensionID::PageableDeviceLocalMemory)?;
+        if !(0.0..=1.0).contains(&priority) {
+            return Err(Error::InvalidMemoryPriority(priority).into());
+        }
+        let fns = self.pageable_device_local_memory().unwrap();
+        // SAFETY: Vulkan API call. This function pointer is not null because we just verified its availability.
+        // The caller guarantees that the memory handle is valid.
+        (fns.set_device_memory_priority_ext)(self.inner.handle.handle(), memory, priority);
+        Ok(())
+    }
+
+    /// Query the memory usage and budget of every memory heap. The usage includes memory allocated by other processes.
+    /// Allocating more memory than the budget of a heap may fail or cause memory to be moved to a slower heap.
+    /// # Errors
+    /// * Fails if [`ExtensionID::MemoryBudget`] is not enabled.
+    pub fn memory_budget(&self) -> Result<Vec<HeapBudget>> {
+        self.require_extension(ExtensionID::MemoryBudget)?;
+        let mut budget = vk::PhysicalDeviceMemoryBudgetPropertiesEXT::default();
+        let mut properties = vk::PhysicalDeviceMemoryProperties2 {
+            p_next: &mut budget as *mut _ as *mut std::ffi::c_void,
+            ..Default::default()
+        };
+        // SAFETY: Vulkan API call. The physical device handle is valid for as long as the instance is, and the budget struct
+        // outlives this call.
+        unsafe {
+            (self.inner.instance_fns_1_1.get_physical_device_memory_properties2)(self.inner.physical_device, &mut properties);
+        }
+        let memory = &properties.memory_properties;
+        Ok(memory.memory_heaps[..memory.memory_heap_count as usize]
+            .iter()
+            .enumerate()
+            .map(|(index, heap)| HeapBudget {
+                heap_index: index as u32,
+                flags: heap.flags,
+                size: heap.size,
+                usage: budget.heap_usage[index],
+                budget: budget.heap_budget[index],
+            })
+            .collect())
+    }
+
+    /// Get the memory types and heaps of the physical device.
+    pub(crate) fn memory_properties(&self) -> vk::PhysicalDeviceMemoryProperties {
+        let mut properties = vk::PhysicalDeviceMemoryProperties2::default();
+        // SAFETY: Vulkan API call. The physical device handle is valid for as long as the instance is.
+        unsafe {
+            (self.inner.instance_fns_1_1.get_physical_device_memory_properties2)(self.inner.physical_device, &mut properties);
+        }
+        properties.memory_properties
+    }
+
+    /// Query the features `format` supports with linear and optimal tiling, and in buffers.
+    pub fn format_properties(&self, format: vk::Format) -> vk::FormatProperties {
+        let mut properties = vk::FormatProperties2::default();
+        // SAFETY: Vulkan API call. The physical device handle is valid for as long as the instance is.
+        unsafe {
+            (self.inner.instance_fns_1_1.get_physical_device_format_properties2)(
+                self.inner.physical_device,
+                format,
+                &mut properties,
+            );
+        }
+        properties.format_properties
+    }
+
+    /// Query the extended format features of `format`, which include features that do not fit in [`vk::FormatFeatureFlags`].
+    pub(crate) fn format_features2(&self, format: vk::Format) -> vk::FormatProperties3 {
+        let mut features = vk::FormatProperties3::default();
+        let mut properties = vk::FormatProperties2::builder().push_next(&mut features);
+        // SAFETY: Vulkan API call. The physical device handle is valid for as long as the instance is.
+        unsafe {
+            (self.inner.instance_fns_1_1.get_physical_device_format_properties2)(
+                self.inner.physical_device,
+                format,
+                &mut *properties,
+            );
+        }
+        features.p_next = std::ptr::null_mut();
+        features
+    }
+
+    /// Get the number and total size of all live buffer and image allocations on this device, per [`MemoryCategory`](crate::MemoryCategory).
+    pub fn allocation_statistics(&self) -> AllocationStatistics {
+        self.inner.memory_tracker.statistics()
+    }
+
+    /// Get the tracker that buffers and images report their allocations to.
+    pub(crate) fn memory_tracker(&self) -> &MemoryTracker {
+        &self.inner.memory_tracker
+    }
+
     /// True we only have a single queue, and thus the sharing mode for resources is always `VK_SHARING_MODE_EXCLUSIVE`.
     /// Not extremely useful on the user side, but maybe you want to know whether one physical queue is being multiplexed
     /// behind your back.
@@ -601,6 +2160,11 @@ impl Drop for DeviceInner {
         #[cfg(feature = "log-objects")]
         trace!("Destroying VkDevice {:p}", self.handle.handle());
         unsafe {
+            // Every object created from this device holds a reference to it, so this is the last object to be destroyed.
+            // We still need to make sure no work is pending on the GPU.
+            if let Err(e) = self.handle.device_wait_idle() {
+                error!("Failed to wait for device idle during teardown: {e}");
+            }
             self.handle.destroy_device(None);
         }
     }
@@ -8,6 +8,7 @@ use gpu_allocator::AllocationError;
 use thiserror::Error;
 
 use crate::core::device::ExtensionID;
+use crate::pipeline::shader::ShaderCompileError;
 
 /// Error type that phobos can return.
 #[derive(Error, Debug)]
@@ -73,14 +74,108 @@ pub enum Error {
     #[error("Poisoned mutex")]
     PoisonError,
     /// Buffer view out of range of original buffer
-    #[error("Buffer view is not a valid range in the parent buffer.")]
-    BufferViewOutOfRange,
-    /// Buffer copy between views of different sizes is not allowed.
-    #[error("Buffer copy has invalid buffer views as range.")]
-    InvalidBufferCopy,
+    #[error("Buffer range [{offset}, {offset} + {size}) is out of range of buffer with size {buffer_size}.")]
+    BufferViewOutOfRange {
+        /// Offset of the requested range.
+        offset: u64,
+        /// Size of the requested range.
+        size: u64,
+        /// Size of the buffer or view the range was taken from.
+        buffer_size: u64,
+    },
+    /// Buffer copy between views of different sizes is not allowed, and the buffer view of a copy between a buffer and an image
+    /// must be large enough for the image region.
+    #[error("Invalid copy from a range of {src_size} bytes to a range of {dst_size} bytes. Buffer copies must have the same size, and buffers copied to or from images must hold the entire image region.")]
+    InvalidBufferCopy {
+        /// Size of the source range.
+        src_size: u64,
+        /// Size of the destination range.
+        dst_size: u64,
+    },
+    /// Indirect command parameters are misaligned or do not fit inside the buffer view they are read from.
+    #[error("Invalid indirect buffer range: {count} commands of {command_size} bytes at offset {offset} with stride {stride} in a buffer view of {buffer_size} bytes. The offset and stride must be a multiple of 4, the stride must be at least the command size, and all commands must fit inside the buffer view.")]
+    InvalidIndirectBuffer {
+        /// Offset of the first command in the buffer view.
+        offset: u64,
+        /// Stride between consecutive commands.
+        stride: u32,
+        /// Number of commands read from the buffer.
+        count: u32,
+        /// Size of a single command.
+        command_size: u64,
+        /// Size of the buffer view.
+        buffer_size: u64,
+    },
+    /// Image region does not fit inside the image it refers to.
+    #[error("Image region is out of range of the image view's base mip level.")]
+    ImageRegionOutOfRange,
+    /// Arithmetic on a [`DeviceSize`](crate::util::device_size::DeviceSize) overflowed.
+    #[error("Device size overflow while computing {lhs} {op} {rhs}.")]
+    DeviceSizeOverflow {
+        /// Left hand side of the operation.
+        lhs: u64,
+        /// The operation that overflowed.
+        op: &'static str,
+        /// Right hand side of the operation.
+        rhs: u64,
+    },
+    /// A range of queries overflowed the query index type.
+    #[error("Query range of {count} queries starting at query {first} overflows.")]
+    QueryRangeOverflow {
+        /// Index of the first query in the range.
+        first: u32,
+        /// Number of queries in the range.
+        count: u32,
+    },
+    /// Buffer fill or update with an invalid range.
+    #[error("Invalid buffer fill or update. Offset and size must be a multiple of 4, and updates are limited to 65536 bytes.")]
+    InvalidBufferUpdate,
     /// Mappable buffer expected
     #[error("Requested mappable buffer, but buffer does not have a memory map")]
     UnmappableBuffer,
+    /// Mapped memory cannot be viewed as a slice of the requested element type.
+    #[error("Mapped buffer range at offset {offset} with size {size} cannot be viewed as a slice of elements with size {element_size} and alignment {element_align}.")]
+    InvalidMappedSlice {
+        /// Offset of the buffer view into its buffer.
+        offset: u64,
+        /// Size of the buffer view.
+        size: u64,
+        /// Size of the requested element type.
+        element_size: usize,
+        /// Alignment of the requested element type.
+        element_align: usize,
+    },
+    /// Resource does not fit in aliased memory at the requested offset, or the offset is not aligned.
+    #[error("Resource with size {size} and alignment {alignment} cannot be placed at offset {offset} in aliased memory with size {memory_size}.")]
+    InvalidPlacement {
+        /// Requested offset into the aliased memory.
+        offset: u64,
+        /// Size of the resource's memory requirements.
+        size: u64,
+        /// Alignment of the resource's memory requirements.
+        alignment: u64,
+        /// Size of the aliased memory.
+        memory_size: u64,
+    },
+    /// Aliased memory was allocated with memory types that cannot back the placed resource.
+    #[error("Aliased memory was not allocated from a memory type supported by the placed resource.")]
+    IncompatibleAliasedMemory,
+    /// Sparse binding operation on a resource that was not created as a sparse resource.
+    #[error("Resource is not a sparse resource.")]
+    NotSparse,
+    /// Sparse memory bind is not aligned to the sparse page size, or is outside of the resource or the sparse memory.
+    #[error("Sparse bind of {size} bytes at offset {offset} is invalid for page size {page_size} and resource size {resource_size}.")]
+    InvalidSparseBind {
+        /// Offset into the resource in bytes. For image region binds, this is the offset of the first page of the region within
+        /// its mip level.
+        offset: u64,
+        /// Size of the bound range in bytes.
+        size: u64,
+        /// Size of a sparse page of the resource.
+        page_size: u64,
+        /// Size of the resource's memory requirements. For image region binds, this is the size of the pages of the mip level.
+        resource_size: u64,
+    },
     /// Shader needs an entry point named `main`.
     #[error("Shader does not have an entry point.")]
     NoEntryPoint,
@@ -93,6 +188,9 @@ pub enum Error {
     /// Tried to look up descriptor binding in reflection info, but does not exist.
     #[error("Descriptor `{0}` does not exist.")]
     NoBinding(String),
+    /// The reflected type of a shader resource does not match the kind of resource it was listed as.
+    #[error("Shader resource `{0}` has an unexpected type.")]
+    UnexpectedResourceType(String),
     /// Returned from [`ExecutionManager::try_on_domain()`](crate::ExecutionManager::try_on_domain) to indicate the queue is currently locked.
     #[error("Returned as a result from ExecutionManager::try_on_domain to indicate the queue is currently locked.")]
     QueueLocked,
@@ -111,6 +209,50 @@ pub enum Error {
     /// Function call requires extension to be enabled, but this extension was not requested or not available.
     #[error("Extension {0} required for this feature, but not enabled.")]
     ExtensionNotSupported(ExtensionID),
+    /// Function call requires a device feature to be enabled, but this feature was not requested or not supported.
+    #[error("Device feature {0} required for this feature, but not enabled.")]
+    FeatureNotSupported(&'static str),
+    /// Memory priorities must be in the range `[0.0, 1.0]`.
+    #[error("Memory priority {0} is outside of the valid range [0.0, 1.0].")]
+    InvalidMemoryPriority(f32),
+    /// Descriptor write to a persistent descriptor set that does not match its set layout.
+    #[error("Invalid descriptor write to binding {binding}, element {element}. The binding does not exist, has a different descriptor type or is too small.")]
+    InvalidDescriptorWrite {
+        /// Binding that was written to.
+        binding: u32,
+        /// Array element that was written to.
+        element: u32,
+    },
+    /// A command buffer recorded with [`vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT`](ash::vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT) was submitted more than once.
+    #[error("Command buffer was recorded with ONE_TIME_SUBMIT, but was submitted more than once.")]
+    CommandBufferResubmitted,
+    /// A command buffer that is still pending execution was submitted again, but was not recorded with
+    /// [`vk::CommandBufferUsageFlags::SIMULTANEOUS_USE`](ash::vk::CommandBufferUsageFlags::SIMULTANEOUS_USE).
+    #[error("Command buffer is still pending execution and was not recorded with SIMULTANEOUS_USE.")]
+    CommandBufferPending,
+    /// Compiling a shader from source failed. Contains every error reported by the compiler.
+    #[error("Shader compilation failed:\n{}", .0.iter().map(|error| error.to_string()).collect::<Vec<_>>().join("\n"))]
+    ShaderCompilationFailed(Vec<ShaderCompileError>),
+    /// The logical device was lost, see [`vk::Result::ERROR_DEVICE_LOST`](ash::vk::Result::ERROR_DEVICE_LOST). All resources
+    /// created from it must be recreated on a new device.
+    #[error("The device was lost.")]
+    DeviceLost,
+    /// Pass constants were requested, but the pass graph being recorded does not have them enabled.
+    #[error("Pass constants are not available. Enable them with BuiltPassGraph::set_frame_index().")]
+    PassConstantsNotEnabled,
+    /// Push constant update that does not match the push constant ranges of the bound pipeline.
+    #[error("Invalid push constant update of {size} bytes at offset {offset} for stages {stage:?}. Every byte must be part of a push constant range of the bound pipeline for each of these stages, and the stages must include all stages of every range that is written to.")]
+    InvalidPushConstantRange {
+        /// Stages the push constants were written for.
+        stage: ash::vk::ShaderStageFlags,
+        /// Byte offset of the update.
+        offset: u32,
+        /// Size of the update in bytes.
+        size: u32,
+    },
+    /// Image readback from an image with a format that can not be converted to RGBA8.
+    #[error("Image format {0:?} is not supported for readback to RGBA8.")]
+    UnsupportedReadbackFormat(ash::vk::Format),
     /// Uncategorized error.
     #[error("Uncategorized error: `{0}`")]
     Uncategorized(&'static str),
@@ -1,16 +1,85 @@
 //! Exposes the Vulkan instance, which represents the loaded Vulkan library
 
-use std::ffi::{CStr, CString};
+use std::ffi::{c_char, c_void, CStr, CString};
 use std::ops::Deref;
 use std::str::FromStr;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use ash;
 use ash::vk;
 
-use crate::{AppSettings, WindowInterface};
+use crate::{AppSettings, DeviceProfile, WindowInterface};
 use crate::util::string::unwrap_to_raw_strings;
 
+// `VK_EXT_layer_settings` is newer than the Vulkan headers ash was generated from, so its structures are declared here.
+const LAYER_SETTINGS_CREATE_INFO_EXT: vk::StructureType = vk::StructureType::from_raw(1000496000);
+const LAYER_SETTING_TYPE_STRING_EXT: i32 = 7;
+
+#[repr(C)]
+struct LayerSettingEXT {
+    p_layer_name: *const c_char,
+    p_setting_name: *const c_char,
+    ty: i32,
+    value_count: u32,
+    p_values: *const c_void,
+}
+
+#[repr(C)]
+struct LayerSettingsCreateInfoEXT {
+    s_type: vk::StructureType,
+    p_next: *const c_void,
+    setting_count: u32,
+    p_settings: *const LayerSettingEXT,
+}
+
+/// Settings for `VK_LAYER_KHRONOS_profiles`, passed to the layer with `VK_EXT_layer_settings`. All pointers point into the
+/// heap allocations owned by this struct, so it can be moved freely.
+struct ProfileLayerSettings {
+    _strings: Vec<(CString, CString)>,
+    _values: Vec<*const c_char>,
+    settings: Vec<LayerSettingEXT>,
+}
+
+impl ProfileLayerSettings {
+    fn new(layer: &CStr, profile: &DeviceProfile) -> Result<Self> {
+        let file = profile
+            .file
+            .to_str()
+            .ok_or_else(|| anyhow!("Device profile path {} is not valid UTF-8", profile.file.display()))?;
+        let mut strings = vec![(CString::new("profile_file")?, CString::new(file)?)];
+        if let Some(name) = &profile.name {
+            strings.push((CString::new("profile_name")?, CString::new(name.as_str())?));
+        }
+        // String settings point to an array of string pointers, each setting here has a single value.
+        let values = strings.iter().map(|(_, value)| value.as_ptr()).collect::<Vec<_>>();
+        let settings = strings
+            .iter()
+            .zip(&values)
+            .map(|((setting, _), value)| LayerSettingEXT {
+                p_layer_name: layer.as_ptr(),
+                p_setting_name: setting.as_ptr(),
+                ty: LAYER_SETTING_TYPE_STRING_EXT,
+                value_count: 1,
+                p_values: value as *const *const c_char as *const c_void,
+            })
+            .collect();
+        Ok(Self {
+            _strings: strings,
+            _values: values,
+            settings,
+        })
+    }
+
+    fn create_info(&self) -> LayerSettingsCreateInfoEXT {
+        LayerSettingsCreateInfoEXT {
+            s_type: LAYER_SETTINGS_CREATE_INFO_EXT,
+            p_next: std::ptr::null(),
+            setting_count: self.settings.len() as u32,
+            p_settings: self.settings.as_ptr(),
+        }
+    }
+}
+
 /// Represents the loaded vulkan instance.
 /// You need to create this to initialize the Vulkan API. This is used
 /// to create the device from.
@@ -21,6 +90,8 @@ pub struct Instance {
     entry: ash::Entry,
     #[derivative(Debug = "ignore")]
     instance: ash::Instance,
+    /// Whether `VK_KHR_get_surface_capabilities2` was enabled.
+    surface_capabilities2: bool,
 }
 
 impl Instance {
@@ -28,18 +99,25 @@ impl Instance {
     /// # Errors
     /// * Can fail if the Vulkan loader was not found. Check for valid Vulkan drivers.
     /// * Can fail if an instance extension or layer was requested that is not supported. This can happen when
-    ///   validation is enabled through [`AppSettings`], but the Vulkan SDK is not installed.
+    ///   validation or a device profile is enabled through [`AppSettings`], but the Vulkan SDK is not installed.
     pub fn new<Window: WindowInterface>(settings: &AppSettings<Window>) -> Result<Self> {
         let entry = unsafe { ash::Entry::load()? };
-        let instance = create_vk_instance(&entry, settings)?;
+        let (instance, surface_capabilities2) = create_vk_instance(&entry, settings)?;
         #[cfg(feature = "log-objects")]
         trace!("Created new VkInstance {:p}", instance.handle());
         Ok(Instance {
             entry,
             instance,
+            surface_capabilities2,
         })
     }
 
+    /// Whether `VK_KHR_get_surface_capabilities2` is enabled on this instance. This is only requested when exclusive
+    /// fullscreen is requested in the [`AppSettings`].
+    pub(crate) fn surface_capabilities2_enabled(&self) -> bool {
+        self.surface_capabilities2
+    }
+
     /// Get unsafe access to the vulkan entry point.
     /// # Safety
     /// Any vulkan calls that modify the system's state may put the system in an undefined state.
@@ -69,7 +147,7 @@ impl Deref for Instance {
 fn create_vk_instance<Window: WindowInterface>(
     entry: &ash::Entry,
     settings: &AppSettings<Window>,
-) -> Result<ash::Instance> {
+) -> Result<(ash::Instance, bool)> {
     let app_name = CString::new(settings.name.clone())?;
     let engine_name = CString::new("Phobos")?;
     let app_info = vk::ApplicationInfo {
@@ -93,6 +171,43 @@ fn create_vk_instance<Window: WindowInterface>(
         extensions.push(CString::from(ash::extensions::ext::DebugUtils::name()));
     }
 
+    // The profiles layer must come after the validation layer, so validation checks against the simulated device.
+    let profiles_layer = CString::new("VK_LAYER_KHRONOS_profiles")?;
+    let mut profile_settings = None;
+    if let Some(profile) = &settings.device_profile {
+        // Older versions of the layer only read their configuration from the environment.
+        let layer_settings_name = c"VK_EXT_layer_settings";
+        let supports_layer_settings = entry
+            .enumerate_instance_extension_properties(Some(&profiles_layer))
+            .unwrap_or_default()
+            .iter()
+            .any(|ext| unsafe { CStr::from_ptr(ext.extension_name.as_ptr()) } == layer_settings_name);
+        if supports_layer_settings {
+            extensions.push(CString::from(layer_settings_name));
+            profile_settings = Some(ProfileLayerSettings::new(&profiles_layer, profile)?);
+        } else {
+            warn!(
+                "VK_LAYER_KHRONOS_profiles does not support VK_EXT_layer_settings, set VK_KHRONOS_PROFILES_PROFILE_FILE and \
+                 VK_KHRONOS_PROFILES_PROFILE_NAME in the environment to select the profile"
+            );
+        }
+        layers.push(profiles_layer.clone());
+        info!("Simulating device profile from {}", profile.file.display());
+    }
+
+    // Exclusive fullscreen is requested through the extended surface capability queries.
+    let mut surface_capabilities2 = false;
+    if settings.window.is_some() && settings.full_screen_exclusive.is_some() {
+        let name = vk::KhrGetSurfaceCapabilities2Fn::name();
+        surface_capabilities2 = entry
+            .enumerate_instance_extension_properties(None)?
+            .iter()
+            .any(|ext| unsafe { CStr::from_ptr(ext.extension_name.as_ptr()) } == name);
+        if surface_capabilities2 {
+            extensions.push(CString::from(name));
+        }
+    }
+
     info!("Enabled instance extensions:");
     for ext in &extensions {
         info!("{:?}", ext);
@@ -110,11 +225,15 @@ fn create_vk_instance<Window: WindowInterface>(
     let layers_raw = unwrap_to_raw_strings(layers.as_slice());
     let extensions_raw = unwrap_to_raw_strings(extensions.as_slice());
 
-    let instance_info = vk::InstanceCreateInfo::builder()
+    let layer_settings = profile_settings.as_ref().map(ProfileLayerSettings::create_info);
+    let mut instance_info = vk::InstanceCreateInfo::builder()
         .application_info(&app_info)
         .enabled_layer_names(layers_raw.as_slice())
         .enabled_extension_names(extensions_raw.as_slice())
         .build();
+    if let Some(layer_settings) = &layer_settings {
+        instance_info.p_next = layer_settings as *const LayerSettingsCreateInfoEXT as *const c_void;
+    }
 
-    Ok(unsafe { entry.create_instance(&instance_info, None)? })
+    Ok((unsafe { entry.create_instance(&instance_info, None)? }, surface_capabilities2))
 }
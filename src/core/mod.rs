@@ -1,6 +1,8 @@
 //! The core module holds all functionality that is minimally required to initialize a Vulkan context.
 
 pub mod app_info;
+#[cfg(feature = "renderdoc")]
+pub mod capture;
 pub mod debug;
 pub mod device;
 pub mod error;
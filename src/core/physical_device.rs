@@ -44,10 +44,16 @@ impl PhysicalDevice {
         surface: Option<&Surface>,
         settings: &AppSettings<Window>,
     ) -> Result<Self> {
-        let devices = unsafe { instance.enumerate_physical_devices()? };
+        let mut devices = unsafe { instance.enumerate_physical_devices()? };
         if devices.is_empty() {
             return Err(anyhow::Error::from(Error::NoGPU));
         }
+        // Only fall back to software implementations if no hardware device matches our requirements.
+        // Note that sort_by_key is stable, so the driver order is kept otherwise.
+        devices.sort_by_key(|device| {
+            let properties = unsafe { instance.get_physical_device_properties(*device) };
+            is_software_implementation(&properties)
+        });
 
         devices
             .iter()
@@ -90,6 +96,13 @@ impl PhysicalDevice {
                 {
                     return None;
                 }
+                // Core features are enabled as requested, so devices that do not support them would fail device creation.
+                let features = unsafe { instance.get_physical_device_features(*device) };
+                if settings.gpu_requirements.features.geometry_shader == vk::TRUE
+                    && features.geometry_shader != vk::TRUE
+                {
+                    return None;
+                }
 
                 physical_device.queues = {
                     settings
@@ -111,6 +124,8 @@ impl PhysicalDevice {
                                             | vk::QueueFlags::VIDEO_DECODE_KHR
                                             | vk::QueueFlags::VIDEO_ENCODE_KHR
                                     }
+                                    QueueType::VideoDecode => vk::QueueFlags::COMPUTE | vk::QueueFlags::GRAPHICS,
+                                    QueueType::SparseBinding => vk::QueueFlags::COMPUTE | vk::QueueFlags::GRAPHICS,
                                 }
                             } else {
                                 vk::QueueFlags::default()
@@ -173,6 +188,21 @@ impl PhysicalDevice {
                     }
                 }
 
+                // Ray queries are required, so reject devices that cannot use them
+                if settings.gpu_requirements.ray_query {
+                    let has_extension = |name: &CStr| {
+                        physical_device
+                            .extension_properties
+                            .iter()
+                            .any(|ext| ext.name == name.to_str().unwrap())
+                    };
+                    if !has_extension(vk::KhrRayQueryFn::name())
+                        || !has_extension(ash::extensions::khr::AccelerationStructure::name())
+                    {
+                        return None;
+                    }
+                }
+
                 // Check if all requested extensions are present
                 if !settings
                     .gpu_requirements
@@ -199,6 +229,12 @@ impl PhysicalDevice {
                     total_video_memory(&physical_device),
                     total_device_memory(&physical_device)
                 );
+                if physical_device.is_software_implementation() {
+                    warn!(
+                        "Physical device {:?} is a software implementation. Expect very poor performance.",
+                        name
+                    );
+                }
                 #[cfg(feature = "log-objects")]
                 trace!("Created new VkPhysicalDevice {:p}", physical_device.handle);
                 Some(physical_device)
@@ -240,6 +276,25 @@ impl PhysicalDevice {
         self.queues.as_slice()
     }
 
+    /// Get the extra compute queue on the graphics queue family, which is used when the
+    /// [`ComputeQueuePolicy::GraphicsFamily`](crate::sync::execution_manager::ComputeQueuePolicy::GraphicsFamily) policy is selected.
+    /// This only exists if the requested compute queue is on a different family than the graphics queue, and the graphics family
+    /// also supports compute.
+    pub(crate) fn graphics_family_compute_queue(&self) -> Option<QueueInfo> {
+        let graphics = self.queues.iter().find(|queue| queue.queue_type == QueueType::Graphics)?;
+        let compute = self.queues.iter().find(|queue| queue.queue_type == QueueType::Compute)?;
+        if compute.family_index == graphics.family_index || !graphics.flags.contains(vk::QueueFlags::COMPUTE) {
+            return None;
+        }
+        Some(QueueInfo {
+            queue_type: QueueType::Compute,
+            dedicated: false,
+            can_present: false,
+            family_index: graphics.family_index,
+            flags: graphics.flags,
+        })
+    }
+
     /// Get unsafe access to the physical device handle
     /// # Safety
     /// Any vulkan calls that mutate this physical device may leave the system in an undefined
@@ -268,6 +323,26 @@ impl PhysicalDevice {
     pub fn memory_properties(&self) -> &vk::PhysicalDeviceMemoryProperties {
         &self.memory_properties
     }
+
+    /// Whether this physical device is a software implementation running on the CPU, such as llvmpipe or SwiftShader.
+    /// These are useful for testing and CI, but are far too slow for real workloads.
+    pub fn is_software_implementation(&self) -> bool {
+        is_software_implementation(&self.properties)
+    }
+}
+
+/// Known names of software Vulkan implementations. Some of these do not report [`vk::PhysicalDeviceType::CPU`] in all versions.
+const SOFTWARE_IMPLEMENTATIONS: [&str; 3] = ["llvmpipe", "lavapipe", "SwiftShader"];
+
+pub(crate) fn is_software_implementation(properties: &vk::PhysicalDeviceProperties) -> bool {
+    if properties.device_type == vk::PhysicalDeviceType::CPU {
+        return true;
+    }
+    // SAFETY: device_name is a null-terminated string filled in by the driver.
+    let name = unsafe { wrap_c_str(properties.device_name.as_ptr()) };
+    SOFTWARE_IMPLEMENTATIONS
+        .iter()
+        .any(|software| name.contains(software))
 }
 
 fn total_video_memory(device: &PhysicalDevice) -> usize {
@@ -28,6 +28,13 @@ pub enum QueueType {
     /// Queue that supports transfer operations. Phobos will try to match this to a hardware queue that only supports
     /// transfer operations if possible.
     Transfer = vk::QueueFlags::TRANSFER.as_raw(),
+    /// Queue that supports video decode operations. These queues do not necessarily support transfer operations. Phobos will try
+    /// to match this to a hardware queue that does not support graphics or compute operations. See the [`video`](crate::video) module.
+    VideoDecode = vk::QueueFlags::VIDEO_DECODE_KHR.as_raw(),
+    /// Queue that supports sparse memory binding operations. Phobos will try to match this to a hardware queue that does not
+    /// support graphics or compute operations. Requesting this queue enables the sparse binding and residency features supported by
+    /// the device. See the [`sparse`](crate::sparse) module.
+    SparseBinding = vk::QueueFlags::SPARSE_BINDING.as_raw(),
 }
 
 /// Stores all information of a queue that was found on the physical device.
@@ -51,6 +58,16 @@ pub(crate) struct DeviceQueue {
     pub handle: vk::Queue,
 }
 
+impl DeviceQueue {
+    /// Submit a fence that is signaled once all work that was submitted to this queue before has completed.
+    pub fn submitted_work_fence(&self, device: &Device) -> Result<Fence> {
+        let fence = Fence::new(device.clone(), false)?;
+        // SAFETY: Vulkan API call. A submission without batches only signals the fence, once all earlier submissions completed.
+        unsafe { device.queue_submit(self.handle, &[], fence.handle())? };
+        Ok(fence)
+    }
+}
+
 /// Exposes a logical command queue on the device. Note that the physical `VkQueue` object could be multiplexed
 /// between different logical queues (e.g. on devices with only one queue).
 #[derive(Derivative)]
@@ -83,6 +100,7 @@ impl Queue {
             info.family_index,
             vk::CommandPoolCreateFlags::TRANSIENT,
         )?;
+        device.register_queue(&queue);
         Ok(Queue {
             device,
             queue,
@@ -92,6 +110,11 @@ impl Queue {
         })
     }
 
+    /// Get the device queue this queue submits to, shared with other logical queues that are multiplexed onto it.
+    pub(crate) fn device_queue(&self) -> Arc<Mutex<DeviceQueue>> {
+        self.queue.clone()
+    }
+
     fn acquire_device_queue(&self) -> Result<MutexGuard<DeviceQueue>> {
         Ok(self.queue.lock().map_err(|_| Error::PoisonError)?)
     }
@@ -111,7 +134,7 @@ impl Queue {
         // * `fence` is null or a valid fence handle (see above).
         // * The user supplied a valid range of `VkSubmitInfo` structures.
         // * `queue` is a valid queue object.
-        unsafe { Ok(self.device.queue_submit(queue.handle, submits, fence)?) }
+        unsafe { Ok(self.device.check_device_lost(self.device.queue_submit(queue.handle, submits, fence))?) }
     }
 
     /// Submits a batch of submissions to the queue, and signals the given fence when the
@@ -130,7 +153,23 @@ impl Queue {
         // * `fence` is null or a valid fence handle (see above).
         // * The user supplied a valid range of `VkSubmitInfo2` structures.
         // * `queue` is a valid queue object.
-        unsafe { Ok(self.device.queue_submit2(queue.handle, submits, fence)?) }
+        unsafe { Ok(self.device.check_device_lost(self.device.queue_submit2(queue.handle, submits, fence))?) }
+    }
+
+    /// Submits a batch of sparse binding operations to the queue, and signals the given fence when the binding is done.
+    /// When possible, prefer binding through [`ExecutionManager::bind_sparse()`](crate::ExecutionManager::bind_sparse).
+    pub fn bind_sparse(&self, infos: &[vk::BindSparseInfo], fence: Option<&Fence>) -> Result<()> {
+        let fence = match fence {
+            None => vk::Fence::null(),
+            // SAFETY: The user supplied a valid fence
+            Some(fence) => unsafe { fence.handle() },
+        };
+        let queue = self.acquire_device_queue()?;
+        // SAFETY:
+        // * `fence` is null or a valid fence handle (see above).
+        // * The user supplied a valid range of `VkBindSparseInfo` structures.
+        // * `queue` is a valid queue object.
+        unsafe { Ok(self.device.check_device_lost(self.device.queue_bind_sparse(queue.handle, infos, fence))?) }
     }
 
     /// Obtain the raw vulkan handle of a queue.
@@ -144,6 +183,7 @@ impl Queue {
     pub(crate) fn allocate_command_buffer<'q, A: Allocator, CmdBuf: IncompleteCmdBuffer<'q, A>>(
         device: Device,
         queue_lock: MutexGuard<'q, Queue>,
+        usage: vk::CommandBufferUsageFlags,
         pipelines: PipelineCache<A>,
         descriptors: DescriptorCache,
     ) -> Result<CmdBuf> {
@@ -163,7 +203,7 @@ impl Queue {
             device,
             queue_lock,
             handle,
-            vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT,
+            usage,
             pipelines,
             descriptors,
         )
@@ -190,3 +230,17 @@ impl Queue {
         &self.family_properties
     }
 }
+
+impl Drop for Queue {
+    fn drop(&mut self) {
+        // Command buffers allocated from this queue's command pool may still be executing,
+        // so wait for the work submitted to this queue before the command pool is destroyed.
+        let result = self
+            .acquire_device_queue()
+            .and_then(|queue| queue.submitted_work_fence(&self.device))
+            .and_then(|mut fence| fence.wait().map(|_| ()));
+        if let Err(e) = result {
+            error!("Failed to wait for submitted work during teardown: {e}");
+        }
+    }
+}
@@ -4,7 +4,7 @@
 use anyhow::Result;
 use ash::vk;
 
-use crate::{BufferView, Error, ImageView, PhysicalResourceBindings, Sampler, VirtualResource};
+use crate::{BufferView, Error, ImageView, PhysicalResourceBindings, Sampler, TexelBufferView, VirtualResource};
 use crate::descriptor::descriptor_set::{
     DescriptorBinding, DescriptorBufferInfo, DescriptorContents, DescriptorImageInfo,
     DescriptorSetBinding,
@@ -114,6 +114,7 @@ impl<'r> DescriptorSetBuilder<'r> {
     pub fn bind_sampled_image(&mut self, binding: u32, image: &ImageView, sampler: &Sampler) {
         self.inner.bindings.push(DescriptorBinding {
             binding,
+            array_element: 0,
             ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
             descriptors: vec![DescriptorContents::Image(DescriptorImageInfo {
                 sampler: unsafe { sampler.handle() },
@@ -123,10 +124,12 @@ impl<'r> DescriptorSetBuilder<'r> {
         });
     }
 
-    /// Bind an entire array of sampled images using the same sampler.
+    /// Bind an entire array of sampled images using the same sampler. Array elements past the end of `images` are left unbound,
+    /// which is valid for partially bound (bindless) arrays.
     pub fn bind_sampled_image_array(&mut self, binding: u32, images: &[ImageView], sampler: &Sampler) {
         self.inner.bindings.push(DescriptorBinding {
             binding,
+            array_element: 0,
             ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
             descriptors: images.iter().map(|image| {
                 DescriptorContents::Image(DescriptorImageInfo {
@@ -138,6 +141,74 @@ impl<'r> DescriptorSetBuilder<'r> {
         });
     }
 
+    /// Bind an entire array of image views as [`vk::DescriptorType::SAMPLED_IMAGE`] descriptors, without a sampler.
+    /// Use this together with [`DescriptorSetBuilder::bind_sampler()`] for bindless texture arrays, where many textures share
+    /// a few separate samplers.
+    pub fn bind_image_array(&mut self, binding: u32, images: &[ImageView]) {
+        self.inner.bindings.push(DescriptorBinding {
+            binding,
+            array_element: 0,
+            ty: vk::DescriptorType::SAMPLED_IMAGE,
+            descriptors: images
+                .iter()
+                .map(|image| {
+                    DescriptorContents::Image(DescriptorImageInfo {
+                        sampler: vk::Sampler::null(),
+                        view: image.clone(),
+                        layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    })
+                })
+                .collect(),
+        });
+    }
+
+    /// Bind a sampler to the given binding as a [`vk::DescriptorType::SAMPLER`].
+    pub fn bind_sampler(&mut self, binding: u32, sampler: &Sampler) {
+        self.inner.bindings.push(DescriptorBinding {
+            binding,
+            array_element: 0,
+            ty: vk::DescriptorType::SAMPLER,
+            descriptors: vec![DescriptorContents::Sampler(unsafe { sampler.handle() })],
+        });
+    }
+
+    /// Bind an image view to a single element of a [`vk::DescriptorType::COMBINED_IMAGE_SAMPLER`] array binding.
+    /// If this element was already bound in this builder, it is overwritten. Elements that are not bound are left untouched, so this
+    /// can be used to update only a few slots of a large (bindless) descriptor array.
+    pub fn bind_sampled_image_at_index(
+        &mut self,
+        binding: u32,
+        index: u32,
+        image: &ImageView,
+        sampler: &Sampler,
+    ) {
+        let contents = DescriptorContents::Image(DescriptorImageInfo {
+            sampler: unsafe { sampler.handle() },
+            view: image.clone(),
+            layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        });
+        // Overwrite the element in place if an earlier binding already covers it
+        let existing = self.inner.bindings.iter_mut().rev().find(|entry| {
+            entry.binding == binding
+                && entry.ty == vk::DescriptorType::COMBINED_IMAGE_SAMPLER
+                && index >= entry.array_element
+                && index < entry.array_element + entry.descriptors.len() as u32
+        });
+        match existing {
+            Some(entry) => {
+                entry.descriptors[(index - entry.array_element) as usize] = contents;
+            }
+            None => {
+                self.inner.bindings.push(DescriptorBinding {
+                    binding,
+                    array_element: index,
+                    ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    descriptors: vec![contents],
+                });
+            }
+        }
+    }
+
     /// Bind an image view to the given binding as a [`vk::DescriptorType::COMBINED_IMAGE_SAMPLER`].
     /// Uses the reflection information provided at construction to look up the correct binding slot by its name
     /// defined in the shader.
@@ -164,6 +235,7 @@ impl<'r> DescriptorSetBuilder<'r> {
     pub fn bind_uniform_buffer(&mut self, binding: u32, buffer: &BufferView) {
         self.inner.bindings.push(DescriptorBinding {
             binding,
+            array_element: 0,
             ty: vk::DescriptorType::UNIFORM_BUFFER,
             descriptors: vec![DescriptorContents::Buffer(DescriptorBufferInfo {
                 buffer: *buffer,
@@ -192,6 +264,7 @@ impl<'r> DescriptorSetBuilder<'r> {
     pub fn bind_storage_buffer(&mut self, binding: u32, buffer: &BufferView) {
         self.inner.bindings.push(DescriptorBinding {
             binding,
+            array_element: 0,
             ty: vk::DescriptorType::STORAGE_BUFFER,
             descriptors: vec![DescriptorContents::Buffer(DescriptorBufferInfo {
                 buffer: *buffer,
@@ -199,10 +272,31 @@ impl<'r> DescriptorSetBuilder<'r> {
         })
     }
 
+    /// Bind a texel buffer view to the given binding as a [`vk::DescriptorType::UNIFORM_TEXEL_BUFFER`].
+    pub fn bind_uniform_texel_buffer(&mut self, binding: u32, view: &TexelBufferView) {
+        self.inner.bindings.push(DescriptorBinding {
+            binding,
+            array_element: 0,
+            ty: vk::DescriptorType::UNIFORM_TEXEL_BUFFER,
+            descriptors: vec![DescriptorContents::TexelBuffer(view.clone())],
+        })
+    }
+
+    /// Bind a texel buffer view to the given binding as a [`vk::DescriptorType::STORAGE_TEXEL_BUFFER`].
+    pub fn bind_storage_texel_buffer(&mut self, binding: u32, view: &TexelBufferView) {
+        self.inner.bindings.push(DescriptorBinding {
+            binding,
+            array_element: 0,
+            ty: vk::DescriptorType::STORAGE_TEXEL_BUFFER,
+            descriptors: vec![DescriptorContents::TexelBuffer(view.clone())],
+        })
+    }
+
     /// Bind a storage image to the specified slot
     pub fn bind_storage_image(&mut self, binding: u32, image: &ImageView) {
         self.inner.bindings.push(DescriptorBinding {
             binding,
+            array_element: 0,
             ty: vk::DescriptorType::STORAGE_IMAGE,
             descriptors: vec![DescriptorContents::Image(DescriptorImageInfo {
                 sampler: vk::Sampler::null(),
@@ -227,15 +321,67 @@ impl<'r> DescriptorSetBuilder<'r> {
         }
     }
 
+    /// Bind an image view to the given binding as a [`vk::DescriptorType::INPUT_ATTACHMENT`]
+    pub fn bind_input_attachment(&mut self, binding: u32, image: &ImageView) {
+        self.inner.bindings.push(DescriptorBinding {
+            binding,
+            array_element: 0,
+            ty: vk::DescriptorType::INPUT_ATTACHMENT,
+            descriptors: vec![DescriptorContents::Image(DescriptorImageInfo {
+                sampler: vk::Sampler::null(),
+                view: image.clone(),
+                layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            })],
+        })
+    }
+
+    /// Resolve and bind an input attachment to a specified slot.
+    pub fn resolve_and_bind_input_attachment(
+        &mut self,
+        binding: u32,
+        resource: &VirtualResource,
+        bindings: &PhysicalResourceBindings,
+    ) -> Result<()> {
+        if let Some(PhysicalResource::Image(image)) = bindings.resolve(resource) {
+            self.bind_input_attachment(binding, image);
+            Ok(())
+        } else {
+            Err(Error::NoResourceBound(resource.uid().to_owned()).into())
+        }
+    }
+
     /// Bind an acceleration structure to the specified slot.
     pub fn bind_acceleration_structure(&mut self, binding: u32, accel: &AccelerationStructure) {
         self.inner.bindings.push(DescriptorBinding {
             binding,
+            array_element: 0,
             ty: vk::DescriptorType::ACCELERATION_STRUCTURE_KHR,
             descriptors: vec![DescriptorContents::AccelerationStructure(unsafe { accel.handle() })],
         })
     }
 
+    /// Resolve and bind an acceleration structure to a specified slot.
+    /// # Errors
+    /// Fails if the binding did not exist, or did not contain an acceleration structure.
+    pub fn resolve_and_bind_acceleration_structure(
+        &mut self,
+        binding: u32,
+        resource: &VirtualResource,
+        bindings: &PhysicalResourceBindings,
+    ) -> Result<()> {
+        if let Some(PhysicalResource::AccelerationStructure(handle)) = bindings.resolve(resource) {
+            self.inner.bindings.push(DescriptorBinding {
+                binding,
+                array_element: 0,
+                ty: vk::DescriptorType::ACCELERATION_STRUCTURE_KHR,
+                descriptors: vec![DescriptorContents::AccelerationStructure(*handle)],
+            });
+            Ok(())
+        } else {
+            Err(Error::NoResourceBound(resource.uid().to_owned()).into())
+        }
+    }
+
     /// Build the descriptor set creation info to pass into the cache.
     pub fn build(self) -> DescriptorSetBinding {
         self.inner
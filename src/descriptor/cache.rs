@@ -1,5 +1,6 @@
 //! The descriptor cache module exposes utilities for creating descriptor sets.
 
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
 use anyhow::Result;
@@ -16,6 +17,9 @@ struct DescriptorCacheInner {
     cache: Cache<DescriptorSet>,
     pool: DescriptorPool,
     deferred_pool_delete: DeletionQueue<DescriptorPool>,
+    /// The most recently created descriptor set binding for each layout. New sets with the same layout are created
+    /// by only writing the descriptors that differ from this set.
+    last_sets: HashMap<vk::DescriptorSetLayout, DescriptorSetBinding>,
 }
 
 /// This structure uses a [`Cache`] over a [`DescriptorSet`] to automatically manage everything related to descriptor sets.
@@ -54,10 +58,19 @@ impl DescriptorCacheInner {
 
         loop {
             bindings.pool = unsafe { self.pool.handle() };
-            let is_ok = { self.cache.get_or_create(&bindings, ()).is_ok() };
+            let is_new = self.cache.get(&bindings).is_none();
+            // Only use the previous set as a base if it is still alive
+            let base = self
+                .last_sets
+                .get(&bindings.layout)
+                .and_then(|base| self.cache.get(base).map(|set| (base, set.handle)));
+            let is_ok = { self.cache.get_or_create(&bindings, base).is_ok() };
             if is_ok {
+                if is_new {
+                    self.last_sets.insert(bindings.layout, bindings.clone());
+                }
                 // Need to query again to fix lifetime compiler error
-                return Ok(self.cache.get_or_create(&bindings, ()).unwrap());
+                return Ok(self.cache.get_or_create(&bindings, None).unwrap());
             } else {
                 let new_size = grow_pool_size(self.pool.size().clone(), &bindings);
                 // Create new pool, swap it out with the old one and then push the old one onto the deletion queue
@@ -79,6 +92,7 @@ impl DescriptorCache {
             cache: Cache::new(device.clone()),
             pool: DescriptorPool::new(device.clone(), DescriptorPoolSize::new(&device, 1))?,
             deferred_pool_delete: DeletionQueue::new(16),
+            last_sets: HashMap::new(),
         };
         Ok(Self {
             inner: Arc::new(Mutex::new(inner)),
@@ -107,5 +121,12 @@ impl DescriptorCache {
         let mut inner = self.inner.lock().unwrap();
         inner.cache.next_frame();
         inner.deferred_pool_delete.next_frame();
+        // Forget about base sets that were deallocated, so we do not keep their resources alive.
+        let DescriptorCacheInner {
+            cache,
+            last_sets,
+            ..
+        } = &mut *inner;
+        last_sets.retain(|_, bindings| cache.get(bindings).is_some());
     }
 }
@@ -7,6 +7,7 @@ use anyhow::Result;
 use ash::vk;
 
 use crate::core::device::ExtensionID;
+use crate::pipeline::set_layout::supports_update_after_bind;
 use crate::Device;
 
 /// Defines how many descriptors a descriptor pool should be able to hold.
@@ -51,7 +52,16 @@ impl DescriptorPool {
         // TODO: this max_sets value is overly pessimistic as it doesnt account for multiple
         // descriptors being held in the same descriptor set. Ideally this grows with the pool too.
         let max_sets = size.0.values().fold(0, |a, x| x + a);
-        let flags = vk::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET;
+        let mut flags = vk::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET;
+        // Layouts with update-after-bind bindings can only be allocated from an update-after-bind pool. Regular layouts
+        // can still be allocated from such a pool, so we do not need a separate one.
+        let update_after_bind = size
+            .0
+            .keys()
+            .any(|ty| supports_update_after_bind(&device, *ty));
+        if update_after_bind {
+            flags |= vk::DescriptorPoolCreateFlags::UPDATE_AFTER_BIND;
+        }
         let pool_sizes = size
             .0
             .iter()
@@ -1,11 +1,13 @@
 //! Wrappers for descriptor set binding info
 
+use std::collections::BTreeMap;
+
 use anyhow::Result;
 use ash::vk;
 
 use crate::util::cache::{Resource, ResourceKey};
 use crate::util::pnext::PNext;
-use crate::{BufferView, Device, ImageView};
+use crate::{BufferView, Device, ImageView, TexelBufferView};
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub(crate) struct DescriptorImageInfo {
@@ -22,13 +24,17 @@ pub(crate) struct DescriptorBufferInfo {
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub(crate) enum DescriptorContents {
     Image(DescriptorImageInfo),
+    Sampler(vk::Sampler),
     Buffer(DescriptorBufferInfo),
     AccelerationStructure(vk::AccelerationStructureKHR),
+    TexelBuffer(TexelBufferView),
 }
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub(crate) struct DescriptorBinding {
     pub binding: u32,
+    /// First element of the descriptor array that is written by this binding.
+    pub array_element: u32,
     pub ty: vk::DescriptorType,
     pub descriptors: Vec<DescriptorContents>,
 }
@@ -58,13 +64,18 @@ fn binding_image_info(binding: &DescriptorBinding) -> Vec<vk::DescriptorImageInf
     binding
         .descriptors
         .iter()
-        .map(|descriptor| {
-            let DescriptorContents::Image(image) = descriptor else { panic!("Missing descriptor type case?") };
-            vk::DescriptorImageInfo {
+        .map(|descriptor| match descriptor {
+            DescriptorContents::Image(image) => vk::DescriptorImageInfo {
                 sampler: image.sampler,
                 image_view: unsafe { image.view.handle() },
                 image_layout: image.layout,
-            }
+            },
+            DescriptorContents::Sampler(sampler) => vk::DescriptorImageInfo {
+                sampler: *sampler,
+                image_view: vk::ImageView::null(),
+                image_layout: vk::ImageLayout::UNDEFINED,
+            },
+            _ => panic!("Missing descriptor type case?"),
         })
         .collect()
 }
@@ -84,6 +95,17 @@ fn binding_buffer_info(binding: &DescriptorBinding) -> Vec<vk::DescriptorBufferI
         .collect()
 }
 
+fn binding_texel_buffer_info(binding: &DescriptorBinding) -> Vec<vk::BufferView> {
+    binding
+        .descriptors
+        .iter()
+        .map(|descriptor| {
+            let DescriptorContents::TexelBuffer(view) = descriptor else { panic!("Missing descriptor type case?") };
+            unsafe { view.handle() }
+        })
+        .collect()
+}
+
 fn binding_accel_structure_info(binding: &DescriptorBinding) -> Vec<vk::AccelerationStructureKHR> {
     binding
         .descriptors
@@ -95,6 +117,86 @@ fn binding_accel_structure_info(binding: &DescriptorBinding) -> Vec<vk::Accelera
         .collect()
 }
 
+impl DescriptorSetBinding {
+    /// The number of descriptors to allocate for a variable-sized descriptor array. Only the highest binding in a set can
+    /// have a variable descriptor count, so this is one past the last array element written to the highest binding.
+    /// If the set layout has no variable-sized binding, this value is ignored by the driver.
+    pub(crate) fn variable_descriptor_count(&self) -> u32 {
+        let Some(last) = self.bindings.iter().map(|entry| entry.binding).max() else { return 0; };
+        self.bindings
+            .iter()
+            .filter(|entry| entry.binding == last)
+            .map(|entry| entry.array_element + entry.descriptors.len() as u32)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Collect the contents of every descriptor array element in a set of bindings, keyed by binding and array element. Later
+/// bindings take priority.
+fn descriptors_by_element(
+    bindings: &DescriptorSetBinding,
+) -> BTreeMap<(u32, u32), (vk::DescriptorType, &DescriptorContents)> {
+    let mut descriptors = BTreeMap::new();
+    for entry in &bindings.bindings {
+        for (i, contents) in entry.descriptors.iter().enumerate() {
+            descriptors.insert((entry.binding, entry.array_element + i as u32), (entry.ty, contents));
+        }
+    }
+    descriptors
+}
+
+/// Splits the bindings in `key` into descriptors that need to be written, and descriptors that are identical
+/// in `base` and can be copied from `base_set` instead. Consecutive array elements that are changed or unchanged are merged into
+/// a single write or copy, so a large descriptor array with a few changed slots only costs a handful of updates.
+fn diff_bindings(
+    key: &DescriptorSetBinding,
+    base: &DescriptorSetBinding,
+    base_set: vk::DescriptorSet,
+    dst_set: vk::DescriptorSet,
+) -> (Vec<DescriptorBinding>, Vec<vk::CopyDescriptorSet>) {
+    let mut writes: Vec<DescriptorBinding> = Vec::new();
+    let mut copies: Vec<vk::CopyDescriptorSet> = Vec::new();
+    let base = descriptors_by_element(base);
+    // Writes are applied before copies, so each element must end up in exactly one of them.
+    for ((binding, element), (ty, contents)) in descriptors_by_element(key) {
+        if base.get(&(binding, element)) == Some(&(ty, contents)) {
+            match copies.last_mut() {
+                Some(copy) if copy.dst_binding == binding && copy.dst_array_element + copy.descriptor_count == element => {
+                    copy.descriptor_count += 1;
+                }
+                _ => copies.push(vk::CopyDescriptorSet {
+                    src_set: base_set,
+                    src_binding: binding,
+                    src_array_element: element,
+                    dst_set,
+                    dst_binding: binding,
+                    dst_array_element: element,
+                    descriptor_count: 1,
+                    ..Default::default()
+                }),
+            }
+        } else {
+            match writes.last_mut() {
+                Some(write)
+                    if write.binding == binding
+                        && write.ty == ty
+                        && write.array_element + write.descriptors.len() as u32 == element =>
+                {
+                    write.descriptors.push(contents.clone());
+                }
+                _ => writes.push(DescriptorBinding {
+                    binding,
+                    array_element: element,
+                    ty,
+                    descriptors: vec![contents.clone()],
+                }),
+            }
+        }
+    }
+    (writes, copies)
+}
+
 struct WriteDescriptorSet {
     pub set: vk::DescriptorSet,
     pub binding: u32,
@@ -103,9 +205,126 @@ struct WriteDescriptorSet {
     pub ty: vk::DescriptorType,
     pub image_info: Option<Vec<vk::DescriptorImageInfo>>,
     pub buffer_info: Option<Vec<vk::DescriptorBufferInfo>>,
+    pub texel_buffer_info: Option<Vec<vk::BufferView>>,
     pub acceleration_structure_info: Option<Vec<vk::AccelerationStructureKHR>>,
 }
 
+/// Write `bindings` and perform `copies` on the descriptor set `set`. The set must not be in use by the GPU, unless
+/// all written bindings are update-after-bind.
+pub(crate) fn update_descriptor_set(
+    device: &Device,
+    set: vk::DescriptorSet,
+    bindings: &[DescriptorBinding],
+    copies: &[vk::CopyDescriptorSet],
+) {
+    let writes = bindings
+        .iter()
+        .map(|binding| {
+            let mut write = WriteDescriptorSet {
+                set,
+                binding: binding.binding,
+                array_element: binding.array_element,
+                count: binding.descriptors.len() as u32,
+                ty: binding.ty,
+                image_info: None,
+                buffer_info: None,
+                texel_buffer_info: None,
+                acceleration_structure_info: None,
+            };
+
+            match binding.ty {
+                vk::DescriptorType::COMBINED_IMAGE_SAMPLER => {
+                    write.image_info = Some(binding_image_info(binding));
+                }
+                vk::DescriptorType::SAMPLER => {
+                    write.image_info = Some(binding_image_info(binding));
+                }
+                vk::DescriptorType::SAMPLED_IMAGE => {
+                    write.image_info = Some(binding_image_info(binding));
+                }
+                vk::DescriptorType::STORAGE_IMAGE => {
+                    write.image_info = Some(binding_image_info(binding));
+                }
+                vk::DescriptorType::INPUT_ATTACHMENT => {
+                    write.image_info = Some(binding_image_info(binding));
+                }
+                vk::DescriptorType::UNIFORM_BUFFER => {
+                    write.buffer_info = Some(binding_buffer_info(binding));
+                }
+                vk::DescriptorType::STORAGE_BUFFER => {
+                    write.buffer_info = Some(binding_buffer_info(binding));
+                }
+                vk::DescriptorType::UNIFORM_TEXEL_BUFFER => {
+                    write.texel_buffer_info = Some(binding_texel_buffer_info(binding));
+                }
+                vk::DescriptorType::STORAGE_TEXEL_BUFFER => {
+                    write.texel_buffer_info = Some(binding_texel_buffer_info(binding));
+                }
+                vk::DescriptorType::ACCELERATION_STRUCTURE_KHR => {
+                    write.acceleration_structure_info =
+                        Some(binding_accel_structure_info(binding));
+                }
+                _ => {
+                    todo!();
+                }
+            }
+            write
+        })
+        .collect::<Vec<WriteDescriptorSet>>();
+
+    let pnext = writes
+        .iter()
+        .map(|write| {
+            if let Some(info) = &write.acceleration_structure_info {
+                Some(PNext::WriteDescriptorSetAccelerationStructure(
+                    vk::WriteDescriptorSetAccelerationStructureKHR {
+                        s_type:
+                            vk::StructureType::WRITE_DESCRIPTOR_SET_ACCELERATION_STRUCTURE_KHR,
+                        p_next: std::ptr::null(),
+                        acceleration_structure_count: info.len() as u32,
+                        p_acceleration_structures: info.as_ptr(),
+                    },
+                ))
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<Option<PNext>>>();
+
+    let vk_writes = writes
+        .iter()
+        .zip(&pnext)
+        .map(|(write, p_next)| vk::WriteDescriptorSet {
+            s_type: vk::StructureType::WRITE_DESCRIPTOR_SET,
+            p_next: p_next
+                .as_ref()
+                .map(|p_next| p_next.as_ptr())
+                .unwrap_or(std::ptr::null()),
+            dst_set: write.set,
+            dst_binding: write.binding,
+            dst_array_element: write.array_element,
+            descriptor_count: write.count,
+            descriptor_type: write.ty,
+            p_image_info: match &write.image_info {
+                None => std::ptr::null(),
+                Some(image) => image.as_ptr(),
+            },
+            p_buffer_info: match &write.buffer_info {
+                None => std::ptr::null(),
+                Some(buffer) => buffer.as_ptr(),
+            },
+            p_texel_buffer_view: match &write.texel_buffer_info {
+                None => std::ptr::null(),
+                Some(view) => view.as_ptr(),
+            },
+        })
+        .collect::<Vec<_>>();
+
+    unsafe {
+        device.update_descriptor_sets(vk_writes.as_slice(), copies);
+    }
+}
+
 impl ResourceKey for DescriptorSetBinding {
     fn persistent(&self) -> bool {
         false
@@ -114,15 +333,30 @@ impl ResourceKey for DescriptorSetBinding {
 
 impl Resource for DescriptorSet {
     type Key = DescriptorSetBinding;
-    type ExtraParams<'a> = ();
+    /// A previously created descriptor set with the same layout. If given, descriptors that did not change compared to this set
+    /// are copied from it, and only the changed descriptors are written.
+    type ExtraParams<'a> = Option<(&'a DescriptorSetBinding, vk::DescriptorSet)>;
     const MAX_TIME_TO_LIVE: u32 = 8;
 
-    fn create(device: Device, key: &Self::Key, _: Self::ExtraParams<'_>) -> Result<Self>
+    fn create(device: Device, key: &Self::Key, base: Self::ExtraParams<'_>) -> Result<Self>
     where
         Self: Sized, {
+        let variable_count = key.variable_descriptor_count();
+        let variable_count_info = vk::DescriptorSetVariableDescriptorCountAllocateInfo {
+            s_type: vk::StructureType::DESCRIPTOR_SET_VARIABLE_DESCRIPTOR_COUNT_ALLOCATE_INFO,
+            p_next: std::ptr::null(),
+            descriptor_set_count: 1,
+            p_descriptor_counts: &variable_count,
+        };
+        // Only chain the variable count if the feature is enabled, otherwise no layout can have a variable-sized binding.
+        let p_next = if device.features_1_2().descriptor_binding_variable_descriptor_count == vk::TRUE {
+            &variable_count_info as *const _ as *const std::ffi::c_void
+        } else {
+            std::ptr::null()
+        };
         let info = vk::DescriptorSetAllocateInfo {
             s_type: vk::StructureType::DESCRIPTOR_SET_ALLOCATE_INFO,
-            p_next: std::ptr::null(),
+            p_next,
             descriptor_pool: key.pool,
             descriptor_set_count: 1,
             p_set_layouts: &key.layout,
@@ -134,97 +368,12 @@ impl Resource for DescriptorSet {
         #[cfg(feature = "log-objects")]
         trace!("Created new VkDescriptorSet {set:p}");
 
-        let writes = key
-            .bindings
-            .iter()
-            .map(|binding| {
-                let mut write = WriteDescriptorSet {
-                    set,
-                    binding: binding.binding,
-                    array_element: 0,
-                    count: binding.descriptors.len() as u32,
-                    ty: binding.ty,
-                    image_info: None,
-                    buffer_info: None,
-                    acceleration_structure_info: None,
-                };
-
-                match binding.ty {
-                    vk::DescriptorType::COMBINED_IMAGE_SAMPLER => {
-                        write.image_info = Some(binding_image_info(binding));
-                    }
-                    vk::DescriptorType::SAMPLED_IMAGE => {
-                        write.image_info = Some(binding_image_info(binding));
-                    }
-                    vk::DescriptorType::STORAGE_IMAGE => {
-                        write.image_info = Some(binding_image_info(binding));
-                    }
-                    vk::DescriptorType::UNIFORM_BUFFER => {
-                        write.buffer_info = Some(binding_buffer_info(binding));
-                    }
-                    vk::DescriptorType::STORAGE_BUFFER => {
-                        write.buffer_info = Some(binding_buffer_info(binding));
-                    }
-                    vk::DescriptorType::ACCELERATION_STRUCTURE_KHR => {
-                        write.acceleration_structure_info =
-                            Some(binding_accel_structure_info(binding));
-                    }
-                    _ => {
-                        todo!();
-                    }
-                }
-                write
-            })
-            .collect::<Vec<WriteDescriptorSet>>();
-
-        let pnext = writes
-            .iter()
-            .map(|write| {
-                if let Some(info) = &write.acceleration_structure_info {
-                    Some(PNext::WriteDescriptorSetAccelerationStructure(
-                        vk::WriteDescriptorSetAccelerationStructureKHR {
-                            s_type:
-                                vk::StructureType::WRITE_DESCRIPTOR_SET_ACCELERATION_STRUCTURE_KHR,
-                            p_next: std::ptr::null(),
-                            acceleration_structure_count: info.len() as u32,
-                            p_acceleration_structures: info.as_ptr(),
-                        },
-                    ))
-                } else {
-                    None
-                }
-            })
-            .collect::<Vec<Option<PNext>>>();
-
-        let vk_writes = writes
-            .iter()
-            .zip(&pnext)
-            .map(|(write, p_next)| vk::WriteDescriptorSet {
-                s_type: vk::StructureType::WRITE_DESCRIPTOR_SET,
-                p_next: p_next
-                    .as_ref()
-                    .map(|p_next| p_next.as_ptr())
-                    .unwrap_or(std::ptr::null()),
-                dst_set: write.set,
-                dst_binding: write.binding,
-                dst_array_element: write.array_element,
-                descriptor_count: write.count,
-                descriptor_type: write.ty,
-                p_image_info: match &write.image_info {
-                    None => std::ptr::null(),
-                    Some(image) => image.as_ptr(),
-                },
-                p_buffer_info: match &write.buffer_info {
-                    None => std::ptr::null(),
-                    Some(buffer) => buffer.as_ptr(),
-                },
-                p_texel_buffer_view: std::ptr::null(),
-            })
-            .collect::<Vec<_>>();
+        let (bindings, copies) = match base {
+            None => (key.bindings.clone(), vec![]),
+            Some((base, base_set)) => diff_bindings(key, base, base_set, set),
+        };
 
-        unsafe {
-            device.update_descriptor_sets(vk_writes.as_slice(), &[]);
-        }
+        update_descriptor_set(&device, set, bindings.as_slice(), copies.as_slice());
 
         Ok(DescriptorSet {
             device,
@@ -26,5 +26,6 @@
 pub mod builder;
 pub mod cache;
 pub mod descriptor_set;
+pub mod persistent;
 
 mod descriptor_pool;
@@ -0,0 +1,229 @@
+//! Persistent descriptor sets are long-lived descriptor sets that are allocated once and updated manually, instead of being
+//! rebuilt by the [`DescriptorCache`](crate::DescriptorCache) every time they are bound.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use ash::vk;
+
+use crate::{BufferView, Device, Error, ImageView, Sampler, TexelBufferView};
+use crate::descriptor::descriptor_pool::{DescriptorPool, DescriptorPoolSize};
+use crate::descriptor::descriptor_set::{
+    DescriptorBinding, DescriptorBufferInfo, DescriptorContents, DescriptorImageInfo,
+    update_descriptor_set,
+};
+use crate::pipeline::set_layout::{DescriptorSetLayout, DescriptorSetLayoutCreateInfo};
+use crate::util::cache::Resource;
+
+/// A descriptor set that is fully managed by the user. This is useful for static data, such as scene-wide buffers or a
+/// bindless texture table, where re-hashing and rebuilding the descriptor state for every draw is wasteful.
+///
+/// The set owns its own descriptor pool and set layout. Descriptors are written with [`PersistentDescriptorSet::write_image()`]
+/// and [`PersistentDescriptorSet::write_buffer()`], and the set is bound through
+/// [`IncompleteCommandBuffer::bind_persistent_descriptor_set()`](crate::IncompleteCommandBuffer::bind_persistent_descriptor_set).
+///
+/// Writes take effect immediately, so the set must not be in use by any pending command buffer while it is written, unless the
+/// written binding is update-after-bind. The set must also outlive all command buffers it is bound to.
+/// # Example
+/// ```
+/// # use anyhow::Result;
+/// # use phobos::*;
+/// fn create_scene_set(device: Device, pipelines: &PipelineCache, camera: &BufferView) -> Result<PersistentDescriptorSet> {
+///     // Take the layout of set 0 from a pipeline that uses this set.
+///     let info = pipelines.pipeline_info("scene").unwrap();
+///     let mut set = PersistentDescriptorSet::new(device, &info.layout().set_layouts[0])?;
+///     set.write_buffer(0, 0, camera)?;
+///     Ok(set)
+/// }
+/// ```
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct PersistentDescriptorSet {
+    #[derivative(Debug = "ignore")]
+    device: Device,
+    pool: DescriptorPool,
+    layout: DescriptorSetLayout,
+    bindings: Vec<vk::DescriptorSetLayoutBinding>,
+    handle: vk::DescriptorSet,
+}
+
+impl PersistentDescriptorSet {
+    /// Allocate a new persistent descriptor set with the given layout. All descriptors start out unbound.
+    /// If the highest binding in the layout has a variable descriptor count, the full descriptor count of that binding is allocated.
+    /// # Errors
+    /// * Fails if the layout has no bindings.
+    /// * Fails if creating the set layout, descriptor pool or descriptor set fails.
+    pub fn new(device: Device, info: &DescriptorSetLayoutCreateInfo) -> Result<Self> {
+        if info.bindings.is_empty() {
+            return Err(Error::EmptyDescriptorBinding.into());
+        }
+
+        let layout = DescriptorSetLayout::create(device.clone(), info, ())?;
+        let mut sizes = HashMap::new();
+        for binding in &info.bindings {
+            *sizes.entry(binding.descriptor_type).or_insert(0) += binding.descriptor_count;
+        }
+        let pool = DescriptorPool::new(device.clone(), DescriptorPoolSize(sizes))?;
+
+        // If the layout has no variable-sized binding, this count is ignored.
+        let variable_count = info
+            .bindings
+            .iter()
+            .max_by_key(|binding| binding.binding)
+            .map(|binding| binding.descriptor_count)
+            .unwrap_or(0);
+        let variable_count_info = vk::DescriptorSetVariableDescriptorCountAllocateInfo {
+            s_type: vk::StructureType::DESCRIPTOR_SET_VARIABLE_DESCRIPTOR_COUNT_ALLOCATE_INFO,
+            p_next: std::ptr::null(),
+            descriptor_set_count: 1,
+            p_descriptor_counts: &variable_count,
+        };
+        let p_next = if device.features_1_2().descriptor_binding_variable_descriptor_count == vk::TRUE {
+            &variable_count_info as *const _ as *const std::ffi::c_void
+        } else {
+            std::ptr::null()
+        };
+        let set_layout = unsafe { layout.handle() };
+        let alloc_info = vk::DescriptorSetAllocateInfo {
+            s_type: vk::StructureType::DESCRIPTOR_SET_ALLOCATE_INFO,
+            p_next,
+            descriptor_pool: unsafe { pool.handle() },
+            descriptor_set_count: 1,
+            p_set_layouts: &set_layout,
+        };
+        let handle = unsafe { device.allocate_descriptor_sets(&alloc_info) }?
+            .first()
+            .cloned()
+            .unwrap();
+        #[cfg(feature = "log-objects")]
+        trace!("Created new VkDescriptorSet (persistent) {handle:p}");
+
+        Ok(Self {
+            device,
+            pool,
+            layout,
+            bindings: info.bindings.clone(),
+            handle,
+        })
+    }
+
+    /// Find the descriptor type of a binding, and verify that the array element is in range.
+    fn descriptor_type(&self, binding: u32, element: u32) -> Result<vk::DescriptorType> {
+        self.bindings
+            .iter()
+            .find(|info| info.binding == binding && element < info.descriptor_count)
+            .map(|info| info.descriptor_type)
+            .ok_or_else(|| {
+                Error::InvalidDescriptorWrite {
+                    binding,
+                    element,
+                }
+                .into()
+            })
+    }
+
+    /// Write an image to a single element of an image binding. The descriptor type is taken from the set layout:
+    /// * [`vk::DescriptorType::COMBINED_IMAGE_SAMPLER`] requires a sampler, and expects the image to be in [`vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL`].
+    /// * [`vk::DescriptorType::SAMPLED_IMAGE`] and [`vk::DescriptorType::INPUT_ATTACHMENT`] expect the image to be in [`vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL`].
+    /// * [`vk::DescriptorType::STORAGE_IMAGE`] expects the image to be in [`vk::ImageLayout::GENERAL`].
+    /// # Errors
+    /// * Fails if the binding does not exist, is not an image binding or `element` is out of range.
+    /// * Fails if the binding is a combined image sampler and no sampler was given.
+    pub fn write_image(
+        &mut self,
+        binding: u32,
+        element: u32,
+        image: &ImageView,
+        sampler: Option<&Sampler>,
+    ) -> Result<()> {
+        let ty = self.descriptor_type(binding, element)?;
+        let (sampler, layout) = match (ty, sampler) {
+            (vk::DescriptorType::COMBINED_IMAGE_SAMPLER, Some(sampler)) => {
+                (unsafe { sampler.handle() }, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            }
+            (vk::DescriptorType::SAMPLED_IMAGE | vk::DescriptorType::INPUT_ATTACHMENT, _) => {
+                (vk::Sampler::null(), vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            }
+            (vk::DescriptorType::STORAGE_IMAGE, _) => (vk::Sampler::null(), vk::ImageLayout::GENERAL),
+            _ => {
+                return Err(Error::InvalidDescriptorWrite {
+                    binding,
+                    element,
+                }
+                .into())
+            }
+        };
+        self.write(DescriptorBinding {
+            binding,
+            array_element: element,
+            ty,
+            descriptors: vec![DescriptorContents::Image(DescriptorImageInfo {
+                sampler,
+                view: image.clone(),
+                layout,
+            })],
+        });
+        Ok(())
+    }
+
+    /// Write a buffer to a single element of a [`vk::DescriptorType::UNIFORM_BUFFER`] or [`vk::DescriptorType::STORAGE_BUFFER`] binding.
+    /// # Errors
+    /// * Fails if the binding does not exist, is not a buffer binding or `element` is out of range.
+    pub fn write_buffer(&mut self, binding: u32, element: u32, buffer: &BufferView) -> Result<()> {
+        let ty = self.descriptor_type(binding, element)?;
+        if ty != vk::DescriptorType::UNIFORM_BUFFER && ty != vk::DescriptorType::STORAGE_BUFFER {
+            return Err(Error::InvalidDescriptorWrite {
+                binding,
+                element,
+            }
+            .into());
+        }
+        self.write(DescriptorBinding {
+            binding,
+            array_element: element,
+            ty,
+            descriptors: vec![DescriptorContents::Buffer(DescriptorBufferInfo {
+                buffer: *buffer,
+            })],
+        });
+        Ok(())
+    }
+
+    /// Write a texel buffer view to a single element of a [`vk::DescriptorType::UNIFORM_TEXEL_BUFFER`] or
+    /// [`vk::DescriptorType::STORAGE_TEXEL_BUFFER`] binding.
+    /// # Errors
+    /// * Fails if the binding does not exist, is not a texel buffer binding or `element` is out of range.
+    pub fn write_texel_buffer(&mut self, binding: u32, element: u32, view: &TexelBufferView) -> Result<()> {
+        let ty = self.descriptor_type(binding, element)?;
+        if ty != vk::DescriptorType::UNIFORM_TEXEL_BUFFER && ty != vk::DescriptorType::STORAGE_TEXEL_BUFFER {
+            return Err(Error::InvalidDescriptorWrite {
+                binding,
+                element,
+            }
+            .into());
+        }
+        self.write(DescriptorBinding {
+            binding,
+            array_element: element,
+            ty,
+            descriptors: vec![DescriptorContents::TexelBuffer(view.clone())],
+        });
+        Ok(())
+    }
+
+    fn write(&mut self, binding: DescriptorBinding) {
+        update_descriptor_set(&self.device, self.handle, std::slice::from_ref(&binding), &[]);
+    }
+
+    /// Get the descriptor set layout of this set.
+    pub fn layout(&self) -> &DescriptorSetLayout {
+        &self.layout
+    }
+
+    /// Get unsafe access to the underlying `VkDescriptorSet` handle.
+    /// # Safety
+    /// Any vulkan calls that mutate the descriptor set may put the system in an undefined state.
+    pub unsafe fn handle(&self) -> vk::DescriptorSet {
+        self.handle
+    }
+}
@@ -0,0 +1,410 @@
+//! Rendering backend for [egui](https://github.com/emilk/egui). This requires the `egui` feature, which also enables
+//! `shaderc` since the shaders used by the renderer are compiled at build time.
+//!
+//! The [`EguiRenderer`] owns all textures egui allocates, such as the font atlas. Every frame:
+//! - Upload changed textures with [`EguiRenderer::update_textures()`], using the textures delta from the egui output.
+//! - Tessellate the shapes from the egui output, and create a pass drawing them on top of a color attachment with
+//!   [`EguiRenderer::pass()`]. Vertex and index data is uploaded through the scratch allocator of the pass.
+//!
+//! egui outputs colors in sRGB space, so the color attachment should use an sRGB format.
+//! # Example
+//! ```
+//! # use anyhow::Result;
+//! # use phobos::prelude::*;
+//! # use phobos::egui::{EguiRenderer, ScreenDescriptor};
+//! fn draw_ui<'cb>(
+//!     renderer: &mut EguiRenderer,
+//!     ctx: &egui::Context,
+//!     output: egui::FullOutput,
+//!     cmd: IncompleteCommandBuffer<'cb, domain::All>,
+//!     allocator: &mut DefaultAllocator,
+//!     swapchain: &VirtualResource,
+//!     size: (u32, u32),
+//! ) -> Result<(IncompleteCommandBuffer<'cb, domain::All>, Pass<'cb, domain::All, ()>)> {
+//!     let cmd = renderer.update_textures(cmd, allocator, &output.textures_delta)?;
+//!     let primitives = ctx.tessellate(output.shapes);
+//!     let screen = ScreenDescriptor {
+//!         size_in_pixels: [size.0, size.1],
+//!         pixels_per_point: ctx.pixels_per_point(),
+//!     };
+//!     let pass = renderer.pass(primitives, swapchain, screen)?;
+//!     Ok((cmd, pass))
+//! }
+//! ```
+
+use std::collections::HashMap;
+
+use ::egui::epaint::{ImageDelta, Primitive, Vertex};
+use ::egui::{ClippedPrimitive, ImageData, TextureFilter, TextureId, TextureOptions, TexturesDelta};
+use anyhow::{anyhow, Result};
+use ash::vk;
+
+use crate::{
+    Allocator, Buffer, DefaultAllocator, DeletionQueue, Device, GfxSupport, Image, ImageCreateInfo, ImageView,
+    MemoryType, PipelineBuilder, PipelineCache, PipelineStage, SamplerCreateInfo, ShaderCreateInfo,
+    TransferSupport, VirtualResource,
+};
+use crate::command_buffer::traits::*;
+use crate::command_buffer::transfer::ImageRegion;
+use crate::graph::pass::{Pass, PassBuilder};
+use crate::pipeline::pipeline_layout::{PipelineLayoutCreateInfo, PushConstantRange};
+use crate::pipeline::set_layout::DescriptorSetLayoutCreateInfo;
+use crate::sync::domain::ExecutionDomain;
+use crate::IncompleteCommandBuffer;
+
+/// Name of the pipeline used to draw egui meshes.
+pub const EGUI_PIPELINE: &str = "phobos_egui";
+
+const VERTEX_SPIRV: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/egui.vert.spv"));
+const FRAGMENT_SPIRV: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/egui.frag.spv"));
+
+/// Describes the surface the UI is drawn to.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ScreenDescriptor {
+    /// Size of the color attachment in physical pixels
+    pub size_in_pixels: [u32; 2],
+    /// Number of physical pixels per egui point
+    pub pixels_per_point: f32,
+}
+
+impl ScreenDescriptor {
+    fn size_in_points(&self) -> [f32; 2] {
+        [
+            self.size_in_pixels[0] as f32 / self.pixels_per_point,
+            self.size_in_pixels[1] as f32 / self.pixels_per_point,
+        ]
+    }
+}
+
+#[derive(Debug)]
+struct EguiTexture<A: Allocator> {
+    #[allow(dead_code)]
+    image: Image<A>,
+    view: ImageView,
+    options: TextureOptions,
+}
+
+/// A single draw call of an egui mesh, with all data it needs copied out of the renderer.
+struct EguiDraw {
+    view: ImageView,
+    sampler: SamplerCreateInfo,
+    scissor: vk::Rect2D,
+    first_index: u32,
+    index_count: u32,
+    vertex_offset: i32,
+}
+
+fn sampler_info(options: &TextureOptions) -> SamplerCreateInfo {
+    let filter = |filter: TextureFilter| match filter {
+        TextureFilter::Nearest => vk::Filter::NEAREST,
+        TextureFilter::Linear => vk::Filter::LINEAR,
+    };
+    SamplerCreateInfo::default()
+        .filter(filter(options.magnification), filter(options.minification))
+        .address_mode(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+}
+
+fn shader(stage: vk::ShaderStageFlags, spirv: &[u8]) -> ShaderCreateInfo {
+    let code = spirv
+        .chunks_exact(4)
+        .map(|word| u32::from_ne_bytes(word.try_into().unwrap()))
+        .collect();
+    ShaderCreateInfo::from_spirv(stage, code)
+}
+
+/// The egui shaders sample the texture at binding 0 of set 0, and use a push constant for the screen size. This is set
+/// explicitly so the pipeline also works without the `shader-reflection` feature.
+fn layout() -> PipelineLayoutCreateInfo {
+    PipelineLayoutCreateInfo {
+        flags: Default::default(),
+        set_layouts: vec![DescriptorSetLayoutCreateInfo {
+            bindings: vec![vk::DescriptorSetLayoutBinding {
+                binding: 0,
+                descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::FRAGMENT,
+                p_immutable_samplers: std::ptr::null(),
+            }],
+            persistent: true,
+            flags: vec![],
+            immutable_samplers: vec![],
+        }],
+        push_constants: vec![PushConstantRange {
+            stage_flags: vk::ShaderStageFlags::VERTEX,
+            offset: 0,
+            size: std::mem::size_of::<[f32; 2]>() as u32,
+        }],
+        persistent: true,
+    }
+}
+
+/// Renders egui output through the pass graph. See the [module-level documentation](self).
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct EguiRenderer<A: Allocator = DefaultAllocator> {
+    device: Device,
+    textures: HashMap<TextureId, EguiTexture<A>>,
+    // Freed textures and staging buffers may still be in use by frames in flight.
+    deferred_textures: DeletionQueue<EguiTexture<A>>,
+    deferred_buffers: DeletionQueue<Buffer<A>>,
+}
+
+impl<A: Allocator> EguiRenderer<A> {
+    /// Create a new egui renderer, and register its pipeline in the pipeline cache. Textures that are freed or replaced are
+    /// kept alive for `frames_in_flight` calls to [`EguiRenderer::update_textures()`], so this should be the number of frames
+    /// that can be in flight at the same time.
+    /// # Errors
+    /// * Fails if registering the pipeline fails.
+    pub fn new(device: Device, cache: &mut PipelineCache<A>, frames_in_flight: u32) -> Result<Self> {
+        let mut info = PipelineBuilder::new(EGUI_PIPELINE)
+            .vertex_input(0, vk::VertexInputRate::VERTEX)
+            .vertex_attribute(0, 0, vk::Format::R32G32_SFLOAT)?
+            .vertex_attribute(0, 1, vk::Format::R32G32_SFLOAT)?
+            .vertex_attribute(0, 2, vk::Format::R8G8B8A8_UNORM)?
+            .dynamic_states(&[vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR])
+            .cull_mask(vk::CullModeFlags::NONE)
+            // egui outputs colors with premultiplied alpha
+            .blend_attachment(
+                vk::BlendFactor::ONE,
+                vk::BlendFactor::ONE_MINUS_SRC_ALPHA,
+                vk::BlendOp::ADD,
+                vk::BlendFactor::ONE_MINUS_DST_ALPHA,
+                vk::BlendFactor::ONE,
+                vk::BlendOp::ADD,
+            )
+            .attach_shader(shader(vk::ShaderStageFlags::VERTEX, VERTEX_SPIRV))
+            .attach_shader(shader(vk::ShaderStageFlags::FRAGMENT, FRAGMENT_SPIRV))
+            .build();
+        info.layout = layout();
+        cache.create_named_pipeline(info)?;
+
+        Ok(Self {
+            device,
+            textures: HashMap::new(),
+            deferred_textures: DeletionQueue::new(frames_in_flight + 1),
+            deferred_buffers: DeletionQueue::new(frames_in_flight + 1),
+        })
+    }
+
+    /// Upload new and changed textures, and free textures egui no longer uses. This records the uploads on `cmd`,
+    /// followed by the barriers needed to sample the textures in the UI pass. Call this once per frame, before recording the pass.
+    /// # Errors
+    /// * Fails if allocating a texture or staging buffer fails.
+    /// * Fails if a partial update refers to a texture that does not exist.
+    pub fn update_textures<'q, D: ExecutionDomain + TransferSupport>(
+        &mut self,
+        mut cmd: IncompleteCommandBuffer<'q, D, A>,
+        allocator: &mut A,
+        delta: &TexturesDelta,
+    ) -> Result<IncompleteCommandBuffer<'q, D, A>> {
+        self.deferred_textures.next_frame();
+        self.deferred_buffers.next_frame();
+
+        for (id, image_delta) in &delta.set {
+            cmd = self.update_texture(cmd, allocator, *id, image_delta)?;
+        }
+        for id in &delta.free {
+            if let Some(texture) = self.textures.remove(id) {
+                self.deferred_textures.push(texture);
+            }
+        }
+        Ok(cmd)
+    }
+
+    fn update_texture<'q, D: ExecutionDomain + TransferSupport>(
+        &mut self,
+        cmd: IncompleteCommandBuffer<'q, D, A>,
+        allocator: &mut A,
+        id: TextureId,
+        delta: &ImageDelta,
+    ) -> Result<IncompleteCommandBuffer<'q, D, A>> {
+        let pixels: Vec<[u8; 4]> = match &delta.image {
+            ImageData::Color(image) => image.pixels.iter().map(|pixel| pixel.to_array()).collect(),
+            ImageData::Font(image) => image.srgba_pixels(None).map(|pixel| pixel.to_array()).collect(),
+        };
+        let [width, height] = delta.image.size();
+        let region = ImageRegion {
+            offset: match delta.pos {
+                None => vk::Offset3D::default(),
+                Some([x, y]) => vk::Offset3D {
+                    x: x as i32,
+                    y: y as i32,
+                    z: 0,
+                },
+            },
+            extent: vk::Extent3D {
+                width: width as u32,
+                height: height as u32,
+                depth: 1,
+            },
+        };
+
+        // A full update replaces the texture, since its size may have changed.
+        let old_layout = if delta.pos.is_none() {
+            let image = Image::new(
+                self.device.clone(),
+                allocator,
+                ImageCreateInfo {
+                    width: width as u32,
+                    height: height as u32,
+                    depth: 1,
+                    usage: vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST,
+                    format: vk::Format::R8G8B8A8_SRGB,
+                    samples: vk::SampleCountFlags::TYPE_1,
+                    mip_levels: 1,
+                    layers: 1,
+                    flags: vk::ImageCreateFlags::empty(),
+                    memory_type: MemoryType::GpuOnly,
+                },
+            )?;
+            let view = image.whole_view(vk::ImageAspectFlags::COLOR)?;
+            let texture = EguiTexture {
+                image,
+                view,
+                options: delta.options,
+            };
+            if let Some(old) = self.textures.insert(id, texture) {
+                self.deferred_textures.push(old);
+            }
+            vk::ImageLayout::UNDEFINED
+        } else {
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL
+        };
+        let texture = self
+            .textures
+            .get_mut(&id)
+            .ok_or_else(|| anyhow!("Partial update of egui texture {id:?} that does not exist"))?;
+        texture.options = delta.options;
+
+        let staging = Buffer::new(
+            self.device.clone(),
+            allocator,
+            std::mem::size_of_val(pixels.as_slice()) as vk::DeviceSize,
+            MemoryType::CpuToGpu,
+        )?;
+        staging
+            .view_full()
+            .mapped_slice::<[u8; 4]>()?
+            .copy_from_slice(&pixels);
+        let cmd = cmd
+            .transition_image(
+                &texture.view,
+                PipelineStage::FRAGMENT_SHADER,
+                PipelineStage::TRANSFER,
+                old_layout,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                vk::AccessFlags2::SHADER_READ,
+                vk::AccessFlags2::TRANSFER_WRITE,
+            )
+            .copy_buffer_to_image_region(&staging.view_full(), &texture.view, &region)?
+            .transition_image(
+                &texture.view,
+                PipelineStage::TRANSFER,
+                PipelineStage::FRAGMENT_SHADER,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                vk::AccessFlags2::TRANSFER_WRITE,
+                vk::AccessFlags2::SHADER_READ,
+            );
+        self.deferred_buffers.push(staging);
+        Ok(cmd)
+    }
+
+    /// Get the image view of a texture managed by this renderer, if it exists.
+    pub fn texture(&self, id: TextureId) -> Option<&ImageView> {
+        self.textures.get(&id).map(|texture| &texture.view)
+    }
+
+    /// Create a pass that draws tessellated egui output on top of `target`. The existing contents of `target` are preserved.
+    /// Paint callbacks are not supported and are skipped.
+    /// # Errors
+    /// * Fails if a mesh uses a texture that does not exist.
+    pub fn pass<'cb, D: ExecutionDomain + GfxSupport, U>(
+        &self,
+        primitives: Vec<ClippedPrimitive>,
+        target: &VirtualResource,
+        screen: ScreenDescriptor,
+    ) -> Result<Pass<'cb, D, U, A>> {
+        let mut vertices: Vec<Vertex> = Vec::new();
+        let mut indices: Vec<u32> = Vec::new();
+        let mut draws = Vec::new();
+        for ClippedPrimitive {
+            clip_rect,
+            primitive,
+        } in primitives
+        {
+            let Primitive::Mesh(mesh) = primitive else {
+                continue;
+            };
+            // Transform the clip rectangle from points to pixels, and clamp it to the screen.
+            let min_x = (clip_rect.min.x * screen.pixels_per_point).round().clamp(0.0, screen.size_in_pixels[0] as f32) as u32;
+            let min_y = (clip_rect.min.y * screen.pixels_per_point).round().clamp(0.0, screen.size_in_pixels[1] as f32) as u32;
+            let max_x = (clip_rect.max.x * screen.pixels_per_point).round().clamp(min_x as f32, screen.size_in_pixels[0] as f32) as u32;
+            let max_y = (clip_rect.max.y * screen.pixels_per_point).round().clamp(min_y as f32, screen.size_in_pixels[1] as f32) as u32;
+            if mesh.indices.is_empty() || max_x == min_x || max_y == min_y {
+                continue;
+            }
+
+            let texture = self
+                .textures
+                .get(&mesh.texture_id)
+                .ok_or_else(|| anyhow!("egui mesh uses texture {:?} that does not exist", mesh.texture_id))?;
+            draws.push(EguiDraw {
+                view: texture.view.clone(),
+                sampler: sampler_info(&texture.options),
+                scissor: vk::Rect2D {
+                    offset: vk::Offset2D {
+                        x: min_x as i32,
+                        y: min_y as i32,
+                    },
+                    extent: vk::Extent2D {
+                        width: max_x - min_x,
+                        height: max_y - min_y,
+                    },
+                },
+                first_index: indices.len() as u32,
+                index_count: mesh.indices.len() as u32,
+                vertex_offset: vertices.len() as i32,
+            });
+            vertices.extend_from_slice(&mesh.vertices);
+            indices.extend_from_slice(&mesh.indices);
+        }
+
+        let pass = PassBuilder::render("egui")
+            .color_attachment(target, vk::AttachmentLoadOp::LOAD, None)?
+            .execute_fn(move |mut cmd, pool, _, _| {
+                if draws.is_empty() {
+                    return Ok(cmd);
+                }
+                let mut vertex_buffer = pool.allocate_scratch_buffer(std::mem::size_of_val(vertices.as_slice()) as vk::DeviceSize)?;
+                // SAFETY: Vertices are plain data, and the scratch allocator aligns allocations to at least 4 bytes.
+                unsafe { vertex_buffer.mapped_slice_unchecked::<Vertex>()? }.copy_from_slice(&vertices);
+                let mut index_buffer = pool.allocate_scratch_buffer(std::mem::size_of_val(indices.as_slice()) as vk::DeviceSize)?;
+                index_buffer.mapped_slice::<u32>()?.copy_from_slice(&indices);
+
+                cmd = cmd
+                    .bind_graphics_pipeline(EGUI_PIPELINE)?
+                    .viewport(vk::Viewport {
+                        x: 0.0,
+                        y: 0.0,
+                        width: screen.size_in_pixels[0] as f32,
+                        height: screen.size_in_pixels[1] as f32,
+                        min_depth: 0.0,
+                        max_depth: 1.0,
+                    })
+                    .bind_vertex_buffer(0, &vertex_buffer)
+                    .bind_index_buffer(&index_buffer, vk::IndexType::UINT32)
+                    .push_constant(vk::ShaderStageFlags::VERTEX, 0, &screen.size_in_points())?;
+                for draw in &draws {
+                    let sampler = pool.get_sampler(&draw.sampler)?;
+                    cmd = cmd
+                        .scissor(draw.scissor)
+                        .bind_sampled_image(0, 0, &draw.view, &sampler)?
+                        .draw_indexed(draw.index_count, 1, draw.first_index, draw.vertex_offset, 0)?;
+                }
+                Ok(cmd)
+            })
+            .build();
+        Ok(pass)
+    }
+}
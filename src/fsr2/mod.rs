@@ -23,9 +23,10 @@ pub use fsr2_sys::{
 use thiserror::Error;
 use widestring::{WideChar as wchar_t, WideCStr};
 
-use crate::{Allocator, ComputeSupport, DeletionQueue, ImageView, IncompleteCommandBuffer};
+use crate::{Allocator, ComputeSupport, DeletionQueue, Device, ImageView, IncompleteCommandBuffer};
 use crate::domain::ExecutionDomain;
 use crate::pool::{Pool, Poolable, Pooled};
+use crate::upscaler::{UpscaleDescription, UpscaleQuality, UpscaleResources, Upscaler};
 
 /// FSR2 API error, stores an error code that describes the cause of the error
 #[derive(Debug, Error)]
@@ -398,10 +399,19 @@ impl Fsr2Context {
         descr: &Fsr2DispatchDescription,
         resources: &Fsr2DispatchResources,
         cmd: &IncompleteCommandBuffer<D, A>,
+    ) -> Result<()> {
+        self.dispatch_raw(descr, resources, unsafe { cmd.handle() })
+    }
+
+    fn dispatch_raw(
+        &mut self,
+        descr: &Fsr2DispatchDescription,
+        resources: &Fsr2DispatchResources,
+        cmd: vk::CommandBuffer,
     ) -> Result<()> {
         // Clean up old fsr2 contexts after resizes
         self.deferred_backend_delete.next_frame();
-        let cmd_raw = unsafe { fsr2_sys::VkCommandBuffer::from_raw(cmd.handle().as_raw()) };
+        let cmd_raw = fsr2_sys::VkCommandBuffer::from_raw(cmd.as_raw());
         let cmd_list = unsafe { ffxGetCommandListVK(cmd_raw) };
         if descr.auto_reactive.is_some() {
             warn!("Auto-reactive is currently not supported. Please open an issue if you would like this added.");
@@ -543,6 +553,135 @@ impl Fsr2Context {
     }
 }
 
+impl From<UpscaleQuality> for FfxFsr2QualityMode {
+    fn from(value: UpscaleQuality) -> Self {
+        match value {
+            UpscaleQuality::Quality => FfxFsr2QualityMode::Quality,
+            UpscaleQuality::Balanced => FfxFsr2QualityMode::Balanced,
+            UpscaleQuality::Performance => FfxFsr2QualityMode::Performance,
+            UpscaleQuality::UltraPerformance => FfxFsr2QualityMode::UltraPerformance,
+        }
+    }
+}
+
+impl From<&UpscaleDescription> for Fsr2DispatchDescription {
+    fn from(value: &UpscaleDescription) -> Self {
+        Self {
+            jitter_offset: FfxFloatCoords2D {
+                x: value.jitter_offset.0,
+                y: value.jitter_offset.1,
+            },
+            motion_vector_scale: FfxFloatCoords2D {
+                x: value.motion_vector_scale.0,
+                y: value.motion_vector_scale.1,
+            },
+            enable_sharpening: value.sharpness.is_some(),
+            sharpness: value.sharpness.unwrap_or_default(),
+            frametime_delta: value.frametime_delta,
+            pre_exposure: value.pre_exposure,
+            reset: value.reset,
+            camera_near: value.camera_near,
+            camera_far: value.camera_far,
+            camera_fov_vertical: value.camera_fov_vertical,
+            viewspace_to_meters_factor: 1.0,
+            auto_reactive: None,
+        }
+    }
+}
+
+impl From<&UpscaleResources> for Fsr2DispatchResources {
+    fn from(value: &UpscaleResources) -> Self {
+        Self {
+            color: value.color.clone(),
+            depth: value.depth.clone(),
+            motion_vectors: value.motion_vectors.clone(),
+            exposure: value.exposure.clone(),
+            reactive: None,
+            transparency_and_composition: None,
+            output: value.output.clone(),
+        }
+    }
+}
+
+impl Upscaler for Fsr2Context {
+    fn dispatch(&mut self, descr: &UpscaleDescription, resources: &UpscaleResources, cmd: vk::CommandBuffer) -> Result<()> {
+        self.dispatch_raw(&descr.into(), &resources.into(), cmd)
+    }
+
+    fn jitter_phase_count(&mut self, render_width: u32) -> u32 {
+        Fsr2Context::jitter_phase_count(self, render_width) as u32
+    }
+
+    fn jitter_offset(&mut self, render_width: u32) -> Result<(f32, f32)> {
+        Fsr2Context::jitter_offset(self, render_width)
+    }
+
+    fn display_size(&self) -> vk::Extent2D {
+        vk::Extent2D {
+            width: self.display_size.width,
+            height: self.display_size.height,
+        }
+    }
+
+    fn render_resolution(&mut self, quality: UpscaleQuality) -> Result<vk::Extent2D> {
+        let size = self.get_render_resolution(quality.into())?;
+        Ok(vk::Extent2D {
+            width: size.width,
+            height: size.height,
+        })
+    }
+
+    fn resize(&mut self, display_size: vk::Extent2D, max_render_size: Option<vk::Extent2D>) -> Result<()> {
+        let to_ffx = |size: vk::Extent2D| FfxDimensions2D {
+            width: size.width,
+            height: size.height,
+        };
+        self.set_display_resolution(to_ffx(display_size), max_render_size.map(to_ffx))
+    }
+}
+
+/// [`Upscaler`] implementation using the FSR2 context owned by the device. The context is locked for the duration of
+/// each call, so this can be moved into an upscale pass with [`PassBuilder::upscale()`](crate::PassBuilder::upscale).
+#[derive(Debug, Clone)]
+pub struct Fsr2Upscaler {
+    device: Device,
+}
+
+impl Fsr2Upscaler {
+    /// Create an upscaler that uses the FSR2 context of this device.
+    pub fn new(device: Device) -> Self {
+        Self {
+            device,
+        }
+    }
+}
+
+impl Upscaler for Fsr2Upscaler {
+    fn dispatch(&mut self, descr: &UpscaleDescription, resources: &UpscaleResources, cmd: vk::CommandBuffer) -> Result<()> {
+        Upscaler::dispatch(&mut **self.device.fsr2_context(), descr, resources, cmd)
+    }
+
+    fn jitter_phase_count(&mut self, render_width: u32) -> u32 {
+        Upscaler::jitter_phase_count(&mut **self.device.fsr2_context(), render_width)
+    }
+
+    fn jitter_offset(&mut self, render_width: u32) -> Result<(f32, f32)> {
+        Upscaler::jitter_offset(&mut **self.device.fsr2_context(), render_width)
+    }
+
+    fn display_size(&self) -> vk::Extent2D {
+        Upscaler::display_size(&**self.device.fsr2_context())
+    }
+
+    fn render_resolution(&mut self, quality: UpscaleQuality) -> Result<vk::Extent2D> {
+        Upscaler::render_resolution(&mut **self.device.fsr2_context(), quality)
+    }
+
+    fn resize(&mut self, display_size: vk::Extent2D, max_render_size: Option<vk::Extent2D>) -> Result<()> {
+        Upscaler::resize(&mut **self.device.fsr2_context(), display_size, max_render_size)
+    }
+}
+
 unsafe impl Send for Fsr2Context {}
 
 unsafe impl Sync for Fsr2Context {}
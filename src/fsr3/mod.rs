@@ -0,0 +1,1353 @@
+//! FSR3 upscaling and frame generation through the [FidelityFX SDK](https://gpuopen.com/fidelityfx-sdk/) API. This requires the `fsr3` feature.
+//!
+//! The FidelityFX API runtime is loaded at runtime, so it must be available next to the executable or in the library search path
+//! (`amd_fidelityfx_vk.dll` on Windows, `libamd_fidelityfx_vk.so` elsewhere). After loading it with [`Fsr3Library::load()`]:
+//! - [`Fsr3Upscaler`] implements [`Upscaler`], so it can be used in an upscale pass like any other upscaler.
+//! - [`Fsr3FrameGenerator`] implements [`PresentHook`]. Once it is registered with
+//!   [`FrameManager::set_present_hook()`](crate::FrameManager::set_present_hook), an interpolated frame is presented before every real frame.
+//!
+//! The [`fsr2`](crate::fsr2) module is kept as a compatibility layer for applications that link FSR2 statically.
+//!
+//! # Frame generation
+//!
+//! Frame generation interpolates a frame between the previous and the current frame, using the depth buffer and motion vectors
+//! of the current frame. Every frame, [`Fsr3FrameGenerator::prepare()`] must be recorded after these were rendered. The generator is
+//! a shared handle, so keep a clone of it after passing it to the frame manager.
+//!
+//! Generated frames are copied into an extra swapchain image, which requires transfer destination usage support on the surface.
+//! The swapchain should also have at least one more image than its minimum image count. If either is not the case, or frame
+//! generation is disabled, frames are presented as usual. Generated and real frames are presented back to back, so a
+//! [`FramePacer`](crate::FramePacer) should be used to pace them.
+//! # Example
+//! ```
+//! # use anyhow::Result;
+//! # use phobos::prelude::*;
+//! # use phobos::fsr3::*;
+//! fn enable_fsr3(
+//!     instance: &Instance,
+//!     physical_device: &PhysicalDevice,
+//!     device: &Device,
+//!     allocator: DefaultAllocator,
+//!     frame: &mut FrameManager,
+//! ) -> Result<(Fsr3Upscaler, Fsr3FrameGenerator)> {
+//!     let library = Fsr3Library::load()?;
+//!     let display_size = vk::Extent2D {
+//!         width: 1920,
+//!         height: 1080,
+//!     };
+//!     let settings = Fsr3Settings::default();
+//!     let upscaler = Fsr3Upscaler::new(&library, instance, physical_device, device.clone(), display_size, None, settings)?;
+//!     let frame_generator = Fsr3FrameGenerator::new(&library, instance, physical_device, device.clone(), allocator, display_size, settings);
+//!     frame.set_present_hook(Some(Box::new(frame_generator.clone())))?;
+//!     Ok((upscaler, frame_generator))
+//! }
+//! ```
+
+use std::ffi::c_void;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Result};
+use ash::prelude::VkResult;
+use ash::vk;
+use ash::vk::Handle;
+use libloading::Library;
+use thiserror::Error;
+
+use crate::command_buffer::command_pool::CommandPool;
+use crate::{
+    Allocator, DefaultAllocator, DeletionQueue, Device, Fence, Image, ImageCreateInfo, ImageView, Instance,
+    PhysicalDevice, PresentContext, PresentHook, Semaphore, Swapchain,
+};
+use crate::upscaler::{UpscaleDescription, UpscaleQuality, UpscaleResources, Upscaler};
+
+#[cfg(windows)]
+const LIBRARY_NAME: &str = "amd_fidelityfx_vk.dll";
+#[cfg(not(windows))]
+const LIBRARY_NAME: &str = "libamd_fidelityfx_vk.so";
+
+/// Timeout in nanoseconds for acquiring the swapchain image a generated frame is presented to. If no image is available in time,
+/// the frame is presented without a generated frame.
+const ACQUIRE_TIMEOUT: u64 = 100_000_000;
+
+type FfxContext = *mut c_void;
+
+// Structure types of the descriptions passed to the FidelityFX API.
+const DESC_TYPE_BACKEND_VK: u64 = 0x0000003;
+const DESC_TYPE_UPSCALE_CREATE: u64 = 0x00010000;
+const DESC_TYPE_UPSCALE_DISPATCH: u64 = 0x00010001;
+const DESC_TYPE_UPSCALE_RENDER_RESOLUTION: u64 = 0x00010003;
+const DESC_TYPE_UPSCALE_JITTER_PHASE_COUNT: u64 = 0x00010004;
+const DESC_TYPE_UPSCALE_JITTER_OFFSET: u64 = 0x00010005;
+const DESC_TYPE_FRAME_GENERATION_CREATE: u64 = 0x00020001;
+const DESC_TYPE_FRAME_GENERATION_CONFIGURE: u64 = 0x00020002;
+const DESC_TYPE_FRAME_GENERATION_DISPATCH: u64 = 0x00020003;
+const DESC_TYPE_FRAME_GENERATION_PREPARE: u64 = 0x00020004;
+
+// Values of `FfxApiResourceType`, `FfxApiResourceUsage` and `FfxApiResourceState`.
+const RESOURCE_TYPE_TEXTURE2D: u32 = 2;
+const RESOURCE_USAGE_READ_ONLY: u32 = 0;
+const RESOURCE_USAGE_UAV: u32 = 1 << 1;
+const RESOURCE_USAGE_DEPTH_TARGET: u32 = 1 << 2;
+const RESOURCE_STATE_UNORDERED_ACCESS: u32 = 1 << 1;
+const RESOURCE_STATE_COMPUTE_READ: u32 = 1 << 2;
+const RESOURCE_STATE_PRESENT: u32 = 1 << 7;
+
+// Values of `FfxApiBackbufferTransferFunction`.
+const TRANSFER_FUNCTION_SRGB: u32 = 0;
+const TRANSFER_FUNCTION_PQ: u32 = 1;
+const TRANSFER_FUNCTION_SCRGB: u32 = 2;
+
+/// Common header of all FidelityFX API descriptions.
+#[repr(C)]
+struct Header {
+    ty: u64,
+    p_next: *mut Header,
+}
+
+impl Header {
+    fn new(ty: u64) -> Self {
+        Self {
+            ty,
+            p_next: std::ptr::null_mut(),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+struct Dimensions2D {
+    width: u32,
+    height: u32,
+}
+
+impl From<vk::Extent2D> for Dimensions2D {
+    fn from(value: vk::Extent2D) -> Self {
+        Self {
+            width: value.width,
+            height: value.height,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+struct FloatCoords2D {
+    x: f32,
+    y: f32,
+}
+
+impl From<(f32, f32)> for FloatCoords2D {
+    fn from(value: (f32, f32)) -> Self {
+        Self {
+            x: value.0,
+            y: value.1,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+struct Rect2D {
+    left: i32,
+    top: i32,
+    width: i32,
+    height: i32,
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+struct ResourceDescription {
+    ty: u32,
+    format: u32,
+    width: u32,
+    height: u32,
+    depth: u32,
+    mip_count: u32,
+    flags: u32,
+    usage: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+struct Resource {
+    resource: *mut c_void,
+    description: ResourceDescription,
+    state: u32,
+}
+
+impl Default for Resource {
+    fn default() -> Self {
+        Self {
+            resource: std::ptr::null_mut(),
+            description: ResourceDescription::default(),
+            state: 0,
+        }
+    }
+}
+
+#[repr(C)]
+struct CreateBackendVk {
+    header: Header,
+    device: vk::Device,
+    physical_device: vk::PhysicalDevice,
+    get_device_proc_addr: vk::PFN_vkGetDeviceProcAddr,
+}
+
+#[repr(C)]
+struct CreateUpscale {
+    header: Header,
+    flags: u32,
+    max_render_size: Dimensions2D,
+    max_upscale_size: Dimensions2D,
+    message_callback: *const c_void,
+}
+
+#[repr(C)]
+struct DispatchUpscale {
+    header: Header,
+    command_list: *mut c_void,
+    color: Resource,
+    depth: Resource,
+    motion_vectors: Resource,
+    exposure: Resource,
+    reactive: Resource,
+    transparency_and_composition: Resource,
+    output: Resource,
+    jitter_offset: FloatCoords2D,
+    motion_vector_scale: FloatCoords2D,
+    render_size: Dimensions2D,
+    upscale_size: Dimensions2D,
+    enable_sharpening: bool,
+    sharpness: f32,
+    frame_time_delta: f32,
+    pre_exposure: f32,
+    reset: bool,
+    camera_near: f32,
+    camera_far: f32,
+    camera_fov_vertical: f32,
+    view_space_to_meters_factor: f32,
+    flags: u32,
+}
+
+#[repr(C)]
+struct QueryRenderResolution {
+    header: Header,
+    display_width: u32,
+    display_height: u32,
+    quality_mode: u32,
+    render_width: *mut u32,
+    render_height: *mut u32,
+}
+
+#[repr(C)]
+struct QueryJitterPhaseCount {
+    header: Header,
+    render_width: u32,
+    display_width: u32,
+    phase_count: *mut i32,
+}
+
+#[repr(C)]
+struct QueryJitterOffset {
+    header: Header,
+    index: i32,
+    phase_count: i32,
+    x: *mut f32,
+    y: *mut f32,
+}
+
+#[repr(C)]
+struct CreateFrameGeneration {
+    header: Header,
+    flags: u32,
+    display_size: Dimensions2D,
+    max_render_size: Dimensions2D,
+    back_buffer_format: u32,
+}
+
+#[repr(C)]
+struct ConfigureFrameGeneration {
+    header: Header,
+    swapchain: *mut c_void,
+    present_callback: *const c_void,
+    present_callback_context: *mut c_void,
+    frame_generation_callback: *const c_void,
+    frame_generation_callback_context: *mut c_void,
+    enabled: bool,
+    allow_async_workloads: bool,
+    hudless_color: Resource,
+    flags: u32,
+    only_present_generated: bool,
+    generation_rect: Rect2D,
+    frame_id: u64,
+}
+
+#[repr(C)]
+struct DispatchFrameGeneration {
+    header: Header,
+    command_list: *mut c_void,
+    present_color: Resource,
+    outputs: [Resource; 4],
+    generated_frame_count: u32,
+    reset: bool,
+    back_buffer_transfer_function: u32,
+    min_max_luminance: [f32; 2],
+    generation_rect: Rect2D,
+    frame_id: u64,
+}
+
+#[repr(C)]
+struct PrepareFrameGeneration {
+    header: Header,
+    frame_id: u64,
+    flags: u32,
+    command_list: *mut c_void,
+    render_size: Dimensions2D,
+    jitter_offset: FloatCoords2D,
+    motion_vector_scale: FloatCoords2D,
+    frame_time_delta: f32,
+    unused_reset: bool,
+    camera_near: f32,
+    camera_far: f32,
+    camera_fov_vertical: f32,
+    view_space_to_meters_factor: f32,
+    depth: Resource,
+    motion_vectors: Resource,
+}
+
+/// Function pointers loaded from the FidelityFX API library. These stay valid as long as the library is loaded.
+#[derive(Copy, Clone)]
+struct Functions {
+    create_context: unsafe extern "C" fn(context: *mut FfxContext, desc: *mut Header, allocator: *const c_void) -> u32,
+    destroy_context: unsafe extern "C" fn(context: *mut FfxContext, allocator: *const c_void) -> u32,
+    configure: unsafe extern "C" fn(context: *mut FfxContext, desc: *const Header) -> u32,
+    query: unsafe extern "C" fn(context: *mut FfxContext, desc: *mut Header) -> u32,
+    dispatch: unsafe extern "C" fn(context: *mut FfxContext, desc: *const Header) -> u32,
+}
+
+/// FidelityFX API error, stores the return code that describes the cause of the error
+#[derive(Debug, Error)]
+pub struct Fsr3Error {
+    /// Return code of the failed FidelityFX API call.
+    pub code: u32,
+}
+
+impl Display for Fsr3Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.code {
+            1 => write!(f, "Generic error"),
+            2 => write!(f, "Unknown description type"),
+            3 => write!(f, "Runtime error"),
+            4 => write!(f, "No provider found for the description"),
+            5 => write!(f, "Memory error"),
+            6 => write!(f, "Invalid parameter"),
+            code => write!(f, "Unknown error {code}"),
+        }
+    }
+}
+
+/// Check the FidelityFX API return code for an Ok status, and return Ok(()) in that case, or an error result instead.
+fn check(code: u32) -> Result<()> {
+    if code == 0 {
+        Ok(())
+    } else {
+        Err(Fsr3Error {
+            code,
+        }
+        .into())
+    }
+}
+
+struct LibraryInner {
+    // Keeps the function pointers valid for as long as the library is in use.
+    _library: Library,
+    functions: Functions,
+}
+
+/// The loaded FidelityFX API library. This is a cheap handle, contexts created from it keep the library loaded.
+#[derive(Derivative, Clone)]
+#[derivative(Debug)]
+pub struct Fsr3Library {
+    #[derivative(Debug = "ignore")]
+    inner: Arc<LibraryInner>,
+}
+
+impl Fsr3Library {
+    /// Load the FidelityFX API library from the library search path.
+    /// # Errors
+    /// * Fails if the library could not be found, or does not export the FidelityFX API.
+    pub fn load() -> Result<Self> {
+        Self::load_from(LIBRARY_NAME)
+    }
+
+    /// Load the FidelityFX API library from a specific path.
+    /// # Errors
+    /// * Fails if the library could not be found, or does not export the FidelityFX API.
+    pub fn load_from(path: impl AsRef<Path>) -> Result<Self> {
+        // SAFETY: Loading the FidelityFX API library does not run any initialization with preconditions.
+        let library = unsafe { Library::new(path.as_ref())? };
+        // SAFETY: These symbols are declared with the signatures from the FidelityFX API headers.
+        let functions = unsafe {
+            Functions {
+                create_context: *library.get(b"ffxCreateContext\0")?,
+                destroy_context: *library.get(b"ffxDestroyContext\0")?,
+                configure: *library.get(b"ffxConfigure\0")?,
+                query: *library.get(b"ffxQuery\0")?,
+                dispatch: *library.get(b"ffxDispatch\0")?,
+            }
+        };
+        Ok(Self {
+            inner: Arc::new(LibraryInner {
+                _library: library,
+                functions,
+            }),
+        })
+    }
+}
+
+/// Handles needed to create contexts with the Vulkan backend.
+#[derive(Copy, Clone)]
+struct Backend {
+    device: vk::Device,
+    physical_device: vk::PhysicalDevice,
+    get_device_proc_addr: vk::PFN_vkGetDeviceProcAddr,
+}
+
+impl Backend {
+    fn new(instance: &Instance, physical_device: &PhysicalDevice, device: &Device) -> Self {
+        Self {
+            // SAFETY: We have valid references to the device and physical device, so their handles are valid.
+            device: unsafe { device.handle() }.handle(),
+            physical_device: unsafe { physical_device.handle() },
+            get_device_proc_addr: instance.fp_v1_0().get_device_proc_addr,
+        }
+    }
+}
+
+/// An FidelityFX API context, destroyed when dropped.
+struct Context {
+    library: Fsr3Library,
+    handle: FfxContext,
+}
+
+impl Context {
+    /// Create a context from a creation description. The Vulkan backend description is chained in.
+    /// # Safety
+    /// `header` must be the header of a valid creation description.
+    unsafe fn new(library: &Fsr3Library, backend: Backend, header: &mut Header) -> Result<Self> {
+        let mut backend = CreateBackendVk {
+            header: Header::new(DESC_TYPE_BACKEND_VK),
+            device: backend.device,
+            physical_device: backend.physical_device,
+            get_device_proc_addr: backend.get_device_proc_addr,
+        };
+        header.p_next = &mut backend.header;
+        let mut handle = std::ptr::null_mut();
+        let result = (library.inner.functions.create_context)(&mut handle, header, std::ptr::null());
+        header.p_next = std::ptr::null_mut();
+        check(result)?;
+        Ok(Self {
+            library: library.clone(),
+            handle,
+        })
+    }
+
+    /// # Safety
+    /// `header` must be the header of a valid configure description for this context.
+    unsafe fn configure(&mut self, header: &Header) -> Result<()> {
+        check((self.library.inner.functions.configure)(&mut self.handle, header))
+    }
+
+    /// # Safety
+    /// `header` must be the header of a valid query description for this context, with valid output pointers.
+    unsafe fn query(&mut self, header: &mut Header) -> Result<()> {
+        check((self.library.inner.functions.query)(&mut self.handle, header))
+    }
+
+    /// # Safety
+    /// `header` must be the header of a valid dispatch description for this context.
+    unsafe fn dispatch(&mut self, header: &Header) -> Result<()> {
+        check((self.library.inner.functions.dispatch)(&mut self.handle, header))
+    }
+}
+
+// SAFETY: The FidelityFX API allows using a context from any thread, as long as access is externally synchronized.
+unsafe impl Send for Context {}
+
+impl Drop for Context {
+    fn drop(&mut self) {
+        // SAFETY: The context was created in `Context::new()` and is not used after this.
+        let result = unsafe { (self.library.inner.functions.destroy_context)(&mut self.handle, std::ptr::null()) };
+        if let Err(e) = check(result) {
+            error!("Failed to destroy FidelityFX context: {e}");
+        }
+    }
+}
+
+/// Get the `FfxApiSurfaceFormat` for a Vulkan format. Depth formats map to the color format with the same layout.
+fn surface_format(format: vk::Format) -> u32 {
+    match format {
+        vk::Format::R32G32B32A32_UINT => 2,
+        vk::Format::R32G32B32A32_SFLOAT => 3,
+        vk::Format::R16G16B16A16_SFLOAT => 4,
+        vk::Format::R32G32B32_SFLOAT => 5,
+        vk::Format::R32G32_SFLOAT => 6,
+        vk::Format::R8_UINT => 7,
+        vk::Format::R32_UINT => 8,
+        vk::Format::R8G8B8A8_UNORM => 10,
+        vk::Format::R8G8B8A8_SNORM => 11,
+        vk::Format::R8G8B8A8_SRGB => 12,
+        vk::Format::B8G8R8A8_UNORM => 14,
+        vk::Format::B8G8R8A8_SRGB => 15,
+        vk::Format::B10G11R11_UFLOAT_PACK32 => 16,
+        vk::Format::A2B10G10R10_UNORM_PACK32 => 17,
+        vk::Format::R16G16_SFLOAT => 18,
+        vk::Format::R16G16_UINT => 19,
+        vk::Format::R16G16_SINT => 20,
+        vk::Format::R16_SFLOAT => 21,
+        vk::Format::R16_UINT => 22,
+        vk::Format::R16_UNORM | vk::Format::D16_UNORM => 23,
+        vk::Format::R16_SNORM => 24,
+        vk::Format::R8_UNORM => 25,
+        vk::Format::R8G8_UNORM => 26,
+        vk::Format::R8G8_UINT => 27,
+        vk::Format::R32_SFLOAT | vk::Format::D32_SFLOAT | vk::Format::D32_SFLOAT_S8_UINT => 28,
+        vk::Format::E5B9G9R9_UFLOAT_PACK32 => 29,
+        _ => 0,
+    }
+}
+
+/// Describe a 2D image to the FidelityFX API.
+fn image_resource(image: vk::Image, format: vk::Format, extent: vk::Extent2D, usage: u32, state: u32) -> Resource {
+    Resource {
+        resource: image.as_raw() as *mut c_void,
+        description: ResourceDescription {
+            ty: RESOURCE_TYPE_TEXTURE2D,
+            format: surface_format(format),
+            width: extent.width,
+            height: extent.height,
+            depth: 1,
+            mip_count: 1,
+            flags: 0,
+            usage,
+        },
+        state,
+    }
+}
+
+/// Describe an image view that is read by a compute shader to the FidelityFX API.
+fn view_resource(view: &ImageView) -> Resource {
+    let usage = if view.aspect().contains(vk::ImageAspectFlags::DEPTH) {
+        RESOURCE_USAGE_DEPTH_TARGET
+    } else {
+        RESOURCE_USAGE_READ_ONLY
+    };
+    let extent = vk::Extent2D {
+        width: view.width(),
+        height: view.height(),
+    };
+    // SAFETY: We have a valid reference to the image view, so its image is valid.
+    image_resource(unsafe { view.image() }, view.format(), extent, usage, RESOURCE_STATE_COMPUTE_READ)
+}
+
+fn frametime_delta_ms(descr: &UpscaleDescription) -> f32 {
+    descr.frametime_delta.as_secs_f32() * 1000.0
+}
+
+/// Settings shared by FSR3 upscaling and frame generation.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Fsr3Settings {
+    /// The color buffers contain high dynamic range values.
+    pub hdr: bool,
+    /// The depth buffer uses reversed depth, with the near plane at 1.0.
+    pub depth_inverted: bool,
+    /// The depth buffer uses an infinite far plane.
+    pub depth_infinite: bool,
+    /// Motion vectors are rendered at display resolution instead of render resolution.
+    pub display_resolution_motion_vectors: bool,
+    /// Motion vectors contain the camera jitter, which must be cancelled out.
+    pub motion_vector_jitter_cancellation: bool,
+    /// Compute the exposure automatically instead of reading it from [`UpscaleResources::exposure`]. Only used for upscaling.
+    pub auto_exposure: bool,
+    /// The render resolution changes between frames. Only used for upscaling.
+    pub dynamic_resolution: bool,
+}
+
+impl Fsr3Settings {
+    fn upscale_flags(&self) -> u32 {
+        let mut flags = 0;
+        for (enabled, flag) in [
+            (self.hdr, 1 << 0),
+            (self.display_resolution_motion_vectors, 1 << 1),
+            (self.motion_vector_jitter_cancellation, 1 << 2),
+            (self.depth_inverted, 1 << 3),
+            (self.depth_infinite, 1 << 4),
+            (self.auto_exposure, 1 << 5),
+            (self.dynamic_resolution, 1 << 6),
+        ] {
+            if enabled {
+                flags |= flag;
+            }
+        }
+        flags
+    }
+
+    fn frame_generation_flags(&self) -> u32 {
+        let mut flags = 0;
+        for (enabled, flag) in [
+            (self.display_resolution_motion_vectors, 1 << 1),
+            (self.motion_vector_jitter_cancellation, 1 << 2),
+            (self.depth_inverted, 1 << 3),
+            (self.depth_infinite, 1 << 4),
+            (self.hdr, 1 << 5),
+        ] {
+            if enabled {
+                flags |= flag;
+            }
+        }
+        flags
+    }
+}
+
+/// [`Upscaler`] implementation using FSR3 upscaling.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct Fsr3Upscaler {
+    library: Fsr3Library,
+    #[derivative(Debug = "ignore")]
+    backend: Backend,
+    #[derivative(Debug = "ignore")]
+    context: Context,
+    #[derivative(Debug = "ignore")]
+    deferred_context_delete: DeletionQueue<Context>,
+    settings: Fsr3Settings,
+    display_size: vk::Extent2D,
+    max_render_size: vk::Extent2D,
+    current_frame: usize,
+}
+
+impl Fsr3Upscaler {
+    /// Create an FSR3 upscaler. If `max_render_size` is `None`, it is assumed to be equal to the display size.
+    /// # Errors
+    /// * Fails if the FidelityFX API could not create an upscaling context, for example because the device is not supported.
+    pub fn new(
+        library: &Fsr3Library,
+        instance: &Instance,
+        physical_device: &PhysicalDevice,
+        device: Device,
+        display_size: vk::Extent2D,
+        max_render_size: Option<vk::Extent2D>,
+        settings: Fsr3Settings,
+    ) -> Result<Self> {
+        let backend = Backend::new(instance, physical_device, &device);
+        let max_render_size = max_render_size.unwrap_or(display_size);
+        let context = Self::create_context(library, backend, settings, display_size, max_render_size)?;
+        Ok(Self {
+            library: library.clone(),
+            backend,
+            context,
+            deferred_context_delete: DeletionQueue::new(4),
+            settings,
+            display_size,
+            max_render_size,
+            current_frame: 0,
+        })
+    }
+
+    fn create_context(
+        library: &Fsr3Library,
+        backend: Backend,
+        settings: Fsr3Settings,
+        display_size: vk::Extent2D,
+        max_render_size: vk::Extent2D,
+    ) -> Result<Context> {
+        let mut desc = CreateUpscale {
+            header: Header::new(DESC_TYPE_UPSCALE_CREATE),
+            flags: settings.upscale_flags(),
+            max_render_size: max_render_size.into(),
+            max_upscale_size: display_size.into(),
+            message_callback: std::ptr::null(),
+        };
+        // SAFETY: The header belongs to a valid upscale creation description.
+        unsafe { Context::new(library, backend, &mut desc.header) }
+    }
+
+    /// Get the settings this upscaler was created with.
+    pub fn settings(&self) -> &Fsr3Settings {
+        &self.settings
+    }
+}
+
+impl Upscaler for Fsr3Upscaler {
+    fn dispatch(&mut self, descr: &UpscaleDescription, resources: &UpscaleResources, cmd: vk::CommandBuffer) -> Result<()> {
+        // Clean up old contexts after resizes
+        self.deferred_context_delete.next_frame();
+        let output_extent = vk::Extent2D {
+            width: resources.output.width(),
+            height: resources.output.height(),
+        };
+        // SAFETY: We have a valid reference to the output view, so its image is valid.
+        let output = image_resource(
+            unsafe { resources.output.image() },
+            resources.output.format(),
+            output_extent,
+            RESOURCE_USAGE_UAV,
+            RESOURCE_STATE_UNORDERED_ACCESS,
+        );
+        let desc = DispatchUpscale {
+            header: Header::new(DESC_TYPE_UPSCALE_DISPATCH),
+            command_list: cmd.as_raw() as *mut c_void,
+            color: view_resource(&resources.color),
+            depth: view_resource(&resources.depth),
+            motion_vectors: view_resource(&resources.motion_vectors),
+            exposure: resources.exposure.as_ref().map(view_resource).unwrap_or_default(),
+            reactive: Resource::default(),
+            transparency_and_composition: Resource::default(),
+            output,
+            jitter_offset: descr.jitter_offset.into(),
+            motion_vector_scale: descr.motion_vector_scale.into(),
+            render_size: Dimensions2D {
+                width: resources.color.width(),
+                height: resources.color.height(),
+            },
+            upscale_size: self.display_size.into(),
+            enable_sharpening: descr.sharpness.is_some(),
+            sharpness: descr.sharpness.unwrap_or_default(),
+            frame_time_delta: frametime_delta_ms(descr),
+            pre_exposure: descr.pre_exposure,
+            reset: descr.reset,
+            camera_near: descr.camera_near,
+            camera_far: descr.camera_far,
+            camera_fov_vertical: descr.camera_fov_vertical,
+            view_space_to_meters_factor: 1.0,
+            flags: 0,
+        };
+        // SAFETY: The header belongs to a valid upscale dispatch description.
+        unsafe { self.context.dispatch(&desc.header)? };
+        self.current_frame += 1;
+        Ok(())
+    }
+
+    fn jitter_phase_count(&mut self, render_width: u32) -> u32 {
+        let mut phase_count = 0;
+        let mut desc = QueryJitterPhaseCount {
+            header: Header::new(DESC_TYPE_UPSCALE_JITTER_PHASE_COUNT),
+            render_width,
+            display_width: self.display_size.width,
+            phase_count: &mut phase_count,
+        };
+        // SAFETY: The header belongs to a valid query description, and its output pointer is valid for writes.
+        match unsafe { self.context.query(&mut desc.header) } {
+            Ok(()) => phase_count.max(1) as u32,
+            Err(e) => {
+                error!("Failed to query FSR3 jitter phase count: {e}");
+                1
+            }
+        }
+    }
+
+    fn jitter_offset(&mut self, render_width: u32) -> Result<(f32, f32)> {
+        let phase_count = self.jitter_phase_count(render_width);
+        let index = self.current_frame % phase_count as usize;
+        let mut x = 0.0;
+        let mut y = 0.0;
+        let mut desc = QueryJitterOffset {
+            header: Header::new(DESC_TYPE_UPSCALE_JITTER_OFFSET),
+            index: index as i32,
+            phase_count: phase_count as i32,
+            x: &mut x,
+            y: &mut y,
+        };
+        // SAFETY: The header belongs to a valid query description, and its output pointers are valid for writes.
+        unsafe { self.context.query(&mut desc.header)? };
+        Ok((x, y))
+    }
+
+    fn display_size(&self) -> vk::Extent2D {
+        self.display_size
+    }
+
+    fn render_resolution(&mut self, quality: UpscaleQuality) -> Result<vk::Extent2D> {
+        let mut width = 0;
+        let mut height = 0;
+        let mut desc = QueryRenderResolution {
+            header: Header::new(DESC_TYPE_UPSCALE_RENDER_RESOLUTION),
+            display_width: self.display_size.width,
+            display_height: self.display_size.height,
+            quality_mode: match quality {
+                UpscaleQuality::Quality => 1,
+                UpscaleQuality::Balanced => 2,
+                UpscaleQuality::Performance => 3,
+                UpscaleQuality::UltraPerformance => 4,
+            },
+            render_width: &mut width,
+            render_height: &mut height,
+        };
+        // SAFETY: The header belongs to a valid query description, and its output pointers are valid for writes.
+        unsafe { self.context.query(&mut desc.header)? };
+        Ok(vk::Extent2D {
+            width,
+            height,
+        })
+    }
+
+    fn resize(&mut self, display_size: vk::Extent2D, max_render_size: Option<vk::Extent2D>) -> Result<()> {
+        let max_render_size = max_render_size.unwrap_or(display_size);
+        if display_size == self.display_size && max_render_size == self.max_render_size {
+            return Ok(());
+        }
+        let context = Self::create_context(&self.library, self.backend, self.settings, display_size, max_render_size)?;
+        // The old context may still be in use by frames in flight.
+        let old_context = std::mem::replace(&mut self.context, context);
+        self.deferred_context_delete.push(old_context);
+        self.display_size = display_size;
+        self.max_render_size = max_render_size;
+        self.current_frame = 0;
+        Ok(())
+    }
+}
+
+/// Per frame in flight resources used to copy a generated frame into the swapchain.
+struct GenerationFrame {
+    cmd: vk::CommandBuffer,
+    fence: Fence,
+    image_available: Semaphore,
+    generated_ready: Semaphore,
+    real_ready: Semaphore,
+}
+
+/// A generated frame that is ready to be presented.
+struct GeneratedFrame {
+    image_index: u32,
+    generated_ready: vk::Semaphore,
+    real_ready: vk::Semaphore,
+}
+
+struct FrameGeneratorInner<A: Allocator> {
+    library: Fsr3Library,
+    backend: Backend,
+    device: Device,
+    allocator: A,
+    settings: Fsr3Settings,
+    max_render_size: vk::Extent2D,
+    enabled: bool,
+    display_size: vk::Extent2D,
+    format: vk::Format,
+    // Whether generated frames can be copied into the swapchain images.
+    can_copy: bool,
+    context: Option<Context>,
+    deferred_context_delete: DeletionQueue<Context>,
+    // Frame generation writes to this image, since swapchain images usually do not support storage usage.
+    output: Option<Image<A>>,
+    frame_id: u64,
+    // Whether `prepare()` was recorded for the current frame.
+    prepared: bool,
+    frames: Vec<GenerationFrame>,
+    command_pool: Option<(u32, CommandPool)>,
+}
+
+impl<A: Allocator> FrameGeneratorInner<A> {
+    fn wait_for_frames(&mut self) -> Result<()> {
+        for frame in &mut self.frames {
+            frame.fence.wait()?;
+        }
+        Ok(())
+    }
+
+    fn recreate(&mut self) -> Result<()> {
+        self.wait_for_frames()?;
+        let mut desc = CreateFrameGeneration {
+            header: Header::new(DESC_TYPE_FRAME_GENERATION_CREATE),
+            flags: self.settings.frame_generation_flags(),
+            display_size: self.display_size.into(),
+            max_render_size: self.max_render_size.into(),
+            back_buffer_format: surface_format(self.format),
+        };
+        // SAFETY: The header belongs to a valid frame generation creation description.
+        let context = unsafe { Context::new(&self.library, self.backend, &mut desc.header)? };
+        // The old context may still be used by prepare commands of frames in flight.
+        if let Some(old_context) = self.context.replace(context) {
+            self.deferred_context_delete.push(old_context);
+        }
+        self.output = Some(Image::new(
+            self.device.clone(),
+            &mut self.allocator,
+            ImageCreateInfo {
+                width: self.display_size.width,
+                height: self.display_size.height,
+                usage: vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::TRANSFER_SRC,
+                format: self.format,
+                // sRGB formats do not support storage usage, so the FidelityFX API writes to it through a view with the UNORM format.
+                flags: vk::ImageCreateFlags::MUTABLE_FORMAT | vk::ImageCreateFlags::EXTENDED_USAGE,
+                ..Default::default()
+            },
+        )?);
+        self.configure()
+    }
+
+    fn configure(&mut self) -> Result<()> {
+        let generation_rect = self.generation_rect();
+        let Some(context) = &mut self.context else {
+            return Ok(());
+        };
+        let desc = ConfigureFrameGeneration {
+            header: Header::new(DESC_TYPE_FRAME_GENERATION_CONFIGURE),
+            // Generated frames are dispatched and presented by the present hook, not by a FidelityFX swapchain.
+            swapchain: std::ptr::null_mut(),
+            present_callback: std::ptr::null(),
+            present_callback_context: std::ptr::null_mut(),
+            frame_generation_callback: std::ptr::null(),
+            frame_generation_callback_context: std::ptr::null_mut(),
+            enabled: self.enabled,
+            allow_async_workloads: false,
+            hudless_color: Resource::default(),
+            flags: 0,
+            only_present_generated: false,
+            generation_rect,
+            frame_id: self.frame_id,
+        };
+        // SAFETY: The header belongs to a valid frame generation configure description.
+        unsafe { context.configure(&desc.header) }
+    }
+
+    fn generation_rect(&self) -> Rect2D {
+        Rect2D {
+            left: 0,
+            top: 0,
+            width: self.display_size.width as i32,
+            height: self.display_size.height as i32,
+        }
+    }
+
+    fn prepare(
+        &mut self,
+        descr: &UpscaleDescription,
+        depth: &ImageView,
+        motion_vectors: &ImageView,
+        cmd: vk::CommandBuffer,
+    ) -> Result<()> {
+        let frame_id = self.frame_id;
+        let Some(context) = self.context.as_mut().filter(|_| self.enabled) else {
+            return Ok(());
+        };
+        let desc = PrepareFrameGeneration {
+            header: Header::new(DESC_TYPE_FRAME_GENERATION_PREPARE),
+            frame_id,
+            flags: 0,
+            command_list: cmd.as_raw() as *mut c_void,
+            render_size: Dimensions2D {
+                width: depth.width(),
+                height: depth.height(),
+            },
+            jitter_offset: descr.jitter_offset.into(),
+            motion_vector_scale: descr.motion_vector_scale.into(),
+            frame_time_delta: frametime_delta_ms(descr),
+            unused_reset: false,
+            camera_near: descr.camera_near,
+            camera_far: descr.camera_far,
+            camera_fov_vertical: descr.camera_fov_vertical,
+            view_space_to_meters_factor: 1.0,
+            depth: view_resource(depth),
+            motion_vectors: view_resource(motion_vectors),
+        };
+        // SAFETY: The header belongs to a valid frame generation prepare description.
+        unsafe { context.dispatch(&desc.header)? };
+        self.prepared = true;
+        Ok(())
+    }
+
+    /// Get the resources for a frame in flight, creating them if needed.
+    fn frame(&mut self, frame_index: usize, queue_family_index: u32) -> Result<&mut GenerationFrame> {
+        if self.command_pool.as_ref().map(|(family, _)| *family) != Some(queue_family_index) {
+            // The present queue changed, so command buffers must be allocated from a new pool.
+            self.wait_for_frames()?;
+            self.frames.clear();
+            let pool = CommandPool::new(
+                self.device.clone(),
+                queue_family_index,
+                vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER,
+            )?;
+            self.command_pool = Some((queue_family_index, pool));
+        }
+        let Some((_, pool)) = &self.command_pool else {
+            unreachable!("command pool was created above");
+        };
+        while self.frames.len() <= frame_index {
+            let info = vk::CommandBufferAllocateInfo {
+                // SAFETY: The command pool is only used from this frame generator, which is externally synchronized.
+                command_pool: unsafe { pool.handle() },
+                level: vk::CommandBufferLevel::PRIMARY,
+                command_buffer_count: 1,
+                ..Default::default()
+            };
+            let cmd = unsafe { self.device.allocate_command_buffers(&info)? }[0];
+            self.frames.push(GenerationFrame {
+                cmd,
+                fence: Fence::new(self.device.clone(), true)?,
+                image_available: Semaphore::new(self.device.clone())?,
+                generated_ready: Semaphore::new(self.device.clone())?,
+                real_ready: Semaphore::new(self.device.clone())?,
+            });
+        }
+        Ok(&mut self.frames[frame_index])
+    }
+
+    /// Generate a frame and copy it into a newly acquired swapchain image. Returns `None` if no frame should be generated.
+    fn generate(&mut self, ctx: &PresentContext) -> Result<Option<GeneratedFrame>> {
+        self.deferred_context_delete.next_frame();
+        let frame_id = self.frame_id;
+        self.frame_id += 1;
+        let prepared = std::mem::take(&mut self.prepared);
+        if !self.enabled || !prepared || !self.can_copy || self.context.is_none() {
+            return Ok(None);
+        }
+        let generation_rect = self.generation_rect();
+        let display_size = self.display_size;
+        let format = self.format;
+        let hdr = self.settings.hdr;
+        let device = self.device.clone();
+
+        let frame = self.frame(ctx.frame_index, ctx.queue_family_index)?;
+        frame.fence.wait()?;
+        // SAFETY: The swapchain is valid for the duration of the present call.
+        let acquired = unsafe {
+            ctx.swapchain.acquire_next_image(
+                ctx.swapchain.handle(),
+                ACQUIRE_TIMEOUT,
+                frame.image_available.handle(),
+                vk::Fence::null(),
+            )
+        };
+        let image_index = match acquired {
+            Ok((index, _)) => index,
+            // The real frame is still presented, which recreates the swapchain if it is out of date.
+            Err(vk::Result::TIMEOUT | vk::Result::NOT_READY | vk::Result::ERROR_OUT_OF_DATE_KHR) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let cmd = frame.cmd;
+        // SAFETY: The semaphores are valid for as long as the frame resources are alive.
+        let (image_available, generated_ready, real_ready) =
+            unsafe { (frame.image_available.handle(), frame.generated_ready.handle(), frame.real_ready.handle()) };
+        // SAFETY: The fence is valid for as long as the frame resources are alive.
+        let fence = unsafe { frame.fence.handle() };
+
+        let swapchain_images = ctx.swapchain.images();
+        // SAFETY: The swapchain images are valid for the duration of the present call.
+        let real_image = unsafe { swapchain_images[ctx.image_index as usize].image.handle() };
+        let generated_image = unsafe { swapchain_images[image_index as usize].image.handle() };
+        let Some(output) = &self.output else {
+            return Err(anyhow!("frame generation output image was not created"));
+        };
+        // SAFETY: The output image is valid for as long as the frame generator is alive.
+        let output_image = unsafe { output.handle() };
+        let range = vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+        let barrier = |image, old_layout, new_layout, src_access_mask, dst_access_mask| vk::ImageMemoryBarrier {
+            src_access_mask,
+            dst_access_mask,
+            old_layout,
+            new_layout,
+            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            image,
+            subresource_range: range,
+            ..Default::default()
+        };
+
+        // SAFETY: The command buffer is not in use, since its fence was waited on.
+        unsafe {
+            device.begin_command_buffer(
+                cmd,
+                &vk::CommandBufferBeginInfo {
+                    flags: vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT,
+                    ..Default::default()
+                },
+            )?;
+            // The previous contents of the output image were already copied, so they can be discarded.
+            device.cmd_pipeline_barrier(
+                cmd,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[barrier(
+                    output_image,
+                    vk::ImageLayout::UNDEFINED,
+                    vk::ImageLayout::GENERAL,
+                    vk::AccessFlags::empty(),
+                    vk::AccessFlags::SHADER_WRITE,
+                )],
+            );
+        }
+        let desc = DispatchFrameGeneration {
+            header: Header::new(DESC_TYPE_FRAME_GENERATION_DISPATCH),
+            command_list: cmd.as_raw() as *mut c_void,
+            present_color: image_resource(real_image, format, display_size, RESOURCE_USAGE_READ_ONLY, RESOURCE_STATE_PRESENT),
+            outputs: [
+                image_resource(output_image, format, display_size, RESOURCE_USAGE_UAV, RESOURCE_STATE_UNORDERED_ACCESS),
+                Resource::default(),
+                Resource::default(),
+                Resource::default(),
+            ],
+            generated_frame_count: 1,
+            reset: false,
+            back_buffer_transfer_function: match (hdr, format) {
+                (false, _) => TRANSFER_FUNCTION_SRGB,
+                (true, vk::Format::R16G16B16A16_SFLOAT) => TRANSFER_FUNCTION_SCRGB,
+                (true, _) => TRANSFER_FUNCTION_PQ,
+            },
+            min_max_luminance: [0.0, 1000.0],
+            generation_rect,
+            frame_id,
+        };
+        let Some(context) = &mut self.context else {
+            unreachable!("frame generation context was checked above");
+        };
+        // SAFETY: The header belongs to a valid frame generation dispatch description, recorded into a command buffer
+        // in the recording state.
+        unsafe { context.dispatch(&desc.header)? };
+
+        // SAFETY: The command buffer is in the recording state, and all images are valid.
+        unsafe {
+            device.cmd_pipeline_barrier(
+                cmd,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[
+                    barrier(
+                        output_image,
+                        vk::ImageLayout::GENERAL,
+                        vk::ImageLayout::GENERAL,
+                        vk::AccessFlags::SHADER_WRITE,
+                        vk::AccessFlags::TRANSFER_READ,
+                    ),
+                    barrier(
+                        generated_image,
+                        vk::ImageLayout::UNDEFINED,
+                        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                        vk::AccessFlags::empty(),
+                        vk::AccessFlags::TRANSFER_WRITE,
+                    ),
+                ],
+            );
+            let layers = vk::ImageSubresourceLayers {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                mip_level: 0,
+                base_array_layer: 0,
+                layer_count: 1,
+            };
+            device.cmd_copy_image(
+                cmd,
+                output_image,
+                vk::ImageLayout::GENERAL,
+                generated_image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[vk::ImageCopy {
+                    src_subresource: layers,
+                    src_offset: vk::Offset3D::default(),
+                    dst_subresource: layers,
+                    dst_offset: vk::Offset3D::default(),
+                    extent: vk::Extent3D {
+                        width: display_size.width,
+                        height: display_size.height,
+                        depth: 1,
+                    },
+                }],
+            );
+            device.cmd_pipeline_barrier(
+                cmd,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[barrier(
+                    generated_image,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    vk::ImageLayout::PRESENT_SRC_KHR,
+                    vk::AccessFlags::TRANSFER_WRITE,
+                    vk::AccessFlags::empty(),
+                )],
+            );
+            device.end_command_buffer(cmd)?;
+
+            // Generation reads the real frame, so it waits for its rendering to complete. Both frames are presented after this.
+            let wait_semaphores = [ctx.wait_semaphore, image_available];
+            let wait_stages = [vk::PipelineStageFlags::ALL_COMMANDS, vk::PipelineStageFlags::TRANSFER];
+            let signal_semaphores = [generated_ready, real_ready];
+            let submit = vk::SubmitInfo::builder()
+                .wait_semaphores(&wait_semaphores)
+                .wait_dst_stage_mask(&wait_stages)
+                .command_buffers(std::slice::from_ref(&cmd))
+                .signal_semaphores(&signal_semaphores)
+                .build();
+            device.reset_fences(&[fence])?;
+            device.queue_submit(ctx.queue, &[submit], fence)?;
+        }
+        Ok(Some(GeneratedFrame {
+            image_index,
+            generated_ready,
+            real_ready,
+        }))
+    }
+}
+
+impl<A: Allocator> Drop for FrameGeneratorInner<A> {
+    fn drop(&mut self) {
+        // Generated frames may still be in flight, and their command buffers are freed with the command pool.
+        if let Err(e) = self.wait_for_frames() {
+            error!("Failed to wait for generated frames during teardown: {e}");
+        }
+    }
+}
+
+/// [`PresentHook`] implementation using FSR3 frame generation. See the [module-level documentation](self) for more information.
+///
+/// This is a shared handle, so it can be registered with the frame manager while a clone is used to prepare frame generation.
+#[derive(Derivative)]
+#[derivative(Debug, Clone(bound = ""))]
+pub struct Fsr3FrameGenerator<A: Allocator = DefaultAllocator> {
+    #[derivative(Debug = "ignore")]
+    inner: Arc<Mutex<FrameGeneratorInner<A>>>,
+}
+
+impl<A: Allocator> Fsr3FrameGenerator<A> {
+    /// Create an FSR3 frame generator. The frame generation context is created once the generator is registered with
+    /// [`FrameManager::set_present_hook()`](crate::FrameManager::set_present_hook), since it depends on the swapchain.
+    /// Frame generation is enabled by default.
+    pub fn new(
+        library: &Fsr3Library,
+        instance: &Instance,
+        physical_device: &PhysicalDevice,
+        device: Device,
+        allocator: A,
+        max_render_size: vk::Extent2D,
+        settings: Fsr3Settings,
+    ) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(FrameGeneratorInner {
+                library: library.clone(),
+                backend: Backend::new(instance, physical_device, &device),
+                device,
+                allocator,
+                settings,
+                max_render_size,
+                enabled: true,
+                display_size: vk::Extent2D::default(),
+                format: vk::Format::UNDEFINED,
+                can_copy: false,
+                context: None,
+                deferred_context_delete: DeletionQueue::new(4),
+                output: None,
+                frame_id: 0,
+                prepared: false,
+                frames: vec![],
+                command_pool: None,
+            })),
+        }
+    }
+
+    /// Enable or disable frame generation. While disabled, frames are presented as usual.
+    /// # Errors
+    /// * Fails if the frame generation context could not be reconfigured.
+    pub fn set_enabled(&self, enabled: bool) -> Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.enabled = enabled;
+        inner.configure()
+    }
+
+    /// Whether frame generation is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.inner.lock().unwrap().enabled
+    }
+
+    /// Set the maximum render resolution, for example after the upscaler was resized.
+    /// # Errors
+    /// * Fails if the frame generation context could not be recreated.
+    pub fn set_max_render_size(&self, max_render_size: vk::Extent2D) -> Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.max_render_size == max_render_size {
+            return Ok(());
+        }
+        inner.max_render_size = max_render_size;
+        if inner.context.is_some() {
+            inner.recreate()?;
+        }
+        Ok(())
+    }
+
+    /// Record the commands that prepare frame generation for the current frame into `cmd`. This must be called every frame,
+    /// after `depth` and `motion_vectors` were rendered, with the same description as the upscale dispatch. Frames without
+    /// a prepare call are presented without a generated frame. Like [`Upscaler::dispatch()`], this does not insert any
+    /// synchronization on the resources used.
+    /// # Errors
+    /// * Fails if the FidelityFX API reports an error while recording.
+    pub fn prepare(
+        &self,
+        descr: &UpscaleDescription,
+        depth: &ImageView,
+        motion_vectors: &ImageView,
+        cmd: vk::CommandBuffer,
+    ) -> Result<()> {
+        self.inner
+            .lock()
+            .unwrap()
+            .prepare(descr, depth, motion_vectors, cmd)
+    }
+}
+
+impl<A: Allocator> PresentHook for Fsr3FrameGenerator<A> {
+    fn swapchain_recreated(&mut self, swapchain: &Swapchain) -> Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.display_size = *swapchain.extent();
+        inner.format = swapchain.format().format;
+        inner.can_copy = swapchain
+            .image_usage()
+            .contains(vk::ImageUsageFlags::TRANSFER_DST);
+        if !inner.can_copy {
+            warn!("Swapchain images do not support transfer destination usage, FSR3 frame generation is disabled.");
+        }
+        inner.recreate()
+    }
+
+    fn present(&mut self, ctx: &PresentContext) -> VkResult<bool> {
+        let generated = match self.inner.lock().unwrap().generate(ctx) {
+            Ok(generated) => generated,
+            Err(e) => {
+                error!("FSR3 frame generation failed, presenting without a generated frame: {e}");
+                None
+            }
+        };
+        let Some(generated) = generated else {
+            return unsafe { ctx.swapchain.queue_present(ctx.queue, &ctx.present_info()) };
+        };
+        // SAFETY: The swapchain is valid for the duration of the present call.
+        let swapchain = unsafe { ctx.swapchain.handle() };
+        // The generated frame is interpolated between the previous frame and this one, so it is presented first.
+        let info = vk::PresentInfoKHR {
+            wait_semaphore_count: 1,
+            p_wait_semaphores: &generated.generated_ready,
+            swapchain_count: 1,
+            p_swapchains: &swapchain,
+            p_image_indices: &generated.image_index,
+            ..Default::default()
+        };
+        let generated_result = unsafe { ctx.swapchain.queue_present(ctx.queue, &info) };
+        let mut info = ctx.present_info();
+        info.p_wait_semaphores = &generated.real_ready;
+        let real_result = unsafe { ctx.swapchain.queue_present(ctx.queue, &info) };
+        let suboptimal = real_result?;
+        Ok(generated_result? || suboptimal)
+    }
+}
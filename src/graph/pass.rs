@@ -71,27 +71,74 @@
 //!
 //! Binding physical resources and recording is covered under the [`graph`](crate::graph) module documentation.
 
-#[cfg(feature = "fsr2")]
 use anyhow::{anyhow, bail};
 use anyhow::Result;
 use ash::vk;
 
-use crate::{Allocator, DefaultAllocator, Error, PhysicalResourceBindings, VirtualResource};
+use crate::{Allocator, ComputeSupport, DefaultAllocator, Error, ImageView, PhysicalResourceBindings, VirtualResource};
 #[cfg(feature = "fsr2")]
-use crate::{ComputeSupport, Device, ImageView};
+use crate::Device;
 use crate::command_buffer::IncompleteCommandBuffer;
 #[cfg(feature = "fsr2")]
 use crate::fsr2::{Fsr2DispatchDescription, Fsr2DispatchResources};
 use crate::graph::pass_graph::PassResource;
-#[cfg(feature = "fsr2")]
 use crate::graph::physical_resource::PhysicalResource;
 use crate::graph::resource::{AttachmentType, ResourceUsage};
 use crate::pipeline::PipelineStage;
 use crate::pool::LocalPool;
 use crate::sync::domain::ExecutionDomain;
-use crate::traits::GfxSupport;
+use crate::upscaler::{UpscaleDescription, UpscaleResources, Upscaler};
+use crate::traits::{GfxSupport, TransferCmdBuffer, TransferSupport};
+use crate::util::mipmaps::generate_mipmaps;
 use crate::util::to_vk::IntoVulkanType;
 
+/// Per-pass constants made available to pass executors by a graph with pass constants enabled through
+/// [`BuiltPassGraph::set_frame_index()`](crate::graph::pass_graph::BuiltPassGraph::set_frame_index).
+/// Inside an executor, read them with [`IncompleteCommandBuffer::pass_constants()`], or upload them with
+/// [`IncompleteCommandBuffer::push_pass_constants()`].
+///
+/// The layout matches the following GLSL block, so it can be used directly as a push constant or uniform buffer:
+/// ```glsl
+/// layout(push_constant) uniform PassConstants {
+///     uint frame_index;
+///     uint pass_index;
+///     uint frame_seed;
+///     uint pass_seed;
+/// } pass;
+/// ```
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PassConstants {
+    /// Frame index given to the graph.
+    pub frame_index: u32,
+    /// Index of the pass in recording order. The first user pass has index 0.
+    pub pass_index: u32,
+    /// Pseudo-random seed derived from the frame index. This is the same for every pass in a frame.
+    pub frame_seed: u32,
+    /// Pseudo-random seed derived from the frame index and pass index. This differs between passes in the same frame.
+    pub pass_seed: u32,
+}
+
+impl PassConstants {
+    /// Compute the pass constants for a pass. Seeds are deterministic, so recording the same frame twice gives the same values.
+    pub fn new(frame_index: u32, pass_index: u32) -> Self {
+        let frame_seed = pcg_hash(frame_index);
+        Self {
+            frame_index,
+            pass_index,
+            frame_seed,
+            pass_seed: pcg_hash(frame_seed ^ pass_index),
+        }
+    }
+}
+
+/// PCG hash, see <https://www.reedbeta.com/blog/hash-functions-for-gpu-rendering/>.
+fn pcg_hash(value: u32) -> u32 {
+    let state = value.wrapping_mul(747796405).wrapping_add(2891336453);
+    let word = ((state >> ((state >> 28) + 4)) ^ state).wrapping_mul(277803737);
+    (word >> 22) ^ word
+}
+
 /// The returned value from a pass callback function.
 pub type PassFnResult<'q, D, A> = Result<IncompleteCommandBuffer<'q, D, A>>;
 
@@ -171,6 +218,7 @@ pub struct Pass<'cb, D: ExecutionDomain, U = (), A: Allocator = DefaultAllocator
     #[derivative(Debug = "ignore")]
     pub(crate) execute: BoxedPassFn<'cb, D, U, A>,
     pub(crate) is_renderpass: bool,
+    pub(crate) view_mask: u32,
 }
 
 /// Represents a clear color for an attachment. The variant used should match
@@ -262,6 +310,7 @@ impl<'cb, D: ExecutionDomain, U, A: Allocator> PassBuilder<'cb, D, U, A> {
                 inputs: vec![],
                 outputs: vec![],
                 is_renderpass: false,
+                view_mask: 0,
             },
         }
     }
@@ -276,6 +325,7 @@ impl<'cb, D: ExecutionDomain, U, A: Allocator> PassBuilder<'cb, D, U, A> {
                 inputs: vec![],
                 outputs: vec![],
                 is_renderpass: true,
+                view_mask: 0,
             },
         }
     }
@@ -298,6 +348,7 @@ impl<'cb, D: ExecutionDomain, U, A: Allocator> PassBuilder<'cb, D, U, A> {
             outputs: vec![],
             execute: EmptyPassExecutor::new_boxed(),
             is_renderpass: false,
+            view_mask: 0,
         }
     }
 
@@ -314,7 +365,39 @@ impl<'cb, D: ExecutionDomain, U, A: Allocator> PassBuilder<'cb, D, U, A> {
         self
     }
 
+    /// Declare that the depth aspect of an image will be sampled in the given pipeline stages. The depth aspect is transitioned
+    /// to [`vk::ImageLayout::DEPTH_READ_ONLY_OPTIMAL`], independently of the stencil aspect. Bind a depth-only view
+    /// (see [`Image::depth_view()`](crate::Image::depth_view)) to sample from it.
+    pub fn sample_depth(mut self, resource: &VirtualResource, stage: PipelineStage) -> Self {
+        self.inner.inputs.push(PassResource {
+            usage: ResourceUsage::ShaderRead,
+            resource: resource.clone(),
+            stage,
+            layout: vk::ImageLayout::DEPTH_READ_ONLY_OPTIMAL,
+            clear_value: None,
+            load_op: None,
+        });
+        self
+    }
+
+    /// Declare that the stencil aspect of an image will be sampled in the given pipeline stages. The stencil aspect is transitioned
+    /// to [`vk::ImageLayout::STENCIL_READ_ONLY_OPTIMAL`], independently of the depth aspect. Bind a stencil-only view
+    /// (see [`Image::stencil_view()`](crate::Image::stencil_view)) to sample from it.
+    pub fn sample_stencil(mut self, resource: &VirtualResource, stage: PipelineStage) -> Self {
+        self.inner.inputs.push(PassResource {
+            usage: ResourceUsage::ShaderRead,
+            resource: resource.clone(),
+            stage,
+            layout: vk::ImageLayout::STENCIL_READ_ONLY_OPTIMAL,
+            clear_value: None,
+            load_op: None,
+        });
+        self
+    }
+
     /// Declare that a resource will be used as a storage image that is written to in the given pipeline stages.
+    /// Bind a single mip level to the resource, see [`Image::storage_view()`](crate::Image::storage_view). This also covers
+    /// 3D images, which are transitioned as a whole mip level.
     pub fn write_storage_image(mut self, resource: &VirtualResource, stage: PipelineStage) -> Self {
         self.inner.inputs.push(PassResource {
             usage: ResourceUsage::ShaderWrite,
@@ -348,6 +431,79 @@ impl<'cb, D: ExecutionDomain, U, A: Allocator> PassBuilder<'cb, D, U, A> {
         self
     }
 
+    /// Declare that a buffer will be read from in the given pipeline stages.
+    pub fn read_buffer(mut self, resource: &VirtualResource, stage: PipelineStage) -> Self {
+        self.inner.inputs.push(PassResource {
+            usage: ResourceUsage::ShaderRead,
+            resource: resource.clone(),
+            stage,
+            layout: vk::ImageLayout::UNDEFINED,
+            clear_value: None,
+            load_op: None,
+        });
+        self
+    }
+
+    /// Declare that a buffer will be written to in the given pipeline stages.
+    pub fn write_buffer(mut self, resource: &VirtualResource, stage: PipelineStage) -> Self {
+        self.inner.inputs.push(PassResource {
+            usage: ResourceUsage::ShaderWrite,
+            resource: resource.clone(),
+            stage,
+            layout: vk::ImageLayout::UNDEFINED,
+            clear_value: None,
+            load_op: None,
+        });
+        self.inner.outputs.push(PassResource {
+            usage: ResourceUsage::ShaderWrite,
+            resource: resource.upgrade(),
+            stage,
+            layout: vk::ImageLayout::UNDEFINED,
+            clear_value: None,
+            load_op: None,
+        });
+        self
+    }
+
+    /// Declare that an acceleration structure will be read in the given pipeline stages, for example by ray queries in a fragment or
+    /// compute shader, or with [`PipelineStage::RAY_TRACING_SHADER_KHR`] by a trace rays command. The resource must be bound with
+    /// [`PhysicalResourceBindings::bind_acceleration_structure()`](crate::PhysicalResourceBindings::bind_acceleration_structure),
+    /// or to the buffer backing the acceleration structure with [`PhysicalResourceBindings::bind_buffer()`](crate::PhysicalResourceBindings::bind_buffer).
+    pub fn read_acceleration_structure(mut self, resource: &VirtualResource, stage: PipelineStage) -> Self {
+        self.inner.inputs.push(PassResource {
+            usage: ResourceUsage::AccelerationStructureRead,
+            resource: resource.clone(),
+            stage,
+            layout: vk::ImageLayout::UNDEFINED,
+            clear_value: None,
+            load_op: None,
+        });
+        self
+    }
+
+    /// Declare that an acceleration structure will be built or updated in this pass. Passes that read the output of this pass with
+    /// [`PassBuilder::read_acceleration_structure()`] wait for the build to complete.
+    pub fn write_acceleration_structure(mut self, resource: &VirtualResource) -> Self {
+        let stage = PipelineStage::ACCELERATION_STRUCTURE_BUILD_KHR;
+        self.inner.inputs.push(PassResource {
+            usage: ResourceUsage::AccelerationStructureWrite,
+            resource: resource.clone(),
+            stage,
+            layout: vk::ImageLayout::UNDEFINED,
+            clear_value: None,
+            load_op: None,
+        });
+        self.inner.outputs.push(PassResource {
+            usage: ResourceUsage::AccelerationStructureWrite,
+            resource: resource.upgrade(),
+            stage,
+            layout: vk::ImageLayout::UNDEFINED,
+            clear_value: None,
+            load_op: None,
+        });
+        self
+    }
+
     #[allow(dead_code)]
     fn sample_optional_image(
         self,
@@ -387,7 +543,118 @@ impl<'cb, D: ExecutionDomain, U, A: Allocator> PassBuilder<'cb, D, U, A> {
     }
 }
 
+impl<'cb, D: TransferSupport + ExecutionDomain, U, A: Allocator> PassBuilder<'cb, D, U, A> {
+    /// Create a pass that fills an entire buffer with a 32-bit value using
+    /// [`vkCmdFillBuffer`](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdFillBuffer.html).
+    /// This is commonly used to reset GPU counters or atomics at the start of each frame. Later passes should use
+    /// the output of this pass, obtained through [`Pass::output()`].
+    ///
+    /// The physical buffer must be created with [`vk::BufferUsageFlags::TRANSFER_DST`], and its offset and size must be a multiple of 4.
+    /// # Example
+    /// ```
+    /// # use phobos::buffer;
+    /// # use phobos::prelude::*;
+    /// # use phobos::sync::domain::Compute;
+    /// let counters = buffer!("counters");
+    /// let clear: Pass<Compute> = PassBuilder::clear_buffer(&counters, 0);
+    /// let cull = PassBuilder::<Compute>::new("cull")
+    ///     .write_buffer(clear.output(&counters).unwrap(), PipelineStage::COMPUTE_SHADER)
+    ///     .build();
+    /// ```
+    pub fn clear_buffer(resource: &VirtualResource, value: u32) -> Pass<'cb, D, U, A> {
+        let target = resource.clone();
+        PassBuilder::new(format!("clear_{}", resource.name()))
+            .transfer_write(resource)
+            .execute_fn(move |cmd, _, bindings, _| {
+                let Some(PhysicalResource::Buffer(buffer)) = bindings.resolve(&target) else {
+                    return Err(Error::NoResourceBound(target.uid()).into());
+                };
+                cmd.fill_buffer(buffer, value)
+            })
+            .build()
+    }
+
+    /// Declare that a resource will be written to by a transfer command.
+    fn transfer_write(mut self, resource: &VirtualResource) -> Self {
+        self.inner.inputs.push(PassResource {
+            usage: ResourceUsage::TransferWrite,
+            resource: resource.clone(),
+            stage: PipelineStage::ALL_TRANSFER,
+            layout: vk::ImageLayout::UNDEFINED,
+            clear_value: None,
+            load_op: None,
+        });
+        self.inner.outputs.push(PassResource {
+            usage: ResourceUsage::TransferWrite,
+            resource: resource.upgrade(),
+            stage: PipelineStage::ALL_TRANSFER,
+            layout: vk::ImageLayout::UNDEFINED,
+            clear_value: None,
+            load_op: None,
+        });
+        self
+    }
+}
+
 impl<'cb, D: GfxSupport + ExecutionDomain, U, A: Allocator> PassBuilder<'cb, D, U, A> {
+    /// Create a pass that generates all mip levels of an image from its base level with [`generate_mipmaps()`]. The physical
+    /// image view should cover all mip levels of the image. After this pass, all mip levels are in
+    /// [`vk::ImageLayout::TRANSFER_SRC_OPTIMAL`] until the graph transitions them for a later pass. Later passes should use
+    /// the output of this pass, obtained through [`Pass::output()`].
+    ///
+    /// The physical image must be created with [`vk::ImageUsageFlags::TRANSFER_SRC`] and [`vk::ImageUsageFlags::TRANSFER_DST`].
+    /// # Example
+    /// ```
+    /// # use phobos::image;
+    /// # use phobos::prelude::*;
+    /// # use phobos::sync::domain::Graphics;
+    /// let scene = image!("scene");
+    /// let mips: Pass<Graphics> = PassBuilder::generate_mipmaps(&scene, vk::Filter::LINEAR);
+    /// let bloom = PassBuilder::<Graphics>::new("bloom")
+    ///     .sample_image(mips.output(&scene).unwrap(), PipelineStage::FRAGMENT_SHADER)
+    ///     .build();
+    /// ```
+    pub fn generate_mipmaps(resource: &VirtualResource, filter: vk::Filter) -> Pass<'cb, D, U, A> {
+        let target = resource.clone();
+        PassBuilder::new(format!("generate_mipmaps_{}", resource.name()))
+            .blit_image_levels(resource)
+            .execute_fn(move |cmd, _, bindings, _| {
+                let Some(PhysicalResource::Image(image)) = bindings.resolve(&target) else {
+                    return Err(Error::NoResourceBound(target.uid()).into());
+                };
+                generate_mipmaps(
+                    cmd,
+                    image,
+                    filter,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                )
+            })
+            .build()
+    }
+
+    /// Declare that the mip levels of an image will be written by blits. The image is expected in
+    /// [`vk::ImageLayout::TRANSFER_DST_OPTIMAL`], and is left in [`vk::ImageLayout::TRANSFER_SRC_OPTIMAL`].
+    fn blit_image_levels(mut self, resource: &VirtualResource) -> Self {
+        self.inner.inputs.push(PassResource {
+            usage: ResourceUsage::TransferWrite,
+            resource: resource.clone(),
+            stage: PipelineStage::BLIT,
+            layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            clear_value: None,
+            load_op: None,
+        });
+        self.inner.outputs.push(PassResource {
+            usage: ResourceUsage::TransferWrite,
+            resource: resource.upgrade(),
+            stage: PipelineStage::BLIT,
+            layout: vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            clear_value: None,
+            load_op: None,
+        });
+        self
+    }
+
     /// Set the color of this pass. This can show up in graphics debuggers like RenderDoc.
     #[cfg(feature = "debug-markers")]
     pub fn color(mut self, color: [f32; 4]) -> Self {
@@ -527,6 +794,160 @@ impl<'cb, D: GfxSupport + ExecutionDomain, U, A: Allocator> PassBuilder<'cb, D,
         Ok(self)
     }
 
+    /// Clear the stencil attachment with the specified clear value
+    /// # Errors
+    /// * Fails if this pass was not created using [`PassBuilder::render()`]
+    pub fn clear_stencil_attachment(self, resource: &VirtualResource, clear: u32) -> Result<Self> {
+        self.stencil_attachment(
+            resource,
+            vk::AttachmentLoadOp::CLEAR,
+            Some(vk::ClearDepthStencilValue {
+                depth: 0.0,
+                stencil: clear,
+            }),
+        )
+    }
+
+    /// Load a stencil attachment
+    /// # Errors
+    /// * Fails if this pass was not created using [`PassBuilder::render()`]
+    pub fn load_stencil_attachment(self, resource: &VirtualResource) -> Result<Self> {
+        self.stencil_attachment(resource, vk::AttachmentLoadOp::LOAD, None)
+    }
+
+    /// Adds a stencil attachment to this pass. If [`vk::AttachmentLoadOp::CLEAR`] was specified, `clear` must not be None.
+    ///
+    /// The stencil aspect is tracked separately from the depth aspect and is transitioned to [`vk::ImageLayout::STENCIL_ATTACHMENT_OPTIMAL`],
+    /// so one aspect can be written while the other is read (`separateDepthStencilLayouts`, enabled automatically when supported).
+    /// To do this, declare the depth and stencil aspects as separate virtual resources. If a pass has both a depth and a stencil attachment,
+    /// both must be bound to the same image view.
+    /// # Errors
+    /// * Fails if this pass was not created using [`PassBuilder::render()`]
+    /// * Fails if `op` was [`vk::AttachmentLoadOp::CLEAR`], but `clear` was [`None`].
+    pub fn stencil_attachment(
+        mut self,
+        resource: &VirtualResource,
+        op: vk::AttachmentLoadOp,
+        clear: Option<vk::ClearDepthStencilValue>,
+    ) -> Result<Self> {
+        if !self.inner.is_renderpass {
+            return Err(Error::Uncategorized(
+                "Cannot attach stencil attachment to a pass that is not a renderpass",
+            )
+            .into());
+        }
+        if op == vk::AttachmentLoadOp::CLEAR && clear.is_none() {
+            return Err(anyhow::Error::from(Error::NoClearValue));
+        }
+        self.inner.inputs.push(PassResource {
+            usage: ResourceUsage::Attachment(AttachmentType::Stencil),
+            resource: resource.clone(),
+            // Same stages as depth attachments, stencil tests are part of the fragment tests.
+            stage: PipelineStage::EARLY_FRAGMENT_TESTS,
+            layout: vk::ImageLayout::STENCIL_ATTACHMENT_OPTIMAL,
+            clear_value: None,
+            load_op: None,
+        });
+
+        self.inner.outputs.push(PassResource {
+            usage: ResourceUsage::Attachment(AttachmentType::Stencil),
+            resource: resource.upgrade(),
+            stage: PipelineStage::LATE_FRAGMENT_TESTS,
+            layout: vk::ImageLayout::STENCIL_ATTACHMENT_OPTIMAL,
+            clear_value: clear.map(|c| vk::ClearValue {
+                depth_stencil: c,
+            }),
+            load_op: Some(op),
+        });
+
+        Ok(self)
+    }
+
+    /// Use an image as the fragment shading rate attachment of this pass. Each texel of the image controls the shading rate of a
+    /// `texel_size` region of the framebuffer. The image must be created with [`vk::ImageUsageFlags::FRAGMENT_SHADING_RATE_ATTACHMENT_KHR`],
+    /// and is automatically transitioned to [`vk::ImageLayout::FRAGMENT_SHADING_RATE_ATTACHMENT_OPTIMAL_KHR`].
+    /// Pipelines used inside this pass are automatically created with support for the attachment.
+    /// Requires [`ExtensionID::FragmentShadingRate`](crate::core::device::ExtensionID::FragmentShadingRate) and the `attachmentFragmentShadingRate` feature.
+    /// # Errors
+    /// * Fails if this pass was not created using [`PassBuilder::render()`]
+    pub fn shading_rate_attachment(
+        mut self,
+        resource: &VirtualResource,
+        texel_size: vk::Extent2D,
+    ) -> Result<Self> {
+        if !self.inner.is_renderpass {
+            return Err(Error::Uncategorized(
+                "Cannot attach shading rate attachment to a pass that is not a renderpass",
+            )
+            .into());
+        }
+        self.inner.inputs.push(PassResource {
+            usage: ResourceUsage::Attachment(AttachmentType::ShadingRate(texel_size)),
+            resource: resource.clone(),
+            stage: PipelineStage::FRAGMENT_SHADING_RATE_ATTACHMENT_KHR,
+            layout: vk::ImageLayout::FRAGMENT_SHADING_RATE_ATTACHMENT_OPTIMAL_KHR,
+            clear_value: None,
+            load_op: None,
+        });
+        Ok(self)
+    }
+
+    /// Render to multiple views of the attachments in this pass. Each bit set in `mask` selects an array layer of the attachments
+    /// that every drawcall is broadcast to, and shaders can read the current view through `gl_ViewIndex`. This is useful for
+    /// stereo rendering, or for rendering all cascades of a layered shadow map at once. Pipelines used inside this pass are
+    /// automatically created with the same view mask.
+    /// Requires the `multiview` feature, which is enabled automatically if it is supported. Pipelines with geometry or tessellation
+    /// shaders additionally require `multiviewGeometryShader` or `multiviewTessellationShader`.
+    /// # Errors
+    /// * Fails if this pass was not created using [`PassBuilder::render()`]
+    /// # Example
+    /// ```
+    /// # use anyhow::Result;
+    /// # use phobos::prelude::*;
+    /// # use phobos::domain::All;
+    /// // Render both eyes of a stereo image, stored in the two layers of `target`.
+    /// fn stereo_pass<'cb>(target: &VirtualResource) -> Result<Pass<'cb, All>> {
+    ///     Ok(PassBuilder::render("stereo")
+    ///         .clear_color_attachment(target, ClearColor::Float([0.0, 0.0, 0.0, 1.0]))?
+    ///         .view_mask(0b11)?
+    ///         .build())
+    /// }
+    /// ```
+    pub fn view_mask(mut self, mask: u32) -> Result<Self> {
+        if !self.inner.is_renderpass {
+            return Err(Error::Uncategorized(
+                "Cannot set view mask of a pass that is not a renderpass",
+            )
+            .into());
+        }
+        self.inner.view_mask = mask;
+        Ok(self)
+    }
+
+    /// Declare that an image will be read as an input attachment in the fragment shader of this pass. The image is transitioned
+    /// to [`vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL`], and the barrier from the pass that wrote it is region-local
+    /// ([`vk::DependencyFlags::BY_REGION`]), so tile-based GPUs can keep the image contents in tile memory between both passes.
+    /// Bind the image with [`IncompleteCommandBuffer::bind_input_attachment()`](crate::IncompleteCommandBuffer::bind_input_attachment).
+    /// # Errors
+    /// * Fails if this pass was not created using [`PassBuilder::render()`]
+    pub fn input_attachment(mut self, resource: &VirtualResource) -> Result<Self> {
+        if !self.inner.is_renderpass {
+            return Err(Error::Uncategorized(
+                "Cannot use input attachment in a pass that is not a renderpass",
+            )
+            .into());
+        }
+        self.inner.inputs.push(PassResource {
+            usage: ResourceUsage::InputAttachment,
+            resource: resource.clone(),
+            stage: PipelineStage::FRAGMENT_SHADER,
+            layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            clear_value: None,
+            load_op: None,
+        });
+        Ok(self)
+    }
+
     /// Does a hardware MSAA resolve from `src` into `dst`.
     pub fn resolve(mut self, src: &VirtualResource, dst: &VirtualResource) -> Self {
         self.inner.inputs.push(PassResource {
@@ -667,3 +1088,72 @@ impl<'cb, D: ExecutionDomain + ComputeSupport, U, A: Allocator> PassBuilder<'cb,
         pass
     }
 }
+
+/// Holds virtual resources needed to declare an upscale pass. See the [`upscaler`](crate::upscaler) module.
+#[derive(Debug, Clone)]
+pub struct UpscaleVirtualResources {
+    /// Color buffer for the current frame, at render resolution.
+    pub color: VirtualResource,
+    /// Depth buffer for the current frame, at render resolution
+    pub depth: VirtualResource,
+    /// Motion vectors for the current frame, at render resolution
+    pub motion_vectors: VirtualResource,
+    /// Optional 1x1 texture with the exposure value
+    pub exposure: Option<VirtualResource>,
+    /// Output color buffer for the current frame at display resolution
+    pub output: VirtualResource,
+}
+
+impl UpscaleVirtualResources {
+    fn resolve_image_resource(
+        resource: &VirtualResource,
+        bindings: &PhysicalResourceBindings,
+    ) -> Result<ImageView> {
+        let resolved = bindings.resolve(resource).ok_or_else(|| {
+            anyhow!("Missing physical binding for required upscaler resource: {}", resource.name())
+        })?;
+        let PhysicalResource::Image(image) = &resolved else {
+            bail!("Upscaler resource {} should be an image", resource.name());
+        };
+
+        Ok(image.clone())
+    }
+
+    /// Resolve all resources to their respective physical resources.
+    /// Fails if any resource resolve fails.
+    pub fn resolve(&self, bindings: &PhysicalResourceBindings) -> Result<UpscaleResources> {
+        Ok(UpscaleResources {
+            color: Self::resolve_image_resource(&self.color, bindings)?,
+            depth: Self::resolve_image_resource(&self.depth, bindings)?,
+            motion_vectors: Self::resolve_image_resource(&self.motion_vectors, bindings)?,
+            exposure: match &self.exposure {
+                None => None,
+                Some(exposure) => Some(Self::resolve_image_resource(exposure, bindings)?),
+            },
+            output: Self::resolve_image_resource(&self.output, bindings)?,
+        })
+    }
+}
+
+impl<'cb, D: ExecutionDomain + ComputeSupport, U, A: Allocator> PassBuilder<'cb, D, U, A> {
+    /// Create a pass that upscales the color buffer with any [`Upscaler`]. The upscaler is moved into the pass, pass
+    /// a mutable reference to keep using it afterwards.
+    pub fn upscale<Up: Upscaler + 'cb>(
+        mut upscaler: Up,
+        descr: UpscaleDescription,
+        resources: UpscaleVirtualResources,
+    ) -> Pass<'cb, D, U, A> {
+        PassBuilder::<'cb, D, U, A>::new("upscale")
+            .sample_image(&resources.color, PipelineStage::COMPUTE_SHADER)
+            .sample_image(&resources.motion_vectors, PipelineStage::COMPUTE_SHADER)
+            .sample_image(&resources.depth, PipelineStage::COMPUTE_SHADER)
+            .sample_optional_image(&resources.exposure, PipelineStage::COMPUTE_SHADER)
+            .write_storage_image(&resources.output, PipelineStage::COMPUTE_SHADER)
+            .execute_fn(move |cmd, _, bindings, _| {
+                let resolved_resources = resources.resolve(bindings)?;
+                upscaler.dispatch(&descr, &resolved_resources, unsafe { cmd.handle() })?;
+                Ok(cmd)
+            })
+            .build()
+    }
+}
@@ -44,6 +44,20 @@ pub struct PassResourceBarrier {
     pub(crate) dst_stage: PipelineStage,
 }
 
+/// State of a resource at the boundary between two pass graphs. Obtain this from the graph that produces the resource with
+/// [`BuiltPassGraph::export`], and give it to the graph that consumes it with [`PassGraph::import`].
+/// This allows composing multiple independent graphs in a single frame, for example an editor overlay graph
+/// that draws on top of the final color output of the main graph.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct GraphResourceState {
+    /// Image layout the resource is in. Ignored for buffers.
+    pub layout: vk::ImageLayout,
+    /// Pipeline stages of the last usage of the resource.
+    pub stage: PipelineStage,
+    /// Access flags of the last usage of the resource.
+    pub access: vk::AccessFlags2,
+}
+
 /// A task in a pass graph. Either a render pass, or a compute pass, etc.
 pub struct PassNode<'cb, R: Resource, D: ExecutionDomain, U = (), A: Allocator = DefaultAllocator> {
     pub(crate) identifier: String,
@@ -52,6 +66,7 @@ pub struct PassNode<'cb, R: Resource, D: ExecutionDomain, U = (), A: Allocator =
     pub(crate) outputs: Vec<R>,
     pub(crate) execute: BoxedPassFn<'cb, D, U, A>,
     pub(crate) is_renderpass: bool,
+    pub(crate) view_mask: u32,
 }
 
 pub(crate) type PassGraphInner<'cb, D, U, A> = Graph<
@@ -66,14 +81,16 @@ pub struct PassGraph<'cb, D: ExecutionDomain, U = (), A: Allocator = DefaultAllo
     // Note that this is guaranteed to be stable.
     // This is because the only time indices are invalidated is when deleting a node, and even then only the last
     // index is invalidated. Since the source is always the first node, this is never invalidated.
-    source: NodeIndex,
+    pub(crate) source: NodeIndex,
     swapchain_final: VirtualResource,
     last_usages: HashMap<String, (usize, PipelineStage)>,
+    imports: HashMap<String, GraphResourceState>,
 }
 
 /// A completely built pass graph, ready for recording.
 pub struct BuiltPassGraph<'cb, D: ExecutionDomain, U = (), A: Allocator = DefaultAllocator> {
     graph: PassGraph<'cb, D, U, A>,
+    pub(crate) frame_index: Option<u32>,
 }
 
 impl<'cb, D: ExecutionDomain, U, A: Allocator> Deref for BuiltPassGraph<'cb, D, U, A> {
@@ -174,6 +191,7 @@ impl<'cb, D: ExecutionDomain, U, A: Allocator> PassGraph<'cb, D, U, A> {
             source: NodeIndex::default(),
             swapchain_final: VirtualResource::final_image("swapchain"),
             last_usages: Default::default(),
+            imports: Default::default(),
         };
 
         // insert dummy 'source' node. This node produces all initial inputs and is used for start of frame sync.
@@ -186,6 +204,7 @@ impl<'cb, D: ExecutionDomain, U, A: Allocator> PassGraph<'cb, D, U, A> {
                 outputs: vec![],
                 execute: EmptyPassExecutor::new_boxed(),
                 is_renderpass: false,
+                view_mask: 0,
             })
             .unwrap();
         graph.source = graph.graph.graph.node_indices().next().unwrap();
@@ -229,11 +248,36 @@ impl<'cb, D: ExecutionDomain, U, A: Allocator> PassGraph<'cb, D, U, A> {
             outputs: pass.outputs,
             execute: pass.execute,
             is_renderpass: pass.is_renderpass,
+            view_mask: pass.view_mask,
         })?;
 
         Ok(self)
     }
 
+    /// Import a resource that was produced outside of this graph, for example by a graph that was recorded earlier in the frame.
+    /// Instead of assuming the resource starts in an undefined layout, the first usage of it in this graph will wait for the given
+    /// stage and access flags, and transition from the given layout. This preserves the contents of the resource.
+    /// The state can be obtained from the producing graph using [`BuiltPassGraph::export`].
+    ///
+    /// Note that this does not synchronize between command buffers. If both graphs are recorded to different command buffers,
+    /// these must still be submitted in order on the same queue.
+    /// # Example
+    /// ```
+    /// # use anyhow::Result;
+    /// # use phobos::prelude::*;
+    /// # use phobos::graph::pass_graph::BuiltPassGraph;
+    /// fn overlay_graph<'cb>(main: &BuiltPassGraph<'cb, domain::Graphics>, overlay: Pass<'cb, domain::Graphics>)
+    ///     -> Result<PassGraph<'cb, domain::Graphics>> {
+    ///     let color = image!("color");
+    ///     let state = main.export(&color).unwrap();
+    ///     PassGraph::new().import(&color, state).add_pass(overlay)
+    /// }
+    /// ```
+    pub fn import(mut self, resource: &VirtualResource, state: GraphResourceState) -> Self {
+        self.imports.insert(resource.name().to_owned(), state);
+        self
+    }
+
     /// Builds the task graph so it can be recorded into a command buffer.
     /// # Errors
     /// * Fails if there are multiple usages of the same resource, which makes it impossible to
@@ -245,6 +289,7 @@ impl<'cb, D: ExecutionDomain, U, A: Allocator> PassGraph<'cb, D, U, A> {
 
         Ok(BuiltPassGraph {
             graph: self,
+            frame_index: None,
         })
     }
 
@@ -344,6 +389,11 @@ impl<'cb, D: ExecutionDomain, U, A: Allocator> PassGraph<'cb, D, U, A> {
                 .is_associated_with(&self.swapchain_final)
             {
                 output.stage = PipelineStage::COLOR_ATTACHMENT_OUTPUT;
+            } else if let Some(state) = self.imports.get(output.resource.name()) {
+                // Imported resources are synchronized with their last usage in the producing graph.
+                output.stage = state.stage;
+                output.layout = state.layout;
+                output.usage = ResourceUsage::Imported(state.access);
             } else {
                 let (_, stage) = self.last_usages.get(output.resource.name()).unwrap();
                 output.stage = *stage;
@@ -410,6 +460,66 @@ impl<'cb, D: ExecutionDomain, U, A: Allocator> PassGraph<'cb, D, U, A> {
     }
 }
 
+impl<'cb, D: ExecutionDomain, U, A: Allocator> BuiltPassGraph<'cb, D, U, A> {
+    /// Enable per-pass constants for the next recordings of this graph. While recording, every pass executor can access a
+    /// [`PassConstants`](crate::graph::pass::PassConstants) structure with the frame index, the index of the pass and
+    /// pseudo-random seeds derived from both, through [`IncompleteCommandBuffer::pass_constants()`](crate::IncompleteCommandBuffer::pass_constants).
+    ///
+    /// Call this every frame with a frame counter that increases by one each frame, so temporal effects see a different value
+    /// every frame. Note that [`InFlightContext::frame_index()`](crate::InFlightContext::frame_index) is not suitable for this,
+    /// since it only cycles through the frames in flight.
+    pub fn set_frame_index(&mut self, frame_index: u32) {
+        self.frame_index = Some(frame_index);
+    }
+
+    /// Disable per-pass constants, see [`BuiltPassGraph::set_frame_index()`].
+    pub fn disable_pass_constants(&mut self) {
+        self.frame_index = None;
+    }
+
+    /// Get the state of a resource after all passes in this graph have executed. This is the layout, stage and access of the
+    /// most recent version of the resource. Give this to [`PassGraph::import`] to consume the resource in a different graph.
+    /// Returns `None` if no pass in this graph uses the resource.
+    pub fn export(&self, resource: &VirtualResource) -> Option<GraphResourceState> {
+        let graph = &self.graph.graph.graph;
+        let mut version = None;
+        let mut state: Option<GraphResourceState> = None;
+        for node in graph.node_indices() {
+            if node == self.graph.source {
+                continue;
+            }
+            let Node::Task(task) = graph.node_weight(node).unwrap() else { continue; };
+            // Outputs always have a more recent version than the inputs they were derived from, so the most recent write
+            // will naturally take precedence over its reads.
+            for usage in task.inputs.iter().chain(task.outputs.iter()) {
+                if !usage.resource.is_associated_with(resource) {
+                    continue;
+                }
+                let usage_state = GraphResourceState {
+                    layout: usage.layout,
+                    stage: usage.stage,
+                    access: usage.usage.access(),
+                };
+                match version {
+                    Some(v) if usage.resource.version() < v => {}
+                    // Multiple reads of the same version, the next user has to wait for all of them.
+                    Some(v) if usage.resource.version() == v => {
+                        let current = state.as_mut().unwrap();
+                        current.layout = usage_state.layout;
+                        current.stage |= usage_state.stage;
+                        current.access |= usage_state.access;
+                    }
+                    _ => {
+                        version = Some(usage.resource.version());
+                        state = Some(usage_state);
+                    }
+                }
+            }
+        }
+        state
+    }
+}
+
 /// Trait that is implemented for the task graph to help with debugging and visualizing the graph.
 pub trait GraphViz {
     /// Get the string representation of this graph in `dot` format.
@@ -4,7 +4,9 @@ use std::collections::HashMap;
 
 use anyhow::Result;
 
-use crate::{BufferView, Error, ImageView, VirtualResource};
+use ash::vk;
+
+use crate::{AccelerationStructure, Allocator, BufferView, CubeFace, Error, Image, ImageView, VirtualResource};
 
 /// Describes any physical resource handle on the GPU.
 #[derive(Debug, Clone)]
@@ -13,6 +15,8 @@ pub enum PhysicalResource {
     Image(ImageView),
     /// A buffer resource
     Buffer(BufferView),
+    /// An acceleration structure resource
+    AccelerationStructure(vk::AccelerationStructureKHR),
 }
 
 /// Stores bindings from virtual resources to physical resources.
@@ -46,12 +50,47 @@ impl PhysicalResourceBindings {
             .insert(name.into(), PhysicalResource::Image(image.clone()));
     }
 
+    /// Bind a cube map for rendering to its faces. Each virtual resource created with [`VirtualResource::cube_face()`]
+    /// is bound to a view of mip level `mip_level` of that face, and `name` itself is bound to a cube view of the whole image.
+    ///
+    /// The graph tracks each face separately. A pass that samples the cube map after rendering to its faces should declare
+    /// each face it depends on with [`PassBuilder::sample_image()`](crate::PassBuilder::sample_image), and bind the cube view
+    /// in its executor.
+    /// # Errors
+    /// * Fails if the image is not cube compatible, see [`Image::is_cube_compatible()`].
+    /// * Fails if `mip_level` is out of range.
+    pub fn bind_cube_faces<A: Allocator>(
+        &mut self,
+        name: impl Into<String>,
+        image: &Image<A>,
+        aspect: vk::ImageAspectFlags,
+        mip_level: u32,
+    ) -> Result<()> {
+        let name = name.into();
+        for face in CubeFace::ALL {
+            let view = image.face_view(aspect, face, mip_level)?;
+            self.bind_image(VirtualResource::cube_face(&name, face).name(), &view);
+        }
+        let view = image.cube_view(aspect)?;
+        self.bind_image(name, &view);
+        Ok(())
+    }
+
     /// Bind a buffer to all virtual resources with this name as their uid.
     pub fn bind_buffer(&mut self, name: impl Into<String>, buffer: &BufferView) {
         self.bindings
             .insert(name.into(), PhysicalResource::Buffer(*buffer));
     }
 
+    /// Bind an acceleration structure to all virtual resources with this name as their uid. The acceleration structure must
+    /// outlive any graph recorded with these bindings.
+    pub fn bind_acceleration_structure(&mut self, name: impl Into<String>, accel: &AccelerationStructure) {
+        self.bindings.insert(
+            name.into(),
+            PhysicalResource::AccelerationStructure(unsafe { accel.handle() }),
+        );
+    }
+
     /// Alias a resource by giving it an alternative name
     pub fn alias(&mut self, new_name: impl Into<String>, resource: &str) -> Result<()> {
         self.bindings.insert(
@@ -1,6 +1,7 @@
 //! Provides methods to record a pass graph to a command buffer
 
-use std::collections::HashSet;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
 use std::ffi::CString;
 use std::sync::Arc;
 
@@ -14,7 +15,10 @@ use crate::{
     Allocator, BufferView, DebugMessenger, Error, ImageView, PassGraph, PhysicalResourceBindings,
 };
 use crate::command_buffer::IncompleteCommandBuffer;
-use crate::command_buffer::state::{RenderingAttachmentInfo, RenderingInfo};
+use crate::command_buffer::state::{
+    RenderingAttachmentInfo, RenderingInfo, ShadingRateAttachmentInfo,
+};
+use crate::graph::pass::PassConstants;
 use crate::graph::pass_graph::{BuiltPassGraph, PassNode, PassResource, PassResourceBarrier};
 use crate::graph::physical_resource::PhysicalResource;
 use crate::graph::resource::{AttachmentType, ResourceUsage};
@@ -90,6 +94,66 @@ fn insert_in_active_set<D: ExecutionDomain, U, A: Allocator>(
     }
 }
 
+/// Compute the order in which nodes are recorded, following the traversal algorithm above.
+fn recording_order<D: ExecutionDomain, U, A: Allocator>(graph: &BuiltPassGraph<'_, D, U, A>) -> Vec<NodeIndex> {
+    let mut order = Vec::with_capacity(graph.num_nodes());
+    let mut active = HashSet::new();
+    let mut children = HashSet::new();
+    for start in graph.graph.sources() {
+        insert_in_active_set(start, graph, &mut active, &mut children);
+    }
+    // Record each initial active node.
+    order.extend(active.iter().copied());
+
+    while active.len() != graph.num_nodes() {
+        // For each node that is a child of an active node
+        let mut recorded_nodes = Vec::new();
+        for child in &children {
+            // If all parents of this child node are in the active set, record it.
+            if parents!(child, graph).all(|parent| active.contains(&parent)) {
+                recorded_nodes.push(*child);
+            }
+        }
+        // Now we swap all recorded nodes to the active set
+        for node in recorded_nodes {
+            order.push(node);
+            insert_in_active_set(node, graph, &mut active, &mut children);
+        }
+    }
+    order
+}
+
+/// Assign an index to every pass in a topological order of the graph, used for the per-pass constants. The recording order
+/// depends on hash set iteration, so it cannot be used for this. Passes that do not depend on each other are ordered by the
+/// order they were added in, so a pass gets the same index every frame.
+fn pass_indices<D: ExecutionDomain, U, A: Allocator>(graph: &BuiltPassGraph<'_, D, U, A>) -> HashMap<NodeIndex, u32> {
+    let task_graph = &graph.task_graph().graph;
+    let mut in_degree = task_graph
+        .node_indices()
+        .map(|node| (node, task_graph.edges_directed(node, Incoming).count()))
+        .collect::<HashMap<_, _>>();
+    let mut ready = in_degree
+        .iter()
+        .filter(|(_, degree)| **degree == 0)
+        .map(|(node, _)| Reverse(*node))
+        .collect::<BinaryHeap<_>>();
+    let mut indices = HashMap::new();
+    while let Some(Reverse(node)) = ready.pop() {
+        // The source node is an internal pass, so it does not count towards the pass index.
+        if node != graph.source && matches!(task_graph.node_weight(node), Some(Node::Task(_))) {
+            indices.insert(node, indices.len() as u32);
+        }
+        for edge in task_graph.edges_directed(node, Outgoing) {
+            let degree = in_degree.get_mut(&edge.target()).unwrap();
+            *degree -= 1;
+            if *degree == 0 {
+                ready.push(Reverse(edge.target()));
+            }
+        }
+    }
+    indices
+}
+
 fn find_resolve_attachment<D: ExecutionDomain, U, A: Allocator>(
     pass: &PassNode<PassResource, D, U, A>,
     bindings: &PhysicalResourceBindings,
@@ -150,19 +214,21 @@ fn color_attachments<D: ExecutionDomain, U, A: Allocator>(
         .collect()
 }
 
-fn depth_attachment<D: ExecutionDomain, U, A: Allocator>(
+/// Find the depth or stencil attachment of a pass. `ty` must be either [`AttachmentType::Depth`] or [`AttachmentType::Stencil`].
+fn depth_stencil_attachment<D: ExecutionDomain, U, A: Allocator>(
     pass: &PassNode<PassResource, D, U, A>,
     bindings: &PhysicalResourceBindings,
+    ty: AttachmentType,
 ) -> Option<Result<RenderingAttachmentInfo>> {
     pass.outputs
         .iter()
         .filter_map(|resource| -> Option<Result<RenderingAttachmentInfo>> {
-            if resource.layout != vk::ImageLayout::DEPTH_ATTACHMENT_OPTIMAL {
+            if resource.usage != ResourceUsage::Attachment(ty.clone()) {
                 return None;
             }
 
             let Some(PhysicalResource::Image(image)) = bindings.resolve(&resource.resource) else {
-                return Some(Err(anyhow!("No image resource bound to depth attachment {}", &resource.resource)));
+                return Some(Err(anyhow!("No image resource bound to depth/stencil attachment {}", &resource.resource)));
             };
 
             let resolve;
@@ -179,7 +245,7 @@ fn depth_attachment<D: ExecutionDomain, U, A: Allocator>(
                 resolve_mode: resolve.is_some().then_some(vk::ResolveModeFlags::AVERAGE),
                 resolve_image_layout: resolve
                     .is_some()
-                    .then_some(vk::ImageLayout::DEPTH_ATTACHMENT_OPTIMAL),
+                    .then_some(resource.layout),
                 resolve_image_view: resolve,
                 load_op: resource.load_op.unwrap(),
                 store_op: vk::AttachmentStoreOp::STORE,
@@ -190,6 +256,26 @@ fn depth_attachment<D: ExecutionDomain, U, A: Allocator>(
         .next()
 }
 
+fn shading_rate_attachment<D: ExecutionDomain, U, A: Allocator>(
+    pass: &PassNode<PassResource, D, U, A>,
+    bindings: &PhysicalResourceBindings,
+) -> Result<Option<ShadingRateAttachmentInfo>> {
+    let attachment = pass.inputs.iter().find_map(|resource| match &resource.usage {
+        ResourceUsage::Attachment(AttachmentType::ShadingRate(texel_size)) => {
+            Some((resource, *texel_size))
+        }
+        _ => None,
+    });
+    let Some((resource, texel_size)) = attachment else { return Ok(None); };
+    let Some(PhysicalResource::Image(image)) = bindings.resolve(&resource.resource) else {
+        bail!("No image resource bound to shading rate attachment {}", &resource.resource);
+    };
+    Ok(Some(ShadingRateAttachmentInfo {
+        image_view: image.clone(),
+        texel_size,
+    }))
+}
+
 fn render_area<D: ExecutionDomain, U, A: Allocator>(
     pass: &PassNode<PassResource, D, U, A>,
     bindings: &PhysicalResourceBindings,
@@ -197,7 +283,11 @@ fn render_area<D: ExecutionDomain, U, A: Allocator>(
     let resource = pass
         .outputs
         .iter()
-        .find(|resource| matches!(resource.usage, ResourceUsage::Attachment(_)))
+        // The shading rate attachment is smaller than the render area, so it cannot be used to determine it.
+        .find(|resource| {
+            matches!(resource.usage, ResourceUsage::Attachment(_))
+                && !matches!(resource.usage, ResourceUsage::Attachment(AttachmentType::ShadingRate(_)))
+        })
         .unwrap();
     let Some(PhysicalResource::Image(image)) = bindings.resolve(&resource.resource) else {
         bail!("No image resource bound to attachment {}", &resource.resource);
@@ -207,10 +297,10 @@ fn render_area<D: ExecutionDomain, U, A: Allocator>(
             x: 0,
             y: 0,
         },
-        // TODO: properly set size of current level?
+        // Attachments may be a single mip level of an image, for example a face of a prefiltered cube map.
         extent: vk::Extent2D {
-            width: image.width(),
-            height: image.height(),
+            width: image.base_level_size().width,
+            height: image.base_level_size().height,
         },
     })
 }
@@ -240,6 +330,45 @@ fn annotate_pass<D: ExecutionDomain, A: Allocator>(
     Ok(cmd)
 }
 
+fn rendering_info<D: ExecutionDomain, U, A: Allocator>(
+    pass: &PassNode<PassResource, D, U, A>,
+    bindings: &PhysicalResourceBindings,
+) -> Result<RenderingInfo> {
+    let mut info = RenderingInfo {
+        flags: Default::default(),
+        render_area: render_area(pass, bindings)?,
+        layer_count: 1,
+        // If this is not zero, the layer count is ignored and each view renders to its own layer.
+        view_mask: pass.view_mask,
+        color_attachments: color_attachments(pass, bindings)?,
+        depth_attachment: match depth_stencil_attachment(pass, bindings, AttachmentType::Depth) {
+            None => None,
+            Some(result) => Some(result?),
+        },
+        stencil_attachment: match depth_stencil_attachment(pass, bindings, AttachmentType::Stencil) {
+            None => None,
+            Some(result) => Some(result?),
+        },
+        shading_rate_attachment: shading_rate_attachment(pass, bindings)?,
+    };
+    // Render to as many layers as all attachments have, so layered attachments can be written with gl_Layer.
+    info.layer_count = info
+        .color_attachments
+        .iter()
+        .chain(info.depth_attachment.iter())
+        .chain(info.stencil_attachment.iter())
+        .map(|attachment| attachment.image_view.layer_count())
+        .min()
+        .unwrap_or(1);
+    if let (Some(depth), Some(stencil)) = (&info.depth_attachment, &info.stencil_attachment) {
+        // Dynamic rendering requires both attachments to use the same image view. Barriers are still recorded per aspect.
+        if depth.image_view.id() != stencil.image_view.id() {
+            bail!("Depth and stencil attachments of pass {} must be bound to the same image view", pass.identifier);
+        }
+    }
+    Ok(info)
+}
+
 fn record_pass<'q, D: ExecutionDomain, U, A: Allocator>(
     pass: &mut PassNode<'_, PassResource, D, U, A>,
     bindings: &PhysicalResourceBindings,
@@ -253,18 +382,7 @@ fn record_pass<'q, D: ExecutionDomain, U, A: Allocator>(
     }
 
     if pass.is_renderpass {
-        let info = RenderingInfo {
-            flags: Default::default(),
-            render_area: render_area(pass, bindings)?,
-            layer_count: 1, // TODO: Multilayer rendering fix
-            view_mask: 0,
-            color_attachments: color_attachments(pass, bindings)?,
-            depth_attachment: match depth_attachment(pass, bindings) {
-                None => None,
-                Some(result) => Some(result?),
-            },
-            stencil_attachment: None, // TODO: Stencil
-        };
+        let info = rendering_info(pass, bindings)?;
         cmd = cmd.begin_rendering(&info);
     }
 
@@ -283,6 +401,29 @@ fn record_pass<'q, D: ExecutionDomain, U, A: Allocator>(
     Ok(cmd)
 }
 
+/// Restrict the barrier aspect to the aspect a depth-only or stencil-only layout applies to. This allows the depth and stencil
+/// aspect of the same image to be in different layouts, even if the bound image view has both aspects. Without the
+/// `separateDepthStencilLayouts` feature both aspects always transition together, so the full aspect of the view is used.
+fn layout_aspect(image: &ImageView, layout: vk::ImageLayout) -> vk::ImageAspectFlags {
+    let aspect = image.aspect();
+    if image.device().features_1_2().separate_depth_stencil_layouts != vk::TRUE {
+        return aspect;
+    }
+    let layout_aspect = match layout {
+        vk::ImageLayout::DEPTH_ATTACHMENT_OPTIMAL | vk::ImageLayout::DEPTH_READ_ONLY_OPTIMAL => {
+            vk::ImageAspectFlags::DEPTH
+        }
+        vk::ImageLayout::STENCIL_ATTACHMENT_OPTIMAL
+        | vk::ImageLayout::STENCIL_READ_ONLY_OPTIMAL => vk::ImageAspectFlags::STENCIL,
+        _ => return aspect,
+    };
+    if aspect.contains(layout_aspect) {
+        layout_aspect
+    } else {
+        aspect
+    }
+}
+
 fn record_image_barrier<'q, D: ExecutionDomain, A: Allocator>(
     barrier: &PassResourceBarrier,
     image: &ImageView,
@@ -305,7 +446,10 @@ fn record_image_barrier<'q, D: ExecutionDomain, A: Allocator>(
         src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
         dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
         image: unsafe { image.image() },
-        subresource_range: image.subresource_range(),
+        subresource_range: vk::ImageSubresourceRange {
+            aspect_mask: layout_aspect(image, dst_resource.layout),
+            ..image.subresource_range()
+        },
     };
 
     let dependency = vk::DependencyInfo {
@@ -330,6 +474,13 @@ fn record_buffer_barrier<'q, D: ExecutionDomain, A: Allocator>(
     cmd: IncompleteCommandBuffer<'q, D, A>,
 ) -> Result<IncompleteCommandBuffer<'q, D, A>> {
     // Since every driver implements buffer barriers as global memory barriers, we will do the same.
+    record_memory_barrier(barrier, cmd)
+}
+
+fn record_memory_barrier<'q, D: ExecutionDomain, A: Allocator>(
+    barrier: &PassResourceBarrier,
+    cmd: IncompleteCommandBuffer<'q, D, A>,
+) -> Result<IncompleteCommandBuffer<'q, D, A>> {
     let vk_barrier = vk::MemoryBarrier2 {
         s_type: vk::StructureType::MEMORY_BARRIER_2,
         p_next: std::ptr::null(),
@@ -367,6 +518,7 @@ fn record_barrier<'q, D: ExecutionDomain, A: Allocator>(
         PhysicalResource::Buffer(buffer) => {
             record_buffer_barrier(barrier, buffer, dst_resource, cmd)
         }
+        PhysicalResource::AccelerationStructure(_) => record_memory_barrier(barrier, cmd),
     }
 }
 
@@ -408,40 +560,161 @@ impl<'cb, D: ExecutionDomain, U, A: Allocator> RecordGraphToCommandBuffer<D, U,
     ) -> Result<IncompleteCommandBuffer<'q, D, A>>
     where
         Self: Sized, {
-        let mut active = HashSet::new();
-        let mut children = HashSet::new();
-        for start in self.graph.sources() {
-            insert_in_active_set(start, self, &mut active, &mut children);
-        }
-        // Record each initial active node.
-        for node in &active {
-            cmd = record_node(self, *node, bindings, local_pool, cmd, debug.clone(), user_data)?;
+        let pass_indices = match self.frame_index {
+            Some(_) => pass_indices(self),
+            None => HashMap::new(),
+        };
+        for node in recording_order(self) {
+            if let (Some(frame_index), Some(pass_index)) = (self.frame_index, pass_indices.get(&node)) {
+                cmd.set_pass_constants(Some(PassConstants::new(frame_index, *pass_index)));
+            }
+            cmd = record_node(self, node, bindings, local_pool, cmd, debug.clone(), user_data)?;
         }
+        cmd.set_pass_constants(None);
+
+        Ok(cmd)
+    }
+}
+
+/// A pipeline barrier that would be recorded by [`BuiltPassGraph::dry_run()`].
+#[derive(Debug, Clone)]
+pub struct DryRunBarrier {
+    /// Name of the virtual resource this barrier applies to.
+    pub resource: String,
+    /// Layout before the barrier. Always [`vk::ImageLayout::UNDEFINED`] for buffers.
+    pub old_layout: vk::ImageLayout,
+    /// Layout after the barrier. Always [`vk::ImageLayout::UNDEFINED`] for buffers.
+    pub new_layout: vk::ImageLayout,
+    /// Image aspect the barrier is recorded for. This is `None` for buffers and for resources without a physical binding.
+    pub aspect: Option<vk::ImageAspectFlags>,
+    /// Source pipeline stage.
+    pub src_stage: vk::PipelineStageFlags2,
+    /// Destination pipeline stage.
+    pub dst_stage: vk::PipelineStageFlags2,
+    /// Source access mask.
+    pub src_access: vk::AccessFlags2,
+    /// Destination access mask.
+    pub dst_access: vk::AccessFlags2,
+}
+
+/// A pass that would be recorded by [`BuiltPassGraph::dry_run()`].
+#[derive(Debug, Clone)]
+pub struct DryRunPass {
+    /// Name of the pass.
+    pub name: String,
+    /// Whether this pass begins a dynamic rendering scope.
+    pub is_renderpass: bool,
+    /// Render area of the pass. Only available for renderpasses where all resources have a physical binding.
+    pub render_area: Option<vk::Rect2D>,
+    /// View mask of the pass, or zero if multiview is not used.
+    pub view_mask: u32,
+}
+
+/// A single recorded command in a [`DryRun`].
+#[derive(Debug, Clone)]
+pub enum DryRunStep {
+    /// A pipeline barrier between two passes.
+    Barrier(DryRunBarrier),
+    /// A pass.
+    Pass(DryRunPass),
+}
+
+/// The result of [`BuiltPassGraph::dry_run()`].
+#[derive(Debug, Clone, Default)]
+pub struct DryRun {
+    /// Every barrier and pass in the graph, in the order they would be recorded.
+    pub steps: Vec<DryRunStep>,
+    /// Names of all virtual resources used in the graph that have no physical binding, sorted by name.
+    pub unbound: Vec<String>,
+}
 
-        while active.len() != self.num_nodes() {
-            // For each node that is a child of an active node
-            let mut recorded_nodes = Vec::new();
-            for child in &children {
-                // If all parents of this child node are in the active set, record it.
-                if parents!(child, self).all(|parent| active.contains(&parent)) {
-                    cmd = record_node(
-                        self,
-                        *child,
-                        bindings,
-                        local_pool,
-                        cmd,
-                        debug.clone(),
-                        user_data,
-                    )?;
-                    recorded_nodes.push(*child);
+impl DryRun {
+    /// Iterate over all passes in recording order.
+    pub fn passes(&self) -> impl Iterator<Item = &DryRunPass> {
+        self.steps.iter().filter_map(|step| match step {
+            DryRunStep::Pass(pass) => Some(pass),
+            DryRunStep::Barrier(_) => None,
+        })
+    }
+
+    /// Iterate over all barriers in recording order.
+    pub fn barriers(&self) -> impl Iterator<Item = &DryRunBarrier> {
+        self.steps.iter().filter_map(|step| match step {
+            DryRunStep::Barrier(barrier) => Some(barrier),
+            DryRunStep::Pass(_) => None,
+        })
+    }
+}
+
+impl<'cb, D: ExecutionDomain, U, A: Allocator> BuiltPassGraph<'cb, D, U, A> {
+    /// Walk the graph in the same order as [`RecordGraphToCommandBuffer::record()`], without recording anything. This resolves
+    /// the physical bindings and plans every barrier and layout transition, so frame logic can be tested without a device.
+    ///
+    /// Resources without a physical binding are not an error here, they are listed in [`DryRun::unbound`] instead. This means an
+    /// empty set of bindings can be used on machines without a GPU. Attachments of a renderpass are only validated if all resources
+    /// used by that pass are bound.
+    ///
+    /// Pass executors are not invoked, since they require a real command buffer.
+    /// # Errors
+    /// * Fails if a renderpass has a bound attachment setup that [`RecordGraphToCommandBuffer::record()`] would reject.
+    pub fn dry_run(&self, bindings: &PhysicalResourceBindings) -> Result<DryRun> {
+        let graph = &self.graph.graph;
+        let mut unbound = HashSet::new();
+        let mut steps = Vec::with_capacity(self.num_nodes());
+        for node in recording_order(self) {
+            match graph.node_weight(node).unwrap() {
+                Node::Task(pass) => {
+                    let mut fully_bound = true;
+                    for resource in pass.inputs.iter().chain(pass.outputs.iter()) {
+                        if bindings.resolve(&resource.resource).is_none() {
+                            unbound.insert(resource.resource.name().to_owned());
+                            fully_bound = false;
+                        }
+                    }
+                    let render_area = if pass.is_renderpass && fully_bound {
+                        Some(rendering_info(pass, bindings)?.render_area)
+                    } else {
+                        None
+                    };
+                    steps.push(DryRunStep::Pass(DryRunPass {
+                        name: pass.identifier.clone(),
+                        is_renderpass: pass.is_renderpass,
+                        render_area,
+                        view_mask: pass.view_mask,
+                    }));
+                }
+                Node::Barrier(barrier) => {
+                    let dst_resource = PassGraph::barrier_dst_resource(graph, node)?;
+                    let aspect = match bindings.resolve(&barrier.resource.resource) {
+                        Some(PhysicalResource::Image(image)) => Some(layout_aspect(image, dst_resource.layout)),
+                        Some(PhysicalResource::Buffer(_)) => None,
+                        Some(PhysicalResource::AccelerationStructure(_)) => None,
+                        None => {
+                            unbound.insert(barrier.resource.resource.name().to_owned());
+                            None
+                        }
+                    };
+                    steps.push(DryRunStep::Barrier(DryRunBarrier {
+                        resource: barrier.resource.resource.name().to_owned(),
+                        old_layout: barrier.resource.layout,
+                        new_layout: dst_resource.layout,
+                        aspect,
+                        src_stage: barrier.src_stage,
+                        dst_stage: barrier.dst_stage,
+                        src_access: barrier.src_access,
+                        dst_access: barrier.dst_access,
+                    }));
+                }
+                Node::_Unreachable(_) => {
+                    unreachable!()
                 }
-            }
-            // Now we swap all recorded nodes to the active set
-            for node in recorded_nodes {
-                insert_in_active_set(node, self, &mut active, &mut children);
             }
         }
-
-        Ok(cmd)
+        let mut unbound = unbound.into_iter().collect::<Vec<_>>();
+        unbound.sort();
+        Ok(DryRun {
+            steps,
+            unbound,
+        })
     }
 }
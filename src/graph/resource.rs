@@ -12,6 +12,8 @@ pub enum ResourceType {
     Image,
     /// Buffer resource
     Buffer,
+    /// Acceleration structure resource
+    AccelerationStructure,
 }
 
 #[derive(Debug, Default, PartialEq, Eq, Clone)]
@@ -19,7 +21,10 @@ pub(crate) enum AttachmentType {
     #[default]
     Color,
     Depth,
+    Stencil,
     Resolve(VirtualResource),
+    /// Fragment shading rate attachment, with the size of the framebuffer region each texel corresponds to.
+    ShadingRate(vk::Extent2D),
 }
 
 /// Resource usage in a task graph.
@@ -32,6 +37,16 @@ pub(crate) enum ResourceUsage {
     Attachment(AttachmentType),
     ShaderRead,
     ShaderWrite,
+    /// Image read as an input attachment in the fragment shader.
+    InputAttachment,
+    /// Resource written by a transfer command, such as a buffer fill.
+    TransferWrite,
+    /// Acceleration structure read by a ray query or a trace rays command, or used as the source of a build.
+    AccelerationStructureRead,
+    /// Acceleration structure written by a build or copy command.
+    AccelerationStructureWrite,
+    /// Resource produced outside of this graph, with the access flags of its last usage.
+    Imported(vk::AccessFlags2),
 }
 
 impl ResourceUsage {
@@ -46,11 +61,22 @@ impl ResourceUsage {
             ResourceUsage::Attachment(AttachmentType::Depth) => {
                 vk::AccessFlags2::DEPTH_STENCIL_ATTACHMENT_WRITE
             }
+            ResourceUsage::Attachment(AttachmentType::Stencil) => {
+                vk::AccessFlags2::DEPTH_STENCIL_ATTACHMENT_WRITE
+            }
             ResourceUsage::Attachment(AttachmentType::Resolve(_)) => {
                 vk::AccessFlags2::COLOR_ATTACHMENT_WRITE
             }
+            ResourceUsage::Attachment(AttachmentType::ShadingRate(_)) => {
+                vk::AccessFlags2::FRAGMENT_SHADING_RATE_ATTACHMENT_READ_KHR
+            }
             ResourceUsage::ShaderRead => vk::AccessFlags2::SHADER_READ,
             ResourceUsage::ShaderWrite => vk::AccessFlags2::SHADER_WRITE,
+            ResourceUsage::InputAttachment => vk::AccessFlags2::INPUT_ATTACHMENT_READ,
+            ResourceUsage::TransferWrite => vk::AccessFlags2::TRANSFER_WRITE,
+            ResourceUsage::AccelerationStructureRead => vk::AccessFlags2::ACCELERATION_STRUCTURE_READ_KHR,
+            ResourceUsage::AccelerationStructureWrite => vk::AccessFlags2::ACCELERATION_STRUCTURE_WRITE_KHR,
+            ResourceUsage::Imported(access) => *access,
         }
     }
 
@@ -59,9 +85,15 @@ impl ResourceUsage {
         match self {
             ResourceUsage::Nothing => true,
             ResourceUsage::Present => false,
+            ResourceUsage::Attachment(AttachmentType::ShadingRate(_)) => true,
             ResourceUsage::Attachment(_) => false,
             ResourceUsage::ShaderRead => true,
             ResourceUsage::ShaderWrite => false,
+            ResourceUsage::InputAttachment => true,
+            ResourceUsage::TransferWrite => false,
+            ResourceUsage::AccelerationStructureRead => true,
+            ResourceUsage::AccelerationStructureWrite => false,
+            ResourceUsage::Imported(_) => false,
         }
     }
 }
@@ -2,6 +2,7 @@
 
 use std::fmt::{Display, Formatter};
 
+use crate::CubeFace;
 use crate::graph::resource::ResourceType;
 
 /// Represents a virtual resource in the system, uniquely identified by a string.
@@ -45,6 +46,13 @@ impl VirtualResource {
         }
     }
 
+    /// Create a new image virtual resource for one face of a cube map, named `{name}_{suffix}` where the suffix is given by
+    /// [`CubeFace::suffix()`]. Use [`PhysicalResourceBindings::bind_cube_faces()`](crate::PhysicalResourceBindings::bind_cube_faces)
+    /// to bind these resources.
+    pub fn cube_face(name: &str, face: CubeFace) -> Self {
+        Self::image(format!("{name}_{}", face.suffix()))
+    }
+
     /// Create a new buffer virtual resource.
     pub fn buffer(name: impl Into<String>) -> Self {
         VirtualResource {
@@ -54,6 +62,15 @@ impl VirtualResource {
         }
     }
 
+    /// Create a new acceleration structure virtual resource.
+    pub fn acceleration_structure(name: impl Into<String>) -> Self {
+        VirtualResource {
+            name: name.into(),
+            version: 0,
+            ty: ResourceType::AccelerationStructure,
+        }
+    }
+
     /// 'Upgrades' the resource to a new version of itself. This is used to obtain the virtual resource name of an input resource after
     /// a task completes.
     pub fn upgrade(&self) -> Self {
@@ -137,3 +154,11 @@ macro_rules! buffer {
         ::phobos::prelude::VirtualResource::buffer($id)
     };
 }
+
+/// Syntax sugar to easily construct acceleration structure virtual resources
+#[macro_export]
+macro_rules! acceleration_structure {
+    ($id:literal) => {
+        ::phobos::prelude::VirtualResource::acceleration_structure($id)
+    };
+}
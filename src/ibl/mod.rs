@@ -0,0 +1,229 @@
+//! Built-in passes for image based lighting (IBL) preprocessing. This requires the `shaderc` feature, since the compute shaders
+//! used by these passes are compiled at build time.
+//!
+//! Three passes are provided:
+//! - [`equirect_to_cubemap()`] converts an equirectangular environment map to a cubemap.
+//! - [`irradiance()`] convolves a cubemap into a diffuse irradiance cubemap.
+//! - [`prefilter()`] prefilters a cubemap for specular reflections, writing one roughness level per mip.
+//!
+//! All cubemaps are accessed through 2D array views with 6 layers (one for each face, in the Vulkan cubemap face order),
+//! so the same view can be written to as a storage image and sampled in a later pass. Cubemap images must be created with
+//! [`vk::Format::R16G16B16A16_SFLOAT`] and both [`vk::ImageUsageFlags::STORAGE`] and [`vk::ImageUsageFlags::SAMPLED`] usage.
+//! To sample the results as a `samplerCube` in your own shaders, create an additional view with [`vk::ImageViewType::CUBE`].
+//!
+//! Before using these passes, register their pipelines once with [`register_pipelines()`].
+//! # Example
+//! ```
+//! # use anyhow::Result;
+//! # use phobos::prelude::*;
+//! # use phobos::ibl;
+//! fn environment_graph<'cb>(cache: &mut PipelineCache) -> Result<phobos::graph::pass_graph::BuiltPassGraph<'cb, domain::Compute>> {
+//!     ibl::register_pipelines(cache)?;
+//!     let equirect = VirtualResource::image("equirect");
+//!     let cubemap = VirtualResource::image("cubemap");
+//!     let irradiance = VirtualResource::image("irradiance");
+//!     // One virtual resource for each mip level of the prefiltered cubemap, bind each to a view of a single mip.
+//!     let prefiltered = (0..5).map(|mip| VirtualResource::image(format!("prefiltered_{mip}"))).collect::<Vec<_>>();
+//!
+//!     let convert = ibl::equirect_to_cubemap(&equirect, &cubemap);
+//!     let cubemap = convert.output(&cubemap).unwrap().clone();
+//!     let mut graph = PassGraph::new()
+//!         .add_pass(convert)?
+//!         .add_pass(ibl::irradiance(&cubemap, &irradiance))?;
+//!     for pass in ibl::prefilter(&cubemap, &prefiltered) {
+//!         graph = graph.add_pass(pass)?;
+//!     }
+//!     graph.build()
+//! }
+//! ```
+
+use anyhow::Result;
+use ash::vk;
+
+use crate::{
+    Allocator, ComputePipelineBuilder, Error, ImageView, PhysicalResourceBindings, PipelineCache,
+    PipelineStage, SamplerCreateInfo, ShaderCreateInfo, VirtualResource,
+};
+use crate::command_buffer::traits::*;
+use crate::graph::pass::{Pass, PassBuilder};
+use crate::graph::physical_resource::PhysicalResource;
+use crate::pipeline::pipeline_layout::{PipelineLayoutCreateInfo, PushConstantRange};
+use crate::pipeline::set_layout::DescriptorSetLayoutCreateInfo;
+use crate::sync::domain::ExecutionDomain;
+
+/// Name of the pipeline used by [`equirect_to_cubemap()`].
+pub const EQUIRECT_TO_CUBEMAP_PIPELINE: &str = "phobos_ibl_equirect_to_cubemap";
+/// Name of the pipeline used by [`irradiance()`].
+pub const IRRADIANCE_PIPELINE: &str = "phobos_ibl_irradiance";
+/// Name of the pipeline used by [`prefilter()`].
+pub const PREFILTER_PIPELINE: &str = "phobos_ibl_prefilter";
+
+const EQUIRECT_TO_CUBEMAP_SPIRV: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/equirect_to_cubemap.spv"));
+const IRRADIANCE_SPIRV: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/irradiance.spv"));
+const PREFILTER_SPIRV: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/prefilter.spv"));
+
+// All IBL shaders work in 8x8 tiles, with one workgroup layer per cube face.
+const TILE_SIZE: u32 = 8;
+
+/// All IBL shaders sample from binding 0 and write to binding 1 of set 0. The prefilter shader additionally uses a push constant
+/// for the roughness. This is set explicitly so the pipelines also work without the `shader-reflection` feature.
+fn layout(push_constant_size: u32) -> PipelineLayoutCreateInfo {
+    let binding = |binding: u32, descriptor_type: vk::DescriptorType| vk::DescriptorSetLayoutBinding {
+        binding,
+        descriptor_type,
+        descriptor_count: 1,
+        stage_flags: vk::ShaderStageFlags::COMPUTE,
+        p_immutable_samplers: std::ptr::null(),
+    };
+    PipelineLayoutCreateInfo {
+        flags: Default::default(),
+        set_layouts: vec![DescriptorSetLayoutCreateInfo {
+            bindings: vec![
+                binding(0, vk::DescriptorType::COMBINED_IMAGE_SAMPLER),
+                binding(1, vk::DescriptorType::STORAGE_IMAGE),
+            ],
+            persistent: true,
+            flags: vec![],
+            immutable_samplers: vec![],
+        }],
+        push_constants: (push_constant_size > 0)
+            .then_some(PushConstantRange {
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                offset: 0,
+                size: push_constant_size,
+            })
+            .into_iter()
+            .collect(),
+        persistent: true,
+    }
+}
+
+fn shader(spirv: &[u8]) -> ShaderCreateInfo {
+    let code = spirv
+        .chunks_exact(4)
+        .map(|word| u32::from_ne_bytes(word.try_into().unwrap()))
+        .collect();
+    ShaderCreateInfo::from_spirv(vk::ShaderStageFlags::COMPUTE, code)
+}
+
+/// Register the compute pipelines used by the IBL passes in a pipeline cache. The pipelines are persistent, so this only
+/// needs to be called once.
+/// # Errors
+/// * Fails if registering a pipeline fails.
+pub fn register_pipelines<A: Allocator>(cache: &mut PipelineCache<A>) -> Result<()> {
+    for (name, spirv, push_constant_size) in [
+        (EQUIRECT_TO_CUBEMAP_PIPELINE, EQUIRECT_TO_CUBEMAP_SPIRV, 0),
+        (IRRADIANCE_PIPELINE, IRRADIANCE_SPIRV, 0),
+        (PREFILTER_PIPELINE, PREFILTER_SPIRV, std::mem::size_of::<f32>() as u32),
+    ] {
+        let mut info = ComputePipelineBuilder::new(name)
+            .set_shader(shader(spirv))
+            .persistent()
+            .build();
+        info.layout = layout(push_constant_size);
+        cache.create_named_compute_pipeline(info)?;
+    }
+    Ok(())
+}
+
+fn resolve_image<'a>(bindings: &'a PhysicalResourceBindings, resource: &VirtualResource) -> Result<&'a ImageView> {
+    match bindings.resolve(resource) {
+        Some(PhysicalResource::Image(image)) => Ok(image),
+        _ => Err(Error::NoResourceBound(resource.uid()).into()),
+    }
+}
+
+/// Build a pass that samples `src` and writes every face of `dst` using one of the IBL pipelines.
+fn convolution_pass<'cb, D: ExecutionDomain + ComputeSupport, U, A: Allocator>(
+    name: String,
+    pipeline: &'static str,
+    src: &VirtualResource,
+    dst: &VirtualResource,
+    sampler: SamplerCreateInfo,
+    roughness: Option<f32>,
+) -> Pass<'cb, D, U, A> {
+    let (src_resource, dst_resource) = (src.clone(), dst.clone());
+    PassBuilder::new(name)
+        .sample_image(src, PipelineStage::COMPUTE_SHADER)
+        .write_storage_image(dst, PipelineStage::COMPUTE_SHADER)
+        .execute_fn(move |cmd, pool, bindings, _| {
+            let src = resolve_image(bindings, &src_resource)?;
+            let dst = resolve_image(bindings, &dst_resource)?;
+            let sampler = pool.get_sampler(&sampler)?;
+            let size = dst.base_level_size();
+            let mut cmd = cmd
+                .bind_compute_pipeline(pipeline)?
+                .bind_sampled_image(0, 0, src, &sampler)?
+                .bind_storage_image(0, 1, dst)?;
+            if let Some(roughness) = roughness {
+                cmd = cmd.push_constant(vk::ShaderStageFlags::COMPUTE, 0, &roughness)?;
+            }
+            cmd.dispatch(
+                (size.width + TILE_SIZE - 1) / TILE_SIZE,
+                (size.height + TILE_SIZE - 1) / TILE_SIZE,
+                6,
+            )
+        })
+        .build()
+}
+
+/// Convert an equirectangular environment map to a cubemap. `equirect` must be bound to a regular 2D view, and `cubemap` to a
+/// 2D array view with 6 layers. Only the first mip level of the cubemap view is written.
+pub fn equirect_to_cubemap<'cb, D: ExecutionDomain + ComputeSupport, U, A: Allocator>(
+    equirect: &VirtualResource,
+    cubemap: &VirtualResource,
+) -> Pass<'cb, D, U, A> {
+    convolution_pass(
+        format!("equirect_to_cubemap_{}", cubemap.name()),
+        EQUIRECT_TO_CUBEMAP_PIPELINE,
+        equirect,
+        cubemap,
+        SamplerCreateInfo::default().address_mode_uvw(
+            vk::SamplerAddressMode::REPEAT,
+            vk::SamplerAddressMode::CLAMP_TO_EDGE,
+            vk::SamplerAddressMode::CLAMP_TO_EDGE,
+        ),
+        None,
+    )
+}
+
+/// Convolve a cubemap into a diffuse irradiance cubemap. Both must be bound to 2D array views with 6 layers. Irradiance varies
+/// slowly, so a small output size like 32x32 is generally sufficient.
+pub fn irradiance<'cb, D: ExecutionDomain + ComputeSupport, U, A: Allocator>(
+    cubemap: &VirtualResource,
+    irradiance: &VirtualResource,
+) -> Pass<'cb, D, U, A> {
+    convolution_pass(
+        format!("irradiance_{}", irradiance.name()),
+        IRRADIANCE_PIPELINE,
+        cubemap,
+        irradiance,
+        SamplerCreateInfo::default().address_mode(vk::SamplerAddressMode::CLAMP_TO_EDGE),
+        None,
+    )
+}
+
+/// Prefilter a cubemap for specular image based lighting. This creates one pass for each element of `mips`, where mip `i` is
+/// filtered with roughness `i / (mips.len() - 1)`. Each element must be bound to a 2D array view with 6 layers of a single mip level
+/// of the prefiltered cubemap, and `cubemap` must be bound to a 2D array view with 6 layers.
+///
+/// If the view of `cubemap` includes mip levels, they are used to reduce aliasing at high roughness values.
+pub fn prefilter<'cb, D: ExecutionDomain + ComputeSupport, U, A: Allocator>(
+    cubemap: &VirtualResource,
+    mips: &[VirtualResource],
+) -> Vec<Pass<'cb, D, U, A>> {
+    let max_mip = mips.len().saturating_sub(1).max(1) as f32;
+    mips.iter()
+        .enumerate()
+        .map(|(mip, resource)| {
+            convolution_pass(
+                format!("prefilter_{}", resource.name()),
+                PREFILTER_PIPELINE,
+                cubemap,
+                resource,
+                SamplerCreateInfo::default().address_mode(vk::SamplerAddressMode::CLAMP_TO_EDGE),
+                Some(mip as f32 / max_mip),
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,398 @@
+//! Rendering backend for [Dear ImGui](https://github.com/imgui-rs/imgui-rs). This requires the `imgui` feature, which
+//! also enables `shaderc` since the shaders used by the renderer are compiled at build time.
+//!
+//! The [`ImGuiRenderer`] draws all textures through a single bindless array of sampled images. Textures are registered
+//! with [`ImGuiRenderer::register_texture()`], which returns a [`TextureId`] that is simply the index of the texture in this array.
+//! This id can be passed directly to widgets such as [`imgui::Image`](::imgui::Image).
+//! - Upload the font atlas once with [`ImGuiRenderer::upload_fonts()`], and again whenever fonts change.
+//! - Every frame, create a pass drawing the ImGui draw data on top of a color attachment with [`ImGuiRenderer::pass()`].
+//!   Vertex and index data is uploaded through the scratch allocator of the pass.
+//!
+//! ImGui colors are written to the attachment unchanged, so the color attachment should use a UNORM format to match
+//! the reference ImGui backends.
+//! # Example
+//! ```
+//! # use anyhow::Result;
+//! # use phobos::prelude::*;
+//! # use phobos::imgui::ImGuiRenderer;
+//! fn draw_ui<'cb>(
+//!     renderer: &mut ImGuiRenderer,
+//!     ctx: &mut imgui::Context,
+//!     swapchain: &VirtualResource,
+//! ) -> Result<Pass<'cb, domain::All, ()>> {
+//!     let ui = ctx.new_frame();
+//!     ui.text("Hello, world!");
+//!     let draw_data = ctx.render();
+//!     renderer.pass(draw_data, swapchain)
+//! }
+//! ```
+
+use ::imgui::{DrawCmd, DrawCmdParams, DrawData, DrawIdx, DrawVert, FontAtlas, TextureId};
+use anyhow::{anyhow, ensure, Result};
+use ash::vk;
+
+use crate::{
+    Allocator, Buffer, DefaultAllocator, DeletionQueue, Device, GfxSupport, Image, ImageCreateInfo, ImageView,
+    MemoryType, PipelineBuilder, PipelineCache, PipelineStage, SamplerCreateInfo, ShaderCreateInfo,
+    TransferSupport, VirtualResource,
+};
+use crate::command_buffer::traits::*;
+use crate::graph::pass::{Pass, PassBuilder};
+use crate::pipeline::pipeline_layout::{PipelineLayoutCreateInfo, PushConstantRange};
+use crate::pipeline::set_layout::DescriptorSetLayoutCreateInfo;
+use crate::sync::domain::ExecutionDomain;
+use crate::IncompleteCommandBuffer;
+
+/// Name of the pipeline used to draw ImGui draw lists.
+pub const IMGUI_PIPELINE: &str = "phobos_imgui";
+
+/// Maximum number of textures that can be registered at the same time.
+pub const MAX_TEXTURES: u32 = 4096;
+
+const VERTEX_SPIRV: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/imgui.vert.spv"));
+const FRAGMENT_SPIRV: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/imgui.frag.spv"));
+
+/// Push constants shared by the vertex and fragment shader.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+struct PushConstants {
+    scale: [f32; 2],
+    translate: [f32; 2],
+    texture_index: u32,
+}
+
+/// A single draw command, with all data it needs copied out of the draw data.
+struct ImGuiDraw {
+    texture_index: u32,
+    scissor: vk::Rect2D,
+    first_index: u32,
+    index_count: u32,
+    vertex_offset: i32,
+}
+
+fn shader(stage: vk::ShaderStageFlags, spirv: &[u8]) -> ShaderCreateInfo {
+    let code = spirv
+        .chunks_exact(4)
+        .map(|word| u32::from_ne_bytes(word.try_into().unwrap()))
+        .collect();
+    ShaderCreateInfo::from_spirv(stage, code)
+}
+
+/// The ImGui shaders sample from a partially bound array of textures at binding 0 of set 0, and use push constants
+/// for the transform and texture index. This is set explicitly so the pipeline also works without the `shader-reflection` feature.
+fn layout() -> PipelineLayoutCreateInfo {
+    PipelineLayoutCreateInfo {
+        flags: Default::default(),
+        set_layouts: vec![DescriptorSetLayoutCreateInfo {
+            bindings: vec![vk::DescriptorSetLayoutBinding {
+                binding: 0,
+                descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                descriptor_count: MAX_TEXTURES,
+                stage_flags: vk::ShaderStageFlags::FRAGMENT,
+                p_immutable_samplers: std::ptr::null(),
+            }],
+            persistent: true,
+            flags: vec![vk::DescriptorBindingFlags::PARTIALLY_BOUND | vk::DescriptorBindingFlags::VARIABLE_DESCRIPTOR_COUNT],
+            immutable_samplers: vec![],
+        }],
+        push_constants: vec![PushConstantRange {
+            stage_flags: vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+            offset: 0,
+            size: std::mem::size_of::<PushConstants>() as u32,
+        }],
+        persistent: true,
+    }
+}
+
+/// Renders Dear ImGui draw data through the pass graph. See the [module-level documentation](self).
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct ImGuiRenderer<A: Allocator = DefaultAllocator> {
+    device: Device,
+    // Registered textures, indexed by texture id. Unregistered slots are reused by the next registration.
+    textures: Vec<Option<ImageView>>,
+    font_texture: Option<(TextureId, Image<A>)>,
+    // Replaced font atlases and staging buffers may still be in use by frames in flight.
+    deferred_images: DeletionQueue<Image<A>>,
+    deferred_buffers: DeletionQueue<Buffer<A>>,
+}
+
+impl<A: Allocator> ImGuiRenderer<A> {
+    /// Create a new ImGui renderer, and register its pipeline in the pipeline cache. Font atlases and staging buffers that
+    /// are replaced are kept alive for `frames_in_flight` calls to [`ImGuiRenderer::pass()`], so this should be the number
+    /// of frames that can be in flight at the same time.
+    /// # Errors
+    /// * Fails if registering the pipeline fails.
+    pub fn new(device: Device, cache: &mut PipelineCache<A>, frames_in_flight: u32) -> Result<Self> {
+        let mut info = PipelineBuilder::new(IMGUI_PIPELINE)
+            .vertex_input(0, vk::VertexInputRate::VERTEX)
+            .vertex_attribute(0, 0, vk::Format::R32G32_SFLOAT)?
+            .vertex_attribute(0, 1, vk::Format::R32G32_SFLOAT)?
+            .vertex_attribute(0, 2, vk::Format::R8G8B8A8_UNORM)?
+            .dynamic_states(&[vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR])
+            .cull_mask(vk::CullModeFlags::NONE)
+            .blend_attachment(
+                vk::BlendFactor::SRC_ALPHA,
+                vk::BlendFactor::ONE_MINUS_SRC_ALPHA,
+                vk::BlendOp::ADD,
+                vk::BlendFactor::ONE,
+                vk::BlendFactor::ONE_MINUS_SRC_ALPHA,
+                vk::BlendOp::ADD,
+            )
+            .attach_shader(shader(vk::ShaderStageFlags::VERTEX, VERTEX_SPIRV))
+            .attach_shader(shader(vk::ShaderStageFlags::FRAGMENT, FRAGMENT_SPIRV))
+            .build();
+        info.layout = layout();
+        cache.create_named_pipeline(info)?;
+
+        Ok(Self {
+            device,
+            textures: Vec::new(),
+            font_texture: None,
+            deferred_images: DeletionQueue::new(frames_in_flight + 1),
+            deferred_buffers: DeletionQueue::new(frames_in_flight + 1),
+        })
+    }
+
+    /// Build the font atlas and upload it to a new texture. This records the upload on `cmd`, followed by the barrier
+    /// needed to sample the texture in the UI pass. The texture id of the atlas is stored in `fonts.tex_id`, and a
+    /// previously uploaded atlas is unregistered.
+    /// # Errors
+    /// * Fails if allocating the texture or staging buffer fails.
+    /// * Fails if all texture slots are in use.
+    pub fn upload_fonts<'q, D: ExecutionDomain + TransferSupport>(
+        &mut self,
+        cmd: IncompleteCommandBuffer<'q, D, A>,
+        allocator: &mut A,
+        fonts: &mut FontAtlas,
+    ) -> Result<IncompleteCommandBuffer<'q, D, A>> {
+        let atlas = fonts.build_rgba32_texture();
+        let image = Image::new(
+            self.device.clone(),
+            allocator,
+            ImageCreateInfo {
+                width: atlas.width,
+                height: atlas.height,
+                depth: 1,
+                usage: vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST,
+                format: vk::Format::R8G8B8A8_UNORM,
+                samples: vk::SampleCountFlags::TYPE_1,
+                mip_levels: 1,
+                layers: 1,
+                flags: vk::ImageCreateFlags::empty(),
+                memory_type: MemoryType::GpuOnly,
+            },
+        )?;
+        let view = image.whole_view(vk::ImageAspectFlags::COLOR)?;
+        let staging = Buffer::new(
+            self.device.clone(),
+            allocator,
+            atlas.data.len() as vk::DeviceSize,
+            MemoryType::CpuToGpu,
+        )?;
+        staging.view_full().mapped_slice::<u8>()?.copy_from_slice(atlas.data);
+        let cmd = cmd
+            .transition_image(
+                &view,
+                PipelineStage::TOP_OF_PIPE,
+                PipelineStage::TRANSFER,
+                vk::ImageLayout::UNDEFINED,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                vk::AccessFlags2::NONE,
+                vk::AccessFlags2::TRANSFER_WRITE,
+            )
+            .copy_buffer_to_image(&staging.view_full(), &view)?
+            .transition_image(
+                &view,
+                PipelineStage::TRANSFER,
+                PipelineStage::FRAGMENT_SHADER,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                vk::AccessFlags2::TRANSFER_WRITE,
+                vk::AccessFlags2::SHADER_READ,
+            );
+        self.deferred_buffers.push(staging);
+
+        if let Some((id, old)) = self.font_texture.take() {
+            self.unregister_texture(id);
+            self.deferred_images.push(old);
+        }
+        let id = self.register_texture(view)?;
+        fonts.tex_id = id;
+        self.font_texture = Some((id, image));
+        Ok(cmd)
+    }
+
+    /// Register a texture so it can be drawn by ImGui. The returned id is the index of the texture in the bindless
+    /// texture array, and stays valid until the texture is unregistered. The image must be in
+    /// [`vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL`] when the UI pass executes.
+    /// # Errors
+    /// * Fails if [`MAX_TEXTURES`] textures are already registered.
+    pub fn register_texture(&mut self, view: ImageView) -> Result<TextureId> {
+        let index = match self.textures.iter().position(Option::is_none) {
+            Some(index) => index,
+            None => {
+                ensure!(
+                    self.textures.len() < MAX_TEXTURES as usize,
+                    "Cannot register more than {MAX_TEXTURES} ImGui textures"
+                );
+                self.textures.push(None);
+                self.textures.len() - 1
+            }
+        };
+        self.textures[index] = Some(view);
+        Ok(TextureId::new(index))
+    }
+
+    /// Unregister a texture, returning its image view if it was registered. Its id may be returned again by a later call
+    /// to [`ImGuiRenderer::register_texture()`]. Passes that were already created keep using the old texture,
+    /// so the image must stay alive until those have finished executing.
+    pub fn unregister_texture(&mut self, id: TextureId) -> Option<ImageView> {
+        let view = self.textures.get_mut(id.id())?.take();
+        // Trim unused slots at the end, so the bound array stays as small as possible.
+        while let Some(None) = self.textures.last() {
+            self.textures.pop();
+        }
+        view
+    }
+
+    /// Get the image view of a registered texture, if it exists.
+    pub fn texture(&self, id: TextureId) -> Option<&ImageView> {
+        self.textures.get(id.id()).and_then(Option::as_ref)
+    }
+
+    /// Create a pass that draws ImGui draw data on top of `target`. The existing contents of `target` are preserved.
+    /// User callbacks are not supported and are skipped. This also frees resources that are no longer in use by frames in flight,
+    /// so it should be called exactly once per frame.
+    /// # Errors
+    /// * Fails if a draw command uses a texture that is not registered.
+    pub fn pass<'cb, D: ExecutionDomain + GfxSupport, U>(
+        &mut self,
+        draw_data: &DrawData,
+        target: &VirtualResource,
+    ) -> Result<Pass<'cb, D, U, A>> {
+        self.deferred_images.next_frame();
+        self.deferred_buffers.next_frame();
+
+        let framebuffer_size = [
+            draw_data.display_size[0] * draw_data.framebuffer_scale[0],
+            draw_data.display_size[1] * draw_data.framebuffer_scale[1],
+        ];
+        let mut vertices: Vec<DrawVert> = Vec::with_capacity(draw_data.total_vtx_count as usize);
+        let mut indices: Vec<DrawIdx> = Vec::with_capacity(draw_data.total_idx_count as usize);
+        let mut draws = Vec::new();
+        for draw_list in draw_data.draw_lists() {
+            for command in draw_list.commands() {
+                let DrawCmd::Elements {
+                    count,
+                    cmd_params:
+                        DrawCmdParams {
+                            clip_rect,
+                            texture_id,
+                            vtx_offset,
+                            idx_offset,
+                        },
+                } = command
+                else {
+                    continue;
+                };
+                // Transform the clip rectangle to framebuffer space, and clamp it to the framebuffer.
+                let clip = |i: usize| {
+                    ((clip_rect[i] - draw_data.display_pos[i % 2]) * draw_data.framebuffer_scale[i % 2]).clamp(0.0, framebuffer_size[i % 2])
+                };
+                let (min_x, min_y, max_x, max_y) = (clip(0), clip(1), clip(2), clip(3));
+                if count == 0 || max_x <= min_x || max_y <= min_y {
+                    continue;
+                }
+
+                ensure!(
+                    self.texture(texture_id).is_some(),
+                    "ImGui draw command uses texture {texture_id:?} that is not registered"
+                );
+                draws.push(ImGuiDraw {
+                    texture_index: texture_id.id() as u32,
+                    scissor: vk::Rect2D {
+                        offset: vk::Offset2D {
+                            x: min_x as i32,
+                            y: min_y as i32,
+                        },
+                        extent: vk::Extent2D {
+                            width: (max_x - min_x) as u32,
+                            height: (max_y - min_y) as u32,
+                        },
+                    },
+                    first_index: (indices.len() + idx_offset) as u32,
+                    index_count: count as u32,
+                    vertex_offset: (vertices.len() + vtx_offset) as i32,
+                });
+            }
+            vertices.extend_from_slice(draw_list.vtx_buffer());
+            indices.extend_from_slice(draw_list.idx_buffer());
+        }
+
+        // Unregistered slots are never sampled, but the array must be contiguous, so fill them with any registered texture.
+        let textures = match self.textures.iter().flatten().next() {
+            None => Vec::new(),
+            Some(fallback) => self
+                .textures
+                .iter()
+                .map(|view| view.as_ref().unwrap_or(fallback).clone())
+                .collect(),
+        };
+        let push_constants = PushConstants {
+            scale: [2.0 / draw_data.display_size[0], 2.0 / draw_data.display_size[1]],
+            translate: [
+                -1.0 - draw_data.display_pos[0] * 2.0 / draw_data.display_size[0],
+                -1.0 - draw_data.display_pos[1] * 2.0 / draw_data.display_size[1],
+            ],
+            texture_index: 0,
+        };
+        let index_type = match std::mem::size_of::<DrawIdx>() {
+            2 => vk::IndexType::UINT16,
+            4 => vk::IndexType::UINT32,
+            size => return Err(anyhow!("Unsupported ImGui index size {size}")),
+        };
+
+        let pass = PassBuilder::render("imgui")
+            .color_attachment(target, vk::AttachmentLoadOp::LOAD, None)?
+            .execute_fn(move |mut cmd, pool, _, _| {
+                if draws.is_empty() {
+                    return Ok(cmd);
+                }
+                let mut vertex_buffer = pool.allocate_scratch_buffer(std::mem::size_of_val(vertices.as_slice()) as vk::DeviceSize)?;
+                // SAFETY: Vertices are plain data, and the scratch allocator aligns allocations to at least 4 bytes.
+                unsafe { vertex_buffer.mapped_slice_unchecked::<DrawVert>()? }.copy_from_slice(&vertices);
+                let mut index_buffer = pool.allocate_scratch_buffer(std::mem::size_of_val(indices.as_slice()) as vk::DeviceSize)?;
+                index_buffer.mapped_slice::<DrawIdx>()?.copy_from_slice(&indices);
+                let sampler = pool.get_sampler(&SamplerCreateInfo::default().address_mode(vk::SamplerAddressMode::CLAMP_TO_EDGE))?;
+
+                cmd = cmd
+                    .bind_graphics_pipeline(IMGUI_PIPELINE)?
+                    .viewport(vk::Viewport {
+                        x: 0.0,
+                        y: 0.0,
+                        width: framebuffer_size[0],
+                        height: framebuffer_size[1],
+                        min_depth: 0.0,
+                        max_depth: 1.0,
+                    })
+                    .bind_vertex_buffer(0, &vertex_buffer)
+                    .bind_index_buffer(&index_buffer, index_type)
+                    .bind_sampled_image_array(0, 0, &textures, &sampler)?
+                    .push_constant(vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT, 0, &push_constants)?;
+                for draw in &draws {
+                    cmd = cmd
+                        .scissor(draw.scissor)
+                        .push_constant(
+                            vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+                            std::mem::size_of::<[f32; 4]>() as u32,
+                            &draw.texture_index,
+                        )?
+                        .draw_indexed(draw.index_count, 1, draw.first_index, draw.vertex_offset, 0)?;
+                }
+                Ok(cmd)
+            })
+            .build();
+        Ok(pass)
+    }
+}
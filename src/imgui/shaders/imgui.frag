@@ -0,0 +1,19 @@
+#version 450
+#extension GL_EXT_nonuniform_qualifier : require
+
+layout(location = 0) in vec2 in_uv;
+layout(location = 1) in vec4 in_color;
+
+layout(push_constant) uniform PC {
+    vec2 scale;
+    vec2 translate;
+    uint texture_index;
+} pc;
+
+layout(set = 0, binding = 0) uniform sampler2D textures[];
+
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    out_color = in_color * texture(textures[pc.texture_index], in_uv);
+}
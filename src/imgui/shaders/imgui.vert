@@ -0,0 +1,22 @@
+#version 450
+
+layout(location = 0) in vec2 in_position;
+layout(location = 1) in vec2 in_uv;
+layout(location = 2) in vec4 in_color;
+
+layout(push_constant) uniform PC {
+    // Transforms ImGui display coordinates to clip space
+    vec2 scale;
+    vec2 translate;
+    // Index of the texture in the bindless texture array
+    uint texture_index;
+} pc;
+
+layout(location = 0) out vec2 out_uv;
+layout(location = 1) out vec4 out_color;
+
+void main() {
+    gl_Position = vec4(in_position * pc.scale + pc.translate, 0.0, 1.0);
+    out_uv = in_uv;
+    out_color = in_color;
+}
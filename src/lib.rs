@@ -7,13 +7,14 @@
 //! To get started, the easiest way is to simply
 //! ```
 //! // Import all important traits
-//! use phobos::prelude::traits;
+//! use phobos::prelude::traits::*;
 //! // Import types under a namespace.
-//! use phobos::prelude as ph;
+//! use phobos::prelude::types as ph;
 //!
 //! // Or, if you dont care about using the types under a namespace
 //! use phobos::prelude::*;
 //! ```
+//! See the [`prelude`] module for an overview of what is exported where.
 //!
 //! # Example
 //!
@@ -85,6 +86,7 @@
 //! - [`image`] for managing [`VkImage`](vk::Image) and [`VkImageView`](vk::ImageView) objects.
 //! - [`buffer`] for managing [`VkBuffer`](vk::Buffer) objects.
 //! - [`util`] for various utilities and common patterns like buffer uploads.
+//! - [`video`] for hardware accelerated video decoding.
 
 #![cfg_attr(feature = "fsr2", feature(new_uninit))]
 #![warn(missing_docs)]
@@ -108,8 +110,22 @@ pub mod graph;
 pub mod pipeline;
 pub mod resource;
 pub mod sync;
+pub mod upscaler;
 pub mod util;
+pub mod video;
 pub mod wsi;
 
+#[cfg(feature = "aftermath")]
+pub mod aftermath;
 #[cfg(feature = "fsr2")]
 pub mod fsr2;
+#[cfg(feature = "fsr3")]
+pub mod fsr3;
+#[cfg(feature = "egui")]
+pub mod egui;
+#[cfg(feature = "imgui")]
+pub mod imgui;
+#[cfg(feature = "shaderc")]
+pub mod ibl;
+#[cfg(feature = "texture-loader")]
+pub mod texture_loader;
@@ -1,12 +1,15 @@
 //! The pipeline builder is used to easily create graphics pipelines correctly.
 
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use anyhow::Result;
 use ash::vk;
+use bytemuck::NoUninit;
 
-use crate::{ByteSize, Error, PipelineCreateInfo, ShaderCreateInfo};
+use crate::{ByteSize, Error, PipelineCreateInfo, Sampler, ShaderCreateInfo};
 use crate::pipeline::create_info::*;
+use crate::pipeline::vertex::VertexInput;
 
 /// Used to facilitate creating a graphics pipeline. For an example, please check the
 /// [`pipeline`](crate::pipeline) module level documentation.
@@ -27,6 +30,8 @@ impl PipelineBuilder {
             inner: PipelineCreateInfo {
                 name: name.into(),
                 layout: Default::default(),
+                immutable_samplers: vec![],
+                reflected_vertex_bindings: vec![],
                 vertex_input_bindings: vec![],
                 vertex_attributes: vec![],
                 shaders: vec![],
@@ -111,8 +116,13 @@ impl PipelineBuilder {
                     color_formats: vec![],
                     depth_format: None,
                     stencil_format: None,
+                    shading_rate_attachment: false,
                 },
                 tesselation_info: None,
+                fragment_shading_rate: None,
+                conservative_rasterization: None,
+                depth_clip_negative_one_to_one: false,
+                shader_objects: false,
                 vk_vertex_inputs: vec![],
                 vk_attributes: vec![],
                 vertex_input_state: vk::PipelineVertexInputStateCreateInfo {
@@ -132,6 +142,9 @@ impl PipelineBuilder {
                 vk_dynamic_state: Default::default(),
                 vk_rendering_state: Default::default(),
                 vk_tessellation_state: None,
+                vk_fragment_shading_rate_state: None,
+                vk_conservative_rasterization_state: None,
+                vk_depth_clip_control_state: None,
             },
             vertex_binding_offsets: Default::default(),
         }
@@ -181,12 +194,65 @@ impl PipelineBuilder {
         Ok(self)
     }
 
-    /// Add a shader to the pipeline.
+    /// Add a vertex input binding with all attributes of a [`VertexInput`] type. The stride of the binding is the size of the
+    /// vertex type, and its attributes are assigned consecutive locations, starting after the highest location used by attributes
+    /// that were added before. See the [`vertex`](crate::pipeline::vertex) module for an example.
+    pub fn vertex_layout<V: VertexInput>(mut self, binding: u32, rate: vk::VertexInputRate) -> Self {
+        let first_location = self
+            .inner
+            .vertex_attributes
+            .iter()
+            .map(|attribute| attribute.0.location + 1)
+            .max()
+            .unwrap_or(0);
+        // Attributes added with `vertex_attribute()` are placed after the vertex type.
+        self.vertex_binding_offsets.insert(binding, V::STRIDE);
+        self.inner
+            .vertex_input_bindings
+            .push(VertexInputBindingDescription(vk::VertexInputBindingDescription {
+                binding,
+                stride: V::STRIDE,
+                input_rate: rate,
+            }));
+        for (location, attribute) in (first_location..).zip(V::attributes()) {
+            self.inner
+                .vertex_attributes
+                .push(VertexInputAttributeDescription(vk::VertexInputAttributeDescription {
+                    location,
+                    binding,
+                    format: attribute.format,
+                    offset: attribute.offset,
+                }));
+        }
+        self
+    }
+
+    /// Add a shader to the pipeline. Geometry shaders require the `geometryShader` device feature, which must be requested in
+    /// [`GPURequirements::features`](crate::GPURequirements::features).
+    /// For tessellation shaders, see [`PipelineBuilder::tessellation_shaders()`].
     pub fn attach_shader(mut self, info: ShaderCreateInfo) -> Self {
         self.inner.shaders.push(info);
         self
     }
 
+    /// Set the value of a specialization constant for every attached shader of the given stage. Shaders must be attached before
+    /// calling this. See [`ShaderCreateInfo::specialization_constant()`].
+    pub fn specialization_constant<T: NoUninit>(mut self, stage: vk::ShaderStageFlags, id: u32, value: &T) -> Self {
+        self.inner.shaders = self
+            .inner
+            .shaders
+            .into_iter()
+            .map(|shader| {
+                if stage.contains(shader.stage()) {
+                    shader.specialization_constant(id, value)
+                } else {
+                    shader
+                }
+            })
+            .collect();
+        self
+    }
+
     /// Set depth testing mode.
     pub fn depth_test(mut self, enable: bool) -> Self {
         self.inner.depth_stencil.0.depth_test_enable = vk::Bool32::from(enable);
@@ -205,12 +271,20 @@ impl PipelineBuilder {
         self
     }
 
-    /// Toggle depth clamping.
+    /// Toggle depth clamping. Instead of clipping primitives against the near and far planes, their depth is clamped to the
+    /// viewport depth range. Requires the `depthClamp` device feature, which is enabled automatically when it is supported.
     pub fn depth_clamp(mut self, enable: bool) -> Self {
         self.inner.rasterizer.0.depth_clamp_enable = vk::Bool32::from(enable);
         self
     }
 
+    /// Use a clip space depth range of `[-1, 1]` like OpenGL, instead of the default `[0, 1]`.
+    /// Requires [`ExtensionID::DepthClipControl`](crate::core::device::ExtensionID::DepthClipControl).
+    pub fn depth_clip_negative_one_to_one(mut self, enable: bool) -> Self {
+        self.inner.depth_clip_negative_one_to_one = enable;
+        self
+    }
+
     /// Configure all depth state in one call.
     pub fn depth(self, test: bool, write: bool, clamp: bool, op: vk::CompareOp) -> Self {
         self.depth_test(test)
@@ -219,7 +293,10 @@ impl PipelineBuilder {
             .depth_op(op)
     }
 
-    /// Add a dynamic state to the pipeline.
+    /// Add a dynamic state to the pipeline. Besides viewport and scissor, the extended dynamic states that are core in Vulkan 1.3
+    /// (such as cull mode, front face, primitive topology and depth test state) can always be made dynamic, and set with the
+    /// corresponding `set_*` functions on [`GraphicsCmdBuffer`](crate::GraphicsCmdBuffer).
+    /// [`vk::DynamicState::COLOR_BLEND_ENABLE_EXT`] requires `VK_EXT_extended_dynamic_state3`.
     pub fn dynamic_state(mut self, state: vk::DynamicState) -> Self {
         self.inner.dynamic_states.push(state);
         // When setting a viewport dynamic state, we still need a dummy viewport to make validation shut up
@@ -252,7 +329,38 @@ impl PipelineBuilder {
         self
     }
 
-    /// Set the polygon mode.
+    /// Set the primitive topology used to assemble vertices. Defaults to [`vk::PrimitiveTopology::TRIANGLE_LIST`].
+    /// # Example
+    /// ```
+    /// # use phobos::prelude::*;
+    /// // A pipeline for drawing debug lines, where the line width can be changed per draw.
+    /// let pci = PipelineBuilder::new("debug_lines")
+    ///     .topology(vk::PrimitiveTopology::LINE_LIST)
+    ///     .dynamic_state(vk::DynamicState::LINE_WIDTH)
+    ///     .build();
+    /// ```
+    pub fn topology(mut self, topology: vk::PrimitiveTopology) -> Self {
+        self.inner.input_assembly.0.topology = topology;
+        self
+    }
+
+    /// Enable primitive restart, so that a special index value (such as `0xFFFFFFFF` for 32-bit indices) starts a new primitive
+    /// in indexed draws. This is only allowed for strip and fan topologies.
+    pub fn primitive_restart(mut self, enable: bool) -> Self {
+        self.inner.input_assembly.0.primitive_restart_enable = vk::Bool32::from(enable);
+        self
+    }
+
+    /// Set the width of rasterized lines. Widths other than `1.0` require the `wideLines` device feature, which is enabled
+    /// automatically when it is supported. To change the line width per draw instead, add [`vk::DynamicState::LINE_WIDTH`] and use
+    /// [`GraphicsCmdBuffer::set_line_width()`](crate::GraphicsCmdBuffer::set_line_width).
+    pub fn line_width(mut self, width: f32) -> Self {
+        self.inner.rasterizer.0.line_width = width;
+        self
+    }
+
+    /// Set the polygon mode. Modes other than [`vk::PolygonMode::FILL`] require the `fillModeNonSolid` device feature, which
+    /// is enabled automatically when it is supported.
     pub fn polygon_mode(mut self, mode: vk::PolygonMode) -> Self {
         self.inner.rasterizer.0.polygon_mode = mode;
         self
@@ -298,6 +406,98 @@ impl PipelineBuilder {
         self
     }
 
+    /// Attach a tessellation control and a tessellation evaluation shader, and enable tessellation with `patch_control_points`
+    /// control points per patch. This sets the primitive topology to [`vk::PrimitiveTopology::PATCH_LIST`], see
+    /// [`PipelineBuilder::tessellation()`]. Requires the `tessellationShader` device feature, which is enabled automatically
+    /// when it is supported.
+    /// # Example
+    /// ```
+    /// # use phobos::prelude::*;
+    /// fn terrain_pipeline(vertex: ShaderCreateInfo, control: ShaderCreateInfo, evaluation: ShaderCreateInfo, fragment: ShaderCreateInfo) -> PipelineCreateInfo {
+    ///     PipelineBuilder::new("terrain")
+    ///         .attach_shader(vertex)
+    ///         .tessellation_shaders(control, evaluation, 4)
+    ///         .attach_shader(fragment)
+    ///         .build()
+    /// }
+    /// ```
+    pub fn tessellation_shaders(
+        self,
+        control: ShaderCreateInfo,
+        evaluation: ShaderCreateInfo,
+        patch_control_points: u32,
+    ) -> Self {
+        self.attach_shader(control)
+            .attach_shader(evaluation)
+            .tessellation(patch_control_points, vk::PipelineTessellationStateCreateFlags::empty())
+    }
+
+    /// Set the fragment shading rate for this pipeline. Requires [`ExtensionID::FragmentShadingRate`](crate::core::device::ExtensionID::FragmentShadingRate).
+    /// The combiner operations determine how this rate is combined with the per-primitive rate (first combiner), and then with the rate
+    /// from the shading rate attachment (second combiner).
+    /// To change the rate per draw, add [`vk::DynamicState::FRAGMENT_SHADING_RATE_KHR`] as a dynamic state and use
+    /// [`set_fragment_shading_rate`](crate::GraphicsCmdBuffer::set_fragment_shading_rate).
+    pub fn fragment_shading_rate(
+        mut self,
+        fragment_size: vk::Extent2D,
+        combiner_ops: [vk::FragmentShadingRateCombinerOpKHR; 2],
+    ) -> Self {
+        self.inner.fragment_shading_rate = Some(PipelineFragmentShadingRateStateCreateInfo(
+            vk::PipelineFragmentShadingRateStateCreateInfoKHR {
+                fragment_size,
+                combiner_ops,
+                ..Default::default()
+            },
+        ));
+        self
+    }
+
+    /// Enable conservative rasterization. With [`vk::ConservativeRasterizationModeEXT::OVERESTIMATE`], every pixel that is
+    /// partially covered by a primitive generates a fragment, which is needed for example to voxelize a scene without holes.
+    /// `extra_primitive_overestimation_size` grows primitives by this amount of pixels in overestimation mode, and must be at most
+    /// `maxExtraPrimitiveOverestimationSize` from [`Device::conservative_rasterization_properties()`](crate::Device::conservative_rasterization_properties).
+    /// Requires [`ExtensionID::ConservativeRasterization`](crate::core::device::ExtensionID::ConservativeRasterization).
+    /// # Example
+    /// ```
+    /// # use phobos::prelude::*;
+    /// fn voxelize_pipeline(vertex: ShaderCreateInfo, geometry: ShaderCreateInfo, fragment: ShaderCreateInfo) -> PipelineCreateInfo {
+    ///     PipelineBuilder::new("voxelize")
+    ///         .attach_shader(vertex)
+    ///         .attach_shader(geometry)
+    ///         .attach_shader(fragment)
+    ///         .conservative_rasterization(vk::ConservativeRasterizationModeEXT::OVERESTIMATE, 0.0)
+    ///         .build()
+    /// }
+    /// ```
+    pub fn conservative_rasterization(
+        mut self,
+        mode: vk::ConservativeRasterizationModeEXT,
+        extra_primitive_overestimation_size: f32,
+    ) -> Self {
+        self.inner.conservative_rasterization = match mode {
+            vk::ConservativeRasterizationModeEXT::DISABLED => None,
+            _ => Some(PipelineRasterizationConservativeStateCreateInfo(
+                vk::PipelineRasterizationConservativeStateCreateInfoEXT {
+                    conservative_rasterization_mode: mode,
+                    extra_primitive_overestimation_size,
+                    ..Default::default()
+                },
+            )),
+        };
+        self
+    }
+
+    /// Bind this pipeline as a set of shader objects instead of a compiled pipeline. The shaders are compiled separately, and all state
+    /// in this create info is set on the command buffer when the pipeline is bound, so changing state or attachment formats never
+    /// requires compiling a new pipeline. See the [`shader_object`](crate::pipeline::shader_object) module for more information.
+    ///
+    /// This is only used if [`ExtensionID::ShaderObject`](crate::core::device::ExtensionID::ShaderObject) is enabled. Otherwise,
+    /// a regular pipeline is created, so the same pipeline works on devices without shader object support.
+    pub fn shader_objects(mut self, enable: bool) -> Self {
+        self.inner.shader_objects = enable;
+        self
+    }
+
     /// Add a blend attachment, but with no blending enabled.
     pub fn blend_attachment_none(mut self) -> Self {
         self.inner
@@ -353,6 +553,58 @@ impl PipelineBuilder {
         self
     }
 
+    /// Use an immutable sampler for the sampler or combined image sampler binding with this name in the shader.
+    /// Immutable samplers are baked into the descriptor set layout, so they do not need to be bound before drawing.
+    /// Samplers with a Y′CbCr conversion can only be used as immutable samplers, see [`Sampler::with_ycbcr_conversion()`].
+    /// The pipeline keeps a reference to the sampler, so it stays alive for as long as the pipeline is registered.
+    ///
+    /// This requires the `shader-reflection` feature to look up the binding. Registering the pipeline will fail if
+    /// no sampler binding with this name exists.
+    pub fn immutable_sampler(mut self, name: impl Into<String>, sampler: Arc<Sampler>) -> Self {
+        self.inner.immutable_samplers.push(NamedImmutableSampler {
+            name: name.into(),
+            sampler,
+        });
+        self
+    }
+
+    /// Derive the vertex attributes of this pipeline from the input variables of the vertex shader, instead of adding them with
+    /// [`PipelineBuilder::vertex_attribute()`]. Only the binding index, input rate and optionally the stride of each vertex binding
+    /// need to be given. Each binding contains the inputs from its `first_location` up to the `first_location` of the next binding,
+    /// and its attributes are tightly packed in the order of their locations.
+    ///
+    /// This requires the `shader-reflection` feature. The attributes are filled in when the pipeline is registered, which fails
+    /// if a vertex input is not part of any binding, or has a type that cannot be used as a vertex attribute.
+    /// # Example
+    /// ```
+    /// # use phobos::prelude::*;
+    /// # use phobos::pipeline::create_info::ReflectedVertexBinding;
+    /// // Per vertex data in binding 0, and per instance data starting at location 3 in binding 1.
+    /// let pci = PipelineBuilder::new("instanced")
+    ///     .vertex_inputs_from_reflection(&[
+    ///         ReflectedVertexBinding {
+    ///             binding: 0,
+    ///             rate: vk::VertexInputRate::VERTEX,
+    ///             stride: None,
+    ///             first_location: 0,
+    ///         },
+    ///         ReflectedVertexBinding {
+    ///             binding: 1,
+    ///             rate: vk::VertexInputRate::INSTANCE,
+    ///             stride: None,
+    ///             first_location: 3,
+    ///         },
+    ///     ])
+    ///     // ... other pipeline options
+    ///     .build();
+    /// ```
+    pub fn vertex_inputs_from_reflection(mut self, bindings: &[ReflectedVertexBinding]) -> Self {
+        self.inner
+            .reflected_vertex_bindings
+            .extend_from_slice(bindings);
+        self
+    }
+
     /// Build the pipeline create info structure.
     pub fn build(self) -> PipelineCreateInfo {
         self.inner
@@ -1,25 +1,32 @@
 //! The pipeline cache stores pipelines and deletes them if appropriate.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ffi::CString;
-use std::sync::{Arc, RwLock};
+use std::path::Path;
+use std::sync::{mpsc, Arc, Condvar, Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant};
 
-use anyhow::Result;
+use anyhow::{ensure, Result};
 use ash::vk;
 
 use crate::{
     Allocator, ComputePipelineCreateInfo, DefaultAllocator, Device, Error, PipelineCreateInfo,
 };
 use crate::core::device::ExtensionID;
-use crate::pipeline::{ComputePipeline, Pipeline, PipelineType, RayTracingPipeline};
+use crate::pipeline::{ComputePipeline, Pipeline, PipelineLibrary, PipelineType, RayTracingPipeline, ShaderObject};
 use crate::pipeline::create_info::PipelineRenderingInfo;
+use crate::pipeline::library::{PipelineLibraryKey, PipelineLibraryPart};
 use crate::pipeline::pipeline_layout::PipelineLayout;
 use crate::pipeline::raytracing::{RayTracingPipelineCreateInfo, ShaderBindingTable, ShaderGroup};
 use crate::pipeline::set_layout::DescriptorSetLayout;
-use crate::pipeline::shader::Shader;
+use crate::pipeline::shader::{Shader, ShaderCreateInfo, SpecializationData};
+use crate::pipeline::shader_object::{ShaderObjectBinding, ShaderObjectKey};
 use crate::util::cache::{Cache, Resource, ResourceKey};
+use crate::util::worker_pool::WorkerPool;
 
-use super::shader_reflection::{build_pipeline_layout, reflect_shaders, ReflectionInfo};
+use super::shader_reflection::{
+    apply_immutable_samplers, apply_reflected_vertex_inputs, build_pipeline_layout, reflect_shaders, ReflectionInfo,
+};
 
 #[derive(Debug)]
 struct PipelineEntry<P>
@@ -27,31 +34,260 @@ where
     P: std::fmt::Debug, {
     pub info: P,
     #[cfg(feature = "shader-reflection")]
-    #[allow(dead_code)]
     pub reflection: ReflectionInfo,
 }
 
-#[derive(Debug)]
+/// Owns the `VkPipelineCache` that all pipelines in a [`PipelineCache`] are created with.
+#[derive(Derivative)]
+#[derivative(Debug)]
+struct VulkanPipelineCache {
+    #[derivative(Debug = "ignore")]
+    device: Device,
+    handle: vk::PipelineCache,
+}
+
+impl VulkanPipelineCache {
+    fn new(device: Device, initial_data: &[u8]) -> Result<Self> {
+        let info = vk::PipelineCacheCreateInfo::builder().initial_data(initial_data);
+        let handle = unsafe { device.create_pipeline_cache(&info, None)? };
+        #[cfg(feature = "log-objects")]
+        trace!("Created new VkPipelineCache {handle:p}");
+        Ok(Self {
+            device,
+            handle,
+        })
+    }
+
+    fn data(&self) -> Result<Vec<u8>> {
+        Ok(unsafe { self.device.get_pipeline_cache_data(self.handle)? })
+    }
+}
+
+impl Drop for VulkanPipelineCache {
+    fn drop(&mut self) {
+        #[cfg(feature = "log-objects")]
+        trace!("Destroying VkPipelineCache {:p}", self.handle);
+        unsafe {
+            self.device.destroy_pipeline_cache(self.handle, None);
+        }
+    }
+}
+
+/// Check whether serialized pipeline cache data was created by the same driver and device as `device`, by validating
+/// the `VkPipelineCacheHeaderVersionOne` header at the start of the data.
+fn is_compatible_cache_data(device: &Device, data: &[u8]) -> bool {
+    const HEADER_SIZE: usize = 16 + 4 * std::mem::size_of::<u32>();
+    if data.len() < HEADER_SIZE {
+        return false;
+    }
+    let read_u32 = |offset: usize| u32::from_ne_bytes(data[offset..offset + 4].try_into().unwrap());
+    let properties = device.properties();
+    read_u32(0) as usize >= HEADER_SIZE
+        && read_u32(4) == vk::PipelineCacheHeaderVersion::ONE.as_raw() as u32
+        && read_u32(8) == properties.vendor_id
+        && read_u32(12) == properties.device_id
+        && data[16..HEADER_SIZE] == properties.pipeline_cache_uuid
+}
+
+#[derive(Derivative)]
+#[derivative(Debug)]
 struct PipelineCacheInner<A: Allocator> {
     allocator: A,
+    vk_cache: VulkanPipelineCache,
     shaders: Cache<Shader>,
     set_layouts: Cache<DescriptorSetLayout>,
     pipeline_layouts: Cache<PipelineLayout>,
     pipelines: Cache<Pipeline>,
+    pipeline_libraries: Cache<PipelineLibrary>,
+    shader_objects: Cache<ShaderObject>,
     compute_pipelines: Cache<ComputePipeline>,
     raytracing_pipelines: Cache<RayTracingPipeline<A>>,
     pipeline_infos: HashMap<String, PipelineEntry<PipelineCreateInfo>>,
     compute_pipeline_infos: HashMap<String, PipelineEntry<ComputePipelineCreateInfo>>,
     raytracing_pipeline_infos: HashMap<String, PipelineEntry<RayTracingPipelineCreateInfo>>,
+    // Graphics pipelines that failed to be created, and the fallback pipeline that is substituted for them.
+    failed_pipelines: HashMap<PipelineCreateInfo, PipelineCreateInfo>,
+    // Graphics pipelines that are being compiled on a worker thread.
+    compiling: HashSet<PipelineCreateInfo>,
+    // Graphics pipelines that failed to compile on a worker thread. These are compiled on the calling thread when they are bound,
+    // so the error is reported there.
+    failed_compilations: HashSet<PipelineCreateInfo>,
+    creation_feedback: CreationFeedbackLog,
+    #[derivative(Debug = "ignore")]
+    compiled_sender: mpsc::Sender<CompiledPipeline>,
+    #[derivative(Debug = "ignore")]
+    compiled: mpsc::Receiver<CompiledPipeline>,
+}
+
+/// Describes a graphics pipeline to compile ahead of time with [`PipelineCache::precompile_pipelines()`]. Graphics pipelines are
+/// compiled for the attachments of the render pass they are used in, so these must match the pass the pipeline will be bound in.
+/// # Example
+/// ```
+/// # use phobos::prelude::*;
+/// let info = PipelinePrecompileInfo {
+///     name: String::from("forward"),
+///     color_formats: vec![vk::Format::R8G8B8A8_SRGB],
+///     depth_format: Some(vk::Format::D32_SFLOAT),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PipelinePrecompileInfo {
+    /// Name of the pipeline, as registered with [`PipelineCache::create_named_pipeline()`].
+    pub name: String,
+    /// Formats of the color attachments.
+    pub color_formats: Vec<vk::Format>,
+    /// Format of the depth attachment, if there is one.
+    pub depth_format: Option<vk::Format>,
+    /// Format of the stencil attachment, if there is one.
+    pub stencil_format: Option<vk::Format>,
+    /// View mask for multiview rendering.
+    pub view_mask: u32,
+    /// Whether the render pass has a fragment shading rate attachment.
+    pub shading_rate_attachment: bool,
+}
+
+impl PipelinePrecompileInfo {
+    fn rendering_info(&self) -> PipelineRenderingInfo {
+        PipelineRenderingInfo {
+            view_mask: self.view_mask,
+            color_formats: self.color_formats.clone(),
+            depth_format: self.depth_format,
+            stencil_format: self.stencil_format,
+            shading_rate_attachment: self.shading_rate_attachment,
+        }
+    }
+}
+
+/// Creation feedback of a single pipeline, as reported by the driver through `VK_EXT_pipeline_creation_feedback`.
+/// This can be used to find pipelines that are slow to compile, and to check whether the driver's pipeline cache was used.
+/// See [`PipelineCache::creation_feedback()`].
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct PipelineCreationFeedback {
+    /// Time spent creating the pipeline. If the driver did not report feedback, this is measured around the creation call instead.
+    /// For graphics pipelines that are linked from pipeline libraries, this only includes linking the libraries.
+    pub duration: Duration,
+    /// Whether the pipeline was found in the `VkPipelineCache`, for example because it was loaded with
+    /// [`PipelineCache::load_from_file()`], so it did not have to be compiled.
+    pub cache_hit: bool,
+    /// Whether the driver reported creation feedback for this pipeline. If this is false, `cache_hit` is always false.
+    pub valid: bool,
+}
+
+impl PipelineCreationFeedback {
+    fn new(feedback: vk::PipelineCreationFeedback, elapsed: Duration) -> Self {
+        let valid = feedback.flags.contains(vk::PipelineCreationFeedbackFlags::VALID);
+        Self {
+            duration: if valid { Duration::from_nanos(feedback.duration) } else { elapsed },
+            cache_hit: valid
+                && feedback
+                    .flags
+                    .contains(vk::PipelineCreationFeedbackFlags::APPLICATION_PIPELINE_CACHE_HIT),
+            valid,
+        }
+    }
+}
+
+/// Statistics over all pipelines created by a [`PipelineCache`] so far. See [`PipelineCache::creation_statistics()`].
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct PipelineCreationStatistics {
+    /// Number of graphics, compute and ray tracing pipelines that were created.
+    pub pipelines_created: u32,
+    /// Number of pipelines that were found in the `VkPipelineCache`.
+    pub cache_hits: u32,
+    /// Total time spent creating pipelines.
+    pub total_duration: Duration,
+}
+
+/// Creation feedback of all pipelines created by a [`PipelineCache`].
+#[derive(Debug, Default)]
+struct CreationFeedbackLog {
+    // Feedback of the most recent creation of each named pipeline.
+    pipelines: HashMap<String, PipelineCreationFeedback>,
+    statistics: PipelineCreationStatistics,
+}
+
+impl CreationFeedbackLog {
+    fn record(&mut self, name: &str, feedback: PipelineCreationFeedback) {
+        self.pipelines.insert(name.to_owned(), feedback);
+        self.statistics.pipelines_created += 1;
+        self.statistics.cache_hits += feedback.cache_hit as u32;
+        self.statistics.total_duration += feedback.duration;
+    }
+}
+
+/// Create a pipeline while capturing its creation feedback. `create` is called with a `VkPipelineCreationFeedbackCreateInfo`
+/// that must be chained into the pipeline create info, and that already points to `p_next`.
+fn with_creation_feedback(
+    p_next: *const std::ffi::c_void,
+    create: impl FnOnce(*const std::ffi::c_void) -> Result<vk::Pipeline>,
+) -> Result<(vk::Pipeline, PipelineCreationFeedback)> {
+    let mut feedback = vk::PipelineCreationFeedback::default();
+    let feedback_info = vk::PipelineCreationFeedbackCreateInfo {
+        p_next,
+        p_pipeline_creation_feedback: &mut feedback,
+        ..Default::default()
+    };
+    let start = Instant::now();
+    let handle = create((&feedback_info as *const vk::PipelineCreationFeedbackCreateInfo).cast())?;
+    Ok((handle, PipelineCreationFeedback::new(feedback, start.elapsed())))
+}
+
+/// A graphics pipeline to compile on a worker thread.
+struct CompileJob {
+    device: Device,
+    info: PipelineCreateInfo,
+    vk_cache: vk::PipelineCache,
+    layout: vk::PipelineLayout,
+    set_layouts: Vec<vk::DescriptorSetLayout>,
+    sender: mpsc::Sender<CompiledPipeline>,
+}
+
+// SAFETY: The Vulkan structures inside the create info are rebuilt on the worker thread before they are used, and the job is
+// only accessed by that thread.
+unsafe impl Send for CompileJob {}
+
+/// A graphics pipeline that finished compiling on a worker thread.
+struct CompiledPipeline {
+    info: PipelineCreateInfo,
+    result: Result<Pipeline>,
+}
+
+// SAFETY: The receiving thread does not use the Vulkan structures inside the create info, it is only used as a cache key.
+unsafe impl Send for CompiledPipeline {}
+
+impl CompileJob {
+    fn run(self) {
+        let mut info = self.info;
+        // The create info was moved into this job, so its Vulkan structures must be rebuilt to point to the new location.
+        info.build_inner();
+        let result = Pipeline::create_standalone(self.device, &info, self.vk_cache, self.layout, self.set_layouts);
+        // Sending only fails if the pipeline cache was dropped, in which case the pipeline is not needed anymore.
+        let _ = self.sender.send(CompiledPipeline {
+            info,
+            result,
+        });
+    }
 }
 
 /// The main pipeline cache struct. This stores all named pipelines and shaders.
 /// To create a pipeline you should obtain a pipeline create info, and then register it using
 /// [`PipelineCache::create_named_pipeline`].
 ///
-/// This struct is `Clone`, `Send` and `Sync`.
+/// This struct is `Clone`, `Send` and `Sync`. Pipelines can be registered from a loading thread while other threads are binding pipelines.
+/// A pipeline only becomes visible once it is completely registered, use [`PipelineCache::contains_pipeline`] or
+/// [`PipelineCache::wait_for_pipeline`] to check whether it is available yet.
 ///
 /// This should generally be accessed through a global [`ResourcePool`](crate::pool::ResourcePool)
+///
+/// In debug builds, a graphics pipeline that fails to be created is replaced by a fallback pipeline with the same layout, which
+/// renders magenta. Built-in shaders are substituted for the stages that failed: the fragment shader always renders magenta, a
+/// failed vertex shader draws nothing, and failed geometry or tessellation stages are left out. Task and mesh shaders have no
+/// substitute. The error is logged once, and creation is not retried until the pipeline is registered again (for example after a shader hot reload).
+///
+/// If graphics pipeline libraries are enabled with [`AppBuilder::graphics_pipeline_library()`](crate::AppBuilder::graphics_pipeline_library)
+/// and supported by the device, graphics pipelines are linked from separately cached parts when they are first bound.
+/// See the [`library`](crate::pipeline::library) module for more information.
 /// # Example usage
 /// ```
 /// use phobos::prelude::*;
@@ -63,7 +299,12 @@ struct PipelineCacheInner<A: Allocator> {
 /// ```
 #[derive(Debug, Clone)]
 pub struct PipelineCache<A: Allocator = DefaultAllocator> {
+    // Declared first so it is dropped first. This waits for pipelines that are still compiling, since they use the
+    // `VkPipelineCache` and pipeline layouts owned by the inner state.
+    compile_workers: Arc<OnceLock<WorkerPool>>,
     inner: Arc<RwLock<PipelineCacheInner<A>>>,
+    // Signaled every time a new pipeline is published to the cache.
+    published: Arc<(Mutex<()>, Condvar)>,
 }
 
 // SAFETY: Inner state is wrapped in an Arc<RwLock<T>>, and all pointers inside point to
@@ -93,6 +334,311 @@ fn verify_valid_dynamic_states(device: &Device, pci: &PipelineCreateInfo) {
         vk::DynamicState::POLYGON_MODE_EXT,
         ExtensionID::ExtendedDynamicState3
     );
+    require_extension!(
+        pci,
+        device,
+        vk::DynamicState::COLOR_BLEND_ENABLE_EXT,
+        ExtensionID::ExtendedDynamicState3
+    );
+    require_extension!(
+        pci,
+        device,
+        vk::DynamicState::FRAGMENT_SHADING_RATE_KHR,
+        ExtensionID::FragmentShadingRate
+    );
+}
+
+/// Create the shader stages for a set of graphics shaders, and call `f` with them. `module` is called to obtain the shader module
+/// of each shader. The stages point to data that only lives for the duration of this call.
+fn with_shader_stages<R>(
+    shaders: &[&ShaderCreateInfo],
+    mut module: impl FnMut(&ShaderCreateInfo) -> Result<vk::ShaderModule>,
+    f: impl FnOnce(&[vk::PipelineShaderStageCreateInfo]) -> Result<R>,
+) -> Result<R> {
+    let entry = CString::new("main")?;
+    let specialization = shaders
+        .iter()
+        .map(|shader| shader.specialization_data())
+        .collect::<Vec<_>>();
+    let specialization_info = specialization.iter().map(SpecializationData::info).collect::<Vec<_>>();
+    let stages = shaders
+        .iter()
+        .zip(&specialization_info)
+        .map(|(shader, specialization)| -> Result<vk::PipelineShaderStageCreateInfo> {
+            let mut stage = vk::PipelineShaderStageCreateInfo::builder()
+                .name(&entry)
+                .stage(shader.stage())
+                .module(module(shader)?);
+            if let Some(specialization) = specialization {
+                stage = stage.specialization_info(specialization);
+            }
+            Ok(stage.build())
+        })
+        .collect::<Result<Vec<_>>>()?;
+    f(&stages)
+}
+
+/// Get the shader module of a shader from the shader cache, creating it if it does not exist yet.
+fn cached_module(shaders: &mut Cache<Shader>, shader: &ShaderCreateInfo) -> Result<vk::ShaderModule> {
+    Ok(unsafe { shaders.get_or_create(&shader.module_key(), ())?.handle() })
+}
+
+/// Create a single graphics pipeline or pipeline library, and capture its creation feedback.
+fn create_graphics_pipeline(
+    device: &Device,
+    vk_cache: vk::PipelineCache,
+    pci: &vk::GraphicsPipelineCreateInfo,
+) -> Result<(vk::Pipeline, PipelineCreationFeedback)> {
+    with_creation_feedback(pci.p_next, |p_next| unsafe {
+        let pci = vk::GraphicsPipelineCreateInfo {
+            p_next,
+            ..*pci
+        };
+        Ok(device
+            .create_graphics_pipelines(vk_cache, std::slice::from_ref(&pci), None)
+            .map_err(|(_, e)| Error::VkError(e))?
+            .first()
+            .cloned()
+            .unwrap())
+    })
+}
+
+/// Check whether a graphics pipeline can be created on this device.
+fn validate_graphics_pipeline(device: &Device, info: &PipelineCreateInfo) -> Result<()> {
+    verify_valid_dynamic_states(device, info);
+    if info.is_mesh_pipeline() {
+        device.require_extension(ExtensionID::MeshShader)?;
+    }
+    if info.fragment_shading_rate.is_some() || info.rendering_info.shading_rate_attachment {
+        device.require_extension(ExtensionID::FragmentShadingRate)?;
+    }
+    if info.has_geometry_shader() && device.features().geometry_shader != vk::TRUE {
+        return Err(Error::FeatureNotSupported("geometryShader").into());
+    }
+    if info.rasterizer.0.line_width != 1.0
+        && !info.dynamic_states.contains(&vk::DynamicState::LINE_WIDTH)
+        && device.features().wide_lines != vk::TRUE
+    {
+        return Err(Error::FeatureNotSupported("wideLines").into());
+    }
+    let input_assembly = &info.input_assembly.0;
+    let list_topology = matches!(
+        input_assembly.topology,
+        vk::PrimitiveTopology::POINT_LIST
+            | vk::PrimitiveTopology::LINE_LIST
+            | vk::PrimitiveTopology::TRIANGLE_LIST
+            | vk::PrimitiveTopology::LINE_LIST_WITH_ADJACENCY
+            | vk::PrimitiveTopology::TRIANGLE_LIST_WITH_ADJACENCY
+            | vk::PrimitiveTopology::PATCH_LIST
+    );
+    ensure!(
+        input_assembly.primitive_restart_enable == vk::FALSE || !list_topology,
+        "Pipeline {} enables primitive restart with list topology {:?}, this is only allowed for strip and fan topologies",
+        info.name,
+        input_assembly.topology
+    );
+    if info.rasterizer.0.polygon_mode != vk::PolygonMode::FILL && device.features().fill_mode_non_solid != vk::TRUE {
+        return Err(Error::FeatureNotSupported("fillModeNonSolid").into());
+    }
+    ensure!(
+        !info.is_mesh_pipeline() || !(info.has_geometry_shader() || info.is_tessellation_pipeline()),
+        "Mesh shading pipeline {} cannot have geometry or tessellation shaders",
+        info.name
+    );
+    if info.rasterizer.0.depth_clamp_enable == vk::TRUE && device.features().depth_clamp != vk::TRUE {
+        return Err(Error::FeatureNotSupported("depthClamp").into());
+    }
+    if info.depth_clip_negative_one_to_one {
+        device.require_extension(ExtensionID::DepthClipControl)?;
+    }
+    if let Some(conservative) = &info.conservative_rasterization {
+        let properties = device.conservative_rasterization_properties()?;
+        ensure!(
+            conservative.0.extra_primitive_overestimation_size >= 0.0
+                && conservative.0.extra_primitive_overestimation_size
+                    <= properties.max_extra_primitive_overestimation_size,
+            "Pipeline {} has extra primitive overestimation size {}, but the maximum is {}",
+            info.name,
+            conservative.0.extra_primitive_overestimation_size,
+            properties.max_extra_primitive_overestimation_size
+        );
+    }
+    if info.rendering_info.view_mask != 0 {
+        let features = device.features_1_1();
+        if features.multiview != vk::TRUE {
+            return Err(Error::FeatureNotSupported("multiview").into());
+        }
+        if info.has_geometry_shader() && features.multiview_geometry_shader != vk::TRUE {
+            return Err(Error::FeatureNotSupported("multiviewGeometryShader").into());
+        }
+        if info.is_tessellation_pipeline() && features.multiview_tessellation_shader != vk::TRUE {
+            return Err(Error::FeatureNotSupported("multiviewTessellationShader").into());
+        }
+    }
+    if info.is_tessellation_pipeline() {
+        if device.features().tessellation_shader != vk::TRUE {
+            return Err(Error::FeatureNotSupported("tessellationShader").into());
+        }
+        let has_stage = |stage| info.shaders.iter().any(|shader| shader.stage() == stage);
+        ensure!(
+            has_stage(vk::ShaderStageFlags::TESSELLATION_CONTROL) && has_stage(vk::ShaderStageFlags::TESSELLATION_EVALUATION),
+            "Pipeline {} must have both a tessellation control and a tessellation evaluation shader",
+            info.name
+        );
+        ensure!(
+            info.tesselation_info.is_some(),
+            "Pipeline {} has tessellation shaders, but no tessellation state. Set it with PipelineBuilder::tessellation()",
+            info.name
+        );
+    }
+    Ok(())
+}
+
+impl ResourceKey for PipelineLibraryKey {
+    fn persistent(&self) -> bool {
+        false
+    }
+}
+
+impl Resource for PipelineLibrary {
+    type Key = PipelineLibraryKey;
+    type ExtraParams<'a> = (vk::PipelineCache, &'a mut Cache<Shader>, vk::PipelineLayout);
+    const MAX_TIME_TO_LIVE: u32 = 8;
+
+    fn create(device: Device, key: &Self::Key, params: Self::ExtraParams<'_>) -> Result<Self> {
+        let (vk_cache, shaders, layout) = params;
+        // The key owns a copy of the create info, so the Vulkan structures inside it must be rebuilt to point into this copy.
+        let mut info = key.info.clone();
+        info.build_inner();
+        let mut pci = info.to_vk(layout);
+        pci.flags |= vk::PipelineCreateFlags::LIBRARY_KHR;
+        let mut library_info = vk::GraphicsPipelineLibraryCreateInfoEXT::builder()
+            .flags(key.part.flags())
+            .build();
+        library_info.p_next = pci.p_next as *mut std::ffi::c_void;
+        pci.p_next = (&library_info as *const vk::GraphicsPipelineLibraryCreateInfoEXT).cast();
+
+        let shader_infos = key.shaders().collect::<Vec<_>>();
+        // Feedback is only reported for the linked pipeline.
+        let (handle, _) = with_shader_stages(&shader_infos, |shader| cached_module(shaders, shader), |stages| {
+            pci.stage_count = stages.len() as u32;
+            pci.p_stages = stages.as_ptr();
+            create_graphics_pipeline(&device, vk_cache, &pci)
+        })?;
+
+        #[cfg(feature = "log-objects")]
+        trace!("Created new VkPipeline (graphics library, {:?}) {handle:p}", key.part);
+
+        Ok(Self {
+            device,
+            handle,
+        })
+    }
+}
+
+impl Drop for PipelineLibrary {
+    fn drop(&mut self) {
+        #[cfg(feature = "log-objects")]
+        trace!("Destroying VkPipeline (graphics library) {:p}", self.handle);
+        unsafe {
+            self.device.destroy_pipeline(self.handle, None);
+        }
+    }
+}
+
+/// Get the shader objects of a graphics pipeline from the shader object cache, creating the ones that do not exist yet.
+fn cached_shader_objects(
+    device: &Device,
+    shader_objects: &mut Cache<ShaderObject>,
+    set_layouts: &mut Cache<DescriptorSetLayout>,
+    info: &PipelineCreateInfo,
+) -> Result<Vec<(vk::ShaderStageFlags, vk::ShaderEXT)>> {
+    let keys = ShaderObjectKey::for_pipeline(info);
+    // Only validate the pipeline when something needs to be compiled, since this happens every time the pipeline is bound.
+    if keys.iter().any(|key| shader_objects.get(key).is_none()) {
+        validate_graphics_pipeline(device, info)?;
+    }
+    keys.iter()
+        .map(|key| {
+            let shader = shader_objects.get_or_create(key, &mut *set_layouts)?;
+            Ok((key.shader.stage(), shader.handle))
+        })
+        .collect()
+}
+
+impl ResourceKey for ShaderObjectKey {
+    fn persistent(&self) -> bool {
+        false
+    }
+}
+
+impl Resource for ShaderObject {
+    type Key = ShaderObjectKey;
+    type ExtraParams<'a> = &'a mut Cache<DescriptorSetLayout>;
+    const MAX_TIME_TO_LIVE: u32 = 8;
+
+    fn create(device: Device, key: &Self::Key, set_layout_cache: Self::ExtraParams<'_>) -> Result<Self> {
+        let funcs = device
+            .shader_object()
+            .ok_or(Error::ExtensionNotSupported(ExtensionID::ShaderObject))?;
+        let set_layouts = key
+            .layout
+            .set_layouts
+            .iter()
+            .map(|info| Ok(unsafe { set_layout_cache.get_or_create(info, ())?.handle() }))
+            .collect::<Result<Vec<_>>>()?;
+        let push_constants = key
+            .layout
+            .push_constants
+            .iter()
+            .map(|pc| pc.to_vk())
+            .collect::<Vec<_>>();
+        let code = key.shader.code();
+        // SAFETY: Reinterpreting a slice of u32 as bytes is always valid, and the size is exactly the size of the slice.
+        let code = unsafe { std::slice::from_raw_parts(code.as_ptr() as *const u8, std::mem::size_of_val(code)) };
+        let entry = CString::new("main")?;
+        let specialization = key.shader.specialization_data();
+        let specialization_info = specialization.info();
+        let mut info = vk::ShaderCreateInfoEXT::builder()
+            .flags(key.flags)
+            .stage(key.shader.stage())
+            .next_stage(key.next_stage)
+            .code_type(vk::ShaderCodeTypeEXT::SPIRV)
+            .code(code)
+            .name(&entry)
+            .set_layouts(&set_layouts)
+            .push_constant_ranges(&push_constants);
+        if let Some(specialization_info) = &specialization_info {
+            info = info.specialization_info(specialization_info);
+        }
+        // SAFETY: Vulkan API call. All pointers in the create info point to data that outlives this call.
+        let handle = unsafe { funcs.create_shaders(std::slice::from_ref(&info), None)? }
+            .first()
+            .cloned()
+            .unwrap();
+
+        #[cfg(feature = "log-objects")]
+        trace!("Created new VkShaderEXT {handle:p}");
+
+        Ok(Self {
+            device,
+            handle,
+        })
+    }
+}
+
+impl Drop for ShaderObject {
+    fn drop(&mut self) {
+        #[cfg(feature = "log-objects")]
+        trace!("Destroying VkShaderEXT {:p}", self.handle);
+        // SAFETY: Shader objects can only be created if the extension is enabled, so the function pointers exist.
+        unsafe {
+            self.device
+                .shader_object()
+                .unwrap()
+                .destroy_shader(self.handle, None);
+        }
+    }
 }
 
 impl ResourceKey for PipelineCreateInfo {
@@ -105,46 +651,44 @@ impl ResourceKey for PipelineCreateInfo {
 impl Resource for Pipeline {
     type Key = PipelineCreateInfo;
     type ExtraParams<'a> = (
+        vk::PipelineCache,
         &'a mut Cache<Shader>,
+        &'a mut Cache<PipelineLibrary>,
         &'a mut Cache<PipelineLayout>,
         &'a mut Cache<DescriptorSetLayout>,
     );
     const MAX_TIME_TO_LIVE: u32 = 8;
 
     fn create(device: Device, info: &Self::Key, params: Self::ExtraParams<'_>) -> Result<Self> {
-        let (shaders, pipeline_layouts, set_layouts) = params;
+        let (vk_cache, shaders, pipeline_libraries, pipeline_layouts, set_layouts) = params;
         let layout = pipeline_layouts.get_or_create(&info.layout, set_layouts)?;
         let mut pci = info.to_vk(unsafe { layout.handle() });
 
-        verify_valid_dynamic_states(&device, info);
+        validate_graphics_pipeline(&device, info)?;
 
-        // Set shader create info
-        let entry = CString::new("main")?;
-        let shader_info: Vec<_> = info
-            .shaders
-            .iter()
-            .map(|shader| -> vk::PipelineShaderStageCreateInfo {
-                vk::PipelineShaderStageCreateInfo::builder()
-                    .name(&entry)
-                    .stage(shader.stage())
-                    .module(unsafe { shaders.get_or_create(shader, ()).unwrap().handle() })
-                    .build()
-            })
-            .collect();
-        pci.stage_count = shader_info.len() as u32;
-        pci.p_stages = shader_info.as_ptr();
-
-        let handle = unsafe {
-            device
-                .create_graphics_pipelines(
-                    vk::PipelineCache::null(),
-                    std::slice::from_ref(&pci),
-                    None,
-                )
-                .map_err(|(_, e)| Error::VkError(e))?
-                .first()
-                .cloned()
-                .unwrap()
+        let (handle, creation_feedback) = if device.is_extension_enabled(ExtensionID::GraphicsPipelineLibrary) {
+            let libraries = PipelineLibraryPart::required(info)
+                .iter()
+                .map(|part| -> Result<vk::Pipeline> {
+                    let key = PipelineLibraryKey::new(*part, info);
+                    Ok(pipeline_libraries
+                        .get_or_create(&key, (vk_cache, &mut *shaders, pci.layout))?
+                        .handle)
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let mut library_info = vk::PipelineLibraryCreateInfoKHR::builder().libraries(&libraries);
+            let link_info = vk::GraphicsPipelineCreateInfo::builder()
+                .flags(pci.flags)
+                .layout(pci.layout)
+                .push_next(&mut library_info);
+            create_graphics_pipeline(&device, vk_cache, &link_info)?
+        } else {
+            let shader_infos = info.shaders.iter().collect::<Vec<_>>();
+            with_shader_stages(&shader_infos, |shader| cached_module(shaders, shader), |stages| {
+                pci.stage_count = stages.len() as u32;
+                pci.p_stages = stages.as_ptr();
+                create_graphics_pipeline(&device, vk_cache, &pci)
+            })?
         };
 
         #[cfg(feature = "log-objects")]
@@ -156,11 +700,54 @@ impl Resource for Pipeline {
                 handle,
                 layout: layout.handle(),
                 set_layouts: layout.set_layouts().to_vec(),
+                push_constants: info.layout.push_constants.clone(),
+                creation_feedback,
             })
         }
     }
 }
 
+impl Pipeline {
+    /// Create a graphics pipeline without using any of the pipeline cache's resource caches, so this can be called from a
+    /// worker thread. The pipeline layout must stay alive until this returns. Shader modules are created for this pipeline only,
+    /// and pipeline libraries are not used.
+    fn create_standalone(
+        device: Device,
+        info: &PipelineCreateInfo,
+        vk_cache: vk::PipelineCache,
+        layout: vk::PipelineLayout,
+        set_layouts: Vec<vk::DescriptorSetLayout>,
+    ) -> Result<Self> {
+        validate_graphics_pipeline(&device, info)?;
+        let mut pci = info.to_vk(layout);
+        let mut modules = Vec::new();
+        let shader_infos = info.shaders.iter().collect::<Vec<_>>();
+        let create_module = |shader: &ShaderCreateInfo| -> Result<vk::ShaderModule> {
+            let module = Shader::create(device.clone(), &shader.module_key(), ())?;
+            let handle = unsafe { module.handle() };
+            modules.push(module);
+            Ok(handle)
+        };
+        let (handle, creation_feedback) = with_shader_stages(&shader_infos, create_module, |stages| {
+            pci.stage_count = stages.len() as u32;
+            pci.p_stages = stages.as_ptr();
+            create_graphics_pipeline(&device, vk_cache, &pci)
+        })?;
+
+        #[cfg(feature = "log-objects")]
+        trace!("Created new VkPipeline (graphics) {handle:p} on a worker thread");
+
+        Ok(Self {
+            device,
+            handle,
+            layout,
+            set_layouts,
+            push_constants: info.layout.push_constants.clone(),
+            creation_feedback,
+        })
+    }
+}
+
 impl Drop for Pipeline {
     fn drop(&mut self) {
         #[cfg(feature = "log-objects")]
@@ -181,6 +768,7 @@ impl ResourceKey for ComputePipelineCreateInfo {
 impl Resource for ComputePipeline {
     type Key = ComputePipelineCreateInfo;
     type ExtraParams<'a> = (
+        vk::PipelineCache,
         &'a mut Cache<Shader>,
         &'a mut Cache<PipelineLayout>,
         &'a mut Cache<DescriptorSetLayout>,
@@ -190,35 +778,39 @@ impl Resource for ComputePipeline {
     fn create(device: Device, info: &Self::Key, params: Self::ExtraParams<'_>) -> Result<Self>
     where
         Self: Sized, {
-        let (shaders, pipeline_layouts, set_layouts) = params;
+        let (vk_cache, shaders, pipeline_layouts, set_layouts) = params;
         let layout = pipeline_layouts.get_or_create(&info.layout, set_layouts)?;
         let mut pci = info.to_vk(unsafe { layout.handle() });
 
         // Set shader create info
         let entry = CString::new("main")?;
-        let shader = match &info.shader {
-            None => Err(Error::Uncategorized("Compute pipeline lacks shader")),
-            Some(shader) => Ok(vk::PipelineShaderStageCreateInfo::builder()
-                .name(&entry)
-                .stage(vk::ShaderStageFlags::COMPUTE)
-                .module(unsafe { shaders.get_or_create(shader, ()).unwrap().handle() })
-                .build()),
-        }?;
+        let Some(shader) = &info.shader else { return Err(Error::Uncategorized("Compute pipeline lacks shader").into()); };
+        let specialization = shader.specialization_data();
+        let specialization_info = specialization.info();
+        let mut stage = vk::PipelineShaderStageCreateInfo::builder()
+            .name(&entry)
+            .stage(vk::ShaderStageFlags::COMPUTE)
+            .module(unsafe { shaders.get_or_create(&shader.module_key(), ())?.handle() });
+        if let Some(specialization_info) = &specialization_info {
+            stage = stage.specialization_info(specialization_info);
+        }
+        let shader = stage.build();
 
         pci.stage = shader;
 
-        let handle = unsafe {
-            device
+        let (handle, creation_feedback) = with_creation_feedback(pci.p_next, |p_next| unsafe {
+            pci.p_next = p_next;
+            Ok(device
                 .create_compute_pipelines(
-                    vk::PipelineCache::null(),
+                    vk_cache,
                     std::slice::from_ref(&pci),
                     None,
                 )
                 .map_err(|(_, e)| Error::VkError(e))?
                 .first()
                 .cloned()
-                .unwrap()
-        };
+                .unwrap())
+        })?;
 
         #[cfg(feature = "log-objects")]
         trace!("Created new VkPipeline (compute) {handle:p}");
@@ -229,6 +821,8 @@ impl Resource for ComputePipeline {
                 handle,
                 layout: layout.handle(),
                 set_layouts: layout.set_layouts().to_vec(),
+                push_constants: info.layout.push_constants.clone(),
+                creation_feedback,
             })
         }
     }
@@ -253,6 +847,7 @@ impl ResourceKey for RayTracingPipelineCreateInfo {
 impl<A: Allocator> Resource for RayTracingPipeline<A> {
     type Key = RayTracingPipelineCreateInfo;
     type ExtraParams<'a> = (
+        vk::PipelineCache,
         A,
         &'a mut Cache<Shader>,
         &'a mut Cache<PipelineLayout>,
@@ -264,23 +859,33 @@ impl<A: Allocator> Resource for RayTracingPipeline<A> {
     where
         Self: Sized, {
         device.require_extension(ExtensionID::RayTracingPipeline)?;
-        let (alloc, shaders, pipeline_layouts, set_layouts) = params;
+        let (vk_cache, alloc, shaders, pipeline_layouts, set_layouts) = params;
         let layout = pipeline_layouts.get_or_create(&info.layout, set_layouts)?;
         let mut pci = info.to_vk(unsafe { layout.handle() });
 
         let entry = CString::new("main")?;
         let mut shader_indices = HashMap::new();
+        let specialization = info
+            .shaders
+            .iter()
+            .map(|shader| shader.specialization_data())
+            .collect::<Vec<_>>();
+        let specialization_info = specialization.iter().map(SpecializationData::info).collect::<Vec<_>>();
         let shader_info: Vec<_> = info
             .shaders
             .iter()
+            .zip(&specialization_info)
             .enumerate()
-            .map(|(idx, shader)| -> vk::PipelineShaderStageCreateInfo {
+            .map(|(idx, (shader, specialization))| -> vk::PipelineShaderStageCreateInfo {
                 shader_indices.insert(shader.code_hash(), idx as u32);
-                vk::PipelineShaderStageCreateInfo::builder()
+                let mut stage = vk::PipelineShaderStageCreateInfo::builder()
                     .name(&entry)
                     .stage(shader.stage())
-                    .module(unsafe { shaders.get_or_create(shader, ()).unwrap().handle() })
-                    .build()
+                    .module(unsafe { shaders.get_or_create(&shader.module_key(), ()).unwrap().handle() });
+                if let Some(specialization) = specialization {
+                    stage = stage.specialization_info(specialization);
+                }
+                stage.build()
             })
             .collect();
         pci.stage_count = shader_info.len() as u32;
@@ -321,6 +926,19 @@ impl<A: Allocator> Resource for RayTracingPipeline<A> {
                         vk::SHADER_UNUSED_KHR,
                         vk::RayTracingShaderGroupTypeKHR::TRIANGLES_HIT_GROUP,
                     ),
+                    ShaderGroup::ProceduralHit {
+                        intersection,
+                        closest_hit,
+                        any_hit,
+                    } => (
+                        vk::SHADER_UNUSED_KHR,
+                        closest_hit
+                            .map(|sh| sh.index)
+                            .unwrap_or(vk::SHADER_UNUSED_KHR),
+                        any_hit.map(|sh| sh.index).unwrap_or(vk::SHADER_UNUSED_KHR),
+                        intersection.index,
+                        vk::RayTracingShaderGroupTypeKHR::PROCEDURAL_HIT_GROUP,
+                    ),
                     ShaderGroup::Callable {
                         shader,
                     } => (
@@ -348,17 +966,19 @@ impl<A: Allocator> Resource for RayTracingPipeline<A> {
         pci.p_groups = groups.as_ptr();
 
         let fns = device.raytracing_pipeline().unwrap();
-        let handle = unsafe {
-            fns.create_ray_tracing_pipelines(
-                vk::DeferredOperationKHR::null(),
-                vk::PipelineCache::null(),
-                std::slice::from_ref(&pci),
-                None,
-            )?
-            .first()
-            .cloned()
-            .unwrap()
-        };
+        let (handle, creation_feedback) = with_creation_feedback(pci.p_next, |p_next| unsafe {
+            pci.p_next = p_next;
+            Ok(fns
+                .create_ray_tracing_pipelines(
+                    vk::DeferredOperationKHR::null(),
+                    vk_cache,
+                    std::slice::from_ref(&pci),
+                    None,
+                )?
+                .first()
+                .cloned()
+                .unwrap())
+        })?;
 
         #[cfg(feature = "log-objects")]
         trace!("Created new VkPipeline (raytracing) {handle:p}");
@@ -370,7 +990,9 @@ impl<A: Allocator> Resource for RayTracingPipeline<A> {
             handle,
             layout: unsafe { layout.handle() },
             set_layouts: layout.set_layouts().to_vec(),
+            push_constants: info.layout.push_constants.clone(),
             shader_binding_table: sbt,
+            creation_feedback,
         })
     }
 }
@@ -386,11 +1008,73 @@ impl<A: Allocator> Drop for RayTracingPipeline<A> {
 }
 
 impl<A: Allocator> PipelineCacheInner<A> {
+    /// Move pipelines that finished compiling on a worker thread into the cache.
+    fn receive_compiled_pipelines(&mut self) {
+        while let Ok(compiled) = self.compiled.try_recv() {
+            self.compiling.remove(&compiled.info);
+            match compiled.result {
+                Ok(pipeline) => {
+                    self.creation_feedback
+                        .record(&compiled.info.name, pipeline.creation_feedback);
+                    self.pipelines.insert(compiled.info, pipeline);
+                }
+                Err(_) => {
+                    self.failed_compilations.insert(compiled.info);
+                }
+            }
+        }
+    }
+
+    /// Check whether a graphics pipeline can be bound without compiling it first. If it cannot, start compiling it on a
+    /// worker thread.
+    fn request_pipeline(
+        &mut self,
+        name: &str,
+        rendering_info: PipelineRenderingInfo,
+        workers: &WorkerPool,
+    ) -> Result<bool> {
+        self.receive_compiled_pipelines();
+        // Shader objects do not depend on the render pass, so there is nothing to compile ahead of time for them.
+        if self.uses_shader_objects(name) {
+            return Ok(true);
+        }
+        let entry = self.pipeline_infos.get_mut(name);
+        let Some(entry) = entry else { return Err(anyhow::Error::from(Error::PipelineNotFound(name.to_string()))); };
+        entry.info.rendering_info = rendering_info;
+        entry.info.build_rendering_state();
+        let info = &entry.info;
+        // Failed pipelines are reported as ready, so binding them reports the error or substitutes the fallback pipeline.
+        if self.pipelines.get(info).is_some() || self.failed_compilations.contains(info) {
+            return Ok(true);
+        }
+        if self.compiling.contains(info) {
+            return Ok(false);
+        }
+        for layout in &info.layout.set_layouts {
+            self.set_layouts.get_or_create(layout, ())?;
+        }
+        let layout = self
+            .pipeline_layouts
+            .get_or_create(&info.layout, &mut self.set_layouts)?;
+        let job = CompileJob {
+            device: self.vk_cache.device.clone(),
+            info: info.clone(),
+            vk_cache: self.vk_cache.handle,
+            layout: unsafe { layout.handle() },
+            set_layouts: layout.set_layouts().to_vec(),
+            sender: self.compiled_sender.clone(),
+        };
+        self.compiling.insert(info.clone());
+        workers.spawn(move || job.run());
+        Ok(false)
+    }
+
     pub(crate) fn get_pipeline(
         &mut self,
         name: &str,
         rendering_info: PipelineRenderingInfo,
     ) -> Result<&Pipeline> {
+        self.receive_compiled_pipelines();
         let entry = self.pipeline_infos.get_mut(name);
         let Some(entry) = entry else { return Err(anyhow::Error::from(Error::PipelineNotFound(name.to_string()))); };
         entry.info.rendering_info = rendering_info;
@@ -401,10 +1085,149 @@ impl<A: Allocator> PipelineCacheInner<A> {
         }
         self.pipeline_layouts
             .get_or_create(&entry.info.layout, &mut self.set_layouts)?;
-        self.pipelines.get_or_create(
-            &entry.info,
-            (&mut self.shaders, &mut self.pipeline_layouts, &mut self.set_layouts),
-        )
+        // Pipelines that failed before are not created again until they are re-registered, their fallback is used directly.
+        if let Some(fallback) = self.failed_pipelines.get(&entry.info) {
+            return self.pipelines.get_or_create(
+                fallback,
+                (
+                    self.vk_cache.handle,
+                    &mut self.shaders,
+                    &mut self.pipeline_libraries,
+                    &mut self.pipeline_layouts,
+                    &mut self.set_layouts,
+                ),
+            );
+        }
+        let created = self.pipelines.get(&entry.info).is_none();
+        let result = self
+            .pipelines
+            .get_or_create(
+                &entry.info,
+                (
+                    self.vk_cache.handle,
+                    &mut self.shaders,
+                    &mut self.pipeline_libraries,
+                    &mut self.pipeline_layouts,
+                    &mut self.set_layouts,
+                ),
+            )
+            .map(|_| ());
+        match result {
+            Ok(()) => {
+                let pipeline = self.pipelines.get(&entry.info).unwrap();
+                if created {
+                    self.creation_feedback
+                        .record(name, pipeline.creation_feedback);
+                }
+                Ok(pipeline)
+            }
+            // In development builds, substitute a pipeline that renders magenta so a broken shader does not stop the application.
+            Err(err) if cfg!(debug_assertions) => {
+                error!("Failed to create pipeline {name}, substituting fallback pipeline: {err:?}");
+                // Shader modules are created separately from the pipeline, so a stage that fails to compile on its own can be
+                // pinpointed. Otherwise, try substituting the fragment stage first, and then every stage.
+                let failed_stages = entry
+                    .info
+                    .shaders
+                    .iter()
+                    .filter(|shader| cached_module(&mut self.shaders, shader).is_err())
+                    .fold(vk::ShaderStageFlags::empty(), |stages, shader| stages | shader.stage());
+                let candidates = if failed_stages.is_empty() {
+                    vec![vk::ShaderStageFlags::FRAGMENT, vk::ShaderStageFlags::ALL_GRAPHICS]
+                } else {
+                    vec![failed_stages]
+                };
+                let mut last_err = err;
+                for stages in candidates {
+                    let fallback = entry.info.fallback(stages);
+                    let result = self
+                        .pipelines
+                        .get_or_create(
+                            &fallback,
+                            (
+                                self.vk_cache.handle,
+                                &mut self.shaders,
+                                &mut self.pipeline_libraries,
+                                &mut self.pipeline_layouts,
+                                &mut self.set_layouts,
+                            ),
+                        )
+                        .map(|_| ());
+                    match result {
+                        Ok(()) => {
+                            self.failed_pipelines
+                                .insert(entry.info.clone(), fallback.clone());
+                            return Ok(self.pipelines.get(&fallback).unwrap());
+                        }
+                        Err(err) => last_err = err,
+                    }
+                }
+                Err(last_err)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Whether a graphics pipeline is bound as a set of shader objects instead of a `VkPipeline`.
+    fn uses_shader_objects(&self, name: &str) -> bool {
+        self.vk_cache.device.is_extension_enabled(ExtensionID::ShaderObject)
+            && self
+                .pipeline_infos
+                .get(name)
+                .is_some_and(|entry| entry.info.shader_objects)
+    }
+
+    pub(crate) fn get_shader_objects(
+        &mut self,
+        name: &str,
+        rendering_info: PipelineRenderingInfo,
+    ) -> Result<ShaderObjectBinding<'_>> {
+        let entry = self.pipeline_infos.get_mut(name);
+        let Some(entry) = entry else { return Err(anyhow::Error::from(Error::PipelineNotFound(name.to_string()))); };
+        entry.info.rendering_info = rendering_info;
+        // Shader objects are created with the descriptor set layouts and push constants, and the pipeline layout is used to bind
+        // descriptor sets, so both need to be kept alive.
+        for layout in &entry.info.layout.set_layouts {
+            self.set_layouts.get_or_create(layout, ())?;
+        }
+        let layout = self
+            .pipeline_layouts
+            .get_or_create(&entry.info.layout, &mut self.set_layouts)?;
+        let (layout, set_layouts) = unsafe { (layout.handle(), layout.set_layouts().to_vec()) };
+        let device = &self.vk_cache.device;
+        let info = &entry.info;
+        if let Some(fallback) = self.failed_pipelines.get(info) {
+            let shaders = cached_shader_objects(device, &mut self.shader_objects, &mut self.set_layouts, fallback)?;
+            return Ok(ShaderObjectBinding {
+                info,
+                shaders,
+                layout,
+                set_layouts,
+            });
+        }
+        let shaders = match cached_shader_objects(device, &mut self.shader_objects, &mut self.set_layouts, info) {
+            Ok(shaders) => shaders,
+            // Same as for regular pipelines, substitute the fallback shaders in development builds. Every stage is a separate
+            // shader object, so the stages that fail to compile are known exactly.
+            Err(err) if cfg!(debug_assertions) => {
+                error!("Failed to create shader objects for pipeline {name}, substituting fallback shader: {err:?}");
+                let failed_stages = ShaderObjectKey::for_pipeline(info)
+                    .iter()
+                    .filter(|key| self.shader_objects.get_or_create(key, &mut self.set_layouts).is_err())
+                    .fold(vk::ShaderStageFlags::empty(), |stages, key| stages | key.shader.stage());
+                let fallback = info.fallback(failed_stages);
+                let shaders = cached_shader_objects(device, &mut self.shader_objects, &mut self.set_layouts, &fallback)?;
+                self.failed_pipelines.insert(info.clone(), fallback);
+                shaders
+            }
+            Err(err) => return Err(err),
+        };
+        Ok(ShaderObjectBinding {
+            info,
+            shaders,
+            layout,
+            set_layouts,
+        })
     }
 
     pub(crate) fn get_compute_pipeline(&mut self, name: &str) -> Result<&ComputePipeline> {
@@ -416,10 +1239,21 @@ impl<A: Allocator> PipelineCacheInner<A> {
         }
         self.pipeline_layouts
             .get_or_create(&entry.info.layout, &mut self.set_layouts)?;
-        self.compute_pipelines.get_or_create(
+        let created = self.compute_pipelines.get(&entry.info).is_none();
+        let pipeline = self.compute_pipelines.get_or_create(
             &entry.info,
-            (&mut self.shaders, &mut self.pipeline_layouts, &mut self.set_layouts),
-        )
+            (
+                self.vk_cache.handle,
+                &mut self.shaders,
+                &mut self.pipeline_layouts,
+                &mut self.set_layouts,
+            ),
+        )?;
+        if created {
+            self.creation_feedback
+                .record(name, pipeline.creation_feedback);
+        }
+        Ok(pipeline)
     }
 
     pub(crate) fn get_raytracing_pipeline(&mut self, name: &str) -> Result<&RayTracingPipeline<A>> {
@@ -431,61 +1265,126 @@ impl<A: Allocator> PipelineCacheInner<A> {
         }
         self.pipeline_layouts
             .get_or_create(&entry.info.layout, &mut self.set_layouts)?;
-        self.raytracing_pipelines.get_or_create(
+        let created = self.raytracing_pipelines.get(&entry.info).is_none();
+        let pipeline = self.raytracing_pipelines.get_or_create(
             &entry.info,
             (
+                self.vk_cache.handle,
                 self.allocator.clone(),
                 &mut self.shaders,
                 &mut self.pipeline_layouts,
                 &mut self.set_layouts,
             ),
-        )
+        )?;
+        if created {
+            self.creation_feedback
+                .record(name, pipeline.creation_feedback);
+        }
+        Ok(pipeline)
     }
 }
 
-// TODO: Maybe incorporate the vulkan pipeline cache api to improve startup times?
-
 impl<A: Allocator> PipelineCache<A> {
     /// Create a new empty pipeline cache.
     pub fn new(device: Device, allocator: A) -> Result<Self> {
+        Self::with_initial_data(device, allocator, &[])
+    }
+
+    /// Create a new pipeline cache, and initialize the driver's pipeline cache with data previously written by
+    /// [`PipelineCache::save_to_file()`]. This avoids recompiling pipelines that were already compiled in a previous run.
+    ///
+    /// If the data was created by a different device or driver version, it is discarded with a warning and the cache starts out empty.
+    /// # Errors
+    /// * Fails if the file could not be read.
+    /// * Fails if creating the `VkPipelineCache` fails.
+    pub fn load_from_file(device: Device, allocator: A, path: impl AsRef<Path>) -> Result<Self> {
+        let data = std::fs::read(path.as_ref())?;
+        if is_compatible_cache_data(&device, &data) {
+            Self::with_initial_data(device, allocator, &data)
+        } else {
+            warn!(
+                "Pipeline cache data in {} was created by a different device or driver, ignoring it.",
+                path.as_ref().display()
+            );
+            Self::with_initial_data(device, allocator, &[])
+        }
+    }
+
+    /// Write the driver's pipeline cache to a file, so it can be loaded with [`PipelineCache::load_from_file()`] on the next run.
+    /// This includes all pipelines that were created through this cache so far.
+    /// # Errors
+    /// * Fails if obtaining the pipeline cache data fails.
+    /// * Fails if the file could not be written.
+    pub fn save_to_file(&self, path: impl AsRef<Path>) -> Result<()> {
+        let data = self.inner.read().unwrap().vk_cache.data()?;
+        std::fs::write(path, data)?;
+        Ok(())
+    }
+
+    fn with_initial_data(device: Device, allocator: A, initial_data: &[u8]) -> Result<Self> {
+        let (compiled_sender, compiled) = mpsc::channel();
         let inner = PipelineCacheInner {
             allocator,
+            vk_cache: VulkanPipelineCache::new(device.clone(), initial_data)?,
             shaders: Cache::new(device.clone()),
             set_layouts: Cache::new(device.clone()),
             pipeline_layouts: Cache::new(device.clone()),
             pipelines: Cache::new(device.clone()),
+            pipeline_libraries: Cache::new(device.clone()),
+            shader_objects: Cache::new(device.clone()),
             compute_pipelines: Cache::new(device.clone()),
             raytracing_pipelines: Cache::new(device),
             pipeline_infos: Default::default(),
             compute_pipeline_infos: Default::default(),
             raytracing_pipeline_infos: Default::default(),
+            failed_pipelines: Default::default(),
+            compiling: Default::default(),
+            failed_compilations: Default::default(),
+            creation_feedback: Default::default(),
+            compiled_sender,
+            compiled,
         };
         Ok(Self {
+            compile_workers: Arc::new(OnceLock::new()),
             inner: Arc::new(RwLock::new(inner)),
+            published: Arc::new((Mutex::new(()), Condvar::new())),
         })
     }
 
+    /// Make a fully built pipeline entry visible to other threads, and wake up any threads waiting for it.
+    fn publish(&self, f: impl FnOnce(&mut PipelineCacheInner<A>)) {
+        {
+            let mut inner = self.inner.write().unwrap();
+            f(&mut inner);
+        }
+        // Take the lock before notifying so a waiting thread cannot miss this wakeup between checking for its pipeline and
+        // starting to wait.
+        let (lock, cvar) = &*self.published;
+        let _guard = lock.lock().unwrap();
+        cvar.notify_all();
+    }
+
     /// Create and register a new pipeline into the cache.
     #[cfg(feature = "shader-reflection")]
     pub fn create_named_pipeline(&mut self, mut info: PipelineCreateInfo) -> Result<()> {
         let refl = reflect_shaders(info.shaders.as_slice())?;
         // Using reflection, we can allow omitting the pipeline layout field.
         info.layout = build_pipeline_layout(&refl);
+        apply_immutable_samplers(&mut info.layout, &refl, &info.immutable_samplers)?;
+        apply_reflected_vertex_inputs(&mut info, &refl)?;
+        info.build_inner();
         let name = info.name.clone();
-        let mut inner = self.inner.write().unwrap();
-        inner.pipeline_infos.insert(
-            name.clone(),
-            PipelineEntry {
-                info,
-                reflection: refl,
-            },
-        );
-        inner
-            .pipeline_infos
-            .get_mut(&name)
-            .unwrap()
-            .info
-            .build_inner();
+        self.publish(|inner| {
+            inner.failed_pipelines.retain(|info, _| info.name != name);
+            inner.failed_compilations.retain(|info| info.name != name);
+            inner.pipeline_infos.insert(
+                name,
+                PipelineEntry {
+                    info,
+                    reflection: refl,
+                },
+            );
+        });
         Ok(())
     }
 
@@ -494,19 +1393,16 @@ impl<A: Allocator> PipelineCache<A> {
     pub fn create_named_pipeline(&mut self, mut info: PipelineCreateInfo) -> Result<()> {
         info.build_inner();
         let name = info.name.clone();
-        let mut inner = self.inner.write().unwrap();
-        inner.pipeline_infos.insert(
-            name.clone(),
-            PipelineEntry {
-                info,
-            },
-        );
-        inner
-            .pipeline_infos
-            .get_mut(&name)
-            .unwrap()
-            .info
-            .build_inner();
+        self.publish(|inner| {
+            inner.failed_pipelines.retain(|info, _| info.name != name);
+            inner.failed_compilations.retain(|info| info.name != name);
+            inner.pipeline_infos.insert(
+                name,
+                PipelineEntry {
+                    info,
+                },
+            );
+        });
         Ok(())
     }
 
@@ -522,6 +1418,7 @@ impl<A: Allocator> PipelineCache<A> {
         };
         // Using reflection, we can allow omitting the pipeline layout field.
         info.layout = build_pipeline_layout(&refl);
+        apply_immutable_samplers(&mut info.layout, &refl, &info.immutable_samplers)?;
         // If this is persistent, then also make the pipeline and descriptor set layouts persistent
         if info.persistent {
             info.layout.persistent = true;
@@ -536,14 +1433,15 @@ impl<A: Allocator> PipelineCache<A> {
             }
         }
         let name = info.name.clone();
-        let mut inner = self.inner.write().unwrap();
-        inner.compute_pipeline_infos.insert(
-            name,
-            PipelineEntry {
-                info,
-                reflection: refl,
-            },
-        );
+        self.publish(|inner| {
+            inner.compute_pipeline_infos.insert(
+                name,
+                PipelineEntry {
+                    info,
+                    reflection: refl,
+                },
+            );
+        });
         Ok(())
     }
 
@@ -554,13 +1452,14 @@ impl<A: Allocator> PipelineCache<A> {
         mut info: ComputePipelineCreateInfo,
     ) -> Result<()> {
         let name = info.name.clone();
-        let mut inner = self.inner.write().unwrap();
-        inner.compute_pipeline_infos.insert(
-            name,
-            PipelineEntry {
-                info,
-            },
-        );
+        self.publish(|inner| {
+            inner.compute_pipeline_infos.insert(
+                name,
+                PipelineEntry {
+                    info,
+                },
+            );
+        });
         Ok(())
     }
 
@@ -575,14 +1474,15 @@ impl<A: Allocator> PipelineCache<A> {
         info.layout = build_pipeline_layout(&refl);
 
         let name = info.name.clone();
-        let mut inner = self.inner.write().unwrap();
-        inner.raytracing_pipeline_infos.insert(
-            name,
-            PipelineEntry {
-                info,
-                reflection: refl,
-            },
-        );
+        self.publish(|inner| {
+            inner.raytracing_pipeline_infos.insert(
+                name,
+                PipelineEntry {
+                    info,
+                    reflection: refl,
+                },
+            );
+        });
         Ok(())
     }
 
@@ -593,13 +1493,14 @@ impl<A: Allocator> PipelineCache<A> {
         mut info: RayTracingPipelineCreateInfo,
     ) -> Result<()> {
         let name = info.name.clone();
-        let mut inner = self.inner.write().unwrap();
-        inner.raytracing_pipeline_infos.insert(
-            name,
-            PipelineEntry {
-                info,
-            },
-        );
+        self.publish(|inner| {
+            inner.raytracing_pipeline_infos.insert(
+                name,
+                PipelineEntry {
+                    info,
+                },
+            );
+        });
         Ok(())
     }
 
@@ -639,6 +1540,63 @@ impl<A: Allocator> PipelineCache<A> {
             .map(|entry| entry.info.clone())
     }
 
+    /// Get the shaders of every pipeline registered in this cache, without duplicates. This can be used to hand shader binaries
+    /// to external tools, such as GPU crash dump tools.
+    pub fn shaders(&self) -> Vec<ShaderCreateInfo> {
+        let inner = self.inner.read().unwrap();
+        let graphics = inner
+            .pipeline_infos
+            .values()
+            .flat_map(|entry| entry.info.shaders.iter());
+        let compute = inner
+            .compute_pipeline_infos
+            .values()
+            .filter_map(|entry| entry.info.shader.as_ref());
+        let raytracing = inner
+            .raytracing_pipeline_infos
+            .values()
+            .flat_map(|entry| entry.info.shaders.iter());
+        let mut seen = HashSet::new();
+        graphics
+            .chain(compute)
+            .chain(raytracing)
+            .filter(|shader| seen.insert(shader.code_hash()))
+            .cloned()
+            .collect()
+    }
+
+    /// Get the shader group handles of a raytracing pipeline, as returned by `vkGetRayTracingShaderGroupHandlesKHR`. Each handle is
+    /// `shaderGroupHandleSize` bytes, and groups are ordered by type: ray generation, miss, hit and callable groups.
+    /// Use these to build a custom shader binding table, and trace rays with it using
+    /// [`trace_rays_with_regions()`](crate::command_buffer::traits::GraphicsCmdBuffer::trace_rays_with_regions).
+    /// # Errors
+    /// - This function can fail if the requested pipeline does not exist in the cache
+    /// - This function can fail if allocating the pipeline fails.
+    pub fn shader_group_handles(&self, name: &str) -> Result<Vec<u8>> {
+        let mut handles = Vec::new();
+        self.with_raytracing_pipeline(name, |pipeline| {
+            handles = pipeline.shader_binding_table.group_handles().to_vec();
+            Ok(())
+        })?;
+        Ok(handles)
+    }
+
+    /// Get the shader reflection information of a graphics, compute or raytracing pipeline. This describes the descriptor
+    /// bindings, push constant ranges and vertex inputs of the pipeline's shaders.
+    /// # Errors
+    /// Returns None if the pipeline was not found in the cache.
+    #[cfg(feature = "shader-reflection")]
+    pub fn reflection_info(&self, name: &str) -> Option<ReflectionInfo> {
+        let inner = self.inner.read().unwrap();
+        inner
+            .pipeline_infos
+            .get(name)
+            .map(|entry| &entry.reflection)
+            .or_else(|| inner.compute_pipeline_infos.get(name).map(|entry| &entry.reflection))
+            .or_else(|| inner.raytracing_pipeline_infos.get(name).map(|entry| &entry.reflection))
+            .cloned()
+    }
+
     /// Returns the pipeline type of a pipeline, or None if the pipeline does not exist.
     pub fn pipeline_type(&self, name: &str) -> Option<PipelineType> {
         let inner = self.inner.read().unwrap();
@@ -653,6 +1611,42 @@ impl<A: Allocator> PipelineCache<A> {
         }
     }
 
+    /// Returns true if a pipeline of any type with this name has been registered in the cache.
+    pub fn contains_pipeline(&self, name: &str) -> bool {
+        self.pipeline_type(name).is_some()
+    }
+
+    /// Block the calling thread until a pipeline with this name is registered in the cache, for example by a loading thread.
+    /// Returns immediately if the pipeline already exists.
+    /// # Example
+    /// ```
+    /// # use phobos::prelude::*;
+    /// fn load_screen(cache: PipelineCache, info: PipelineCreateInfo) {
+    ///     let mut loader = cache.clone();
+    ///     std::thread::spawn(move || loader.create_named_pipeline(info));
+    ///     // Render a loading screen, then wait until the pipeline is ready.
+    ///     cache.wait_for_pipeline("my_pipeline");
+    /// }
+    /// ```
+    pub fn wait_for_pipeline(&self, name: &str) {
+        let (lock, cvar) = &*self.published;
+        let guard = lock.lock().unwrap();
+        let _guard = cvar
+            .wait_while(guard, |_| !self.contains_pipeline(name))
+            .unwrap();
+    }
+
+    /// Block the calling thread until a pipeline with this name is registered in the cache, or until the timeout expires.
+    /// Returns true if the pipeline is available.
+    pub fn wait_for_pipeline_timeout(&self, name: &str, timeout: Duration) -> bool {
+        let (lock, cvar) = &*self.published;
+        let guard = lock.lock().unwrap();
+        let _guard = cvar
+            .wait_timeout_while(guard, timeout, |_| !self.contains_pipeline(name))
+            .unwrap();
+        self.contains_pipeline(name)
+    }
+
     /// Obtain a pipeline from the cache and do some work with it.
     /// # Errors
     /// - This function can fail if the requested pipeline does not exist in the cache
@@ -696,10 +1690,140 @@ impl<A: Allocator> PipelineCache<A> {
         f(pipeline)
     }
 
+    /// Whether a graphics pipeline is bound as a set of shader objects. This is the case if it was created with
+    /// [`PipelineBuilder::shader_objects()`](crate::PipelineBuilder::shader_objects) and `VK_EXT_shader_object` is enabled.
+    pub(crate) fn uses_shader_objects(&self, name: &str) -> bool {
+        self.inner.read().unwrap().uses_shader_objects(name)
+    }
+
+    /// Obtain the shader objects of a graphics pipeline from the cache and do some work with them.
+    /// # Errors
+    /// - This function can fail if the requested pipeline does not exist in the cache
+    /// - This function can fail if creating the shader objects fails.
+    pub(crate) fn with_shader_objects<F: FnOnce(&ShaderObjectBinding) -> Result<()>>(
+        &self,
+        name: &str,
+        rendering_info: PipelineRenderingInfo,
+        f: F,
+    ) -> Result<()> {
+        let mut inner = self.inner.write().unwrap();
+        let binding = inner.get_shader_objects(name, rendering_info)?;
+        f(&binding)
+    }
+
+    fn compile_workers(&self) -> &WorkerPool {
+        self.compile_workers.get_or_init(|| {
+            let threads = std::thread::available_parallelism().map_or(1, |n| n.get().saturating_sub(1));
+            WorkerPool::new("phobos pipeline compiler", threads)
+        })
+    }
+
+    /// Check whether a graphics pipeline can be bound in a render pass with the given attachments without compiling it on
+    /// the calling thread. If it cannot, it is compiled on a worker thread.
+    /// # Errors
+    /// - This function can fail if the requested pipeline does not exist in the cache.
+    pub(crate) fn request_pipeline(&self, name: &str, rendering_info: PipelineRenderingInfo) -> Result<bool> {
+        let mut inner = self.inner.write().unwrap();
+        inner.request_pipeline(name, rendering_info, self.compile_workers())
+    }
+
+    /// Start compiling graphics pipelines on worker threads, so they do not need to be compiled when they are first bound.
+    /// This is typically called at load time with all pipelines a scene uses. The number of pipelines that are still being
+    /// compiled can be queried with [`PipelineCache::num_compiling_pipelines()`].
+    ///
+    /// To avoid compiling pipelines in the render loop altogether, check
+    /// [`GraphicsCmdBuffer::is_graphics_pipeline_ready()`](crate::GraphicsCmdBuffer::is_graphics_pipeline_ready) before binding a pipeline.
+    /// # Errors
+    /// - This function can fail if one of the pipelines does not exist in the cache. Pipelines before it are still compiled.
+    /// # Example
+    /// ```
+    /// # use anyhow::Result;
+    /// # use phobos::prelude::*;
+    /// fn load(cache: &PipelineCache) -> Result<()> {
+    ///     let formats = PipelinePrecompileInfo {
+    ///         color_formats: vec![vk::Format::R8G8B8A8_SRGB],
+    ///         depth_format: Some(vk::Format::D32_SFLOAT),
+    ///         ..Default::default()
+    ///     };
+    ///     let pipelines = ["opaque", "alpha_tested", "skinned"].map(|name| PipelinePrecompileInfo {
+    ///         name: name.to_owned(),
+    ///         ..formats.clone()
+    ///     });
+    ///     cache.precompile_pipelines(&pipelines)?;
+    ///     while cache.num_compiling_pipelines() > 0 {
+    ///         // Render a loading screen
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn precompile_pipelines(&self, pipelines: &[PipelinePrecompileInfo]) -> Result<()> {
+        let mut inner = self.inner.write().unwrap();
+        for pipeline in pipelines {
+            inner.request_pipeline(&pipeline.name, pipeline.rendering_info(), self.compile_workers())?;
+        }
+        Ok(())
+    }
+
+    /// Get the number of graphics pipelines that are currently being compiled on worker threads.
+    pub fn num_compiling_pipelines(&self) -> usize {
+        let mut inner = self.inner.write().unwrap();
+        inner.receive_compiled_pipelines();
+        inner.compiling.len()
+    }
+
+    /// Get the creation feedback of every pipeline that was created by this cache so far, sorted from slowest to fastest.
+    /// If a pipeline was created multiple times, for example for different render pass attachments, this is the feedback of the
+    /// most recent creation. Pipelines compiled on worker threads are included once they are finished.
+    /// # Example
+    /// ```
+    /// # use phobos::prelude::*;
+    /// # use std::time::Duration;
+    /// fn log_slow_pipelines(cache: &PipelineCache) {
+    ///     for (name, feedback) in cache.creation_feedback() {
+    ///         if feedback.duration > Duration::from_millis(10) {
+    ///             println!("Pipeline {name} took {:?} to compile (cache hit: {})", feedback.duration, feedback.cache_hit);
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn creation_feedback(&self) -> Vec<(String, PipelineCreationFeedback)> {
+        let mut inner = self.inner.write().unwrap();
+        inner.receive_compiled_pipelines();
+        let mut feedback = inner
+            .creation_feedback
+            .pipelines
+            .iter()
+            .map(|(name, feedback)| (name.clone(), *feedback))
+            .collect::<Vec<_>>();
+        feedback.sort_by_key(|(_, feedback)| std::cmp::Reverse(feedback.duration));
+        feedback
+    }
+
+    /// Get statistics over all pipelines that were created by this cache so far. This can be used to check whether the
+    /// pipeline cache loaded with [`PipelineCache::load_from_file()`] was effective.
+    pub fn creation_statistics(&self) -> PipelineCreationStatistics {
+        let mut inner = self.inner.write().unwrap();
+        inner.receive_compiled_pipelines();
+        inner.creation_feedback.statistics
+    }
+
     /// Advance cache resource time to live so resources that have not been used in a while can be cleaned up
     pub fn next_frame(&self) {
         let mut inner = self.inner.write().unwrap();
+        let inner = &mut *inner;
+        inner.receive_compiled_pipelines();
+        // Pipelines that are still compiling need their layouts to stay alive.
+        for info in &inner.compiling {
+            for layout in &info.layout.set_layouts {
+                let _ = inner.set_layouts.get_or_create(layout, ());
+            }
+            let _ = inner
+                .pipeline_layouts
+                .get_or_create(&info.layout, &mut inner.set_layouts);
+        }
         inner.pipelines.next_frame();
+        inner.pipeline_libraries.next_frame();
+        inner.shader_objects.next_frame();
         inner.compute_pipelines.next_frame();
         inner.raytracing_pipelines.next_frame();
         inner.pipeline_layouts.next_frame();
@@ -1,9 +1,13 @@
 //! Wrapper types for compute pipelines
 
+use std::sync::Arc;
+
 use ash::vk;
+use bytemuck::NoUninit;
 
+use crate::pipeline::create_info::NamedImmutableSampler;
 use crate::pipeline::pipeline_layout::PipelineLayoutCreateInfo;
-use crate::ShaderCreateInfo;
+use crate::{Sampler, ShaderCreateInfo};
 
 /// Create info for a compute pipeline. Use the [`ComputePipelineBuilder`](crate::ComputePipelineBuilder)
 /// struct to construct this.
@@ -14,9 +18,17 @@ pub struct ComputePipelineCreateInfo {
     pub(crate) name: String,
     pub(crate) layout: PipelineLayoutCreateInfo,
     pub(crate) persistent: bool,
+    /// Immutable samplers for named bindings, applied to the reflected pipeline layout.
+    pub(crate) immutable_samplers: Vec<NamedImmutableSampler>,
 }
 
 impl ComputePipelineCreateInfo {
+    /// Get the pipeline layout of this compute pipeline. When using shader reflection, this is only filled in after the pipeline was
+    /// registered to a [`PipelineCache`](crate::PipelineCache).
+    pub fn layout(&self) -> &PipelineLayoutCreateInfo {
+        &self.layout
+    }
+
     // create compute pipeline create info, but without the shader filled out
     pub(crate) fn to_vk(&self, layout: vk::PipelineLayout) -> vk::ComputePipelineCreateInfo {
         vk::ComputePipelineCreateInfo {
@@ -47,6 +59,7 @@ impl ComputePipelineBuilder {
                 name: name.into(),
                 layout: Default::default(),
                 persistent: false,
+                immutable_samplers: vec![],
             },
         }
     }
@@ -58,6 +71,13 @@ impl ComputePipelineBuilder {
         self
     }
 
+    /// Set the value of a specialization constant of the compute shader. The shader must be set before calling this.
+    /// See [`ShaderCreateInfo::specialization_constant()`].
+    pub fn specialization_constant<T: NoUninit>(mut self, id: u32, value: &T) -> Self {
+        self.inner.shader = self.inner.shader.map(|shader| shader.specialization_constant(id, value));
+        self
+    }
+
     /// Make this compute pipeline persistent, meaning it will never get cleaned up by the cache.
     /// Use this with caution, frequently recreating persistent pipelines will cause a pileup of memory.
     /// This is intentionally not available for graphics pipelines to avoid this issue, since those
@@ -67,6 +87,16 @@ impl ComputePipelineBuilder {
         self
     }
 
+    /// Use an immutable sampler for the sampler or combined image sampler binding with this name in the shader.
+    /// See [`PipelineBuilder::immutable_sampler()`](crate::PipelineBuilder::immutable_sampler).
+    pub fn immutable_sampler(mut self, name: impl Into<String>, sampler: Arc<Sampler>) -> Self {
+        self.inner.immutable_samplers.push(NamedImmutableSampler {
+            name: name.into(),
+            sampler,
+        });
+        self
+    }
+
     /// Build the compute pipeline create info.
     pub fn build(self) -> ComputePipelineCreateInfo {
         self.inner
@@ -1,9 +1,12 @@
 //! Wrapper structs for pipeline create info objects.
 
+use std::sync::Arc;
+
 use ash::vk;
 
+use crate::pipeline::fallback::{magenta_fragment_shader, null_vertex_shader};
 use crate::pipeline::pipeline_layout::PipelineLayoutCreateInfo;
-use crate::ShaderCreateInfo;
+use crate::{Sampler, ShaderCreateInfo};
 
 #[derive(Debug, Copy, Clone)]
 pub(crate) struct VertexInputBindingDescription(pub(super) vk::VertexInputBindingDescription);
@@ -41,12 +44,24 @@ pub(crate) struct PipelineTessellationStateCreateInfo(
     pub(super) vk::PipelineTessellationStateCreateInfo,
 );
 
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct PipelineFragmentShadingRateStateCreateInfo(
+    pub(super) vk::PipelineFragmentShadingRateStateCreateInfoKHR,
+);
+
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct PipelineRasterizationConservativeStateCreateInfo(
+    pub(super) vk::PipelineRasterizationConservativeStateCreateInfoEXT,
+);
+
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub(crate) struct PipelineRenderingInfo {
     pub view_mask: u32,
     pub color_formats: Vec<vk::Format>,
     pub depth_format: Option<vk::Format>,
     pub stencil_format: Option<vk::Format>,
+    /// Whether the render pass has a fragment shading rate attachment.
+    pub shading_rate_attachment: bool,
 }
 
 /// Newtype wrapper for a Vulkan viewport. Implements `Hash` and `Eq`.
@@ -57,6 +72,30 @@ pub struct Viewport(pub(super) vk::Viewport);
 #[derive(Debug, Copy, Clone)]
 pub struct Rect2D(pub(super) vk::Rect2D);
 
+/// An immutable sampler for the binding with this name in the shader. The create info holds a reference to the sampler, so it
+/// stays alive for as long as the pipeline can be created. Samplers are compared by their handle.
+#[derive(Debug, Clone)]
+pub(crate) struct NamedImmutableSampler {
+    pub name: String,
+    pub sampler: Arc<Sampler>,
+}
+
+/// A vertex binding whose attributes are derived from the vertex shader inputs through shader reflection.
+/// See [`PipelineBuilder::vertex_inputs_from_reflection()`](crate::PipelineBuilder::vertex_inputs_from_reflection).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ReflectedVertexBinding {
+    /// Binding index, as used in `vkCmdBindVertexBuffers`.
+    pub binding: u32,
+    /// Whether this binding advances per vertex or per instance.
+    pub rate: vk::VertexInputRate,
+    /// Distance between two consecutive elements in the vertex buffer. If this is `None`, the attributes are assumed to be
+    /// tightly packed.
+    pub stride: Option<u32>,
+    /// First shader location read from this binding. The binding contains all inputs from this location up to the first
+    /// location of the next binding.
+    pub first_location: u32,
+}
+
 /// Defines a full graphics pipeline. Use the pipeline builder to construct this properly.
 #[derive(Debug, Clone, Derivative)]
 #[derivative(PartialEq, Eq, Hash)]
@@ -78,6 +117,16 @@ pub struct PipelineCreateInfo {
     pub(crate) blend_enable_logic_op: bool,
     pub(crate) rendering_info: PipelineRenderingInfo,
     pub(crate) tesselation_info: Option<PipelineTessellationStateCreateInfo>,
+    pub(crate) fragment_shading_rate: Option<PipelineFragmentShadingRateStateCreateInfo>,
+    pub(crate) conservative_rasterization: Option<PipelineRasterizationConservativeStateCreateInfo>,
+    /// Whether the depth range in clip space is `[-1, 1]` instead of `[0, 1]`, through `VK_EXT_depth_clip_control`.
+    pub(crate) depth_clip_negative_one_to_one: bool,
+    /// Whether to bind shader objects instead of a pipeline, if `VK_EXT_shader_object` is enabled.
+    pub(crate) shader_objects: bool,
+    /// Immutable samplers for named bindings, applied to the reflected pipeline layout.
+    pub(crate) immutable_samplers: Vec<NamedImmutableSampler>,
+    /// Vertex bindings to fill with the reflected vertex shader inputs.
+    pub(crate) reflected_vertex_bindings: Vec<ReflectedVertexBinding>,
 
     #[derivative(PartialEq = "ignore")]
     #[derivative(Hash = "ignore")]
@@ -112,9 +161,45 @@ pub struct PipelineCreateInfo {
     #[derivative(PartialEq = "ignore")]
     #[derivative(Hash = "ignore")]
     pub(super) vk_tessellation_state: Option<vk::PipelineTessellationStateCreateInfo>,
+    #[derivative(PartialEq = "ignore")]
+    #[derivative(Hash = "ignore")]
+    pub(super) vk_fragment_shading_rate_state: Option<vk::PipelineFragmentShadingRateStateCreateInfoKHR>,
+    #[derivative(PartialEq = "ignore")]
+    #[derivative(Hash = "ignore")]
+    pub(super) vk_conservative_rasterization_state: Option<vk::PipelineRasterizationConservativeStateCreateInfoEXT>,
+    #[derivative(PartialEq = "ignore")]
+    #[derivative(Hash = "ignore")]
+    pub(super) vk_depth_clip_control_state: Option<vk::PipelineViewportDepthClipControlCreateInfoEXT>,
 }
 
 impl PipelineCreateInfo {
+    /// Create a copy of this pipeline with the built-in fallback shaders substituted for the stages in `failed_stages`.
+    /// This is used as a substitute when creating this pipeline fails. The fragment shader is always replaced by one that renders
+    /// magenta. A failed vertex shader is replaced by one that draws nothing, and failed geometry or tessellation stages are removed,
+    /// together with any later stages that read their outputs. Task and mesh shaders have no substitute.
+    pub(crate) fn fallback(&self, failed_stages: vk::ShaderStageFlags) -> Self {
+        let tessellation = vk::ShaderStageFlags::TESSELLATION_CONTROL | vk::ShaderStageFlags::TESSELLATION_EVALUATION;
+        let mut removed = failed_stages | vk::ShaderStageFlags::FRAGMENT;
+        if failed_stages.intersects(vk::ShaderStageFlags::VERTEX | tessellation) {
+            removed |= tessellation | vk::ShaderStageFlags::GEOMETRY;
+        }
+        let mut info = self.clone();
+        info.shaders.retain(|shader| !removed.contains(shader.stage()));
+        if removed.intersects(tessellation) && !info.is_tessellation_pipeline() {
+            info.tesselation_info = None;
+            if info.input_assembly.0.topology == vk::PrimitiveTopology::PATCH_LIST {
+                info.input_assembly.0.topology = vk::PrimitiveTopology::TRIANGLE_LIST;
+            }
+        }
+        if failed_stages.contains(vk::ShaderStageFlags::VERTEX) {
+            info.shaders.push(null_vertex_shader());
+        }
+        info.shaders.push(magenta_fragment_shader());
+        // The cloned Vulkan structures still point into the original create info.
+        info.build_inner();
+        info
+    }
+
     pub(crate) fn build_rendering_state(&mut self) {
         self.vk_rendering_state = vk::PipelineRenderingCreateInfo::builder()
             .view_mask(self.rendering_info.view_mask)
@@ -130,6 +215,19 @@ impl PipelineCreateInfo {
                     .unwrap_or(vk::Format::UNDEFINED),
             )
             .build();
+        // Chain the fragment shading rate state so it gets picked up by the graphics pipeline create info.
+        if let Some(state) = &mut self.vk_fragment_shading_rate_state {
+            self.vk_rendering_state.p_next = (state as *mut _) as *mut std::ffi::c_void;
+        }
+        // These states are stored inline too, so their pointers must be refreshed whenever the create info may have moved.
+        self.rasterizer.0.p_next = match &self.vk_conservative_rasterization_state {
+            None => std::ptr::null(),
+            Some(state) => (state as *const _) as *const std::ffi::c_void,
+        };
+        self.viewport_state.p_next = match &self.vk_depth_clip_control_state {
+            None => std::ptr::null(),
+            Some(state) => (state as *const _) as *const std::ffi::c_void,
+        };
     }
 
     /// Build the inner state of the pipeline. This must be called at least once before actually creating the pipeline.
@@ -161,19 +259,80 @@ impl PipelineCreateInfo {
                 .flags(info.0.flags)
                 .build()
         });
+        self.vk_fragment_shading_rate_state = self.fragment_shading_rate.map(|info| {
+            vk::PipelineFragmentShadingRateStateCreateInfoKHR::builder()
+                .fragment_size(info.0.fragment_size)
+                .combiner_ops(info.0.combiner_ops)
+                .build()
+        });
+        self.vk_conservative_rasterization_state = self.conservative_rasterization.map(|info| {
+            vk::PipelineRasterizationConservativeStateCreateInfoEXT::builder()
+                .conservative_rasterization_mode(info.0.conservative_rasterization_mode)
+                .extra_primitive_overestimation_size(info.0.extra_primitive_overestimation_size)
+                .build()
+        });
+        self.vk_depth_clip_control_state = match self.depth_clip_negative_one_to_one {
+            true => Some(
+                vk::PipelineViewportDepthClipControlCreateInfoEXT::builder()
+                    .negative_one_to_one(true)
+                    .build(),
+            ),
+            false => None,
+        };
         self.build_rendering_state();
     }
 
+    /// Get the pipeline layout of this pipeline. When using shader reflection, this is only filled in after the pipeline was
+    /// registered to a [`PipelineCache`](crate::PipelineCache), so query it through [`PipelineCache::pipeline_info()`](crate::PipelineCache::pipeline_info).
+    pub fn layout(&self) -> &PipelineLayoutCreateInfo {
+        &self.layout
+    }
+
+    /// Whether this pipeline uses mesh shading, meaning it has a mesh shader stage instead of a vertex shader stage.
+    pub fn is_mesh_pipeline(&self) -> bool {
+        self.shaders
+            .iter()
+            .any(|shader| shader.stage().contains(vk::ShaderStageFlags::MESH_EXT))
+    }
+
+    /// Whether this pipeline uses tessellation, meaning it has a tessellation control or evaluation shader stage.
+    pub fn is_tessellation_pipeline(&self) -> bool {
+        self.shaders.iter().any(|shader| {
+            shader.stage().intersects(
+                vk::ShaderStageFlags::TESSELLATION_CONTROL | vk::ShaderStageFlags::TESSELLATION_EVALUATION,
+            )
+        })
+    }
+
+    /// Whether this pipeline has a geometry shader stage.
+    pub fn has_geometry_shader(&self) -> bool {
+        self.shaders
+            .iter()
+            .any(|shader| shader.stage() == vk::ShaderStageFlags::GEOMETRY)
+    }
+
     // Shader stage not yet filled out
     pub(crate) fn to_vk(&self, layout: vk::PipelineLayout) -> vk::GraphicsPipelineCreateInfo {
+        // Mesh pipelines have no vertex input stage, so these states are ignored.
+        let mesh = self.is_mesh_pipeline();
         vk::GraphicsPipelineCreateInfo {
             s_type: vk::StructureType::GRAPHICS_PIPELINE_CREATE_INFO,
             p_next: (&self.vk_rendering_state as *const _) as *const std::ffi::c_void,
-            flags: Default::default(),
+            // Pipelines used with a shading rate attachment in dynamic rendering need this flag, see VUID-vkCmdDraw-imageView-06183
+            flags: match self.rendering_info.shading_rate_attachment {
+                true => vk::PipelineCreateFlags::RENDERING_FRAGMENT_SHADING_RATE_ATTACHMENT_KHR,
+                false => vk::PipelineCreateFlags::empty(),
+            },
             stage_count: 0,
             p_stages: std::ptr::null(),
-            p_vertex_input_state: &self.vertex_input_state,
-            p_input_assembly_state: &self.input_assembly.0,
+            p_vertex_input_state: match mesh {
+                true => std::ptr::null(),
+                false => &self.vertex_input_state,
+            },
+            p_input_assembly_state: match mesh {
+                true => std::ptr::null(),
+                false => &self.input_assembly.0,
+            },
             p_tessellation_state: match &self.vk_tessellation_state {
                 None => std::ptr::null(),
                 Some(info) => info,
@@ -0,0 +1,117 @@
+//! Built-in fallback shaders, used to keep rendering when a pipeline fails to compile.
+
+use ash::vk;
+
+use crate::pipeline::shader::ShaderCreateInfo;
+
+/// SPIR-V for a fragment shader that writes magenta to the first color attachment. Equivalent to the following GLSL:
+/// ```glsl
+/// #version 450
+/// layout(location = 0) out vec4 out_color;
+/// void main() {
+///     out_color = vec4(1.0, 0.0, 1.0, 1.0);
+/// }
+/// ```
+#[rustfmt::skip]
+const MAGENTA_FRAGMENT_SPIRV: &[u32] = &[
+    // Header: magic number, SPIR-V 1.0, generator, id bound, schema
+    0x07230203, 0x00010000, 0x00000000, 12, 0,
+    // OpCapability Shader
+    0x00020011, 1,
+    // OpMemoryModel Logical GLSL450
+    0x0003000e, 0, 1,
+    // OpEntryPoint Fragment %1 "main" %2
+    0x0006000f, 4, 1, 0x6e69616d, 0x00000000, 2,
+    // OpExecutionMode %1 OriginUpperLeft
+    0x00030010, 1, 7,
+    // OpDecorate %2 Location 0
+    0x00040047, 2, 30, 0,
+    // %3 = OpTypeVoid
+    0x00020013, 3,
+    // %4 = OpTypeFunction %3
+    0x00030021, 4, 3,
+    // %5 = OpTypeFloat 32
+    0x00030016, 5, 32,
+    // %6 = OpTypeVector %5 4
+    0x00040017, 6, 5, 4,
+    // %7 = OpTypePointer Output %6
+    0x00040020, 7, 3, 6,
+    // %2 = OpVariable %7 Output
+    0x0004003b, 7, 2, 3,
+    // %8 = OpConstant %5 1.0
+    0x0004002b, 5, 8, 0x3f800000,
+    // %9 = OpConstant %5 0.0
+    0x0004002b, 5, 9, 0x00000000,
+    // %10 = OpConstantComposite %6 %8 %9 %8 %8
+    0x0007002c, 6, 10, 8, 9, 8, 8,
+    // %1 = OpFunction %3 None %4
+    0x00050036, 3, 1, 0, 4,
+    // %11 = OpLabel
+    0x000200f8, 11,
+    // OpStore %2 %10
+    0x0003003e, 2, 10,
+    // OpReturn
+    0x000100fd,
+    // OpFunctionEnd
+    0x00010038,
+];
+
+/// Get a fragment shader that renders everything in magenta.
+pub(crate) fn magenta_fragment_shader() -> ShaderCreateInfo {
+    ShaderCreateInfo::from_spirv(vk::ShaderStageFlags::FRAGMENT, MAGENTA_FRAGMENT_SPIRV.to_vec())
+}
+
+/// SPIR-V for a vertex shader that places every vertex at the origin, so nothing is drawn. It has no inputs, so it can be used with
+/// any vertex layout. Equivalent to the following GLSL:
+/// ```glsl
+/// #version 450
+/// void main() {
+///     gl_Position = vec4(0.0, 0.0, 0.0, 1.0);
+/// }
+/// ```
+#[rustfmt::skip]
+const NULL_VERTEX_SPIRV: &[u32] = &[
+    // Header: magic number, SPIR-V 1.0, generator, id bound, schema
+    0x07230203, 0x00010000, 0x00000000, 12, 0,
+    // OpCapability Shader
+    0x00020011, 1,
+    // OpMemoryModel Logical GLSL450
+    0x0003000e, 0, 1,
+    // OpEntryPoint Vertex %1 "main" %2
+    0x0006000f, 0, 1, 0x6e69616d, 0x00000000, 2,
+    // OpDecorate %2 BuiltIn Position
+    0x00040047, 2, 11, 0,
+    // %3 = OpTypeVoid
+    0x00020013, 3,
+    // %4 = OpTypeFunction %3
+    0x00030021, 4, 3,
+    // %5 = OpTypeFloat 32
+    0x00030016, 5, 32,
+    // %6 = OpTypeVector %5 4
+    0x00040017, 6, 5, 4,
+    // %7 = OpTypePointer Output %6
+    0x00040020, 7, 3, 6,
+    // %2 = OpVariable %7 Output
+    0x0004003b, 7, 2, 3,
+    // %8 = OpConstant %5 1.0
+    0x0004002b, 5, 8, 0x3f800000,
+    // %9 = OpConstant %5 0.0
+    0x0004002b, 5, 9, 0x00000000,
+    // %10 = OpConstantComposite %6 %9 %9 %9 %8
+    0x0007002c, 6, 10, 9, 9, 9, 8,
+    // %1 = OpFunction %3 None %4
+    0x00050036, 3, 1, 0, 4,
+    // %11 = OpLabel
+    0x000200f8, 11,
+    // OpStore %2 %10
+    0x0003003e, 2, 10,
+    // OpReturn
+    0x000100fd,
+    // OpFunctionEnd
+    0x00010038,
+];
+
+/// Get a vertex shader that does not draw anything, used in place of a vertex shader that failed to compile.
+pub(crate) fn null_vertex_shader() -> ShaderCreateInfo {
+    ShaderCreateInfo::from_spirv(vk::ShaderStageFlags::VERTEX, NULL_VERTEX_SPIRV.to_vec())
+}
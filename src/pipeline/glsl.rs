@@ -0,0 +1,124 @@
+//! Runtime GLSL compilation through shaderc, enabled with the `runtime-shaderc` feature.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use ash::vk;
+use shaderc_runtime::{CompileOptions, EnvVersion, IncludeType, ResolvedInclude, ShaderKind, TargetEnv};
+
+use crate::Error;
+use crate::pipeline::shader::{parse_compile_errors, ShaderCompileError, ShaderCreateInfo};
+
+/// Name used in error messages for GLSL source code that did not come from a file.
+const SOURCE_NAME: &str = "<source>";
+
+fn shader_kind(stage: vk::ShaderStageFlags) -> Result<ShaderKind> {
+    Ok(match stage {
+        vk::ShaderStageFlags::VERTEX => ShaderKind::Vertex,
+        vk::ShaderStageFlags::FRAGMENT => ShaderKind::Fragment,
+        vk::ShaderStageFlags::COMPUTE => ShaderKind::Compute,
+        vk::ShaderStageFlags::GEOMETRY => ShaderKind::Geometry,
+        vk::ShaderStageFlags::TESSELLATION_CONTROL => ShaderKind::TessControl,
+        vk::ShaderStageFlags::TESSELLATION_EVALUATION => ShaderKind::TessEvaluation,
+        vk::ShaderStageFlags::RAYGEN_KHR => ShaderKind::RayGeneration,
+        vk::ShaderStageFlags::ANY_HIT_KHR => ShaderKind::AnyHit,
+        vk::ShaderStageFlags::CLOSEST_HIT_KHR => ShaderKind::ClosestHit,
+        vk::ShaderStageFlags::MISS_KHR => ShaderKind::Miss,
+        vk::ShaderStageFlags::INTERSECTION_KHR => ShaderKind::Intersection,
+        vk::ShaderStageFlags::CALLABLE_KHR => ShaderKind::Callable,
+        vk::ShaderStageFlags::TASK_EXT => ShaderKind::Task,
+        vk::ShaderStageFlags::MESH_EXT => ShaderKind::Mesh,
+        _ => return Err(Error::Uncategorized("Shader stage cannot be compiled from GLSL, it must be a single stage.").into()),
+    })
+}
+
+/// Resolve an include directive. Relative includes (`#include "file"`) are first looked up next to the file that includes them,
+/// all includes are then looked up in the include directories in order.
+fn resolve_include(
+    requested: &str,
+    ty: IncludeType,
+    requesting: &str,
+    include_dirs: &[PathBuf],
+) -> Result<ResolvedInclude, String> {
+    let relative_dir = match ty {
+        IncludeType::Relative if requesting != SOURCE_NAME => Path::new(requesting).parent(),
+        _ => None,
+    };
+    let path = relative_dir
+        .into_iter()
+        .chain(include_dirs.iter().map(PathBuf::as_path))
+        .map(|dir| dir.join(requested))
+        .find(|path| path.is_file())
+        .ok_or_else(|| format!("Cannot find include file {requested}"))?;
+    let content = std::fs::read_to_string(&path).map_err(|e| format!("Cannot read include file {}: {e}", path.display()))?;
+    Ok(ResolvedInclude {
+        resolved_name: path.to_string_lossy().into_owned(),
+        content,
+    })
+}
+
+fn compile(stage: vk::ShaderStageFlags, source: &str, name: &str, include_dirs: Vec<PathBuf>) -> Result<ShaderCreateInfo> {
+    let kind = shader_kind(stage)?;
+    let compiler = shaderc_runtime::Compiler::new().ok_or(Error::Uncategorized("Failed to initialize shaderc compiler."))?;
+    let mut options = CompileOptions::new().ok_or(Error::Uncategorized("Failed to initialize shaderc compiler."))?;
+    options.set_target_env(TargetEnv::Vulkan, EnvVersion::Vulkan1_2 as u32);
+    options.set_include_callback(move |requested, ty, requesting, _| {
+        resolve_include(requested, ty, requesting, &include_dirs)
+    });
+    match compiler.compile_into_spirv(source, kind, name, "main", Some(&options)) {
+        Ok(artifact) => {
+            if artifact.get_num_warnings() > 0 {
+                warn!("Shader {name} compiled with warnings:\n{}", artifact.get_warning_messages());
+            }
+            Ok(ShaderCreateInfo::from_spirv(stage, artifact.as_binary().to_vec()))
+        }
+        Err(shaderc_runtime::Error::CompilationError(_, messages)) => {
+            Err(Error::ShaderCompilationFailed(parse_compile_errors(&messages, name)).into())
+        }
+        Err(e) => Err(Error::ShaderCompilationFailed(vec![ShaderCompileError {
+            file: name.to_owned(),
+            line: None,
+            message: e.to_string(),
+        }])
+        .into()),
+    }
+}
+
+impl ShaderCreateInfo {
+    /// Compile GLSL source code to a shader with the `main` entry point. `#include` directives are resolved by searching the
+    /// given include directories in order. This requires the `runtime-shaderc` feature.
+    /// # Errors
+    /// * Fails with [`Error::ShaderCompilationFailed`] if the shader does not compile. This contains the file and line of every error,
+    ///   where errors in the source itself are reported in the file `<source>`.
+    /// * Fails if `stage` is not a single shader stage.
+    /// # Example
+    /// ```
+    /// # use anyhow::Result;
+    /// # use phobos::prelude::*;
+    /// fn fullscreen_shader() -> Result<ShaderCreateInfo> {
+    ///     let source = r#"
+    ///         #version 450
+    ///         #include "common.glsl"
+    ///         layout(location = 0) out vec4 color;
+    ///         void main() { color = vec4(1.0); }
+    ///     "#;
+    ///     ShaderCreateInfo::from_glsl(vk::ShaderStageFlags::FRAGMENT, source, &["shaders/include"])
+    /// }
+    /// ```
+    pub fn from_glsl(stage: vk::ShaderStageFlags, source: &str, includes: &[impl AsRef<Path>]) -> Result<Self> {
+        let include_dirs = includes.iter().map(|dir| dir.as_ref().to_path_buf()).collect();
+        compile(stage, source, SOURCE_NAME, include_dirs)
+    }
+
+    /// Compile a GLSL file to a shader with the `main` entry point. This is the same as [`ShaderCreateInfo::from_glsl()`], except that
+    /// errors refer to the file path, and relative includes are also looked up in the directory of the file.
+    /// # Errors
+    /// * Fails if the file cannot be read.
+    /// * Fails with [`Error::ShaderCompilationFailed`] if the shader does not compile.
+    /// * Fails if `stage` is not a single shader stage.
+    pub fn from_glsl_file(stage: vk::ShaderStageFlags, path: impl AsRef<Path>, includes: &[impl AsRef<Path>]) -> Result<Self> {
+        let source = std::fs::read_to_string(path.as_ref())?;
+        let include_dirs = includes.iter().map(|dir| dir.as_ref().to_path_buf()).collect();
+        compile(stage, &source, &path.as_ref().to_string_lossy(), include_dirs)
+    }
+}
@@ -10,7 +10,11 @@ use crate::ShaderCreateInfo;
 
 impl Hash for ShaderCreateInfo {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        state.write_u64(self.code_hash())
+        state.write_u64(self.code_hash());
+        for (id, data) in self.specialization_constants() {
+            id.hash(state);
+            data.hash(state);
+        }
     }
 }
 
@@ -113,6 +117,27 @@ impl Hash for PipelineTessellationStateCreateInfo {
     }
 }
 
+impl Hash for PipelineFragmentShadingRateStateCreateInfo {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.fragment_size.hash(state);
+        self.0.combiner_ops.hash(state);
+    }
+}
+
+impl Hash for PipelineRasterizationConservativeStateCreateInfo {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.conservative_rasterization_mode.hash(state);
+        self.0.extra_primitive_overestimation_size.to_bits().hash(state);
+    }
+}
+
+impl Hash for NamedImmutableSampler {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        unsafe { self.sampler.handle() }.hash(state);
+    }
+}
+
 impl Hash for Viewport {
     fn hash<H: Hasher>(&self, hasher: &mut H) {
         self.0.x.to_bits().hash(hasher);
@@ -142,6 +167,8 @@ impl Hash for DescriptorSetLayoutCreateInfo {
             binding.stage_flags.hash(state);
             binding.p_immutable_samplers.hash(state);
         }
+        self.flags.hash(state);
+        self.immutable_samplers.hash(state);
     }
 }
 
@@ -178,6 +205,7 @@ impl PartialEq<Self> for PipelineLayoutCreateInfo {
 impl PartialEq<Self> for ShaderCreateInfo {
     fn eq(&self, other: &Self) -> bool {
         self.code_hash() == other.code_hash()
+            && self.specialization_constants().eq(other.specialization_constants())
     }
 }
 
@@ -266,6 +294,26 @@ impl PartialEq<Self> for PipelineTessellationStateCreateInfo {
     }
 }
 
+impl PartialEq<Self> for PipelineFragmentShadingRateStateCreateInfo {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.fragment_size == other.0.fragment_size
+            && self.0.combiner_ops == other.0.combiner_ops
+    }
+}
+
+impl PartialEq<Self> for PipelineRasterizationConservativeStateCreateInfo {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.conservative_rasterization_mode == other.0.conservative_rasterization_mode
+            && self.0.extra_primitive_overestimation_size == other.0.extra_primitive_overestimation_size
+    }
+}
+
+impl PartialEq for NamedImmutableSampler {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && unsafe { self.sampler.handle() == other.sampler.handle() }
+    }
+}
+
 impl PartialEq for Viewport {
     fn eq(&self, other: &Self) -> bool {
         self.0.x == other.0.x
@@ -297,5 +345,10 @@ impl Eq for PipelineColorBlendAttachmentState {}
 
 impl Eq for PipelineTessellationStateCreateInfo {}
 
+impl Eq for PipelineFragmentShadingRateStateCreateInfo {}
+
+impl Eq for PipelineRasterizationConservativeStateCreateInfo {}
+
+impl Eq for NamedImmutableSampler {}
 impl Eq for Viewport {}
 impl Eq for Rect2D {}
@@ -0,0 +1,179 @@
+//! Runtime HLSL compilation through DXC, enabled with the `dxc` feature.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use ash::vk;
+
+use crate::Error;
+use crate::pipeline::shader::{parse_compile_errors, ShaderCompileError, ShaderCreateInfo};
+
+/// Name used in error messages for HLSL source code that did not come from a file.
+const SOURCE_NAME: &str = "<source>";
+
+/// Options for compiling HLSL shaders with [`ShaderCreateInfo::from_hlsl()`].
+/// # Example
+/// ```
+/// # use phobos::prelude::*;
+/// let options = HlslCompileOptions::default()
+///     .entry_point("ps_main")
+///     .shader_model(6, 6)
+///     .define("USE_SHADOWS", None)
+///     .include_dir("shaders/include");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HlslCompileOptions {
+    /// Name of the entry point function. In the resulting SPIR-V, the entry point is always renamed to `main`, since
+    /// that is the entry point used by phobos pipelines. Defaults to `main`.
+    pub entry_point: String,
+    /// Shader model to target, as `(major, minor)`. Defaults to 6.0.
+    pub shader_model: (u32, u32),
+    /// Preprocessor definitions, with an optional value.
+    pub defines: Vec<(String, Option<String>)>,
+    /// Directories to search for `#include` directives.
+    pub include_dirs: Vec<PathBuf>,
+    /// Additional arguments passed to DXC directly.
+    pub args: Vec<String>,
+}
+
+impl Default for HlslCompileOptions {
+    fn default() -> Self {
+        Self {
+            entry_point: String::from("main"),
+            shader_model: (6, 0),
+            defines: vec![],
+            include_dirs: vec![],
+            args: vec![],
+        }
+    }
+}
+
+impl HlslCompileOptions {
+    /// Set the name of the entry point function.
+    pub fn entry_point(mut self, name: impl Into<String>) -> Self {
+        self.entry_point = name.into();
+        self
+    }
+
+    /// Set the shader model to target.
+    pub fn shader_model(mut self, major: u32, minor: u32) -> Self {
+        self.shader_model = (major, minor);
+        self
+    }
+
+    /// Add a preprocessor definition.
+    pub fn define(mut self, name: impl Into<String>, value: Option<&str>) -> Self {
+        self.defines.push((name.into(), value.map(String::from)));
+        self
+    }
+
+    /// Add a directory to search for `#include` directives.
+    pub fn include_dir(mut self, dir: impl AsRef<Path>) -> Self {
+        self.include_dirs.push(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Add an additional argument that is passed to DXC directly.
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Get the DXC target profile for a shader stage, such as `ps_6_0`.
+    fn target_profile(&self, stage: vk::ShaderStageFlags) -> Result<String> {
+        let prefix = match stage {
+            vk::ShaderStageFlags::VERTEX => "vs",
+            vk::ShaderStageFlags::FRAGMENT => "ps",
+            vk::ShaderStageFlags::COMPUTE => "cs",
+            vk::ShaderStageFlags::GEOMETRY => "gs",
+            vk::ShaderStageFlags::TESSELLATION_CONTROL => "hs",
+            vk::ShaderStageFlags::TESSELLATION_EVALUATION => "ds",
+            vk::ShaderStageFlags::TASK_EXT => "as",
+            vk::ShaderStageFlags::MESH_EXT => "ms",
+            _ => {
+                return Err(Error::Uncategorized(
+                    "Shader stage cannot be compiled from HLSL, it must be a single non-raytracing stage.",
+                )
+                .into())
+            }
+        };
+        let (major, minor) = self.shader_model;
+        Ok(format!("{prefix}_{major}_{minor}"))
+    }
+}
+
+fn compile(stage: vk::ShaderStageFlags, source: &str, name: &str, options: &HlslCompileOptions) -> Result<ShaderCreateInfo> {
+    let profile = options.target_profile(stage)?;
+    let mut args = vec![
+        String::from("-spirv"),
+        String::from("-fspv-target-env=vulkan1.2"),
+        String::from("-fspv-entrypoint-name=main"),
+    ];
+    for dir in &options.include_dirs {
+        args.push(String::from("-I"));
+        args.push(dir.to_string_lossy().into_owned());
+    }
+    args.extend(options.args.iter().cloned());
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+    let defines = options
+        .defines
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.as_deref()))
+        .collect::<Vec<_>>();
+
+    match hassle_rs::compile_hlsl(name, source, &options.entry_point, &profile, &args, &defines) {
+        Ok(spirv) => {
+            let code = spirv
+                .chunks_exact(4)
+                .map(|word| u32::from_ne_bytes(word.try_into().unwrap()))
+                .collect();
+            Ok(ShaderCreateInfo::from_spirv(stage, code))
+        }
+        Err(hassle_rs::HassleError::CompileError(messages)) => {
+            Err(Error::ShaderCompilationFailed(parse_compile_errors(&messages, name)).into())
+        }
+        Err(e) => Err(Error::ShaderCompilationFailed(vec![ShaderCompileError {
+            file: name.to_owned(),
+            line: None,
+            message: e.to_string(),
+        }])
+        .into()),
+    }
+}
+
+impl ShaderCreateInfo {
+    /// Compile HLSL source code to a SPIR-V shader using DXC. This requires the `dxc` feature, and the DXC shared library
+    /// (`dxcompiler.dll` or `libdxcompiler.so`) must be available at runtime.
+    /// # Errors
+    /// * Fails with [`Error::ShaderCompilationFailed`] if the shader does not compile, or if DXC could not be loaded.
+    ///   Errors in the source itself are reported in the file `<source>`.
+    /// * Fails if `stage` is not a single shader stage, or is a ray tracing stage.
+    /// # Example
+    /// ```
+    /// # use anyhow::Result;
+    /// # use phobos::prelude::*;
+    /// fn compute_shader() -> Result<ShaderCreateInfo> {
+    ///     let source = r#"
+    ///         RWTexture2D<float4> output : register(u0);
+    ///         [numthreads(8, 8, 1)]
+    ///         void cs_main(uint3 id : SV_DispatchThreadID) { output[id.xy] = float4(1.0, 0.0, 0.0, 1.0); }
+    ///     "#;
+    ///     let options = HlslCompileOptions::default().entry_point("cs_main").shader_model(6, 2);
+    ///     ShaderCreateInfo::from_hlsl(vk::ShaderStageFlags::COMPUTE, source, &options)
+    /// }
+    /// ```
+    pub fn from_hlsl(stage: vk::ShaderStageFlags, source: &str, options: &HlslCompileOptions) -> Result<Self> {
+        compile(stage, source, SOURCE_NAME, options)
+    }
+
+    /// Compile an HLSL file to a SPIR-V shader using DXC. This is the same as [`ShaderCreateInfo::from_hlsl()`], except that
+    /// errors refer to the file path.
+    /// # Errors
+    /// * Fails if the file cannot be read.
+    /// * Fails with [`Error::ShaderCompilationFailed`] if the shader does not compile, or if DXC could not be loaded.
+    /// * Fails if `stage` is not a single shader stage, or is a ray tracing stage.
+    pub fn from_hlsl_file(stage: vk::ShaderStageFlags, path: impl AsRef<Path>, options: &HlslCompileOptions) -> Result<Self> {
+        let source = std::fs::read_to_string(path.as_ref())?;
+        compile(stage, &source, &path.as_ref().to_string_lossy(), options)
+    }
+}
@@ -0,0 +1,178 @@
+//! Graphics pipeline libraries through `VK_EXT_graphics_pipeline_library`.
+//!
+//! When this extension is enabled, the [`PipelineCache`](crate::PipelineCache) does not compile every graphics pipeline as a whole.
+//! Instead, a pipeline is split into its vertex input, pre-rasterization, fragment shader and fragment output parts. Each part is
+//! compiled into a separate library, which is cached using only the state that belongs to that part. Pipelines that for example only
+//! differ in their fragment shader share their other three libraries, and linking the libraries is much cheaper than compiling a
+//! full pipeline.
+
+use ash::vk;
+
+use crate::pipeline::create_info::*;
+use crate::pipeline::pipeline_layout::PipelineLayoutCreateInfo;
+use crate::{PipelineCreateInfo, ShaderCreateInfo};
+
+/// One of the four parts a graphics pipeline is split into.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum PipelineLibraryPart {
+    /// Vertex bindings, attributes and input assembly.
+    VertexInput,
+    /// All shader stages before rasterization, and the rasterization state.
+    PreRasterization,
+    /// The fragment shader and depth stencil state.
+    FragmentShader,
+    /// Color blending and the attachment formats.
+    FragmentOutput,
+}
+
+impl PipelineLibraryPart {
+    /// All parts that are needed to link a pipeline. Mesh shading pipelines have no vertex input stage.
+    pub(crate) fn required(info: &PipelineCreateInfo) -> &'static [PipelineLibraryPart] {
+        use PipelineLibraryPart::*;
+        if info.is_mesh_pipeline() {
+            &[PreRasterization, FragmentShader, FragmentOutput]
+        } else {
+            &[VertexInput, PreRasterization, FragmentShader, FragmentOutput]
+        }
+    }
+
+    pub(crate) fn flags(self) -> vk::GraphicsPipelineLibraryFlagsEXT {
+        match self {
+            PipelineLibraryPart::VertexInput => vk::GraphicsPipelineLibraryFlagsEXT::VERTEX_INPUT_INTERFACE,
+            PipelineLibraryPart::PreRasterization => vk::GraphicsPipelineLibraryFlagsEXT::PRE_RASTERIZATION_SHADERS,
+            PipelineLibraryPart::FragmentShader => vk::GraphicsPipelineLibraryFlagsEXT::FRAGMENT_SHADER,
+            PipelineLibraryPart::FragmentOutput => vk::GraphicsPipelineLibraryFlagsEXT::FRAGMENT_OUTPUT_INTERFACE,
+        }
+    }
+
+    /// Whether a shader stage is compiled as part of this library.
+    pub(crate) fn includes_stage(self, stage: vk::ShaderStageFlags) -> bool {
+        match self {
+            PipelineLibraryPart::PreRasterization => stage != vk::ShaderStageFlags::FRAGMENT,
+            PipelineLibraryPart::FragmentShader => stage == vk::ShaderStageFlags::FRAGMENT,
+            _ => false,
+        }
+    }
+}
+
+/// The state of a pipeline that is used by a single library. Two libraries are equal if this state is equal.
+#[derive(PartialEq, Eq, Hash)]
+enum LibraryState<'a> {
+    VertexInput {
+        bindings: &'a [VertexInputBindingDescription],
+        attributes: &'a [VertexInputAttributeDescription],
+        input_assembly: &'a PipelineInputAssemblyStateCreateInfo,
+        dynamic_states: &'a [vk::DynamicState],
+    },
+    PreRasterization {
+        shaders: Vec<&'a ShaderCreateInfo>,
+        layout: &'a PipelineLayoutCreateInfo,
+        viewports: &'a [Viewport],
+        scissors: &'a [Rect2D],
+        rasterizer: &'a PipelineRasterizationStateCreateInfo,
+        tessellation: &'a Option<PipelineTessellationStateCreateInfo>,
+        fragment_shading_rate: &'a Option<PipelineFragmentShadingRateStateCreateInfo>,
+        conservative_rasterization: &'a Option<PipelineRasterizationConservativeStateCreateInfo>,
+        depth_clip_negative_one_to_one: bool,
+        view_mask: u32,
+        shading_rate_attachment: bool,
+        dynamic_states: &'a [vk::DynamicState],
+    },
+    FragmentShader {
+        shaders: Vec<&'a ShaderCreateInfo>,
+        layout: &'a PipelineLayoutCreateInfo,
+        depth_stencil: &'a PipelineDepthStencilStateCreateInfo,
+        multisample: &'a PipelineMultisampleStateCreateInfo,
+        fragment_shading_rate: &'a Option<PipelineFragmentShadingRateStateCreateInfo>,
+        rendering_info: &'a PipelineRenderingInfo,
+        dynamic_states: &'a [vk::DynamicState],
+    },
+    FragmentOutput {
+        blend_attachments: &'a [PipelineColorBlendAttachmentState],
+        blend_enable_logic_op: bool,
+        multisample: &'a PipelineMultisampleStateCreateInfo,
+        rendering_info: &'a PipelineRenderingInfo,
+        dynamic_states: &'a [vk::DynamicState],
+    },
+}
+
+/// Cache key for a pipeline library. This stores a copy of the full pipeline create info, but only the state used by
+/// the library is hashed and compared.
+#[derive(Debug, Clone)]
+pub struct PipelineLibraryKey {
+    pub(crate) part: PipelineLibraryPart,
+    pub(crate) info: PipelineCreateInfo,
+}
+
+impl PipelineLibraryKey {
+    pub(crate) fn new(part: PipelineLibraryPart, info: &PipelineCreateInfo) -> Self {
+        Self {
+            part,
+            info: info.clone(),
+        }
+    }
+
+    /// Get the shaders that are compiled into this library.
+    pub(crate) fn shaders(&self) -> impl Iterator<Item = &ShaderCreateInfo> {
+        self.info
+            .shaders
+            .iter()
+            .filter(|shader| self.part.includes_stage(shader.stage()))
+    }
+
+    fn state(&self) -> LibraryState<'_> {
+        let info = &self.info;
+        match self.part {
+            PipelineLibraryPart::VertexInput => LibraryState::VertexInput {
+                bindings: &info.vertex_input_bindings,
+                attributes: &info.vertex_attributes,
+                input_assembly: &info.input_assembly,
+                dynamic_states: &info.dynamic_states,
+            },
+            PipelineLibraryPart::PreRasterization => LibraryState::PreRasterization {
+                shaders: self.shaders().collect(),
+                layout: &info.layout,
+                viewports: &info.viewports,
+                scissors: &info.scissors,
+                rasterizer: &info.rasterizer,
+                tessellation: &info.tesselation_info,
+                fragment_shading_rate: &info.fragment_shading_rate,
+                conservative_rasterization: &info.conservative_rasterization,
+                depth_clip_negative_one_to_one: info.depth_clip_negative_one_to_one,
+                view_mask: info.rendering_info.view_mask,
+                shading_rate_attachment: info.rendering_info.shading_rate_attachment,
+                dynamic_states: &info.dynamic_states,
+            },
+            PipelineLibraryPart::FragmentShader => LibraryState::FragmentShader {
+                shaders: self.shaders().collect(),
+                layout: &info.layout,
+                depth_stencil: &info.depth_stencil,
+                multisample: &info.multisample,
+                fragment_shading_rate: &info.fragment_shading_rate,
+                rendering_info: &info.rendering_info,
+                dynamic_states: &info.dynamic_states,
+            },
+            PipelineLibraryPart::FragmentOutput => LibraryState::FragmentOutput {
+                blend_attachments: &info.blend_attachments,
+                blend_enable_logic_op: info.blend_enable_logic_op,
+                multisample: &info.multisample,
+                rendering_info: &info.rendering_info,
+                dynamic_states: &info.dynamic_states,
+            },
+        }
+    }
+}
+
+impl std::hash::Hash for PipelineLibraryKey {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.state().hash(state);
+    }
+}
+
+impl PartialEq for PipelineLibraryKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.state() == other.state()
+    }
+}
+
+impl Eq for PipelineLibraryKey {}
@@ -54,6 +54,8 @@
 use ash::vk;
 
 use crate::{Allocator, Device};
+use crate::pipeline::cache::PipelineCreationFeedback;
+use crate::pipeline::pipeline_layout::PushConstantRange;
 use crate::pipeline::raytracing::ShaderBindingTable;
 
 pub mod builder;
@@ -65,8 +67,16 @@ pub mod pipeline_layout;
 pub mod raytracing;
 pub mod set_layout;
 pub mod shader;
+pub mod shader_object;
 
-pub(crate) mod shader_reflection;
+pub(crate) mod fallback;
+#[cfg(feature = "runtime-shaderc")]
+pub(crate) mod glsl;
+#[cfg(feature = "dxc")]
+pub mod hlsl;
+pub mod library;
+pub mod shader_reflection;
+pub mod vertex;
 
 /// Pipeline stage in the GPU pipeline.
 pub type PipelineStage = vk::PipelineStageFlags2;
@@ -81,6 +91,28 @@ pub struct Pipeline {
     pub(crate) handle: vk::Pipeline,
     pub(crate) layout: vk::PipelineLayout,
     pub(crate) set_layouts: Vec<vk::DescriptorSetLayout>,
+    pub(crate) push_constants: Vec<PushConstantRange>,
+    pub(crate) creation_feedback: PipelineCreationFeedback,
+}
+
+/// One part of a graphics pipeline, compiled separately through `VK_EXT_graphics_pipeline_library`.
+/// These are managed by the pipeline cache and linked into a [`Pipeline`], so they cannot be manually cloned or dropped.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct PipelineLibrary {
+    #[derivative(Debug = "ignore")]
+    device: Device,
+    pub(crate) handle: vk::Pipeline,
+}
+
+/// A single shader compiled through `VK_EXT_shader_object`. These are managed by the pipeline cache and bound instead of a
+/// [`Pipeline`], so they cannot be manually cloned or dropped.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct ShaderObject {
+    #[derivative(Debug = "ignore")]
+    device: Device,
+    pub(crate) handle: vk::ShaderEXT,
 }
 
 /// A fully built Vulkan compute pipeline. This is a managed resource, so it cannot be manually
@@ -93,6 +125,8 @@ pub struct ComputePipeline {
     pub(crate) handle: vk::Pipeline,
     pub(crate) layout: vk::PipelineLayout,
     pub(crate) set_layouts: Vec<vk::DescriptorSetLayout>,
+    pub(crate) push_constants: Vec<PushConstantRange>,
+    pub(crate) creation_feedback: PipelineCreationFeedback,
 }
 
 /// A fully built Vulkan ray tracing pipeline. This is a managed resource, so it cannot be manually
@@ -104,7 +138,9 @@ pub struct RayTracingPipeline<A: Allocator> {
     pub(crate) handle: vk::Pipeline,
     pub(crate) layout: vk::PipelineLayout,
     pub(crate) set_layouts: Vec<vk::DescriptorSetLayout>,
+    pub(crate) push_constants: Vec<PushConstantRange>,
     pub(crate) shader_binding_table: ShaderBindingTable<A>,
+    pub(crate) creation_feedback: PipelineCreationFeedback,
 }
 
 /// Pipeline type.
@@ -129,3 +129,28 @@ impl PushConstantRange {
         }
     }
 }
+
+/// Check whether a push constant update is valid for a pipeline layout with the given push constant ranges. For every byte that
+/// is written, there must be a range containing it for each stage in `stage`, and `stage` must include all stages of every range
+/// that contains it.
+pub(crate) fn is_valid_push_constant_update(
+    ranges: &[PushConstantRange],
+    stage: vk::ShaderStageFlags,
+    offset: u32,
+    size: u32,
+) -> bool {
+    let Some(end) = offset.checked_add(size) else { return false; };
+    if stage.is_empty() || size == 0 || offset % 4 != 0 || size % 4 != 0 {
+        return false;
+    }
+    // Offsets and sizes of push constant ranges are a multiple of 4, so checking each word is enough.
+    (offset..end).step_by(4).all(|byte| {
+        let containing = ranges
+            .iter()
+            .filter(|range| range.offset <= byte && byte - range.offset < range.size);
+        let stages = containing
+            .clone()
+            .fold(vk::ShaderStageFlags::empty(), |stages, range| stages | range.stage_flags);
+        stages.contains(stage) && containing.into_iter().all(|range| stage.contains(range.stage_flags))
+    })
+}
@@ -1,11 +1,11 @@
 //! Wrappers around Vulkan ray tracing pipelines and related objects.
 
-use anyhow::Result;
+use anyhow::{ensure, Result};
 use ash::vk;
 
 use crate::core::device::ExtensionID;
 use crate::pipeline::pipeline_layout::PipelineLayoutCreateInfo;
-use crate::{Allocator, Buffer, Device, MemoryType, ShaderCreateInfo};
+use crate::{Allocator, Buffer, Device, Error, MemoryType, ShaderCreateInfo};
 
 /// An index of a shader in a shader group into the shaders array.
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
@@ -25,6 +25,9 @@ pub(crate) const fn shader_group_index(group: &ShaderGroup) -> u32 {
         ShaderGroup::RayHit {
             ..
         } => 2,
+        ShaderGroup::ProceduralHit {
+            ..
+        } => 2,
         ShaderGroup::Callable {
             ..
         } => 3,
@@ -51,6 +54,15 @@ pub enum ShaderGroup {
         /// Optionally an anyhit shader
         any_hit: Option<ShaderIndex>,
     },
+    /// Specifies a hit group for procedural geometry, with an intersection shader and two optional shaders to be called
+    ProceduralHit {
+        /// The intersection shader, called to test a ray against the primitive in an AABB
+        intersection: ShaderIndex,
+        /// Optionally a closest hit shader
+        closest_hit: Option<ShaderIndex>,
+        /// Optionally an anyhit shader
+        any_hit: Option<ShaderIndex>,
+    },
     /// Specifies a callable shader group,  with a single shader object
     Callable {
         /// The shader to be called
@@ -68,6 +80,10 @@ pub struct SBTEntry {
 }
 
 /// A ShaderBindingTable resource. This can be derived from the ray tracing pipeline.
+///
+/// Each region of the table stores the handles of all shader groups of one type, followed by the shader record data attached to
+/// each group with [`RayTracingPipelineBuilder::shader_record()`]. The stride of a region is large enough to fit the largest
+/// record in it.
 #[allow(dead_code)]
 #[derive(Debug)]
 pub struct ShaderBindingTable<A: Allocator> {
@@ -76,10 +92,15 @@ pub struct ShaderBindingTable<A: Allocator> {
     pub(crate) ray_miss: SBTEntry,
     pub(crate) ray_hit: SBTEntry,
     pub(crate) callable: SBTEntry,
-    pub(crate) group_size: u32,
+    pub(crate) handles: Vec<u8>,
     pub(crate) regions: [vk::StridedDeviceAddressRegionKHR; 4],
 }
 
+/// Round `value` up to a multiple of `alignment`, which must be a power of two.
+fn align_up(value: u64, alignment: u64) -> u64 {
+    (value + (alignment - 1)) & !(alignment - 1)
+}
+
 impl<A: Allocator> ShaderBindingTable<A> {
     pub(crate) fn new(
         device: Device,
@@ -88,151 +109,114 @@ impl<A: Allocator> ShaderBindingTable<A> {
         info: &RayTracingPipelineCreateInfo,
     ) -> Result<Self> {
         device.require_extension(ExtensionID::RayTracingPipeline)?;
+        let properties = device.ray_tracing_properties()?;
         let group_count = info.shader_groups.len() as u32;
-        let group_handle_size = device.ray_tracing_properties()?.shader_group_handle_size;
-        let group_alignment = device.ray_tracing_properties()?.shader_group_base_alignment;
-        let aligned_group_size =
-            (group_handle_size + (group_alignment - 1)) & !(group_alignment - 1);
-        let sbt_size = aligned_group_size * group_count;
-
-        let buffer = Buffer::new(
-            device.clone(),
-            &mut allocator,
-            sbt_size as u64,
-            MemoryType::CpuToGpu,
-        )?;
+        let handle_size = properties.shader_group_handle_size as u64;
         let handles = unsafe {
             device
                 .raytracing_pipeline()
                 .unwrap()
-                .get_ray_tracing_shader_group_handles(pipeline, 0, group_count, sbt_size as usize)?
+                .get_ray_tracing_shader_group_handles(
+                    pipeline,
+                    0,
+                    group_count,
+                    (handle_size * group_count as u64) as usize,
+                )?
         };
 
-        // Copy over handles to the aligned buffer
-        let mut src_pointer = handles.as_ptr();
-        let mut dst_pointer = buffer.view_full().mapped_slice::<u8>()?.as_mut_ptr();
-        for _group in 0..group_count {
-            unsafe {
-                src_pointer.copy_to(dst_pointer, aligned_group_size as usize);
-                src_pointer = src_pointer.add(group_handle_size as usize);
-                dst_pointer = dst_pointer.add(aligned_group_size as usize);
-            }
-        }
-
-        // Now figure out the entry offsets and counts
-        let ray_gen_count = info
-            .shader_groups
-            .iter()
-            .filter(|sh| matches!(sh, ShaderGroup::RayGeneration { .. }))
-            .count() as u64;
-        let ray_miss_count = info
-            .shader_groups
-            .iter()
-            .filter(|sh| matches!(sh, ShaderGroup::RayMiss { .. }))
-            .count() as u64;
-        let ray_hit_count = info
-            .shader_groups
-            .iter()
-            .filter(|sh| matches!(sh, ShaderGroup::RayHit { .. }))
-            .count() as u64;
-        let callable_count = info
-            .shader_groups
-            .iter()
-            .filter(|sh| matches!(sh, ShaderGroup::Callable { .. }))
-            .count() as u64;
-
-        let ray_gen_offset = 0;
-        let ray_miss_offset = if ray_miss_count > 0 {
-            info.shader_groups
+        // Shader groups are sorted by type, so each region is a contiguous range of groups.
+        let entries = [0, 1, 2, 3].map(|ty| {
+            let offset = info
+                .shader_groups
                 .iter()
-                .enumerate()
-                .find(|(_, sh)| matches!(sh, ShaderGroup::RayMiss { .. }))
-                .unwrap()
-                .0 as u32
-        } else {
-            0
-        };
-
-        let ray_hit_offset = if ray_hit_count > 0 {
-            info.shader_groups
+                .position(|group| shader_group_index(group) == ty)
+                .unwrap_or(0);
+            let count = info
+                .shader_groups
                 .iter()
-                .enumerate()
-                .find(|(_, sh)| matches!(sh, ShaderGroup::RayHit { .. }))
-                .unwrap()
-                .0 as u32
-        } else {
-            0
-        };
+                .filter(|group| shader_group_index(group) == ty)
+                .count();
+            SBTEntry {
+                offset: offset as u32,
+                count: count as u32,
+            }
+        });
+        let groups = |entry: &SBTEntry| entry.offset as usize..(entry.offset + entry.count) as usize;
 
-        let callable_offset = if callable_count > 0 {
-            info.shader_groups
+        // Every entry in a region has the same stride, which must fit the handle and the largest shader record.
+        let strides = entries.each_ref().map(|entry| {
+            let record_size = info.shader_records[groups(entry)]
                 .iter()
-                .enumerate()
-                .find(|(_, sh)| matches!(sh, ShaderGroup::Callable { .. }))
-                .unwrap()
-                .0 as u32
-        } else {
-            0
-        };
-
-        let address = buffer.address();
+                .map(|record| record.len() as u64)
+                .max()
+                .unwrap_or(0);
+            align_up(handle_size + record_size, properties.shader_group_handle_alignment as u64)
+        });
+        for stride in strides {
+            ensure!(
+                stride <= properties.max_shader_group_stride as u64,
+                "shader binding table stride {stride} exceeds the maximum of {}. Shader record data is too large.",
+                properties.max_shader_group_stride
+            );
+        }
+        let mut offsets = [0u64; 4];
+        let mut sbt_size = 0;
+        for (index, entry) in entries.iter().enumerate() {
+            offsets[index] = align_up(sbt_size, properties.shader_group_base_alignment as u64);
+            sbt_size = offsets[index] + strides[index] * entry.count as u64;
+        }
 
-        let size = aligned_group_size as u64;
-        let stride = aligned_group_size as vk::DeviceSize;
+        let buffer = Buffer::new(device.clone(), &mut allocator, sbt_size, MemoryType::CpuToGpu)?;
+        let mut view = buffer.view_full();
+        let memory = view.mapped_slice::<u8>()?;
+        for (index, entry) in entries.iter().enumerate() {
+            for (i, group) in groups(entry).enumerate() {
+                let start = (offsets[index] + strides[index] * i as u64) as usize;
+                let handle = &handles[group * handle_size as usize..(group + 1) * handle_size as usize];
+                let record = &info.shader_records[group];
+                memory[start..start + handle.len()].copy_from_slice(handle);
+                memory[start + handle.len()..start + handle.len() + record.len()].copy_from_slice(record);
+            }
+        }
 
-        let regions: [vk::StridedDeviceAddressRegionKHR; 4] = [
-            vk::StridedDeviceAddressRegionKHR {
-                device_address: address,
-                stride,
-                size: (size * ray_gen_count) as vk::DeviceSize,
-            },
-            vk::StridedDeviceAddressRegionKHR {
-                device_address: address + ray_miss_offset as u64 * size,
-                stride,
-                size: (size * ray_miss_count) as vk::DeviceSize,
-            },
-            vk::StridedDeviceAddressRegionKHR {
-                device_address: address + ray_hit_offset as u64 * size,
-                stride,
-                size: (size * ray_hit_count) as vk::DeviceSize,
-            },
-            vk::StridedDeviceAddressRegionKHR {
-                device_address: if callable_count > 0 {
-                    address + callable_offset as u64 * size
-                } else {
-                    0
-                },
-                stride: if callable_count > 0 {
-                    stride
-                } else {
-                    0
-                },
-                size: (callable_count * size) as vk::DeviceSize,
-            },
-        ];
+        let address = buffer.address();
+        let mut regions = [vk::StridedDeviceAddressRegionKHR::default(); 4];
+        for (index, entry) in entries.iter().enumerate() {
+            if entry.count > 0 {
+                regions[index] = vk::StridedDeviceAddressRegionKHR {
+                    device_address: address + offsets[index],
+                    stride: strides[index],
+                    size: strides[index] * entry.count as u64,
+                };
+            }
+        }
+        // The ray generation region must contain exactly one entry.
+        regions[0].size = regions[0].stride;
 
+        let [ray_gen, ray_miss, ray_hit, callable] = entries;
         Ok(ShaderBindingTable {
             buffer,
-            ray_gen: SBTEntry {
-                offset: ray_gen_offset,
-                count: ray_gen_count as u32,
-            },
-            ray_miss: SBTEntry {
-                offset: ray_miss_offset,
-                count: ray_miss_count as u32,
-            },
-            ray_hit: SBTEntry {
-                offset: ray_hit_offset,
-                count: ray_hit_count as u32,
-            },
-            callable: SBTEntry {
-                offset: callable_offset,
-                count: callable_count as u32,
-            },
-            group_size: aligned_group_size,
+            ray_gen,
+            ray_miss,
+            ray_hit,
+            callable,
+            handles,
             regions,
         })
     }
+
+    /// Get the shader group handles of the pipeline, as returned by `vkGetRayTracingShaderGroupHandlesKHR`. Each handle is
+    /// `shaderGroupHandleSize` bytes, and the groups are ordered by type: ray generation, miss, hit and callable groups.
+    /// These can be used to build a custom shader binding table.
+    pub fn group_handles(&self) -> &[u8] {
+        &self.handles
+    }
+
+    /// Get the regions of this shader binding table, in the order expected by `vkCmdTraceRaysKHR`: ray generation, miss,
+    /// hit and callable regions.
+    pub fn regions(&self) -> &[vk::StridedDeviceAddressRegionKHR; 4] {
+        &self.regions
+    }
 }
 
 /// Ray tracing pipeline create info. Prefer using the buidler to construct this correctly
@@ -242,6 +226,8 @@ pub struct RayTracingPipelineCreateInfo {
     pub(crate) layout: PipelineLayoutCreateInfo,
     pub(crate) max_recursion_depth: u32,
     pub(crate) shader_groups: Vec<ShaderGroup>,
+    /// Shader record data of each shader group, stored after its handle in the shader binding table.
+    pub(crate) shader_records: Vec<Vec<u8>>,
     /// All shaders used. These must always be sorted by their type.
     pub shaders: Vec<ShaderCreateInfo>,
 }
@@ -282,6 +268,7 @@ impl RayTracingPipelineBuilder {
                 layout: Default::default(),
                 max_recursion_depth: 0,
                 shader_groups: vec![],
+                shader_records: vec![],
                 shaders: vec![],
             },
         }
@@ -294,7 +281,7 @@ impl RayTracingPipelineBuilder {
             .shaders
             .iter()
             .enumerate()
-            .find(|(_, sh)| sh.code_hash() == shader.code_hash())
+            .find(|(_, sh)| **sh == shader)
         {
             ShaderIndex {
                 index: idx as u32,
@@ -310,6 +297,7 @@ impl RayTracingPipelineBuilder {
     /// Add a shader group
     pub fn add_shader_group(mut self, group: ShaderGroup) -> Self {
         self.inner.shader_groups.push(group);
+        self.inner.shader_records.push(vec![]);
         self
     }
 
@@ -319,6 +307,7 @@ impl RayTracingPipelineBuilder {
         self.inner.shader_groups.push(ShaderGroup::RayGeneration {
             shader,
         });
+        self.inner.shader_records.push(vec![]);
         self
     }
 
@@ -328,6 +317,7 @@ impl RayTracingPipelineBuilder {
         self.inner.shader_groups.push(ShaderGroup::RayMiss {
             shader,
         });
+        self.inner.shader_records.push(vec![]);
         self
     }
 
@@ -343,6 +333,27 @@ impl RayTracingPipelineBuilder {
             closest_hit,
             any_hit,
         });
+        self.inner.shader_records.push(vec![]);
+        self
+    }
+
+    /// Add a ray hit group for procedural geometry. The intersection shader is called for rays that hit an AABB
+    /// in the acceleration structure, the other two shaders are optional.
+    pub fn add_procedural_hit_group(
+        mut self,
+        intersection: ShaderCreateInfo,
+        closest_hit: Option<ShaderCreateInfo>,
+        any_hit: Option<ShaderCreateInfo>,
+    ) -> Self {
+        let intersection = self.add_shader(intersection);
+        let closest_hit = closest_hit.map(|sh| self.add_shader(sh));
+        let any_hit = any_hit.map(|sh| self.add_shader(sh));
+        self.inner.shader_groups.push(ShaderGroup::ProceduralHit {
+            intersection,
+            closest_hit,
+            any_hit,
+        });
+        self.inner.shader_records.push(vec![]);
         self
     }
 
@@ -352,9 +363,49 @@ impl RayTracingPipelineBuilder {
         self.inner.shader_groups.push(ShaderGroup::Callable {
             shader,
         });
+        self.inner.shader_records.push(vec![]);
         self
     }
 
+    /// Attach shader record data to the most recently added shader group. This data is stored right after the group's handle in
+    /// the shader binding table, and can be read in the shader through a `shaderRecordEXT` buffer block. This allows passing
+    /// parameters to each hit group without going through descriptors.
+    /// # Errors
+    /// * Fails if no shader group was added yet.
+    /// # Example
+    /// ```
+    /// # use anyhow::Result;
+    /// # use phobos::prelude::*;
+    /// # use phobos::pipeline::raytracing::RayTracingPipelineBuilder;
+    /// #[repr(C)]
+    /// #[derive(Copy, Clone)]
+    /// struct Material {
+    ///     albedo: [f32; 4],
+    ///     texture_index: u32,
+    /// }
+    ///
+    /// fn material_groups(closest_hit: ShaderCreateInfo) -> Result<RayTracingPipelineBuilder> {
+    ///     let red = Material { albedo: [1.0, 0.0, 0.0, 1.0], texture_index: 0 };
+    ///     let blue = Material { albedo: [0.0, 0.0, 1.0, 1.0], texture_index: 1 };
+    ///     RayTracingPipelineBuilder::new("rt")
+    ///         .add_ray_hit_group(Some(closest_hit.clone()), None)
+    ///         .shader_record(&red)?
+    ///         .add_ray_hit_group(Some(closest_hit), None)
+    ///         .shader_record(&blue)
+    /// }
+    /// ```
+    pub fn shader_record<T: Copy>(mut self, data: &T) -> Result<Self> {
+        let record = self
+            .inner
+            .shader_records
+            .last_mut()
+            .ok_or(Error::Uncategorized("Cannot add shader record data without a shader group"))?;
+        // SAFETY: every data structure can be aligned to a byte slice.
+        let (_, data, _) = unsafe { std::slice::from_ref(data).align_to::<u8>() };
+        *record = data.to_vec();
+        Ok(self)
+    }
+
     /// Set the max recursion depth for this pipeline
     pub fn max_recursion_depth(mut self, depth: u32) -> Self {
         self.inner.max_recursion_depth = depth;
@@ -368,10 +419,15 @@ impl RayTracingPipelineBuilder {
 
     /// Build the pipeline create info
     pub fn build(mut self) -> RayTracingPipelineCreateInfo {
-        // Sort shader groups by type
-        self.inner
+        // Sort shader groups by type, keeping their shader records with them.
+        let mut groups = self
+            .inner
             .shader_groups
-            .sort_by_key(|group| shader_group_index(group));
+            .drain(..)
+            .zip(self.inner.shader_records.drain(..))
+            .collect::<Vec<_>>();
+        groups.sort_by_key(|(group, _)| shader_group_index(group));
+        (self.inner.shader_groups, self.inner.shader_records) = groups.into_iter().unzip();
         self.inner
     }
 }
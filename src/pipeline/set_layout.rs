@@ -1,10 +1,10 @@
 //! Exposes wrappers for `VkDescriptorSetLayout` objects.
 
-use anyhow::Result;
+use anyhow::{ensure, Result};
 use ash::vk;
 
 use crate::util::cache::{Resource, ResourceKey};
-use crate::Device;
+use crate::{Device, Error};
 
 /// A fully built Vulkan descriptor set layout. This is a managed resource, so it cannot be manually
 /// created or dropped.
@@ -25,6 +25,16 @@ impl DescriptorSetLayout {
     }
 }
 
+/// Immutable samplers for a single binding in a descriptor set layout. Immutable samplers are baked into the set layout,
+/// so they never need to be bound. The samplers must stay alive for as long as the set layout is in use.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ImmutableSamplerBinding {
+    /// Binding that uses these samplers. Must be a [`vk::DescriptorType::SAMPLER`] or [`vk::DescriptorType::COMBINED_IMAGE_SAMPLER`] binding.
+    pub binding: u32,
+    /// One sampler for each array element of the binding. A single sampler is used for all array elements.
+    pub samplers: Vec<vk::Sampler>,
+}
+
 /// Describes a descriptor set layout.
 /// Generally you don't need to construct this manually, as shader reflection can infer all
 /// information necessary.
@@ -36,7 +46,12 @@ pub struct DescriptorSetLayoutCreateInfo {
     /// this belongs to is also persistent.
     pub persistent: bool,
     /// The binding flags for each binding, these are set separately because they go in a separate vulkan struct.
+    /// Flags that require optional device features (`UPDATE_AFTER_BIND` and `VARIABLE_DESCRIPTOR_COUNT`) are silently dropped
+    /// if the device does not support them. If any binding is `UPDATE_AFTER_BIND`, the layout is created with
+    /// [`vk::DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND_POOL`].
     pub flags: Vec<vk::DescriptorBindingFlags>,
+    /// Immutable samplers for bindings in this set layout.
+    pub immutable_samplers: Vec<ImmutableSamplerBinding>,
 }
 
 impl ResourceKey for DescriptorSetLayoutCreateInfo {
@@ -46,21 +61,115 @@ impl ResourceKey for DescriptorSetLayoutCreateInfo {
     }
 }
 
+/// Whether descriptors of this type can be updated after binding the set, given the enabled device features.
+pub(crate) fn supports_update_after_bind(device: &Device, ty: vk::DescriptorType) -> bool {
+    let features = device.features_1_2();
+    match ty {
+        vk::DescriptorType::SAMPLER
+        | vk::DescriptorType::COMBINED_IMAGE_SAMPLER
+        | vk::DescriptorType::SAMPLED_IMAGE => {
+            features.descriptor_binding_sampled_image_update_after_bind == vk::TRUE
+        }
+        vk::DescriptorType::STORAGE_IMAGE => {
+            features.descriptor_binding_storage_image_update_after_bind == vk::TRUE
+        }
+        vk::DescriptorType::UNIFORM_BUFFER => {
+            features.descriptor_binding_uniform_buffer_update_after_bind == vk::TRUE
+        }
+        vk::DescriptorType::STORAGE_BUFFER => {
+            features.descriptor_binding_storage_buffer_update_after_bind == vk::TRUE
+        }
+        _ => false,
+    }
+}
+
+/// Remove binding flags that the device does not support, so bindless layouts still work (with a fixed descriptor count)
+/// on devices without the optional descriptor indexing features.
+fn supported_binding_flags(
+    device: &Device,
+    binding: &vk::DescriptorSetLayoutBinding,
+    mut flags: vk::DescriptorBindingFlags,
+) -> vk::DescriptorBindingFlags {
+    if !supports_update_after_bind(device, binding.descriptor_type) {
+        flags &= !vk::DescriptorBindingFlags::UPDATE_AFTER_BIND;
+    }
+    if device.features_1_2().descriptor_binding_variable_descriptor_count != vk::TRUE {
+        flags &= !vk::DescriptorBindingFlags::VARIABLE_DESCRIPTOR_COUNT;
+    }
+    flags
+}
+
 impl Resource for DescriptorSetLayout {
     type Key = DescriptorSetLayoutCreateInfo;
     type ExtraParams<'a> = ();
     const MAX_TIME_TO_LIVE: u32 = 8;
 
     fn create(device: Device, key: &Self::Key, _: Self::ExtraParams<'_>) -> Result<Self> {
+        let binding_flags = key
+            .bindings
+            .iter()
+            .zip(&key.flags)
+            .map(|(binding, flags)| supported_binding_flags(&device, binding, *flags))
+            .collect::<Vec<_>>();
+        // Update-after-bind bindings may only be allocated from update-after-bind pools.
+        let create_flags = if binding_flags
+            .iter()
+            .any(|flags| flags.contains(vk::DescriptorBindingFlags::UPDATE_AFTER_BIND))
+        {
+            vk::DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND_POOL
+        } else {
+            vk::DescriptorSetLayoutCreateFlags::empty()
+        };
+
+        // Expand immutable samplers to one per array element, and point the bindings to them.
+        let immutable_samplers = key
+            .immutable_samplers
+            .iter()
+            .map(|immutable| {
+                let binding = key
+                    .bindings
+                    .iter()
+                    .find(|binding| binding.binding == immutable.binding)
+                    .ok_or_else(|| Error::NoBinding(immutable.binding.to_string()))?;
+                let samplers = match immutable.samplers.as_slice() {
+                    [sampler] => vec![*sampler; binding.descriptor_count as usize],
+                    samplers => samplers.to_vec(),
+                };
+                ensure!(
+                    samplers.len() == binding.descriptor_count as usize,
+                    "binding {} has {} descriptors, but {} immutable samplers were given",
+                    binding.binding,
+                    binding.descriptor_count,
+                    samplers.len()
+                );
+                Ok((immutable.binding, samplers))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let bindings = key
+            .bindings
+            .iter()
+            .map(|binding| {
+                let mut binding = *binding;
+                if let Some((_, samplers)) = immutable_samplers
+                    .iter()
+                    .find(|(index, _)| *index == binding.binding)
+                {
+                    binding.p_immutable_samplers = samplers.as_ptr();
+                }
+                binding
+            })
+            .collect::<Vec<_>>();
+
         let mut flags = vk::DescriptorSetLayoutBindingFlagsCreateInfo {
             s_type: vk::StructureType::DESCRIPTOR_SET_LAYOUT_BINDING_FLAGS_CREATE_INFO,
             p_next: std::ptr::null(),
-            binding_count: key.bindings.len() as u32,
-            p_binding_flags: key.flags.as_ptr(),
+            binding_count: binding_flags.len() as u32,
+            p_binding_flags: binding_flags.as_ptr(),
         };
 
         let info = vk::DescriptorSetLayoutCreateInfo::builder()
-            .bindings(key.bindings.as_slice())
+            .flags(create_flags)
+            .bindings(bindings.as_slice())
             .push_next(&mut flags)
             .build();
         let handle = unsafe { device.create_descriptor_set_layout(&info, None)? };
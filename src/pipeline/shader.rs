@@ -1,10 +1,13 @@
 //! Exposes wrappers for `VkShaderModule` objects.
 
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
 
 use anyhow::Result;
 use ash::vk;
+use bytemuck::NoUninit;
 
 use crate::util::cache::{Resource, ResourceKey};
 use crate::Device;
@@ -33,6 +36,7 @@ pub struct ShaderCreateInfo {
     stage: vk::ShaderStageFlags,
     code: Vec<u32>,
     code_hash: u64,
+    specialization: BTreeMap<u32, Vec<u8>>,
     pub(crate) persistent: bool,
 }
 
@@ -51,6 +55,82 @@ impl ShaderCreateInfo {
     pub fn code_hash(&self) -> u64 {
         self.code_hash
     }
+
+    /// Set the value of a specialization constant, given as raw bytes. Specialization constants are part of the pipeline hash, so
+    /// pipelines that only differ in their specialization constants are cached separately.
+    pub fn set_specialization_data(&mut self, id: u32, data: Vec<u8>) {
+        self.specialization.insert(id, data);
+    }
+
+    /// Set the value of a specialization constant. Note that `bool` constants must be given as a 32-bit [`vk::Bool32`].
+    /// # Example
+    /// ```
+    /// # use phobos::prelude::*;
+    /// fn specialize(shader: ShaderCreateInfo) -> ShaderCreateInfo {
+    ///     // layout(local_size_x_id = 0) in;
+    ///     // layout(constant_id = 1) const uint QUALITY = 0;
+    ///     shader
+    ///         .specialization_constant(0, &64u32)
+    ///         .specialization_constant(1, &2u32)
+    /// }
+    /// ```
+    pub fn specialization_constant<T: NoUninit>(mut self, id: u32, value: &T) -> Self {
+        self.set_specialization_data(id, bytemuck::bytes_of(value).to_vec());
+        self
+    }
+
+    /// Get all specialization constants of this shader, sorted by their id.
+    pub fn specialization_constants(&self) -> impl Iterator<Item = (u32, &[u8])> {
+        self.specialization.iter().map(|(id, data)| (*id, data.as_slice()))
+    }
+
+    /// Get the key used to cache the shader module. Specialization constants are only applied when creating a pipeline, so shaders
+    /// that differ only in their specialization constants share a shader module.
+    pub(crate) fn module_key(&self) -> Cow<'_, ShaderCreateInfo> {
+        if self.specialization.is_empty() {
+            Cow::Borrowed(self)
+        } else {
+            let mut key = self.clone();
+            key.specialization.clear();
+            Cow::Owned(key)
+        }
+    }
+
+    /// Collect the specialization constants in the layout Vulkan expects.
+    pub(crate) fn specialization_data(&self) -> SpecializationData {
+        let mut data = SpecializationData::default();
+        for (id, value) in &self.specialization {
+            data.entries.push(vk::SpecializationMapEntry {
+                constant_id: *id,
+                offset: data.data.len() as u32,
+                size: value.len(),
+            });
+            data.data.extend_from_slice(value);
+        }
+        data
+    }
+}
+
+/// Owns the map entries and data referenced by a [`vk::SpecializationInfo`].
+#[derive(Debug, Default)]
+pub(crate) struct SpecializationData {
+    entries: Vec<vk::SpecializationMapEntry>,
+    data: Vec<u8>,
+}
+
+impl SpecializationData {
+    /// Get the Vulkan specialization info, or `None` if there are no specialization constants. The returned value borrows from `self`.
+    pub(crate) fn info(&self) -> Option<vk::SpecializationInfo> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        Some(vk::SpecializationInfo {
+            map_entry_count: self.entries.len() as u32,
+            p_map_entries: self.entries.as_ptr(),
+            data_size: self.data.len(),
+            p_data: self.data.as_ptr().cast(),
+        })
+    }
 }
 
 impl ResourceKey for ShaderCreateInfo {
@@ -95,6 +175,71 @@ impl Drop for Shader {
     }
 }
 
+/// A single error reported while compiling a shader from source, such as with
+/// [`ShaderCreateInfo::from_glsl()`](ShaderCreateInfo::from_glsl) or [`ShaderCreateInfo::from_hlsl()`](ShaderCreateInfo::from_hlsl).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShaderCompileError {
+    /// Name of the file the error occurred in. For included files, this is the resolved path of the include.
+    pub file: String,
+    /// Line the error occurred on, if the compiler reported one.
+    pub line: Option<u32>,
+    /// The error message.
+    pub message: String,
+}
+
+impl std::fmt::Display for ShaderCompileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            None => write!(f, "{}: {}", self.file, self.message),
+            Some(line) => write!(f, "{}:{}: {}", self.file, line, self.message),
+        }
+    }
+}
+
+/// Parse the diagnostic output of a shader compiler into a list of errors. Both shaderc and DXC report errors as
+/// `file:line: error: message` or `file:line:column: error: message`. Any other lines, such as source excerpts and summaries,
+/// are skipped. If no error lines could be found, the entire output is returned as a single error in `default_file`.
+#[cfg(any(feature = "runtime-shaderc", feature = "dxc"))]
+pub(crate) fn parse_compile_errors(output: &str, default_file: &str) -> Vec<ShaderCompileError> {
+    let errors = output
+        .lines()
+        .filter_map(|line| {
+            let (location, message) = [": fatal error: ", ": error: "]
+                .iter()
+                .find_map(|marker| line.split_once(marker))?;
+            // The file name may contain colons, so strip the column and line numbers from the back.
+            let mut file = location;
+            let mut line = None;
+            for _ in 0..2 {
+                match file
+                    .rsplit_once(':')
+                    .and_then(|(head, number)| Some((head, number.trim().parse::<u32>().ok()?)))
+                {
+                    Some((head, number)) => {
+                        file = head;
+                        line = Some(number);
+                    }
+                    None => break,
+                }
+            }
+            Some(ShaderCompileError {
+                file: file.to_owned(),
+                line,
+                message: message.trim().to_owned(),
+            })
+        })
+        .collect::<Vec<_>>();
+    if errors.is_empty() {
+        vec![ShaderCompileError {
+            file: default_file.to_owned(),
+            line: None,
+            message: output.trim().to_owned(),
+        }]
+    } else {
+        errors
+    }
+}
+
 impl ShaderCreateInfo {
     /// Load in a spirv binary into a shader create info structure.
     pub fn from_spirv(stage: vk::ShaderStageFlags, code: Vec<u32>) -> Self {
@@ -104,6 +249,7 @@ impl ShaderCreateInfo {
             stage,
             code,
             code_hash: hasher.finish(),
+            specialization: BTreeMap::new(),
             persistent: false,
         }
     }
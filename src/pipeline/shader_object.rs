@@ -0,0 +1,331 @@
+//! Binding shaders directly through `VK_EXT_shader_object`.
+//!
+//! Graphics pipelines created with [`PipelineBuilder::shader_objects()`](crate::PipelineBuilder::shader_objects) are not compiled
+//! into a `VkPipeline` when shader objects are enabled. Instead, each shader is compiled into a separate `VkShaderEXT`, and binding
+//! the pipeline binds these shaders and sets all state from the pipeline create info dynamically. Shaders are cached per stage,
+//! so pipelines that only differ in their state or attachment formats never need to compile anything new.
+//!
+//! State that was added as a dynamic state with [`PipelineBuilder::dynamic_state()`](crate::PipelineBuilder::dynamic_state) is
+//! not set when binding, and must be set on the command buffer as usual.
+
+use anyhow::Result;
+use ash::vk;
+
+use crate::core::device::ExtensionID;
+use crate::pipeline::pipeline_layout::PipelineLayoutCreateInfo;
+use crate::{Device, Error, PipelineCreateInfo, ShaderCreateInfo};
+
+/// Cache key for a single shader object. Unlike a shader module, a shader object is compiled with its specialization constants,
+/// the stage that follows it and the pipeline layout, so all of these are part of the key.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ShaderObjectKey {
+    pub(crate) shader: ShaderCreateInfo,
+    pub(crate) next_stage: vk::ShaderStageFlags,
+    pub(crate) flags: vk::ShaderCreateFlagsEXT,
+    pub(crate) layout: PipelineLayoutCreateInfo,
+}
+
+impl ShaderObjectKey {
+    /// Get the keys of all shader objects used by a pipeline.
+    pub(crate) fn for_pipeline(info: &PipelineCreateInfo) -> Vec<Self> {
+        let has_stage = |stage| info.shaders.iter().any(|shader| shader.stage() == stage);
+        info.shaders
+            .iter()
+            .map(|shader| {
+                let next_stage = match shader.stage() {
+                    vk::ShaderStageFlags::VERTEX if info.is_tessellation_pipeline() => {
+                        vk::ShaderStageFlags::TESSELLATION_CONTROL
+                    }
+                    vk::ShaderStageFlags::TESSELLATION_CONTROL => vk::ShaderStageFlags::TESSELLATION_EVALUATION,
+                    vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::TESSELLATION_EVALUATION
+                        if info.has_geometry_shader() =>
+                    {
+                        vk::ShaderStageFlags::GEOMETRY
+                    }
+                    vk::ShaderStageFlags::TASK_EXT => vk::ShaderStageFlags::MESH_EXT,
+                    vk::ShaderStageFlags::FRAGMENT => vk::ShaderStageFlags::empty(),
+                    _ => vk::ShaderStageFlags::FRAGMENT,
+                };
+                // A mesh shader that is not preceded by a task shader must say so, see VUID-VkShaderCreateInfoEXT-flags-08414
+                let flags = match shader.stage() {
+                    vk::ShaderStageFlags::MESH_EXT if !has_stage(vk::ShaderStageFlags::TASK_EXT) => {
+                        vk::ShaderCreateFlagsEXT::NO_TASK_SHADER
+                    }
+                    _ => vk::ShaderCreateFlagsEXT::empty(),
+                };
+                Self {
+                    shader: shader.clone(),
+                    next_stage,
+                    flags,
+                    layout: info.layout.clone(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Everything needed to bind a pipeline that uses shader objects.
+pub(crate) struct ShaderObjectBinding<'a> {
+    pub info: &'a PipelineCreateInfo,
+    pub shaders: Vec<(vk::ShaderStageFlags, vk::ShaderEXT)>,
+    pub layout: vk::PipelineLayout,
+    pub set_layouts: Vec<vk::DescriptorSetLayout>,
+}
+
+impl ShaderObjectBinding<'_> {
+    /// Bind the shaders, and set all state of the pipeline that is not a dynamic state. Dynamic viewports and scissors are set to
+    /// `render_area`, so they are valid even if they are not set later.
+    /// # Safety
+    /// * `cmd` must be a valid command buffer in the recording state, created from `device`.
+    /// * The shader objects and layouts in this binding must be valid.
+    pub(crate) unsafe fn record(&self, device: &Device, cmd: vk::CommandBuffer, render_area: vk::Rect2D) -> Result<()> {
+        let funcs = device
+            .shader_object()
+            .ok_or(Error::ExtensionNotSupported(ExtensionID::ShaderObject))?;
+        let info = self.info;
+        let dynamic = |state| info.dynamic_states.contains(&state);
+        let features = device.features();
+
+        // Every graphics stage the device supports must have a shader bound, unused stages are bound to a null shader.
+        let mut stages = vec![vk::ShaderStageFlags::VERTEX, vk::ShaderStageFlags::FRAGMENT];
+        if features.tessellation_shader == vk::TRUE {
+            stages.push(vk::ShaderStageFlags::TESSELLATION_CONTROL);
+            stages.push(vk::ShaderStageFlags::TESSELLATION_EVALUATION);
+        }
+        if features.geometry_shader == vk::TRUE {
+            stages.push(vk::ShaderStageFlags::GEOMETRY);
+        }
+        if device.is_extension_enabled(ExtensionID::MeshShader) {
+            stages.push(vk::ShaderStageFlags::TASK_EXT);
+            stages.push(vk::ShaderStageFlags::MESH_EXT);
+        }
+        let shaders = stages
+            .iter()
+            .map(|stage| {
+                self.shaders
+                    .iter()
+                    .find(|(shader_stage, _)| shader_stage == stage)
+                    .map_or(vk::ShaderEXT::null(), |(_, shader)| *shader)
+            })
+            .collect::<Vec<_>>();
+        funcs.cmd_bind_shaders(cmd, &stages, &shaders);
+
+        let full_viewport = vk::Viewport {
+            x: render_area.offset.x as f32,
+            y: render_area.offset.y as f32,
+            width: render_area.extent.width as f32,
+            height: render_area.extent.height as f32,
+            min_depth: 0.0,
+            max_depth: 1.0,
+        };
+        let viewports = match dynamic(vk::DynamicState::VIEWPORT) || info.viewports.is_empty() {
+            true => vec![full_viewport; info.viewports.len().max(1)],
+            false => info.viewports.iter().map(|viewport| viewport.0).collect(),
+        };
+        let scissors = match dynamic(vk::DynamicState::SCISSOR) || info.scissors.is_empty() {
+            true => vec![render_area; info.scissors.len().max(1)],
+            false => info.scissors.iter().map(|scissor| scissor.0).collect(),
+        };
+        funcs.cmd_set_viewport_with_count(cmd, &viewports);
+        funcs.cmd_set_scissor_with_count(cmd, &scissors);
+
+        if !info.is_mesh_pipeline() {
+            let input_assembly = &info.input_assembly.0;
+            if !dynamic(vk::DynamicState::VERTEX_INPUT_EXT) {
+                let bindings = info
+                    .vertex_input_bindings
+                    .iter()
+                    .map(|binding| vk::VertexInputBindingDescription2EXT {
+                        binding: binding.0.binding,
+                        stride: binding.0.stride,
+                        input_rate: binding.0.input_rate,
+                        divisor: 1,
+                        ..Default::default()
+                    })
+                    .collect::<Vec<_>>();
+                let attributes = info
+                    .vertex_attributes
+                    .iter()
+                    .map(|attribute| vk::VertexInputAttributeDescription2EXT {
+                        location: attribute.0.location,
+                        binding: attribute.0.binding,
+                        format: attribute.0.format,
+                        offset: attribute.0.offset,
+                        ..Default::default()
+                    })
+                    .collect::<Vec<_>>();
+                funcs.cmd_set_vertex_input(cmd, &bindings, &attributes);
+            }
+            if !dynamic(vk::DynamicState::PRIMITIVE_TOPOLOGY) {
+                funcs.cmd_set_primitive_topology(cmd, input_assembly.topology);
+            }
+            if !dynamic(vk::DynamicState::PRIMITIVE_RESTART_ENABLE) {
+                funcs.cmd_set_primitive_restart_enable(cmd, input_assembly.primitive_restart_enable == vk::TRUE);
+            }
+        }
+
+        if let Some(tessellation) = &info.tesselation_info {
+            if !dynamic(vk::DynamicState::PATCH_CONTROL_POINTS_EXT) {
+                funcs.cmd_set_patch_control_points(cmd, tessellation.0.patch_control_points);
+            }
+            if !dynamic(vk::DynamicState::TESSELLATION_DOMAIN_ORIGIN_EXT) {
+                funcs.cmd_set_tessellation_domain_origin(cmd, vk::TessellationDomainOrigin::UPPER_LEFT);
+            }
+        }
+
+        let rasterizer = &info.rasterizer.0;
+        if !dynamic(vk::DynamicState::RASTERIZER_DISCARD_ENABLE) {
+            funcs.cmd_set_rasterizer_discard_enable(cmd, rasterizer.rasterizer_discard_enable == vk::TRUE);
+        }
+        if !dynamic(vk::DynamicState::POLYGON_MODE_EXT) {
+            funcs.cmd_set_polygon_mode(cmd, rasterizer.polygon_mode);
+        }
+        if !dynamic(vk::DynamicState::CULL_MODE) {
+            funcs.cmd_set_cull_mode(cmd, rasterizer.cull_mode);
+        }
+        if !dynamic(vk::DynamicState::FRONT_FACE) {
+            funcs.cmd_set_front_face(cmd, rasterizer.front_face);
+        }
+        if !dynamic(vk::DynamicState::LINE_WIDTH) {
+            device.cmd_set_line_width(cmd, rasterizer.line_width);
+        }
+        if !dynamic(vk::DynamicState::DEPTH_BIAS_ENABLE) {
+            funcs.cmd_set_depth_bias_enable(cmd, rasterizer.depth_bias_enable == vk::TRUE);
+        }
+        if !dynamic(vk::DynamicState::DEPTH_BIAS) {
+            device.cmd_set_depth_bias(
+                cmd,
+                rasterizer.depth_bias_constant_factor,
+                rasterizer.depth_bias_clamp,
+                rasterizer.depth_bias_slope_factor,
+            );
+        }
+        if features.depth_clamp == vk::TRUE && !dynamic(vk::DynamicState::DEPTH_CLAMP_ENABLE_EXT) {
+            funcs.cmd_set_depth_clamp_enable(cmd, rasterizer.depth_clamp_enable == vk::TRUE);
+        }
+        if device.is_extension_enabled(ExtensionID::ConservativeRasterization) {
+            let (mode, size) = info
+                .conservative_rasterization
+                .map_or((vk::ConservativeRasterizationModeEXT::DISABLED, 0.0), |state| {
+                    (state.0.conservative_rasterization_mode, state.0.extra_primitive_overestimation_size)
+                });
+            if !dynamic(vk::DynamicState::CONSERVATIVE_RASTERIZATION_MODE_EXT) {
+                funcs.cmd_set_conservative_rasterization_mode(cmd, mode);
+            }
+            if !dynamic(vk::DynamicState::EXTRA_PRIMITIVE_OVERESTIMATION_SIZE_EXT) {
+                funcs.cmd_set_extra_primitive_overestimation_size(cmd, size);
+            }
+        }
+        if device.is_extension_enabled(ExtensionID::DepthClipControl)
+            && !dynamic(vk::DynamicState::DEPTH_CLIP_NEGATIVE_ONE_TO_ONE_EXT)
+        {
+            funcs.cmd_set_depth_clip_negative_one_to_one(cmd, info.depth_clip_negative_one_to_one);
+        }
+
+        let multisample = &info.multisample.0;
+        if !dynamic(vk::DynamicState::RASTERIZATION_SAMPLES_EXT) {
+            funcs.cmd_set_rasterization_samples(cmd, multisample.rasterization_samples);
+        }
+        if !dynamic(vk::DynamicState::SAMPLE_MASK_EXT) {
+            // The mask has one word for every 32 samples, up to 64 samples.
+            let words = (multisample.rasterization_samples.as_raw() as usize).div_ceil(32);
+            funcs.cmd_set_sample_mask(cmd, multisample.rasterization_samples, &[u32::MAX; 2][..words]);
+        }
+        if !dynamic(vk::DynamicState::ALPHA_TO_COVERAGE_ENABLE_EXT) {
+            funcs.cmd_set_alpha_to_coverage_enable(cmd, multisample.alpha_to_coverage_enable == vk::TRUE);
+        }
+        if features.alpha_to_one == vk::TRUE && !dynamic(vk::DynamicState::ALPHA_TO_ONE_ENABLE_EXT) {
+            funcs.cmd_set_alpha_to_one_enable(cmd, multisample.alpha_to_one_enable == vk::TRUE);
+        }
+
+        let depth_stencil = &info.depth_stencil.0;
+        if !dynamic(vk::DynamicState::DEPTH_TEST_ENABLE) {
+            funcs.cmd_set_depth_test_enable(cmd, depth_stencil.depth_test_enable == vk::TRUE);
+        }
+        if !dynamic(vk::DynamicState::DEPTH_WRITE_ENABLE) {
+            funcs.cmd_set_depth_write_enable(cmd, depth_stencil.depth_write_enable == vk::TRUE);
+        }
+        if !dynamic(vk::DynamicState::DEPTH_COMPARE_OP) {
+            funcs.cmd_set_depth_compare_op(cmd, depth_stencil.depth_compare_op);
+        }
+        if !dynamic(vk::DynamicState::DEPTH_BOUNDS_TEST_ENABLE) {
+            funcs.cmd_set_depth_bounds_test_enable(cmd, depth_stencil.depth_bounds_test_enable == vk::TRUE);
+        }
+        if !dynamic(vk::DynamicState::DEPTH_BOUNDS) {
+            device.cmd_set_depth_bounds(cmd, depth_stencil.min_depth_bounds, depth_stencil.max_depth_bounds);
+        }
+        if !dynamic(vk::DynamicState::STENCIL_TEST_ENABLE) {
+            funcs.cmd_set_stencil_test_enable(cmd, depth_stencil.stencil_test_enable == vk::TRUE);
+        }
+        for (face, state) in [
+            (vk::StencilFaceFlags::FRONT, &depth_stencil.front),
+            (vk::StencilFaceFlags::BACK, &depth_stencil.back),
+        ] {
+            if !dynamic(vk::DynamicState::STENCIL_OP) {
+                funcs.cmd_set_stencil_op(cmd, face, state.fail_op, state.pass_op, state.depth_fail_op, state.compare_op);
+            }
+            if !dynamic(vk::DynamicState::STENCIL_COMPARE_MASK) {
+                device.cmd_set_stencil_compare_mask(cmd, face, state.compare_mask);
+            }
+            if !dynamic(vk::DynamicState::STENCIL_WRITE_MASK) {
+                device.cmd_set_stencil_write_mask(cmd, face, state.write_mask);
+            }
+            if !dynamic(vk::DynamicState::STENCIL_REFERENCE) {
+                device.cmd_set_stencil_reference(cmd, face, state.reference);
+            }
+        }
+
+        if features.logic_op == vk::TRUE && !dynamic(vk::DynamicState::LOGIC_OP_ENABLE_EXT) {
+            funcs.cmd_set_logic_op_enable(cmd, info.blend_enable_logic_op);
+        }
+        if info.blend_enable_logic_op && !dynamic(vk::DynamicState::LOGIC_OP_EXT) {
+            funcs.cmd_set_logic_op(cmd, info.blend_state.logic_op);
+        }
+        if !dynamic(vk::DynamicState::BLEND_CONSTANTS) {
+            device.cmd_set_blend_constants(cmd, &info.blend_state.blend_constants);
+        }
+        if !info.blend_attachments.is_empty() {
+            let attachments = info.blend_attachments.iter().map(|attachment| attachment.0);
+            if !dynamic(vk::DynamicState::COLOR_BLEND_ENABLE_EXT) {
+                let enables = attachments.clone().map(|state| state.blend_enable).collect::<Vec<_>>();
+                funcs.cmd_set_color_blend_enable(cmd, 0, &enables);
+            }
+            if !dynamic(vk::DynamicState::COLOR_BLEND_EQUATION_EXT) {
+                let equations = attachments
+                    .clone()
+                    .map(|state| vk::ColorBlendEquationEXT {
+                        src_color_blend_factor: state.src_color_blend_factor,
+                        dst_color_blend_factor: state.dst_color_blend_factor,
+                        color_blend_op: state.color_blend_op,
+                        src_alpha_blend_factor: state.src_alpha_blend_factor,
+                        dst_alpha_blend_factor: state.dst_alpha_blend_factor,
+                        alpha_blend_op: state.alpha_blend_op,
+                    })
+                    .collect::<Vec<_>>();
+                funcs.cmd_set_color_blend_equation(cmd, 0, &equations);
+            }
+            if !dynamic(vk::DynamicState::COLOR_WRITE_MASK_EXT) {
+                let masks = attachments.map(|state| state.color_write_mask).collect::<Vec<_>>();
+                funcs.cmd_set_color_write_mask(cmd, 0, &masks);
+            }
+        }
+
+        if device.fragment_shading_rate_features().pipeline_fragment_shading_rate == vk::TRUE
+            && !dynamic(vk::DynamicState::FRAGMENT_SHADING_RATE_KHR)
+        {
+            let fns = device.fragment_shading_rate().unwrap();
+            let (fragment_size, combiner_ops) = info.fragment_shading_rate.map_or(
+                (
+                    vk::Extent2D {
+                        width: 1,
+                        height: 1,
+                    },
+                    [vk::FragmentShadingRateCombinerOpKHR::KEEP; 2],
+                ),
+                |state| (state.0.fragment_size, state.0.combiner_ops),
+            );
+            (fns.cmd_set_fragment_shading_rate_khr)(cmd, &fragment_size, &combiner_ops);
+        }
+        Ok(())
+    }
+}
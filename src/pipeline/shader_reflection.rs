@@ -1,16 +1,38 @@
-//! Implements shader reflection to generate pipeline layouts automatically
+//! Implements shader reflection to generate pipeline layouts automatically.
+//!
+//! The reflection information of every pipeline registered in the [`PipelineCache`](crate::PipelineCache) can be obtained with
+//! [`PipelineCache::reflection_info()`](crate::PipelineCache::reflection_info). This exposes the descriptor bindings, push constant
+//! ranges and vertex inputs of the pipeline's shaders, for example to generate material parameter layouts.
+//! # Example
+//! ```
+//! # use phobos::prelude::*;
+//! fn print_bindings(cache: &PipelineCache) {
+//!     let Some(info) = cache.reflection_info("my_pipeline") else { return; };
+//!     for (name, binding) in info.bindings() {
+//!         println!("{name}: set {}, binding {}, {:?}", binding.set, binding.binding, binding.ty);
+//!     }
+//! }
+//! ```
 
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 
+#[cfg(feature = "shader-reflection")]
+use anyhow::ensure;
 use anyhow::Result;
 use ash::vk;
 #[cfg(feature = "shader-reflection")]
-use spv_cross::spirv::{Decoration, ExecutionModel, ShaderResources, Type};
+use spv_cross::spirv::{Decoration, Dim, ImageType, ShaderResources, Type};
 
+#[cfg(feature = "shader-reflection")]
+use crate::pipeline::create_info::{NamedImmutableSampler, VertexInputAttributeDescription, VertexInputBindingDescription};
 use crate::pipeline::pipeline_layout::{PipelineLayoutCreateInfo, PushConstantRange};
 use crate::pipeline::set_layout::DescriptorSetLayoutCreateInfo;
+#[cfg(feature = "shader-reflection")]
+use crate::pipeline::set_layout::ImmutableSamplerBinding;
 use crate::{Error, ShaderCreateInfo};
+#[cfg(feature = "shader-reflection")]
+use crate::{ByteSize, PipelineCreateInfo};
 
 #[cfg(all(feature = "shader-reflection", not(feature = "hlsl")))]
 type Ast = spv_cross::spirv::Ast<spv_cross::glsl::Target>;
@@ -18,53 +40,98 @@ type Ast = spv_cross::spirv::Ast<spv_cross::glsl::Target>;
 #[cfg(all(feature = "shader-reflection", feature = "hlsl"))]
 type Ast = spv_cross::spirv::Ast<spv_cross::hlsl::Target>;
 
+/// A descriptor binding used by the shaders of a pipeline.
 #[cfg(feature = "shader-reflection")]
-#[derive(Debug, Copy, Clone)]
-pub(crate) struct BindingInfo {
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BindingInfo {
+    /// Descriptor set index of this binding.
     pub set: u32,
+    /// Binding index inside the descriptor set.
     pub binding: u32,
+    /// All shader stages that use this binding.
     pub stage: vk::ShaderStageFlags,
+    /// Number of descriptors in this binding. Runtime sized arrays are reported with a count of 4096.
     pub count: u32,
+    /// Descriptor type of this binding.
     pub ty: vk::DescriptorType,
+    /// Binding flags used for this binding in the generated descriptor set layout.
     pub flags: vk::DescriptorBindingFlags,
 }
 
+/// An input variable of the vertex shader of a pipeline. Matrix and array inputs use one location for every column and array
+/// element, these are reported as separate inputs named `name[element][column]`.
+#[cfg(feature = "shader-reflection")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VertexInputInfo {
+    /// Name of the input variable in the shader.
+    pub name: String,
+    /// Location of this input.
+    pub location: u32,
+    /// Format matching the type of this input, or [`vk::Format::UNDEFINED`] for types that have no matching format.
+    pub format: vk::Format,
+}
+
 /// Stores reflection information about a pipeline. Can be used to derive a pipeline layout
 /// automatically, or access names of descriptor bindings.
 #[cfg(feature = "shader-reflection")]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ReflectionInfo {
     pub(crate) bindings: HashMap<String, BindingInfo>,
     pub(crate) push_constants: Vec<PushConstantRange>,
+    pub(crate) vertex_inputs: Vec<VertexInputInfo>,
 }
 
 #[cfg(feature = "shader-reflection")]
-fn get_shader_stage(ast: &Ast) -> Result<vk::ShaderStageFlags> {
-    let entry = ast
-        .get_entry_points()?
-        .first()
-        .cloned()
-        .ok_or_else(|| Error::NoEntryPoint)?;
-    Ok(match entry.execution_model {
-        ExecutionModel::Vertex => vk::ShaderStageFlags::VERTEX,
-        ExecutionModel::TessellationControl => vk::ShaderStageFlags::TESSELLATION_CONTROL,
-        ExecutionModel::TessellationEvaluation => vk::ShaderStageFlags::TESSELLATION_EVALUATION,
-        ExecutionModel::Geometry => vk::ShaderStageFlags::GEOMETRY, // EVIL
-        ExecutionModel::Fragment => vk::ShaderStageFlags::FRAGMENT,
-        ExecutionModel::GlCompute => vk::ShaderStageFlags::COMPUTE,
-        ExecutionModel::RayGenerationKHR => vk::ShaderStageFlags::RAYGEN_KHR,
-        ExecutionModel::IntersectionKHR => vk::ShaderStageFlags::INTERSECTION_KHR,
-        ExecutionModel::AnyHitKHR => vk::ShaderStageFlags::ANY_HIT_KHR,
-        ExecutionModel::ClosestHitKHR => vk::ShaderStageFlags::CLOSEST_HIT_KHR,
-        ExecutionModel::MissKHR => vk::ShaderStageFlags::MISS_KHR,
-        ExecutionModel::Kernel => {
-            unimplemented!()
-        }
-    })
+impl ReflectionInfo {
+    /// Get all descriptor bindings used by the pipeline, by their name in the shader.
+    pub fn bindings(&self) -> impl Iterator<Item = (&str, &BindingInfo)> {
+        self.bindings
+            .iter()
+            .map(|(name, binding)| (name.as_str(), binding))
+    }
+
+    /// Look up a descriptor binding by its name in the shader.
+    pub fn binding(&self, name: &str) -> Option<&BindingInfo> {
+        self.bindings.get(name)
+    }
+
+    /// Get the push constant ranges used by the pipeline. There is one range for each shader stage that uses push constants.
+    pub fn push_constants(&self) -> &[PushConstantRange] {
+        &self.push_constants
+    }
+
+    /// Get the inputs of the vertex shader, sorted by location. This is empty for pipelines without a vertex shader.
+    pub fn vertex_inputs(&self) -> &[VertexInputInfo] {
+        &self.vertex_inputs
+    }
 }
 
 // Note that aliasing is not supported
 
+/// Get the descriptor count and binding flags for a (possibly arrayed) image or sampler binding.
+/// Runtime sized arrays (`textures[]`) are treated as bindless arrays: they are partially bound, can be updated after binding,
+/// and get a variable descriptor count if they are the last binding in their set.
+#[cfg(feature = "shader-reflection")]
+fn array_binding_info(array: &[u32]) -> (u32, vk::DescriptorBindingFlags) {
+    match array.first() {
+        None => (1, vk::DescriptorBindingFlags::empty()),
+        Some(0) => (
+            4096,
+            vk::DescriptorBindingFlags::PARTIALLY_BOUND
+                | vk::DescriptorBindingFlags::VARIABLE_DESCRIPTOR_COUNT
+                | vk::DescriptorBindingFlags::UPDATE_AFTER_BIND,
+        ),
+        Some(count) => (*count, vk::DescriptorBindingFlags::PARTIALLY_BOUND),
+    }
+}
+
+/// Whether an image type is a texel buffer (`samplerBuffer`, `textureBuffer` or `imageBuffer` in GLSL), which is bound as a
+/// [`TexelBufferView`](crate::TexelBufferView) instead of an image.
+#[cfg(feature = "shader-reflection")]
+fn is_texel_buffer(image: &ImageType) -> bool {
+    matches!(image.dim, Dim::DimBuffer)
+}
+
 #[cfg(feature = "shader-reflection")]
 fn find_sampled_images(
     ast: &mut Ast,
@@ -76,25 +143,94 @@ fn find_sampled_images(
         let binding = ast.get_decoration(image.id, Decoration::Binding)?;
         let set = ast.get_decoration(image.id, Decoration::DescriptorSet)?;
         let ty = ast.get_type(image.type_id)?;
-        let Type::SampledImage { array, .. } = ty else { unimplemented!() };
-        let (count, flags) = if !array.is_empty() {
-            if array[0] == 0 {
-                (4096, vk::DescriptorBindingFlags::PARTIALLY_BOUND)
-            } else {
-                (array[0], vk::DescriptorBindingFlags::PARTIALLY_BOUND)
-            }
+        let name = ast.get_name(image.id)?;
+        let Type::SampledImage { array, image: image_type, .. } = ty else {
+            return Err(Error::UnexpectedResourceType(name).into());
+        };
+        let (count, flags) = array_binding_info(&array);
+        let ty = if is_texel_buffer(&image_type) {
+            vk::DescriptorType::UNIFORM_TEXEL_BUFFER
         } else {
-            (1, vk::DescriptorBindingFlags::empty())
+            vk::DescriptorType::COMBINED_IMAGE_SAMPLER
         };
 
         info.bindings.insert(
-            ast.get_name(image.id)?,
+            name,
             BindingInfo {
                 set,
                 binding,
                 stage,
                 count,
-                ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                ty,
+                flags,
+            },
+        );
+    }
+    Ok(())
+}
+
+#[cfg(feature = "shader-reflection")]
+fn find_separate_images(
+    ast: &mut Ast,
+    stage: vk::ShaderStageFlags,
+    resources: &ShaderResources,
+    info: &mut ReflectionInfo,
+) -> Result<()> {
+    for image in &resources.separate_images {
+        let binding = ast.get_decoration(image.id, Decoration::Binding)?;
+        let set = ast.get_decoration(image.id, Decoration::DescriptorSet)?;
+        let ty = ast.get_type(image.type_id)?;
+        let name = ast.get_name(image.id)?;
+        let Type::Image { array, image: image_type, .. } = ty else {
+            return Err(Error::UnexpectedResourceType(name).into());
+        };
+        let (count, flags) = array_binding_info(&array);
+        let ty = if is_texel_buffer(&image_type) {
+            vk::DescriptorType::UNIFORM_TEXEL_BUFFER
+        } else {
+            vk::DescriptorType::SAMPLED_IMAGE
+        };
+
+        info.bindings.insert(
+            name,
+            BindingInfo {
+                set,
+                binding,
+                stage,
+                count,
+                ty,
+                flags,
+            },
+        );
+    }
+    Ok(())
+}
+
+#[cfg(feature = "shader-reflection")]
+fn find_separate_samplers(
+    ast: &mut Ast,
+    stage: vk::ShaderStageFlags,
+    resources: &ShaderResources,
+    info: &mut ReflectionInfo,
+) -> Result<()> {
+    for sampler in &resources.separate_samplers {
+        let binding = ast.get_decoration(sampler.id, Decoration::Binding)?;
+        let set = ast.get_decoration(sampler.id, Decoration::DescriptorSet)?;
+        let ty = ast.get_type(sampler.type_id)?;
+        let name = ast.get_name(sampler.id)?;
+        let Type::Sampler { array, .. } = ty else {
+            return Err(Error::UnexpectedResourceType(name).into());
+        };
+        let (count, flags) = array_binding_info(&array);
+
+        info.bindings.insert(
+            name,
+            BindingInfo {
+                set,
+                binding,
+                stage,
+                count,
+                ty: vk::DescriptorType::SAMPLER,
                 flags,
             },
         );
@@ -162,6 +298,10 @@ fn find_storage_images(
     for image in &resources.storage_images {
         let binding = ast.get_decoration(image.id, Decoration::Binding)?;
         let set = ast.get_decoration(image.id, Decoration::DescriptorSet)?;
+        let ty = match ast.get_type(image.type_id)? {
+            Type::Image { image, .. } if is_texel_buffer(&image) => vk::DescriptorType::STORAGE_TEXEL_BUFFER,
+            _ => vk::DescriptorType::STORAGE_IMAGE,
+        };
         info.bindings.insert(
             ast.get_name(image.id)?,
             BindingInfo {
@@ -169,7 +309,32 @@ fn find_storage_images(
                 binding,
                 stage,
                 count: 1,
-                ty: vk::DescriptorType::STORAGE_IMAGE,
+                ty,
+                flags: vk::DescriptorBindingFlags::empty(),
+            },
+        );
+    }
+    Ok(())
+}
+
+#[cfg(feature = "shader-reflection")]
+fn find_input_attachments(
+    ast: &mut Ast,
+    stage: vk::ShaderStageFlags,
+    resources: &ShaderResources,
+    info: &mut ReflectionInfo,
+) -> Result<()> {
+    for input in &resources.subpass_inputs {
+        let binding = ast.get_decoration(input.id, Decoration::Binding)?;
+        let set = ast.get_decoration(input.id, Decoration::DescriptorSet)?;
+        info.bindings.insert(
+            ast.get_name(input.id)?,
+            BindingInfo {
+                set,
+                binding,
+                stage,
+                count: 1,
+                ty: vk::DescriptorType::INPUT_ATTACHMENT,
                 flags: vk::DescriptorBindingFlags::empty(),
             },
         );
@@ -222,22 +387,141 @@ fn find_push_constants(
     Ok(())
 }
 
+/// Get the format of a vertex input with the given scalar type and number of components.
+#[cfg(feature = "shader-reflection")]
+fn vertex_input_format(ty: &Type) -> vk::Format {
+    const FLOAT: [vk::Format; 4] = [
+        vk::Format::R32_SFLOAT,
+        vk::Format::R32G32_SFLOAT,
+        vk::Format::R32G32B32_SFLOAT,
+        vk::Format::R32G32B32A32_SFLOAT,
+    ];
+    const INT: [vk::Format; 4] = [
+        vk::Format::R32_SINT,
+        vk::Format::R32G32_SINT,
+        vk::Format::R32G32B32_SINT,
+        vk::Format::R32G32B32A32_SINT,
+    ];
+    const UINT: [vk::Format; 4] = [
+        vk::Format::R32_UINT,
+        vk::Format::R32G32_UINT,
+        vk::Format::R32G32B32_UINT,
+        vk::Format::R32G32B32A32_UINT,
+    ];
+    const DOUBLE: [vk::Format; 4] = [
+        vk::Format::R64_SFLOAT,
+        vk::Format::R64G64_SFLOAT,
+        vk::Format::R64G64B64_SFLOAT,
+        vk::Format::R64G64B64A64_SFLOAT,
+    ];
+    let (formats, vecsize) = match ty {
+        Type::Float {
+            vecsize, ..
+        } => (&FLOAT, *vecsize),
+        Type::Int {
+            vecsize, ..
+        } => (&INT, *vecsize),
+        Type::UInt {
+            vecsize, ..
+        } => (&UINT, *vecsize),
+        Type::Double {
+            vecsize, ..
+        } => (&DOUBLE, *vecsize),
+        _ => return vk::Format::UNDEFINED,
+    };
+    formats
+        .get((vecsize as usize).wrapping_sub(1))
+        .copied()
+        .unwrap_or(vk::Format::UNDEFINED)
+}
+
+/// Get the number of matrix columns and the array dimensions of a vertex input type.
+#[cfg(feature = "shader-reflection")]
+fn vertex_input_shape(ty: &Type) -> (u32, &[u32]) {
+    match ty {
+        Type::Float {
+            columns,
+            array,
+            ..
+        }
+        | Type::Int {
+            columns,
+            array,
+            ..
+        }
+        | Type::UInt {
+            columns,
+            array,
+            ..
+        }
+        | Type::Double {
+            columns,
+            array,
+            ..
+        } => ((*columns).max(1), array.as_slice()),
+        _ => (1, &[]),
+    }
+}
+
+#[cfg(feature = "shader-reflection")]
+fn find_vertex_inputs(ast: &mut Ast, resources: &ShaderResources, info: &mut ReflectionInfo) -> Result<()> {
+    for input in &resources.stage_inputs {
+        let location = ast.get_decoration(input.id, Decoration::Location)?;
+        let ty = ast.get_type(input.type_id)?;
+        let name = ast.get_name(input.id)?;
+        let format = vertex_input_format(&ty);
+        let (columns, array) = vertex_input_shape(&ty);
+        // 64-bit vectors with more than two components use two locations per column.
+        let locations_per_column = if format.byte_size() > 16 { 2 } else { 1 };
+        let elements = array.iter().product::<u32>();
+        for element in 0..elements {
+            for column in 0..columns {
+                let mut input_name = name.clone();
+                if !array.is_empty() {
+                    input_name += &format!("[{element}]");
+                }
+                if columns > 1 {
+                    input_name += &format!("[{column}]");
+                }
+                info.vertex_inputs.push(VertexInputInfo {
+                    name: input_name,
+                    location: location + (element * columns + column) * locations_per_column,
+                    format,
+                });
+            }
+        }
+    }
+    info.vertex_inputs.sort_by_key(|input| input.location);
+    Ok(())
+}
+
 #[cfg(feature = "shader-reflection")]
-fn reflect_module(module: spv_cross::spirv::Module) -> Result<ReflectionInfo> {
+fn reflect_module(
+    module: spv_cross::spirv::Module,
+    stage: vk::ShaderStageFlags,
+) -> Result<ReflectionInfo> {
+    // Note that we use the stage supplied in the shader create info instead of the execution model of the entry point,
+    // since spirv-cross does not know about all execution models (such as mesh and task shaders).
     let mut ast: Ast = Ast::parse(&module)?;
     let resources = ast.get_shader_resources()?;
-    let stage = get_shader_stage(&ast)?;
 
     let mut info = ReflectionInfo {
         bindings: Default::default(),
         push_constants: Default::default(),
+        vertex_inputs: Default::default(),
     };
     find_sampled_images(&mut ast, stage, &resources, &mut info)?;
+    find_separate_images(&mut ast, stage, &resources, &mut info)?;
+    find_separate_samplers(&mut ast, stage, &resources, &mut info)?;
     find_uniform_buffers(&mut ast, stage, &resources, &mut info)?;
     find_storage_buffers(&mut ast, stage, &resources, &mut info)?;
     find_push_constants(&mut ast, stage, &resources, &mut info)?;
     find_storage_images(&mut ast, stage, &resources, &mut info)?;
+    find_input_attachments(&mut ast, stage, &resources, &mut info)?;
     find_acceleration_structures(&mut ast, stage, &resources, &mut info)?;
+    if stage == vk::ShaderStageFlags::VERTEX {
+        find_vertex_inputs(&mut ast, &resources, &mut info)?;
+    }
     Ok(info)
 }
 
@@ -268,7 +552,7 @@ pub(crate) fn reflect_shaders(shaders: &[ShaderCreateInfo]) -> Result<Reflection
     let mut reflected_shaders = Vec::new();
     for shader in shaders {
         let module = spv_cross::spirv::Module::from_words(shader.code());
-        reflected_shaders.push(reflect_module(module)?);
+        reflected_shaders.push(reflect_module(module, shader.stage())?);
     }
 
     Ok(ReflectionInfo {
@@ -299,6 +583,10 @@ pub(crate) fn reflect_shaders(shaders: &[ShaderCreateInfo]) -> Result<Reflection
                 acc
             }),
         push_constants: merge_push_constants(&reflected_shaders)?,
+        vertex_inputs: reflected_shaders
+            .iter()
+            .flat_map(|shader| shader.vertex_inputs.iter().cloned())
+            .collect(),
     })
 }
 
@@ -337,14 +625,109 @@ pub(crate) fn build_pipeline_layout(info: &ReflectionInfo) -> PipelineLayoutCrea
                     }],
                     flags: vec![binding.flags],
                     persistent: false,
+                    immutable_samplers: vec![],
                 });
             }
         }
     }
 
+    // Only the binding with the highest binding number in a set may have a variable descriptor count.
+    for set in sets.values_mut() {
+        let last = set.bindings.iter().map(|binding| binding.binding).max();
+        for (binding, flags) in set.bindings.iter().zip(set.flags.iter_mut()) {
+            if Some(binding.binding) != last {
+                *flags &= !vk::DescriptorBindingFlags::VARIABLE_DESCRIPTOR_COUNT;
+            }
+        }
+    }
+
     for i in 0..sets.len() as u32 {
         layout.set_layouts.push(sets.get(&i).unwrap().clone());
     }
 
     layout
 }
+
+/// Bake immutable samplers into a pipeline layout built from reflection, by looking up each binding by its name in the shader.
+/// # Errors
+/// * Fails if a binding with this name does not exist, or is not a sampler binding.
+#[cfg(feature = "shader-reflection")]
+pub(crate) fn apply_immutable_samplers(
+    layout: &mut PipelineLayoutCreateInfo,
+    info: &ReflectionInfo,
+    samplers: &[NamedImmutableSampler],
+) -> Result<()> {
+    for NamedImmutableSampler { name, sampler } in samplers {
+        let binding = info
+            .bindings
+            .get(name)
+            .filter(|binding| {
+                binding.ty == vk::DescriptorType::SAMPLER
+                    || binding.ty == vk::DescriptorType::COMBINED_IMAGE_SAMPLER
+            })
+            .ok_or_else(|| Error::NoBinding(name.clone()))?;
+        let set = layout
+            .set_layouts
+            .get_mut(binding.set as usize)
+            .ok_or_else(|| Error::NoBinding(name.clone()))?;
+        set.immutable_samplers.push(ImmutableSamplerBinding {
+            binding: binding.binding,
+            samplers: vec![unsafe { sampler.handle() }],
+        });
+    }
+    Ok(())
+}
+
+/// Fill in the vertex bindings and attributes of a graphics pipeline from the reflected vertex shader inputs, for the bindings
+/// requested with [`PipelineBuilder::vertex_inputs_from_reflection()`](crate::PipelineBuilder::vertex_inputs_from_reflection).
+/// # Errors
+/// * Fails if a vertex input is not part of any binding.
+/// * Fails if a vertex input has a type that cannot be used as a vertex attribute.
+#[cfg(feature = "shader-reflection")]
+pub(crate) fn apply_reflected_vertex_inputs(pci: &mut PipelineCreateInfo, info: &ReflectionInfo) -> Result<()> {
+    if pci.reflected_vertex_bindings.is_empty() {
+        return Ok(());
+    }
+    let mut bindings = pci.reflected_vertex_bindings.clone();
+    bindings.sort_by_key(|binding| binding.first_location);
+    for input in &info.vertex_inputs {
+        ensure!(
+            input.location >= bindings[0].first_location,
+            "vertex input `{}` at location {} is not part of any vertex binding.",
+            input.name,
+            input.location
+        );
+        ensure!(
+            input.format != vk::Format::UNDEFINED,
+            "vertex input `{}` has a type that cannot be used as a vertex attribute.",
+            input.name
+        );
+    }
+    for (index, binding) in bindings.iter().enumerate() {
+        let end = bindings
+            .get(index + 1)
+            .map_or(u32::MAX, |next| next.first_location);
+        let mut offset = 0;
+        for input in info
+            .vertex_inputs
+            .iter()
+            .filter(|input| (binding.first_location..end).contains(&input.location))
+        {
+            pci.vertex_attributes
+                .push(VertexInputAttributeDescription(vk::VertexInputAttributeDescription {
+                    location: input.location,
+                    binding: binding.binding,
+                    format: input.format,
+                    offset,
+                }));
+            offset += input.format.byte_size() as u32;
+        }
+        pci.vertex_input_bindings
+            .push(VertexInputBindingDescription(vk::VertexInputBindingDescription {
+                binding: binding.binding,
+                stride: binding.stride.unwrap_or(offset),
+                input_rate: binding.rate,
+            }));
+    }
+    Ok(())
+}
@@ -0,0 +1,136 @@
+//! Describe vertex layouts with Rust types instead of manually listing vertex attributes.
+//!
+//! A `#[repr(C)]` vertex struct can implement [`VertexInput`] with the [`impl_vertex_input!`](crate::impl_vertex_input) macro.
+//! This derives the format and offset of each attribute from the field types and the struct layout, and the stride from the
+//! size of the struct. The layout can then be added to a pipeline with
+//! [`PipelineBuilder::vertex_layout()`](crate::PipelineBuilder::vertex_layout).
+//! # Example
+//! ```
+//! # use phobos::prelude::*;
+//! # use phobos::impl_vertex_input;
+//! #[repr(C)]
+//! #[derive(Debug, Copy, Clone)]
+//! struct Vertex {
+//!     position: [f32; 3],
+//!     normal: [f32; 3],
+//!     uv: [f32; 2],
+//! }
+//!
+//! // Attributes get locations 0, 1 and 2, in the order they are listed here.
+//! impl_vertex_input!(Vertex { position, normal, uv });
+//!
+//! let pci = PipelineBuilder::new("mesh")
+//!     .vertex_layout::<Vertex>(0, vk::VertexInputRate::VERTEX)
+//!     // ... other pipeline options
+//!     .build();
+//! ```
+
+use ash::vk;
+
+/// A type that can be used as a single vertex attribute. This maps the type to the `VkFormat` used to read it in the shader.
+pub trait VertexFormat {
+    /// Format of this attribute.
+    const FORMAT: vk::Format;
+}
+
+macro_rules! impl_vertex_format {
+    ($($ty:ty => $format:ident),* $(,)?) => {
+        $(
+            impl VertexFormat for $ty {
+                const FORMAT: vk::Format = vk::Format::$format;
+            }
+        )*
+    };
+}
+
+impl_vertex_format!(
+    f32 => R32_SFLOAT,
+    [f32; 1] => R32_SFLOAT,
+    [f32; 2] => R32G32_SFLOAT,
+    [f32; 3] => R32G32B32_SFLOAT,
+    [f32; 4] => R32G32B32A32_SFLOAT,
+    f64 => R64_SFLOAT,
+    [f64; 1] => R64_SFLOAT,
+    [f64; 2] => R64G64_SFLOAT,
+    [f64; 3] => R64G64B64_SFLOAT,
+    [f64; 4] => R64G64B64A64_SFLOAT,
+    i32 => R32_SINT,
+    [i32; 1] => R32_SINT,
+    [i32; 2] => R32G32_SINT,
+    [i32; 3] => R32G32B32_SINT,
+    [i32; 4] => R32G32B32A32_SINT,
+    u32 => R32_UINT,
+    [u32; 1] => R32_UINT,
+    [u32; 2] => R32G32_UINT,
+    [u32; 3] => R32G32B32_UINT,
+    [u32; 4] => R32G32B32A32_UINT,
+    i16 => R16_SINT,
+    [i16; 2] => R16G16_SINT,
+    [i16; 4] => R16G16B16A16_SINT,
+    u16 => R16_UINT,
+    [u16; 2] => R16G16_UINT,
+    [u16; 4] => R16G16B16A16_UINT,
+    i8 => R8_SINT,
+    [i8; 2] => R8G8_SINT,
+    [i8; 4] => R8G8B8A8_SINT,
+    u8 => R8_UINT,
+    [u8; 2] => R8G8_UINT,
+    [u8; 4] => R8G8B8A8_UINT,
+);
+
+/// A single attribute of a [`VertexInput`] type.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct VertexAttribute {
+    /// Format of this attribute.
+    pub format: vk::Format,
+    /// Offset of this attribute from the start of the vertex, in bytes.
+    pub offset: u32,
+}
+
+/// A vertex type that describes its own layout. This is usually implemented with the
+/// [`impl_vertex_input!`](crate::impl_vertex_input) macro, see the [module level documentation](self) for an example.
+pub trait VertexInput: Copy {
+    /// Distance between two consecutive vertices in a vertex buffer, in bytes.
+    const STRIDE: u32 = std::mem::size_of::<Self>() as u32;
+
+    /// Get the attributes of this vertex type. These are assigned to consecutive shader locations in this order.
+    fn attributes() -> Vec<VertexAttribute>;
+}
+
+/// Get the vertex format of a field, by using a closure that accesses it. Used by [`impl_vertex_input!`](crate::impl_vertex_input)
+/// so the field type does not need to be repeated.
+#[doc(hidden)]
+pub fn field_format<V, T: VertexFormat>(_field: impl Fn(&V) -> &T) -> vk::Format {
+    T::FORMAT
+}
+
+/// Implement [`VertexInput`](crate::pipeline::vertex::VertexInput) for a `#[repr(C)]` struct, by listing its fields in the
+/// order of their shader locations. Each field type must implement [`VertexFormat`](crate::pipeline::vertex::VertexFormat).
+/// Offsets are taken from the struct layout, so padding between fields is handled automatically.
+/// # Example
+/// ```
+/// # use phobos::impl_vertex_input;
+/// #[repr(C)]
+/// #[derive(Copy, Clone)]
+/// struct Vertex {
+///     position: [f32; 2],
+///     color: [f32; 4],
+/// }
+///
+/// impl_vertex_input!(Vertex { position, color });
+/// ```
+#[macro_export]
+macro_rules! impl_vertex_input {
+    ($ty:ty { $($field:ident),+ $(,)? }) => {
+        impl $crate::pipeline::vertex::VertexInput for $ty {
+            fn attributes() -> ::std::vec::Vec<$crate::pipeline::vertex::VertexAttribute> {
+                ::std::vec![$(
+                    $crate::pipeline::vertex::VertexAttribute {
+                        format: $crate::pipeline::vertex::field_format(|vertex: &$ty| &vertex.$field),
+                        offset: ::std::mem::offset_of!($ty, $field) as u32,
+                    }
+                ),+]
+            }
+        }
+    };
+}
@@ -1,59 +1,131 @@
-//! Re-exports most commonly used types in the library
+//! Re-exports most commonly used types and traits in the library.
+//!
+//! The prelude is split into two modules:
+//! - [`prelude::types`](crate::prelude::types) re-exports all commonly used structs, enums and type aliases.
+//! - [`prelude::traits`](crate::prelude::traits) re-exports all traits, including the execution domain capability traits
+//!   ([`GfxSupport`](crate::GfxSupport), [`TransferSupport`](crate::TransferSupport), [`ComputeSupport`](crate::ComputeSupport),
+//!   [`DecodeSupport`](crate::DecodeSupport))
+//!   and the command buffer traits needed to call most commands.
+//!
+//! Both are re-exported from this module, so `use phobos::prelude::*;` imports everything at once.
+//! # Example
+//! ```
+//! // Bring all traits into scope, but keep types namespaced.
+//! use phobos::prelude::traits::*;
+//! use phobos::prelude as ph;
+//!
+//! // Generic code over execution domains only needs the capability traits.
+//! fn clear<'q, D: ph::domain::ExecutionDomain + TransferSupport>(cmd: ph::IncompleteCommandBuffer<'q, D>, buffer: &ph::BufferView) -> anyhow::Result<ph::IncompleteCommandBuffer<'q, D>> {
+//!     // fill_buffer() is a TransferCmdBuffer method, which is in scope through prelude::traits.
+//!     cmd.fill_buffer(buffer, 0)
+//! }
+//! ```
 
 pub use ash::vk;
 
 pub use traits::*;
+pub use types::*;
 
-pub use crate::allocator::default_allocator;
-pub use crate::allocator::default_allocator::DefaultAllocator;
-pub use crate::allocator::memory_type::MemoryType;
-pub use crate::allocator::scratch_allocator::ScratchAllocator;
-pub use crate::command_buffer::{CommandBuffer, IncompleteCommandBuffer};
-pub use crate::core::app_info::*;
-pub use crate::core::debug::DebugMessenger;
-pub use crate::core::device::Device;
-pub use crate::core::error::Error;
-pub use crate::core::init::*;
-pub use crate::core::instance::Instance;
-pub use crate::core::physical_device::*;
-pub use crate::core::queue::QueueType;
-pub use crate::descriptor::cache::DescriptorCache;
-pub use crate::descriptor::descriptor_set::DescriptorSet;
-pub use crate::graph::pass::{ClearColor, ClearDepthStencil, Pass, PassBuilder};
-pub use crate::graph::pass_graph::PassGraph;
-pub use crate::graph::physical_resource::PhysicalResourceBindings;
-pub use crate::graph::virtual_resource::VirtualResource;
-pub use crate::pipeline::{PipelineStage, PipelineType};
-pub use crate::pipeline::builder::PipelineBuilder;
-pub use crate::pipeline::cache::PipelineCache;
-pub use crate::pipeline::compute::{ComputePipelineBuilder, ComputePipelineCreateInfo};
-pub use crate::pipeline::create_info::PipelineCreateInfo;
-pub use crate::pipeline::hash::*;
-pub use crate::pipeline::raytracing::RayTracingPipelineBuilder;
-pub use crate::pipeline::shader::ShaderCreateInfo;
-pub use crate::resource::*;
-pub use crate::resource::buffer::{Buffer, BufferView};
-pub use crate::resource::image::{Image, ImageView};
-pub use crate::resource::query_pool::*;
-pub use crate::resource::raytracing::*;
-pub use crate::sampler::Sampler;
-pub use crate::sync::domain;
-pub use crate::sync::execution_manager::ExecutionManager;
-pub use crate::sync::fence::*;
-pub use crate::sync::semaphore::*;
-pub use crate::util::address::*;
-pub use crate::util::byte_size::ByteSize;
-pub use crate::util::deferred_delete::DeletionQueue;
-pub use crate::util::transform::TransformMatrix;
-pub use crate::wsi::frame::{FrameManager, InFlightContext};
-pub use crate::wsi::surface::Surface;
-pub use crate::wsi::swapchain::Swapchain;
+/// Re-exports all commonly used types of the library
+pub mod types {
+    pub use crate::allocator::default_allocator;
+    pub use crate::allocator::default_allocator::DefaultAllocator;
+    pub use crate::allocator::memory_type::MemoryType;
+    pub use crate::allocator::scratch_allocator::ScratchAllocator;
+    pub use crate::allocator::statistics::{
+        AllocationStatistics, CategoryStatistics, HeapBudget, MemoryCategory, MemoryReport,
+    };
+    pub use crate::command_buffer::{CommandBuffer, IncompleteCommandBuffer};
+    pub use crate::command_buffer::graphics::{
+        DrawIndexedIndirectCommand, DrawIndirectCommand, DrawMeshTasksIndirectCommand,
+        TraceRaysIndirectCommand,
+    };
+    pub use crate::command_buffer::transfer::ImageRegion;
+    pub use crate::core::app_info::*;
+    #[cfg(feature = "renderdoc")]
+    pub use crate::core::capture::{Capture, CaptureScope};
+    pub use crate::core::debug::DebugMessenger;
+    pub use crate::core::device::{Device, ExtensionID};
+    pub use crate::core::error::Error;
+    pub use crate::core::init::*;
+    pub use crate::core::instance::Instance;
+    pub use crate::core::physical_device::*;
+    pub use crate::core::queue::QueueType;
+    pub use crate::descriptor::cache::DescriptorCache;
+    pub use crate::descriptor::descriptor_set::DescriptorSet;
+    pub use crate::descriptor::persistent::PersistentDescriptorSet;
+    pub use crate::graph::pass::{
+        ClearColor, ClearDepthStencil, Pass, PassBuilder, PassConstants, UpscaleVirtualResources,
+    };
+    pub use crate::graph::pass_graph::{GraphResourceState, PassGraph};
+    pub use crate::graph::physical_resource::PhysicalResourceBindings;
+    pub use crate::graph::record::{DryRun, DryRunBarrier, DryRunPass, DryRunStep};
+    pub use crate::graph::virtual_resource::VirtualResource;
+    pub use crate::pipeline::{PipelineStage, PipelineType};
+    pub use crate::pipeline::builder::PipelineBuilder;
+    pub use crate::pipeline::cache::{
+        PipelineCache, PipelineCreationFeedback, PipelineCreationStatistics, PipelinePrecompileInfo,
+    };
+    pub use crate::pipeline::compute::{ComputePipelineBuilder, ComputePipelineCreateInfo};
+    pub use crate::pipeline::create_info::PipelineCreateInfo;
+    pub use crate::pipeline::hash::*;
+    #[cfg(feature = "dxc")]
+    pub use crate::pipeline::hlsl::HlslCompileOptions;
+    pub use crate::pipeline::raytracing::RayTracingPipelineBuilder;
+    pub use crate::pipeline::shader::{ShaderCompileError, ShaderCreateInfo};
+    pub use crate::pipeline::vertex::{VertexFormat, VertexInput};
+    #[cfg(feature = "shader-reflection")]
+    pub use crate::pipeline::shader_reflection::{BindingInfo, ReflectionInfo, VertexInputInfo};
+    pub use crate::resource::*;
+    pub use crate::resource::aliased_memory::{AliasedMemory, AliasedResource, PlacedResourceInfo};
+    pub use crate::resource::buffer::{Buffer, BufferView};
+    pub use crate::resource::gpu_vec::GpuVec;
+    pub use crate::resource::image::{CubeFace, Image, ImageCreateInfo, ImageView};
+    pub use crate::resource::pool::{LocalPool, ResourcePool};
+    pub use crate::resource::query_pool::*;
+    pub use crate::resource::sparse::{SparseBindBatch, SparseMemory};
+    pub use crate::resource::texel_buffer::TexelBufferView;
+    pub use crate::resource::typed_buffer::{TypedBuffer, TypedBufferView};
+    pub use crate::resource::raytracing::*;
+    pub use crate::resource::ycbcr::{SamplerYcbcrConversion, YcbcrConversionCreateInfo};
+    pub use crate::sampler::{CustomBorderColor, Sampler, SamplerCache, SamplerCreateInfo};
+    pub use crate::sync::domain;
+    pub use crate::sync::execution_manager::{ComputeQueuePolicy, ExecutionManager};
+    pub use crate::sync::fence::*;
+    pub use crate::sync::semaphore::*;
+    pub use crate::sync::submit_batch::SubmitBatch;
+    pub use crate::upscaler::{UpscaleDescription, UpscaleQuality, UpscaleResources};
+    pub use crate::util::address::*;
+    pub use crate::util::deferred_delete::DeletionQueue;
+    pub use crate::util::compressed::{compressed_block, compressed_level_size, is_block_compressed, CompressedBlock};
+    pub use crate::util::device_size::DeviceSize;
+    pub use crate::util::host_image_copy::IMAGE_USAGE_HOST_TRANSFER;
+    pub use crate::util::mipmaps::generate_mipmaps;
+    pub use crate::util::readback::{CompletedReadback, FrameReadback, ImageData, ReadbackId, ReadbackQueue, ReadbackSource};
+    pub use crate::util::transform::TransformMatrix;
+    pub use crate::util::upload::{MipLevelData, PendingUpload};
+    pub use crate::util::upload_manager::{QueuedUpload, UploadManager};
+    pub use crate::wsi::frame::{FrameManager, FramePacer, InFlightContext, PerFrame, PerImage, PresentContext};
+    pub use crate::wsi::headless::HeadlessFrameManager;
+    pub use crate::wsi::surface::Surface;
+    pub use crate::wsi::swapchain::Swapchain;
+}
 
 /// Re-exports all important traits of the library
 pub mod traits {
     pub use crate::allocator::traits::*;
     pub use crate::command_buffer::traits::*;
+    pub use crate::core::init::ContextInit;
+    pub use crate::core::traits::Nameable;
+    pub use crate::graph::pass::PassExecutor;
     pub use crate::graph::pass_graph::GraphViz;
     pub use crate::graph::record::RecordGraphToCommandBuffer;
+    pub use crate::resource::pool::Poolable;
+    pub use crate::resource::query_pool::{AccelerationStructurePropertyQuery, Query, ScopedQuery};
+    pub use crate::sync::domain::ExecutionDomain;
+    pub use crate::sync::fence::FenceValue;
+    pub use crate::upscaler::Upscaler;
+    pub use crate::util::byte_size::ByteSize;
+    pub use crate::wsi::frame::PresentHook;
     pub use crate::wsi::window::{WindowInterface, WindowSize};
 }
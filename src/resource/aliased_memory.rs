@@ -0,0 +1,328 @@
+//! Manual memory aliasing through resources placed in a shared allocation.
+//!
+//! An [`AliasedMemory`] is a single memory allocation that any number of buffers and images can be placed in, at offsets chosen
+//! by the application. Resources may overlap, which allows transient resources that are never used at the same time to share
+//! the same memory. This is the building block for managing transient memory manually.
+//!
+//! Only one of the overlapping resources holds valid contents at any time. Before using a resource after another resource
+//! overlapping it was used, it must be activated with [`IncompleteCommandBuffer::activate_aliased()`](crate::IncompleteCommandBuffer::activate_aliased).
+//! This inserts a barrier that waits for the previous use of the memory, and transitions images from `VK_IMAGE_LAYOUT_UNDEFINED`.
+//! The contents of a resource are undefined after activating it.
+//!
+//! Placed resources keep the memory alive, so the [`AliasedMemory`] may be dropped before the resources placed in it.
+//! # Example
+//! ```
+//! # use anyhow::Result;
+//! # use phobos::prelude::*;
+//! # use phobos::sync::domain::ExecutionDomain;
+//! fn transient_images<D: ExecutionDomain + TransferSupport>(
+//!     device: Device,
+//!     mut allocator: DefaultAllocator,
+//!     cmd: IncompleteCommandBuffer<D>,
+//!     info: ImageCreateInfo,
+//! ) -> Result<IncompleteCommandBuffer<D>> {
+//!     // Allocate memory large enough to hold either image, and place both images at the start of it.
+//!     let memory = AliasedMemory::new(
+//!         device,
+//!         &mut allocator,
+//!         &[PlacedResourceInfo::Image(info), PlacedResourceInfo::Image(info)],
+//!         MemoryType::GpuOnly,
+//!     )?;
+//!     let first = memory.create_image(0, info)?;
+//!     let second = memory.create_image(0, info)?;
+//!     let first_view = first.whole_view(vk::ImageAspectFlags::COLOR)?;
+//!     let second_view = second.whole_view(vk::ImageAspectFlags::COLOR)?;
+//!     let cmd = cmd
+//!         .activate_aliased(
+//!             AliasedResource::Image { view: &first_view, layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL },
+//!             PipelineStage::NONE,
+//!             vk::AccessFlags2::NONE,
+//!             PipelineStage::TRANSFER,
+//!             vk::AccessFlags2::TRANSFER_WRITE,
+//!         )
+//!         .clear_color_image(&first_view, vk::ClearColorValue { float32: [0.0; 4] })?
+//!         // The second image overwrites the first one, so wait until the clear is done.
+//!         .activate_aliased(
+//!             AliasedResource::Image { view: &second_view, layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL },
+//!             PipelineStage::TRANSFER,
+//!             vk::AccessFlags2::TRANSFER_WRITE,
+//!             PipelineStage::TRANSFER,
+//!             vk::AccessFlags2::TRANSFER_WRITE,
+//!         )
+//!         .clear_color_image(&second_view, vk::ClearColorValue { float32: [1.0; 4] })?;
+//!     Ok(cmd)
+//! }
+//! ```
+
+use std::ffi::c_void;
+use std::ptr::NonNull;
+use std::sync::Arc;
+
+use anyhow::Result;
+use ash::vk;
+
+use crate::allocator::statistics::MemoryCategory;
+use crate::resource::buffer::buffer_memory_requirements;
+use crate::resource::image::image_memory_requirements;
+use crate::{Allocation, Allocator, Buffer, BufferView, DefaultAllocator, Device, Error, Image, ImageCreateInfo, ImageView, MemoryType};
+
+/// Describes a resource that will be placed in [`AliasedMemory`], used to compute the memory requirements of the allocation.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum PlacedResourceInfo {
+    /// A buffer with the given size, created with [`AliasedMemory::create_buffer()`].
+    Buffer(vk::DeviceSize),
+    /// An image created with [`AliasedMemory::create_image()`].
+    Image(ImageCreateInfo),
+}
+
+impl PlacedResourceInfo {
+    /// Get the memory requirements of this resource, without creating it.
+    /// # Errors
+    /// * Fails if the image extents are invalid.
+    pub fn memory_requirements(&self, device: &Device) -> Result<vk::MemoryRequirements> {
+        match self {
+            PlacedResourceInfo::Buffer(size) => Ok(buffer_memory_requirements(device, *size)),
+            PlacedResourceInfo::Image(info) => image_memory_requirements(device, info),
+        }
+    }
+}
+
+/// A resource placed in [`AliasedMemory`] that is about to be used, see [`IncompleteCommandBuffer::activate_aliased()`](crate::IncompleteCommandBuffer::activate_aliased).
+#[derive(Debug, Copy, Clone)]
+pub enum AliasedResource<'a> {
+    /// A range of a placed buffer.
+    Buffer(&'a BufferView),
+    /// A placed image, which is transitioned to `layout`.
+    Image {
+        /// View of the image subresources to activate.
+        view: &'a ImageView,
+        /// Layout the image is used in after activation.
+        layout: vk::ImageLayout,
+    },
+}
+
+/// Memory allocation shared by all resources placed in an [`AliasedMemory`].
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub(crate) struct AliasedAllocation<A: Allocator> {
+    #[derivative(Debug = "ignore")]
+    device: Device,
+    #[derivative(Debug = "ignore")]
+    allocation: A::Allocation,
+    size: vk::DeviceSize,
+    /// Index of the memory type the allocation was made from.
+    memory_type_index: u32,
+}
+
+impl<A: Allocator> AliasedAllocation<A> {
+    /// Verify that a resource with these memory requirements can be placed at `offset`.
+    pub fn check_placement(&self, offset: vk::DeviceSize, requirements: &vk::MemoryRequirements) -> Result<()> {
+        if requirements.memory_type_bits & (1 << self.memory_type_index) == 0 {
+            return Err(Error::IncompatibleAliasedMemory.into());
+        }
+        let fits = offset
+            .checked_add(requirements.size)
+            .is_some_and(|end| end <= self.size);
+        if !fits || (self.allocation.offset() + offset) % requirements.alignment != 0 {
+            return Err(Error::InvalidPlacement {
+                offset,
+                size: requirements.size,
+                alignment: requirements.alignment,
+                memory_size: self.size,
+            }
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// Pick the memory type an allocation in `location` is made from, out of the types allowed by `memory_type_bits`. This prefers
+/// the same memory properties as the allocator does for `location`.
+fn select_memory_type(
+    properties: &vk::PhysicalDeviceMemoryProperties,
+    memory_type_bits: u32,
+    location: MemoryType,
+) -> Option<u32> {
+    let host_visible = vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT;
+    let (required, preferred) = match location {
+        MemoryType::GpuOnly => (vk::MemoryPropertyFlags::empty(), vk::MemoryPropertyFlags::DEVICE_LOCAL),
+        MemoryType::CpuToGpu => (host_visible, vk::MemoryPropertyFlags::DEVICE_LOCAL),
+        MemoryType::GpuToCpu => (host_visible, vk::MemoryPropertyFlags::HOST_CACHED),
+    };
+    let find = |flags: vk::MemoryPropertyFlags| {
+        (0..properties.memory_type_count).find(|&index| {
+            memory_type_bits & (1 << index) != 0 && properties.memory_types[index as usize].property_flags.contains(flags)
+        })
+    };
+    find(required | preferred).or_else(|| find(required))
+}
+
+impl<A: Allocator> Drop for AliasedAllocation<A> {
+    fn drop(&mut self) {
+        self.device
+            .memory_tracker()
+            .free(MemoryCategory::Aliased, self.size);
+    }
+}
+
+/// Memory backing a [`Buffer`] or [`Image`].
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub(crate) enum ResourceMemory<A: Allocator> {
+    /// The resource owns a dedicated allocation.
+    Owned(#[derivative(Debug = "ignore")] A::Allocation),
+    /// The resource is placed in aliased memory, which it keeps alive.
+    Placed {
+        memory: Arc<AliasedAllocation<A>>,
+        offset: vk::DeviceSize,
+    },
+    /// The resource is sparse, its memory is bound through sparse binding operations.
+    Sparse,
+}
+
+impl<A: Allocator> ResourceMemory<A> {
+    /// Whether this memory is a dedicated allocation owned by the resource.
+    pub fn is_owned(&self) -> bool {
+        matches!(self, ResourceMemory::Owned(_))
+    }
+
+    /// Whether the resource is sparse and has no memory bound at creation.
+    pub fn is_sparse(&self) -> bool {
+        matches!(self, ResourceMemory::Sparse)
+    }
+
+    /// Get the `VkDeviceMemory` handle of the memory block this resource is bound to. This is a null handle for sparse resources.
+    /// # Safety
+    /// The handle must not be used after the resource is dropped.
+    pub unsafe fn memory(&self) -> vk::DeviceMemory {
+        match self {
+            ResourceMemory::Owned(allocation) => allocation.memory(),
+            ResourceMemory::Placed {
+                memory,
+                ..
+            } => memory.allocation.memory(),
+            ResourceMemory::Sparse => vk::DeviceMemory::null(),
+        }
+    }
+
+    /// Get the offset of this resource in its `VkDeviceMemory` block.
+    pub fn offset(&self) -> vk::DeviceSize {
+        match self {
+            ResourceMemory::Owned(allocation) => allocation.offset(),
+            ResourceMemory::Placed {
+                memory,
+                offset,
+            } => memory.allocation.offset() + offset,
+            ResourceMemory::Sparse => 0,
+        }
+    }
+
+    /// Get a pointer to the mapped memory of this resource, if it is mappable.
+    pub fn mapped_ptr(&self) -> Option<NonNull<c_void>> {
+        match self {
+            ResourceMemory::Owned(allocation) => allocation.mapped_ptr(),
+            ResourceMemory::Placed {
+                memory,
+                offset,
+            } => memory
+                .allocation
+                .mapped_ptr()
+                // SAFETY: The placement was checked to be inside the allocation, so the offset pointer stays inside the mapping.
+                .map(|ptr| unsafe { NonNull::new_unchecked(ptr.as_ptr().cast::<u8>().add(*offset as usize).cast()) }),
+            ResourceMemory::Sparse => None,
+        }
+    }
+}
+
+/// A memory allocation that buffers and images can be placed in at overlapping offsets.
+/// See the [module-level documentation](self) for more information.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct AliasedMemory<A: Allocator = DefaultAllocator> {
+    memory: Arc<AliasedAllocation<A>>,
+}
+
+// SAFETY: The unsafe part of this is the mapped pointer of the allocation, but this is a pointer to GPU memory
+// so its value is not dropped when sending this to a different thread.
+unsafe impl<A: Allocator> Send for AliasedMemory<A> {}
+
+impl<A: Allocator> AliasedMemory<A> {
+    /// Allocate memory that can hold any of the given resources when placed at offset zero. The size and alignment of the
+    /// allocation are the largest size and alignment of all resources.
+    /// # Errors
+    /// * Fails if `resources` is empty.
+    /// * Fails with [`Error::IncompatibleAliasedMemory`] if no memory type supports all resources.
+    /// * Fails if allocating the memory fails.
+    pub fn new(device: Device, allocator: &mut A, resources: &[PlacedResourceInfo], location: MemoryType) -> Result<Self> {
+        anyhow::ensure!(!resources.is_empty(), "Aliased memory needs at least one resource");
+        let mut requirements = vk::MemoryRequirements {
+            size: 0,
+            alignment: 1,
+            memory_type_bits: u32::MAX,
+        };
+        for resource in resources {
+            let resource = resource.memory_requirements(&device)?;
+            requirements.size = requirements.size.max(resource.size);
+            requirements.alignment = requirements.alignment.max(resource.alignment);
+            requirements.memory_type_bits &= resource.memory_type_bits;
+        }
+        Self::with_requirements(device, allocator, requirements, location)
+    }
+
+    /// Allocate memory with explicit requirements, for example to place resources at different offsets. Use
+    /// [`PlacedResourceInfo::memory_requirements()`] to get the requirements of each resource.
+    /// # Errors
+    /// * Fails with [`Error::IncompatibleAliasedMemory`] if no memory type in `requirements.memory_type_bits` can be used
+    ///   for `location`.
+    /// * Fails if allocating the memory fails.
+    pub fn with_requirements(
+        device: Device,
+        allocator: &mut A,
+        requirements: vk::MemoryRequirements,
+        location: MemoryType,
+    ) -> Result<Self> {
+        // Resources are placed in this memory later, and must support the memory type it was actually allocated from. The
+        // allocator does not report which type it picked, so restrict it to a single type.
+        let memory_type_index = select_memory_type(&device.memory_properties(), requirements.memory_type_bits, location)
+            .ok_or(Error::IncompatibleAliasedMemory)?;
+        let requirements = vk::MemoryRequirements {
+            memory_type_bits: 1 << memory_type_index,
+            ..requirements
+        };
+        let allocation = allocator.allocate("aliased_memory", &requirements, location)?;
+        device
+            .memory_tracker()
+            .allocate(MemoryCategory::Aliased, requirements.size);
+        Ok(Self {
+            memory: Arc::new(AliasedAllocation {
+                device,
+                allocation,
+                size: requirements.size,
+                memory_type_index,
+            }),
+        })
+    }
+
+    /// Get the size of this allocation in bytes.
+    pub fn size(&self) -> vk::DeviceSize {
+        self.memory.size
+    }
+
+    /// Create a buffer placed at `offset` bytes into this memory. The buffer has the same usage flags as one created with
+    /// [`Buffer::new()`]. Its contents are undefined until it is activated.
+    /// # Errors
+    /// * Fails with [`Error::InvalidPlacement`] if the buffer does not fit at `offset`, or `offset` is not suitably aligned.
+    /// * Fails with [`Error::IncompatibleAliasedMemory`] if this memory cannot back the buffer.
+    pub fn create_buffer(&self, offset: vk::DeviceSize, size: vk::DeviceSize) -> Result<Buffer<A>> {
+        Buffer::new_placed(self.memory.device.clone(), &self.memory, offset, size)
+    }
+
+    /// Create an image placed at `offset` bytes into this memory. The memory type in `info` is ignored. The image contents
+    /// are undefined until it is activated.
+    /// # Errors
+    /// * Fails with [`Error::InvalidPlacement`] if the image does not fit at `offset`, or `offset` is not suitably aligned.
+    /// * Fails with [`Error::IncompatibleAliasedMemory`] if this memory cannot back the image.
+    pub fn create_image(&self, offset: vk::DeviceSize, info: ImageCreateInfo) -> Result<Image<A>> {
+        Image::new_placed(self.memory.device.clone(), &self.memory, offset, info)
+    }
+}
@@ -4,7 +4,9 @@
 //! [`BufferView`] does not own a vulkan resource, so it cane be freely copied around as long as the owning [`Buffer`] lives.
 //!
 //! It also exposes some utilities for writing to memory-mapped buffers. For this you can use [`BufferView::mapped_slice`]. This only succeeds
-//! if the buffer was allocated from a mappable heap (one that has the `HOST_VISIBLE` bit set).
+//! if the buffer was allocated from a mappable heap (one that has the `HOST_VISIBLE` bit set). The element type must implement
+//! [`bytemuck::Pod`], and the size and alignment of the view are checked against it. For other types,
+//! [`BufferView::mapped_slice_unchecked`] can be used.
 //!
 //! # Example
 //!
@@ -30,14 +32,19 @@
 
 use std::ffi::c_void;
 use std::ptr::NonNull;
+use std::sync::Arc;
 
 use anyhow::Result;
 use ash::vk;
 use ash::vk::Handle;
+use bytemuck::Pod;
 
+use crate::allocator::statistics::MemoryCategory;
+use crate::resource::aliased_memory::{AliasedAllocation, ResourceMemory};
 use crate::core::device::ExtensionID;
 use crate::core::traits::{AsRaw, Nameable};
 use crate::util::align::align;
+use crate::util::device_size::DeviceSize;
 use crate::{Allocation, Allocator, DefaultAllocator, Device, Error, MemoryType};
 
 /// Wrapper around a [`VkBuffer`](vk::Buffer).
@@ -47,12 +54,14 @@ pub struct Buffer<A: Allocator = DefaultAllocator> {
     #[derivative(Debug = "ignore")]
     device: Device,
     #[derivative(Debug = "ignore")]
-    #[allow(dead_code)]
-    memory: A::Allocation,
+    memory: ResourceMemory<A>,
     address: vk::DeviceAddress,
     pointer: Option<NonNull<c_void>>,
     handle: vk::Buffer,
     size: vk::DeviceSize,
+    category: MemoryCategory,
+    // Size of the memory allocation, which may be larger than the buffer size.
+    memory_size: vk::DeviceSize,
 }
 
 // SAFETY: The unsafe part of this is the mapped pointer, but this is a pointer to GPU memory
@@ -90,6 +99,10 @@ fn get_buffer_usage_flags(device: &Device) -> vk::BufferUsageFlags {
         usage |= vk::BufferUsageFlags::ACCELERATION_STRUCTURE_STORAGE_KHR
             | vk::BufferUsageFlags::ACCELERATION_STRUCTURE_BUILD_INPUT_READ_ONLY_KHR;
     }
+    if device.is_extension_enabled(ExtensionID::OpacityMicromap) {
+        usage |= vk::BufferUsageFlags::MICROMAP_STORAGE_EXT
+            | vk::BufferUsageFlags::MICROMAP_BUILD_INPUT_READ_ONLY_EXT;
+    }
     if device.is_extension_enabled(ExtensionID::RayTracingPipeline) {
         usage |= vk::BufferUsageFlags::SHADER_BINDING_TABLE_KHR;
     }
@@ -97,6 +110,52 @@ fn get_buffer_usage_flags(device: &Device) -> vk::BufferUsageFlags {
     usage
 }
 
+/// Create info for a buffer with all usage flags supported by the device and `extra_usage`. The queue family indices point into
+/// the device, so the create info must not outlive it.
+fn buffer_create_info(
+    device: &Device,
+    size: vk::DeviceSize,
+    extra_usage: vk::BufferUsageFlags,
+    p_next: *const std::ffi::c_void,
+) -> vk::BufferCreateInfo {
+    let sharing_mode = if device.is_single_queue() {
+        vk::SharingMode::EXCLUSIVE
+    } else {
+        vk::SharingMode::CONCURRENT
+    };
+    vk::BufferCreateInfo {
+        s_type: vk::StructureType::BUFFER_CREATE_INFO,
+        p_next,
+        flags: vk::BufferCreateFlags::empty(),
+        size,
+        usage: get_buffer_usage_flags(device) | extra_usage,
+        sharing_mode,
+        queue_family_index_count: if sharing_mode == vk::SharingMode::CONCURRENT {
+            device.queue_families().len() as u32
+        } else {
+            0
+        },
+        p_queue_family_indices: if sharing_mode == vk::SharingMode::CONCURRENT {
+            device.queue_families().as_ptr()
+        } else {
+            std::ptr::null()
+        },
+    }
+}
+
+/// Get the memory requirements of a buffer created with [`Buffer::new()`], without creating it.
+pub(crate) fn buffer_memory_requirements(device: &Device, size: vk::DeviceSize) -> vk::MemoryRequirements {
+    let info = buffer_create_info(device, size, vk::BufferUsageFlags::empty(), std::ptr::null());
+    let query = vk::DeviceBufferMemoryRequirements {
+        p_create_info: &info,
+        ..Default::default()
+    };
+    let mut requirements = vk::MemoryRequirements2::default();
+    // SAFETY: Vulkan API call. The create info is valid for the duration of this call.
+    unsafe { device.get_device_buffer_memory_requirements(&query, &mut requirements) };
+    requirements.memory_requirements
+}
+
 impl<A: Allocator> Buffer<A> {
     /// Allocate a new buffer with a specific size, at a specific memory location.
     /// Buffers are created with all possible usage flags, excecpt for sparse memory flags.
@@ -106,38 +165,21 @@ impl<A: Allocator> Buffer<A> {
         size: impl Into<vk::DeviceSize>,
         location: MemoryType,
     ) -> Result<Self> {
-        let size = size.into();
-        let sharing_mode = if device.is_single_queue() {
-            vk::SharingMode::EXCLUSIVE
-        } else {
-            vk::SharingMode::CONCURRENT
-        };
-
-        let usage = get_buffer_usage_flags(&device);
+        Self::new_with_p_next(device, allocator, size, location, vk::BufferUsageFlags::empty(), std::ptr::null())
+    }
 
-        let handle = unsafe {
-            device.create_buffer(
-                &vk::BufferCreateInfo {
-                    s_type: vk::StructureType::BUFFER_CREATE_INFO,
-                    p_next: std::ptr::null(),
-                    flags: vk::BufferCreateFlags::empty(),
-                    size,
-                    usage,
-                    sharing_mode,
-                    queue_family_index_count: if sharing_mode == vk::SharingMode::CONCURRENT {
-                        device.queue_families().len() as u32
-                    } else {
-                        0
-                    },
-                    p_queue_family_indices: if sharing_mode == vk::SharingMode::CONCURRENT {
-                        device.queue_families().as_ptr()
-                    } else {
-                        std::ptr::null()
-                    },
-                },
-                None,
-            )?
-        };
+    /// Allocate a new buffer with extra usage flags that can not be enabled on every buffer, and extra structures in the `pNext`
+    /// chain of its `VkBufferCreateInfo`. This is used for usage flags that require extra create info, such as video bitstream buffers.
+    pub(crate) fn new_with_p_next(
+        device: Device,
+        allocator: &mut A,
+        size: impl Into<vk::DeviceSize>,
+        location: MemoryType,
+        extra_usage: vk::BufferUsageFlags,
+        p_next: *const std::ffi::c_void,
+    ) -> Result<Self> {
+        let size = size.into();
+        let handle = unsafe { device.create_buffer(&buffer_create_info(&device, size, extra_usage, p_next), None)? };
         #[cfg(feature = "log-objects")]
         trace!("Created new VkBuffer {handle:p} (size = {size} bytes)");
 
@@ -154,13 +196,19 @@ impl<A: Allocator> Buffer<A> {
             })
         };
 
+        device
+            .memory_tracker()
+            .allocate(MemoryCategory::Buffer, requirements.size);
+
         Ok(Self {
             device,
             pointer: memory.mapped_ptr(),
-            memory,
+            memory: ResourceMemory::Owned(memory),
             handle,
             size,
             address,
+            category: MemoryCategory::Buffer,
+            memory_size: requirements.size,
         })
     }
 
@@ -174,32 +222,9 @@ impl<A: Allocator> Buffer<A> {
     ) -> Result<Self> {
         let alignment = alignment.into();
         let size = align(size.into(), alignment);
-        let sharing_mode = if device.is_single_queue() {
-            vk::SharingMode::EXCLUSIVE
-        } else {
-            vk::SharingMode::CONCURRENT
-        };
-        let usage = get_buffer_usage_flags(&device);
         let handle = unsafe {
             device.create_buffer(
-                &vk::BufferCreateInfo {
-                    s_type: vk::StructureType::BUFFER_CREATE_INFO,
-                    p_next: std::ptr::null(),
-                    flags: vk::BufferCreateFlags::empty(),
-                    size,
-                    usage,
-                    sharing_mode,
-                    queue_family_index_count: if sharing_mode == vk::SharingMode::CONCURRENT {
-                        device.queue_families().len() as u32
-                    } else {
-                        0
-                    },
-                    p_queue_family_indices: if sharing_mode == vk::SharingMode::CONCURRENT {
-                        device.queue_families().as_ptr()
-                    } else {
-                        std::ptr::null()
-                    },
-                },
+                &buffer_create_info(&device, size, vk::BufferUsageFlags::empty(), std::ptr::null()),
                 None,
             )?
         };
@@ -220,6 +245,58 @@ impl<A: Allocator> Buffer<A> {
             })
         };
 
+        device
+            .memory_tracker()
+            .allocate(MemoryCategory::Buffer, requirements.size);
+
+        Ok(Self {
+            device,
+            pointer: memory.mapped_ptr(),
+            memory: ResourceMemory::Owned(memory),
+            handle,
+            size,
+            address,
+            category: MemoryCategory::Buffer,
+            memory_size: requirements.size,
+        })
+    }
+
+    /// Create a buffer placed at `offset` into aliased memory. See [`AliasedMemory::create_buffer()`](crate::AliasedMemory::create_buffer).
+    pub(crate) fn new_placed(
+        device: Device,
+        memory: &Arc<AliasedAllocation<A>>,
+        offset: vk::DeviceSize,
+        size: vk::DeviceSize,
+    ) -> Result<Self> {
+        let handle = unsafe {
+            device.create_buffer(
+                &buffer_create_info(&device, size, vk::BufferUsageFlags::empty(), std::ptr::null()),
+                None,
+            )?
+        };
+        #[cfg(feature = "log-objects")]
+        trace!("Created new placed VkBuffer {handle:p} (size = {size} bytes, offset = {offset})");
+
+        let requirements = unsafe { device.get_buffer_memory_requirements(handle) };
+        if let Err(err) = memory.check_placement(offset, &requirements) {
+            unsafe { device.destroy_buffer(handle, None) };
+            return Err(err);
+        }
+        let memory = ResourceMemory::Placed {
+            memory: memory.clone(),
+            offset,
+        };
+
+        unsafe { device.bind_buffer_memory(handle, memory.memory(), memory.offset())? };
+
+        let address = unsafe {
+            device.get_buffer_device_address(&vk::BufferDeviceAddressInfo {
+                s_type: vk::StructureType::BUFFER_DEVICE_ADDRESS_INFO,
+                p_next: std::ptr::null(),
+                buffer: handle,
+            })
+        };
+
         Ok(Self {
             device,
             pointer: memory.mapped_ptr(),
@@ -227,6 +304,49 @@ impl<A: Allocator> Buffer<A> {
             handle,
             size,
             address,
+            category: MemoryCategory::Buffer,
+            memory_size: requirements.size,
+        })
+    }
+
+    /// Create a sparse buffer without any memory bound to it. Memory is bound to ranges of the buffer with
+    /// [`SparseBindBatch::bind_buffer()`](crate::SparseBindBatch::bind_buffer). If `residency` is true, the buffer may be
+    /// partially bound while it is used, otherwise it must be fully bound before use. See the [`sparse`](crate::sparse) module.
+    /// # Errors
+    /// * Fails if the `sparseBinding` feature is not enabled, or `residency` is true and `sparseResidencyBuffer` is not enabled.
+    pub fn new_sparse(device: Device, size: impl Into<vk::DeviceSize>, residency: bool) -> Result<Self> {
+        if device.features().sparse_binding != vk::TRUE {
+            return Err(Error::FeatureNotSupported("sparseBinding").into());
+        }
+        let mut info = buffer_create_info(&device, size.into(), vk::BufferUsageFlags::empty(), std::ptr::null());
+        info.flags = vk::BufferCreateFlags::SPARSE_BINDING;
+        if residency {
+            if device.features().sparse_residency_buffer != vk::TRUE {
+                return Err(Error::FeatureNotSupported("sparseResidencyBuffer").into());
+            }
+            info.flags |= vk::BufferCreateFlags::SPARSE_RESIDENCY;
+        }
+        let handle = unsafe { device.create_buffer(&info, None)? };
+        #[cfg(feature = "log-objects")]
+        trace!("Created new sparse VkBuffer {handle:p} (size = {} bytes)", info.size);
+
+        let address = unsafe {
+            device.get_buffer_device_address(&vk::BufferDeviceAddressInfo {
+                s_type: vk::StructureType::BUFFER_DEVICE_ADDRESS_INFO,
+                p_next: std::ptr::null(),
+                buffer: handle,
+            })
+        };
+
+        Ok(Self {
+            device,
+            memory: ResourceMemory::Sparse,
+            pointer: None,
+            handle,
+            size: info.size,
+            address,
+            category: MemoryCategory::Buffer,
+            memory_size: 0,
         })
     }
 
@@ -243,7 +363,8 @@ impl<A: Allocator> Buffer<A> {
     /// # Lifetime
     /// This view is valid as long as the buffer is valid.
     /// # Errors
-    /// Fails if `offset + size > self.size`.
+    /// * Fails if `offset + size > self.size`.
+    /// * Fails if `offset + size` overflows.
     pub fn view(
         &self,
         offset: impl Into<vk::DeviceSize>,
@@ -251,8 +372,12 @@ impl<A: Allocator> Buffer<A> {
     ) -> Result<BufferView> {
         let offset = offset.into();
         let size = size.into();
-        if offset + size > self.size {
-            Err(anyhow::Error::from(Error::BufferViewOutOfRange))
+        if !DeviceSize::new(self.size).contains_range(offset, size)? {
+            Err(anyhow::Error::from(Error::BufferViewOutOfRange {
+                offset,
+                size,
+                buffer_size: self.size,
+            }))
         } else {
             Ok(BufferView {
                 handle: self.handle,
@@ -302,6 +427,51 @@ impl<A: Allocator> Buffer<A> {
     pub fn address(&self) -> vk::DeviceAddress {
         self.address
     }
+
+    /// Whether this is a sparse buffer created with [`Buffer::new_sparse()`].
+    pub fn is_sparse(&self) -> bool {
+        self.memory.is_sparse()
+    }
+
+    /// Get the memory requirements of this buffer. For sparse buffers, the alignment is the size of a sparse page.
+    pub fn memory_requirements(&self) -> vk::MemoryRequirements {
+        // SAFETY: Vulkan API call. The buffer handle is valid as long as self is.
+        unsafe { self.device.get_buffer_memory_requirements(self.handle) }
+    }
+
+    /// Suggest a residency priority for the memory backing this buffer. Buffers with a higher priority are less likely
+    /// to be demoted to system memory when device local memory is oversubscribed. The priority must be in the range `[0.0, 1.0]`.
+    ///
+    /// The priority applies to the entire memory block this buffer was allocated from, so other resources sharing this block are
+    /// affected as well. See also [`Device::set_memory_priority`].
+    /// # Errors
+    /// * Fails if [`ExtensionID::PageableDeviceLocalMemory`] is not enabled.
+    /// * Fails if the priority is outside the `[0.0, 1.0]` range.
+    /// * Fails if this is a sparse buffer, see [`Buffer::is_sparse()`].
+    pub fn set_priority(&self, priority: f32) -> Result<()> {
+        anyhow::ensure!(!self.memory.is_sparse(), "Cannot set the memory priority of a sparse buffer");
+        // SAFETY: The allocation is owned by this buffer and is valid as long as self is.
+        unsafe { self.device.set_memory_priority(self.memory.memory(), priority) }
+    }
+
+    /// Get the category this buffer's memory is counted in by [`Device::allocation_statistics()`].
+    pub fn memory_category(&self) -> MemoryCategory {
+        self.category
+    }
+
+    /// Set the category this buffer's memory is counted in by [`Device::allocation_statistics()`]. New buffers are
+    /// counted as [`MemoryCategory::Buffer`]. Buffers created by phobos for scratch memory and acceleration structures are
+    /// categorized automatically, but buffers passed to [`AccelerationStructure::new()`](crate::AccelerationStructure::new)
+    /// should be categorized by the caller.
+    pub fn set_memory_category(&mut self, category: MemoryCategory) {
+        // Placed buffers are counted as part of their aliased memory.
+        if self.memory.is_owned() {
+            self.device
+                .memory_tracker()
+                .recategorize(self.category, category, self.memory_size);
+        }
+        self.category = category;
+    }
 }
 
 unsafe impl AsRaw for Buffer {
@@ -321,14 +491,47 @@ impl<A: Allocator> Drop for Buffer<A> {
         unsafe {
             self.device.destroy_buffer(self.handle, None);
         }
+        if self.memory.is_owned() {
+            self.device
+                .memory_tracker()
+                .free(self.category, self.memory_size);
+        }
     }
 }
 
 impl BufferView {
-    /// Obtain a slice to the mapped memory of this buffer.
+    /// Obtain a slice to the mapped memory of this buffer. The size of this view must be a multiple of the size of `T`,
+    /// and the mapped memory must be aligned to the alignment of `T`.
     /// # Errors
-    /// Fails if this buffer is not mappable (not `HOST_VISIBLE`).
-    pub fn mapped_slice<T>(&mut self) -> Result<&mut [T]> {
+    /// * Fails if this buffer is not mappable (not `HOST_VISIBLE`).
+    /// * Fails with [`Error::InvalidMappedSlice`] if the size or alignment of this view does not match `T`.
+    pub fn mapped_slice<T: Pod>(&mut self) -> Result<&mut [T]> {
+        let pointer = self.pointer.ok_or(Error::UnmappableBuffer)?;
+        let element_size = std::mem::size_of::<T>();
+        let element_align = std::mem::align_of::<T>();
+        let valid = element_size != 0
+            && self.size % element_size as vk::DeviceSize == 0
+            && pointer.as_ptr() as usize % element_align == 0;
+        if !valid {
+            return Err(anyhow::Error::from(Error::InvalidMappedSlice {
+                offset: self.offset,
+                size: self.size,
+                element_size,
+                element_align,
+            }));
+        }
+        // SAFETY: The size and alignment were checked above, and any bit pattern is a valid value of a Pod type.
+        unsafe { self.mapped_slice_unchecked() }
+    }
+
+    /// Obtain a slice to the mapped memory of this buffer, for element types that do not implement [`Pod`]. Bytes at the end of the
+    /// view that do not fit a whole element are not part of the slice.
+    /// # Safety
+    /// * The mapped memory must be aligned to the alignment of `T`.
+    /// * Elements must only be read after a valid value of `T` was written to them.
+    /// # Errors
+    /// * Fails if this buffer is not mappable (not `HOST_VISIBLE`).
+    pub unsafe fn mapped_slice_unchecked<T>(&mut self) -> Result<&mut [T]> {
         if let Some(pointer) = self.pointer {
             Ok(unsafe {
                 std::slice::from_raw_parts_mut(
@@ -349,6 +552,36 @@ impl BufferView {
         self.handle
     }
 
+    /// Creates a view into a range of this view. The offset is relative to the start of this view.
+    /// # Errors
+    /// * Fails if `offset + size > self.size()`.
+    /// * Fails if `offset + size` overflows.
+    pub fn view(
+        &self,
+        offset: impl Into<vk::DeviceSize>,
+        size: impl Into<vk::DeviceSize>,
+    ) -> Result<BufferView> {
+        let offset = offset.into();
+        let size = size.into();
+        if !DeviceSize::new(self.size).contains_range(offset, size)? {
+            return Err(anyhow::Error::from(Error::BufferViewOutOfRange {
+                offset,
+                size,
+                buffer_size: self.size,
+            }));
+        }
+        Ok(BufferView {
+            handle: self.handle,
+            pointer: unsafe {
+                self.pointer
+                    .map(|p| NonNull::new(p.as_ptr().offset(offset as isize)).unwrap())
+            },
+            address: self.address + offset,
+            offset: self.offset + offset,
+            size,
+        })
+    }
+
     /// Get the offset of this buffer view into the owning buffer
     pub fn offset(&self) -> vk::DeviceSize {
         self.offset
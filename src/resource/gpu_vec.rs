@@ -0,0 +1,268 @@
+//! A growable array of elements in device local memory.
+//!
+//! A [`GpuVec<T>`] behaves like a [`Vec<T>`] whose elements live in a device local [`TypedBuffer<T>`]. New elements are written
+//! through a staging buffer, with the copy recorded on a command buffer. When the buffer is full, a buffer of at least twice the
+//! capacity is allocated, and a copy of the old elements is recorded before the new ones. The old buffer may still be in use by
+//! frames in flight, so it is kept alive for a number of calls to [`GpuVec::next_frame()`], together with the staging buffers.
+//!
+//! Reallocating changes the device address and [`BufferView`] of the elements. There are two ways to deal with this:
+//! - [`GpuVec::indirect_address()`] and [`GpuVec::indirect_view()`] point to a small buffer that never moves, and always holds
+//!   the device address of the first element. Shaders read the address from it, so it can be bound once, for example in a
+//!   bindless descriptor set or a push constant.
+//! - [`GpuVec::generation()`] is incremented every time the elements move, so descriptors that refer to the elements directly
+//!   can be rewritten when it changes.
+//!
+//! The indirection buffer is updated on the GPU, in the same command buffer as the copy of the old elements, so commands that
+//! execute before that command buffer still see the old address.
+//! # Example
+//! ```
+//! # use anyhow::Result;
+//! # use phobos::prelude::*;
+//! # use phobos::sync::domain::ExecutionDomain;
+//! // Transform matrices of all instances in the scene.
+//! type Instance = [f32; 16];
+//!
+//! fn spawn<'q, D: ExecutionDomain + TransferSupport>(
+//!     cmd: IncompleteCommandBuffer<'q, D>,
+//!     allocator: &mut DefaultAllocator,
+//!     instances: &mut GpuVec<Instance>,
+//!     new: &[Instance],
+//! ) -> Result<IncompleteCommandBuffer<'q, D>> {
+//!     // Once per frame, release buffers that are no longer in use.
+//!     instances.next_frame();
+//!     let cmd = instances.extend(cmd, allocator, new)?;
+//!     // Shaders find the instances through this address, even after the buffer grew.
+//!     let _address = instances.indirect_address();
+//!     Ok(cmd)
+//! }
+//! ```
+
+use anyhow::Result;
+use ash::vk;
+use bytemuck::Pod;
+
+use crate::command_buffer::traits::*;
+use crate::sync::domain::ExecutionDomain;
+use crate::{
+    Allocator, Buffer, BufferView, DefaultAllocator, DeletionQueue, Device, Error, IncompleteCommandBuffer, MemoryType,
+    PipelineStage, TypedBuffer, TypedBufferView,
+};
+
+/// A growable array of elements in a device local buffer. See the [module-level documentation](self).
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""))]
+pub struct GpuVec<T: Pod, A: Allocator = DefaultAllocator> {
+    #[derivative(Debug = "ignore")]
+    device: Device,
+    buffer: Option<TypedBuffer<T, A>>,
+    len: usize,
+    // Always holds the device address of `buffer`, so it can be used as a stable indirection.
+    indirection: TypedBuffer<vk::DeviceAddress, A>,
+    generation: u64,
+    // Staging buffers and replaced buffers may still be in use by frames in flight.
+    #[derivative(Debug = "ignore")]
+    deferred_delete: DeletionQueue<Buffer<A>>,
+}
+
+impl<T: Pod, A: Allocator> GpuVec<T, A> {
+    /// Create a new, empty vector. Memory for the elements is allocated once the first elements are added. Buffers that are
+    /// no longer needed are kept alive for `frames_in_flight` calls to [`GpuVec::next_frame()`], so this should be the number of
+    /// frames that can be in flight at the same time.
+    /// # Errors
+    /// * Fails if `T` is a zero-sized type.
+    /// * Fails if allocating the indirection buffer fails.
+    pub fn new(device: Device, allocator: &mut A, frames_in_flight: u32) -> Result<Self> {
+        anyhow::ensure!(std::mem::size_of::<T>() != 0, "GpuVec cannot hold zero-sized types");
+        Ok(Self {
+            indirection: TypedBuffer::new_device_local(device.clone(), allocator, 1)?,
+            device,
+            buffer: None,
+            len: 0,
+            generation: 0,
+            deferred_delete: DeletionQueue::new(frames_in_flight + 1),
+        })
+    }
+
+    /// Advance the frame counter of the buffers that are waiting to be deleted. Call this once per frame.
+    pub fn next_frame(&mut self) {
+        self.deferred_delete.next_frame();
+    }
+
+    /// Make sure at least `additional` more elements fit without reallocating. If the buffer grows, this records a copy of all
+    /// elements to the new buffer on `cmd`, and updates the indirection buffer to point to it. Commands recorded afterwards
+    /// see the new buffer.
+    /// # Errors
+    /// * Fails if the new capacity overflows.
+    /// * Fails if allocating the new buffer fails.
+    pub fn reserve<'q, D: ExecutionDomain + TransferSupport>(
+        &mut self,
+        cmd: IncompleteCommandBuffer<'q, D, A>,
+        allocator: &mut A,
+        additional: usize,
+    ) -> Result<IncompleteCommandBuffer<'q, D, A>> {
+        let needed = self
+            .len
+            .checked_add(additional)
+            .ok_or_else(|| anyhow::anyhow!("GpuVec capacity overflows"))?;
+        if needed <= self.capacity() {
+            return Ok(cmd);
+        }
+
+        // Grow by at least a factor two, so adding a few elements every frame does not reallocate every frame.
+        let capacity = needed.max(self.capacity() * 2);
+        let buffer = TypedBuffer::<T, A>::new_device_local(self.device.clone(), allocator, capacity)?;
+        let address = buffer.address();
+        let mut cmd = cmd
+            // Previous commands may still be writing to the old buffer or reading the indirection buffer.
+            .memory_barrier(
+                PipelineStage::ALL_COMMANDS,
+                vk::AccessFlags2::MEMORY_WRITE,
+                PipelineStage::TRANSFER,
+                vk::AccessFlags2::TRANSFER_READ | vk::AccessFlags2::TRANSFER_WRITE,
+            )
+            .update_buffer(&self.indirection.view_full().untyped(), std::slice::from_ref(&address))?;
+        if let Some(old) = self.buffer.replace(buffer) {
+            if self.len > 0 {
+                let src = old.view(0, self.len)?;
+                let dst = self.buffer.as_ref().unwrap().view(0, self.len)?;
+                cmd = cmd.copy_buffer(&src.untyped(), &dst.untyped())?;
+            }
+            self.deferred_delete.push(old.into_buffer());
+        }
+        self.generation += 1;
+        Ok(cmd.memory_barrier(
+            PipelineStage::TRANSFER,
+            vk::AccessFlags2::TRANSFER_WRITE,
+            PipelineStage::ALL_COMMANDS,
+            vk::AccessFlags2::MEMORY_READ | vk::AccessFlags2::MEMORY_WRITE,
+        ))
+    }
+
+    /// Append `data` to the end of the vector. This writes the elements to a staging buffer and records a copy on `cmd`,
+    /// followed by a barrier so all commands recorded afterwards see the new elements. The buffer is reallocated first
+    /// if the elements do not fit, see [`GpuVec::reserve()`].
+    /// # Errors
+    /// * Fails if reallocating the buffer fails.
+    /// * Fails if allocating the staging buffer fails.
+    pub fn extend<'q, D: ExecutionDomain + TransferSupport>(
+        &mut self,
+        cmd: IncompleteCommandBuffer<'q, D, A>,
+        allocator: &mut A,
+        data: &[T],
+    ) -> Result<IncompleteCommandBuffer<'q, D, A>> {
+        let cmd = self.reserve(cmd, allocator, data.len())?;
+        let start = self.len;
+        self.len += data.len();
+        let result = self.write(cmd, allocator, start, data);
+        if result.is_err() {
+            self.len = start;
+        }
+        result
+    }
+
+    /// Overwrite the elements starting at `start` with `data`. This writes the elements to a staging buffer and records a copy
+    /// on `cmd`, surrounded by barriers.
+    /// # Errors
+    /// * Fails if `start + data.len() > self.len()`.
+    /// * Fails if allocating the staging buffer fails.
+    pub fn write<'q, D: ExecutionDomain + TransferSupport>(
+        &mut self,
+        cmd: IncompleteCommandBuffer<'q, D, A>,
+        allocator: &mut A,
+        start: usize,
+        data: &[T],
+    ) -> Result<IncompleteCommandBuffer<'q, D, A>> {
+        if data.is_empty() {
+            return Ok(cmd);
+        }
+        let in_range = start
+            .checked_add(data.len())
+            .is_some_and(|end| end <= self.len);
+        if !in_range {
+            return Err(anyhow::Error::from(Error::BufferViewOutOfRange {
+                offset: start as u64,
+                size: data.len() as u64,
+                buffer_size: self.len as u64,
+            }));
+        }
+        // The range is not empty, so memory was allocated.
+        let dst = self.buffer.as_ref().unwrap().view(start, data.len())?;
+        let staging = TypedBuffer::from_slice(self.device.clone(), allocator, data, MemoryType::CpuToGpu)?;
+        let cmd = cmd
+            // Previous commands may still be accessing the elements that are overwritten.
+            .memory_barrier(
+                PipelineStage::ALL_COMMANDS,
+                vk::AccessFlags2::MEMORY_WRITE,
+                PipelineStage::TRANSFER,
+                vk::AccessFlags2::TRANSFER_WRITE,
+            )
+            .copy_buffer(&staging.view_full().untyped(), &dst.untyped())?
+            .memory_barrier(
+                PipelineStage::TRANSFER,
+                vk::AccessFlags2::TRANSFER_WRITE,
+                PipelineStage::ALL_COMMANDS,
+                vk::AccessFlags2::MEMORY_READ | vk::AccessFlags2::MEMORY_WRITE,
+            );
+        self.deferred_delete.push(staging.into_buffer());
+        Ok(cmd)
+    }
+
+    /// Shorten the vector to `len` elements. This does not free any memory. Has no effect if `len` is not smaller than the
+    /// current length.
+    pub fn truncate(&mut self, len: usize) {
+        self.len = self.len.min(len);
+    }
+
+    /// Remove all elements. This does not free any memory.
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Get the number of elements in the vector.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the vector holds no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get the number of elements that fit without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.buffer.as_ref().map(|buffer| buffer.len()).unwrap_or(0)
+    }
+
+    /// Get the number of times the elements moved to a new buffer. Descriptors and device addresses that refer to the
+    /// elements directly must be updated when this changes.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Get a view of all elements. Returns `None` if no memory was allocated yet.
+    /// # Lifetime
+    /// This view is valid until the buffer is reallocated, and the old buffer is deleted after that.
+    pub fn view(&self) -> Option<TypedBufferView<T>> {
+        let buffer = self.buffer.as_ref()?;
+        // The length never exceeds the capacity, so this cannot be out of range.
+        Some(buffer.view(0, self.len).unwrap())
+    }
+
+    /// Get the device address of the first element. Returns zero if no memory was allocated yet. This changes every time the
+    /// buffer is reallocated, see [`GpuVec::indirect_address()`] for an address that does not.
+    pub fn address(&self) -> vk::DeviceAddress {
+        self.buffer.as_ref().map(|buffer| buffer.address()).unwrap_or(0)
+    }
+
+    /// Get the device address of the indirection buffer. This address never changes, and the 8 bytes at it hold the device
+    /// address of the first element once memory for the elements has been allocated.
+    pub fn indirect_address(&self) -> vk::DeviceAddress {
+        self.indirection.address()
+    }
+
+    /// Get a view of the indirection buffer, which holds the device address of the first element. This view never changes,
+    /// so it can be written to a descriptor set once.
+    pub fn indirect_view(&self) -> BufferView {
+        self.indirection.view_full().untyped()
+    }
+}
@@ -20,7 +20,10 @@ use anyhow::Result;
 use ash::vk;
 use ash::vk::Handle;
 
-use crate::{Allocation, Allocator, DefaultAllocator, Device, MemoryType};
+use crate::{Allocation, Allocator, DefaultAllocator, Device, Error, MemoryType};
+use crate::allocator::statistics::MemoryCategory;
+use crate::resource::aliased_memory::{AliasedAllocation, ResourceMemory};
+use crate::resource::ycbcr::validate_multi_planar;
 use crate::core::traits::{AsRaw, Nameable};
 
 /// Abstraction over a [`VkImage`](vk::Image). Stores information about size, format, etc. Additionally couples the image data together
@@ -33,10 +36,12 @@ pub struct Image<A: Allocator = DefaultAllocator> {
     device: Device,
     /// [`VkImage`](vk::Image) handle.
     handle: vk::Image,
-    /// GPU memory allocation. If this is None, then the image is not owned by our system (for example a swapchain image) and should not be
-    /// destroyed.
+    /// GPU memory allocation, or the aliased memory this image is placed in. If this is None, then the image is not owned by our
+    /// system (for example a swapchain image) and should not be destroyed.
     #[derivative(Debug = "ignore")]
-    memory: Option<A::Allocation>,
+    memory: Option<ResourceMemory<A>>,
+    /// Size of the memory allocation, used for allocation statistics. Zero if the image is not owned.
+    memory_size: vk::DeviceSize,
     /// Image format
     format: vk::Format,
     /// Size of the image. Note that this is 3D because 3D images also exist.
@@ -48,6 +53,12 @@ pub struct Image<A: Allocator = DefaultAllocator> {
     mip_levels: u32,
     /// Number of samples. Useful for multisampled attachments
     samples: vk::SampleCountFlags,
+    /// Flags the image was created with.
+    flags: vk::ImageCreateFlags,
+    /// Dimensionality of the image.
+    image_type: vk::ImageType,
+    /// Whether the image is owned by a single queue family, or shared between all of them.
+    sharing_mode: vk::SharingMode,
 }
 
 unsafe impl<A: Allocator> Send for Image<A> {}
@@ -83,8 +94,14 @@ pub struct ImgView {
     level_count: u32,
     /// First array layer in the viewed array layer range.
     base_layer: u32,
-    /// Amount of array layers in the viewed array layer range.
+    /// Amount of array layers in the viewed array layer range. For views of depth slices of a 3D image, this is the amount of slices.
     layer_count: u32,
+    /// Type of the view.
+    view_type: vk::ImageViewType,
+    /// Dimensionality of the viewed image.
+    image_type: vk::ImageType,
+    /// Sharing mode of the viewed image.
+    sharing_mode: vk::SharingMode,
     /// Unique ID for this image view, because vk handles may be reused.
     id: u64,
 }
@@ -104,6 +121,32 @@ pub struct ImageViewCreateInfo {
     pub base_layer: u32,
     /// Number of layers to use in the view (set to None to use the rest)
     pub layers: Option<u32>,
+    /// Format to interpret the image data with (set to None to use the image format). Using a different format than the
+    /// image requires the image to be created with [`vk::ImageCreateFlags::MUTABLE_FORMAT`].
+    pub format: Option<vk::Format>,
+    /// Swizzle of the red, green, blue and alpha components, in that order. Use `Default::default()` for the identity swizzle.
+    pub swizzle: [vk::ComponentSwizzle; 4],
+    /// Usage of the view (set to None to use the usage of the image). This must be a subset of the image usage, and can be used
+    /// to drop usages the view format does not support, for example storage usage on an sRGB view of an
+    /// [`vk::ImageCreateFlags::EXTENDED_USAGE`] image.
+    pub usage: Option<vk::ImageUsageFlags>,
+}
+
+impl Default for ImageViewCreateInfo {
+    /// A 2D view of all mip levels and layers of the color aspect, with the image format and usage.
+    fn default() -> Self {
+        Self {
+            aspect: vk::ImageAspectFlags::COLOR,
+            view_type: vk::ImageViewType::TYPE_2D,
+            base_mip_level: 0,
+            level_count: None,
+            base_layer: 0,
+            layers: None,
+            format: None,
+            swizzle: Default::default(),
+            usage: None,
+        }
+    }
 }
 
 
@@ -130,7 +173,8 @@ pub struct ImageCreateInfo {
     pub width: u32,
     /// Height in pixels of the image
     pub height: u32,
-    /// Depth in pixels of the image, set to 1 for 2D images.
+    /// Depth in pixels of the image, set to 1 for 2D images. Images with a depth larger than 1 are 3D images, which must have
+    /// a single layer and sample.
     pub depth: u32,
     /// Image usage flags
     pub usage: vk::ImageUsageFlags,
@@ -140,12 +184,199 @@ pub struct ImageCreateInfo {
     pub samples: vk::SampleCountFlags,
     /// Number of mip levels. Set to 1 if not using mipmapping
     pub mip_levels: u32,
-    /// Number of array layers. Set to 1 for non-array textures.
+    /// Number of array layers. Set to 1 for non-array textures. Must be a multiple of 6 for cube compatible images.
     pub layers: u32,
+    /// Image creation flags. Use [`vk::ImageCreateFlags::CUBE_COMPATIBLE`] to create cube maps, see [`Image::cube_view()`].
+    /// Use [`vk::ImageCreateFlags::TYPE_2D_ARRAY_COMPATIBLE`] on 3D images to render to their depth slices, see [`Image::slice_view()`].
+    /// Use [`vk::ImageCreateFlags::MUTABLE_FORMAT`] to view the image with a different format, see [`Image::view_with_format()`].
+    /// Sparse flags are set by [`Image::new_sparse()`] and should not be given here.
+    pub flags: vk::ImageCreateFlags,
     /// The memory location that VMA will use for allocating the image
     pub memory_type: MemoryType,
 }
 
+impl Default for ImageCreateInfo {
+    /// A single texel 2D image without usage flags or format, with one mip level, layer and sample, in GPU only memory.
+    /// This is meant to be used with struct update syntax, so new fields do not break existing code.
+    /// # Example
+    /// ```
+    /// # use phobos::prelude::*;
+    /// let info = ImageCreateInfo {
+    ///     width: 1920,
+    ///     height: 1080,
+    ///     usage: vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::SAMPLED,
+    ///     format: vk::Format::R8G8B8A8_SRGB,
+    ///     ..Default::default()
+    /// };
+    /// ```
+    fn default() -> Self {
+        Self {
+            width: 1,
+            height: 1,
+            depth: 1,
+            usage: vk::ImageUsageFlags::empty(),
+            format: vk::Format::UNDEFINED,
+            samples: vk::SampleCountFlags::TYPE_1,
+            mip_levels: 1,
+            layers: 1,
+            flags: vk::ImageCreateFlags::empty(),
+            memory_type: MemoryType::GpuOnly,
+        }
+    }
+}
+
+/// One face of a cube map. Each face is stored in its own array layer, in the order of the variants.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum CubeFace {
+    /// The face in the +X direction, stored in layer 0.
+    PositiveX,
+    /// The face in the -X direction, stored in layer 1.
+    NegativeX,
+    /// The face in the +Y direction, stored in layer 2.
+    PositiveY,
+    /// The face in the -Y direction, stored in layer 3.
+    NegativeY,
+    /// The face in the +Z direction, stored in layer 4.
+    PositiveZ,
+    /// The face in the -Z direction, stored in layer 5.
+    NegativeZ,
+}
+
+impl CubeFace {
+    /// All cube faces, in layer order.
+    pub const ALL: [CubeFace; 6] = [
+        CubeFace::PositiveX,
+        CubeFace::NegativeX,
+        CubeFace::PositiveY,
+        CubeFace::NegativeY,
+        CubeFace::PositiveZ,
+        CubeFace::NegativeZ,
+    ];
+
+    /// Get the array layer of this face, relative to the first layer of its cube.
+    pub fn layer(&self) -> u32 {
+        *self as u32
+    }
+
+    /// Get a short name for this face, such as `px` for [`CubeFace::PositiveX`].
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            CubeFace::PositiveX => "px",
+            CubeFace::NegativeX => "nx",
+            CubeFace::PositiveY => "py",
+            CubeFace::NegativeY => "ny",
+            CubeFace::PositiveZ => "pz",
+            CubeFace::NegativeZ => "nz",
+        }
+    }
+}
+
+/// Get the dimensionality of an image from its extents.
+fn image_type(width: u32, height: u32, depth: u32) -> Result<vk::ImageType> {
+    if width == 0 || height == 0 || depth == 0 {
+        anyhow::bail!("Image extents invalid");
+    }
+    Ok(if height == 1 && depth == 1 {
+        vk::ImageType::TYPE_1D
+    } else if depth > 1 {
+        vk::ImageType::TYPE_3D
+    } else {
+        vk::ImageType::TYPE_2D
+    })
+}
+
+/// Get the sharing mode of an image created with [`Image::new()`]. Attachments are exclusive to a single queue family, other
+/// images are shared between all queue families of the device.
+pub(crate) fn image_sharing_mode(device: &Device, info: &ImageCreateInfo) -> vk::SharingMode {
+    if device.is_single_queue()
+        || info.usage.intersects(
+            vk::ImageUsageFlags::COLOR_ATTACHMENT
+                | vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+        ) {
+        vk::SharingMode::EXCLUSIVE
+    } else {
+        vk::SharingMode::CONCURRENT
+    }
+}
+
+/// Create info for an image with optimal tiling. The queue family indices point into the device, so the create info must not
+/// outlive it.
+fn image_create_info(
+    device: &Device,
+    info: &ImageCreateInfo,
+    p_next: *const std::ffi::c_void,
+) -> Result<vk::ImageCreateInfo> {
+    let sharing_mode = image_sharing_mode(device, info);
+
+    if info.flags.contains(vk::ImageCreateFlags::CUBE_COMPATIBLE) {
+        anyhow::ensure!(
+            info.width == info.height && info.depth == 1,
+            "Cube compatible images must be square and two-dimensional"
+        );
+        anyhow::ensure!(
+            info.layers >= 6 && info.layers % 6 == 0,
+            "Cube compatible images must have a multiple of 6 layers"
+        );
+    }
+
+    validate_multi_planar(info)?;
+    let image_type = image_type(info.width, info.height, info.depth)?;
+    if image_type == vk::ImageType::TYPE_3D {
+        anyhow::ensure!(info.layers == 1, "3D images must have a single layer");
+        anyhow::ensure!(info.samples == vk::SampleCountFlags::TYPE_1, "3D images cannot be multisampled");
+    } else {
+        anyhow::ensure!(
+            !info.flags.contains(vk::ImageCreateFlags::TYPE_2D_ARRAY_COMPATIBLE),
+            "Only 3D images can be created 2D array compatible"
+        );
+    }
+
+    Ok(vk::ImageCreateInfo {
+        s_type: vk::StructureType::IMAGE_CREATE_INFO,
+        p_next,
+        flags: info.flags,
+        image_type,
+        format: info.format,
+        extent: vk::Extent3D {
+            width: info.width,
+            height: info.height,
+            depth: info.depth,
+        },
+        mip_levels: info.mip_levels,
+        array_layers: info.layers,
+        samples: info.samples,
+        tiling: vk::ImageTiling::OPTIMAL,
+        usage: info.usage,
+        sharing_mode,
+        queue_family_index_count: if sharing_mode == vk::SharingMode::CONCURRENT {
+            device.queue_families().len() as u32
+        } else {
+            0
+        },
+        p_queue_family_indices: if sharing_mode == vk::SharingMode::CONCURRENT {
+            device.queue_families().as_ptr()
+        } else {
+            std::ptr::null()
+        },
+        initial_layout: vk::ImageLayout::UNDEFINED,
+    })
+}
+
+/// Get the memory requirements of an image created with [`Image::new()`], without creating it.
+/// # Errors
+/// * Fails if the image extents are invalid.
+pub(crate) fn image_memory_requirements(device: &Device, info: &ImageCreateInfo) -> Result<vk::MemoryRequirements> {
+    let create_info = image_create_info(device, info, std::ptr::null())?;
+    let query = vk::DeviceImageMemoryRequirements {
+        p_create_info: &create_info,
+        ..Default::default()
+    };
+    let mut requirements = vk::MemoryRequirements2::default();
+    // SAFETY: Vulkan API call. The create info is valid for the duration of this call.
+    unsafe { device.get_device_image_memory_requirements(&query, &mut requirements) };
+    Ok(requirements.memory_requirements)
+}
+
 impl<A: Allocator> Image<A> {
     /// Create a new simple [`VkImage`] and allocate some memory to it.
     pub fn new(
@@ -153,61 +384,19 @@ impl<A: Allocator> Image<A> {
         alloc: &mut A,
         info: ImageCreateInfo,
     ) -> Result<Self> {
-        let sharing_mode = if device.is_single_queue()
-            || info.usage.intersects(
-                vk::ImageUsageFlags::COLOR_ATTACHMENT
-                    | vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
-            ) {
-            vk::SharingMode::EXCLUSIVE
-        } else {
-            vk::SharingMode::CONCURRENT
-        };
-
-        let image_type = if info.height == 1 && info.depth == 1 {
-            vk::ImageType::TYPE_1D
-        } else if info.depth > 1 {
-            vk::ImageType::TYPE_3D
-        } else if info.height > 1 {
-            vk::ImageType::TYPE_2D
-        } else {
-            return Err(anyhow::anyhow!("Image extents invalid"));
-        };
+        Self::new_with_p_next(device, alloc, info, std::ptr::null())
+    }
 
-        let extent = vk::Extent3D {
-            width: info.width,
-            height: info.height,
-            depth: info.depth
-        };
-        let handle = unsafe {
-            device.create_image(
-                &vk::ImageCreateInfo {
-                    s_type: vk::StructureType::IMAGE_CREATE_INFO,
-                    p_next: std::ptr::null(),
-                    flags: Default::default(),
-                    image_type,
-                    format: info.format,
-                    extent,
-                    mip_levels: info.mip_levels,
-                    array_layers: info.layers,
-                    samples: info.samples,
-                    tiling: vk::ImageTiling::OPTIMAL,
-                    usage: info.usage,
-                    sharing_mode,
-                    queue_family_index_count: if sharing_mode == vk::SharingMode::CONCURRENT {
-                        device.queue_families().len() as u32
-                    } else {
-                        0
-                    },
-                    p_queue_family_indices: if sharing_mode == vk::SharingMode::CONCURRENT {
-                        device.queue_families().as_ptr()
-                    } else {
-                        std::ptr::null()
-                    },
-                    initial_layout: vk::ImageLayout::UNDEFINED,
-                },
-                None,
-            )?
-        };
+    /// Create a new image with extra structures in the `pNext` chain of its `VkImageCreateInfo`.
+    pub(crate) fn new_with_p_next(
+        device: Device,
+        alloc: &mut A,
+        info: ImageCreateInfo,
+        p_next: *const std::ffi::c_void,
+    ) -> Result<Self> {
+        let create_info = image_create_info(&device, &info, p_next)?;
+        let extent = create_info.extent;
+        let handle = unsafe { device.create_image(&create_info, None)? };
         #[cfg(feature = "log-objects")]
         trace!("Created new VkImage {handle:p}");
 
@@ -218,6 +407,10 @@ impl<A: Allocator> Image<A> {
             device.bind_image_memory(handle, memory.memory(), memory.offset())?;
         }
 
+        device
+            .memory_tracker()
+            .allocate(MemoryCategory::Image, requirements.size);
+
         Ok(Self {
             device,
             handle,
@@ -226,7 +419,104 @@ impl<A: Allocator> Image<A> {
             layers: info.layers,
             mip_levels: info.mip_levels,
             samples: info.samples,
+            flags: info.flags,
+            image_type: create_info.image_type,
+            sharing_mode: create_info.sharing_mode,
+            memory: Some(ResourceMemory::Owned(memory)),
+            memory_size: requirements.size,
+        })
+    }
+
+    /// Create an image placed at `offset` into aliased memory. See [`AliasedMemory::create_image()`](crate::AliasedMemory::create_image).
+    pub(crate) fn new_placed(
+        device: Device,
+        memory: &Arc<AliasedAllocation<A>>,
+        offset: vk::DeviceSize,
+        info: ImageCreateInfo,
+    ) -> Result<Self> {
+        let create_info = image_create_info(&device, &info, std::ptr::null())?;
+        let extent = create_info.extent;
+        let handle = unsafe { device.create_image(&create_info, None)? };
+        #[cfg(feature = "log-objects")]
+        trace!("Created new placed VkImage {handle:p} (offset = {offset})");
+
+        let requirements = unsafe { device.get_image_memory_requirements(handle) };
+        if let Err(err) = memory.check_placement(offset, &requirements) {
+            unsafe { device.destroy_image(handle, None) };
+            return Err(err);
+        }
+        let memory = ResourceMemory::Placed {
+            memory: memory.clone(),
+            offset,
+        };
+        unsafe {
+            device.bind_image_memory(handle, memory.memory(), memory.offset())?;
+        }
+
+        Ok(Self {
+            device,
+            handle,
+            format: info.format,
+            size: extent,
+            layers: info.layers,
+            mip_levels: info.mip_levels,
+            samples: info.samples,
+            flags: info.flags,
+            image_type: create_info.image_type,
+            sharing_mode: create_info.sharing_mode,
             memory: Some(memory),
+            memory_size: requirements.size,
+        })
+    }
+
+    /// Create a sparse image without any memory bound to it. Memory is bound to the image with
+    /// [`SparseBindBatch::bind_image()`](crate::SparseBindBatch::bind_image) or
+    /// [`SparseBindBatch::bind_image_opaque()`](crate::SparseBindBatch::bind_image_opaque). If `residency` is true, the image may be
+    /// partially bound while it is used, otherwise it must be fully bound before use. See the [`sparse`](crate::sparse) module.
+    /// # Errors
+    /// * Fails if the `sparseBinding` feature is not enabled.
+    /// * Fails if `residency` is true and `sparseResidencyImage2D` or `sparseResidencyImage3D` is not enabled for this image type.
+    /// * Fails if the image extents are invalid.
+    pub fn new_sparse(device: Device, info: ImageCreateInfo, residency: bool) -> Result<Self> {
+        let features = device.features();
+        if features.sparse_binding != vk::TRUE {
+            return Err(Error::FeatureNotSupported("sparseBinding").into());
+        }
+        let mut create_info = image_create_info(&device, &info, std::ptr::null())?;
+        create_info.flags |= vk::ImageCreateFlags::SPARSE_BINDING;
+        if residency {
+            match create_info.image_type {
+                vk::ImageType::TYPE_2D if features.sparse_residency_image2_d != vk::TRUE => {
+                    return Err(Error::FeatureNotSupported("sparseResidencyImage2D").into());
+                }
+                vk::ImageType::TYPE_3D if features.sparse_residency_image3_d != vk::TRUE => {
+                    return Err(Error::FeatureNotSupported("sparseResidencyImage3D").into());
+                }
+                vk::ImageType::TYPE_1D => {
+                    return Err(anyhow::anyhow!("Sparse residency is not supported for 1D images"));
+                }
+                _ => {}
+            }
+            create_info.flags |= vk::ImageCreateFlags::SPARSE_RESIDENCY;
+        }
+        let extent = create_info.extent;
+        let handle = unsafe { device.create_image(&create_info, None)? };
+        #[cfg(feature = "log-objects")]
+        trace!("Created new sparse VkImage {handle:p}");
+
+        Ok(Self {
+            device,
+            handle,
+            format: info.format,
+            size: extent,
+            layers: info.layers,
+            mip_levels: info.mip_levels,
+            samples: info.samples,
+            flags: create_info.flags,
+            image_type: create_info.image_type,
+            sharing_mode: create_info.sharing_mode,
+            memory: Some(ResourceMemory::Sparse),
+            memory_size: 0,
         })
     }
 
@@ -243,11 +533,15 @@ impl<A: Allocator> Image<A> {
             device,
             handle,
             memory: None,
+            memory_size: 0,
             format,
             size,
             layers,
             mip_levels,
             samples,
+            flags: vk::ImageCreateFlags::empty(),
+            image_type: vk::ImageType::TYPE_2D,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
         }
     }
 
@@ -259,28 +553,217 @@ impl<A: Allocator> Image<A> {
     /// # Lifetime
     /// The returned [`ImageView`] is valid as long as `self` is valid.
     pub fn whole_view(&self, aspect: vk::ImageAspectFlags) -> Result<ImageView> {
-        let view_type = if self.size.width == 1 && self.size.width == 1 && self.size.depth == 1 {
-            vk::ImageViewType::TYPE_1D
-        } else if self.size.depth > 1 {
-            vk::ImageViewType::TYPE_3D
-        } else if self.size.height > 1 {
-            vk::ImageViewType::TYPE_2D
-        } else {
-            anyhow::bail!("Image extents invalid");
-        };
-
         self.view(
             ImageViewCreateInfo {
                 aspect,
-                view_type,
+                view_type: self.default_view_type(),
                 base_mip_level: 0,
                 level_count: None,
                 base_layer: 0,
                 layers: None,
+                format: None,
+                swizzle: Default::default(),
+                usage: None,
             },
         )
     }
 
+    /// Get the view type that covers all layers of this image: an array view for layered 1D and 2D images, and a 3D view for 3D images.
+    fn default_view_type(&self) -> vk::ImageViewType {
+        match (self.image_type, self.layers) {
+            (vk::ImageType::TYPE_1D, 1) => vk::ImageViewType::TYPE_1D,
+            (vk::ImageType::TYPE_1D, _) => vk::ImageViewType::TYPE_1D_ARRAY,
+            (vk::ImageType::TYPE_3D, _) => vk::ImageViewType::TYPE_3D,
+            (_, 1) => vk::ImageViewType::TYPE_2D,
+            (_, _) => vk::ImageViewType::TYPE_2D_ARRAY,
+        }
+    }
+
+    /// Construct a view of a single mip level of this image, covering all layers. Storage image descriptors access a single
+    /// mip level, so this is the view to bind as a storage image. For 3D images this is a 3D view, so volumes such as froxel
+    /// grids can be written from compute shaders.
+    /// # Errors
+    /// * Fails if `mip_level` is out of range.
+    /// # Lifetime
+    /// The returned [`ImageView`] is valid as long as `self` is valid.
+    pub fn storage_view(&self, mip_level: u32) -> Result<ImageView> {
+        anyhow::ensure!(
+            mip_level < self.mip_levels,
+            "Mip level {mip_level} out of range for an image with {} mip levels",
+            self.mip_levels
+        );
+        self.view(ImageViewCreateInfo {
+            aspect: vk::ImageAspectFlags::COLOR,
+            view_type: self.default_view_type(),
+            base_mip_level: mip_level,
+            level_count: Some(1),
+            base_layer: 0,
+            layers: None,
+            format: None,
+            swizzle: Default::default(),
+            usage: None,
+        })
+    }
+
+    /// Construct a 2D array view of the depth slices of one mip level of this 3D image. Each slice is one layer of the view, so
+    /// the view can be used as a layered attachment to render to the slices. Set `slices` to None to view the remaining slices
+    /// of the mip level.
+    /// # Errors
+    /// * Fails if the image is not a 3D image created with [`vk::ImageCreateFlags::TYPE_2D_ARRAY_COMPATIBLE`].
+    /// * Fails if `mip_level` or the slice range is out of range.
+    /// # Lifetime
+    /// The returned [`ImageView`] is valid as long as `self` is valid.
+    pub fn slice_view(
+        &self,
+        aspect: vk::ImageAspectFlags,
+        mip_level: u32,
+        first_slice: u32,
+        slices: Option<u32>,
+    ) -> Result<ImageView> {
+        anyhow::ensure!(
+            self.image_type == vk::ImageType::TYPE_3D
+                && self.flags.contains(vk::ImageCreateFlags::TYPE_2D_ARRAY_COMPATIBLE),
+            "Slice views can only be created from 3D images that are 2D array compatible"
+        );
+        anyhow::ensure!(
+            mip_level < self.mip_levels,
+            "Mip level {mip_level} out of range for an image with {} mip levels",
+            self.mip_levels
+        );
+        let depth = (self.size.depth >> mip_level).max(1);
+        let slices = slices.unwrap_or(depth.saturating_sub(first_slice));
+        anyhow::ensure!(
+            slices > 0 && first_slice + slices <= depth,
+            "Slices {first_slice}..{} out of range for a mip level with {depth} slices",
+            first_slice + slices
+        );
+        self.view(ImageViewCreateInfo {
+            aspect,
+            view_type: vk::ImageViewType::TYPE_2D_ARRAY,
+            base_mip_level: mip_level,
+            level_count: Some(1),
+            base_layer: first_slice,
+            layers: Some(slices),
+            format: None,
+            swizzle: Default::default(),
+            usage: None,
+        })
+    }
+
+    /// Construct a view of only the depth aspect of this depth or depth/stencil image. This is needed to sample
+    /// depth from an image with a combined depth/stencil format.
+    /// # Lifetime
+    /// The returned [`ImageView`] is valid as long as `self` is valid.
+    pub fn depth_view(&self) -> Result<ImageView> {
+        self.whole_view(vk::ImageAspectFlags::DEPTH)
+    }
+
+    /// Construct a view of only the stencil aspect of this stencil or depth/stencil image. This is needed to sample
+    /// stencil from an image with a combined depth/stencil format.
+    /// # Lifetime
+    /// The returned [`ImageView`] is valid as long as `self` is valid.
+    pub fn stencil_view(&self) -> Result<ImageView> {
+        self.whole_view(vk::ImageAspectFlags::STENCIL)
+    }
+
+    /// Construct a view of the whole image that interprets its data with a different format, for example to write to an sRGB
+    /// image as UNORM from a compute shader. The format must be compatible with the image format.
+    ///
+    /// The image must be created with [`vk::ImageCreateFlags::MUTABLE_FORMAT`]. If the view is used with a usage that the image
+    /// format does not support, such as storage writes to an sRGB image, the image must also be created with
+    /// [`vk::ImageCreateFlags::EXTENDED_USAGE`].
+    /// # Errors
+    /// * Fails if `format` differs from the image format and the image does not have a mutable format.
+    /// # Lifetime
+    /// The returned [`ImageView`] is valid as long as `self` is valid.
+    pub fn view_with_format(&self, aspect: vk::ImageAspectFlags, format: vk::Format) -> Result<ImageView> {
+        self.view(ImageViewCreateInfo {
+            aspect,
+            view_type: self.default_view_type(),
+            base_mip_level: 0,
+            level_count: None,
+            base_layer: 0,
+            layers: None,
+            format: Some(format),
+            swizzle: Default::default(),
+            usage: None,
+        })
+    }
+
+    /// Construct a cube view of this image. If the image has more than 6 layers, this is a cube array view covering all cubes.
+    /// The view covers all mip levels.
+    /// # Errors
+    /// * Fails if the image was not created with [`vk::ImageCreateFlags::CUBE_COMPATIBLE`].
+    /// # Lifetime
+    /// The returned [`ImageView`] is valid as long as `self` is valid.
+    pub fn cube_view(&self, aspect: vk::ImageAspectFlags) -> Result<ImageView> {
+        anyhow::ensure!(self.is_cube_compatible(), "Cannot create a cube view of an image that is not cube compatible");
+        let view_type = if self.layers == 6 {
+            vk::ImageViewType::CUBE
+        } else {
+            vk::ImageViewType::CUBE_ARRAY
+        };
+        self.view(ImageViewCreateInfo {
+            aspect,
+            view_type,
+            base_mip_level: 0,
+            level_count: None,
+            base_layer: 0,
+            layers: None,
+            format: None,
+            swizzle: Default::default(),
+            usage: None,
+        })
+    }
+
+    /// Construct a 2D view of a single array layer of this image, covering all mip levels.
+    /// # Errors
+    /// * Fails if `layer` is out of range.
+    /// # Lifetime
+    /// The returned [`ImageView`] is valid as long as `self` is valid.
+    pub fn layer_view(&self, aspect: vk::ImageAspectFlags, layer: u32) -> Result<ImageView> {
+        anyhow::ensure!(layer < self.layers, "Layer {layer} out of range for an image with {} layers", self.layers);
+        self.view(ImageViewCreateInfo {
+            aspect,
+            view_type: vk::ImageViewType::TYPE_2D,
+            base_mip_level: 0,
+            level_count: None,
+            base_layer: layer,
+            layers: Some(1),
+            format: None,
+            swizzle: Default::default(),
+            usage: None,
+        })
+    }
+
+    /// Construct a 2D view of a single mip level of one face of this cube map. Because it covers a single subresource, this view
+    /// can be used as an attachment to render to the face. For cube arrays, this views a face of the first cube, use
+    /// [`Image::view()`] for the other cubes.
+    /// # Errors
+    /// * Fails if the image was not created with [`vk::ImageCreateFlags::CUBE_COMPATIBLE`].
+    /// * Fails if `mip_level` is out of range.
+    /// # Lifetime
+    /// The returned [`ImageView`] is valid as long as `self` is valid.
+    pub fn face_view(&self, aspect: vk::ImageAspectFlags, face: CubeFace, mip_level: u32) -> Result<ImageView> {
+        anyhow::ensure!(self.is_cube_compatible(), "Cannot create a face view of an image that is not cube compatible");
+        anyhow::ensure!(
+            mip_level < self.mip_levels,
+            "Mip level {mip_level} out of range for an image with {} mip levels",
+            self.mip_levels
+        );
+        self.view(ImageViewCreateInfo {
+            aspect,
+            view_type: vk::ImageViewType::TYPE_2D,
+            base_mip_level: mip_level,
+            level_count: Some(1),
+            base_layer: face.layer(),
+            layers: Some(1),
+            format: None,
+            swizzle: Default::default(),
+            usage: None,
+        })
+    }
+
     /// Construct an [`ImageView`] from this [`Image`]. This is an image view that views the
     /// image subresource specified by the given arguments.
     /// * `aspect` - The image aspect flags that will be used to create the image view
@@ -289,6 +772,9 @@ impl<A: Allocator> Image<A> {
     /// * `level_count` - End mip level that the view sub-resource will look to. Set to None to get the remainder of the mip levels
     /// * `base_layer` - Starting layer that the view sub-resource will look from
     /// * `layers` - End layer that the view sub-resource will look to. Set to None to get the remainder of the layer
+    /// * `format` - Format to interpret the image with. Set to None to use the image format
+    /// * `swizzle` - Component swizzle applied when reading from the view
+    /// * `usage` - Usage of the view, must be a subset of the image usage. Set to None to use the image usage
     /// <br>
     /// <br>
     /// # Lifetime
@@ -296,6 +782,17 @@ impl<A: Allocator> Image<A> {
     pub fn view(
         &self,
         create_info: ImageViewCreateInfo,
+    ) -> Result<ImageView> {
+        self.create_view(create_info, std::ptr::null(), self.size)
+    }
+
+    /// Create an image view with extra structures in the `pNext` chain of its `VkImageViewCreateInfo`. `size` is the size of the
+    /// viewed image data, which differs from the image size for views of a subsampled plane.
+    pub(crate) fn create_view(
+        &self,
+        create_info: ImageViewCreateInfo,
+        p_next: *const std::ffi::c_void,
+        size: vk::Extent3D,
     ) -> Result<ImageView> {
         let ImageViewCreateInfo {
             aspect,
@@ -303,18 +800,39 @@ impl<A: Allocator> Image<A> {
             base_mip_level,
             level_count,
             base_layer,
-            layers
+            layers,
+            format,
+            swizzle,
+            usage,
         } = create_info;
+        let format = format.unwrap_or(self.format);
+        if format != self.format && !self.flags.contains(vk::ImageCreateFlags::MUTABLE_FORMAT) {
+            anyhow::bail!("Cannot view an image as {format:?} without creating it with a mutable format");
+        }
+
+        let usage_info = usage.map(|usage| vk::ImageViewUsageCreateInfo {
+            s_type: vk::StructureType::IMAGE_VIEW_USAGE_CREATE_INFO,
+            p_next,
+            usage,
+        });
 
         // TODO: Validate args
         let info = vk::ImageViewCreateInfo {
             s_type: vk::StructureType::IMAGE_VIEW_CREATE_INFO,
-            p_next: std::ptr::null(),
+            p_next: match &usage_info {
+                Some(usage_info) => usage_info as *const _ as *const std::ffi::c_void,
+                None => p_next,
+            },
             flags: Default::default(),
             image: self.handle,
             view_type,
-            format: self.format,
-            components: vk::ComponentMapping::default(),
+            format,
+            components: vk::ComponentMapping {
+                r: swizzle[0],
+                g: swizzle[1],
+                b: swizzle[2],
+                a: swizzle[3],
+            },
             subresource_range: vk::ImageSubresourceRange {
                 aspect_mask: aspect,
                 base_mip_level,
@@ -337,14 +855,17 @@ impl<A: Allocator> Image<A> {
             device: self.device.clone(),
             handle: view_handle,
             image: self.handle,
-            format: self.format,
+            format,
             samples: self.samples,
             aspect,
-            size: self.size,
+            size,
             base_level: base_mip_level,
             level_count: info.subresource_range.level_count,
             base_layer,
             layer_count: info.subresource_range.layer_count,
+            view_type,
+            image_type: self.image_type,
+            sharing_mode: self.sharing_mode,
             id: ImgView::get_new_id(),
         })))
     }
@@ -354,6 +875,51 @@ impl<A: Allocator> Image<A> {
         self.memory.is_some()
     }
 
+    /// Whether this is a sparse image created with [`Image::new_sparse()`].
+    pub fn is_sparse(&self) -> bool {
+        self.memory.as_ref().is_some_and(|memory| memory.is_sparse())
+    }
+
+    /// Get the memory requirements of this image. For sparse images, the alignment is the size of a sparse page.
+    pub fn memory_requirements(&self) -> vk::MemoryRequirements {
+        // SAFETY: Vulkan API call. The image handle is valid as long as self is.
+        unsafe { self.device.get_image_memory_requirements(self.handle) }
+    }
+
+    /// Get the sparse memory requirements of this image. These describe the block size of each aspect, and where its mip tail starts.
+    /// Empty if this is not a sparse image.
+    pub fn sparse_memory_requirements(&self) -> Vec<vk::SparseImageMemoryRequirements> {
+        if !self.is_sparse() {
+            return Vec::new();
+        }
+        // SAFETY: Vulkan API call. The image handle is valid as long as self is.
+        unsafe { self.device.get_image_sparse_memory_requirements(self.handle) }
+    }
+
+    /// Suggest a residency priority for the memory backing this image. Use this to keep important render targets
+    /// in device local memory when it is oversubscribed. The priority must be in the range `[0.0, 1.0]`.
+    ///
+    /// The priority applies to the entire memory block this image was allocated from, so other resources sharing this block are
+    /// affected as well. See also [`Device::set_memory_priority`].
+    /// # Errors
+    /// * Fails if [`ExtensionID::PageableDeviceLocalMemory`](crate::core::device::ExtensionID::PageableDeviceLocalMemory) is not enabled.
+    /// * Fails if the priority is outside the `[0.0, 1.0]` range.
+    /// * Fails if this image is not owned by the application, see [`Image::is_owned`].
+    /// * Fails if this is a sparse image, see [`Image::is_sparse`].
+    pub fn set_priority(&self, priority: f32) -> Result<()> {
+        let memory = self.memory.as_ref().ok_or(Error::Uncategorized(
+            "Cannot set the memory priority of an image that is not owned by the application.",
+        ))?;
+        anyhow::ensure!(!memory.is_sparse(), "Cannot set the memory priority of a sparse image");
+        // SAFETY: The allocation is owned by this image and is valid as long as self is.
+        unsafe { self.device.set_memory_priority(memory.memory(), priority) }
+    }
+
+    /// Get the device this image was created on.
+    pub(crate) fn device(&self) -> &Device {
+        &self.device
+    }
+
     /// Get unsafe access to the underlying `VkImage` handle.
     /// # Safety
     /// Any vulkan calls that mutate this image's state may put the system into an undefined state.
@@ -400,6 +966,29 @@ impl<A: Allocator> Image<A> {
     pub fn samples(&self) -> vk::SampleCountFlags {
         self.samples
     }
+
+    /// Get the flags this image was created with.
+    pub fn flags(&self) -> vk::ImageCreateFlags {
+        self.flags
+    }
+
+    /// Get the dimensionality of this image.
+    pub fn image_type(&self) -> vk::ImageType {
+        self.image_type
+    }
+
+    /// Whether this image was created with [`vk::ImageCreateFlags::CUBE_COMPATIBLE`], so cube views can be made from it.
+    pub fn is_cube_compatible(&self) -> bool {
+        self.flags.contains(vk::ImageCreateFlags::CUBE_COMPATIBLE)
+    }
+
+    /// Get the sharing mode of this image. On devices with multiple queue families, images are created with
+    /// [`vk::SharingMode::CONCURRENT`], except images with attachment usage. Those are [`vk::SharingMode::EXCLUSIVE`],
+    /// so using them on a queue of another family requires a queue family ownership transfer, see
+    /// [`IncompleteCommandBuffer::release_image()`](crate::IncompleteCommandBuffer::release_image).
+    pub fn sharing_mode(&self) -> vk::SharingMode {
+        self.sharing_mode
+    }
 }
 
 unsafe impl AsRaw for Image {
@@ -420,6 +1009,12 @@ impl<A: Allocator> Drop for Image<A> {
             unsafe {
                 self.device.destroy_image(self.handle, None);
             }
+            // Placed images are counted as part of their aliased memory.
+            if self.memory.as_ref().is_some_and(|memory| memory.is_owned()) {
+                self.device
+                    .memory_tracker()
+                    .free(MemoryCategory::Image, self.memory_size);
+            }
         }
     }
 }
@@ -430,14 +1025,25 @@ impl ImgView {
         COUNTER.fetch_add(1, Ordering::Relaxed)
     }
 
-    /// Returns the subresource range of the original image that this image view covers.
+    /// Get the device this image view was created on.
+    pub(crate) fn device(&self) -> &Device {
+        &self.device
+    }
+
+    /// Returns the subresource range of the original image that this image view covers. 3D images have a single layer, so
+    /// for views of their depth slices this covers the whole mip level.
     pub fn subresource_range(&self) -> vk::ImageSubresourceRange {
+        let (base_array_layer, layer_count) = if self.image_type == vk::ImageType::TYPE_3D {
+            (0, 1)
+        } else {
+            (self.base_layer, self.layer_count)
+        };
         vk::ImageSubresourceRange {
             aspect_mask: self.aspect,
             base_mip_level: self.base_level,
             level_count: self.level_count,
-            base_array_layer: self.base_layer,
-            layer_count: self.layer_count,
+            base_array_layer,
+            layer_count,
         }
     }
 
@@ -465,11 +1071,25 @@ impl ImgView {
         self.samples
     }
 
+    /// Get the sharing mode of the viewed image. See [`Image::sharing_mode()`].
+    pub fn sharing_mode(&self) -> vk::SharingMode {
+        self.sharing_mode
+    }
+
     /// Get the image aspect that this view was built from
     pub fn aspect(&self) -> vk::ImageAspectFlags {
         self.aspect
     }
 
+    /// Get the size of the first mip level this view was made from.
+    pub fn base_level_size(&self) -> vk::Extent3D {
+        vk::Extent3D {
+            width: (self.size.width >> self.base_level).max(1),
+            height: (self.size.height >> self.base_level).max(1),
+            depth: (self.size.depth >> self.base_level).max(1),
+        }
+    }
+
     /// Get the image size
     pub fn size(&self) -> vk::Extent3D {
         self.size
@@ -500,6 +1120,11 @@ impl ImgView {
         self.layer_count
     }
 
+    /// Get the type of this view
+    pub fn view_type(&self) -> vk::ImageViewType {
+        self.view_type
+    }
+
     /// Get the first mip level this view was made from
     pub fn base_level(&self) -> u32 {
         self.base_level
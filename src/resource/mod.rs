@@ -1,8 +1,14 @@
 //! Exposes common Vulkan resources such as buffers and images.
 
+pub mod aliased_memory;
 pub mod buffer;
+pub mod gpu_vec;
 pub mod image;
 pub mod pool;
 pub mod query_pool;
 pub mod raytracing;
 pub mod sampler;
+pub mod sparse;
+pub mod texel_buffer;
+pub mod typed_buffer;
+pub mod ycbcr;
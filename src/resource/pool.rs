@@ -15,6 +15,9 @@ use crate::{
     Allocator, BufferView, DefaultAllocator, DescriptorCache, Device, Fence, PipelineCache,
     ScratchAllocator,
 };
+use crate::allocator::statistics::{self, MemoryReport};
+use crate::query_pool::{PipelineStatisticsQuery, QueryPool, QueryPoolCreateInfo, TimestampQuery};
+use crate::sampler::{Sampler, SamplerCache, SamplerCreateInfo};
 
 /// Indicates that this object can be pooled in a [`Pool`](crate::pool::Pool)
 pub trait Poolable {
@@ -48,7 +51,7 @@ pub struct Pooled<P: Poolable> {
     key: Option<P::Key>,
 }
 
-type BoxedCreateFunc<P> = Box<dyn FnMut(&<P as Poolable>::Key) -> Result<P>>;
+type BoxedCreateFunc<P> = Box<dyn FnMut(&<P as Poolable>::Key) -> Result<P> + Send>;
 
 struct PoolInner<P: Poolable> {
     items: MultiMap<P::Key, P>,
@@ -64,16 +67,26 @@ pub struct Pool<P: Poolable> {
 #[derive(Clone, Derivative)]
 #[derivative(Debug)]
 pub struct ResourcePool<A: Allocator = DefaultAllocator> {
+    #[derivative(Debug = "ignore")]
+    device: Device,
     /// Pipeline cache used to create pipelines on demand
     pub pipelines: PipelineCache<A>,
     /// Descriptor cache used to create descriptor sets on demand
     pub descriptors: DescriptorCache,
+    /// Sampler cache used to share samplers with the same settings
+    pub samplers: SamplerCache,
     /// Scratch allocator pool used to easily create scratch buffers anywhere
     #[derivative(Debug = "ignore")]
     pub allocators: Pool<ScratchAllocator<A>>,
     /// Fence pool to reuse fences where possible
     #[derivative(Debug = "ignore")]
     pub fences: Pool<Fence<()>>,
+    /// Timestamp query pools, reset when they are released so they can be reused every frame
+    #[derivative(Debug = "ignore")]
+    pub timestamp_queries: Pool<QueryPool<TimestampQuery>>,
+    /// Pipeline statistics query pools, reset when they are released so they can be reused every frame
+    #[derivative(Debug = "ignore")]
+    pub pipeline_statistics_queries: Pool<QueryPool<PipelineStatisticsQuery>>,
 }
 
 /// Information needed to create a resource pool
@@ -89,7 +102,6 @@ pub struct ResourcePoolCreateInfo<A: Allocator = DefaultAllocator> {
 /// A local pool that will release its resources back to the main resource pool when it goes out of scope.
 /// Such a scope could be a frame context, or a task spawned on a background thread.
 pub struct LocalPool<A: Allocator = DefaultAllocator> {
-    #[allow(dead_code)]
     pool: ResourcePool<A>,
     scratch_allocator: Pooled<ScratchAllocator<A>>,
 }
@@ -185,7 +197,7 @@ impl<P: Poolable> Pool<P> {
     /// Create a new pool. This must be supplied with a callback to be called
     /// when the pool needs to allocate a new object.
     /// Optionally also takes in a count of objects to preallocate using this callback.
-    pub fn new(create_fn: impl FnMut(&P::Key) -> Result<P> + 'static) -> Result<Self> {
+    pub fn new(create_fn: impl FnMut(&P::Key) -> Result<P> + Send + 'static) -> Result<Self> {
         let inner = PoolInner {
             items: MultiMap::new(),
             create_fn: Box::new(create_fn),
@@ -202,6 +214,7 @@ impl<A: Allocator + 'static> ResourcePool<A> {
     pub fn new(info: ResourcePoolCreateInfo<A>) -> Result<Self> {
         let pipelines = PipelineCache::new(info.device.clone(), info.allocator.clone())?;
         let descriptors = DescriptorCache::new(info.device.clone())?;
+        let samplers = SamplerCache::new(info.device.clone());
         let device = info.device.clone();
         let mut alloc = info.allocator.clone();
         let allocators = Pool::new(move |_| {
@@ -209,12 +222,20 @@ impl<A: Allocator + 'static> ResourcePool<A> {
         })?;
         let device = info.device.clone();
         let fences = Pool::new(move |_| Ok(Fence::new(device.clone(), false)?))?;
+        let device = info.device.clone();
+        let timestamp_queries = Pool::new(move |info| QueryPool::new(device.clone(), *info))?;
+        let device = info.device.clone();
+        let pipeline_statistics_queries = Pool::new(move |info| QueryPool::new(device.clone(), *info))?;
 
         Ok(Self {
+            device: info.device.clone(),
             pipelines,
             descriptors,
+            samplers,
             allocators,
             fences,
+            timestamp_queries,
+            pipeline_statistics_queries,
         })
     }
 }
@@ -230,11 +251,58 @@ impl<A: Allocator> ResourcePool<A> {
         )
     }
 
+    /// Get a timestamp query pool with `count` queries from the pool. The query pool is reset and returned to the pool when
+    /// it is dropped, so keep it alive until its results were read, for example by storing it with the data of the frame it is used in.
+    /// # Example
+    /// ```
+    /// # use phobos::prelude::*;
+    /// # use phobos::pool::Pooled;
+    /// # use anyhow::Result;
+    /// fn frame_queries(pool: &ResourcePool) -> Result<Pooled<QueryPool<TimestampQuery>>> {
+    ///     // Reuses a query pool released in an earlier frame if one is available.
+    ///     pool.get_timestamp_query_pool(16)
+    /// }
+    /// ```
+    pub fn get_timestamp_query_pool(&self, count: u32) -> Result<Pooled<QueryPool<TimestampQuery>>> {
+        QueryPool::new_in_pool(
+            &self.timestamp_queries,
+            &QueryPoolCreateInfo {
+                count,
+                statistic_flags: None,
+            },
+        )
+    }
+
+    /// Get a pipeline statistics query pool with `count` queries, recording the statistics in `flags`, from the pool.
+    /// The query pool is reset and returned to the pool when it is dropped.
+    pub fn get_pipeline_statistics_query_pool(
+        &self,
+        count: u32,
+        flags: vk::QueryPipelineStatisticFlags,
+    ) -> Result<Pooled<QueryPool<PipelineStatisticsQuery>>> {
+        QueryPool::new_in_pool(
+            &self.pipeline_statistics_queries,
+            &QueryPoolCreateInfo {
+                count,
+                statistic_flags: Some(flags),
+            },
+        )
+    }
+
     /// Advance internal caches to reclaim resources when possible
     pub fn next_frame(&self) {
         self.pipelines.next_frame();
         self.descriptors.next_frame();
     }
+
+    /// Get a report of the memory usage of the device this pool was created for, containing allocation statistics per
+    /// [`MemoryCategory`](crate::MemoryCategory) and, if [`ExtensionID::MemoryBudget`](crate::ExtensionID::MemoryBudget) is enabled,
+    /// the usage and budget of every memory heap. See also the [`statistics`](crate::allocator::statistics) module.
+    /// # Errors
+    /// * Fails if querying the memory budget fails.
+    pub fn memory_report(&self) -> Result<MemoryReport> {
+        statistics::memory_report(&self.device)
+    }
 }
 
 impl<A: Allocator> LocalPool<A> {
@@ -253,4 +321,11 @@ impl<A: Allocator> LocalPool<A> {
     pub fn allocate_scratch_buffer(&mut self, size: vk::DeviceSize) -> Result<BufferView> {
         self.scratch_allocator.allocate(size)
     }
+
+    /// Get a sampler with the given settings from the sampler cache of the global resource pool.
+    /// This allows passes to request samplers by description, instead of storing them.
+    /// See also: [`SamplerCache`]
+    pub fn get_sampler(&self, info: &SamplerCreateInfo) -> Result<Arc<Sampler>> {
+        self.pool.samplers.get(info)
+    }
 }
@@ -1,18 +1,28 @@
 //! Abstraction for `VkQueryPool` objects.
 
+use std::collections::{HashMap, VecDeque};
 use std::ops::Sub;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+use std::ffi::CStr;
 
 use anyhow::{ensure, Result};
 use ash::vk;
 
-use crate::{Device, PipelineStage};
+use crate::core::device::ExtensionID;
+use crate::pool::Poolable;
+use crate::{Device, Error, PhysicalDevice, PipelineStage};
 
 /// Trait that must be implemented for each Vulkan query
 pub trait Query: Clone + Sized {
     /// The Vulkan query type
     const QUERY_TYPE: vk::QueryType;
 
+    /// Flags used to read back the results of this query. Each result item is read as a 64-bit value.
+    const RESULT_FLAGS: vk::QueryResultFlags = vk::QueryResultFlags::from_raw(
+        vk::QueryResultFlags::TYPE_64.as_raw() | vk::QueryResultFlags::WAIT.as_raw(),
+    );
+
     /// Output data from this query
     type Output;
 
@@ -66,6 +76,108 @@ impl Sub<Timestamp> for Timestamp {
     }
 }
 
+// The host time domain that `Instant` is based on.
+#[cfg(windows)]
+const HOST_TIME_DOMAIN: vk::TimeDomainEXT = vk::TimeDomainEXT::QUERY_PERFORMANCE_COUNTER;
+#[cfg(not(windows))]
+const HOST_TIME_DOMAIN: vk::TimeDomainEXT = vk::TimeDomainEXT::CLOCK_MONOTONIC;
+
+/// A device timestamp and a host timestamp that were sampled at the same moment. This can be used to place
+/// [`Timestamp`]s obtained from timestamp queries on the host timeline, so CPU and GPU spans can be shown side by side.
+/// Requires [`ExtensionID::CalibratedTimestamps`].
+/// # Example
+/// ```
+/// # use anyhow::Result;
+/// # use phobos::prelude::*;
+/// # use std::time::Instant;
+/// fn gpu_span(device: &Device, begin: Timestamp, end: Timestamp) -> Result<(Instant, Instant)> {
+///     let calibration = TimestampCalibration::calibrate(device)?;
+///     Ok((calibration.to_instant(begin), calibration.to_instant(end)))
+/// }
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct TimestampCalibration {
+    device: Timestamp,
+    host: u64,
+    host_domain: vk::TimeDomainEXT,
+    instant: Instant,
+    max_deviation: u64,
+}
+
+impl TimestampCalibration {
+    /// Sample the device clock together with the host clock used by [`Instant`].
+    /// # Errors
+    /// * Fails if [`ExtensionID::CalibratedTimestamps`] is not enabled.
+    /// * Fails if the device cannot calibrate its timestamps against the host clock.
+    pub fn calibrate(device: &Device) -> Result<Self> {
+        device.require_extension(ExtensionID::CalibratedTimestamps)?;
+        let domains = device.calibrateable_time_domains();
+        ensure!(
+            domains.contains(&vk::TimeDomainEXT::DEVICE) && domains.contains(&HOST_TIME_DOMAIN),
+            "device cannot calibrate timestamps against time domain {:?}",
+            HOST_TIME_DOMAIN
+        );
+        let fns = device.calibrated_timestamps().unwrap();
+        let infos = [
+            vk::CalibratedTimestampInfoEXT {
+                time_domain: vk::TimeDomainEXT::DEVICE,
+                ..Default::default()
+            },
+            vk::CalibratedTimestampInfoEXT {
+                time_domain: HOST_TIME_DOMAIN,
+                ..Default::default()
+            },
+        ];
+        // We cannot read the raw value of an Instant, so use the midpoint of the call as the calibrated instant.
+        let before = Instant::now();
+        // SAFETY: Vulkan API call. The device handle is valid, and the extension is enabled.
+        let (timestamps, max_deviation) = unsafe { fns.get_calibrated_timestamps(device.handle().handle(), &infos)? };
+        let after = Instant::now();
+        Ok(Self {
+            device: Timestamp {
+                value: timestamps[0],
+                period: device.properties().limits.timestamp_period,
+            },
+            host: timestamps[1],
+            host_domain: HOST_TIME_DOMAIN,
+            instant: before + (after - before) / 2,
+            // Only a single deviation is written, the rest of this vector is unused.
+            max_deviation: max_deviation[0],
+        })
+    }
+
+    /// Get the device timestamp that was sampled
+    pub fn device_timestamp(&self) -> Timestamp {
+        self.device
+    }
+
+    /// Get the raw host timestamp that was sampled, in the units of [`TimestampCalibration::host_time_domain()`]
+    pub fn host_timestamp(&self) -> u64 {
+        self.host
+    }
+
+    /// Get the time domain of the host timestamp
+    pub fn host_time_domain(&self) -> vk::TimeDomainEXT {
+        self.host_domain
+    }
+
+    /// Get the maximum deviation between the sampled device and host timestamps
+    pub fn max_deviation(&self) -> Duration {
+        Duration::from_nanos(self.max_deviation)
+    }
+
+    /// Convert a device timestamp to the moment it corresponds to on the host timeline
+    pub fn to_instant(&self, timestamp: Timestamp) -> Instant {
+        let device = self.device.nanoseconds();
+        let value = timestamp.nanoseconds();
+        if value >= device {
+            self.instant + Duration::from_nanos(value - device)
+        } else {
+            self.instant - Duration::from_nanos(device - value)
+        }
+    }
+}
+
 /// A timestamp query
 #[derive(Default, Copy, Clone)]
 pub struct TimestampQuery {
@@ -135,9 +247,9 @@ pub struct PipelineStatistics {
     pub tessellation_evaluation_shader_invocations: Option<u64>,
     /// Number of compute shader invocations
     pub compute_shader_invocations: Option<u64>,
-    /// Number of task shader invocations
+    /// Number of task shader invocations. Requires the `mesh_shader_queries` feature of [`ExtensionID::MeshShader`].
     pub task_shader_invocations: Option<u64>,
-    /// Number of mesh shader invocations
+    /// Number of mesh shader invocations. Requires the `mesh_shader_queries` feature of [`ExtensionID::MeshShader`].
     pub mesh_shader_invocations: Option<u64>,
 }
 
@@ -242,6 +354,230 @@ impl Query for PipelineStatisticsQuery {
 
 impl ScopedQuery for PipelineStatisticsQuery {}
 
+/// Query for the number of primitives emitted by the mesh shader stage, through `VK_EXT_mesh_shader`.
+/// Requires the `mesh_shader_queries` feature of [`ExtensionID::MeshShader`].
+#[derive(Default, Clone, Copy)]
+pub struct MeshPrimitivesGeneratedQuery;
+
+impl Query for MeshPrimitivesGeneratedQuery {
+    const QUERY_TYPE: vk::QueryType = vk::QueryType::MESH_PRIMITIVES_GENERATED_EXT;
+    type Output = u64;
+
+    fn new(_pool: &QueryPoolCreateInfo) -> Self {
+        Self
+    }
+
+    fn size(&self) -> usize {
+        1
+    }
+
+    fn parse_query(&self, _device: &Device, data: &[u64]) -> Self::Output {
+        *data.first().unwrap()
+    }
+}
+
+impl ScopedQuery for MeshPrimitivesGeneratedQuery {}
+
+/// Summary of a single pipeline statistic over a window of samples.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct StatisticSummary {
+    /// Number of samples this summary was computed from
+    pub samples: usize,
+    /// Average value over all samples
+    pub average: f64,
+    /// Smallest sampled value
+    pub min: u64,
+    /// Largest sampled value
+    pub max: u64,
+    /// Median value
+    pub p50: u64,
+    /// 95th percentile value
+    pub p95: u64,
+    /// 99th percentile value
+    pub p99: u64,
+}
+
+/// Nearest-rank percentile of a sorted, non-empty slice.
+fn percentile_sorted(sorted: &[u64], p: f64) -> u64 {
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+impl StatisticSummary {
+    fn from_samples(mut samples: Vec<u64>) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        samples.sort_unstable();
+        let sum = samples.iter().map(|value| *value as f64).sum::<f64>();
+        Some(Self {
+            samples: samples.len(),
+            average: sum / samples.len() as f64,
+            min: *samples.first().unwrap(),
+            max: *samples.last().unwrap(),
+            p50: percentile_sorted(&samples, 50.0),
+            p95: percentile_sorted(&samples, 95.0),
+            p99: percentile_sorted(&samples, 99.0),
+        })
+    }
+}
+
+/// Summary of all pipeline statistics of a pass over a window of frames. Each field is `None` if the
+/// statistic was not present in any of the samples.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct PipelineStatisticsSummary {
+    /// Number of vertices in the input assembly stage
+    pub input_assembly_vertices: Option<StatisticSummary>,
+    /// Number of primitives in the input assembly stage
+    pub input_assembly_primitives: Option<StatisticSummary>,
+    /// Number of vertex shader invocations
+    pub vertex_shader_invocations: Option<StatisticSummary>,
+    /// Number of geometry shader invocations
+    pub geometry_shader_invocations: Option<StatisticSummary>,
+    /// Number of vertex shader primitives
+    pub geometry_shader_primitives: Option<StatisticSummary>,
+    /// Number of clipping stage invocations
+    pub clipping_invocations: Option<StatisticSummary>,
+    /// Number of clipping stage primitives
+    pub clipping_primitives: Option<StatisticSummary>,
+    /// Number of fragment shader invocations
+    pub fragment_shader_invocations: Option<StatisticSummary>,
+    /// Number of patches in the tessellation control shader
+    pub tessellation_control_shader_patches: Option<StatisticSummary>,
+    /// Number of tessellation evaluation shader invocations
+    pub tessellation_evaluation_shader_invocations: Option<StatisticSummary>,
+    /// Number of compute shader invocations
+    pub compute_shader_invocations: Option<StatisticSummary>,
+    /// Number of task shader invocations
+    pub task_shader_invocations: Option<StatisticSummary>,
+    /// Number of mesh shader invocations
+    pub mesh_shader_invocations: Option<StatisticSummary>,
+}
+
+/// Accumulates [`PipelineStatistics`] per pass over a sliding window of frames, and computes averages and percentiles
+/// over this window.
+/// # Example
+/// ```
+/// # use phobos::*;
+/// # use anyhow::Result;
+/// fn report(aggregator: &mut PipelineStatisticsAggregator, pool: &mut QueryPool<PipelineStatisticsQuery>) -> Result<()> {
+///     // Query 0 was used to record the statistics of the main pass this frame.
+///     let stats = pool.wait_for_single_result(0)?;
+///     aggregator.record("main_pass", stats);
+///     if let Some(summary) = aggregator.summary("main_pass") {
+///         if let Some(fragments) = summary.fragment_shader_invocations {
+///             println!("fragment invocations: avg {:.0}, p95 {}", fragments.average, fragments.p95);
+///         }
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct PipelineStatisticsAggregator {
+    window: usize,
+    passes: HashMap<String, VecDeque<PipelineStatistics>>,
+}
+
+impl PipelineStatisticsAggregator {
+    /// Create a new aggregator that keeps the last `window` samples of each pass.
+    pub fn new(window: usize) -> Self {
+        Self {
+            window: window.max(1),
+            passes: HashMap::new(),
+        }
+    }
+
+    /// The maximum number of samples kept for each pass.
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    /// Record the statistics of a pass for the current frame. If the window for this pass is full,
+    /// the oldest sample is discarded.
+    pub fn record(&mut self, pass: &str, statistics: PipelineStatistics) {
+        let samples = self.passes.entry(pass.to_owned()).or_default();
+        if samples.len() == self.window {
+            samples.pop_front();
+        }
+        samples.push_back(statistics);
+    }
+
+    /// Iterate over the names of all passes that have recorded samples.
+    pub fn passes(&self) -> impl Iterator<Item = &str> {
+        self.passes.keys().map(|name| name.as_str())
+    }
+
+    /// Get all samples currently in the window of a pass, ordered from oldest to newest.
+    pub fn samples(&self, pass: &str) -> Option<&VecDeque<PipelineStatistics>> {
+        self.passes.get(pass)
+    }
+
+    /// Compute a summary of a single statistic of a pass. The statistic is selected with the `statistic` function,
+    /// samples where it returns `None` are skipped.
+    /// # Example
+    /// ```
+    /// # use phobos::*;
+    /// fn average_cs_invocations(aggregator: &PipelineStatisticsAggregator) -> Option<f64> {
+    ///     aggregator
+    ///         .summarize("culling", |stats| stats.compute_shader_invocations)
+    ///         .map(|summary| summary.average)
+    /// }
+    /// ```
+    pub fn summarize(
+        &self,
+        pass: &str,
+        statistic: impl Fn(&PipelineStatistics) -> Option<u64>,
+    ) -> Option<StatisticSummary> {
+        let samples = self.passes.get(pass)?;
+        StatisticSummary::from_samples(samples.iter().filter_map(statistic).collect())
+    }
+
+    /// Get an arbitrary percentile (in the range `[0, 100]`) of a single statistic of a pass.
+    pub fn percentile(
+        &self,
+        pass: &str,
+        statistic: impl Fn(&PipelineStatistics) -> Option<u64>,
+        p: f64,
+    ) -> Option<u64> {
+        let samples = self.passes.get(pass)?;
+        let mut values = samples.iter().filter_map(statistic).collect::<Vec<_>>();
+        if values.is_empty() {
+            return None;
+        }
+        values.sort_unstable();
+        Some(percentile_sorted(&values, p.clamp(0.0, 100.0)))
+    }
+
+    /// Compute a summary of all statistics of a pass. Returns `None` if no samples were recorded for this pass.
+    pub fn summary(&self, pass: &str) -> Option<PipelineStatisticsSummary> {
+        if !self.passes.contains_key(pass) {
+            return None;
+        }
+        Some(PipelineStatisticsSummary {
+            input_assembly_vertices: self.summarize(pass, |s| s.input_assembly_vertices),
+            input_assembly_primitives: self.summarize(pass, |s| s.input_assembly_primitives),
+            vertex_shader_invocations: self.summarize(pass, |s| s.vertex_shader_invocations),
+            geometry_shader_invocations: self.summarize(pass, |s| s.geometry_shader_invocations),
+            geometry_shader_primitives: self.summarize(pass, |s| s.geometry_shader_primitives),
+            clipping_invocations: self.summarize(pass, |s| s.clipping_invocations),
+            clipping_primitives: self.summarize(pass, |s| s.clipping_primitives),
+            fragment_shader_invocations: self.summarize(pass, |s| s.fragment_shader_invocations),
+            tessellation_control_shader_patches: self
+                .summarize(pass, |s| s.tessellation_control_shader_patches),
+            tessellation_evaluation_shader_invocations: self
+                .summarize(pass, |s| s.tessellation_evaluation_shader_invocations),
+            compute_shader_invocations: self.summarize(pass, |s| s.compute_shader_invocations),
+            task_shader_invocations: self.summarize(pass, |s| s.task_shader_invocations),
+            mesh_shader_invocations: self.summarize(pass, |s| s.mesh_shader_invocations),
+        })
+    }
+
+    /// Discard all recorded samples.
+    pub fn clear(&mut self) {
+        self.passes.clear();
+    }
+}
+
 /// Query for the compacted size of an acceleration structure
 #[derive(Default, Clone, Copy)]
 pub struct AccelerationStructureCompactedSizeQuery;
@@ -265,8 +601,287 @@ impl Query for AccelerationStructureCompactedSizeQuery {
     }
 }
 
+/// Query for the size needed to serialize an acceleration structure with
+/// [`ComputeCmdBuffer::serialize_acceleration_structure()`](crate::ComputeCmdBuffer::serialize_acceleration_structure)
+#[derive(Default, Clone, Copy)]
+pub struct AccelerationStructureSerializationSizeQuery;
+
+impl AccelerationStructurePropertyQuery for AccelerationStructureSerializationSizeQuery {}
+
+impl Query for AccelerationStructureSerializationSizeQuery {
+    const QUERY_TYPE: vk::QueryType = vk::QueryType::ACCELERATION_STRUCTURE_SERIALIZATION_SIZE_KHR;
+    type Output = u64;
+
+    fn new(_pool: &QueryPoolCreateInfo) -> Self {
+        Self
+    }
+
+    fn size(&self) -> usize {
+        1
+    }
+
+    fn parse_query(&self, _device: &Device, data: &[u64]) -> Self::Output {
+        *data.first().unwrap()
+    }
+}
+
+/// Description of a hardware performance counter, obtained from [`PerformanceQuery::enumerate_counters()`].
+#[derive(Debug, Clone)]
+pub struct PerformanceCounter {
+    /// Index of this counter in the list of counters of the queue family. This is the index used to select the counter
+    /// in [`QueryPool::new_performance()`].
+    pub index: u32,
+    /// Unit of the counter value
+    pub unit: vk::PerformanceCounterUnitKHR,
+    /// Scope of the counter, this decides which commands the query must surround
+    pub scope: vk::PerformanceCounterScopeKHR,
+    /// Storage type of the counter value
+    pub storage: vk::PerformanceCounterStorageKHR,
+    /// Unique identifier of this counter, stable across runs of the application on the same driver
+    pub uuid: [u8; vk::UUID_SIZE],
+    /// Short name of the counter
+    pub name: String,
+    /// Category the counter belongs to
+    pub category: String,
+    /// Longer description of what the counter measures
+    pub description: String,
+}
+
+/// Value of a single performance counter, in the storage type of the counter.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PerformanceCounterValue {
+    /// A signed 32-bit integer value
+    Int32(i32),
+    /// A signed 64-bit integer value
+    Int64(i64),
+    /// An unsigned 32-bit integer value
+    Uint32(u32),
+    /// An unsigned 64-bit integer value
+    Uint64(u64),
+    /// A 32-bit floating point value
+    Float32(f32),
+    /// A 64-bit floating point value
+    Float64(f64),
+}
+
+impl PerformanceCounterValue {
+    /// Convert the counter value to a 64-bit float, regardless of its storage type.
+    pub fn as_f64(&self) -> f64 {
+        match *self {
+            PerformanceCounterValue::Int32(value) => value as f64,
+            PerformanceCounterValue::Int64(value) => value as f64,
+            PerformanceCounterValue::Uint32(value) => value as f64,
+            PerformanceCounterValue::Uint64(value) => value as f64,
+            PerformanceCounterValue::Float32(value) => value as f64,
+            PerformanceCounterValue::Float64(value) => value,
+        }
+    }
+}
+
+/// A hardware performance counter query, through `VK_KHR_performance_query`. Each query measures all counters
+/// the query pool was created with. Create the query pool with [`QueryPool::new_performance()`].
+///
+/// Performance queries have a few requirements that other queries do not have:
+/// * The profiling lock must be held while recording and submitting command buffers using performance queries,
+///   see [`PerformanceQuery::acquire_profiling_lock()`].
+/// * Drivers may not be able to collect all counters at once. In this case the command buffer must be submitted once for
+///   every counter pass with [`ExecutionManager::submit_counter_pass()`](crate::ExecutionManager::submit_counter_pass), so it must
+///   not be recorded for one time submission. The number of passes is obtained with [`PerformanceQuery::num_passes()`].
+/// # Example
+/// ```
+/// # use phobos::prelude::*;
+/// # use anyhow::Result;
+/// fn profile(
+///     device: Device,
+///     physical_device: &PhysicalDevice,
+///     exec: &ExecutionManager,
+///     counters: &[u32],
+/// ) -> Result<Vec<PerformanceCounterValue>> {
+///     let family = exec.get_queue::<domain::Compute>().unwrap().info().family_index;
+///     let mut pool = QueryPool::<PerformanceQuery>::new_performance(device.clone(), physical_device, 1, family, counters)?;
+///     let passes = PerformanceQuery::num_passes(&device, physical_device, family, counters)?;
+///     let _lock = PerformanceQuery::acquire_profiling_lock(&device)?;
+///     let mut cmd = exec
+///         .on_domain_with_usage::<domain::Compute>(vk::CommandBufferUsageFlags::empty())?
+///         .begin_query(&pool, 0)
+///         .dispatch(64, 1, 1)?
+///         .end_query(&pool, 0)
+///         .finish()?;
+///     for pass in 0..passes {
+///         exec.submit_counter_pass(&mut cmd, pass)?.wait()?;
+///     }
+///     pool.wait_for_single_result(0)
+/// }
+/// ```
+#[derive(Default, Clone)]
+pub struct PerformanceQuery {
+    storage: Vec<vk::PerformanceCounterStorageKHR>,
+}
+
+impl PerformanceQuery {
+    /// Enumerate all performance counters available on a queue family.
+    /// # Errors
+    /// * Fails if [`ExtensionID::PerformanceQuery`] is not enabled.
+    pub fn enumerate_counters(
+        device: &Device,
+        physical_device: &PhysicalDevice,
+        queue_family: u32,
+    ) -> Result<Vec<PerformanceCounter>> {
+        device.require_extension(ExtensionID::PerformanceQuery)?;
+        let fns = device.performance_query().unwrap();
+        let enumerate = fns.enumerate_physical_device_queue_family_performance_query_counters_khr;
+        let mut count = 0;
+        unsafe {
+            enumerate(
+                physical_device.handle(),
+                queue_family,
+                &mut count,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+            )
+            .result()?;
+        }
+        let mut counters = vec![vk::PerformanceCounterKHR::default(); count as usize];
+        let mut descriptions = vec![vk::PerformanceCounterDescriptionKHR::default(); count as usize];
+        unsafe {
+            enumerate(
+                physical_device.handle(),
+                queue_family,
+                &mut count,
+                counters.as_mut_ptr(),
+                descriptions.as_mut_ptr(),
+            )
+            .result()?;
+        }
+
+        let to_string = |chars: &[std::ffi::c_char]| unsafe { CStr::from_ptr(chars.as_ptr()) }.to_string_lossy().into_owned();
+        Ok(counters
+            .iter()
+            .zip(descriptions.iter())
+            .take(count as usize)
+            .enumerate()
+            .map(|(index, (counter, description))| PerformanceCounter {
+                index: index as u32,
+                unit: counter.unit,
+                scope: counter.scope,
+                storage: counter.storage,
+                uuid: counter.uuid,
+                name: to_string(&description.name),
+                category: to_string(&description.category),
+                description: to_string(&description.description),
+            })
+            .collect())
+    }
+
+    /// Get the number of passes needed to collect the given counters on a queue family. Each command buffer using a performance
+    /// query pool with these counters must be submitted this many times.
+    /// # Errors
+    /// * Fails if [`ExtensionID::PerformanceQuery`] is not enabled.
+    pub fn num_passes(
+        device: &Device,
+        physical_device: &PhysicalDevice,
+        queue_family: u32,
+        counters: &[u32],
+    ) -> Result<u32> {
+        device.require_extension(ExtensionID::PerformanceQuery)?;
+        let fns = device.performance_query().unwrap();
+        let info = vk::QueryPoolPerformanceCreateInfoKHR {
+            queue_family_index: queue_family,
+            counter_index_count: counters.len() as u32,
+            p_counter_indices: counters.as_ptr(),
+            ..Default::default()
+        };
+        let mut passes = 0;
+        unsafe {
+            (fns.get_physical_device_queue_family_performance_query_passes_khr)(
+                physical_device.handle(),
+                &info,
+                &mut passes,
+            );
+        }
+        Ok(passes)
+    }
+
+    /// Acquire the profiling lock. This must be held while recording and submitting command buffers that use performance
+    /// queries. The lock is released when the returned [`ProfilingLock`] is dropped.
+    /// # Errors
+    /// * Fails if [`ExtensionID::PerformanceQuery`] is not enabled.
+    /// * Fails with [`vk::Result::TIMEOUT`] if the lock could not be acquired, for example because another application holds it.
+    pub fn acquire_profiling_lock(device: &Device) -> Result<ProfilingLock> {
+        device.require_extension(ExtensionID::PerformanceQuery)?;
+        let fns = device.performance_query().unwrap();
+        let info = vk::AcquireProfilingLockInfoKHR {
+            timeout: u64::MAX,
+            ..Default::default()
+        };
+        unsafe {
+            (fns.acquire_profiling_lock_khr)(device.handle().handle(), &info).result()?;
+        }
+        Ok(ProfilingLock {
+            device: device.clone(),
+        })
+    }
+}
+
+impl Query for PerformanceQuery {
+    const QUERY_TYPE: vk::QueryType = vk::QueryType::PERFORMANCE_QUERY_KHR;
+    // Performance query results may not be read with the 64-bit flag. Each result is a VkPerformanceCounterResultKHR,
+    // which is 64 bits in size.
+    const RESULT_FLAGS: vk::QueryResultFlags = vk::QueryResultFlags::WAIT;
+    type Output = Vec<PerformanceCounterValue>;
+
+    fn new(_pool: &QueryPoolCreateInfo) -> Self {
+        Self::default()
+    }
+
+    fn size(&self) -> usize {
+        self.storage.len()
+    }
+
+    fn parse_query(&self, _device: &Device, data: &[u64]) -> Self::Output {
+        self.storage
+            .iter()
+            .zip(data)
+            .map(|(storage, value)| {
+                let result = vk::PerformanceCounterResultKHR {
+                    uint64: *value,
+                };
+                // SAFETY: Every union member is a plain integer or float, so any bit pattern is valid.
+                unsafe {
+                    match *storage {
+                        vk::PerformanceCounterStorageKHR::INT32 => PerformanceCounterValue::Int32(result.int32),
+                        vk::PerformanceCounterStorageKHR::INT64 => PerformanceCounterValue::Int64(result.int64),
+                        vk::PerformanceCounterStorageKHR::UINT32 => PerformanceCounterValue::Uint32(result.uint32),
+                        vk::PerformanceCounterStorageKHR::FLOAT32 => PerformanceCounterValue::Float32(result.float32),
+                        vk::PerformanceCounterStorageKHR::FLOAT64 => PerformanceCounterValue::Float64(result.float64),
+                        _ => PerformanceCounterValue::Uint64(result.uint64),
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+impl ScopedQuery for PerformanceQuery {}
+
+/// Holds the profiling lock needed to use performance queries. The lock is released when this is dropped.
+/// See [`PerformanceQuery::acquire_profiling_lock()`].
+pub struct ProfilingLock {
+    device: Device,
+}
+
+impl Drop for ProfilingLock {
+    fn drop(&mut self) {
+        unsafe {
+            // The lock was acquired, so the extension is enabled.
+            let fns = self.device.performance_query().unwrap();
+            (fns.release_profiling_lock_khr)(self.device.handle().handle());
+        }
+    }
+}
+
 /// Information required to create a query pool
-#[derive(Default, Debug, Copy, Clone)]
+#[derive(Default, Debug, Copy, Clone, Hash, PartialEq, Eq)]
 pub struct QueryPoolCreateInfo {
     /// The number of queries the query pool must reserve memory for
     pub count: u32,
@@ -287,9 +902,24 @@ pub struct QueryPool<Q: Query> {
 impl<Q: Query> QueryPool<Q> {
     /// Create a new query pool with at most `count` entries.
     pub fn new(device: Device, info: QueryPoolCreateInfo) -> Result<Self> {
+        Self::new_with_p_next(device, info, std::ptr::null(), Q::new(&info))
+    }
+
+    fn new_with_p_next(device: Device, info: QueryPoolCreateInfo, p_next: *const std::ffi::c_void, query: Q) -> Result<Self> {
+        let mesh_statistics = vk::QueryPipelineStatisticFlags::TASK_SHADER_INVOCATIONS_EXT
+            | vk::QueryPipelineStatisticFlags::MESH_SHADER_INVOCATIONS_EXT;
+        if Q::QUERY_TYPE == vk::QueryType::MESH_PRIMITIVES_GENERATED_EXT
+            || info.statistic_flags.unwrap_or_default().intersects(mesh_statistics)
+        {
+            ensure!(
+                device.mesh_shader_features().mesh_shader_queries == vk::TRUE,
+                "mesh shader queries require the mesh_shader_queries feature of VK_EXT_mesh_shader"
+            );
+        }
+
         let vk_info = vk::QueryPoolCreateInfo {
             s_type: vk::StructureType::QUERY_POOL_CREATE_INFO,
-            p_next: std::ptr::null(),
+            p_next,
             flags: Default::default(),
             query_type: Q::QUERY_TYPE,
             query_count: info.count,
@@ -308,10 +938,45 @@ impl<Q: Query> QueryPool<Q> {
             device,
             current: 0,
             count: info.count,
-            queries: vec![Q::new(&info); info.count as usize],
+            queries: vec![query; info.count as usize],
         })
     }
 
+    /// Get the total number of queries in this pool.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Get the number of result items written for each query in this pool.
+    pub(crate) fn items_per_query(&self) -> usize {
+        // Every query in the pool has the same number of items.
+        self.queries
+            .first()
+            .map(|query| query.size())
+            .unwrap_or_default()
+    }
+
+    /// Read back the raw result items of a range of queries.
+    fn read_results(&self, first: u32, count: u32, items_per_query: usize) -> Result<Vec<u64>> {
+        let mut buffer = vec![u64::default(); count as usize * items_per_query];
+        // ash::Device::get_query_pool_results() assumes every query has a single result item, so call the function directly
+        // to pass the correct stride.
+        unsafe {
+            (self.device.fp_v1_0().get_query_pool_results)(
+                self.device.handle().handle(),
+                self.handle,
+                first,
+                count,
+                std::mem::size_of_val(buffer.as_slice()),
+                buffer.as_mut_ptr().cast(),
+                (items_per_query * std::mem::size_of::<u64>()) as vk::DeviceSize,
+                Q::RESULT_FLAGS,
+            )
+            .result()?;
+        }
+        Ok(buffer)
+    }
+
     /// Advance the query pool to the next query, and return the previous index.
     /// Returns None if the query pool was out of entries.
     pub fn next(&mut self) -> Option<u32> {
@@ -324,6 +989,27 @@ impl<Q: Query> QueryPool<Q> {
         }
     }
 
+    /// Advance the query pool by `count` queries, and return the index of the first query in this range.
+    /// Returns None if the query pool does not have `count` entries left. In this case the query pool is not advanced.
+    /// # Errors
+    /// * Fails with [`Error::QueryRangeOverflow`] if the end of the range does not fit in a `u32`.
+    pub fn next_range(&mut self, count: u32) -> Result<Option<u32>> {
+        let end = self
+            .current
+            .checked_add(count)
+            .ok_or(Error::QueryRangeOverflow {
+                first: self.current,
+                count,
+            })?;
+        if end > self.count {
+            Ok(None)
+        } else {
+            let old = self.current;
+            self.current = end;
+            Ok(Some(old))
+        }
+    }
+
     /// Returns the current query pool index. This returns the same value as next() would, except it does not advance the query pool.
     pub fn current(&self) -> u32 {
         if self.current == 0 {
@@ -336,25 +1022,16 @@ impl<Q: Query> QueryPool<Q> {
     /// Wait for a range of results in the query pool
     pub fn wait_for_results(&mut self, first: u32, count: u32) -> Result<Vec<Q::Output>> {
         ensure!(first < self.count, "Query range out of range of query pool");
-        ensure!(first + count <= self.count, "Query range out of range of query pool");
-
-        let flags = vk::QueryResultFlags::TYPE_64 | vk::QueryResultFlags::WAIT;
-        // Assumption: Every query in the pool has the same number of items, this should always be the case.
-        let items_per_query = self
-            .queries
-            .first()
-            .map(|query| query.size())
-            .unwrap_or_default();
-        let mut buffer = vec![u64::default(); count as usize * items_per_query];
-        unsafe {
-            self.device.get_query_pool_results(
-                self.handle,
+        let end = first
+            .checked_add(count)
+            .ok_or(Error::QueryRangeOverflow {
                 first,
                 count,
-                buffer.as_mut_slice(),
-                flags,
-            )?;
-        }
+            })?;
+        ensure!(end <= self.count, "Query range out of range of query pool");
+
+        let items_per_query = self.items_per_query();
+        let buffer = self.read_results(first, count, items_per_query)?;
         let data = buffer
             .chunks_exact(items_per_query)
             .into_iter()
@@ -373,19 +1050,8 @@ impl<Q: Query> QueryPool<Q> {
     /// Wait for the result of a single query in the pool
     pub fn wait_for_single_result(&mut self, index: u32) -> Result<Q::Output> {
         ensure!(index < self.count, "Query range out of range of query pool");
-        let flags = vk::QueryResultFlags::TYPE_64 | vk::QueryResultFlags::WAIT;
         let query = self.queries.get(index as usize).unwrap();
-        let num_items = query.size();
-        let mut buffer = vec![u64::default(); num_items];
-        unsafe {
-            self.device.get_query_pool_results(
-                self.handle,
-                index,
-                1,
-                buffer.as_mut_slice(),
-                flags,
-            )?;
-        }
+        let buffer = self.read_results(index, 1, query.size())?;
         let data = query.parse_query(&self.device, buffer.as_slice());
         Ok(data)
     }
@@ -396,6 +1062,25 @@ impl<Q: Query> QueryPool<Q> {
         self.current = 0;
     }
 
+    /// Reset a range of queries in the query pool from the host, so they can be used again. Unlike [`QueryPool::reset()`],
+    /// this does not rewind the index returned by [`QueryPool::next()`].
+    /// # Errors
+    /// * Fails if the range is out of range of the query pool.
+    pub fn reset_range(&mut self, first: u32, count: u32) -> Result<()> {
+        ensure!(
+            first.checked_add(count).is_some_and(|end| end <= self.count),
+            "Query range out of range of query pool"
+        );
+        unsafe { self.device.reset_query_pool(self.handle, first, count) };
+        Ok(())
+    }
+
+    /// Rewind the query pool so the next call to [`QueryPool::next()`] returns the first query again. Used after a reset
+    /// was recorded on a command buffer.
+    pub(crate) fn rewind(&mut self) {
+        self.current = 0;
+    }
+
     /// Get unsafe access to the underlying `VkQueryPool` handle
     /// # Safety
     /// Modifying this object may put the system in an undefined state
@@ -404,6 +1089,50 @@ impl<Q: Query> QueryPool<Q> {
     }
 }
 
+impl QueryPool<PerformanceQuery> {
+    /// Create a new performance query pool with at most `count` entries. Each query measures all counters in `counters`, which are
+    /// indices into the counters returned by [`PerformanceQuery::enumerate_counters()`] for the same queue family. The queries can only
+    /// be used in command buffers submitted to this queue family.
+    /// # Errors
+    /// * Fails if [`ExtensionID::PerformanceQuery`] is not enabled.
+    /// * Fails if a counter index is out of range.
+    pub fn new_performance(
+        device: Device,
+        physical_device: &PhysicalDevice,
+        count: u32,
+        queue_family: u32,
+        counters: &[u32],
+    ) -> Result<Self> {
+        let available = PerformanceQuery::enumerate_counters(&device, physical_device, queue_family)?;
+        let storage = counters
+            .iter()
+            .map(|&index| {
+                available
+                    .get(index as usize)
+                    .map(|counter| counter.storage)
+                    .ok_or_else(|| anyhow::anyhow!("Performance counter index {index} out of range"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let performance_info = vk::QueryPoolPerformanceCreateInfoKHR {
+            queue_family_index: queue_family,
+            counter_index_count: counters.len() as u32,
+            p_counter_indices: counters.as_ptr(),
+            ..Default::default()
+        };
+        Self::new_with_p_next(
+            device,
+            QueryPoolCreateInfo {
+                count,
+                statistic_flags: None,
+            },
+            (&performance_info as *const vk::QueryPoolPerformanceCreateInfoKHR).cast(),
+            PerformanceQuery {
+                storage,
+            },
+        )
+    }
+}
+
 impl QueryPool<TimestampQuery> {
     pub(crate) fn write_timestamp(
         &mut self,
@@ -420,6 +1149,16 @@ impl QueryPool<TimestampQuery> {
     }
 }
 
+impl<Q: Query> Poolable for QueryPool<Q> {
+    /// Query pools with the same create info are interchangeable
+    type Key = QueryPoolCreateInfo;
+
+    fn on_release(&mut self) {
+        // Reset all queries so the pool can be used again immediately, without recreating it.
+        self.reset();
+    }
+}
+
 impl<Q: Query> Drop for QueryPool<Q> {
     fn drop(&mut self) {
         #[cfg(feature = "log-objects")]
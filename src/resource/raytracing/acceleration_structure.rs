@@ -1,6 +1,6 @@
 //! Acceleration structure resource
 
-use anyhow::Result;
+use anyhow::{ensure, Result};
 use ash::vk;
 use ash::vk::Handle;
 
@@ -9,6 +9,9 @@ use crate::core::traits::{AsRaw, Nameable};
 use crate::util::to_vk::IntoVulkanType;
 use crate::{AccelerationStructureType, BufferView, Device};
 
+/// Size of the header at the start of serialized acceleration structure data.
+const SERIALIZED_HEADER_SIZE: usize = 2 * vk::UUID_SIZE + 3 * std::mem::size_of::<u64>();
+
 /// Wrapper around a [`VkAccelerationStructureKHR`](vk::AccelerationStructureKHR)
 pub struct AccelerationStructure {
     device: Device,
@@ -92,6 +95,38 @@ impl AccelerationStructure {
     pub fn ty(&self) -> AccelerationStructureType {
         self.ty
     }
+
+    /// Check whether serialized acceleration structure data can be deserialized on this device. This should be checked
+    /// before loading acceleration structures that were cached on disk, since the data is only valid for the driver
+    /// that serialized it. `data` must contain at least the header of the serialized acceleration structure.
+    /// # Errors
+    /// * Fails if [`ExtensionID::AccelerationStructure`] is not enabled.
+    /// * Fails if `data` is too small to contain the version header.
+    pub fn is_compatible(device: &Device, data: &[u8]) -> Result<bool> {
+        device.require_extension(ExtensionID::AccelerationStructure)?;
+        ensure!(
+            data.len() >= SERIALIZED_HEADER_SIZE,
+            "Serialized acceleration structure data is too small to contain a header"
+        );
+        let fns = device.acceleration_structure().unwrap();
+        let info = vk::AccelerationStructureVersionInfoKHR {
+            s_type: vk::StructureType::ACCELERATION_STRUCTURE_VERSION_INFO_KHR,
+            p_next: std::ptr::null(),
+            // The version data is the driver UUID followed by the compatibility UUID, which are at the start of the header.
+            p_version_data: data.as_ptr().cast(),
+        };
+        let compatibility = unsafe { fns.get_device_acceleration_structure_compatibility(&info) };
+        Ok(compatibility == vk::AccelerationStructureCompatibilityKHR::COMPATIBLE)
+    }
+
+    /// Get the size of the buffer needed to deserialize an acceleration structure from serialized data. This is read from
+    /// the header of the serialized data. Returns `None` if `data` is too small to contain the header.
+    pub fn deserialized_size(data: &[u8]) -> Option<vk::DeviceSize> {
+        // Header layout: driver UUID, compatibility UUID, serialized size, deserialized size, handle count.
+        let offset = 2 * vk::UUID_SIZE + std::mem::size_of::<u64>();
+        let bytes = data.get(offset..offset + std::mem::size_of::<u64>())?;
+        Some(u64::from_ne_bytes(bytes.try_into().unwrap()))
+    }
 }
 
 unsafe impl AsRaw for AccelerationStructure {
@@ -0,0 +1,280 @@
+//! Build many acceleration structures at once, with shared scratch memory and optional compaction.
+//!
+//! Building a single acceleration structure requires querying its build sizes, allocating a backing buffer and a scratch buffer,
+//! recording the build and synchronizing it. The [`AccelerationStructureBuilder`] does all of this for a list of build inputs.
+//! Builds are batched into as few `vkCmdBuildAccelerationStructuresKHR` calls as the scratch budget allows, and all batches share
+//! a single scratch buffer. If compaction is enabled, the compacted sizes are queried after building, and every acceleration
+//! structure is compacted into a new, smaller buffer.
+//! # Example
+//! ```
+//! # use anyhow::Result;
+//! # use phobos::prelude::*;
+//! fn build_meshes(
+//!     device: Device,
+//!     exec: &ExecutionManager,
+//!     allocator: &mut DefaultAllocator,
+//!     meshes: Vec<AccelerationStructureBuildInfo<'static>>,
+//! ) -> Result<Vec<BuiltAccelerationStructure>> {
+//!     let mut builder = AccelerationStructureBuilder::new(device).compact(true);
+//!     for mesh in meshes {
+//!         // Each build info holds the geometry and the primitive ranges of one bottom level acceleration structure.
+//!         // The destination acceleration structure and the scratch data are filled in by the builder.
+//!         builder = builder.push(mesh);
+//!     }
+//!     builder.build(exec, allocator)
+//! }
+//! ```
+
+use anyhow::Result;
+use ash::vk;
+
+use crate::allocator::statistics::MemoryCategory;
+use crate::command_buffer::traits::*;
+use crate::query_pool::{AccelerationStructureCompactedSizeQuery, QueryPool, QueryPoolCreateInfo};
+use crate::sync::domain;
+use crate::util::align::align;
+use crate::util::address::DeviceOrHostAddress;
+use crate::{
+    query_build_size, AccelerationStructure, AccelerationStructureBuildGeometryInfo,
+    AccelerationStructureBuildInfo, AccelerationStructureBuildType, Allocator, Buffer,
+    DefaultAllocator, Device, ExecutionManager, PipelineStage,
+};
+
+/// Default maximum size of the scratch buffer shared by a batch of builds.
+const DEFAULT_MAX_SCRATCH_SIZE: vk::DeviceSize = 64 * 1024 * 1024;
+
+/// An acceleration structure built by an [`AccelerationStructureBuilder`], together with the buffer backing its memory.
+/// The buffer must be kept alive as long as the acceleration structure is used.
+pub struct BuiltAccelerationStructure<A: Allocator = DefaultAllocator> {
+    /// The acceleration structure.
+    pub accel: AccelerationStructure,
+    /// Buffer backing the memory of the acceleration structure.
+    pub buffer: Buffer<A>,
+}
+
+/// Builds many acceleration structures with batched build commands. See the [module-level documentation](self).
+pub struct AccelerationStructureBuilder<'a> {
+    device: Device,
+    inputs: Vec<AccelerationStructureBuildInfo<'a>>,
+    compact: bool,
+    max_scratch_size: vk::DeviceSize,
+}
+
+impl<'a> AccelerationStructureBuilder<'a> {
+    /// Create a new builder without any build inputs. Compaction is disabled by default.
+    pub fn new(device: Device) -> Self {
+        Self {
+            device,
+            inputs: vec![],
+            compact: false,
+            max_scratch_size: DEFAULT_MAX_SCRATCH_SIZE,
+        }
+    }
+
+    /// Add an acceleration structure to build. The build info must contain all geometry and one primitive range per geometry.
+    /// Its destination and scratch data are ignored, since these are allocated by the builder. Acceleration structures are
+    /// returned from [`AccelerationStructureBuilder::build()`] in the order they were pushed.
+    pub fn push(mut self, info: AccelerationStructureBuildInfo<'a>) -> Self {
+        self.inputs.push(info);
+        self
+    }
+
+    /// Compact all acceleration structures after building them. The build infos should have the
+    /// [`vk::BuildAccelerationStructureFlagsKHR::ALLOW_COMPACTION`] flag set.
+    pub fn compact(mut self, compact: bool) -> Self {
+        self.compact = compact;
+        self
+    }
+
+    /// Set the maximum size of the scratch buffer. Builds are batched so that the scratch memory of all builds in a batch
+    /// fits in this size. A single build that needs more scratch memory than this is placed in a batch of its own.
+    pub fn max_scratch_size(mut self, size: impl Into<vk::DeviceSize>) -> Self {
+        self.max_scratch_size = size.into();
+        self
+    }
+
+    /// Build all acceleration structures on the compute domain, and wait for the builds to finish.
+    /// # Errors
+    /// * Fails if [`ExtensionID::AccelerationStructure`](crate::ExtensionID::AccelerationStructure) is not enabled.
+    /// * Fails if a build info does not have one primitive range per geometry.
+    /// * Fails if allocating memory, recording or submitting the builds fails.
+    pub fn build<A: Allocator + 'static>(
+        self,
+        exec: &ExecutionManager<A>,
+        allocator: &mut A,
+    ) -> Result<Vec<BuiltAccelerationStructure<A>>> {
+        if self.inputs.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let sizes = self
+            .inputs
+            .iter()
+            .map(|info| {
+                let primitive_counts = info
+                    .build_range_infos
+                    .iter()
+                    .map(|range| range.primitive_count)
+                    .collect::<Vec<_>>();
+                query_build_size(&self.device, AccelerationStructureBuildType::Device, info, &primitive_counts)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut buffers = Vec::with_capacity(sizes.len());
+        let mut accels = Vec::with_capacity(sizes.len());
+        for (info, size) in self.inputs.iter().zip(&sizes) {
+            let mut buffer = Buffer::new_device_local(self.device.clone(), allocator, size.size)?;
+            buffer.set_memory_category(MemoryCategory::AccelerationStructure);
+            let accel = AccelerationStructure::new(
+                self.device.clone(),
+                info.ty(),
+                buffer.view_full(),
+                vk::AccelerationStructureCreateFlagsKHR::default(),
+            )?;
+            buffers.push(buffer);
+            accels.push(accel);
+        }
+
+        // Split the builds into batches whose scratch memory fits in the budget.
+        let mut batches: Vec<Vec<usize>> = vec![];
+        let mut batch_scratch = 0;
+        for (index, size) in sizes.iter().enumerate() {
+            match batches.last_mut() {
+                Some(batch) if batch_scratch + size.build_scratch_size <= self.max_scratch_size => {
+                    batch.push(index);
+                    batch_scratch += size.build_scratch_size;
+                }
+                _ => {
+                    batches.push(vec![index]);
+                    batch_scratch = size.build_scratch_size;
+                }
+            }
+        }
+        let scratch_size = batches
+            .iter()
+            .map(|batch| batch.iter().map(|&index| sizes[index].build_scratch_size).sum::<u64>())
+            .max()
+            .unwrap_or(0);
+
+        // The scratch address of each build must be aligned, so allocate some extra space to align the base address.
+        let scratch_alignment = self
+            .device
+            .acceleration_structure_properties()?
+            .min_acceleration_structure_scratch_offset_alignment as vk::DeviceSize;
+        let mut scratch = Buffer::new_device_local(self.device.clone(), allocator, scratch_size + scratch_alignment)?;
+        scratch.set_memory_category(MemoryCategory::Scratch);
+        let scratch_base: vk::DeviceAddress = align(scratch.address(), scratch_alignment);
+
+        let mut cmd = exec.on_domain::<domain::Compute>()?;
+        for batch in &batches {
+            let mut offset = 0;
+            let infos = batch
+                .iter()
+                .map(|&index| {
+                    let input = &self.inputs[index];
+                    let info = AccelerationStructureBuildInfo {
+                        geometry: AccelerationStructureBuildGeometryInfo {
+                            ty: input.geometry.ty,
+                            flags: input.geometry.flags,
+                            mode: vk::BuildAccelerationStructureModeKHR::BUILD,
+                            src: None,
+                            dst: Some(&accels[index]),
+                            geometries: input.geometry.geometries.clone(),
+                            scratch_data: DeviceOrHostAddress::from(scratch_base + offset),
+                        },
+                        build_range_infos: input.build_range_infos.clone(),
+                        // The geometries still point to the opacity micromaps owned by the input, which outlives this info.
+                        opacity_micromaps: vec![],
+                        opacity_micromap_infos: vec![],
+                    };
+                    offset += sizes[index].build_scratch_size;
+                    info
+                })
+                .collect::<Vec<_>>();
+            // Each batch reuses the scratch buffer, so it must wait for the previous batch to finish.
+            cmd = cmd
+                .build_acceleration_structures(&infos)?
+                .memory_barrier(
+                    PipelineStage::ACCELERATION_STRUCTURE_BUILD_KHR,
+                    vk::AccessFlags2::ACCELERATION_STRUCTURE_WRITE_KHR,
+                    PipelineStage::ACCELERATION_STRUCTURE_BUILD_KHR,
+                    vk::AccessFlags2::ACCELERATION_STRUCTURE_READ_KHR
+                        | vk::AccessFlags2::ACCELERATION_STRUCTURE_WRITE_KHR,
+                );
+        }
+
+        if !self.compact {
+            let cmd = cmd
+                .memory_barrier(
+                    PipelineStage::ACCELERATION_STRUCTURE_BUILD_KHR,
+                    vk::AccessFlags2::ACCELERATION_STRUCTURE_WRITE_KHR,
+                    PipelineStage::ALL_COMMANDS,
+                    vk::AccessFlags2::ACCELERATION_STRUCTURE_READ_KHR,
+                )
+                .finish()?;
+            exec.submit(cmd)?.wait()?;
+            return Ok(accels
+                .into_iter()
+                .zip(buffers)
+                .map(|(accel, buffer)| BuiltAccelerationStructure {
+                    accel,
+                    buffer,
+                })
+                .collect());
+        }
+
+        // Query the compacted sizes of all acceleration structures in the same submission as the builds.
+        let mut query_pool = QueryPool::<AccelerationStructureCompactedSizeQuery>::new(
+            self.device.clone(),
+            QueryPoolCreateInfo {
+                count: accels.len() as u32,
+                statistic_flags: None,
+            },
+        )?;
+        let cmd = cmd
+            .write_acceleration_structures_properties(&accels, &mut query_pool)?
+            .finish()?;
+        exec.submit(cmd)?.wait()?;
+        let compacted_sizes = query_pool.wait_for_all_results()?;
+
+        let mut compacted_buffers = Vec::with_capacity(accels.len());
+        let mut compacted = Vec::with_capacity(accels.len());
+        for (accel, size) in accels.iter().zip(compacted_sizes) {
+            let mut buffer = Buffer::new_device_local(
+                self.device.clone(),
+                allocator,
+                align(size, AccelerationStructure::alignment()),
+            )?;
+            buffer.set_memory_category(MemoryCategory::AccelerationStructure);
+            let compact = AccelerationStructure::new(
+                self.device.clone(),
+                accel.ty(),
+                buffer.view_full(),
+                vk::AccelerationStructureCreateFlagsKHR::default(),
+            )?;
+            compacted_buffers.push(buffer);
+            compacted.push(compact);
+        }
+
+        let cmd = exec
+            .on_domain::<domain::Compute>()?
+            .compact_acceleration_structures(&accels, &compacted)?
+            .memory_barrier(
+                PipelineStage::ACCELERATION_STRUCTURE_COPY_KHR,
+                vk::AccessFlags2::ACCELERATION_STRUCTURE_WRITE_KHR,
+                PipelineStage::ALL_COMMANDS,
+                vk::AccessFlags2::ACCELERATION_STRUCTURE_READ_KHR,
+            )
+            .finish()?;
+        exec.submit(cmd)?.wait()?;
+
+        Ok(compacted
+            .into_iter()
+            .zip(compacted_buffers)
+            .map(|(accel, buffer)| BuiltAccelerationStructure {
+                accel,
+                buffer,
+            })
+            .collect())
+    }
+}
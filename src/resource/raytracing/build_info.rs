@@ -6,14 +6,19 @@ use crate::util::address::DeviceOrHostAddress;
 use crate::util::to_vk::{AsVulkanType, IntoVulkanType};
 use crate::{
     AccelerationStructure, AccelerationStructureBuildGeometryInfo,
-    AccelerationStructureGeometryInstancesData, AccelerationStructureGeometryTrianglesData,
-    AccelerationStructureType,
+    AccelerationStructureGeometryAabbsData, AccelerationStructureGeometryInstancesData, AccelerationStructureGeometryTrianglesData,
+    AccelerationStructureTrianglesOpacityMicromap, AccelerationStructureType,
 };
 
 /// All information required to build an acceleration structure
 pub struct AccelerationStructureBuildInfo<'a> {
     pub(crate) geometry: AccelerationStructureBuildGeometryInfo<'a>,
     pub(crate) build_range_infos: Vec<vk::AccelerationStructureBuildRangeInfoKHR>,
+    // Geometries point to these through their p_next chain, together with the index of the geometry they belong to.
+    // Pushing may move them, so the pointers are updated whenever one is added.
+    pub(crate) opacity_micromaps: Vec<(usize, vk::AccelerationStructureTrianglesOpacityMicromapEXT)>,
+    // Owns the usage counts the opacity micromaps point to.
+    pub(crate) opacity_micromap_infos: Vec<AccelerationStructureTrianglesOpacityMicromap<'a>>,
 }
 
 impl<'a> Default for AccelerationStructureBuildInfo<'a> {
@@ -30,6 +35,8 @@ impl<'a> Default for AccelerationStructureBuildInfo<'a> {
                 scratch_data: DeviceOrHostAddress::null_host(),
             },
             build_range_infos: vec![],
+            opacity_micromaps: vec![],
+            opacity_micromap_infos: vec![],
         }
     }
 }
@@ -89,20 +96,51 @@ impl<'a> AccelerationStructureBuildInfo<'a> {
         self
     }
 
-    /// Add an AABB geometry to this acceleration structure
-    pub fn push_aabbs(
+    /// Add a triangle geometry with an opacity micromap attached to it. Requires [`ExtensionID::OpacityMicromap`](crate::ExtensionID::OpacityMicromap).
+    /// See the [`micromap`](crate::raytracing::micromap) module for more information.
+    pub fn push_triangles_with_opacity_micromap(
         mut self,
-        aabbs: vk::AccelerationStructureGeometryAabbsDataKHR,
-        flags: vk::GeometryFlagsKHR,
+        triangles: AccelerationStructureGeometryTrianglesData,
+        micromap: AccelerationStructureTrianglesOpacityMicromap<'a>,
     ) -> Self {
+        let flags = triangles.flags;
+        let index = self.geometry.geometries.len();
+        self.opacity_micromaps.push((index, micromap.as_vulkan()));
+        self.opacity_micromap_infos.push(micromap);
+        self = self.push_geometry(vk::AccelerationStructureGeometryKHR {
+            s_type: vk::StructureType::ACCELERATION_STRUCTURE_GEOMETRY_KHR,
+            p_next: std::ptr::null(),
+            flags,
+            geometry_type: vk::GeometryTypeKHR::TRIANGLES,
+            geometry: vk::AccelerationStructureGeometryDataKHR {
+                triangles: triangles.into_vulkan(),
+            },
+        });
+        self.link_opacity_micromaps();
+        self
+    }
+
+    /// Point the geometries with an opacity micromap to its current location in `opacity_micromaps`.
+    fn link_opacity_micromaps(&mut self) {
+        for (index, micromap) in &self.opacity_micromaps {
+            let p_next = (micromap as *const vk::AccelerationStructureTrianglesOpacityMicromapEXT).cast();
+            // Geometries with an opacity micromap are always triangle geometries.
+            self.geometry.geometries[*index].geometry.triangles.p_next = p_next;
+        }
+    }
+
+    /// Add an AABB geometry to this acceleration structure. Rays hitting these boxes invoke the intersection shader of
+    /// the hit group, which must be added with
+    /// [`add_procedural_hit_group()`](crate::pipeline::raytracing::RayTracingPipelineBuilder::add_procedural_hit_group).
+    pub fn push_aabbs(mut self, aabbs: AccelerationStructureGeometryAabbsData) -> Self {
         self = self.push_geometry(vk::AccelerationStructureGeometryKHR {
             s_type: vk::StructureType::ACCELERATION_STRUCTURE_GEOMETRY_KHR,
             p_next: std::ptr::null(),
             geometry_type: vk::GeometryTypeKHR::AABBS,
+            flags: aabbs.flags,
             geometry: vk::AccelerationStructureGeometryDataKHR {
-                aabbs,
+                aabbs: aabbs.into_vulkan(),
             },
-            flags,
         });
         self
     }
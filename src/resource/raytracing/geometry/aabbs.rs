@@ -0,0 +1,64 @@
+//! Wrappers for acceleration structure AABB geometry data
+
+use ash::vk;
+
+use crate::util::address::DeviceOrHostAddressConst;
+use crate::util::to_vk::{AsVulkanType, IntoVulkanType};
+
+/// An axis-aligned bounding box in an AABB buffer. Procedural geometry is stored as a buffer of these, and rays that hit a box
+/// invoke the intersection shader of the hit group to test the primitive inside it.
+pub type AabbPositions = vk::AabbPositionsKHR;
+
+/// AABB data in an acceleration structure, used for procedural geometry.
+pub struct AccelerationStructureGeometryAabbsData {
+    /// Address of the buffer with [`AabbPositions`]
+    pub data: DeviceOrHostAddressConst,
+    /// Stride between each AABB in the buffer. This must be a multiple of 8.
+    pub stride: vk::DeviceSize,
+    /// Geometry flags
+    pub flags: vk::GeometryFlagsKHR,
+}
+
+impl Default for AccelerationStructureGeometryAabbsData {
+    /// Create AABB data with default settings. The stride is set to the size of a tightly packed [`AabbPositions`].
+    fn default() -> Self {
+        Self {
+            data: DeviceOrHostAddressConst::null_host(),
+            stride: std::mem::size_of::<AabbPositions>() as vk::DeviceSize,
+            flags: Default::default(),
+        }
+    }
+}
+
+impl AccelerationStructureGeometryAabbsData {
+    /// Set the AABB buffer address
+    pub fn data(mut self, data: impl Into<DeviceOrHostAddressConst>) -> Self {
+        self.data = data.into();
+        self
+    }
+
+    /// Set the stride between AABBs
+    pub fn stride(mut self, stride: impl Into<vk::DeviceSize>) -> Self {
+        self.stride = stride.into();
+        self
+    }
+
+    /// Set the geometry flags
+    pub fn flags(mut self, flags: vk::GeometryFlagsKHR) -> Self {
+        self.flags = flags;
+        self
+    }
+}
+
+impl IntoVulkanType for AccelerationStructureGeometryAabbsData {
+    type Output = vk::AccelerationStructureGeometryAabbsDataKHR;
+
+    fn into_vulkan(self) -> Self::Output {
+        vk::AccelerationStructureGeometryAabbsDataKHR {
+            s_type: vk::StructureType::ACCELERATION_STRUCTURE_GEOMETRY_AABBS_DATA_KHR,
+            p_next: std::ptr::null(),
+            data: self.data.as_vulkan(),
+            stride: self.stride,
+        }
+    }
+}
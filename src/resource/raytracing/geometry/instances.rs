@@ -24,6 +24,11 @@ pub struct AccelerationStructureInstance(vk::AccelerationStructureInstanceKHR);
 
 const_assert_eq!(std::mem::size_of::<AccelerationStructureInstance>(), 64);
 
+// SAFETY: The instance struct consists of a transform matrix of floats, packed integers and a device address or handle,
+// without any padding. Any bit pattern is a valid value for all of these.
+unsafe impl bytemuck::Zeroable for AccelerationStructureInstance {}
+unsafe impl bytemuck::Pod for AccelerationStructureInstance {}
+
 impl IntoVulkanType for AccelerationStructureGeometryInstancesData {
     type Output = vk::AccelerationStructureGeometryInstancesDataKHR;
 
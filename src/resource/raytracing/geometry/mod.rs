@@ -1,6 +1,7 @@
 //! Exposes different acceleration structure geometry types
 
 use ash::vk;
+pub use aabbs::*;
 pub use instances::*;
 pub use triangles::*;
 
@@ -8,6 +9,7 @@ use crate::util::address::DeviceOrHostAddress;
 use crate::util::to_vk::{AsVulkanType, IntoVulkanType};
 use crate::{AccelerationStructure, AccelerationStructureType};
 
+pub mod aabbs;
 pub mod instances;
 pub mod triangles;
 
@@ -0,0 +1,140 @@
+//! Upload acceleration structure instances to the GPU for top level acceleration structure builds.
+//!
+//! The instance data of a top level acceleration structure must live in a buffer whose device address is aligned to 16 bytes.
+//! An [`InstanceBuffer`] owns such a buffer, and uploads new instances to it through a staging buffer every time
+//! [`InstanceBuffer::update()`] is called. This makes it easy to rebuild or update a TLAS every frame.
+//! # Example
+//! ```
+//! # use anyhow::Result;
+//! # use phobos::prelude::*;
+//! # use phobos::sync::domain::ExecutionDomain;
+//! fn build_tlas<'q, D: ExecutionDomain + ComputeSupport>(
+//!     cmd: IncompleteCommandBuffer<'q, D>,
+//!     allocator: &mut DefaultAllocator,
+//!     instances: &mut InstanceBuffer,
+//!     data: &[AccelerationStructureInstance],
+//!     tlas: &AccelerationStructure,
+//!     scratch: &Buffer,
+//! ) -> Result<IncompleteCommandBuffer<'q, D>> {
+//!     let (cmd, geometry) = instances.update(cmd, allocator, data)?;
+//!     let info = AccelerationStructureBuildInfo::new_build()
+//!         .set_type(AccelerationStructureType::TopLevel)
+//!         .push_instances(geometry)
+//!         .push_range(data.len() as u32, 0, 0, 0)
+//!         .dst(tlas)
+//!         .scratch_data(scratch.address());
+//!     cmd.build_acceleration_structure(&info)
+//! }
+//! ```
+
+use anyhow::Result;
+use ash::vk;
+
+use crate::allocator::statistics::MemoryCategory;
+use crate::command_buffer::traits::*;
+use crate::sync::domain::ExecutionDomain;
+use crate::util::address::DeviceOrHostAddressConst;
+use crate::{
+    AccelerationStructureGeometryInstancesData, AccelerationStructureInstance, Allocator, Buffer,
+    DefaultAllocator, DeletionQueue, Device, IncompleteCommandBuffer, MemoryType, PipelineStage,
+};
+
+/// Required alignment of the device address of instance data, see
+/// VUID-vkCmdBuildAccelerationStructuresKHR-pInfos-03715.
+const INSTANCE_ALIGNMENT: vk::DeviceSize = 16;
+
+/// A device local buffer holding acceleration structure instances, that can be updated every frame.
+/// See the [module-level documentation](self).
+pub struct InstanceBuffer<A: Allocator = DefaultAllocator> {
+    device: Device,
+    buffer: Option<Buffer<A>>,
+    capacity: usize,
+    // Staging buffers and replaced instance buffers may still be in use by frames in flight.
+    deferred_delete: DeletionQueue<Buffer<A>>,
+}
+
+impl<A: Allocator> InstanceBuffer<A> {
+    /// Create a new, empty instance buffer. Memory is allocated on the first update. Buffers that are no longer needed are kept
+    /// alive for `frames_in_flight` calls to [`InstanceBuffer::update()`], so this should be the number of frames that can be
+    /// in flight at the same time.
+    pub fn new(device: Device, frames_in_flight: u32) -> Self {
+        Self {
+            device,
+            buffer: None,
+            capacity: 0,
+            deferred_delete: DeletionQueue::new(frames_in_flight + 1),
+        }
+    }
+
+    /// Upload new instances. This writes the instances to a staging buffer, and records a copy to the instance buffer on `cmd`,
+    /// followed by a barrier so acceleration structure builds recorded afterwards read the new instances. The instance buffer is
+    /// reallocated if it is too small to hold all instances.
+    ///
+    /// Returns the command buffer, and the instance geometry to use in an
+    /// [`AccelerationStructureBuildInfo`](crate::AccelerationStructureBuildInfo).
+    /// # Errors
+    /// * Fails if allocating the staging or instance buffer fails.
+    /// * Fails if recording the copy fails.
+    pub fn update<'q, D: ExecutionDomain + TransferSupport>(
+        &mut self,
+        cmd: IncompleteCommandBuffer<'q, D, A>,
+        allocator: &mut A,
+        instances: &[AccelerationStructureInstance],
+    ) -> Result<(IncompleteCommandBuffer<'q, D, A>, AccelerationStructureGeometryInstancesData)> {
+        self.deferred_delete.next_frame();
+
+        let size = std::mem::size_of_val(instances) as vk::DeviceSize;
+        if self.buffer.is_none() || instances.len() > self.capacity {
+            // Grow by at least a factor two, so adding a few instances every frame does not reallocate every frame.
+            self.capacity = instances.len().max(self.capacity * 2).max(1);
+            let mut buffer = Buffer::new_aligned(
+                self.device.clone(),
+                allocator,
+                (self.capacity * std::mem::size_of::<AccelerationStructureInstance>()) as vk::DeviceSize,
+                INSTANCE_ALIGNMENT,
+                MemoryType::GpuOnly,
+            )?;
+            buffer.set_memory_category(MemoryCategory::AccelerationStructure);
+            if let Some(old) = self.buffer.replace(buffer) {
+                self.deferred_delete.push(old);
+            }
+        }
+        let buffer = self.buffer.as_ref().unwrap();
+
+        let data = AccelerationStructureGeometryInstancesData {
+            data: DeviceOrHostAddressConst::Device(buffer.address()),
+            flags: vk::GeometryFlagsKHR::empty(),
+        };
+        if instances.is_empty() {
+            return Ok((cmd, data));
+        }
+
+        let staging = Buffer::new(self.device.clone(), allocator, size, MemoryType::CpuToGpu)?;
+        staging
+            .view_full()
+            .mapped_slice::<AccelerationStructureInstance>()?
+            .copy_from_slice(instances);
+        let cmd = cmd
+            // Previous builds may still be reading the instance buffer.
+            .memory_barrier(
+                PipelineStage::ACCELERATION_STRUCTURE_BUILD_KHR,
+                vk::AccessFlags2::SHADER_READ,
+                PipelineStage::TRANSFER,
+                vk::AccessFlags2::TRANSFER_WRITE,
+            )
+            .copy_buffer(&staging.view_full(), &buffer.view(0u64, size)?)?
+            .memory_barrier(
+                PipelineStage::TRANSFER,
+                vk::AccessFlags2::TRANSFER_WRITE,
+                PipelineStage::ACCELERATION_STRUCTURE_BUILD_KHR,
+                vk::AccessFlags2::SHADER_READ,
+            );
+        self.deferred_delete.push(staging);
+        Ok((cmd, data))
+    }
+
+    /// Get the number of instances that fit in the instance buffer without reallocating it.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
@@ -0,0 +1,325 @@
+//! Opacity micromaps, through `VK_EXT_opacity_micromap`.
+//!
+//! An opacity micromap subdivides each triangle of a geometry into micro-triangles, and stores whether each of them is opaque,
+//! transparent or unknown. Rays hitting opaque or transparent micro-triangles do not need to invoke the any hit shader, which
+//! greatly speeds up tracing rays against alpha-tested geometry such as foliage.
+//!
+//! Micromaps are built from a buffer of opacity data and a [`MicromapTriangle`] array describing where the data of each
+//! triangle is located, using [`ComputeCmdBuffer::build_micromap()`](crate::ComputeCmdBuffer::build_micromap).
+//! They are then attached to triangle geometry with
+//! [`AccelerationStructureBuildInfo::push_triangles_with_opacity_micromap()`](crate::AccelerationStructureBuildInfo::push_triangles_with_opacity_micromap).
+//! Building the micromap must complete before it is used in an acceleration structure build, use a barrier from
+//! [`PipelineStage::MICROMAP_BUILD_EXT`](crate::PipelineStage::MICROMAP_BUILD_EXT) with [`vk::AccessFlags2::MICROMAP_WRITE_EXT`]
+//! to [`PipelineStage::ACCELERATION_STRUCTURE_BUILD_KHR`](crate::PipelineStage::ACCELERATION_STRUCTURE_BUILD_KHR) with
+//! [`vk::AccessFlags2::MICROMAP_READ_EXT`].
+
+use anyhow::Result;
+use ash::vk;
+use ash::vk::Handle;
+
+use crate::core::device::ExtensionID;
+use crate::core::traits::{AsRaw, Nameable};
+use crate::util::address::{DeviceOrHostAddress, DeviceOrHostAddressConst};
+use crate::util::align::align;
+use crate::util::to_vk::{AsVulkanType, IntoVulkanType};
+use crate::{AccelerationStructureBuildType, BufferView, Device};
+
+/// Describes the location and format of the opacity data of a single triangle in a micromap build.
+pub type MicromapTriangle = vk::MicromapTriangleEXT;
+
+/// The number of triangles with a specific subdivision level and format in a micromap.
+pub type MicromapUsage = vk::MicromapUsageEXT;
+
+/// Wrapper around a [`VkMicromapEXT`](vk::MicromapEXT) storing opacity data.
+pub struct Micromap {
+    device: Device,
+    handle: vk::MicromapEXT,
+}
+
+impl Micromap {
+    /// Create a new opacity micromap.
+    /// # Parameters
+    /// * `device`  - The vulkan device.
+    /// * `buffer`  - The backing memory buffer for this micromap. Its offset must be a multiple of [`Micromap::alignment()`].
+    /// * `flags`   - Micromap create flags.
+    /// # Errors
+    /// * Fails if [`ExtensionID::OpacityMicromap`] is not enabled.
+    pub fn new(device: Device, buffer: BufferView, flags: vk::MicromapCreateFlagsEXT) -> Result<Self> {
+        device.require_extension(ExtensionID::OpacityMicromap)?;
+        let fns = device.opacity_micromap().unwrap();
+        let info = vk::MicromapCreateInfoEXT {
+            s_type: vk::StructureType::MICROMAP_CREATE_INFO_EXT,
+            p_next: std::ptr::null(),
+            create_flags: flags,
+            buffer: unsafe { buffer.handle() },
+            offset: buffer.offset(),
+            size: buffer.size(),
+            ty: vk::MicromapTypeEXT::OPACITY_MICROMAP,
+            // should be left at zero
+            device_address: 0,
+        };
+
+        let mut handle = vk::MicromapEXT::null();
+        unsafe {
+            (fns.create_micromap_ext)(device.handle().handle(), &info, std::ptr::null(), &mut handle).result()?;
+        }
+
+        #[cfg(feature = "log-objects")]
+        trace!("Created new VkMicromapEXT {:p}", handle);
+
+        Ok(Self {
+            device,
+            handle,
+        })
+    }
+
+    /// Get the required alignment for the backing memory
+    pub fn alignment() -> u64 {
+        // From the spec: 'offset must be a multiple of 256 bytes'
+        // (https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/VkMicromapCreateInfoEXT.html)
+        256
+    }
+
+    /// Get unsafe access to the raw Vulkan handle of this micromap
+    /// # Safety
+    /// Any mutation of the micromap may put the system in an undefined state
+    pub unsafe fn handle(&self) -> vk::MicromapEXT {
+        self.handle
+    }
+}
+
+unsafe impl AsRaw for Micromap {
+    unsafe fn as_raw(&self) -> u64 {
+        self.handle().as_raw()
+    }
+}
+
+impl Nameable for Micromap {
+    const OBJECT_TYPE: vk::ObjectType = vk::ObjectType::MICROMAP_EXT;
+}
+
+impl Drop for Micromap {
+    fn drop(&mut self) {
+        #[cfg(feature = "log-objects")]
+        trace!("Destroying VkMicromapEXT {:p}", self.handle);
+        unsafe {
+            // Since we created this object successfully surely the extension is supported
+            let fns = self.device.opacity_micromap().unwrap();
+            (fns.destroy_micromap_ext)(self.device.handle().handle(), self.handle, std::ptr::null());
+        }
+    }
+}
+
+/// All information required to build an opacity micromap
+pub struct MicromapBuildInfo<'a> {
+    /// Micromap build flags
+    pub flags: vk::BuildMicromapFlagsEXT,
+    /// Destination micromap
+    pub dst: Option<&'a Micromap>,
+    /// Number of triangles using each combination of subdivision level and format
+    pub usage_counts: Vec<MicromapUsage>,
+    /// Address of the opacity data, must be aligned to 256 bytes
+    pub data: DeviceOrHostAddressConst,
+    /// Scratch data used for building
+    pub scratch_data: DeviceOrHostAddress,
+    /// Address of the array of [`MicromapTriangle`] entries, must be aligned to 256 bytes
+    pub triangle_array: DeviceOrHostAddressConst,
+    /// Stride between entries in the triangle array
+    pub triangle_array_stride: vk::DeviceSize,
+}
+
+impl<'a> Default for MicromapBuildInfo<'a> {
+    /// Create a default micromap build info
+    fn default() -> Self {
+        Self {
+            flags: Default::default(),
+            dst: None,
+            usage_counts: vec![],
+            data: DeviceOrHostAddressConst::null_host(),
+            scratch_data: DeviceOrHostAddress::null_host(),
+            triangle_array: DeviceOrHostAddressConst::null_host(),
+            triangle_array_stride: std::mem::size_of::<MicromapTriangle>() as vk::DeviceSize,
+        }
+    }
+}
+
+impl<'a> MicromapBuildInfo<'a> {
+    /// Create a build info struct for micromap build operations
+    pub fn new_build() -> Self {
+        Self::default()
+    }
+
+    /// Set the micromap build flags
+    pub fn flags(mut self, flags: vk::BuildMicromapFlagsEXT) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Set the destination micromap
+    pub fn dst(mut self, dst: &'a Micromap) -> Self {
+        self.dst = Some(dst);
+        self
+    }
+
+    /// Add the number of triangles that use a specific subdivision level and format
+    pub fn push_usage(mut self, count: u32, subdivision_level: u32, format: vk::OpacityMicromapFormatEXT) -> Self {
+        self.usage_counts.push(MicromapUsage {
+            count,
+            subdivision_level,
+            format: format.as_raw() as u32,
+        });
+        self
+    }
+
+    /// Set the opacity data address
+    pub fn data(mut self, data: impl Into<DeviceOrHostAddressConst>) -> Self {
+        self.data = data.into();
+        self
+    }
+
+    /// Set the scratch buffer device address used for the build operation
+    pub fn scratch_data(mut self, data: impl Into<DeviceOrHostAddress>) -> Self {
+        self.scratch_data = data.into();
+        self
+    }
+
+    /// Set the triangle array address and the stride between its entries
+    pub fn triangle_array(mut self, data: impl Into<DeviceOrHostAddressConst>, stride: impl Into<vk::DeviceSize>) -> Self {
+        self.triangle_array = data.into();
+        self.triangle_array_stride = stride.into();
+        self
+    }
+}
+
+impl<'a> AsVulkanType for MicromapBuildInfo<'a> {
+    type Output = vk::MicromapBuildInfoEXT;
+
+    fn as_vulkan(&self) -> Self::Output {
+        vk::MicromapBuildInfoEXT {
+            s_type: vk::StructureType::MICROMAP_BUILD_INFO_EXT,
+            p_next: std::ptr::null(),
+            ty: vk::MicromapTypeEXT::OPACITY_MICROMAP,
+            flags: self.flags,
+            mode: vk::BuildMicromapModeEXT::BUILD,
+            dst_micromap: self.dst.map(|m| unsafe { m.handle() }).unwrap_or_default(),
+            usage_counts_count: self.usage_counts.len() as u32,
+            p_usage_counts: self.usage_counts.as_ptr(),
+            pp_usage_counts: std::ptr::null(),
+            data: self.data.as_vulkan(),
+            scratch_data: self.scratch_data.as_vulkan(),
+            triangle_array: self.triangle_array.as_vulkan(),
+            triangle_array_stride: self.triangle_array_stride,
+        }
+    }
+}
+
+/// Holds the required sizes of buffers for a micromap
+#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone)]
+pub struct MicromapBuildSize {
+    /// Required size of the micromap
+    pub size: vk::DeviceSize,
+    /// Required size of the scratch buffer for build operations
+    pub build_scratch_size: vk::DeviceSize,
+}
+
+/// Get the build sizes for this micromap build info. Only the usage counts and flags of the build info are used.
+pub fn query_micromap_build_size(
+    device: &Device,
+    ty: AccelerationStructureBuildType,
+    info: &MicromapBuildInfo,
+) -> Result<MicromapBuildSize> {
+    device.require_extension(ExtensionID::OpacityMicromap)?;
+    let fns = device.opacity_micromap().unwrap();
+    let mut sizes = vk::MicromapBuildSizesInfoEXT::default();
+    unsafe {
+        (fns.get_micromap_build_sizes_ext)(device.handle().handle(), ty.into_vulkan(), &info.as_vulkan(), &mut sizes);
+    }
+    let scratch_align = device
+        .acceleration_structure_properties()?
+        .min_acceleration_structure_scratch_offset_alignment
+        as vk::DeviceSize;
+    Ok(MicromapBuildSize {
+        size: align(sizes.micromap_size, Micromap::alignment()),
+        build_scratch_size: align(sizes.build_scratch_size, scratch_align),
+    })
+}
+
+/// An opacity micromap attached to triangle geometry, see
+/// [`AccelerationStructureBuildInfo::push_triangles_with_opacity_micromap()`](crate::AccelerationStructureBuildInfo::push_triangles_with_opacity_micromap).
+pub struct AccelerationStructureTrianglesOpacityMicromap<'a> {
+    /// The micromap to attach
+    pub micromap: &'a Micromap,
+    /// Index type of the index buffer, or `vk::IndexType::NONE_KHR` to use the index of the triangle in the geometry
+    pub index_type: vk::IndexType,
+    /// Address of the buffer with the micromap triangle index of each triangle in the geometry. Negative indices
+    /// select a special index, see [`vk::OpacityMicromapSpecialIndexEXT`].
+    pub index_buffer: DeviceOrHostAddressConst,
+    /// Stride between entries in the index buffer
+    pub index_stride: vk::DeviceSize,
+    /// Offset added to the micromap triangle index of every triangle
+    pub base_triangle: u32,
+    /// Number of triangles referencing each combination of subdivision level and format
+    pub usage_counts: Vec<MicromapUsage>,
+}
+
+impl<'a> AccelerationStructureTrianglesOpacityMicromap<'a> {
+    /// Attach a micromap, where each triangle in the geometry uses the micromap triangle with the same index.
+    pub fn new(micromap: &'a Micromap) -> Self {
+        Self {
+            micromap,
+            index_type: vk::IndexType::NONE_KHR,
+            index_buffer: DeviceOrHostAddressConst::null_host(),
+            index_stride: 0,
+            base_triangle: 0,
+            usage_counts: vec![],
+        }
+    }
+
+    /// Set the index buffer address, its type and the stride between indices
+    pub fn index_buffer(
+        mut self,
+        ty: vk::IndexType,
+        data: impl Into<DeviceOrHostAddressConst>,
+        stride: impl Into<vk::DeviceSize>,
+    ) -> Self {
+        self.index_type = ty;
+        self.index_buffer = data.into();
+        self.index_stride = stride.into();
+        self
+    }
+
+    /// Set the offset added to every micromap triangle index
+    pub fn base_triangle(mut self, base_triangle: u32) -> Self {
+        self.base_triangle = base_triangle;
+        self
+    }
+
+    /// Add the number of triangles that reference a specific subdivision level and format
+    pub fn push_usage(mut self, count: u32, subdivision_level: u32, format: vk::OpacityMicromapFormatEXT) -> Self {
+        self.usage_counts.push(MicromapUsage {
+            count,
+            subdivision_level,
+            format: format.as_raw() as u32,
+        });
+        self
+    }
+}
+
+impl<'a> AsVulkanType for AccelerationStructureTrianglesOpacityMicromap<'a> {
+    type Output = vk::AccelerationStructureTrianglesOpacityMicromapEXT;
+
+    fn as_vulkan(&self) -> Self::Output {
+        vk::AccelerationStructureTrianglesOpacityMicromapEXT {
+            s_type: vk::StructureType::ACCELERATION_STRUCTURE_TRIANGLES_OPACITY_MICROMAP_EXT,
+            p_next: std::ptr::null_mut(),
+            index_type: self.index_type,
+            index_buffer: self.index_buffer.as_vulkan(),
+            index_stride: self.index_stride,
+            base_triangle: self.base_triangle,
+            usage_counts_count: self.usage_counts.len() as u32,
+            p_usage_counts: self.usage_counts.as_ptr(),
+            pp_usage_counts: std::ptr::null(),
+            micromap: unsafe { self.micromap.handle() },
+        }
+    }
+}
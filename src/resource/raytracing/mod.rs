@@ -3,13 +3,19 @@
 pub use acceleration_structure::*;
 pub use as_build_type::*;
 pub use as_type::*;
+pub use batch_build::*;
 pub use build_info::*;
 pub use build_size::*;
 pub use geometry::*;
+pub use instance_buffer::*;
+pub use micromap::*;
 
 pub mod acceleration_structure;
 pub mod as_build_type;
 pub mod as_type;
+pub mod batch_build;
 pub mod build_info;
 pub mod build_size;
 pub mod geometry;
+pub mod instance_buffer;
+pub mod micromap;
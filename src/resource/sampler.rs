@@ -1,9 +1,262 @@
 //! Abstraction for Vulkan sampler objects
 
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+
 use anyhow::Result;
 use ash::vk;
 
-use crate::Device;
+use crate::{Device, Error, SamplerYcbcrConversion};
+use crate::core::device::ExtensionID;
+
+/// A custom sampler border color. Requires [`ExtensionID::CustomBorderColor`], see
+/// [`AppBuilder::custom_border_color()`](crate::AppBuilder::custom_border_color).
+#[derive(Debug, Copy, Clone)]
+pub enum CustomBorderColor {
+    /// Floating point border color, for images with floating point or normalized formats.
+    Float([f32; 4]),
+    /// Integer border color, for images with integer formats.
+    Int([i32; 4]),
+}
+
+impl CustomBorderColor {
+    fn border_color(&self) -> vk::BorderColor {
+        match self {
+            CustomBorderColor::Float(_) => vk::BorderColor::FLOAT_CUSTOM_EXT,
+            CustomBorderColor::Int(_) => vk::BorderColor::INT_CUSTOM_EXT,
+        }
+    }
+
+    fn clear_value(&self) -> vk::ClearColorValue {
+        match self {
+            CustomBorderColor::Float(color) => vk::ClearColorValue {
+                float32: *color,
+            },
+            CustomBorderColor::Int(color) => vk::ClearColorValue {
+                int32: *color,
+            },
+        }
+    }
+
+    fn bits(&self) -> [u32; 4] {
+        match self {
+            CustomBorderColor::Float(color) => color.map(f32::to_bits),
+            CustomBorderColor::Int(color) => color.map(|c| c as u32),
+        }
+    }
+}
+
+/// Describes the settings of a sampler. This is used as the key in a [`SamplerCache`], so samplers
+/// can be requested by description instead of being created and stored manually.
+///
+/// The default settings are the same as [`Sampler::default()`].
+/// # Example
+/// ```
+/// # use phobos::*;
+/// let info = SamplerCreateInfo::default()
+///     .filter(vk::Filter::NEAREST, vk::Filter::NEAREST)
+///     .address_mode(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+///     .anisotropy(16.0);
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct SamplerCreateInfo {
+    /// Magnification filter
+    pub mag_filter: vk::Filter,
+    /// Minification filter
+    pub min_filter: vk::Filter,
+    /// Filter used between mipmap levels
+    pub mipmap_mode: vk::SamplerMipmapMode,
+    /// Address modes for the `u`, `v` and `w` coordinates
+    pub address_mode: [vk::SamplerAddressMode; 3],
+    /// Bias added to the computed mip level
+    pub mip_lod_bias: f32,
+    /// Maximum anisotropy, or `None` to disable anisotropic filtering.
+    pub max_anisotropy: Option<f32>,
+    /// Comparison operator for depth comparison samplers, or `None` to disable depth comparison.
+    pub compare_op: Option<vk::CompareOp>,
+    /// Lowest mip level that can be sampled
+    pub min_lod: f32,
+    /// Highest mip level that can be sampled. Use [`vk::LOD_CLAMP_NONE`] for no limit.
+    pub max_lod: f32,
+    /// Border color used for [`vk::SamplerAddressMode::CLAMP_TO_BORDER`]. Ignored if a custom border color is set.
+    pub border_color: vk::BorderColor,
+    /// Custom border color used for [`vk::SamplerAddressMode::CLAMP_TO_BORDER`], with the format of the sampled image.
+    /// The format may be [`vk::Format::UNDEFINED`] if the `customBorderColorWithoutFormat` feature is supported.
+    pub custom_border_color: Option<(CustomBorderColor, vk::Format)>,
+    /// How texels are combined when filtering. Anything other than [`vk::SamplerReductionMode::WEIGHTED_AVERAGE`]
+    /// requires the `samplerFilterMinmax` feature.
+    pub reduction_mode: vk::SamplerReductionMode,
+    /// Whether to use unnormalized texel coordinates
+    pub unnormalized_coordinates: bool,
+}
+
+impl Default for SamplerCreateInfo {
+    fn default() -> Self {
+        Self {
+            mag_filter: vk::Filter::LINEAR,
+            min_filter: vk::Filter::LINEAR,
+            mipmap_mode: vk::SamplerMipmapMode::LINEAR,
+            address_mode: [vk::SamplerAddressMode::REPEAT; 3],
+            mip_lod_bias: 0.0,
+            max_anisotropy: None,
+            compare_op: None,
+            min_lod: 0.0,
+            max_lod: vk::LOD_CLAMP_NONE,
+            border_color: vk::BorderColor::INT_OPAQUE_BLACK,
+            custom_border_color: None,
+            reduction_mode: vk::SamplerReductionMode::WEIGHTED_AVERAGE,
+            unnormalized_coordinates: false,
+        }
+    }
+}
+
+impl SamplerCreateInfo {
+    /// Set the magnification and minification filters.
+    pub fn filter(mut self, mag: vk::Filter, min: vk::Filter) -> Self {
+        self.mag_filter = mag;
+        self.min_filter = min;
+        self
+    }
+
+    /// Set the filter used between mipmap levels.
+    pub fn mipmap_mode(mut self, mode: vk::SamplerMipmapMode) -> Self {
+        self.mipmap_mode = mode;
+        self
+    }
+
+    /// Set the address mode for all coordinates.
+    pub fn address_mode(mut self, mode: vk::SamplerAddressMode) -> Self {
+        self.address_mode = [mode; 3];
+        self
+    }
+
+    /// Set the address mode for the `u`, `v` and `w` coordinates separately.
+    pub fn address_mode_uvw(
+        mut self,
+        u: vk::SamplerAddressMode,
+        v: vk::SamplerAddressMode,
+        w: vk::SamplerAddressMode,
+    ) -> Self {
+        self.address_mode = [u, v, w];
+        self
+    }
+
+    /// Enable anisotropic filtering. The `samplerAnisotropy` device feature must be enabled for this.
+    /// The value is clamped to the maximum anisotropy the device supports when the sampler is created.
+    pub fn anisotropy(mut self, max_anisotropy: f32) -> Self {
+        self.max_anisotropy = Some(max_anisotropy);
+        self
+    }
+
+    /// Set the mip level bias and the range of mip levels that can be sampled.
+    pub fn lod(mut self, bias: f32, min: f32, max: f32) -> Self {
+        self.mip_lod_bias = bias;
+        self.min_lod = min;
+        self.max_lod = max;
+        self
+    }
+
+    /// Turn this sampler into a depth comparison sampler using the given compare operator.
+    pub fn compare_op(mut self, op: vk::CompareOp) -> Self {
+        self.compare_op = Some(op);
+        self
+    }
+
+    /// Set the border color used for [`vk::SamplerAddressMode::CLAMP_TO_BORDER`].
+    pub fn border_color(mut self, color: vk::BorderColor) -> Self {
+        self.border_color = color;
+        self
+    }
+
+    /// Set a custom border color used for [`vk::SamplerAddressMode::CLAMP_TO_BORDER`]. `format` is the format of the images
+    /// sampled with this sampler, or [`vk::Format::UNDEFINED`] if the `customBorderColorWithoutFormat` feature is supported.
+    pub fn custom_border_color(mut self, color: CustomBorderColor, format: vk::Format) -> Self {
+        self.custom_border_color = Some((color, format));
+        self
+    }
+
+    /// Set the reduction mode used when filtering. [`vk::SamplerReductionMode::MIN`] and [`vk::SamplerReductionMode::MAX`]
+    /// return the minimum or maximum of the filtered texels instead of their weighted average, which is useful for
+    /// building a hierarchical depth buffer.
+    pub fn reduction_mode(mut self, mode: vk::SamplerReductionMode) -> Self {
+        self.reduction_mode = mode;
+        self
+    }
+
+    /// Use unnormalized texel coordinates instead of normalized coordinates.
+    pub fn unnormalized_coordinates(mut self) -> Self {
+        self.unnormalized_coordinates = true;
+        self
+    }
+
+    fn to_vk(self, device: &Device) -> vk::SamplerCreateInfoBuilder<'static> {
+        let max_anisotropy = self
+            .max_anisotropy
+            .map(|value| value.min(device.properties().limits.max_sampler_anisotropy));
+        vk::SamplerCreateInfo::builder()
+            .mag_filter(self.mag_filter)
+            .min_filter(self.min_filter)
+            .mipmap_mode(self.mipmap_mode)
+            .address_mode_u(self.address_mode[0])
+            .address_mode_v(self.address_mode[1])
+            .address_mode_w(self.address_mode[2])
+            .mip_lod_bias(self.mip_lod_bias)
+            .anisotropy_enable(max_anisotropy.is_some())
+            .max_anisotropy(max_anisotropy.unwrap_or(0.0))
+            .compare_enable(self.compare_op.is_some())
+            .compare_op(self.compare_op.unwrap_or(vk::CompareOp::ALWAYS))
+            .min_lod(self.min_lod)
+            .max_lod(self.max_lod)
+            .border_color(match &self.custom_border_color {
+                None => self.border_color,
+                Some((color, _)) => color.border_color(),
+            })
+            .unnormalized_coordinates(self.unnormalized_coordinates)
+    }
+}
+
+// Floating point fields are compared and hashed by their bit patterns, so this can be used as a hash map key.
+impl PartialEq for SamplerCreateInfo {
+    fn eq(&self, other: &Self) -> bool {
+        self.mag_filter == other.mag_filter
+            && self.min_filter == other.min_filter
+            && self.mipmap_mode == other.mipmap_mode
+            && self.address_mode == other.address_mode
+            && self.mip_lod_bias.to_bits() == other.mip_lod_bias.to_bits()
+            && self.max_anisotropy.map(f32::to_bits) == other.max_anisotropy.map(f32::to_bits)
+            && self.compare_op == other.compare_op
+            && self.min_lod.to_bits() == other.min_lod.to_bits()
+            && self.max_lod.to_bits() == other.max_lod.to_bits()
+            && self.border_color == other.border_color
+            && self.custom_border_color.map(|(color, format)| (color.bits(), format))
+                == other.custom_border_color.map(|(color, format)| (color.bits(), format))
+            && self.reduction_mode == other.reduction_mode
+            && self.unnormalized_coordinates == other.unnormalized_coordinates
+    }
+}
+
+impl Eq for SamplerCreateInfo {}
+
+impl Hash for SamplerCreateInfo {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.mag_filter.hash(state);
+        self.min_filter.hash(state);
+        self.mipmap_mode.hash(state);
+        self.address_mode.hash(state);
+        self.mip_lod_bias.to_bits().hash(state);
+        self.max_anisotropy.map(f32::to_bits).hash(state);
+        self.compare_op.hash(state);
+        self.min_lod.to_bits().hash(state);
+        self.max_lod.to_bits().hash(state);
+        self.border_color.hash(state);
+        self.custom_border_color
+            .map(|(color, format)| (color.bits(), format))
+            .hash(state);
+        self.reduction_mode.hash(state);
+        self.unnormalized_coordinates.hash(state);
+    }
+}
 
 /// Represents a vulkan sampler object.
 #[derive(Derivative)]
@@ -12,6 +265,8 @@ pub struct Sampler {
     #[derivative(Debug = "ignore")]
     device: Device,
     handle: vk::Sampler,
+    /// Kept alive for as long as the sampler uses it.
+    ycbcr_conversion: Option<Arc<SamplerYcbcrConversion>>,
 }
 
 impl Sampler {
@@ -46,17 +301,90 @@ impl Sampler {
         Ok(Self {
             device: device.clone(),
             handle: unsafe { device.create_sampler(&info, None)? },
+            ycbcr_conversion: None,
         })
     }
 
+    /// Create a new sampler from a [`SamplerCreateInfo`]. To share samplers with the same settings,
+    /// request them from a [`SamplerCache`] instead.
+    /// # Errors
+    /// * Fails if a custom border color is used, but [`ExtensionID::CustomBorderColor`] is not enabled.
+    /// * Fails if a custom border color without format is used, but the `customBorderColorWithoutFormat` feature is not enabled.
+    /// * Fails if a min/max reduction mode is used, but the `samplerFilterMinmax` feature is not enabled.
+    pub fn from_info(device: Device, info: &SamplerCreateInfo) -> Result<Self> {
+        let mut vk_info = info.to_vk(&device);
+
+        let mut reduction = vk::SamplerReductionModeCreateInfo::builder().reduction_mode(info.reduction_mode);
+        if info.reduction_mode != vk::SamplerReductionMode::WEIGHTED_AVERAGE {
+            if device.features_1_2().sampler_filter_minmax != vk::TRUE {
+                return Err(Error::FeatureNotSupported("samplerFilterMinmax").into());
+            }
+            vk_info = vk_info.push_next(&mut reduction);
+        }
+
+        let mut border_color = vk::SamplerCustomBorderColorCreateInfoEXT::builder();
+        if let Some((color, format)) = &info.custom_border_color {
+            device.require_extension(ExtensionID::CustomBorderColor)?;
+            if *format == vk::Format::UNDEFINED
+                && device.custom_border_color_features().custom_border_color_without_format != vk::TRUE
+            {
+                return Err(Error::FeatureNotSupported("customBorderColorWithoutFormat").into());
+            }
+            border_color = border_color
+                .custom_border_color(color.clear_value())
+                .format(*format);
+            vk_info = vk_info.push_next(&mut border_color);
+        }
+
+        Self::new(device, vk_info.build())
+    }
+
     /// Create a new `VkSampler` object with given settings.
     pub fn new(device: Device, info: vk::SamplerCreateInfo) -> Result<Self> {
         Ok(Self {
             device: device.clone(),
             handle: unsafe { device.create_sampler(&info, None)? },
+            ycbcr_conversion: None,
         })
     }
 
+    /// Create a new sampler that converts Y′CbCr images to RGB using a sampler Y′CbCr conversion. Views sampled with this sampler
+    /// must use the same conversion, see [`Image::ycbcr_view()`](crate::Image::ycbcr_view). The sampler must be used as an immutable
+    /// sampler, see [`PipelineBuilder::immutable_sampler()`](crate::PipelineBuilder::immutable_sampler).
+    /// The conversion is kept alive for as long as the sampler exists.
+    ///
+    /// Unless the format supports separate reconstruction filters, the min and mag filters must be equal to the chroma filter
+    /// of the conversion. See the [`ycbcr`](crate::ycbcr) module.
+    /// # Errors
+    /// * Fails if an address mode other than [`vk::SamplerAddressMode::CLAMP_TO_EDGE`] is used.
+    /// * Fails if anisotropic filtering or unnormalized coordinates are used.
+    /// * Fails if a min/max reduction mode or a custom border color is used.
+    pub fn with_ycbcr_conversion(
+        device: Device,
+        info: &SamplerCreateInfo,
+        conversion: &Arc<SamplerYcbcrConversion>,
+    ) -> Result<Self> {
+        anyhow::ensure!(
+            info.address_mode == [vk::SamplerAddressMode::CLAMP_TO_EDGE; 3],
+            "Samplers with a Y'CbCr conversion must use the CLAMP_TO_EDGE address mode"
+        );
+        anyhow::ensure!(
+            info.max_anisotropy.is_none() && !info.unnormalized_coordinates,
+            "Samplers with a Y'CbCr conversion cannot use anisotropic filtering or unnormalized coordinates"
+        );
+        anyhow::ensure!(
+            info.reduction_mode == vk::SamplerReductionMode::WEIGHTED_AVERAGE && info.custom_border_color.is_none(),
+            "Samplers with a Y'CbCr conversion cannot use a reduction mode or custom border color"
+        );
+        let mut conversion_info = vk::SamplerYcbcrConversionInfo::builder()
+            // SAFETY: The conversion is kept alive by the sampler.
+            .conversion(unsafe { conversion.handle() });
+        let vk_info = info.to_vk(&device).push_next(&mut conversion_info);
+        let mut sampler = Self::new(device, vk_info.build())?;
+        sampler.ycbcr_conversion = Some(conversion.clone());
+        Ok(sampler)
+    }
+
     /// Get unsafe access to the underlying `VkSampler` object.
     /// # Safety
     /// Any vulkan calls that mutate the sampler may put the system in an undefined state.
@@ -72,3 +400,49 @@ impl Drop for Sampler {
         }
     }
 }
+
+/// Caches samplers by their [`SamplerCreateInfo`], so that every unique sampler is only created once.
+/// Samplers are kept alive until the cache is dropped. A sampler cache is available in every
+/// [`ResourcePool`](crate::pool::ResourcePool).
+/// # Example
+/// ```
+/// # use phobos::*;
+/// # use anyhow::Result;
+/// fn get_shadow_sampler(pool: &ResourcePool) -> Result<std::sync::Arc<Sampler>> {
+///     let info = SamplerCreateInfo::default()
+///         .address_mode(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+///         .compare_op(vk::CompareOp::LESS_OR_EQUAL);
+///     // Subsequent calls with the same settings return the same sampler.
+///     pool.samplers.get(&info)
+/// }
+/// ```
+#[derive(Clone, Derivative)]
+#[derivative(Debug)]
+pub struct SamplerCache {
+    #[derivative(Debug = "ignore")]
+    device: Device,
+    samplers: Arc<Mutex<HashMap<SamplerCreateInfo, Arc<Sampler>>>>,
+}
+
+impl SamplerCache {
+    /// Create a new, empty sampler cache.
+    pub fn new(device: Device) -> Self {
+        Self {
+            device,
+            samplers: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Get a sampler with the given settings, creating it if it does not exist yet.
+    /// # Errors
+    /// * Fails if creating the sampler fails.
+    pub fn get(&self, info: &SamplerCreateInfo) -> Result<Arc<Sampler>> {
+        let mut samplers = self.samplers.lock().unwrap();
+        if let Some(sampler) = samplers.get(info) {
+            return Ok(sampler.clone());
+        }
+        let sampler = Arc::new(Sampler::from_info(self.device.clone(), info)?);
+        samplers.insert(*info, sampler.clone());
+        Ok(sampler)
+    }
+}
@@ -0,0 +1,386 @@
+//! Sparse buffers and images, with memory bound through a sparse binding queue.
+//!
+//! Sparse resources are created without any memory. Instead, memory is bound to ranges of the resource in pages, which can be
+//! changed at any time by submitting a sparse binding operation. This makes it possible to only keep the parts of a large
+//! resource in memory that are actually in use, for example with virtual texturing.
+//!
+//! To use sparse resources, request a queue with [`QueueType::SparseBinding`](crate::QueueType::SparseBinding) in the
+//! [`GPURequirements`](crate::GPURequirements). This enables the sparse binding and residency features supported by the device.
+//! Sparse resources are created with [`Buffer::new_sparse()`] and [`Image::new_sparse()`], and memory for them is allocated
+//! as a number of pages in a [`SparseMemory`]. A [`SparseBindBatch`] records which pages are bound to which parts of the
+//! resources, and is submitted to the sparse binding queue with [`ExecutionManager::bind_sparse()`](crate::ExecutionManager::bind_sparse).
+//!
+//! Sparse binding operations are not ordered with other queue submissions. Use [`SparseBindBatch::wait_semaphore()`] and
+//! [`SparseBindBatch::signal_semaphore()`] to synchronize them with command buffers that use the resources.
+//! # Example
+//! ```
+//! # use anyhow::Result;
+//! # use phobos::prelude::*;
+//! fn bind_first_page(
+//!     exec: &ExecutionManager,
+//!     device: Device,
+//!     mut allocator: DefaultAllocator,
+//! ) -> Result<(Buffer, SparseMemory)> {
+//!     // A large buffer of which only a small part is resident.
+//!     let buffer = Buffer::new_sparse(device.clone(), 64 * 1024 * 1024u64, true)?;
+//!     let requirements = buffer.memory_requirements();
+//!     let memory = SparseMemory::new(device.clone(), &mut allocator, &requirements, 1, MemoryType::GpuOnly)?;
+//!     let bound = Semaphore::new(device)?;
+//!     // Bind a single page of memory to the start of the buffer. Command buffers using the buffer should wait on `bound`.
+//!     let batch = SparseBindBatch::default()
+//!         .bind_buffer(&buffer, 0, memory.page_size(), Some((&memory, 0)))?
+//!         .signal_semaphore(&bound);
+//!     exec.bind_sparse(batch)?.wait()?;
+//!     // The memory must stay alive while it is bound to the buffer.
+//!     Ok((buffer, memory))
+//! }
+//! ```
+
+use anyhow::Result;
+use ash::vk;
+
+use crate::{Allocation, Allocator, Buffer, DefaultAllocator, Device, Error, Image, MemoryType, Semaphore};
+use crate::allocator::statistics::MemoryCategory;
+
+/// A memory allocation of a number of sparse pages, that can be bound to sparse buffers and images.
+/// See the [module-level documentation](self) for more information.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct SparseMemory<A: Allocator = DefaultAllocator> {
+    #[derivative(Debug = "ignore")]
+    device: Device,
+    #[derivative(Debug = "ignore")]
+    allocation: A::Allocation,
+    page_size: vk::DeviceSize,
+    page_count: u64,
+}
+
+// SAFETY: The unsafe part of this is the mapped pointer of the allocation, but this is a pointer to GPU memory
+// so its value is not dropped when sending this to a different thread.
+unsafe impl<A: Allocator> Send for SparseMemory<A> {}
+
+impl<A: Allocator> SparseMemory<A> {
+    /// Allocate `page_count` pages of memory for the sparse resource with the given memory requirements. The page size is the
+    /// alignment of the requirements, see [`Buffer::memory_requirements()`] and [`Image::memory_requirements()`].
+    /// # Errors
+    /// * Fails if `page_count` is zero.
+    /// * Fails if allocating the memory fails.
+    pub fn new(
+        device: Device,
+        allocator: &mut A,
+        requirements: &vk::MemoryRequirements,
+        page_count: u64,
+        location: MemoryType,
+    ) -> Result<Self> {
+        anyhow::ensure!(page_count > 0, "Sparse memory needs at least one page");
+        let page_size = requirements.alignment;
+        let requirements = vk::MemoryRequirements {
+            size: page_size * page_count,
+            alignment: page_size,
+            memory_type_bits: requirements.memory_type_bits,
+        };
+        let allocation = allocator.allocate("sparse_memory", &requirements, location)?;
+        device
+            .memory_tracker()
+            .allocate(MemoryCategory::Sparse, requirements.size);
+        Ok(Self {
+            device,
+            allocation,
+            page_size,
+            page_count,
+        })
+    }
+
+    /// Get the size of a single page in bytes.
+    pub fn page_size(&self) -> vk::DeviceSize {
+        self.page_size
+    }
+
+    /// Get the number of pages in this allocation.
+    pub fn page_count(&self) -> u64 {
+        self.page_count
+    }
+
+    /// Get the memory handle and offset of a range of `size` bytes starting at `first_page`.
+    fn bind_range(&self, first_page: u64, size: vk::DeviceSize) -> Result<(vk::DeviceMemory, vk::DeviceSize)> {
+        let offset = first_page * self.page_size;
+        let fits = offset
+            .checked_add(size)
+            .is_some_and(|end| end <= self.page_size * self.page_count);
+        anyhow::ensure!(
+            fits,
+            "Sparse bind of {size} bytes starting at page {first_page} does not fit in {} pages",
+            self.page_count
+        );
+        // SAFETY: The memory handle is only used in sparse bind operations. Keeping the memory alive while it is bound is
+        // documented on SparseBindBatch.
+        Ok(unsafe { (self.allocation.memory(), self.allocation.offset() + offset) })
+    }
+}
+
+impl<A: Allocator> Drop for SparseMemory<A> {
+    fn drop(&mut self) {
+        self.device
+            .memory_tracker()
+            .free(MemoryCategory::Sparse, self.page_size * self.page_count);
+    }
+}
+
+/// A batch of sparse memory binding operations, submitted with [`ExecutionManager::bind_sparse()`](crate::ExecutionManager::bind_sparse).
+///
+/// Each bind either binds pages of a [`SparseMemory`] to a range of a resource, or unbinds that range if no memory is given.
+/// The batch does not keep the resources and memory alive. They must stay alive until the batch has been executed, and
+/// the memory must stay alive as long as it is bound to a resource that is used.
+/// See the [module-level documentation](self) for more information.
+#[derive(Debug, Default)]
+pub struct SparseBindBatch {
+    buffer_binds: Vec<(vk::Buffer, vk::SparseMemoryBind)>,
+    image_opaque_binds: Vec<(vk::Image, vk::SparseMemoryBind)>,
+    image_binds: Vec<(vk::Image, vk::SparseImageMemoryBind)>,
+    wait_semaphores: Vec<vk::Semaphore>,
+    signal_semaphores: Vec<vk::Semaphore>,
+}
+
+/// Check that a bind of `size` bytes at `offset` is page aligned and inside a resource with the given requirements.
+/// The last page of a resource may be smaller than the page size.
+fn check_opaque_bind(offset: vk::DeviceSize, size: vk::DeviceSize, requirements: &vk::MemoryRequirements) -> Result<()> {
+    let page_size = requirements.alignment;
+    let end = offset.checked_add(size);
+    let aligned_end = end.is_some_and(|end| end % page_size == 0 || end == requirements.size);
+    let inside = end.is_some_and(|end| end <= requirements.size);
+    if size == 0 || offset % page_size != 0 || !aligned_end || !inside {
+        return Err(Error::InvalidSparseBind {
+            offset,
+            size,
+            page_size,
+            resource_size: requirements.size,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Get the memory handle and offset to bind, or a null handle to unbind.
+fn memory_range<A: Allocator>(
+    memory: Option<(&SparseMemory<A>, u64)>,
+    size: vk::DeviceSize,
+) -> Result<(vk::DeviceMemory, vk::DeviceSize)> {
+    match memory {
+        None => Ok((vk::DeviceMemory::null(), 0)),
+        Some((memory, first_page)) => memory.bind_range(first_page, size),
+    }
+}
+
+impl SparseBindBatch {
+    /// Bind pages of memory to `size` bytes of a sparse buffer starting at `offset`, or unbind this range if `memory` is `None`.
+    /// `memory` is the sparse memory together with the index of the first page to bind.
+    /// # Errors
+    /// * Fails with [`Error::NotSparse`] if the buffer is not a sparse buffer.
+    /// * Fails with [`Error::InvalidSparseBind`] if the range is not page aligned or outside of the buffer.
+    /// * Fails if the pages are outside of the sparse memory.
+    pub fn bind_buffer<A: Allocator>(
+        mut self,
+        buffer: &Buffer<A>,
+        offset: vk::DeviceSize,
+        size: vk::DeviceSize,
+        memory: Option<(&SparseMemory<A>, u64)>,
+    ) -> Result<Self> {
+        if !buffer.is_sparse() {
+            return Err(Error::NotSparse.into());
+        }
+        check_opaque_bind(offset, size, &buffer.memory_requirements())?;
+        let (memory, memory_offset) = memory_range(memory, size)?;
+        self.buffer_binds.push((
+            // SAFETY: The handle is only used for this bind, which requires the buffer to stay alive.
+            unsafe { buffer.handle() },
+            vk::SparseMemoryBind {
+                resource_offset: offset,
+                size,
+                memory,
+                memory_offset,
+                flags: vk::SparseMemoryBindFlags::empty(),
+            },
+        ));
+        Ok(self)
+    }
+
+    /// Bind pages of memory to `size` bytes of the opaque memory of a sparse image starting at `offset`, or unbind this range if
+    /// `memory` is `None`. Opaque binds treat the image as a linear range of memory, and are used for images without sparse
+    /// residency and for the mip tail of resident images. See [`Image::sparse_memory_requirements()`].
+    /// # Errors
+    /// * Fails with [`Error::NotSparse`] if the image is not a sparse image.
+    /// * Fails with [`Error::InvalidSparseBind`] if the range is not page aligned or outside of the image.
+    /// * Fails if the pages are outside of the sparse memory.
+    pub fn bind_image_opaque<A: Allocator>(
+        mut self,
+        image: &Image<A>,
+        offset: vk::DeviceSize,
+        size: vk::DeviceSize,
+        memory: Option<(&SparseMemory<A>, u64)>,
+    ) -> Result<Self> {
+        if !image.is_sparse() {
+            return Err(Error::NotSparse.into());
+        }
+        check_opaque_bind(offset, size, &image.memory_requirements())?;
+        let (memory, memory_offset) = memory_range(memory, size)?;
+        self.image_opaque_binds.push((
+            // SAFETY: The handle is only used for this bind, which requires the image to stay alive.
+            unsafe { image.handle() },
+            vk::SparseMemoryBind {
+                resource_offset: offset,
+                size,
+                memory,
+                memory_offset,
+                flags: vk::SparseMemoryBindFlags::empty(),
+            },
+        ));
+        Ok(self)
+    }
+
+    /// Bind pages of memory to a region of a single mip level and array layer of a sparse resident image, or unbind this region
+    /// if `memory` is `None`. The offset must be a multiple of the image granularity of the aspect, and the extent must be a
+    /// multiple of it unless the region touches the edge of the mip level. Each block of the granularity uses one page.
+    /// Mip levels in the mip tail can only be bound with [`SparseBindBatch::bind_image_opaque()`].
+    /// # Errors
+    /// * Fails with [`Error::NotSparse`] if the image is not a sparse image.
+    /// * Fails with [`Error::InvalidSparseBind`] if the region is not aligned to the image granularity, or is outside of the mip level.
+    /// * Fails if the image has no sparse memory requirements for the aspect of the subresource.
+    /// * Fails if the subresource is outside of the image, or its mip level is part of the mip tail.
+    /// * Fails if the pages are outside of the sparse memory.
+    pub fn bind_image<A: Allocator>(
+        mut self,
+        image: &Image<A>,
+        subresource: vk::ImageSubresource,
+        offset: vk::Offset3D,
+        extent: vk::Extent3D,
+        memory: Option<(&SparseMemory<A>, u64)>,
+    ) -> Result<Self> {
+        if !image.is_sparse() {
+            return Err(Error::NotSparse.into());
+        }
+        anyhow::ensure!(
+            subresource.mip_level < image.mip_levels() && subresource.array_layer < image.layers(),
+            "Sparse bind to mip level {} and array layer {} is outside of the image",
+            subresource.mip_level,
+            subresource.array_layer
+        );
+        let requirements = image.memory_requirements();
+        let sparse = image
+            .sparse_memory_requirements()
+            .into_iter()
+            .find(|sparse| sparse.format_properties.aspect_mask.contains(subresource.aspect_mask))
+            .ok_or_else(|| anyhow::anyhow!("Image has no sparse memory requirements for aspect {:?}", subresource.aspect_mask))?;
+        anyhow::ensure!(
+            subresource.mip_level < sparse.image_mip_tail_first_lod,
+            "Mip level {} is part of the mip tail, which must be bound as opaque memory",
+            subresource.mip_level
+        );
+        let granularity = sparse.format_properties.image_granularity;
+        let page_size = requirements.alignment;
+        let mip_size = image.size();
+        let mip_size = [
+            (mip_size.width >> subresource.mip_level).max(1),
+            (mip_size.height >> subresource.mip_level).max(1),
+            (mip_size.depth >> subresource.mip_level).max(1),
+        ];
+        let granularity = [granularity.width, granularity.height, granularity.depth];
+        let offset_xyz = [offset.x, offset.y, offset.z];
+        let extent_xyz = [extent.width, extent.height, extent.depth];
+        // Number of pages along each axis, of the mip level and of the bound region.
+        let mip_blocks = [0, 1, 2].map(|i| mip_size[i].div_ceil(granularity[i]) as u64);
+        let blocks = [0, 1, 2].map(|i| extent_xyz[i].div_ceil(granularity[i]) as u64);
+        let size = blocks.iter().product::<u64>() * page_size;
+        let first_block = [0, 1, 2].map(|i| offset_xyz[i].max(0) as u64 / granularity[i] as u64);
+        let first_page = (first_block[2] * mip_blocks[1] + first_block[1]) * mip_blocks[0] + first_block[0];
+        let valid = (0..3).all(|i| {
+            let start = offset_xyz[i];
+            let end = (start as i64).checked_add(extent_xyz[i] as i64);
+            start >= 0
+                && start as u32 % granularity[i] == 0
+                && extent_xyz[i] > 0
+                && end.is_some_and(|end| end <= mip_size[i] as i64)
+        });
+        if !valid {
+            return Err(Error::InvalidSparseBind {
+                offset: first_page * page_size,
+                size,
+                page_size,
+                resource_size: mip_blocks.iter().product::<u64>() * page_size,
+            }
+            .into());
+        }
+        let (memory, memory_offset) = memory_range(memory, size)?;
+        self.image_binds.push((
+            // SAFETY: The handle is only used for this bind, which requires the image to stay alive.
+            unsafe { image.handle() },
+            vk::SparseImageMemoryBind {
+                subresource,
+                offset,
+                extent,
+                memory,
+                memory_offset,
+                flags: vk::SparseMemoryBindFlags::empty(),
+            },
+        ));
+        Ok(self)
+    }
+
+    /// Wait on a semaphore before executing the binds in this batch.
+    pub fn wait_semaphore(mut self, semaphore: &Semaphore) -> Self {
+        // SAFETY: The semaphore must stay alive until the batch has been executed, see the struct documentation.
+        self.wait_semaphores.push(unsafe { semaphore.handle() });
+        self
+    }
+
+    /// Signal a semaphore once the binds in this batch have been executed. Command buffers using the bound resources should
+    /// wait on this semaphore.
+    pub fn signal_semaphore(mut self, semaphore: &Semaphore) -> Self {
+        // SAFETY: The semaphore must stay alive until the batch has been executed, see the struct documentation.
+        self.signal_semaphores.push(unsafe { semaphore.handle() });
+        self
+    }
+
+    /// Whether this batch contains no binds.
+    pub fn is_empty(&self) -> bool {
+        self.buffer_binds.is_empty() && self.image_opaque_binds.is_empty() && self.image_binds.is_empty()
+    }
+
+    /// Build the bind info structures for this batch, and pass a `VkBindSparseInfo` pointing to them to `f`.
+    pub(crate) fn with_bind_info<R>(&self, f: impl FnOnce(&vk::BindSparseInfo) -> R) -> R {
+        let buffer_infos = self
+            .buffer_binds
+            .iter()
+            .map(|(buffer, bind)| vk::SparseBufferMemoryBindInfo {
+                buffer: *buffer,
+                bind_count: 1,
+                p_binds: bind,
+            })
+            .collect::<Vec<_>>();
+        let image_opaque_infos = self
+            .image_opaque_binds
+            .iter()
+            .map(|(image, bind)| vk::SparseImageOpaqueMemoryBindInfo {
+                image: *image,
+                bind_count: 1,
+                p_binds: bind,
+            })
+            .collect::<Vec<_>>();
+        let image_infos = self
+            .image_binds
+            .iter()
+            .map(|(image, bind)| vk::SparseImageMemoryBindInfo {
+                image: *image,
+                bind_count: 1,
+                p_binds: bind,
+            })
+            .collect::<Vec<_>>();
+        let info = vk::BindSparseInfo::builder()
+            .wait_semaphores(&self.wait_semaphores)
+            .buffer_binds(&buffer_infos)
+            .image_opaque_binds(&image_opaque_infos)
+            .image_binds(&image_infos)
+            .signal_semaphores(&self.signal_semaphores)
+            .build();
+        f(&info)
+    }
+}
@@ -0,0 +1,177 @@
+//! Texel buffer views, which let shaders access a range of a buffer as a one-dimensional array of formatted texels.
+//!
+//! A [`TexelBufferView`] wraps a [`VkBufferView`](vk::BufferView) with a format. Uniform texel buffers (`samplerBuffer` or
+//! `textureBuffer` in GLSL) convert texels to floating point or integer values on reads, like sampled images do. Storage texel
+//! buffers (`imageBuffer` in GLSL) also support writes and atomics. Which of these a format supports is given by the
+//! `buffer_features` of [`Device::format_properties()`](crate::Device::format_properties).
+//!
+//! Bind texel buffer views with [`IncompleteCommandBuffer::bind_uniform_texel_buffer()`](crate::IncompleteCommandBuffer::bind_uniform_texel_buffer)
+//! and [`IncompleteCommandBuffer::bind_storage_texel_buffer()`](crate::IncompleteCommandBuffer::bind_storage_texel_buffer),
+//! or write them to a [`PersistentDescriptorSet`](crate::PersistentDescriptorSet).
+//! # Example
+//! ```
+//! # use anyhow::Result;
+//! # use phobos::prelude::*;
+//! # use phobos::sync::domain::ExecutionDomain;
+//! fn bind_palette<'q, D: ExecutionDomain + ComputeSupport>(
+//!     device: Device,
+//!     cmd: IncompleteCommandBuffer<'q, D>,
+//!     palette: &Buffer,
+//! ) -> Result<(IncompleteCommandBuffer<'q, D>, TexelBufferView)> {
+//!     // The shader reads the buffer as `uniform samplerBuffer palette;`
+//!     let view = TexelBufferView::new(device, &palette.view_full(), vk::Format::R8G8B8A8_UNORM)?;
+//!     let cmd = cmd.bind_uniform_texel_buffer(0, 0, &view)?;
+//!     // The view must stay alive until the command buffer finished executing.
+//!     Ok((cmd, view))
+//! }
+//! ```
+
+use std::ops::Deref;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use anyhow::{anyhow, ensure, Result};
+use ash::vk;
+
+use crate::core::traits::{AsRaw, Nameable};
+use crate::util::format::texel_size;
+use crate::{BufferView, Device};
+
+/// Abstraction over a [`VkBufferView`](vk::BufferView). Use the reference-counted [`TexelBufferView`] instead of this directly.
+#[derive(Derivative)]
+#[derivative(Debug, Hash, PartialEq, Eq)]
+pub struct TexelView {
+    #[derivative(Debug = "ignore")]
+    #[derivative(Hash = "ignore")]
+    #[derivative(PartialEq = "ignore")]
+    device: Device,
+    handle: vk::BufferView,
+    /// The viewed range of the buffer.
+    buffer: BufferView,
+    /// Format of the texels.
+    format: vk::Format,
+    /// Unique ID for this view, because vk handles may be reused.
+    id: u64,
+}
+
+/// Reference-counted version of [`TexelView`]. See the [module-level documentation](self).
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct TexelBufferView(pub Arc<TexelView>);
+
+impl Deref for TexelBufferView {
+    type Target = Arc<TexelView>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+// SAFETY: The view is never mutated after creation, and the viewed buffer range can only be mapped through a mutable reference.
+unsafe impl Send for TexelView {}
+
+unsafe impl Sync for TexelView {}
+
+impl TexelBufferView {
+    /// Create a view of `buffer` that interprets its contents as texels of `format`.
+    /// # Errors
+    /// * Fails if `format` cannot be used for uniform or storage texel buffers on this device.
+    /// * Fails if the offset of `buffer` is not a multiple of `minTexelBufferOffsetAlignment`.
+    /// * Fails if the size of `buffer` is not a multiple of the texel size, or holds more than `maxTexelBufferElements` texels.
+    /// * Fails if creating the view fails.
+    /// # Lifetime
+    /// The returned view is valid as long as the buffer of `buffer` is valid.
+    pub fn new(device: Device, buffer: &BufferView, format: vk::Format) -> Result<Self> {
+        let features = device.format_properties(format).buffer_features;
+        ensure!(
+            features.intersects(
+                vk::FormatFeatureFlags::UNIFORM_TEXEL_BUFFER | vk::FormatFeatureFlags::STORAGE_TEXEL_BUFFER
+            ),
+            "Format {format:?} cannot be used for texel buffers on this device"
+        );
+        let limits = device.properties().limits;
+        let alignment = limits.min_texel_buffer_offset_alignment;
+        ensure!(
+            buffer.offset() % alignment == 0,
+            "Texel buffer view offset {} is not aligned to {alignment} bytes",
+            buffer.offset()
+        );
+        let texel = texel_size(format).ok_or_else(|| anyhow!("Unknown texel size of format {format:?}"))? as vk::DeviceSize;
+        ensure!(
+            buffer.size() % texel == 0,
+            "Texel buffer view size {} is not a multiple of the texel size {texel} of {format:?}",
+            buffer.size()
+        );
+        let elements = buffer.size() / texel;
+        ensure!(
+            elements <= limits.max_texel_buffer_elements as vk::DeviceSize,
+            "Texel buffer view has {elements} texels, but at most {} are supported",
+            limits.max_texel_buffer_elements
+        );
+        let info = vk::BufferViewCreateInfo {
+            buffer: unsafe { buffer.handle() },
+            format,
+            offset: buffer.offset(),
+            range: buffer.size(),
+            ..Default::default()
+        };
+        let handle = unsafe { device.create_buffer_view(&info, None)? };
+        #[cfg(feature = "log-objects")]
+        trace!("Created new VkBufferView {handle:p}");
+        Ok(Self(Arc::new(TexelView {
+            device,
+            handle,
+            buffer: *buffer,
+            format,
+            id: TexelView::get_new_id(),
+        })))
+    }
+}
+
+impl TexelView {
+    fn get_new_id() -> u64 {
+        static COUNTER: AtomicU64 = AtomicU64::new(1);
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Get unsafe access to the underlying `VkBufferView` object.
+    /// # Safety
+    /// Any vulkan calls that mutate the buffer view may put the system in an undefined state.
+    pub unsafe fn handle(&self) -> vk::BufferView {
+        self.handle
+    }
+
+    /// Get the viewed range of the buffer.
+    pub fn buffer(&self) -> BufferView {
+        self.buffer
+    }
+
+    /// Get the format of the texels.
+    pub fn format(&self) -> vk::Format {
+        self.format
+    }
+
+    /// Get the unique ID of this view.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+unsafe impl AsRaw for TexelView {
+    unsafe fn as_raw(&self) -> u64 {
+        vk::Handle::as_raw(self.handle())
+    }
+}
+
+impl Nameable for TexelView {
+    const OBJECT_TYPE: vk::ObjectType = vk::ObjectType::BUFFER_VIEW;
+}
+
+impl Drop for TexelView {
+    fn drop(&mut self) {
+        #[cfg(feature = "log-objects")]
+        trace!("Destroying VkBufferView {:p}", self.handle);
+        unsafe {
+            self.device.destroy_buffer_view(self.handle, None);
+        }
+    }
+}
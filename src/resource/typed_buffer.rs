@@ -0,0 +1,257 @@
+//! Typed wrappers around [`Buffer`] and [`BufferView`].
+//!
+//! A [`TypedBuffer<T>`] is a buffer holding a number of elements of type `T`. All sizes, offsets and device addresses
+//! are expressed in elements instead of bytes, so there is no need to multiply with `size_of::<T>()` everywhere. Since `T`
+//! must implement [`bytemuck::Pod`], the mapped memory can be safely accessed as a `&mut [T]`.
+//!
+//! A [`TypedBufferView<T>`] is the typed counterpart to a [`BufferView`], and converts into one when an untyped view is needed,
+//! for example to pass to a command buffer.
+//!
+//! # Example
+//!
+//! ```
+//! # use anyhow::Result;
+//! # use phobos::prelude::*;
+//! fn upload_vertices(device: Device, mut allocator: DefaultAllocator) -> Result<TypedBuffer<[f32; 3]>> {
+//!     let vertices = [[0.0, -0.5, 0.0], [0.5, 0.5, 0.0], [-0.5, 0.5, 0.0]];
+//!     let buffer = TypedBuffer::from_slice(device, &mut allocator, &vertices, MemoryType::CpuToGpu)?;
+//!     // Device address of the second vertex, for use in a shader.
+//!     let _address = buffer.address_of(1)?;
+//!     Ok(buffer)
+//! }
+//! ```
+
+use std::marker::PhantomData;
+
+use anyhow::{anyhow, ensure, Result};
+use ash::vk;
+use bytemuck::Pod;
+
+use crate::{Allocator, Buffer, BufferView, DefaultAllocator, Device, Error, MemoryType};
+
+/// A [`Buffer`] holding elements of type `T`. See the [module-level documentation](self) for more information.
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""))]
+pub struct TypedBuffer<T: Pod, A: Allocator = DefaultAllocator> {
+    buffer: Buffer<A>,
+    len: usize,
+    #[derivative(Debug = "ignore")]
+    _marker: PhantomData<T>,
+}
+
+/// View into a range of elements of a [`TypedBuffer`].
+/// Like a [`BufferView`], there is no checking that the buffer is not dropped while using this.
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""), Copy(bound = ""), Clone(bound = ""), PartialEq(bound = ""), Eq(bound = ""), Hash(bound = ""))]
+pub struct TypedBufferView<T: Pod> {
+    view: BufferView,
+    len: usize,
+    #[derivative(Debug = "ignore")]
+    _marker: PhantomData<T>,
+}
+
+/// Size in bytes of `len` elements of type `T`.
+fn byte_size<T: Pod>(len: usize) -> Result<vk::DeviceSize> {
+    ensure!(std::mem::size_of::<T>() != 0, "Typed buffers cannot hold zero-sized types");
+    len.checked_mul(std::mem::size_of::<T>())
+        .map(|size| size as vk::DeviceSize)
+        .ok_or_else(|| anyhow!("Size of {len} elements overflows"))
+}
+
+impl<T: Pod, A: Allocator> TypedBuffer<T, A> {
+    /// Allocate a new buffer that holds `len` elements, at a specific memory location.
+    /// # Errors
+    /// * Fails if `T` is a zero-sized type.
+    /// * Fails if allocating the buffer fails.
+    pub fn new(device: Device, allocator: &mut A, len: usize, location: MemoryType) -> Result<Self> {
+        let buffer = Buffer::new(device, allocator, byte_size::<T>(len)?, location)?;
+        Ok(Self {
+            buffer,
+            len,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Allocate a new buffer that holds `len` elements in device local memory (VRAM).
+    /// # Errors
+    /// * Fails if `T` is a zero-sized type.
+    /// * Fails if allocating the buffer fails.
+    pub fn new_device_local(device: Device, allocator: &mut A, len: usize) -> Result<Self> {
+        Self::new(device, allocator, len, MemoryType::GpuOnly)
+    }
+
+    /// Allocate a new buffer at a mappable memory location and copy `data` into it.
+    /// # Errors
+    /// * Fails if `T` is a zero-sized type.
+    /// * Fails if allocating the buffer fails.
+    /// * Fails if `location` is not mappable.
+    pub fn from_slice(device: Device, allocator: &mut A, data: &[T], location: MemoryType) -> Result<Self> {
+        let mut buffer = Self::new(device, allocator, data.len(), location)?;
+        buffer.mapped_slice()?.copy_from_slice(data);
+        Ok(buffer)
+    }
+
+    /// Wrap an existing buffer. Any bytes at the end of the buffer that do not fit a whole element are ignored.
+    /// # Errors
+    /// * Fails if `T` is a zero-sized type.
+    pub fn from_buffer(buffer: Buffer<A>) -> Result<Self> {
+        byte_size::<T>(0)?;
+        let len = buffer.size() as usize / std::mem::size_of::<T>();
+        Ok(Self {
+            buffer,
+            len,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Get the number of elements in this buffer.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether this buffer holds no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Creates a view into `len` elements of the buffer, starting at element `start`.
+    /// # Lifetime
+    /// This view is valid as long as the buffer is valid.
+    /// # Errors
+    /// * Fails if `start + len > self.len()`.
+    pub fn view(&self, start: usize, len: usize) -> Result<TypedBufferView<T>> {
+        self.view_full().view(start, len)
+    }
+
+    /// Creates a view of all elements in the buffer.
+    /// # Lifetime
+    /// This view is valid as long as the buffer is valid.
+    pub fn view_full(&self) -> TypedBufferView<T> {
+        TypedBufferView {
+            // The size of the buffer was computed from the number of elements, so this cannot be out of range.
+            view: self
+                .buffer
+                .view(0u64, (self.len * std::mem::size_of::<T>()) as vk::DeviceSize)
+                .unwrap(),
+            len: self.len,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Obtain a slice of all elements in the mapped memory of this buffer.
+    /// # Errors
+    /// * Fails if this buffer is not mappable (not `HOST_VISIBLE`).
+    /// * Fails if the mapped memory is not aligned to the alignment of `T`.
+    pub fn mapped_slice(&mut self) -> Result<&mut [T]> {
+        let mut view = self.view_full();
+        let slice = view.mapped_slice()?;
+        // SAFETY: The memory is owned by this buffer, and the mutable borrow of self ensures it is not aliased.
+        Ok(unsafe { std::slice::from_raw_parts_mut(slice.as_mut_ptr(), slice.len()) })
+    }
+
+    /// Get the device address of the first element in this buffer.
+    pub fn address(&self) -> vk::DeviceAddress {
+        self.buffer.address()
+    }
+
+    /// Get the device address of the element at `index`.
+    /// # Errors
+    /// * Fails if `index >= self.len()`.
+    pub fn address_of(&self, index: usize) -> Result<vk::DeviceAddress> {
+        self.view_full().address_of(index)
+    }
+
+    /// Get the untyped buffer.
+    pub fn buffer(&self) -> &Buffer<A> {
+        &self.buffer
+    }
+
+    /// Unwrap this into the untyped buffer.
+    pub fn into_buffer(self) -> Buffer<A> {
+        self.buffer
+    }
+}
+
+impl<T: Pod> TypedBufferView<T> {
+    /// Interpret an untyped view as a view of elements of type `T`. Any bytes at the end of the view that do not fit a whole
+    /// element are ignored.
+    /// # Errors
+    /// * Fails if `T` is a zero-sized type.
+    pub fn from_view(view: BufferView) -> Result<Self> {
+        byte_size::<T>(0)?;
+        let len = view.size() as usize / std::mem::size_of::<T>();
+        Ok(Self {
+            view: view.view(0u64, byte_size::<T>(len)?)?,
+            len,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Get the number of elements in this view.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether this view holds no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Creates a view into `len` elements of this view, starting at element `start` relative to the start of this view.
+    /// # Errors
+    /// * Fails if `start + len > self.len()`.
+    pub fn view(&self, start: usize, len: usize) -> Result<TypedBufferView<T>> {
+        let in_range = start
+            .checked_add(len)
+            .is_some_and(|end| end <= self.len);
+        if !in_range {
+            return Err(anyhow::Error::from(Error::BufferViewOutOfRange {
+                offset: start as u64,
+                size: len as u64,
+                buffer_size: self.len as u64,
+            }));
+        }
+        Ok(TypedBufferView {
+            view: self.view.view(byte_size::<T>(start)?, byte_size::<T>(len)?)?,
+            len,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Obtain a slice of the elements in this view.
+    /// # Errors
+    /// * Fails if the buffer is not mappable (not `HOST_VISIBLE`).
+    /// * Fails if the mapped memory is not aligned to the alignment of `T`.
+    pub fn mapped_slice(&mut self) -> Result<&mut [T]> {
+        self.view.mapped_slice()
+    }
+
+    /// Get the device address of the first element in this view.
+    pub fn address(&self) -> vk::DeviceAddress {
+        self.view.address()
+    }
+
+    /// Get the device address of the element at `index`, relative to the start of this view.
+    /// # Errors
+    /// * Fails if `index >= self.len()`.
+    pub fn address_of(&self, index: usize) -> Result<vk::DeviceAddress> {
+        Ok(self.view(index, 1)?.address())
+    }
+
+    /// Get the offset of this view into the owning buffer, in elements. For views created with [`TypedBufferView::from_view()`],
+    /// this is rounded down to a whole element.
+    pub fn offset(&self) -> usize {
+        self.view.offset() as usize / std::mem::size_of::<T>()
+    }
+
+    /// Get the untyped view of the same range, for example to pass to a command buffer.
+    pub fn untyped(&self) -> BufferView {
+        self.view
+    }
+}
+
+impl<T: Pod> From<TypedBufferView<T>> for BufferView {
+    fn from(value: TypedBufferView<T>) -> Self {
+        value.view
+    }
+}
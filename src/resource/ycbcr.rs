@@ -0,0 +1,336 @@
+//! Sample multi-planar Y′CbCr images, such as video decode output or camera frames, through a sampler Y′CbCr conversion.
+//!
+//! Multi-planar images store luma and chroma in separate planes, often at a lower chroma resolution. They are created like any
+//! other image, by using a multi-planar format such as [`vk::Format::G8_B8R8_2PLANE_420_UNORM`] in [`ImageCreateInfo`](crate::ImageCreateInfo).
+//! To sample them as RGB, create a [`SamplerYcbcrConversion`] for the format, and use it for both the image view
+//! (see [`Image::ycbcr_view()`]) and the sampler (see [`Sampler::with_ycbcr_conversion()`]). The sampler must be bound as an immutable
+//! sampler of a combined image sampler binding, see [`PipelineBuilder::immutable_sampler()`](crate::PipelineBuilder::immutable_sampler).
+//!
+//! Single planes can be viewed with [`Image::plane_view()`], for example to write them from a compute shader. This requires the
+//! image to be created with [`vk::ImageCreateFlags::MUTABLE_FORMAT`].
+//!
+//! This requires the `samplerYcbcrConversion` feature, which is enabled automatically when it is supported.
+//! # Example
+//! ```
+//! # use std::sync::Arc;
+//! # use anyhow::Result;
+//! # use phobos::prelude::*;
+//! fn video_sampler(device: Device, frame: &Image) -> Result<(ImageView, Arc<Sampler>)> {
+//!     let info = YcbcrConversionCreateInfo::new(frame.format())
+//!         .model(vk::SamplerYcbcrModelConversion::YCBCR_709, vk::SamplerYcbcrRange::ITU_NARROW);
+//!     let conversion = Arc::new(SamplerYcbcrConversion::new(device.clone(), info)?);
+//!     let view = frame.ycbcr_view(&conversion)?;
+//!     let sampler = Arc::new(Sampler::with_ycbcr_conversion(
+//!         device,
+//!         &SamplerCreateInfo::default().address_mode(vk::SamplerAddressMode::CLAMP_TO_EDGE),
+//!         &conversion,
+//!     )?);
+//!     // Register the pipeline with `.immutable_sampler("video", sampler.clone())`, then bind `view` with `bind_sampled_image()`.
+//!     Ok((view, sampler))
+//! }
+//! ```
+
+use anyhow::{ensure, Result};
+use ash::vk;
+
+use crate::{Allocator, Device, Error, Image, ImageCreateInfo, ImageView};
+use crate::resource::image::ImageViewCreateInfo;
+
+/// Get the plane count, the formats of the luma and chroma planes, and the chroma subsampling of a multi-planar format.
+fn planes(format: vk::Format) -> Option<(u32, vk::Format, vk::Format, (u32, u32))> {
+    use vk::Format as F;
+
+    const BITS_8: (F, F) = (F::R8_UNORM, F::R8G8_UNORM);
+    const BITS_10: (F, F) = (F::R10X6_UNORM_PACK16, F::R10X6G10X6_UNORM_2PACK16);
+    const BITS_12: (F, F) = (F::R12X4_UNORM_PACK16, F::R12X4G12X4_UNORM_2PACK16);
+    const BITS_16: (F, F) = (F::R16_UNORM, F::R16G16_UNORM);
+
+    let (count, (luma, chroma), subsampling) = match format {
+        F::G8_B8_R8_3PLANE_420_UNORM => (3, BITS_8, (2, 2)),
+        F::G8_B8R8_2PLANE_420_UNORM => (2, BITS_8, (2, 2)),
+        F::G8_B8_R8_3PLANE_422_UNORM => (3, BITS_8, (2, 1)),
+        F::G8_B8R8_2PLANE_422_UNORM => (2, BITS_8, (2, 1)),
+        F::G8_B8_R8_3PLANE_444_UNORM => (3, BITS_8, (1, 1)),
+        F::G8_B8R8_2PLANE_444_UNORM => (2, BITS_8, (1, 1)),
+        F::G10X6_B10X6_R10X6_3PLANE_420_UNORM_3PACK16 => (3, BITS_10, (2, 2)),
+        F::G10X6_B10X6R10X6_2PLANE_420_UNORM_3PACK16 => (2, BITS_10, (2, 2)),
+        F::G10X6_B10X6_R10X6_3PLANE_422_UNORM_3PACK16 => (3, BITS_10, (2, 1)),
+        F::G10X6_B10X6R10X6_2PLANE_422_UNORM_3PACK16 => (2, BITS_10, (2, 1)),
+        F::G10X6_B10X6_R10X6_3PLANE_444_UNORM_3PACK16 => (3, BITS_10, (1, 1)),
+        F::G10X6_B10X6R10X6_2PLANE_444_UNORM_3PACK16 => (2, BITS_10, (1, 1)),
+        F::G12X4_B12X4_R12X4_3PLANE_420_UNORM_3PACK16 => (3, BITS_12, (2, 2)),
+        F::G12X4_B12X4R12X4_2PLANE_420_UNORM_3PACK16 => (2, BITS_12, (2, 2)),
+        F::G12X4_B12X4_R12X4_3PLANE_422_UNORM_3PACK16 => (3, BITS_12, (2, 1)),
+        F::G12X4_B12X4R12X4_2PLANE_422_UNORM_3PACK16 => (2, BITS_12, (2, 1)),
+        F::G12X4_B12X4_R12X4_3PLANE_444_UNORM_3PACK16 => (3, BITS_12, (1, 1)),
+        F::G12X4_B12X4R12X4_2PLANE_444_UNORM_3PACK16 => (2, BITS_12, (1, 1)),
+        F::G16_B16_R16_3PLANE_420_UNORM => (3, BITS_16, (2, 2)),
+        F::G16_B16R16_2PLANE_420_UNORM => (2, BITS_16, (2, 2)),
+        F::G16_B16_R16_3PLANE_422_UNORM => (3, BITS_16, (2, 1)),
+        F::G16_B16R16_2PLANE_422_UNORM => (2, BITS_16, (2, 1)),
+        F::G16_B16_R16_3PLANE_444_UNORM => (3, BITS_16, (1, 1)),
+        F::G16_B16R16_2PLANE_444_UNORM => (2, BITS_16, (1, 1)),
+        _ => return None,
+    };
+    // In two-plane formats, both chroma channels share the second plane.
+    let chroma = if count == 2 {
+        chroma
+    } else {
+        luma
+    };
+    Some((count, luma, chroma, subsampling))
+}
+
+/// Get the number of planes of a format. This is 1 for formats that are not multi-planar.
+pub fn plane_count(format: vk::Format) -> u32 {
+    planes(format).map_or(1, |(count, ..)| count)
+}
+
+/// Whether this is a multi-planar format.
+pub fn is_multi_planar(format: vk::Format) -> bool {
+    plane_count(format) > 1
+}
+
+/// Get the format that a single plane of a multi-planar format is compatible with. This is the format of views of that plane.
+/// Returns `None` if the format is not multi-planar, or the plane does not exist.
+pub fn plane_format(format: vk::Format, plane: u32) -> Option<vk::Format> {
+    let (count, luma, chroma, _) = planes(format)?;
+    match plane {
+        0 => Some(luma),
+        plane if plane < count => Some(chroma),
+        _ => None,
+    }
+}
+
+/// Get the size of a plane of an image with a multi-planar format. Chroma planes may be subsampled. For other formats, this
+/// returns `extent`.
+pub fn plane_extent(format: vk::Format, extent: vk::Extent3D, plane: u32) -> vk::Extent3D {
+    match planes(format) {
+        Some((_, _, _, (x, y))) if plane > 0 => vk::Extent3D {
+            width: extent.width / x,
+            height: extent.height / y,
+            depth: extent.depth,
+        },
+        _ => extent,
+    }
+}
+
+/// Check the restrictions on images with a multi-planar format.
+pub(crate) fn validate_multi_planar(info: &ImageCreateInfo) -> Result<()> {
+    let Some((_, _, _, (x, y))) = planes(info.format) else {
+        return Ok(());
+    };
+    ensure!(
+        info.depth == 1 && info.mip_levels == 1,
+        "Multi-planar images must be 2D images with a single mip level"
+    );
+    ensure!(info.samples == vk::SampleCountFlags::TYPE_1, "Multi-planar images cannot be multisampled");
+    ensure!(
+        info.width % x == 0 && info.height % y == 0,
+        "The size of a multi-planar image must be a multiple of its chroma subsampling ({x}x{y})"
+    );
+    Ok(())
+}
+
+/// Get the view type that covers all layers of a multi-planar image.
+fn view_type(layers: u32) -> vk::ImageViewType {
+    if layers > 1 {
+        vk::ImageViewType::TYPE_2D_ARRAY
+    } else {
+        vk::ImageViewType::TYPE_2D
+    }
+}
+
+fn plane_aspect(plane: u32) -> vk::ImageAspectFlags {
+    match plane {
+        0 => vk::ImageAspectFlags::PLANE_0,
+        1 => vk::ImageAspectFlags::PLANE_1,
+        _ => vk::ImageAspectFlags::PLANE_2,
+    }
+}
+
+/// Settings that describe how a [`SamplerYcbcrConversion`] converts Y′CbCr image data to RGB.
+///
+/// The defaults convert BT.709 narrow range data with linear chroma reconstruction, and chroma samples that are cosited
+/// horizontally and centered vertically, as is common for video.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct YcbcrConversionCreateInfo {
+    /// Format of the images that are converted
+    pub format: vk::Format,
+    /// Color model to convert from
+    pub model: vk::SamplerYcbcrModelConversion,
+    /// Range of the encoded values
+    pub range: vk::SamplerYcbcrRange,
+    /// Swizzle of the red, green, blue and alpha components, applied before the conversion. Use `Default::default()` for the
+    /// identity swizzle.
+    pub swizzle: [vk::ComponentSwizzle; 4],
+    /// Location of the chroma samples relative to the luma samples, horizontally and vertically.
+    pub chroma_offset: [vk::ChromaLocation; 2],
+    /// Filter used to reconstruct chroma samples. [`vk::Filter::LINEAR`] requires the format to support linear chroma filtering.
+    pub chroma_filter: vk::Filter,
+    /// Whether chroma reconstruction must be done explicitly, instead of being left to the implementation.
+    pub force_explicit_reconstruction: bool,
+}
+
+impl YcbcrConversionCreateInfo {
+    /// Create conversion settings for images with the given format, using the default settings.
+    pub fn new(format: vk::Format) -> Self {
+        Self {
+            format,
+            model: vk::SamplerYcbcrModelConversion::YCBCR_709,
+            range: vk::SamplerYcbcrRange::ITU_NARROW,
+            swizzle: Default::default(),
+            chroma_offset: [vk::ChromaLocation::COSITED_EVEN, vk::ChromaLocation::MIDPOINT],
+            chroma_filter: vk::Filter::LINEAR,
+            force_explicit_reconstruction: false,
+        }
+    }
+
+    /// Set the color model and the range of the encoded values.
+    pub fn model(mut self, model: vk::SamplerYcbcrModelConversion, range: vk::SamplerYcbcrRange) -> Self {
+        self.model = model;
+        self.range = range;
+        self
+    }
+
+    /// Set the location of the chroma samples, horizontally and vertically.
+    pub fn chroma_offset(mut self, x: vk::ChromaLocation, y: vk::ChromaLocation) -> Self {
+        self.chroma_offset = [x, y];
+        self
+    }
+
+    /// Set the filter used to reconstruct chroma samples.
+    pub fn chroma_filter(mut self, filter: vk::Filter) -> Self {
+        self.chroma_filter = filter;
+        self
+    }
+}
+
+/// Represents a [`VkSamplerYcbcrConversion`](vk::SamplerYcbcrConversion) object. A conversion is used by both the image view and
+/// the sampler used to sample a Y′CbCr image, so it is usually wrapped in an `Arc`. See the [module-level documentation](self).
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct SamplerYcbcrConversion {
+    #[derivative(Debug = "ignore")]
+    device: Device,
+    handle: vk::SamplerYcbcrConversion,
+    info: YcbcrConversionCreateInfo,
+}
+
+impl SamplerYcbcrConversion {
+    /// Create a new sampler Y′CbCr conversion.
+    /// # Errors
+    /// * Fails if the `samplerYcbcrConversion` feature is not enabled.
+    pub fn new(device: Device, info: YcbcrConversionCreateInfo) -> Result<Self> {
+        if device.features_1_1().sampler_ycbcr_conversion != vk::TRUE {
+            return Err(Error::FeatureNotSupported("samplerYcbcrConversion").into());
+        }
+        let vk_info = vk::SamplerYcbcrConversionCreateInfo {
+            format: info.format,
+            ycbcr_model: info.model,
+            ycbcr_range: info.range,
+            components: vk::ComponentMapping {
+                r: info.swizzle[0],
+                g: info.swizzle[1],
+                b: info.swizzle[2],
+                a: info.swizzle[3],
+            },
+            x_chroma_offset: info.chroma_offset[0],
+            y_chroma_offset: info.chroma_offset[1],
+            chroma_filter: info.chroma_filter,
+            force_explicit_reconstruction: info.force_explicit_reconstruction as vk::Bool32,
+            ..Default::default()
+        };
+        // SAFETY: Vulkan API call. The create info is valid for the duration of this call.
+        let handle = unsafe { device.create_sampler_ycbcr_conversion(&vk_info, None)? };
+        #[cfg(feature = "log-objects")]
+        trace!("Created new VkSamplerYcbcrConversion {handle:p}");
+        Ok(Self {
+            device,
+            handle,
+            info,
+        })
+    }
+
+    /// Get the settings this conversion was created with.
+    pub fn info(&self) -> &YcbcrConversionCreateInfo {
+        &self.info
+    }
+
+    /// Get unsafe access to the underlying `VkSamplerYcbcrConversion` object.
+    /// # Safety
+    /// Any vulkan calls that mutate the conversion may put the system in an undefined state.
+    pub unsafe fn handle(&self) -> vk::SamplerYcbcrConversion {
+        self.handle
+    }
+}
+
+impl Drop for SamplerYcbcrConversion {
+    fn drop(&mut self) {
+        #[cfg(feature = "log-objects")]
+        trace!("Destroying VkSamplerYcbcrConversion {:p}", self.handle);
+        unsafe {
+            self.device.destroy_sampler_ycbcr_conversion(self.handle, None);
+        }
+    }
+}
+
+impl<A: Allocator> Image<A> {
+    /// Construct a view of this image that samples it as RGB through a sampler Y′CbCr conversion. The view must be sampled
+    /// with a sampler that uses the same conversion, see [`Sampler::with_ycbcr_conversion()`](crate::Sampler::with_ycbcr_conversion).
+    /// # Errors
+    /// * Fails if the conversion was created for a different format than the image format.
+    /// # Lifetime
+    /// The returned [`ImageView`] is valid as long as `self` and the conversion are valid.
+    pub fn ycbcr_view(&self, conversion: &SamplerYcbcrConversion) -> Result<ImageView> {
+        ensure!(
+            conversion.info().format == self.format(),
+            "Y'CbCr conversion for {:?} cannot be used with an image of format {:?}",
+            conversion.info().format,
+            self.format()
+        );
+        let conversion_info = vk::SamplerYcbcrConversionInfo {
+            // SAFETY: The conversion is valid for the duration of this call.
+            conversion: unsafe { conversion.handle() },
+            ..Default::default()
+        };
+        let info = ImageViewCreateInfo {
+            aspect: vk::ImageAspectFlags::COLOR,
+            view_type: view_type(self.layers()),
+            base_mip_level: 0,
+            level_count: None,
+            base_layer: 0,
+            layers: None,
+            format: None,
+            swizzle: Default::default(),
+            usage: None,
+        };
+        self.create_view(info, &conversion_info as *const _ as *const std::ffi::c_void, self.size())
+    }
+
+    /// Construct a view of a single plane of this multi-planar image, with the format of that plane. The size of the view is the
+    /// size of the plane, which is smaller than the image for subsampled chroma planes.
+    ///
+    /// The image must be created with [`vk::ImageCreateFlags::MUTABLE_FORMAT`].
+    /// # Errors
+    /// * Fails if the image format is not multi-planar, or the plane does not exist.
+    /// * Fails if the image does not have a mutable format.
+    /// # Lifetime
+    /// The returned [`ImageView`] is valid as long as `self` is valid.
+    pub fn plane_view(&self, plane: u32) -> Result<ImageView> {
+        let format = plane_format(self.format(), plane)
+            .ok_or_else(|| anyhow::anyhow!("Image of format {:?} has no plane {plane}", self.format()))?;
+        let info = ImageViewCreateInfo {
+            aspect: plane_aspect(plane),
+            view_type: view_type(self.layers()),
+            base_mip_level: 0,
+            level_count: None,
+            base_layer: 0,
+            layers: None,
+            format: Some(format),
+            swizzle: Default::default(),
+            usage: None,
+        };
+        self.create_view(info, std::ptr::null(), plane_extent(self.format(), self.size(), plane))
+    }
+}
@@ -1,10 +1,11 @@
 //! Domains abstract over the concept of Vulkan queue families.
 //!
-//! Commands are divided into four domains:
+//! Commands are divided into five domains:
 //! - [Transfer](crate::domain::Transfer): All transfer and copy related commands.
 //! - [Graphics](crate::domain::Graphics): All graphics and rendering related commands.
 //! - [Compute](crate::domain::Compute): GPU compute commands, most notably `vkCmdDispatch`
 //! - [All](crate::domain::All): All of the above.
+//! - [VideoDecode](crate::domain::VideoDecode): Video decode commands, see the [`video`](crate::video) module.
 //!
 //! A command buffer over a domain is allocated from a queue that supports all operations
 //! on its domain, and as few other domains (to try to catch dedicated transfer/async compute queues). For this reason, always try to
@@ -43,6 +44,9 @@ pub struct Transfer;
 /// Supports compute operations. For main rendering, you typically want to use the [`All`] domain
 /// instead of this, as switching between queues for every compute operation has too much overhead.
 pub struct Compute;
+/// Supports video decode operations. This domain does not support any other operations, and is always submitted to a queue
+/// requested with [`QueueType::VideoDecode`].
+pub struct VideoDecode;
 
 impl ExecutionDomain for Graphics {
     /// Returns true if the selected queue can be used to submit commands from this entire domain
@@ -77,6 +81,17 @@ impl ExecutionDomain for Compute {
     type CmdBuf<'q, A: Allocator> = IncompleteCommandBuffer<'q, Compute, A>;
 }
 
+impl ExecutionDomain for VideoDecode {
+    /// Returns true if the selected queue can be used to submit commands from this entire domain
+    /// to.
+    fn queue_is_compatible(queue: &Queue) -> bool {
+        queue.info().queue_type == QueueType::VideoDecode
+    }
+
+    /// Type of the command buffer that will be submitted to this domain.
+    type CmdBuf<'q, A: Allocator> = IncompleteCommandBuffer<'q, VideoDecode, A>;
+}
+
 impl ExecutionDomain for All {
     /// Returns true if the selected queue can be used to submit commands from this entire domain
     /// to.
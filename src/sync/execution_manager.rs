@@ -1,15 +1,17 @@
 //! Exposes the [`ExecutionManager`], used to allocate and submit command buffers.
 
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex, MutexGuard, TryLockError, TryLockResult};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock, TryLockError, TryLockResult};
+use std::sync::atomic::Ordering;
 
 use anyhow::Result;
 use ash::vk;
 
-use crate::{Allocator, CmdBuffer, DefaultAllocator, Device, Error, Fence, PhysicalDevice};
+use crate::{Allocator, CmdBuffer, DefaultAllocator, Device, Error, Fence, GpuFuture, PhysicalDevice};
 use crate::command_buffer::*;
-use crate::core::queue::{DeviceQueue, Queue};
+use crate::core::queue::{DeviceQueue, Queue, QueueType};
 use crate::pool::{Poolable, Pooled, ResourcePool};
+use crate::sparse::SparseBindBatch;
 use crate::sync::domain::ExecutionDomain;
 use crate::sync::submit_batch::SubmitBatch;
 
@@ -27,6 +29,9 @@ use crate::sync::submit_batch::SubmitBatch;
 /// Note that all domains also implement a couple commands that apply to all domains with no
 /// restrictions on queue type support, such as pipeline barriers.
 ///
+/// Which hardware queue the [`domain::Compute`](crate::domain::Compute) domain maps to can be changed at runtime with
+/// [`ExecutionManager::set_compute_queue_policy()`].
+///
 /// # Example
 /// ```
 /// use phobos::prelude::*;
@@ -44,9 +49,24 @@ use crate::sync::submit_batch::SubmitBatch;
 pub struct ExecutionManager<A: Allocator = DefaultAllocator> {
     device: Device,
     queues: Arc<Vec<Mutex<Queue>>>,
+    // Indices in `queues` of the requested compute queue and the extra compute queue on the graphics family, if both exist.
+    compute_queues: Option<(usize, usize)>,
+    compute_policy: Arc<RwLock<ComputeQueuePolicy>>,
     pool: ResourcePool<A>,
 }
 
+/// Controls which queue the [`domain::Compute`](crate::domain::Compute) domain submits to. On some GPUs, a dedicated compute
+/// queue is slower for work that is closely tied to graphics work, so it can be worth benchmarking both options.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum ComputeQueuePolicy {
+    /// Use the requested compute queue, which is on a dedicated queue family if one was available.
+    #[default]
+    Dedicated,
+    /// Use a second queue of the graphics queue family. Only has an effect if the compute queue is on a dedicated family and the
+    /// graphics family supports compute, otherwise this is the same as [`ComputeQueuePolicy::Dedicated`].
+    GraphicsFamily,
+}
+
 fn max_queue_count(family: u32, families: &[vk::QueueFamilyProperties]) -> u32 {
     // TODO: missing queue family in the middle will panic
     families.get(family as usize).unwrap().queue_count
@@ -63,7 +83,7 @@ impl<A: Allocator> ExecutionManager<A> {
         let mut counts = HashMap::new();
         let mut device_queues = HashMap::new();
 
-        let queues = physical_device
+        let mut queues = physical_device
             .queues()
             .iter()
             .map(|queue| -> Result<Mutex<Queue>> {
@@ -99,6 +119,39 @@ impl<A: Allocator> ExecutionManager<A> {
             })
             .collect::<Result<Vec<Mutex<Queue>>>>()?;
 
+        // Always create the compute queue on the graphics family when it exists, so the compute policy can be switched at runtime.
+        // It is placed last so it is never picked over the graphics queue for other domains.
+        let compute_queues = match physical_device.graphics_family_compute_queue() {
+            None => None,
+            Some(info) => {
+                let index = counts.entry(info.family_index).or_insert(0);
+                let device_queue = if *index
+                    >= max_queue_count(info.family_index, physical_device.queue_families())
+                {
+                    // No more hardware queues available in this family, share the graphics queue.
+                    device_queues.get(&info.family_index).cloned().unwrap()
+                } else {
+                    Arc::new(Mutex::new(DeviceQueue {
+                        handle: unsafe { device.get_device_queue(info.family_index, *index) },
+                    }))
+                };
+                queues.push(Mutex::new(Queue::new(
+                    device.clone(),
+                    device_queue,
+                    info,
+                    *physical_device
+                        .queue_families()
+                        .get(info.family_index as usize)
+                        .unwrap(),
+                )?));
+                physical_device
+                    .queues()
+                    .iter()
+                    .position(|queue| queue.queue_type == QueueType::Compute)
+                    .map(|dedicated| (dedicated, queues.len() - 1))
+            }
+        };
+
         info!("Created device queues:");
         for queue in &queues {
             let lock = queue.lock().unwrap();
@@ -112,17 +165,61 @@ impl<A: Allocator> ExecutionManager<A> {
         Ok(ExecutionManager {
             device,
             queues: Arc::new(queues),
+            compute_queues,
+            compute_policy: Default::default(),
             pool,
         })
     }
 
+    /// Set which queue new command buffers on the [`domain::Compute`](crate::domain::Compute) domain are allocated from.
+    /// Command buffers that were already allocated are still submitted to the queue they were allocated from.
+    /// # Example
+    /// ```
+    /// # use phobos::prelude::*;
+    /// fn prefer_graphics_family(exec: &ExecutionManager) {
+    ///     exec.set_compute_queue_policy(ComputeQueuePolicy::GraphicsFamily);
+    /// }
+    /// ```
+    pub fn set_compute_queue_policy(&self, policy: ComputeQueuePolicy) {
+        *self.compute_policy.write().unwrap() = policy;
+    }
+
+    /// Get the current compute queue policy.
+    pub fn compute_queue_policy(&self) -> ComputeQueuePolicy {
+        *self.compute_policy.read().unwrap()
+    }
+
+    /// Whether the queue at this index may be picked for new command buffers under the current compute queue policy.
+    fn is_queue_selected(&self, index: usize) -> bool {
+        let Some((dedicated, graphics_family)) = self.compute_queues else { return true; };
+        let policy = self.compute_queue_policy();
+        if index == dedicated {
+            policy == ComputeQueuePolicy::Dedicated
+        } else if index == graphics_family {
+            policy == ComputeQueuePolicy::GraphicsFamily
+        } else {
+            true
+        }
+    }
+
     /// Tries to obtain a command buffer over a domain, or returns an Err state if the lock is currently being held.
     /// If this command buffer needs access to pipelines or descriptor sets, pass in the relevant caches.
+    /// The command buffer is recorded with [`vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT`].
     pub fn try_on_domain<'q, D: ExecutionDomain>(&'q self) -> Result<D::CmdBuf<'q, A>> {
+        self.try_on_domain_with_usage::<D>(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT)
+    }
+
+    /// Tries to obtain a command buffer over a domain with the given usage flags, or returns an Err state if the lock is
+    /// currently being held. See also [`ExecutionManager::on_domain_with_usage()`].
+    pub fn try_on_domain_with_usage<'q, D: ExecutionDomain>(
+        &'q self,
+        usage: vk::CommandBufferUsageFlags,
+    ) -> Result<D::CmdBuf<'q, A>> {
         let queue = self.try_get_queue::<D>().map_err(|_| Error::QueueLocked)?;
         Queue::allocate_command_buffer::<'q, A, D::CmdBuf<'q, A>>(
             self.device.clone(),
             queue,
+            usage,
             self.pool.pipelines.clone(),
             self.pool.descriptors.clone(),
         )
@@ -130,11 +227,34 @@ impl<A: Allocator> ExecutionManager<A> {
 
     /// Obtain a command buffer capable of operating on the specified domain.
     /// If this command buffer needs access to pipelines or descriptor sets, pass in the relevant caches.
+    /// The command buffer is recorded with [`vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT`].
     pub fn on_domain<'q, D: ExecutionDomain>(&'q self) -> Result<D::CmdBuf<'q, A>> {
+        self.on_domain_with_usage::<D>(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT)
+    }
+
+    /// Obtain a command buffer capable of operating on the specified domain, recorded with the given usage flags.
+    /// Command buffers recorded without [`vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT`] can be submitted multiple times
+    /// using [`ExecutionManager::submit_reusable()`]. Add [`vk::CommandBufferUsageFlags::SIMULTANEOUS_USE`] to allow submitting
+    /// the command buffer again while a previous submission is still pending.
+    /// # Example
+    /// ```
+    /// # use phobos::prelude::*;
+    /// # use anyhow::Result;
+    /// fn record_reusable(exec: &ExecutionManager) -> Result<CommandBuffer<domain::Compute>> {
+    ///     exec.on_domain_with_usage::<domain::Compute>(vk::CommandBufferUsageFlags::empty())?
+    ///         .dispatch(64, 1, 1)?
+    ///         .finish()
+    /// }
+    /// ```
+    pub fn on_domain_with_usage<'q, D: ExecutionDomain>(
+        &'q self,
+        usage: vk::CommandBufferUsageFlags,
+    ) -> Result<D::CmdBuf<'q, A>> {
         let queue = self.get_queue::<D>().ok_or_else(|| Error::NoCapableQueue)?;
         Queue::allocate_command_buffer::<'q, A, D::CmdBuf<'q, A>>(
             self.device.clone(),
             queue,
+            usage,
             self.pool.pipelines.clone(),
             self.pool.descriptors.clone(),
         )
@@ -158,13 +278,18 @@ impl<A: Allocator> ExecutionManager<A> {
         SubmitBatch::new(self.device.clone(), self.clone(), &self.pool)
     }
 
-    /// Submit multiple SubmitInfo2 structures.
+    /// Submit multiple SubmitInfo2 structures to the queue of the given family, or to any queue of the domain if no family is given.
     pub(crate) fn submit_batch<D: ExecutionDomain>(
         &self,
+        family: Option<u32>,
         submits: &[vk::SubmitInfo2],
         fence: &Fence,
     ) -> Result<()> {
-        let queue = self.get_queue::<D>().ok_or_else(|| Error::NoCapableQueue)?;
+        let queue = match family {
+            Some(family) => self.get_queue_for_family::<D>(family),
+            None => self.get_queue::<D>(),
+        }
+        .ok_or_else(|| Error::NoCapableQueue)?;
         queue.submit2(submits, Some(fence))?;
         Ok(())
     }
@@ -180,51 +305,107 @@ impl<A: Allocator> ExecutionManager<A> {
     /// Try to get a reference to a queue matching the domain, or return an error state if this would need to block
     /// to lock the queue.
     pub fn try_get_queue<D: ExecutionDomain>(&self) -> TryLockResult<MutexGuard<Queue>> {
-        let q = self.queues.iter().find(|&q| {
-            let q = q.try_lock();
-            match q {
-                Ok(queue) => D::queue_is_compatible(&queue),
-                Err(_) => false,
-            }
-        });
+        let q = self
+            .queues
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| self.is_queue_selected(*index))
+            .map(|(_, q)| q)
+            .find(|&q| {
+                let q = q.try_lock();
+                match q {
+                    Ok(queue) => D::queue_is_compatible(&queue),
+                    Err(_) => false,
+                }
+            });
         match q {
             None => Err(TryLockError::WouldBlock),
             Some(q) => Ok(q.lock()?),
         }
     }
 
+    /// Get the device this execution manager submits to.
+    pub(crate) fn device(&self) -> &Device {
+        &self.device
+    }
+
     /// Obtain a reference to a queue matching the domain. Blocks if this queue is currently locked.
     pub fn get_queue<D: ExecutionDomain>(&self) -> Option<MutexGuard<Queue>> {
         self.queues
             .iter()
+            .enumerate()
+            .filter(|(index, _)| self.is_queue_selected(*index))
+            .map(|(_, q)| q)
             .find(|&q| {
                 let q = q.lock().unwrap();
                 D::queue_is_compatible(&q)
             })
             .map(|q| q.lock().unwrap())
     }
+
+    /// Obtain a reference to the queue matching the domain on a specific queue family, regardless of the compute queue policy.
+    /// This is the queue a command buffer allocated from this family must be submitted to. Blocks if this queue is currently locked.
+    pub(crate) fn get_queue_for_family<D: ExecutionDomain>(&self, family: u32) -> Option<MutexGuard<Queue>> {
+        self.queues
+            .iter()
+            .find(|&q| {
+                let q = q.lock().unwrap();
+                q.info().family_index == family && D::queue_is_compatible(&q)
+            })
+            .map(|q| q.lock().unwrap())
+    }
 }
 
 impl<A: Allocator + 'static> ExecutionManager<A> {
-    /// Submit a command buffer to its queue.
-    pub fn submit<D: ExecutionDomain + 'static>(
-        &self,
-        mut cmd: CommandBuffer<D>,
-    ) -> Result<Pooled<Fence>> {
-        let mut fence = Fence::new_in_pool(&self.pool.fences, &())?;
+    /// Submit a batch of sparse binding operations to the sparse binding queue. The returned fence is signaled once all binds
+    /// have been executed. See the [`sparse`](crate::sparse) module.
+    /// # Errors
+    /// * Fails with [`Error::NoCapableQueue`] if there is no queue that supports sparse binding. Request one with
+    ///   [`QueueType::SparseBinding`].
+    /// * Fails if the queue submission fails.
+    pub fn bind_sparse(&self, batch: SparseBindBatch) -> Result<Pooled<Fence>> {
+        let queue = self
+            .queues
+            .iter()
+            .find(|q| q.lock().unwrap().info().queue_type == QueueType::SparseBinding)
+            .or_else(|| {
+                self.queues
+                    .iter()
+                    .find(|q| q.lock().unwrap().info().flags.contains(vk::QueueFlags::SPARSE_BINDING))
+            })
+            .ok_or_else(|| Error::NoCapableQueue)?
+            .lock()
+            .unwrap();
+        let fence = Fence::new_in_pool(&self.pool.fences, &())?;
+        batch.with_bind_info(|info| queue.bind_sparse(std::slice::from_ref(info), Some(&fence)))?;
+        Ok(fence)
+    }
 
-        let handle = unsafe { cmd.handle() };
+    /// Submit a single command buffer to the queue of its domain. `p_next` is chained to the submit info.
+    fn submit_impl<D: ExecutionDomain>(&self, cmd: &CommandBuffer<D>, p_next: *const std::ffi::c_void) -> Result<Pooled<Fence>> {
+        let fence = Fence::new_in_pool(&self.pool.fences, &())?;
+        self.submit_with_fence_impl(cmd, p_next, &fence)?;
+        Ok(fence)
+    }
+
+    /// Submit a single command buffer to the queue of its domain, signaling `fence` when it completes.
+    fn submit_with_fence_impl<D: ExecutionDomain>(
+        &self,
+        cmd: &CommandBuffer<D>,
+        p_next: *const std::ffi::c_void,
+        fence: &Fence,
+    ) -> Result<()> {
 
         let command_buffer_info = vk::CommandBufferSubmitInfo {
             s_type: vk::StructureType::COMMAND_BUFFER_SUBMIT_INFO,
             p_next: std::ptr::null(),
-            command_buffer: handle,
+            command_buffer: unsafe { cmd.handle() },
             device_mask: 0,
         };
 
         let info = vk::SubmitInfo2 {
             s_type: vk::StructureType::SUBMIT_INFO_2,
-            p_next: std::ptr::null(),
+            p_next,
             flags: Default::default(),
             wait_semaphore_info_count: 0,
             p_wait_semaphore_infos: std::ptr::null(),
@@ -234,14 +415,137 @@ impl<A: Allocator + 'static> ExecutionManager<A> {
             p_signal_semaphore_infos: std::ptr::null(),
         };
 
-        let queue = self.get_queue::<D>().ok_or_else(|| Error::NoCapableQueue)?;
-        queue.submit2(std::slice::from_ref(&info), Some(&fence))?;
+        let queue = self
+            .get_queue_for_family::<D>(cmd.queue_family())
+            .ok_or_else(|| Error::NoCapableQueue)?;
+        queue.submit2(std::slice::from_ref(&info), Some(fence))
+    }
+
+    /// Submit a command buffer to its queue. The command buffer is deleted once it has finished executing, or immediately if
+    /// submitting it fails.
+    /// # Errors
+    /// * Fails with [`Error::CommandBufferResubmitted`] if this is a one-time submit command buffer that was already submitted.
+    /// * Fails with [`Error::CommandBufferPending`] if this command buffer is still pending and does not allow simultaneous use.
+    /// * Fails if the queue submission fails, for example with [`vk::Result::ERROR_DEVICE_LOST`].
+    pub fn submit<D: ExecutionDomain + 'static>(
+        &self,
+        mut cmd: CommandBuffer<D>,
+    ) -> Result<Pooled<Fence>> {
+        let pending = cmd.begin_submit()?;
+        let mut fence = match self.submit_impl::<D>(&cmd, std::ptr::null()) {
+            Ok(fence) => fence,
+            Err(e) => {
+                pending.fetch_sub(1, Ordering::AcqRel);
+                // SAFETY: The submission failed, so the command buffer is not executing.
+                if let Err(delete_error) = unsafe { cmd.delete(self.clone()) } {
+                    warn!("Failed to delete command buffer after failed submission: {delete_error}");
+                }
+                return Err(e);
+            }
+        };
         let exec = self.clone();
         fence.replace(move |fence| {
             fence.with_cleanup(move || unsafe {
+                pending.fetch_sub(1, Ordering::AcqRel);
                 cmd.delete(exec).unwrap();
             })
         });
         Ok(fence)
     }
+
+    /// Submit a command buffer to its queue, and return a future that resolves to the result of `f`. The function is called once
+    /// the command buffer has finished executing, so it can read the results of the commands, for example from a readback buffer.
+    /// The command buffer is deleted once it has finished executing, or immediately if submitting it fails.
+    /// # Errors
+    /// * Fails with [`Error::CommandBufferResubmitted`] if this is a one-time submit command buffer that was already submitted.
+    /// * Fails with [`Error::CommandBufferPending`] if this command buffer is still pending and does not allow simultaneous use.
+    /// * Fails if creating the fence or the queue submission fails.
+    pub fn submit_with_value<D: ExecutionDomain + 'static, T>(
+        &self,
+        mut cmd: CommandBuffer<D>,
+        f: impl FnOnce() -> T + 'static,
+    ) -> Result<GpuFuture<T>> {
+        // The fence holds a value of a different type than the fences in the pool, so it is not pooled.
+        let fence = Fence::new(self.device.clone(), false)?;
+        let pending = cmd.begin_submit()?;
+        if let Err(e) = self.submit_with_fence_impl::<D>(&cmd, std::ptr::null(), &fence) {
+            pending.fetch_sub(1, Ordering::AcqRel);
+            // SAFETY: The submission failed, so the command buffer is not executing.
+            if let Err(delete_error) = unsafe { cmd.delete(self.clone()) } {
+                warn!("Failed to delete command buffer after failed submission: {delete_error}");
+            }
+            return Err(e);
+        }
+        let exec = self.clone();
+        Ok(fence
+            .with_cleanup(move || unsafe {
+                pending.fetch_sub(1, Ordering::AcqRel);
+                cmd.delete(exec).unwrap();
+            })
+            .attach_value_with(f))
+    }
+
+    /// Submit a command buffer to its queue without taking ownership of it, so it can be submitted again later.
+    /// The command buffer must not be recorded with [`vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT`] to be submitted more than once,
+    /// see [`ExecutionManager::on_domain_with_usage()`]. It is not deleted automatically, call [`CmdBuffer::delete()`] once it is
+    /// no longer pending.
+    /// # Errors
+    /// * Fails with [`Error::CommandBufferResubmitted`] if this is a one-time submit command buffer that was already submitted.
+    /// * Fails with [`Error::CommandBufferPending`] if this command buffer is still pending and does not allow simultaneous use.
+    /// # Example
+    /// ```
+    /// # use phobos::prelude::*;
+    /// # use anyhow::Result;
+    /// fn run_twice(exec: &ExecutionManager, cmd: &mut CommandBuffer<domain::Compute>) -> Result<()> {
+    ///     exec.submit_reusable(cmd)?.wait()?;
+    ///     exec.submit_reusable(cmd)?.wait()?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn submit_reusable<D: ExecutionDomain + 'static>(
+        &self,
+        cmd: &mut CommandBuffer<D>,
+    ) -> Result<Pooled<Fence>> {
+        self.submit_reusable_impl(cmd, std::ptr::null())
+    }
+
+    /// Submit a command buffer using performance queries for a single counter pass. When the counters of a
+    /// [`PerformanceQuery`](crate::query_pool::PerformanceQuery) pool need more than one pass, the command buffer must be submitted
+    /// once for each pass index in `0..PerformanceQuery::num_passes()`. Just like with [`ExecutionManager::submit_reusable()`],
+    /// the command buffer is not deleted automatically.
+    /// # Errors
+    /// * Fails with [`Error::CommandBufferResubmitted`] if this is a one-time submit command buffer that was already submitted.
+    /// * Fails with [`Error::CommandBufferPending`] if this command buffer is still pending and does not allow simultaneous use.
+    pub fn submit_counter_pass<D: ExecutionDomain + 'static>(
+        &self,
+        cmd: &mut CommandBuffer<D>,
+        pass: u32,
+    ) -> Result<Pooled<Fence>> {
+        let pass_info = vk::PerformanceQuerySubmitInfoKHR {
+            counter_pass_index: pass,
+            ..Default::default()
+        };
+        self.submit_reusable_impl(cmd, (&pass_info as *const vk::PerformanceQuerySubmitInfoKHR).cast())
+    }
+
+    fn submit_reusable_impl<D: ExecutionDomain + 'static>(
+        &self,
+        cmd: &mut CommandBuffer<D>,
+        p_next: *const std::ffi::c_void,
+    ) -> Result<Pooled<Fence>> {
+        let pending = cmd.begin_submit()?;
+        let mut fence = match self.submit_impl::<D>(cmd, p_next) {
+            Ok(fence) => fence,
+            Err(e) => {
+                pending.fetch_sub(1, Ordering::AcqRel);
+                return Err(e);
+            }
+        };
+        fence.replace(move |fence| {
+            fence.with_cleanup(move || {
+                pending.fetch_sub(1, Ordering::AcqRel);
+            })
+        });
+        Ok(fence)
+    }
 }
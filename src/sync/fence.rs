@@ -55,7 +55,7 @@ pub trait FenceValue<T> {
 ///
 /// use phobos::prelude::*;
 ///
-/// async fn upload_buffer<T: Copy>(device: Device, mut allocator: DefaultAllocator, exec: ExecutionManager, src: &[T]) -> Result<Buffer> {
+/// async fn upload_buffer<T: bytemuck::Pod>(device: Device, mut allocator: DefaultAllocator, exec: ExecutionManager, src: &[T]) -> Result<Buffer> {
 ///     // Create our result buffer
 ///     let size = (src.len() * size_of::<T>()) as u64;
 ///     let buffer = Buffer::new_device_local(device.clone(), &mut allocator, size, vk::BufferUsageFlags::TRANSFER_DST)?;
@@ -112,6 +112,8 @@ pub struct Fence<T = ()> {
     #[derivative(Debug = "ignore")]
     first_cleanup_fn: Option<Box<CleanupFnLink<'static>>>,
     value: Option<T>,
+    #[derivative(Debug = "ignore")]
+    value_fn: Option<Box<dyn FnOnce() -> T>>,
     handle: vk::Fence,
     wait_thread_spawned: bool,
 }
@@ -129,7 +131,7 @@ impl<T> FenceValue<T> for Fence<T> {
     /// Get the value of this fence. Note that using this without an attached value will panic.
     /// Using this before the fence was awaited may result in undefined behaviour.
     fn value(&mut self) -> Option<T> {
-        self.value.take()
+        self.value.take().or_else(|| self.value_fn.take().map(|f| f()))
     }
 }
 
@@ -144,6 +146,22 @@ impl Fence<()> {
             first_cleanup_fn: self.first_cleanup_fn.take(),
             device: self.device.clone(),
             value: Some(value),
+            value_fn: None,
+            wait_thread_spawned: false,
+        }
+    }
+
+    /// Attach a function that computes the value returned from the future when it completes. The function is called after
+    /// the fence is signaled, so it can read results written by the GPU, such as the contents of a readback buffer.
+    pub fn attach_value_with<T>(mut self, f: impl FnOnce() -> T + 'static) -> Fence<T> {
+        let mut handle = vk::Fence::null();
+        std::mem::swap(&mut self.handle, &mut handle);
+        Fence::<T> {
+            handle,
+            first_cleanup_fn: self.first_cleanup_fn.take(),
+            device: self.device.clone(),
+            value: None,
+            value_fn: Some(Box::new(f)),
             wait_thread_spawned: false,
         }
     }
@@ -172,6 +190,7 @@ impl<T> Fence<T> {
             device,
             first_cleanup_fn: None,
             value: None,
+            value_fn: None,
             wait_thread_spawned: false,
         })
     }
@@ -195,13 +214,14 @@ impl<T> Fence<T> {
         }
     }
 
-    fn poll_status(&self) -> VkResult<bool> {
-        unsafe { self.device.get_fence_status(self.handle) }
+    pub(crate) fn poll_status(&self) -> VkResult<bool> {
+        self.device
+            .check_device_lost(unsafe { self.device.get_fence_status(self.handle) })
     }
 
     pub(crate) unsafe fn wait_without_cleanup(&self) -> VkResult<()> {
         self.device
-            .wait_for_fences(slice::from_ref(&self.handle), true, u64::MAX)
+            .check_device_lost(self.device.wait_for_fences(slice::from_ref(&self.handle), true, u64::MAX))
     }
 
     /// Waits for the fence by polling repeatedly and yielding execution to the OS. This is useful if you don't care about quickly knowing the fence is
@@ -321,8 +341,12 @@ impl<T> Poolable for Fence<T> {
     type Key = ();
 
     fn on_release(&mut self) {
-        self.reset().unwrap();
+        // Resetting only fails if the device was lost, in which case the fence will never be used again.
+        if let Err(e) = self.reset() {
+            warn!("Failed to reset fence returned to the pool: {e}");
+        }
         self.value = None;
+        self.value_fn = None;
         self.first_cleanup_fn = None;
     }
 }
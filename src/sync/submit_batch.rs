@@ -1,6 +1,7 @@
 //! Provides the [`SubmitBatch`] struct to batch submits together and synchronize between them easily.
 
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
 
 use anyhow::{ensure, Result};
 use ash::vk;
@@ -123,22 +124,24 @@ impl<D: ExecutionDomain + 'static, A: Allocator> SubmitBatch<D, A> {
             .map(|handle| self.get_submit_semaphore(*handle).unwrap())
             .collect::<Vec<_>>();
         let mut wait_stages = wait_stages.to_vec();
-        let frame_wait_semaphore = ifc.wait_semaphore;
-        // Add this semaphore as a wait semaphore for the first submit, or to the frame commands if there is no other submit
-        match self.submits.first_mut() {
-            None => {
-                wait_semaphores.push(frame_wait_semaphore);
-                wait_stages.push(PipelineStage::COLOR_ATTACHMENT_OUTPUT);
-            }
-            Some(submit) => {
-                submit.wait_stages.push(PipelineStage::TOP_OF_PIPE);
-                submit.wait_semaphores.push(frame_wait_semaphore);
+        // Add this semaphore as a wait semaphore for the first submit, or to the frame commands if there is no other submit.
+        // Headless frames have nothing to wait on.
+        if let Some(frame_wait_semaphore) = ifc.wait_semaphore {
+            match self.submits.first_mut() {
+                None => {
+                    wait_semaphores.push(frame_wait_semaphore);
+                    wait_stages.push(PipelineStage::COLOR_ATTACHMENT_OUTPUT);
+                }
+                Some(submit) => {
+                    submit.wait_stages.push(PipelineStage::TOP_OF_PIPE);
+                    submit.wait_semaphores.push(frame_wait_semaphore);
+                }
             }
         }
 
         self.submits.push(SubmitInfo {
             cmd,
-            signal_semaphore: Some(ifc.signal_semaphore),
+            signal_semaphore: ifc.signal_semaphore,
             wait_semaphores,
             wait_stages,
         });
@@ -183,7 +186,28 @@ impl<D: ExecutionDomain + 'static, A: Allocator> SubmitBatch<D, A> {
 impl<D: ExecutionDomain + 'static, A: Allocator + 'static> SubmitBatch<D, A> {
     /// Finish this batch by submitting it to the execution manager.
     /// This returns a [`Fence`] that can be awaited to wait for completion.
+    /// # Errors
+    /// * Fails if any command buffer in the batch may not be submitted again, see [`ExecutionManager::submit()`](crate::ExecutionManager::submit).
     pub fn finish(mut self) -> Result<Pooled<Fence>> {
+        // All command buffers in a batch are submitted to the queue they were allocated from, so this must be the same queue.
+        let family = self.submits.first().map(|submit| submit.cmd.queue_family());
+        ensure!(
+            self.submits.iter().all(|submit| Some(submit.cmd.queue_family()) == family),
+            "All command buffers in a submit batch must be allocated from the same queue."
+        );
+
+        // Every command buffer stays pending until the batch finishes executing, or until submitting it fails.
+        let mut pending = Vec::with_capacity(self.submits.len());
+        for submit in &mut self.submits {
+            match submit.cmd.begin_submit() {
+                Ok(counter) => pending.push(counter),
+                Err(err) => {
+                    end_submit(&pending);
+                    return Err(err);
+                }
+            }
+        }
+
         struct PerSubmit {
             wait_semaphores: Vec<vk::SemaphoreSubmitInfo>,
             cmd_buffer: Vec<vk::CommandBufferSubmitInfo>,
@@ -245,10 +269,16 @@ impl<D: ExecutionDomain + 'static, A: Allocator + 'static> SubmitBatch<D, A> {
             })
             .collect::<Vec<_>>();
 
-        self.exec
-            .submit_batch::<D>(submits.as_slice(), &self.signal_fence)?;
+        if let Err(err) = self
+            .exec
+            .submit_batch::<D>(family, submits.as_slice(), &self.signal_fence)
+        {
+            end_submit(&pending);
+            return Err(err);
+        }
         self.signal_fence.replace(move |fence| {
             fence.with_cleanup(move || {
+                end_submit(&pending);
                 // Take ownership of every resource inside the submit batch, to delete it afterwards
                 let _pool = self.local_pool;
                 for mut submit in self.submits {
@@ -262,6 +292,13 @@ impl<D: ExecutionDomain + 'static, A: Allocator + 'static> SubmitBatch<D, A> {
     }
 }
 
+/// Mark command buffers that were marked as pending by `CommandBuffer::begin_submit()` as no longer pending.
+fn end_submit(pending: &[Arc<AtomicU32>]) {
+    for counter in pending {
+        counter.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
 impl SubmitHandle {
     /// Add another submit to the batch that waits on this submit at the specified wait stage mask.
     pub fn then<D: ExecutionDomain + 'static, A: Allocator>(
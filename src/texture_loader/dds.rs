@@ -0,0 +1,196 @@
+//! Parser for the DirectDraw Surface (DDS) format, including the DX10 header extension.
+
+use std::borrow::Cow;
+
+use anyhow::{anyhow, bail, ensure, Result};
+use ash::vk;
+
+use super::{level_size, max_mip_levels, read_bytes, read_u32};
+use super::{Supercompression, TextureEncoding, TextureFile, TextureLevel};
+
+/// The magic number every DDS file starts with.
+pub(super) const MAGIC: &[u8; 4] = b"DDS ";
+
+/// Size of the magic number and header, in bytes.
+const HEADER_SIZE: usize = 128;
+/// Size of the DX10 header extension, in bytes.
+const DX10_HEADER_SIZE: usize = 20;
+
+const DDPF_FOURCC: u32 = 0x4;
+const DDPF_RGB: u32 = 0x40;
+const DDPF_LUMINANCE: u32 = 0x20000;
+const DDSCAPS2_CUBEMAP: u32 = 0x200;
+const DDSCAPS2_CUBEMAP_ALL_FACES: u32 = 0xFC00;
+const DDSCAPS2_VOLUME: u32 = 0x200000;
+const D3D10_RESOURCE_DIMENSION_TEXTURE3D: u32 = 4;
+const D3D10_RESOURCE_MISC_TEXTURECUBE: u32 = 0x4;
+
+const fn four_cc(code: &[u8; 4]) -> u32 {
+    u32::from_le_bytes(*code)
+}
+
+pub(super) fn parse(bytes: &[u8]) -> Result<TextureFile<'_>> {
+    ensure!(bytes.starts_with(MAGIC), "Not a DDS file");
+    ensure!(read_u32(bytes, 4)? == 124, "DDS header has an invalid size");
+    let height = read_u32(bytes, 12)?.max(1);
+    let width = read_u32(bytes, 16)?.max(1);
+    let depth = read_u32(bytes, 24)?;
+    let mip_levels = read_u32(bytes, 28)?.max(1);
+    let pixel_flags = read_u32(bytes, 80)?;
+    let code = read_u32(bytes, 84)?;
+    let caps2 = read_u32(bytes, 112)?;
+
+    let (format, layers, cube, volume, data_offset) = if pixel_flags & DDPF_FOURCC != 0 && code == four_cc(b"DX10") {
+        let dxgi = read_u32(bytes, HEADER_SIZE)?;
+        let dimension = read_u32(bytes, HEADER_SIZE + 4)?;
+        let misc = read_u32(bytes, HEADER_SIZE + 8)?;
+        let array_size = read_u32(bytes, HEADER_SIZE + 12)?;
+        let format = dxgi_format(dxgi).ok_or_else(|| anyhow!("Unsupported DXGI format {dxgi} in DDS file"))?;
+        (
+            format,
+            array_size.max(1),
+            misc & D3D10_RESOURCE_MISC_TEXTURECUBE != 0,
+            dimension == D3D10_RESOURCE_DIMENSION_TEXTURE3D,
+            HEADER_SIZE + DX10_HEADER_SIZE,
+        )
+    } else {
+        let format = legacy_format(bytes, pixel_flags, code)?;
+        let cube = caps2 & DDSCAPS2_CUBEMAP != 0;
+        ensure!(
+            !cube || caps2 & DDSCAPS2_CUBEMAP_ALL_FACES == DDSCAPS2_CUBEMAP_ALL_FACES,
+            "DDS cubemaps must store all six faces"
+        );
+        (format, 1, cube, caps2 & DDSCAPS2_VOLUME != 0, HEADER_SIZE)
+    };
+
+    let size = vk::Extent3D {
+        width,
+        height,
+        depth: if volume { depth.max(1) } else { 1 },
+    };
+    let faces = if cube { 6 } else { 1 };
+    ensure!(
+        mip_levels <= max_mip_levels(size),
+        "DDS file has {mip_levels} mip levels, but a texture of size {size:?} has at most {}",
+        max_mip_levels(size)
+    );
+    let images = layers
+        .checked_mul(faces)
+        .ok_or_else(|| anyhow!("DDS file has too many array layers"))?;
+    let image_sizes = (0..mip_levels)
+        .map(|mip_level| {
+            level_size(format, size, mip_level, 1)?.ok_or_else(|| anyhow!("Unsupported DDS format {format:?}"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    // Check that the file holds all images before allocating memory for them.
+    let total_size = image_sizes
+        .iter()
+        .try_fold(0u64, |total, &size| total.checked_add(size))
+        .and_then(|chain| chain.checked_mul(images as u64))
+        .ok_or_else(|| anyhow!("DDS file is too large"))?;
+    read_bytes(bytes, data_offset as u64, total_size)?;
+
+    // DDS stores every array layer (and cube face) with all of its mip levels after each other, while uploads expect all
+    // layers of a mip level after each other.
+    let mut levels = image_sizes
+        .iter()
+        .map(|&size| Vec::with_capacity((size * images as u64) as usize))
+        .collect::<Vec<_>>();
+    let mut offset = data_offset as u64;
+    for _ in 0..images {
+        for (level, &len) in levels.iter_mut().zip(&image_sizes) {
+            level.extend_from_slice(read_bytes(bytes, offset, len)?);
+            offset += len;
+        }
+    }
+
+    Ok(TextureFile {
+        encoding: TextureEncoding::Format(format),
+        size,
+        layers,
+        faces,
+        supercompression: Supercompression::None,
+        global_data: &[],
+        levels: levels
+            .into_iter()
+            .map(|data| TextureLevel {
+                uncompressed_size: data.len() as u64,
+                data: Cow::Owned(data),
+            })
+            .collect(),
+    })
+}
+
+/// Map a `DXGI_FORMAT` to a Vulkan format.
+fn dxgi_format(format: u32) -> Option<vk::Format> {
+    use vk::Format as F;
+
+    Some(match format {
+        2 => F::R32G32B32A32_SFLOAT,
+        10 => F::R16G16B16A16_SFLOAT,
+        11 => F::R16G16B16A16_UNORM,
+        24 => F::A2B10G10R10_UNORM_PACK32,
+        26 => F::B10G11R11_UFLOAT_PACK32,
+        28 => F::R8G8B8A8_UNORM,
+        29 => F::R8G8B8A8_SRGB,
+        34 => F::R16G16_SFLOAT,
+        41 => F::R32_SFLOAT,
+        49 => F::R8G8_UNORM,
+        54 => F::R16_SFLOAT,
+        56 => F::R16_UNORM,
+        61 => F::R8_UNORM,
+        67 => F::E5B9G9R9_UFLOAT_PACK32,
+        71 => F::BC1_RGBA_UNORM_BLOCK,
+        72 => F::BC1_RGBA_SRGB_BLOCK,
+        74 => F::BC2_UNORM_BLOCK,
+        75 => F::BC2_SRGB_BLOCK,
+        77 => F::BC3_UNORM_BLOCK,
+        78 => F::BC3_SRGB_BLOCK,
+        80 => F::BC4_UNORM_BLOCK,
+        81 => F::BC4_SNORM_BLOCK,
+        83 => F::BC5_UNORM_BLOCK,
+        84 => F::BC5_SNORM_BLOCK,
+        87 => F::B8G8R8A8_UNORM,
+        91 => F::B8G8R8A8_SRGB,
+        95 => F::BC6H_UFLOAT_BLOCK,
+        96 => F::BC6H_SFLOAT_BLOCK,
+        98 => F::BC7_UNORM_BLOCK,
+        99 => F::BC7_SRGB_BLOCK,
+        _ => return None,
+    })
+}
+
+/// Map the pixel format of a DDS file without a DX10 header to a Vulkan format.
+fn legacy_format(bytes: &[u8], pixel_flags: u32, code: u32) -> Result<vk::Format> {
+    use vk::Format as F;
+
+    if pixel_flags & DDPF_FOURCC != 0 {
+        return Ok(match code {
+            c if c == four_cc(b"DXT1") => F::BC1_RGBA_UNORM_BLOCK,
+            c if c == four_cc(b"DXT2") || c == four_cc(b"DXT3") => F::BC2_UNORM_BLOCK,
+            c if c == four_cc(b"DXT4") || c == four_cc(b"DXT5") => F::BC3_UNORM_BLOCK,
+            c if c == four_cc(b"ATI1") || c == four_cc(b"BC4U") => F::BC4_UNORM_BLOCK,
+            c if c == four_cc(b"BC4S") => F::BC4_SNORM_BLOCK,
+            c if c == four_cc(b"ATI2") || c == four_cc(b"BC5U") => F::BC5_UNORM_BLOCK,
+            c if c == four_cc(b"BC5S") => F::BC5_SNORM_BLOCK,
+            // D3DFMT_A16B16G16R16F and D3DFMT_A32B32G32R32F
+            113 => F::R16G16B16A16_SFLOAT,
+            116 => F::R32G32B32A32_SFLOAT,
+            _ => bail!("Unsupported DDS FourCC code {code:#x}"),
+        });
+    }
+
+    let bit_count = read_u32(bytes, 88)?;
+    let masks = [
+        read_u32(bytes, 92)?,
+        read_u32(bytes, 96)?,
+        read_u32(bytes, 100)?,
+        read_u32(bytes, 104)?,
+    ];
+    match (pixel_flags & (DDPF_RGB | DDPF_LUMINANCE), bit_count, masks) {
+        (DDPF_RGB, 32, [0xFF, 0xFF00, 0xFF0000, 0xFF000000]) => Ok(F::R8G8B8A8_UNORM),
+        (DDPF_RGB, 32, [0xFF0000, 0xFF00, 0xFF, 0xFF000000]) => Ok(F::B8G8R8A8_UNORM),
+        (DDPF_LUMINANCE, 8, _) => Ok(F::R8_UNORM),
+        _ => bail!("Unsupported DDS pixel format with {bit_count} bits and masks {masks:x?}"),
+    }
+}
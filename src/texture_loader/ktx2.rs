@@ -0,0 +1,152 @@
+//! Parser for the KTX2 container format.
+
+use std::borrow::Cow;
+
+use anyhow::{anyhow, bail, ensure, Result};
+use ash::vk;
+
+use super::{level_size, max_mip_levels, read_bytes, read_u32, read_u64};
+use super::{BasisEncoding, BasisInfo, Supercompression, TextureEncoding, TextureFile, TextureLevel};
+use crate::util::compressed::CompressedBlock;
+use crate::util::upload::mip_level_size;
+
+/// The identifier every KTX2 file starts with.
+pub(super) const IDENTIFIER: [u8; 12] = [0xAB, b'K', b'T', b'X', b' ', b'2', b'0', 0xBB, b'\r', b'\n', 0x1A, b'\n'];
+
+/// Size of the header and index before the level index.
+const HEADER_SIZE: usize = 80;
+/// Size of a single entry in the level index.
+const LEVEL_INDEX_ENTRY_SIZE: usize = 24;
+
+/// UASTC encodes 4x4 blocks of texels in 16 bytes.
+const UASTC_BLOCK: CompressedBlock = CompressedBlock {
+    width: 4,
+    height: 4,
+    bytes: 16,
+};
+
+/// Color model of UASTC data in the data format descriptor.
+const KHR_DF_MODEL_UASTC: u8 = 166;
+/// sRGB transfer function in the data format descriptor.
+const KHR_DF_TRANSFER_SRGB: u8 = 2;
+/// UASTC channel ids that include an alpha channel.
+const KHR_DF_CHANNEL_UASTC_RGBA: u8 = 3;
+const KHR_DF_CHANNEL_UASTC_RRRG: u8 = 5;
+
+pub(super) fn parse(bytes: &[u8]) -> Result<TextureFile<'_>> {
+    ensure!(bytes.starts_with(&IDENTIFIER), "Not a KTX2 file");
+    let format = vk::Format::from_raw(read_u32(bytes, 12)? as i32);
+    let width = read_u32(bytes, 20)?;
+    // Height and depth are zero for 1D and 2D textures, and the layer count is zero for textures that are not arrays.
+    let height = read_u32(bytes, 24)?.max(1);
+    let depth = read_u32(bytes, 28)?.max(1);
+    let layers = read_u32(bytes, 32)?.max(1);
+    let faces = read_u32(bytes, 36)?;
+    // A level count of zero asks the loader to generate mipmaps, only the base level is stored.
+    let level_count = read_u32(bytes, 40)?.max(1);
+    let supercompression = match read_u32(bytes, 44)? {
+        0 => Supercompression::None,
+        1 => Supercompression::BasisLz,
+        2 => Supercompression::Zstandard,
+        3 => Supercompression::Zlib,
+        scheme => bail!("Unknown KTX2 supercompression scheme {scheme}"),
+    };
+    ensure!(faces == 1 || faces == 6, "KTX2 file has {faces} faces, expected 1 or 6");
+    let size = vk::Extent3D {
+        width,
+        height,
+        depth,
+    };
+    ensure!(
+        level_count <= max_mip_levels(size),
+        "KTX2 file has {level_count} mip levels, but a texture of size {size:?} has at most {}",
+        max_mip_levels(size)
+    );
+    let images = layers
+        .checked_mul(faces)
+        .ok_or_else(|| anyhow!("KTX2 file has too many array layers"))?;
+
+    let dfd_offset = read_u32(bytes, 48)? as usize;
+    let sgd_offset = read_u64(bytes, 64)?;
+    let sgd_length = read_u64(bytes, 72)?;
+    let global_data = read_bytes(bytes, sgd_offset, sgd_length)?;
+
+    let encoding = if format == vk::Format::UNDEFINED {
+        TextureEncoding::Basis(basis_info(bytes, dfd_offset, supercompression)?)
+    } else {
+        ensure!(
+            supercompression != Supercompression::BasisLz,
+            "BasisLZ supercompression is only allowed for Basis Universal textures"
+        );
+        TextureEncoding::Format(format)
+    };
+
+    let levels = (0..level_count)
+        .map(|level| {
+            let entry = HEADER_SIZE + level as usize * LEVEL_INDEX_ENTRY_SIZE;
+            let offset = read_u64(bytes, entry)?;
+            let length = read_u64(bytes, entry + 8)?;
+            let uncompressed_size = read_u64(bytes, entry + 16)?;
+            // The uncompressed size is used to decompress the level, so it must match the size of the level.
+            let expected = match encoding {
+                TextureEncoding::Format(format) => level_size(format, size, level, images)?,
+                TextureEncoding::Basis(BasisInfo {
+                    encoding: BasisEncoding::Uastc,
+                    ..
+                }) => Some(UASTC_BLOCK.region_size(mip_level_size(size, level), images)?),
+                TextureEncoding::Basis(_) => None,
+            };
+            if let Some(expected) = expected {
+                ensure!(
+                    uncompressed_size == expected,
+                    "KTX2 mip level {level} has an uncompressed size of {uncompressed_size} bytes, expected {expected}"
+                );
+            }
+            Ok(TextureLevel {
+                data: Cow::Borrowed(read_bytes(bytes, offset, length)?),
+                uncompressed_size,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(TextureFile {
+        encoding,
+        size,
+        layers,
+        faces,
+        supercompression,
+        global_data,
+        levels,
+    })
+}
+
+/// Read the Basis Universal encoding from the basic data format descriptor block.
+fn basis_info(bytes: &[u8], dfd_offset: usize, supercompression: Supercompression) -> Result<BasisInfo> {
+    // The descriptor block starts after the total size of the descriptor.
+    let block = dfd_offset + 4;
+    let block_size = read_u32(bytes, block + 4)? >> 16;
+    let model = read_u32(bytes, block + 8)?;
+    let color_model = model as u8;
+    let transfer = (model >> 16) as u8;
+    // Every sample takes 16 bytes after the 24 byte block header. The channel id is in the top byte of the first word.
+    let samples = block_size.saturating_sub(24) / 16;
+    ensure!(samples > 0, "KTX2 data format descriptor has no samples");
+    let channel = (read_u32(bytes, block + 24)? >> 24) as u8 & 0xF;
+
+    let (encoding, has_alpha) = if supercompression == Supercompression::BasisLz {
+        // ETC1S stores alpha in a second slice.
+        (BasisEncoding::Etc1s, samples == 2)
+    } else if color_model == KHR_DF_MODEL_UASTC {
+        (
+            BasisEncoding::Uastc,
+            channel == KHR_DF_CHANNEL_UASTC_RGBA || channel == KHR_DF_CHANNEL_UASTC_RRRG,
+        )
+    } else {
+        bail!("KTX2 file without a Vulkan format is not encoded with Basis Universal")
+    };
+    Ok(BasisInfo {
+        encoding,
+        srgb: transfer == KHR_DF_TRANSFER_SRGB,
+        has_alpha,
+    })
+}
@@ -0,0 +1,453 @@
+//! Load KTX2 and DDS textures into sampled images. This requires the `texture-loader` feature.
+//!
+//! A [`TextureFile`] is parsed from the contents of a `.ktx2` or `.dds` file, and describes the format, size, array layers,
+//! cube faces and mip levels stored in it. The [`TextureLoader`] creates an image for it and queues its upload on an
+//! [`UploadManager`]. The returned [`QueuedUpload`] resolves to a [`Texture`], which holds the image together with a view of
+//! all of its mip levels and layers. Cubemaps are created as cube compatible images and get a cube (array) view.
+//!
+//! Files that store a Vulkan format directly are uploaded as-is, and fail to load if the device cannot sample that format.
+//! KTX2 files encoded with Basis Universal (ETC1S or UASTC) are transcoded at load time. phobos does not include a Basis
+//! Universal transcoder itself, instead it is provided through the [`BasisTranscoder`] trait, for example on top of the
+//! `basis-universal` crate. The loader picks the best block-compressed format supported by both the device and the transcoder,
+//! and falls back to RGBA8 if none is available.
+//!
+//! Zstandard and ZLIB supercompressed KTX2 files are supported, and are decompressed by the loader itself. This includes the
+//! common UASTC textures with Zstandard supercompression, whose decompressed data is passed to the transcoder. Supercompressed
+//! files that store a Vulkan format do not need a transcoder. BasisLZ supercompression is part of ETC1S, and is handled by the
+//! transcoder.
+//! # Example
+//! ```
+//! # use std::sync::Arc;
+//! # use anyhow::Result;
+//! # use phobos::prelude::*;
+//! # use phobos::texture_loader::{Texture, TextureLoader};
+//! fn load_skybox(device: Device, allocator: DefaultAllocator, uploads: Arc<UploadManager>) -> Result<Texture> {
+//!     let loader = TextureLoader::new(device, allocator, uploads.clone());
+//!     let bytes = std::fs::read("skybox.ktx2")?;
+//!     let texture = loader.load(&bytes)?;
+//!     uploads.flush();
+//!     // The view of the texture is a cube view, since the file stores a cubemap.
+//!     texture.wait()
+//! }
+//! ```
+
+use std::borrow::Cow;
+use std::io::Read;
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, ensure, Result};
+use ash::vk;
+
+use crate::util::compressed::compressed_block;
+use crate::util::format::{texel_region_size, texel_size};
+use crate::util::upload::{mip_level_size, MipLevelData};
+use crate::{
+    Allocator, DefaultAllocator, Device, Image, ImageCreateInfo, ImageView, MemoryType, QueuedUpload, UploadManager,
+};
+
+mod dds;
+mod ktx2;
+
+/// Supercompression applied to the mip levels of a KTX2 file on top of their format.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Supercompression {
+    /// The mip levels are stored as-is.
+    None,
+    /// Basis Universal ETC1S data, which also uses the global data of the file. Only handled by [`BasisTranscoder::transcode()`].
+    BasisLz,
+    /// Zstandard compression.
+    Zstandard,
+    /// ZLIB compression.
+    Zlib,
+}
+
+/// The Basis Universal encoding of a texture.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum BasisEncoding {
+    /// ETC1S, which is always supercompressed with [`Supercompression::BasisLz`].
+    Etc1s,
+    /// UASTC, which is optionally supercompressed with Zstandard.
+    Uastc,
+}
+
+/// Describes a texture encoded with Basis Universal.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct BasisInfo {
+    /// The encoding of the texture.
+    pub encoding: BasisEncoding,
+    /// Whether the texture stores sRGB encoded colors.
+    pub srgb: bool,
+    /// Whether the texture has an alpha channel.
+    pub has_alpha: bool,
+}
+
+/// How the texel data of a [`TextureFile`] is stored.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum TextureEncoding {
+    /// Texel data in a Vulkan format, which can be uploaded directly.
+    Format(vk::Format),
+    /// Basis Universal data, which must be transcoded before it can be uploaded.
+    Basis(BasisInfo),
+}
+
+/// A single mip level of a [`TextureFile`].
+#[derive(Debug, Clone)]
+pub struct TextureLevel<'a> {
+    /// Data of all array layers and cube faces of this level, after each other. This is still supercompressed, use
+    /// [`TextureFile::level_data()`] to decompress it.
+    pub data: Cow<'a, [u8]>,
+    /// Size of the data after removing the supercompression. Zero if it is not known, as is the case for [`Supercompression::BasisLz`].
+    pub uncompressed_size: u64,
+}
+
+/// A texture parsed from a KTX2 or DDS file. See the [module-level documentation](self).
+#[derive(Debug, Clone)]
+pub struct TextureFile<'a> {
+    /// How the texel data is stored.
+    pub encoding: TextureEncoding,
+    /// Size of the base mip level.
+    pub size: vk::Extent3D,
+    /// Number of array layers, not counting cube faces.
+    pub layers: u32,
+    /// Number of cube faces, either 1 or 6.
+    pub faces: u32,
+    /// Supercompression of the mip levels.
+    pub supercompression: Supercompression,
+    /// Supercompression global data, used by [`Supercompression::BasisLz`].
+    pub global_data: &'a [u8],
+    /// All mip levels, starting at the base level.
+    pub levels: Vec<TextureLevel<'a>>,
+}
+
+impl<'a> TextureFile<'a> {
+    /// Parse a KTX2 or DDS file, based on its magic number.
+    /// # Errors
+    /// * Fails if the file is neither a KTX2 nor a DDS file.
+    /// * Fails if the file is truncated, or uses a format or layout that is not supported.
+    /// * Fails if the sizes in the file are inconsistent, such as more mip levels than the size of the texture allows.
+    pub fn parse(bytes: &'a [u8]) -> Result<Self> {
+        if bytes.starts_with(&ktx2::IDENTIFIER) {
+            Self::parse_ktx2(bytes)
+        } else if bytes.starts_with(dds::MAGIC) {
+            Self::parse_dds(bytes)
+        } else {
+            bail!("Texture is neither a KTX2 nor a DDS file")
+        }
+    }
+
+    /// Parse a KTX2 file. The mip levels borrow from `bytes`.
+    /// # Errors
+    /// * Fails if the file is not a valid KTX2 file, or is truncated.
+    /// * Fails if a file without a Vulkan format is not encoded with Basis Universal.
+    pub fn parse_ktx2(bytes: &'a [u8]) -> Result<Self> {
+        ktx2::parse(bytes)
+    }
+
+    /// Parse a DDS file, including files with a DX10 header. DDS files store each array layer with all of its mip levels
+    /// after each other, so the mip levels are copied out of `bytes`.
+    /// # Errors
+    /// * Fails if the file is not a valid DDS file, or is truncated.
+    /// * Fails if the file uses a DXGI format or legacy pixel format that has no matching Vulkan format.
+    /// * Fails if the file is a cubemap that does not store all six faces.
+    pub fn parse_dds(bytes: &'a [u8]) -> Result<Self> {
+        dds::parse(bytes)
+    }
+
+    /// Number of array layers of the image, which includes all cube faces.
+    pub fn image_layers(&self) -> u32 {
+        self.layers * self.faces
+    }
+
+    /// Number of mip levels stored in the file.
+    pub fn mip_levels(&self) -> u32 {
+        self.levels.len() as u32
+    }
+
+    /// Whether this texture is a cubemap or a cubemap array.
+    pub fn is_cube(&self) -> bool {
+        self.faces == 6
+    }
+
+    /// Get the data of a mip level with its Zstandard or ZLIB supercompression removed. Levels without supercompression and
+    /// [`Supercompression::BasisLz`] levels are returned as-is, since the latter can only be decoded by a [`BasisTranscoder`].
+    /// # Errors
+    /// * Fails if `mip_level` is not stored in the file.
+    /// * Fails if the level cannot be decompressed, or its decompressed size does not match the size stored in the file.
+    pub fn level_data(&self, mip_level: u32) -> Result<Cow<'_, [u8]>> {
+        let level = self
+            .levels
+            .get(mip_level as usize)
+            .ok_or_else(|| anyhow!("Mip level {mip_level} is not stored in the texture file"))?;
+        let data = match self.supercompression {
+            Supercompression::None | Supercompression::BasisLz => return Ok(Cow::Borrowed(&level.data)),
+            Supercompression::Zstandard => {
+                let decoder = ruzstd::StreamingDecoder::new(level.data.as_ref())
+                    .map_err(|err| anyhow!("Invalid Zstandard data in mip level {mip_level}: {err}"))?;
+                // The size comes from the file, so it is only used to limit the output instead of allocating it up front.
+                let mut data = Vec::new();
+                decoder
+                    .take(level.uncompressed_size.saturating_add(1))
+                    .read_to_end(&mut data)
+                    .map_err(|err| anyhow!("Invalid Zstandard data in mip level {mip_level}: {err}"))?;
+                data
+            }
+            Supercompression::Zlib => {
+                miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(&level.data, level.uncompressed_size as usize)
+                    .map_err(|err| anyhow!("Invalid ZLIB data in mip level {mip_level}: {err}"))?
+            }
+        };
+        ensure!(
+            data.len() as u64 == level.uncompressed_size,
+            "Decompressed mip level has {} bytes, expected {}",
+            data.len(),
+            level.uncompressed_size
+        );
+        Ok(Cow::Owned(data))
+    }
+}
+
+/// A mip level of a Basis Universal texture, passed to [`BasisTranscoder::transcode()`].
+#[derive(Debug, Copy, Clone)]
+pub struct BasisLevel<'a> {
+    /// Describes the encoding of the texture.
+    pub info: BasisInfo,
+    /// Index of this mip level.
+    pub mip_level: u32,
+    /// Size of this mip level.
+    pub extent: vk::Extent3D,
+    /// Number of images in this mip level, which is the number of array layers times the number of cube faces.
+    pub images: u32,
+    /// Encoded data of all images in this level. Zstandard supercompression was already removed.
+    pub data: &'a [u8],
+    /// Supercompression global data of the file, for ETC1S textures. Empty for UASTC textures.
+    pub global_data: &'a [u8],
+}
+
+/// Transcodes Basis Universal textures for the [`TextureLoader`].
+///
+/// phobos does not implement this itself, since it requires the Basis Universal transcoder library. Zstandard
+/// supercompression of UASTC textures is removed before [`BasisTranscoder::transcode()`] is called.
+pub trait BasisTranscoder: Send + Sync {
+    /// Whether this transcoder can transcode textures with `encoding` to `format`.
+    fn supports(&self, encoding: BasisEncoding, format: vk::Format) -> bool;
+
+    /// Transcode all images of a mip level to `format`. The result must hold the tightly packed blocks (or texels) of all
+    /// images after each other.
+    fn transcode(&self, level: &BasisLevel, format: vk::Format) -> Result<Vec<u8>>;
+}
+
+/// A loaded texture, with a view of all of its mip levels and layers.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct Texture<A: Allocator = DefaultAllocator> {
+    /// The texture image, in [`vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL`].
+    pub image: Image<A>,
+    /// A view of the whole image. This is a cube or cube array view for cubemaps.
+    pub view: ImageView,
+}
+
+/// Loads KTX2 and DDS textures through an [`UploadManager`]. See the [module-level documentation](self).
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct TextureLoader<A: Allocator + 'static = DefaultAllocator> {
+    #[derivative(Debug = "ignore")]
+    device: Device,
+    #[derivative(Debug = "ignore")]
+    allocator: A,
+    uploads: Arc<UploadManager<A>>,
+    #[derivative(Debug = "ignore")]
+    transcoder: Option<Arc<dyn BasisTranscoder>>,
+}
+
+impl<A: Allocator + 'static> TextureLoader<A> {
+    /// Create a texture loader that allocates images from `allocator`, and uploads them through `uploads`.
+    pub fn new(device: Device, allocator: A, uploads: Arc<UploadManager<A>>) -> Self {
+        Self {
+            device,
+            allocator,
+            uploads,
+            transcoder: None,
+        }
+    }
+
+    /// Set the transcoder used for Basis Universal textures.
+    pub fn transcoder(mut self, transcoder: Arc<dyn BasisTranscoder>) -> Self {
+        self.transcoder = Some(transcoder);
+        self
+    }
+
+    /// Parse a KTX2 or DDS file and queue its upload. See [`TextureLoader::load_file()`].
+    /// # Errors
+    /// * Fails if parsing the file fails, see [`TextureFile::parse()`].
+    /// * Fails if loading the texture fails, see [`TextureLoader::load_file()`].
+    pub fn load(&self, bytes: &[u8]) -> Result<QueuedUpload<Texture<A>>> {
+        self.load_file(&TextureFile::parse(bytes)?)
+    }
+
+    /// Create an image for a parsed texture file and queue the upload of all of its mip levels. Basis Universal textures are
+    /// transcoded first, which happens on the calling thread. The returned upload resolves to the texture.
+    /// # Errors
+    /// * Fails if the device cannot sample the format of the texture.
+    /// * Fails if the texture is encoded with Basis Universal, and no transcoder was set.
+    /// * Fails if removing the supercompression of a mip level fails, see [`TextureFile::level_data()`].
+    /// * Fails if no format supported by both the device and the transcoder was found, or transcoding fails.
+    /// * Fails if creating the image or queueing the upload fails.
+    pub fn load_file(&self, file: &TextureFile) -> Result<QueuedUpload<Texture<A>>> {
+        let (format, levels) = match file.encoding {
+            TextureEncoding::Format(format) => {
+                ensure!(self.is_supported(format), "Texture format {format:?} cannot be sampled on this device");
+                let levels = (0..file.mip_levels())
+                    .map(|mip_level| file.level_data(mip_level))
+                    .collect::<Result<Vec<_>>>()?;
+                (format, levels)
+            }
+            TextureEncoding::Basis(info) => self.transcode(file, info)?,
+        };
+
+        let mut allocator = self.allocator.clone();
+        let image = Image::new(
+            self.device.clone(),
+            &mut allocator,
+            ImageCreateInfo {
+                width: file.size.width,
+                height: file.size.height,
+                depth: file.size.depth,
+                usage: vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST,
+                format,
+                samples: vk::SampleCountFlags::TYPE_1,
+                mip_levels: file.mip_levels(),
+                layers: file.image_layers(),
+                flags: if file.is_cube() {
+                    vk::ImageCreateFlags::CUBE_COMPATIBLE
+                } else {
+                    vk::ImageCreateFlags::empty()
+                },
+                memory_type: MemoryType::GpuOnly,
+            },
+        )?;
+        let view = if file.is_cube() {
+            image.cube_view(vk::ImageAspectFlags::COLOR)?
+        } else {
+            image.whole_view(vk::ImageAspectFlags::COLOR)?
+        };
+        let data = levels
+            .iter()
+            .enumerate()
+            .map(|(mip_level, level)| MipLevelData::full(file.size, mip_level as u32, level))
+            .collect::<Vec<_>>();
+        let upload = self.uploads.upload_image(
+            &view,
+            &data,
+            vk::ImageLayout::UNDEFINED,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        )?;
+        Ok(upload.map(|()| Texture {
+            image,
+            view,
+        }))
+    }
+
+    /// Whether images with `format` can be uploaded to and sampled on this device.
+    fn is_supported(&self, format: vk::Format) -> bool {
+        self.device
+            .format_properties(format)
+            .optimal_tiling_features
+            .contains(vk::FormatFeatureFlags::SAMPLED_IMAGE | vk::FormatFeatureFlags::TRANSFER_DST)
+    }
+
+    fn require_transcoder(&self) -> Result<&dyn BasisTranscoder> {
+        self.transcoder
+            .as_deref()
+            .ok_or_else(|| anyhow!("A BasisTranscoder is required to load Basis Universal textures"))
+    }
+
+    /// Pick a target format for a Basis Universal texture and transcode all of its mip levels to it.
+    fn transcode<'a>(&self, file: &'a TextureFile, info: BasisInfo) -> Result<(vk::Format, Vec<Cow<'a, [u8]>>)> {
+        let transcoder = self.require_transcoder()?;
+        let format = basis_targets(info)
+            .into_iter()
+            .find(|&format| transcoder.supports(info.encoding, format) && self.is_supported(format))
+            .ok_or_else(|| anyhow!("No format to transcode {:?} textures to is supported", info.encoding))?;
+        let levels = (0..file.mip_levels())
+            .map(|mip_level| {
+                let data = file.level_data(mip_level)?;
+                let level = BasisLevel {
+                    info,
+                    mip_level,
+                    extent: mip_level_size(file.size, mip_level),
+                    images: file.image_layers(),
+                    data: &data,
+                    global_data: file.global_data,
+                };
+                Ok(Cow::Owned(transcoder.transcode(&level, format)?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok((format, levels))
+    }
+}
+
+/// Formats to transcode a Basis Universal texture to, from most to least preferred. ETC1S maps directly onto ETC2, and UASTC
+/// onto ASTC 4x4, so these are preferred for their encoding. RGBA8 is always supported as a last resort.
+fn basis_targets(info: BasisInfo) -> [vk::Format; 5] {
+    use vk::Format as F;
+
+    let pick = |unorm: F, srgb: F| if info.srgb { srgb } else { unorm };
+    let etc2 = if info.has_alpha {
+        pick(F::ETC2_R8G8B8A8_UNORM_BLOCK, F::ETC2_R8G8B8A8_SRGB_BLOCK)
+    } else {
+        pick(F::ETC2_R8G8B8_UNORM_BLOCK, F::ETC2_R8G8B8_SRGB_BLOCK)
+    };
+    let bc = if info.has_alpha {
+        pick(F::BC3_UNORM_BLOCK, F::BC3_SRGB_BLOCK)
+    } else {
+        pick(F::BC1_RGB_UNORM_BLOCK, F::BC1_RGB_SRGB_BLOCK)
+    };
+    let astc = pick(F::ASTC_4X4_UNORM_BLOCK, F::ASTC_4X4_SRGB_BLOCK);
+    let bc7 = pick(F::BC7_UNORM_BLOCK, F::BC7_SRGB_BLOCK);
+    let rgba = pick(F::R8G8B8A8_UNORM, F::R8G8B8A8_SRGB);
+    match info.encoding {
+        BasisEncoding::Etc1s => [etc2, bc7, astc, bc, rgba],
+        BasisEncoding::Uastc => [astc, bc7, etc2, bc, rgba],
+    }
+}
+
+/// Maximum number of mip levels of an image with base size `size`.
+fn max_mip_levels(size: vk::Extent3D) -> u32 {
+    u32::BITS - size.width.max(size.height).max(size.depth).max(1).leading_zeros()
+}
+
+/// Get the size in bytes of `images` images of a mip level stored in `format`. Returns `None` if the format is neither
+/// block-compressed nor has a known texel size.
+/// # Errors
+/// * Fails if the size does not fit in a `u64`.
+fn level_size(format: vk::Format, size: vk::Extent3D, mip_level: u32, images: u32) -> Result<Option<u64>> {
+    let extent = mip_level_size(size, mip_level);
+    match (compressed_block(format), texel_size(format)) {
+        (Some(block), _) => block.region_size(extent, images).map(Some),
+        (None, Some(texel)) => texel_region_size(extent, images, texel)
+            .map(Some)
+            .ok_or_else(|| anyhow!("Mip level {mip_level} of the texture is too large")),
+        (None, None) => Ok(None),
+    }
+}
+
+/// Read a little endian `u32` at `offset`.
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32> {
+    let bytes = read_bytes(bytes, offset as u64, 4)?;
+    Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+/// Read a little endian `u64` at `offset`.
+fn read_u64(bytes: &[u8], offset: usize) -> Result<u64> {
+    let bytes = read_bytes(bytes, offset as u64, 8)?;
+    Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+/// Get `len` bytes at `offset`.
+fn read_bytes(bytes: &[u8], offset: u64, len: u64) -> Result<&[u8]> {
+    offset
+        .checked_add(len)
+        .filter(|&end| end <= bytes.len() as u64)
+        .map(|end| &bytes[offset as usize..end as usize])
+        .ok_or_else(|| anyhow!("Texture file is truncated"))
+}
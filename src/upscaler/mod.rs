@@ -0,0 +1,188 @@
+//! Generic interface for temporal upscalers.
+//!
+//! The [`Upscaler`] trait abstracts over upscaling libraries such as FSR2, so render code can be written once and
+//! different upscalers can be plugged in without changing any pass code. An upscaler takes the color, depth and
+//! motion vector buffers of the current frame at render resolution, and writes an upscaled color buffer at display resolution.
+//!
+//! Upscalers rely on subpixel jitter applied to the camera projection. The jitter offset for the current frame
+//! is obtained with [`Upscaler::jitter_offset()`], and must be passed back to the upscaler in the [`UpscaleDescription`].
+//!
+//! To add an upscale pass to a pass graph, use [`PassBuilder::upscale()`](crate::PassBuilder::upscale).
+//! With the `fsr3` feature enabled, [`Fsr3Upscaler`](crate::fsr3::Fsr3Upscaler) implements this trait. The `fsr2` feature
+//! is kept for compatibility, with [`Fsr2Upscaler`](crate::fsr2::Fsr2Upscaler) implementing this trait.
+//! # Example
+//! ```
+//! # use anyhow::Result;
+//! # use phobos::prelude::*;
+//! # use phobos::sync::domain::All;
+//! fn upscale_pass<'cb, U: Upscaler + 'cb>(
+//!     mut upscaler: U,
+//!     render_width: u32,
+//!     resources: UpscaleVirtualResources,
+//! ) -> Result<Pass<'cb, All, ()>> {
+//!     let jitter_offset = upscaler.jitter_offset(render_width)?;
+//!     let descr = UpscaleDescription {
+//!         jitter_offset,
+//!         ..Default::default()
+//!     };
+//!     Ok(PassBuilder::upscale(upscaler, descr, resources))
+//! }
+//! ```
+
+use std::time::Duration;
+
+use anyhow::Result;
+use ash::vk;
+
+use crate::ImageView;
+
+/// Quality mode of an upscaler. Lower quality modes use a lower render resolution.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum UpscaleQuality {
+    /// Highest image quality, with the highest render resolution.
+    #[default]
+    Quality,
+    /// Balance between quality and performance.
+    Balanced,
+    /// Favour performance over quality.
+    Performance,
+    /// Lowest render resolution, for very high display resolutions.
+    UltraPerformance,
+}
+
+/// Stores the resources needed to execute an upscale dispatch.
+#[derive(Debug, Clone)]
+pub struct UpscaleResources {
+    /// Color buffer for the current frame, at render resolution.
+    pub color: ImageView,
+    /// Depth buffer for the current frame, at render resolution
+    pub depth: ImageView,
+    /// Motion vectors for the current frame, at render resolution
+    pub motion_vectors: ImageView,
+    /// Optional 1x1 texture with the exposure value
+    pub exposure: Option<ImageView>,
+    /// Output color buffer for the current frame at display resolution
+    pub output: ImageView,
+}
+
+/// Holds the settings for an upscale dispatch that are shared by all upscalers.
+#[derive(Debug, Clone)]
+pub struct UpscaleDescription {
+    /// Subpixel jitter offset applied to the camera, obtained from [`Upscaler::jitter_offset()`].
+    pub jitter_offset: (f32, f32),
+    /// Scale factor to apply to motion vectors
+    pub motion_vector_scale: (f32, f32),
+    /// 0..1 value for additional sharpening, or `None` to disable sharpening
+    pub sharpness: Option<f32>,
+    /// Delta time between this frame and the previous frame
+    pub frametime_delta: Duration,
+    /// Pre exposure value, must be > 0.0
+    pub pre_exposure: f32,
+    /// Indicates the camera has moved discontinuously, so history must be discarded
+    pub reset: bool,
+    /// Distance to the near plane of the camera
+    pub camera_near: f32,
+    /// Distance to the far plane of the camera
+    pub camera_far: f32,
+    /// Camera angle FOV in the vertical direction, in radians
+    pub camera_fov_vertical: f32,
+}
+
+impl Default for UpscaleDescription {
+    fn default() -> Self {
+        Self {
+            jitter_offset: (0.0, 0.0),
+            motion_vector_scale: (1.0, 1.0),
+            sharpness: None,
+            frametime_delta: Duration::default(),
+            pre_exposure: 1.0,
+            reset: false,
+            camera_near: 0.1,
+            camera_far: 1000.0,
+            camera_fov_vertical: std::f32::consts::FRAC_PI_2,
+        }
+    }
+}
+
+/// A temporal upscaler. See the [module-level documentation](self) for more information.
+pub trait Upscaler {
+    /// Record the upscale commands into `cmd`. This does not insert any synchronization on the resources used, this is
+    /// left to the caller (or the pass graph).
+    /// # Errors
+    /// * Fails if the upscaler reports an error while recording.
+    fn dispatch(&mut self, descr: &UpscaleDescription, resources: &UpscaleResources, cmd: vk::CommandBuffer) -> Result<()>;
+
+    /// Get the number of jitter phases, given the current render width.
+    fn jitter_phase_count(&mut self, render_width: u32) -> u32;
+
+    /// Get the jitter offset to apply to the camera for the current frame, given the current render width.
+    /// # Errors
+    /// * Fails if the upscaler cannot compute a jitter offset.
+    fn jitter_offset(&mut self, render_width: u32) -> Result<(f32, f32)>;
+
+    /// Get the current display resolution.
+    fn display_size(&self) -> vk::Extent2D;
+
+    /// Get the recommended render resolution for a quality mode at the current display resolution.
+    /// # Errors
+    /// * Fails if the upscaler does not support the quality mode.
+    fn render_resolution(&mut self, quality: UpscaleQuality) -> Result<vk::Extent2D>;
+
+    /// Resize the upscaler to a new display resolution. If `max_render_size` is `None`, it is assumed to be equal to
+    /// the display size. Attachments that depend on the display or render size should be recreated afterwards.
+    /// # Errors
+    /// * Fails if the upscaler could not be recreated with the new size.
+    fn resize(&mut self, display_size: vk::Extent2D, max_render_size: Option<vk::Extent2D>) -> Result<()>;
+}
+
+impl<U: Upscaler + ?Sized> Upscaler for &mut U {
+    fn dispatch(&mut self, descr: &UpscaleDescription, resources: &UpscaleResources, cmd: vk::CommandBuffer) -> Result<()> {
+        (**self).dispatch(descr, resources, cmd)
+    }
+
+    fn jitter_phase_count(&mut self, render_width: u32) -> u32 {
+        (**self).jitter_phase_count(render_width)
+    }
+
+    fn jitter_offset(&mut self, render_width: u32) -> Result<(f32, f32)> {
+        (**self).jitter_offset(render_width)
+    }
+
+    fn display_size(&self) -> vk::Extent2D {
+        (**self).display_size()
+    }
+
+    fn render_resolution(&mut self, quality: UpscaleQuality) -> Result<vk::Extent2D> {
+        (**self).render_resolution(quality)
+    }
+
+    fn resize(&mut self, display_size: vk::Extent2D, max_render_size: Option<vk::Extent2D>) -> Result<()> {
+        (**self).resize(display_size, max_render_size)
+    }
+}
+
+impl<U: Upscaler + ?Sized> Upscaler for Box<U> {
+    fn dispatch(&mut self, descr: &UpscaleDescription, resources: &UpscaleResources, cmd: vk::CommandBuffer) -> Result<()> {
+        (**self).dispatch(descr, resources, cmd)
+    }
+
+    fn jitter_phase_count(&mut self, render_width: u32) -> u32 {
+        (**self).jitter_phase_count(render_width)
+    }
+
+    fn jitter_offset(&mut self, render_width: u32) -> Result<(f32, f32)> {
+        (**self).jitter_offset(render_width)
+    }
+
+    fn display_size(&self) -> vk::Extent2D {
+        (**self).display_size()
+    }
+
+    fn render_resolution(&mut self, quality: UpscaleQuality) -> Result<vk::Extent2D> {
+        (**self).render_resolution(quality)
+    }
+
+    fn resize(&mut self, display_size: vk::Extent2D, max_render_size: Option<vk::Extent2D>) -> Result<()> {
+        (**self).resize(display_size, max_render_size)
+    }
+}
@@ -81,6 +81,25 @@ impl<R: Resource + Sized> Cache<R> {
         Ok(&entry.value)
     }
 
+    /// Access a resource in the cache by its key without creating it. Unlike [`Cache::get_or_create`], this does not
+    /// reset the time to live of the resource.
+    pub fn get(&self, key: &R::Key) -> Option<&R> {
+        self.store.get(key).map(|entry| &entry.value)
+    }
+
+    /// Insert a resource that was created outside of the cache, for example on another thread. If a resource with this key
+    /// already exists, it is kept since it might be in use, and `value` is dropped instead.
+    pub(crate) fn insert(&mut self, key: R::Key, value: R) {
+        if let hash_map::Entry::Vacant(entry) = self.store.entry(key) {
+            let persistent = entry.key().persistent();
+            entry.insert(Entry {
+                value,
+                ttl: R::MAX_TIME_TO_LIVE,
+                persistent,
+            });
+        }
+    }
+
     /// Updates the cache to deallocate resources that have not been accessed for too long.
     pub(crate) fn next_frame(&mut self) {
         self.store.iter_mut().for_each(|(_, entry)| {
@@ -92,3 +111,13 @@ impl<R: Resource + Sized> Cache<R> {
             .retain(|_, entry| entry.persistent || entry.ttl != 0);
     }
 }
+
+impl<R: Resource + Sized> Drop for Cache<R> {
+    fn drop(&mut self) {
+        // Resources in the cache may still be in use by work that was submitted before the cache was dropped,
+        // so that work must complete before they are destroyed.
+        if !self.store.is_empty() {
+            self.device.wait_for_submitted_work();
+        }
+    }
+}
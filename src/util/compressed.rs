@@ -0,0 +1,185 @@
+//! Helpers for uploading block-compressed images, such as BCn, ETC2 and ASTC textures.
+//!
+//! Block-compressed formats store fixed size blocks of texels instead of single texels, so the size of a mip level is not
+//! `width * height * bytes_per_texel`. Mip levels smaller than a block still take up a whole block. Use [`compressed_block()`]
+//! to get the block size of a format, and [`compressed_level_size()`] to get the size of a mip level.
+//!
+//! [`MipLevelData::compressed_chain()`] splits the data of a full mip chain into the levels accepted by
+//! [`Image::from_data()`](crate::Image::from_data) and the [`UploadManager`](crate::UploadManager). Regions of compressed images
+//! are validated when uploading: their offsets must be aligned to the block size, their extents must be a multiple of the block
+//! size unless they reach the edge of the mip level, and their data must have the exact compressed size.
+//!
+//! Sampling compressed images requires the matching device feature (`textureCompressionBC`, `textureCompressionETC2` or
+//! `textureCompressionASTC_LDR`), which is enabled automatically when it is supported.
+//! # Example
+//! ```
+//! # use anyhow::Result;
+//! # use phobos::prelude::*;
+//! async fn upload_bc7(
+//!     mut allocator: DefaultAllocator,
+//!     exec: ExecutionManager,
+//!     mip_chain: &[u8],
+//! ) -> Result<Image> {
+//!     let size = vk::Extent3D { width: 256, height: 256, depth: 1 };
+//!     let info = ImageCreateInfo {
+//!         width: size.width,
+//!         height: size.height,
+//!         depth: 1,
+//!         usage: vk::ImageUsageFlags::SAMPLED,
+//!         format: vk::Format::BC7_SRGB_BLOCK,
+//!         samples: vk::SampleCountFlags::TYPE_1,
+//!         mip_levels: 9,
+//!         layers: 1,
+//!         flags: vk::ImageCreateFlags::empty(),
+//!         memory_type: MemoryType::GpuOnly,
+//!     };
+//!     // The mip chain holds all 9 levels after each other, down to a single 4x4 block.
+//!     let levels = MipLevelData::compressed_chain(info.format, size, info.mip_levels, info.layers, mip_chain)?;
+//!     let image = Image::from_data(&mut allocator, &exec, info, &levels, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)?;
+//!     image.await
+//! }
+//! ```
+
+use anyhow::{ensure, Result};
+use ash::vk;
+
+use crate::util::format::texel_region_size;
+use crate::util::upload::{mip_level_size, MipLevelData};
+use crate::ImageRegion;
+
+/// The size of a block of a block-compressed format.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct CompressedBlock {
+    /// Width of a block in texels
+    pub width: u32,
+    /// Height of a block in texels
+    pub height: u32,
+    /// Size of a block in bytes
+    pub bytes: u32,
+}
+
+impl CompressedBlock {
+    /// Get the number of blocks needed to cover `extent` horizontally and vertically. Partial blocks count as whole blocks.
+    pub fn block_count(&self, extent: vk::Extent3D) -> (u32, u32) {
+        (extent.width.div_ceil(self.width), extent.height.div_ceil(self.height))
+    }
+
+    /// Get the size in bytes of a region of `layers` array layers with the given extent.
+    /// # Errors
+    /// * Fails if the size does not fit in a `u64`.
+    pub fn region_size(&self, extent: vk::Extent3D, layers: u32) -> Result<u64> {
+        let (x, y) = self.block_count(extent);
+        let blocks = vk::Extent3D {
+            width: x,
+            height: y,
+            depth: extent.depth,
+        };
+        texel_region_size(blocks, layers, self.bytes)
+            .ok_or_else(|| anyhow::anyhow!("Compressed region with extent {extent:?} and {layers} layers is too large"))
+    }
+}
+
+/// Get the block size of a block-compressed format. Returns `None` for formats that are not block-compressed.
+pub fn compressed_block(format: vk::Format) -> Option<CompressedBlock> {
+    use vk::Format as F;
+
+    let (width, height, bytes) = match format {
+        F::BC1_RGB_UNORM_BLOCK | F::BC1_RGB_SRGB_BLOCK | F::BC1_RGBA_UNORM_BLOCK | F::BC1_RGBA_SRGB_BLOCK => (4, 4, 8),
+        F::BC2_UNORM_BLOCK | F::BC2_SRGB_BLOCK | F::BC3_UNORM_BLOCK | F::BC3_SRGB_BLOCK => (4, 4, 16),
+        F::BC4_UNORM_BLOCK | F::BC4_SNORM_BLOCK => (4, 4, 8),
+        F::BC5_UNORM_BLOCK | F::BC5_SNORM_BLOCK => (4, 4, 16),
+        F::BC6H_UFLOAT_BLOCK | F::BC6H_SFLOAT_BLOCK | F::BC7_UNORM_BLOCK | F::BC7_SRGB_BLOCK => (4, 4, 16),
+        F::ETC2_R8G8B8_UNORM_BLOCK
+        | F::ETC2_R8G8B8_SRGB_BLOCK
+        | F::ETC2_R8G8B8A1_UNORM_BLOCK
+        | F::ETC2_R8G8B8A1_SRGB_BLOCK => (4, 4, 8),
+        F::ETC2_R8G8B8A8_UNORM_BLOCK | F::ETC2_R8G8B8A8_SRGB_BLOCK => (4, 4, 16),
+        F::EAC_R11_UNORM_BLOCK | F::EAC_R11_SNORM_BLOCK => (4, 4, 8),
+        F::EAC_R11G11_UNORM_BLOCK | F::EAC_R11G11_SNORM_BLOCK => (4, 4, 16),
+        // All ASTC blocks are 16 bytes, regardless of their size.
+        F::ASTC_4X4_UNORM_BLOCK | F::ASTC_4X4_SRGB_BLOCK | F::ASTC_4X4_SFLOAT_BLOCK => (4, 4, 16),
+        F::ASTC_5X4_UNORM_BLOCK | F::ASTC_5X4_SRGB_BLOCK | F::ASTC_5X4_SFLOAT_BLOCK => (5, 4, 16),
+        F::ASTC_5X5_UNORM_BLOCK | F::ASTC_5X5_SRGB_BLOCK | F::ASTC_5X5_SFLOAT_BLOCK => (5, 5, 16),
+        F::ASTC_6X5_UNORM_BLOCK | F::ASTC_6X5_SRGB_BLOCK | F::ASTC_6X5_SFLOAT_BLOCK => (6, 5, 16),
+        F::ASTC_6X6_UNORM_BLOCK | F::ASTC_6X6_SRGB_BLOCK | F::ASTC_6X6_SFLOAT_BLOCK => (6, 6, 16),
+        F::ASTC_8X5_UNORM_BLOCK | F::ASTC_8X5_SRGB_BLOCK | F::ASTC_8X5_SFLOAT_BLOCK => (8, 5, 16),
+        F::ASTC_8X6_UNORM_BLOCK | F::ASTC_8X6_SRGB_BLOCK | F::ASTC_8X6_SFLOAT_BLOCK => (8, 6, 16),
+        F::ASTC_8X8_UNORM_BLOCK | F::ASTC_8X8_SRGB_BLOCK | F::ASTC_8X8_SFLOAT_BLOCK => (8, 8, 16),
+        F::ASTC_10X5_UNORM_BLOCK | F::ASTC_10X5_SRGB_BLOCK | F::ASTC_10X5_SFLOAT_BLOCK => (10, 5, 16),
+        F::ASTC_10X6_UNORM_BLOCK | F::ASTC_10X6_SRGB_BLOCK | F::ASTC_10X6_SFLOAT_BLOCK => (10, 6, 16),
+        F::ASTC_10X8_UNORM_BLOCK | F::ASTC_10X8_SRGB_BLOCK | F::ASTC_10X8_SFLOAT_BLOCK => (10, 8, 16),
+        F::ASTC_10X10_UNORM_BLOCK | F::ASTC_10X10_SRGB_BLOCK | F::ASTC_10X10_SFLOAT_BLOCK => (10, 10, 16),
+        F::ASTC_12X10_UNORM_BLOCK | F::ASTC_12X10_SRGB_BLOCK | F::ASTC_12X10_SFLOAT_BLOCK => (12, 10, 16),
+        F::ASTC_12X12_UNORM_BLOCK | F::ASTC_12X12_SRGB_BLOCK | F::ASTC_12X12_SFLOAT_BLOCK => (12, 12, 16),
+        _ => return None,
+    };
+    Some(CompressedBlock {
+        width,
+        height,
+        bytes,
+    })
+}
+
+/// Whether this is a block-compressed format.
+pub fn is_block_compressed(format: vk::Format) -> bool {
+    compressed_block(format).is_some()
+}
+
+/// Get the size in bytes of mip level `mip_level` of a block-compressed image with base size `size` and `layers` array layers.
+/// # Errors
+/// * Fails if `format` is not block-compressed.
+pub fn compressed_level_size(format: vk::Format, size: vk::Extent3D, mip_level: u32, layers: u32) -> Result<u64> {
+    let block = compressed_block(format).ok_or_else(|| anyhow::anyhow!("{format:?} is not a block-compressed format"))?;
+    block.region_size(mip_level_size(size, mip_level), layers)
+}
+
+/// Check that a region of a mip level with size `level_size` is aligned to the blocks of a compressed format.
+pub(crate) fn validate_block_region(block: CompressedBlock, region: &ImageRegion, level_size: vk::Extent3D) -> Result<()> {
+    let aligned = |offset: i32, extent: u32, size: u32, block: u32| {
+        offset as u32 % block == 0 && (extent % block == 0 || offset as u32 + extent == size)
+    };
+    ensure!(
+        aligned(region.offset.x, region.extent.width, level_size.width, block.width)
+            && aligned(region.offset.y, region.extent.height, level_size.height, block.height),
+        "Region at {:?} with extent {:?} is not aligned to {}x{} compressed blocks",
+        region.offset,
+        region.extent,
+        block.width,
+        block.height
+    );
+    Ok(())
+}
+
+impl<'a> MipLevelData<'a> {
+    /// Split the data of a full mip chain of a block-compressed image into one [`MipLevelData`] per level. `data` must hold
+    /// all `mip_levels` levels after each other, starting at the base level, where each level holds all array layers.
+    /// # Errors
+    /// * Fails if `format` is not block-compressed.
+    /// * Fails if the size of `data` does not match the size of the mip chain.
+    pub fn compressed_chain(
+        format: vk::Format,
+        size: vk::Extent3D,
+        mip_levels: u32,
+        layers: u32,
+        data: &'a [u8],
+    ) -> Result<Vec<Self>> {
+        let mut offset = 0usize;
+        let mut levels = Vec::new();
+        for mip_level in 0..mip_levels {
+            let level_size = compressed_level_size(format, size, mip_level, layers)? as usize;
+            ensure!(
+                offset.checked_add(level_size).is_some_and(|end| end <= data.len()),
+                "Compressed data of {} bytes is too small for mip level {mip_level}",
+                data.len()
+            );
+            levels.push(MipLevelData::full(size, mip_level, &data[offset..offset + level_size]));
+            offset += level_size;
+        }
+        ensure!(
+            offset == data.len(),
+            "Compressed data has {} bytes, but a mip chain of {mip_levels} levels needs {offset} bytes",
+            data.len()
+        );
+        Ok(levels)
+    }
+}
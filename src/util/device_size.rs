@@ -0,0 +1,143 @@
+//! Overflow-safe arithmetic for sizes and offsets of device memory.
+
+use std::fmt::{Display, Formatter};
+
+use anyhow::Result;
+use ash::vk;
+
+use crate::Error;
+
+/// A size or offset in device memory, in bytes. All arithmetic on this type is checked, and returns
+/// [`Error::DeviceSizeOverflow`] instead of silently wrapping around. Since this converts into a [`vk::DeviceSize`],
+/// it can be passed to any function taking `impl Into<vk::DeviceSize>`.
+/// # Example
+/// ```
+/// # use anyhow::Result;
+/// # use phobos::prelude::*;
+/// fn element<A: Allocator>(buffer: &Buffer<A>, index: u64, stride: u64) -> Result<BufferView> {
+///     let offset = DeviceSize::new(stride).checked_mul(index)?;
+///     buffer.view(offset, stride)
+/// }
+/// ```
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DeviceSize(vk::DeviceSize);
+
+impl DeviceSize {
+    /// A size of zero bytes.
+    pub const ZERO: Self = Self(0);
+
+    /// Create a new device size from a raw amount of bytes.
+    pub const fn new(bytes: vk::DeviceSize) -> Self {
+        Self(bytes)
+    }
+
+    /// Get the raw amount of bytes.
+    pub const fn get(self) -> vk::DeviceSize {
+        self.0
+    }
+
+    /// Add two sizes.
+    /// # Errors
+    /// Fails if the result overflows.
+    pub fn checked_add(self, rhs: impl Into<DeviceSize>) -> Result<Self> {
+        let rhs = rhs.into();
+        self.0
+            .checked_add(rhs.0)
+            .map(Self)
+            .ok_or_else(|| Self::overflow(self, "+", rhs))
+    }
+
+    /// Subtract `rhs` from this size.
+    /// # Errors
+    /// Fails if `rhs` is larger than `self`.
+    pub fn checked_sub(self, rhs: impl Into<DeviceSize>) -> Result<Self> {
+        let rhs = rhs.into();
+        self.0
+            .checked_sub(rhs.0)
+            .map(Self)
+            .ok_or_else(|| Self::overflow(self, "-", rhs))
+    }
+
+    /// Multiply two sizes, for example an element count with a stride.
+    /// # Errors
+    /// Fails if the result overflows.
+    pub fn checked_mul(self, rhs: impl Into<DeviceSize>) -> Result<Self> {
+        let rhs = rhs.into();
+        self.0
+            .checked_mul(rhs.0)
+            .map(Self)
+            .ok_or_else(|| Self::overflow(self, "*", rhs))
+    }
+
+    /// Round this size up to the next multiple of `alignment`. Sizes that are already aligned are returned unchanged.
+    /// An alignment of zero is treated as no alignment requirement.
+    /// # Errors
+    /// Fails if the aligned size overflows.
+    pub fn align_up(self, alignment: impl Into<DeviceSize>) -> Result<Self> {
+        let alignment = alignment.into();
+        if alignment.0 <= 1 {
+            return Ok(self);
+        }
+        match self.0 % alignment.0 {
+            0 => Ok(self),
+            rem => self.checked_add(alignment.0 - rem),
+        }
+    }
+
+    /// Whether this size is a multiple of `alignment`.
+    pub fn is_aligned(self, alignment: impl Into<DeviceSize>) -> bool {
+        let alignment = alignment.into();
+        alignment.0 == 0 || self.0 % alignment.0 == 0
+    }
+
+    /// Check whether the range `[offset, offset + size)` lies completely inside a resource of `self` bytes.
+    /// # Errors
+    /// Fails if `offset + size` overflows.
+    pub fn contains_range(
+        self,
+        offset: impl Into<DeviceSize>,
+        size: impl Into<DeviceSize>,
+    ) -> Result<bool> {
+        let end = offset.into().checked_add(size)?;
+        Ok(end <= self)
+    }
+
+    fn overflow(lhs: Self, op: &'static str, rhs: Self) -> anyhow::Error {
+        Error::DeviceSizeOverflow {
+            lhs: lhs.0,
+            op,
+            rhs: rhs.0,
+        }
+        .into()
+    }
+}
+
+impl From<vk::DeviceSize> for DeviceSize {
+    fn from(value: vk::DeviceSize) -> Self {
+        Self(value)
+    }
+}
+
+impl From<u32> for DeviceSize {
+    fn from(value: u32) -> Self {
+        Self(value as vk::DeviceSize)
+    }
+}
+
+impl From<usize> for DeviceSize {
+    fn from(value: usize) -> Self {
+        Self(value as vk::DeviceSize)
+    }
+}
+
+impl From<DeviceSize> for vk::DeviceSize {
+    fn from(value: DeviceSize) -> Self {
+        value.0
+    }
+}
+
+impl Display for DeviceSize {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} bytes", self.0)
+    }
+}
@@ -0,0 +1,103 @@
+//! Properties of image formats that are needed to size copies on the host.
+
+use ash::vk;
+
+/// Size in bytes of a single texel of an uncompressed color format. Returns `None` for block-compressed, depth/stencil,
+/// multi-planar and unknown formats.
+pub(crate) fn texel_size(format: vk::Format) -> Option<u32> {
+    Some(match format.as_raw() {
+        // R4G4_UNORM_PACK8
+        1 => 1,
+        // R4G4B4A4_UNORM_PACK16 up to A1R5G5B5_UNORM_PACK16
+        2..=8 => 2,
+        // R8_*
+        9..=15 => 1,
+        // R8G8_*
+        16..=22 => 2,
+        // R8G8B8_* and B8G8R8_*
+        23..=36 => 3,
+        // R8G8B8A8_*, B8G8R8A8_*, A8B8G8R8_*_PACK32, A2R10G10B10_*_PACK32 and A2B10G10R10_*_PACK32
+        37..=69 => 4,
+        // R16_*
+        70..=76 => 2,
+        // R16G16_*
+        77..=83 => 4,
+        // R16G16B16_*
+        84..=90 => 6,
+        // R16G16B16A16_*
+        91..=97 => 8,
+        // R32_*
+        98..=100 => 4,
+        // R32G32_*
+        101..=103 => 8,
+        // R32G32B32_*
+        104..=106 => 12,
+        // R32G32B32A32_*
+        107..=109 => 16,
+        // R64_*
+        110..=112 => 8,
+        // R64G64_*
+        113..=115 => 16,
+        // R64G64B64_*
+        116..=118 => 24,
+        // R64G64B64A64_*
+        119..=121 => 32,
+        // B10G11R11_UFLOAT_PACK32 and E5B9G9R9_UFLOAT_PACK32
+        122..=123 => 4,
+        _ => match format {
+            vk::Format::A4R4G4B4_UNORM_PACK16 | vk::Format::A4B4G4R4_UNORM_PACK16 => 2,
+            _ => return None,
+        },
+    })
+}
+
+/// Size in bytes of `layers` array layers of a region of tightly packed texels of `texel_size` bytes. Returns `None` if the size
+/// does not fit in a `u64`.
+pub(crate) fn texel_region_size(extent: vk::Extent3D, layers: u32, texel_size: u32) -> Option<u64> {
+    [extent.height, extent.depth, layers, texel_size]
+        .into_iter()
+        .try_fold(extent.width as u64, |size, factor| size.checked_mul(factor as u64))
+}
+
+/// Whether `format` has a depth or stencil aspect.
+pub(crate) fn is_depth_stencil(format: vk::Format) -> bool {
+    matches!(
+        format,
+        vk::Format::D16_UNORM
+            | vk::Format::X8_D24_UNORM_PACK32
+            | vk::Format::D32_SFLOAT
+            | vk::Format::S8_UINT
+            | vk::Format::D16_UNORM_S8_UINT
+            | vk::Format::D24_UNORM_S8_UINT
+            | vk::Format::D32_SFLOAT_S8_UINT
+    )
+}
+
+/// Size in bytes of a single texel of `aspect` of `format` in buffer to image copies. Depth and stencil aspects are copied
+/// separately, and have their own texel size. Returns `None` if copies of this aspect are not supported.
+pub(crate) fn aspect_texel_size(format: vk::Format, aspect: vk::ImageAspectFlags) -> Option<u32> {
+    if aspect == vk::ImageAspectFlags::COLOR {
+        return texel_size(format);
+    }
+    if aspect == vk::ImageAspectFlags::STENCIL {
+        let has_stencil = matches!(
+            format,
+            vk::Format::S8_UINT
+                | vk::Format::D16_UNORM_S8_UINT
+                | vk::Format::D24_UNORM_S8_UINT
+                | vk::Format::D32_SFLOAT_S8_UINT
+        );
+        return has_stencil.then_some(1);
+    }
+    if aspect != vk::ImageAspectFlags::DEPTH {
+        return None;
+    }
+    match format {
+        vk::Format::D16_UNORM | vk::Format::D16_UNORM_S8_UINT => Some(2),
+        vk::Format::X8_D24_UNORM_PACK32
+        | vk::Format::D24_UNORM_S8_UINT
+        | vk::Format::D32_SFLOAT
+        | vk::Format::D32_SFLOAT_S8_UINT => Some(4),
+        _ => None,
+    }
+}
@@ -0,0 +1,422 @@
+//! Copy data between host memory and images without staging buffers, through `VK_EXT_host_image_copy`.
+//!
+//! With host image copies, the CPU writes texels directly into image memory, and reads them back the same way. This skips
+//! the staging buffer and the transfer queue entirely, which is fastest on systems where the CPU can access device local memory,
+//! such as integrated GPUs and GPUs with resizable BAR. The extension is enabled with
+//! [`AppBuilder::host_image_copy()`](crate::AppBuilder::host_image_copy), and is available if
+//! [`ExtensionID::HostImageCopy`](crate::ExtensionID::HostImageCopy) is enabled on the device.
+//!
+//! Images used with host copies must be created with [`IMAGE_USAGE_HOST_TRANSFER`], and must not be in use by the GPU
+//! during the copy. [`Image::from_data_host()`] creates an image with this usage and uploads its initial data.
+//! Since there are no barriers for host copies, the image layout is changed on the host with [`Image::transition_layout_host()`].
+//! The layout used for a copy must be supported by the device for host copies.
+//!
+//! Only uncompressed color formats are supported. Texels are tightly packed, with the data for all array layers after each other,
+//! like with [`Image::from_data()`]. The size of a texel is derived from the format of the image.
+//!
+//! [`Image::from_data()`] also uploads with host copies when the extension is enabled, the format supports host transfers and
+//! the requested layout is supported for host copies.
+//! # Example
+//! ```
+//! # use anyhow::Result;
+//! # use phobos::prelude::*;
+//! fn upload_texture(device: Device, mut allocator: DefaultAllocator, pixels: &[u8]) -> Result<(Image, Vec<u8>)> {
+//!     let info = ImageCreateInfo {
+//!         width: 256,
+//!         height: 256,
+//!         depth: 1,
+//!         usage: vk::ImageUsageFlags::SAMPLED,
+//!         format: vk::Format::R8G8B8A8_SRGB,
+//!         samples: vk::SampleCountFlags::TYPE_1,
+//!         mip_levels: 1,
+//!         layers: 1,
+//!         flags: vk::ImageCreateFlags::empty(),
+//!         memory_type: MemoryType::GpuOnly,
+//!     };
+//!     let size = vk::Extent3D { width: 256, height: 256, depth: 1 };
+//!     let levels = [MipLevelData::full(size, 0, pixels)];
+//!     // The image is ready to use as soon as this returns, no command buffer is needed.
+//!     let layout = vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL;
+//!     let image = Image::from_data_host(device, &mut allocator, info, &levels, layout)?;
+//!     // Read back the top left quarter of the image.
+//!     let region = ImageRegion {
+//!         offset: vk::Offset3D::default(),
+//!         extent: vk::Extent3D { width: 128, height: 128, depth: 1 },
+//!     };
+//!     let texels = image.download_host(0, region, layout)?;
+//!     Ok((image, texels))
+//! }
+//! ```
+
+use std::ffi::{c_void, CStr};
+
+use anyhow::{ensure, Result};
+use ash::vk;
+
+use crate::util::compressed::is_block_compressed;
+use crate::util::format::{is_depth_stencil, texel_size};
+use crate::util::upload::{pack_mip_levels, validate_region, MipLevelData};
+use crate::{Allocator, Device, ExtensionID, Image, ImageCreateInfo, ImageRegion, Instance};
+
+/// Image usage flag required for host image copies, `VK_IMAGE_USAGE_HOST_TRANSFER_BIT_EXT`.
+pub const IMAGE_USAGE_HOST_TRANSFER: vk::ImageUsageFlags = vk::ImageUsageFlags::from_raw(0x0040_0000);
+
+/// Format feature flag of formats that support host image copies, `VK_FORMAT_FEATURE_2_HOST_IMAGE_TRANSFER_BIT_EXT`.
+const FORMAT_FEATURE_HOST_IMAGE_TRANSFER: vk::FormatFeatureFlags2 = vk::FormatFeatureFlags2::from_raw(0x0000_4000_0000_0000);
+
+// ash does not provide definitions for this extension yet, so the structures we use are defined here.
+
+const STRUCTURE_TYPE_PHYSICAL_DEVICE_HOST_IMAGE_COPY_FEATURES: vk::StructureType = vk::StructureType::from_raw(1000270000);
+const STRUCTURE_TYPE_PHYSICAL_DEVICE_HOST_IMAGE_COPY_PROPERTIES: vk::StructureType = vk::StructureType::from_raw(1000270001);
+const STRUCTURE_TYPE_MEMORY_TO_IMAGE_COPY: vk::StructureType = vk::StructureType::from_raw(1000270002);
+const STRUCTURE_TYPE_IMAGE_TO_MEMORY_COPY: vk::StructureType = vk::StructureType::from_raw(1000270003);
+const STRUCTURE_TYPE_COPY_IMAGE_TO_MEMORY_INFO: vk::StructureType = vk::StructureType::from_raw(1000270004);
+const STRUCTURE_TYPE_COPY_MEMORY_TO_IMAGE_INFO: vk::StructureType = vk::StructureType::from_raw(1000270005);
+const STRUCTURE_TYPE_HOST_IMAGE_LAYOUT_TRANSITION_INFO: vk::StructureType = vk::StructureType::from_raw(1000270006);
+
+/// `VkPhysicalDeviceHostImageCopyFeaturesEXT`
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct PhysicalDeviceHostImageCopyFeatures {
+    pub s_type: vk::StructureType,
+    pub p_next: *mut c_void,
+    pub host_image_copy: vk::Bool32,
+}
+
+impl Default for PhysicalDeviceHostImageCopyFeatures {
+    fn default() -> Self {
+        Self {
+            s_type: STRUCTURE_TYPE_PHYSICAL_DEVICE_HOST_IMAGE_COPY_FEATURES,
+            p_next: std::ptr::null_mut(),
+            host_image_copy: vk::FALSE,
+        }
+    }
+}
+
+// SAFETY: The structure starts with sType and pNext, and is valid in the pNext chain of VkDeviceCreateInfo.
+unsafe impl vk::ExtendsDeviceCreateInfo for PhysicalDeviceHostImageCopyFeatures {}
+
+// SAFETY: The structure starts with sType and pNext, and is valid in the pNext chain of VkPhysicalDeviceFeatures2.
+unsafe impl vk::ExtendsPhysicalDeviceFeatures2 for PhysicalDeviceHostImageCopyFeatures {}
+
+/// `VkPhysicalDeviceHostImageCopyPropertiesEXT`
+#[repr(C)]
+struct PhysicalDeviceHostImageCopyProperties {
+    s_type: vk::StructureType,
+    p_next: *mut c_void,
+    copy_src_layout_count: u32,
+    p_copy_src_layouts: *mut vk::ImageLayout,
+    copy_dst_layout_count: u32,
+    p_copy_dst_layouts: *mut vk::ImageLayout,
+    optimal_tiling_layout_uuid: [u8; vk::UUID_SIZE],
+    identical_memory_type_requirements: vk::Bool32,
+}
+
+// SAFETY: The structure starts with sType and pNext, and is valid in the pNext chain of VkPhysicalDeviceProperties2.
+unsafe impl vk::ExtendsPhysicalDeviceProperties2 for PhysicalDeviceHostImageCopyProperties {}
+
+/// `VkMemoryToImageCopyEXT`
+#[repr(C)]
+struct MemoryToImageCopy {
+    s_type: vk::StructureType,
+    p_next: *const c_void,
+    p_host_pointer: *const c_void,
+    memory_row_length: u32,
+    memory_image_height: u32,
+    image_subresource: vk::ImageSubresourceLayers,
+    image_offset: vk::Offset3D,
+    image_extent: vk::Extent3D,
+}
+
+/// `VkImageToMemoryCopyEXT`
+#[repr(C)]
+struct ImageToMemoryCopy {
+    s_type: vk::StructureType,
+    p_next: *const c_void,
+    p_host_pointer: *mut c_void,
+    memory_row_length: u32,
+    memory_image_height: u32,
+    image_subresource: vk::ImageSubresourceLayers,
+    image_offset: vk::Offset3D,
+    image_extent: vk::Extent3D,
+}
+
+/// `VkCopyMemoryToImageInfoEXT`
+#[repr(C)]
+struct CopyMemoryToImageInfo {
+    s_type: vk::StructureType,
+    p_next: *const c_void,
+    flags: vk::Flags,
+    dst_image: vk::Image,
+    dst_image_layout: vk::ImageLayout,
+    region_count: u32,
+    p_regions: *const MemoryToImageCopy,
+}
+
+/// `VkCopyImageToMemoryInfoEXT`
+#[repr(C)]
+struct CopyImageToMemoryInfo {
+    s_type: vk::StructureType,
+    p_next: *const c_void,
+    flags: vk::Flags,
+    src_image: vk::Image,
+    src_image_layout: vk::ImageLayout,
+    region_count: u32,
+    p_regions: *const ImageToMemoryCopy,
+}
+
+/// `VkHostImageLayoutTransitionInfoEXT`
+#[repr(C)]
+struct HostImageLayoutTransitionInfo {
+    s_type: vk::StructureType,
+    p_next: *const c_void,
+    image: vk::Image,
+    old_layout: vk::ImageLayout,
+    new_layout: vk::ImageLayout,
+    subresource_range: vk::ImageSubresourceRange,
+}
+
+type PfnCopyMemoryToImage = unsafe extern "system" fn(vk::Device, *const CopyMemoryToImageInfo) -> vk::Result;
+type PfnCopyImageToMemory = unsafe extern "system" fn(vk::Device, *const CopyImageToMemoryInfo) -> vk::Result;
+type PfnTransitionImageLayout =
+    unsafe extern "system" fn(vk::Device, u32, *const HostImageLayoutTransitionInfo) -> vk::Result;
+
+/// Function pointers for `VK_EXT_host_image_copy`.
+#[derive(Clone)]
+pub(crate) struct HostImageCopyFn {
+    copy_memory_to_image: PfnCopyMemoryToImage,
+    copy_image_to_memory: PfnCopyImageToMemory,
+    transition_image_layout: PfnTransitionImageLayout,
+}
+
+impl HostImageCopyFn {
+    pub fn name() -> &'static CStr {
+        c"VK_EXT_host_image_copy"
+    }
+
+    /// Load the function pointers with `f`. Returns `None` if any of them could not be loaded.
+    pub fn load(mut f: impl FnMut(&CStr) -> *const c_void) -> Option<Self> {
+        let mut load = |name: &CStr| {
+            let function = f(name);
+            (!function.is_null()).then_some(function)
+        };
+        // SAFETY: The function pointers were loaded by their name, so they have the signature from the Vulkan headers.
+        unsafe {
+            Some(Self {
+                copy_memory_to_image: std::mem::transmute::<*const c_void, PfnCopyMemoryToImage>(load(
+                    c"vkCopyMemoryToImageEXT",
+                )?),
+                copy_image_to_memory: std::mem::transmute::<*const c_void, PfnCopyImageToMemory>(load(
+                    c"vkCopyImageToMemoryEXT",
+                )?),
+                transition_image_layout: std::mem::transmute::<*const c_void, PfnTransitionImageLayout>(
+                    load(c"vkTransitionImageLayoutEXT")?,
+                ),
+            })
+        }
+    }
+}
+
+/// Query the image layouts that can be used as the destination of host image copies.
+pub(crate) fn query_copy_dst_layouts(instance: &Instance, physical_device: vk::PhysicalDevice) -> Vec<vk::ImageLayout> {
+    let mut properties = PhysicalDeviceHostImageCopyProperties {
+        s_type: STRUCTURE_TYPE_PHYSICAL_DEVICE_HOST_IMAGE_COPY_PROPERTIES,
+        p_next: std::ptr::null_mut(),
+        copy_src_layout_count: 0,
+        p_copy_src_layouts: std::ptr::null_mut(),
+        copy_dst_layout_count: 0,
+        p_copy_dst_layouts: std::ptr::null_mut(),
+        optimal_tiling_layout_uuid: [0; vk::UUID_SIZE],
+        identical_memory_type_requirements: vk::FALSE,
+    };
+    // SAFETY: Vulkan API call. The first call only writes the layout counts, the second one fills `layouts`, which is large
+    // enough to hold them.
+    unsafe {
+        let mut query = vk::PhysicalDeviceProperties2::builder().push_next(&mut properties);
+        instance.get_physical_device_properties2(physical_device, &mut query);
+        let mut layouts = vec![vk::ImageLayout::UNDEFINED; properties.copy_dst_layout_count as usize];
+        properties.copy_src_layout_count = 0;
+        properties.p_copy_dst_layouts = layouts.as_mut_ptr();
+        let mut query = vk::PhysicalDeviceProperties2::builder().push_next(&mut properties);
+        instance.get_physical_device_properties2(physical_device, &mut query);
+        layouts.truncate(properties.copy_dst_layout_count as usize);
+        layouts
+    }
+}
+
+/// Whether an image with `format` can be uploaded with host copies, and transitioned to `layout` for the copy.
+pub(crate) fn supports_host_upload(device: &Device, format: vk::Format, layout: vk::ImageLayout) -> bool {
+    device.is_extension_enabled(ExtensionID::HostImageCopy)
+        && host_copy_texel_size(format).is_ok()
+        && device
+            .format_features2(format)
+            .optimal_tiling_features
+            .contains(FORMAT_FEATURE_HOST_IMAGE_TRANSFER)
+        && device.host_image_copy_dst_layouts().contains(&layout)
+}
+
+/// Get the size of a texel of an image used for host copies.
+/// # Errors
+/// * Fails if `format` is block-compressed, has a depth or stencil aspect, or is not a known uncompressed color format.
+fn host_copy_texel_size(format: vk::Format) -> Result<u32> {
+    ensure!(
+        !is_block_compressed(format) && !is_depth_stencil(format),
+        "Host image copies only support uncompressed color formats, got {format:?}"
+    );
+    texel_size(format).ok_or_else(|| anyhow::anyhow!("Unknown texel size of format {format:?} for host image copies"))
+}
+
+/// Get the function pointers for host image copies.
+/// # Errors
+/// * Fails if [`ExtensionID::HostImageCopy`] is not enabled.
+fn host_image_copy_fns(device: &Device) -> Result<&HostImageCopyFn> {
+    device.require_extension(ExtensionID::HostImageCopy)?;
+    Ok(device.host_image_copy().unwrap())
+}
+
+/// Size in bytes of `extent` texels in `layers` array layers.
+fn region_size(extent: vk::Extent3D, layers: u32, texel_size: u32) -> usize {
+    extent.width as usize * extent.height as usize * extent.depth as usize * layers as usize * texel_size as usize
+}
+
+impl<A: Allocator> Image<A> {
+    /// Create an image and upload regions of its mip levels from the host, without a staging buffer or command buffer.
+    /// The image is transitioned to `layout` on the host, and is ready to use when this returns. Mip levels that are not uploaded
+    /// are left undefined. [`IMAGE_USAGE_HOST_TRANSFER`] is always added to `info.usage`.
+    /// # Errors
+    /// * Fails if [`ExtensionID::HostImageCopy`] is not enabled.
+    /// * Fails if the format of the image is not an uncompressed color format.
+    /// * Fails if `levels` is empty, a region is outside of its mip level, or the data of a region does not have the size of the region.
+    /// * Fails if creating the image or the host copy fails.
+    pub fn from_data_host(
+        device: Device,
+        allocator: &mut A,
+        mut info: ImageCreateInfo,
+        levels: &[MipLevelData],
+        layout: vk::ImageLayout,
+    ) -> Result<Self> {
+        ensure!(!levels.is_empty(), "Cannot create an image from empty data");
+        device.require_extension(ExtensionID::HostImageCopy)?;
+        host_copy_texel_size(info.format)?;
+        info.usage |= IMAGE_USAGE_HOST_TRANSFER;
+        let image = Image::new(device, allocator, info)?;
+        image.transition_layout_host(vk::ImageLayout::UNDEFINED, layout)?;
+        image.upload_host(levels, layout)?;
+        Ok(image)
+    }
+
+    /// Transition all subresources of this image from `old_layout` to `new_layout` on the host. The image must have been
+    /// created with [`IMAGE_USAGE_HOST_TRANSFER`], and must not be in use by the GPU.
+    /// # Errors
+    /// * Fails if [`ExtensionID::HostImageCopy`] is not enabled.
+    /// * Fails if the layout transition fails.
+    pub fn transition_layout_host(&self, old_layout: vk::ImageLayout, new_layout: vk::ImageLayout) -> Result<()> {
+        let fns = host_image_copy_fns(self.device())?;
+        let transition = HostImageLayoutTransitionInfo {
+            s_type: STRUCTURE_TYPE_HOST_IMAGE_LAYOUT_TRANSITION_INFO,
+            p_next: std::ptr::null(),
+            // SAFETY: The image handle is valid as long as self is.
+            image: unsafe { self.handle() },
+            old_layout,
+            new_layout,
+            subresource_range: vk::ImageSubresourceRange {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                base_mip_level: 0,
+                level_count: self.mip_levels(),
+                base_array_layer: 0,
+                layer_count: self.layers(),
+            },
+        };
+        // SAFETY: Vulkan API call. The transition info is valid for the duration of this call.
+        unsafe { (fns.transition_image_layout)(self.device().handle().handle(), 1, &transition).result()? };
+        Ok(())
+    }
+
+    /// Upload regions of mip levels of this image from the host, covering all array layers. The image must have been created with
+    /// [`IMAGE_USAGE_HOST_TRANSFER`], must be in `layout`, and must not be in use by the GPU.
+    /// # Errors
+    /// * Fails if [`ExtensionID::HostImageCopy`] is not enabled.
+    /// * Fails if the format of the image is not an uncompressed color format.
+    /// * Fails if a region is outside of its mip level, or the data of a region does not have the size of the region.
+    /// * Fails if the host copy fails.
+    pub fn upload_host(&self, levels: &[MipLevelData], layout: vk::ImageLayout) -> Result<()> {
+        let fns = host_image_copy_fns(self.device())?;
+        host_copy_texel_size(self.format())?;
+        // This also checks that the data of every region has the size of the region.
+        pack_mip_levels(self.size(), self.format(), self.layers(), 0, self.mip_levels(), levels)?;
+        let copies = levels
+            .iter()
+            .map(|level| MemoryToImageCopy {
+                s_type: STRUCTURE_TYPE_MEMORY_TO_IMAGE_COPY,
+                p_next: std::ptr::null(),
+                p_host_pointer: level.data.as_ptr().cast(),
+                memory_row_length: 0,
+                memory_image_height: 0,
+                image_subresource: vk::ImageSubresourceLayers {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    mip_level: level.mip_level,
+                    base_array_layer: 0,
+                    layer_count: self.layers(),
+                },
+                image_offset: level.region.offset,
+                image_extent: level.region.extent,
+            })
+            .collect::<Vec<_>>();
+        let info = CopyMemoryToImageInfo {
+            s_type: STRUCTURE_TYPE_COPY_MEMORY_TO_IMAGE_INFO,
+            p_next: std::ptr::null(),
+            flags: 0,
+            // SAFETY: The image handle is valid as long as self is.
+            dst_image: unsafe { self.handle() },
+            dst_image_layout: layout,
+            region_count: copies.len() as u32,
+            p_regions: copies.as_ptr(),
+        };
+        // SAFETY: Vulkan API call. The regions were validated above, so every copy reads inside its slice of data.
+        unsafe { (fns.copy_memory_to_image)(self.device().handle().handle(), &info).result()? };
+        Ok(())
+    }
+
+    /// Download a region of a mip level of this image to the host, covering all array layers. Texels are tightly packed in the
+    /// result. The image must have been created with [`IMAGE_USAGE_HOST_TRANSFER`], must be in `layout`, and all writes to it
+    /// by the GPU must have completed.
+    /// # Errors
+    /// * Fails if [`ExtensionID::HostImageCopy`] is not enabled.
+    /// * Fails if the format of the image is not an uncompressed color format.
+    /// * Fails if the region is outside of the mip level.
+    /// * Fails if the host copy fails.
+    pub fn download_host(&self, mip_level: u32, region: ImageRegion, layout: vk::ImageLayout) -> Result<Vec<u8>> {
+        let fns = host_image_copy_fns(self.device())?;
+        let texel_size = host_copy_texel_size(self.format())?;
+        validate_region(self.size(), self.format(), 0, self.mip_levels(), mip_level, &region)?;
+        let mut data = vec![0u8; region_size(region.extent, self.layers(), texel_size)];
+        let copy = ImageToMemoryCopy {
+            s_type: STRUCTURE_TYPE_IMAGE_TO_MEMORY_COPY,
+            p_next: std::ptr::null(),
+            p_host_pointer: data.as_mut_ptr().cast(),
+            memory_row_length: 0,
+            memory_image_height: 0,
+            image_subresource: vk::ImageSubresourceLayers {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                mip_level,
+                base_array_layer: 0,
+                layer_count: self.layers(),
+            },
+            image_offset: region.offset,
+            image_extent: region.extent,
+        };
+        let info = CopyImageToMemoryInfo {
+            s_type: STRUCTURE_TYPE_COPY_IMAGE_TO_MEMORY_INFO,
+            p_next: std::ptr::null(),
+            flags: 0,
+            // SAFETY: The image handle is valid as long as self is.
+            src_image: unsafe { self.handle() },
+            src_image_layout: layout,
+            region_count: 1,
+            p_regions: &copy,
+        };
+        // SAFETY: Vulkan API call. `data` is large enough to hold the region in all layers.
+        unsafe { (fns.copy_image_to_memory)(self.device().handle().handle(), &info).result()? };
+        Ok(data)
+    }
+}
@@ -0,0 +1,192 @@
+//! Generate the mip chain of an image by repeatedly blitting each mip level to the next one.
+//!
+//! [`generate_mipmaps()`] records the blits into a command buffer, together with the barriers between each level. The base
+//! level of the image view must contain the image data, and the contents of all other levels are overwritten. Afterwards, all
+//! mip levels of the view are in the requested layout.
+//!
+//! Inside a [`PassGraph`](crate::PassGraph), use [`PassBuilder::generate_mipmaps()`](crate::PassBuilder::generate_mipmaps) instead,
+//! which declares the required image layouts so the graph can insert the surrounding barriers.
+//!
+//! The image must be created with `TRANSFER_SRC` and `TRANSFER_DST` usage, and its format must support blits. If `filter` is
+//! [`vk::Filter::LINEAR`], the format must also support linear filtering.
+//! # Example
+//! ```
+//! # use anyhow::Result;
+//! # use phobos::prelude::*;
+//! # use phobos::sync::domain::ExecutionDomain;
+//! fn mipmapped_texture<'q, D: ExecutionDomain + GfxSupport>(
+//!     cmd: IncompleteCommandBuffer<'q, D>,
+//!     texture: &ImageView,
+//! ) -> Result<IncompleteCommandBuffer<'q, D>> {
+//!     // The base level was just uploaded, so it is in TRANSFER_DST_OPTIMAL.
+//!     generate_mipmaps(
+//!         cmd,
+//!         texture,
+//!         vk::Filter::LINEAR,
+//!         vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+//!         vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+//!     )
+//! }
+//! ```
+
+use anyhow::{ensure, Result};
+use ash::vk;
+
+use crate::command_buffer::IncompleteCommandBuffer;
+use crate::sync::domain::ExecutionDomain;
+use crate::traits::GfxSupport;
+use crate::util::upload::mip_level_size;
+use crate::{Allocator, ImageView, PipelineStage};
+
+/// Build an image barrier on a range of mip levels of `image`, relative to the base level of the view. The barrier covers all
+/// layers of the view.
+fn level_barrier(
+    image: &ImageView,
+    levels: std::ops::Range<u32>,
+    old_layout: vk::ImageLayout,
+    new_layout: vk::ImageLayout,
+    src: (PipelineStage, vk::AccessFlags2),
+    dst: (PipelineStage, vk::AccessFlags2),
+) -> vk::ImageMemoryBarrier2 {
+    vk::ImageMemoryBarrier2 {
+        src_stage_mask: src.0,
+        src_access_mask: src.1,
+        dst_stage_mask: dst.0,
+        dst_access_mask: dst.1,
+        old_layout,
+        new_layout,
+        src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        // SAFETY: A valid image view object has a valid `VkImage` handle.
+        image: unsafe { image.image() },
+        subresource_range: vk::ImageSubresourceRange {
+            base_mip_level: image.base_level() + levels.start,
+            level_count: levels.end - levels.start,
+            ..image.subresource_range()
+        },
+        ..Default::default()
+    }
+}
+
+/// Get the far corner of a mip level, as used by [`VkImageBlit`](vk::ImageBlit).
+fn level_corner(size: vk::Extent3D) -> vk::Offset3D {
+    vk::Offset3D {
+        x: size.width as i32,
+        y: size.height as i32,
+        z: size.depth as i32,
+    }
+}
+
+/// Fill all mip levels of `image` after its base level by blitting each level to the next one with `filter`.
+/// The base level must be in `layout`, and the contents of all other levels are discarded. Afterwards, all mip levels of the
+/// view are in `final_layout`. This waits for all previous writes to the image, and makes the results available to all
+/// later commands.
+///
+/// If the view has a single mip level, this only transitions it to `final_layout`.
+/// See the [module-level documentation](self) for more information.
+/// # Errors
+/// * Fails if `filter` is not [`vk::Filter::NEAREST`] for a view without a color aspect.
+pub fn generate_mipmaps<'q, D: ExecutionDomain + GfxSupport, A: Allocator>(
+    cmd: IncompleteCommandBuffer<'q, D, A>,
+    image: &ImageView,
+    filter: vk::Filter,
+    layout: vk::ImageLayout,
+    final_layout: vk::ImageLayout,
+) -> Result<IncompleteCommandBuffer<'q, D, A>> {
+    ensure!(
+        filter == vk::Filter::NEAREST || image.aspect().contains(vk::ImageAspectFlags::COLOR),
+        "Mipmaps of depth and stencil images can only be generated with nearest filtering"
+    );
+    let level_count = image.level_count();
+    let all_writes = (PipelineStage::ALL_COMMANDS, vk::AccessFlags2::MEMORY_WRITE);
+    let transfer_read = (PipelineStage::BLIT, vk::AccessFlags2::TRANSFER_READ);
+    let transfer_write = (PipelineStage::BLIT, vk::AccessFlags2::TRANSFER_WRITE);
+    let all_access = (
+        PipelineStage::ALL_COMMANDS,
+        vk::AccessFlags2::MEMORY_READ | vk::AccessFlags2::MEMORY_WRITE,
+    );
+
+    if level_count == 1 {
+        let barrier = level_barrier(image, 0..1, layout, final_layout, all_writes, all_access);
+        return Ok(cmd.pipeline_barrier(&vk::DependencyInfo {
+            image_memory_barrier_count: 1,
+            p_image_memory_barriers: &barrier,
+            ..Default::default()
+        }));
+    }
+
+    let start = [
+        level_barrier(image, 0..1, layout, vk::ImageLayout::TRANSFER_SRC_OPTIMAL, all_writes, transfer_read),
+        level_barrier(
+            image,
+            1..level_count,
+            vk::ImageLayout::UNDEFINED,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            all_writes,
+            transfer_write,
+        ),
+    ];
+    let mut cmd = cmd.pipeline_barrier(&vk::DependencyInfo {
+        image_memory_barrier_count: start.len() as u32,
+        p_image_memory_barriers: start.as_ptr(),
+        ..Default::default()
+    });
+
+    let base_size = image.base_level_size();
+    let device = cmd.device().clone();
+    let range = image.subresource_range();
+    for level in 1..level_count {
+        let layers = |mip_level| vk::ImageSubresourceLayers {
+            aspect_mask: range.aspect_mask,
+            mip_level: range.base_mip_level + mip_level,
+            base_array_layer: range.base_array_layer,
+            layer_count: range.layer_count,
+        };
+        let blit = vk::ImageBlit {
+            src_subresource: layers(level - 1),
+            src_offsets: [vk::Offset3D::default(), level_corner(mip_level_size(base_size, level - 1))],
+            dst_subresource: layers(level),
+            dst_offsets: [vk::Offset3D::default(), level_corner(mip_level_size(base_size, level))],
+        };
+        // SAFETY: The command buffer is recording, and the image view is valid for the duration of this call.
+        unsafe {
+            device.cmd_blit_image(
+                cmd.handle(),
+                image.image(),
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                image.image(),
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                std::slice::from_ref(&blit),
+                filter,
+            );
+        }
+        // The level that was just written is the source of the next blit.
+        let barrier = level_barrier(
+            image,
+            level..level + 1,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            transfer_write,
+            transfer_read,
+        );
+        cmd = cmd.pipeline_barrier(&vk::DependencyInfo {
+            image_memory_barrier_count: 1,
+            p_image_memory_barriers: &barrier,
+            ..Default::default()
+        });
+    }
+
+    let barrier = level_barrier(
+        image,
+        0..level_count,
+        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+        final_layout,
+        transfer_write,
+        all_access,
+    );
+    Ok(cmd.pipeline_barrier(&vk::DependencyInfo {
+        image_memory_barrier_count: 1,
+        p_image_memory_barriers: &barrier,
+        ..Default::default()
+    }))
+}
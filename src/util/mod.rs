@@ -2,11 +2,20 @@
 
 pub mod byte_size;
 pub mod deferred_delete;
+pub mod device_size;
 
 pub mod address;
 pub mod align;
 pub(crate) mod cache;
+pub mod compressed;
+pub(crate) mod format;
+pub mod host_image_copy;
+pub mod mipmaps;
 pub(crate) mod pnext;
+pub mod readback;
 pub(crate) mod string;
 pub mod to_vk;
 pub mod transform;
+pub mod upload;
+pub mod upload_manager;
+pub(crate) mod worker_pool;
@@ -0,0 +1,844 @@
+//! Queue for reading back images and buffers from the GPU, with explicit handling of failed submissions and device loss.
+//!
+//! Long-running tools such as capture utilities cannot assume every readback succeeds. The [`ReadbackQueue`] owns the staging
+//! memory of every in-flight readback, so when a submission fails or the device is lost, the staging buffer and command buffer
+//! are released immediately and the failure is reported as [`Error::DeviceLost`] instead of a panic or a leak.
+//!
+//! If retrying is enabled with [`ReadbackQueue::retry_on_device_lost()`], readbacks that failed because of device loss are kept.
+//! Once the application has recreated its device and resources, [`ReadbackQueue::reinitialize()`] submits them again.
+//!
+//! To read back an image at a specific point in a frame, such as a screenshot of the swapchain image, use [`readback_image()`]
+//! instead. This records the copy into an existing command buffer and returns a [`FrameReadback`] future that resolves to RGBA8 pixels.
+//!
+//! For one-off readbacks, such as GPU picking or reading back a histogram, [`readback_buffer()`] and [`readback_image_region()`]
+//! submit the copy and return a [`GpuFuture`] that resolves to the raw bytes once the copy's fence is signaled.
+//! # Example
+//! ```
+//! # use anyhow::Result;
+//! # use phobos::prelude::*;
+//! fn capture(queue: &mut ReadbackQueue, image: &ImageView) -> Result<()> {
+//!     // An image that was just rendered to.
+//!     let id = queue.request(ReadbackSource::Image {
+//!         view: image.clone(),
+//!         layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+//!     })?;
+//!     println!("Submitted readback {id:?}");
+//!     // Later, for example once per frame:
+//!     for readback in queue.poll() {
+//!         match readback.result {
+//!             Ok(data) => println!("Readback {:?} finished with {} bytes", readback.id, data.len()),
+//!             Err(Error::DeviceLost) => println!("Device lost, readback {:?} is waiting for a retry", readback.id),
+//!             Err(e) => println!("Readback {:?} failed: {e}", readback.id),
+//!         }
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use anyhow::Result;
+use ash::prelude::VkResult;
+use ash::vk;
+
+use crate::{
+    Allocator, Buffer, BufferView, DefaultAllocator, Device, Error, ExecutionManager, GpuFuture, ImageRegion, ImageView,
+    MemoryType, PipelineStage,
+};
+use crate::command_buffer::IncompleteCommandBuffer;
+use crate::command_buffer::traits::*;
+use crate::core::queue::DeviceQueue;
+use crate::sync::domain::ExecutionDomain;
+use crate::pool::Pooled;
+use crate::sync::domain;
+use crate::sync::fence::Fence;
+use crate::util::format::aspect_texel_size;
+
+/// Identifies a readback request in a [`ReadbackQueue`]. A request keeps its id when it is retried.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ReadbackId(u64);
+
+/// Resource to read back from.
+#[derive(Debug, Clone)]
+pub enum ReadbackSource {
+    /// The base mip level of an image view. The image is transitioned to [`vk::ImageLayout::TRANSFER_SRC_OPTIMAL`] for the copy,
+    /// and back to `layout` afterwards. Texels are tightly packed in the result, their size is derived from the format and
+    /// aspect of the view.
+    Image {
+        /// The image view to read back. All layers of the view are read.
+        view: ImageView,
+        /// Layout the image is in when the readback is submitted.
+        layout: vk::ImageLayout,
+    },
+    /// The full range of a buffer view.
+    Buffer(BufferView),
+}
+
+impl ReadbackSource {
+    /// Size of the staging buffer needed to read back this source.
+    /// # Errors
+    /// * Fails if the image format or aspect cannot be copied to a buffer.
+    fn size(&self) -> Result<vk::DeviceSize> {
+        match self {
+            ReadbackSource::Image {
+                view,
+                ..
+            } => {
+                let texel_size = aspect_texel_size(view.format(), view.aspect()).ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Cannot read back aspect {:?} of images with format {:?}",
+                        view.aspect(),
+                        view.format()
+                    )
+                })?;
+                let extent = view.base_level_size();
+                Ok(extent.width as vk::DeviceSize
+                    * extent.height as vk::DeviceSize
+                    * extent.depth as vk::DeviceSize
+                    * view.subresource_range().layer_count as vk::DeviceSize
+                    * texel_size as vk::DeviceSize)
+            }
+            ReadbackSource::Buffer(buffer) => Ok(buffer.size()),
+        }
+    }
+}
+
+/// A readback that finished, either successfully or with an error. Returned from [`ReadbackQueue::poll()`].
+#[derive(Debug)]
+pub struct CompletedReadback {
+    /// Id of the request, as returned from [`ReadbackQueue::request()`].
+    pub id: ReadbackId,
+    /// The data that was read back, or the reason the readback failed. If this is [`Error::DeviceLost`] and retrying is enabled,
+    /// the request is kept for [`ReadbackQueue::reinitialize()`].
+    pub result: Result<Vec<u8>, Error>,
+}
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+struct PendingReadback<A: Allocator> {
+    id: ReadbackId,
+    source: ReadbackSource,
+    staging: Buffer<A>,
+    #[derivative(Debug = "ignore")]
+    fence: Pooled<Fence>,
+}
+
+/// Submits readbacks on the graphics domain and tracks them until they complete. See the [module-level documentation](self).
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct ReadbackQueue<A: Allocator = DefaultAllocator> {
+    #[derivative(Debug = "ignore")]
+    device: Device,
+    #[derivative(Debug = "ignore")]
+    exec: ExecutionManager<A>,
+    #[derivative(Debug = "ignore")]
+    allocator: A,
+    next_id: u64,
+    pending: Vec<PendingReadback<A>>,
+    lost: Vec<(ReadbackId, ReadbackSource)>,
+    retry_on_device_lost: bool,
+}
+
+/// Check whether an error was caused by losing the device.
+fn is_device_lost(error: &anyhow::Error) -> bool {
+    matches!(error.downcast_ref::<vk::Result>(), Some(&vk::Result::ERROR_DEVICE_LOST))
+        || matches!(error.downcast_ref::<Error>(), Some(Error::VkError(vk::Result::ERROR_DEVICE_LOST) | Error::DeviceLost))
+}
+
+impl<A: Allocator + 'static> ReadbackQueue<A> {
+    /// Create a new, empty readback queue. Retrying is disabled by default.
+    pub fn new(device: Device, exec: ExecutionManager<A>, allocator: A) -> Self {
+        Self {
+            device,
+            exec,
+            allocator,
+            next_id: 0,
+            pending: vec![],
+            lost: vec![],
+            retry_on_device_lost: false,
+        }
+    }
+
+    /// Keep readbacks that failed because the device was lost, so they can be submitted again with
+    /// [`ReadbackQueue::reinitialize()`]. Without this, such readbacks are dropped after reporting the error.
+    pub fn retry_on_device_lost(mut self, retry: bool) -> Self {
+        self.retry_on_device_lost = retry;
+        self
+    }
+
+    /// Submit a new readback. The result is reported by [`ReadbackQueue::poll()`] once the copy has finished.
+    /// # Errors
+    /// * Fails with [`Error::DeviceLost`] if the device was lost while submitting. The staging buffer is released, and the request
+    ///   is kept for a retry if this is enabled.
+    /// * Fails if the format of an image source cannot be copied to a buffer.
+    /// * Fails if allocating the staging buffer, recording or submitting the copy fails. The staging buffer is released.
+    pub fn request(&mut self, source: ReadbackSource) -> Result<ReadbackId> {
+        let id = ReadbackId(self.next_id);
+        self.next_id += 1;
+        self.submit(id, source)?;
+        Ok(id)
+    }
+
+    fn submit(&mut self, id: ReadbackId, source: ReadbackSource) -> Result<()> {
+        match self.try_submit(&source) {
+            Ok((staging, fence)) => {
+                self.pending.push(PendingReadback {
+                    id,
+                    source,
+                    staging,
+                    fence,
+                });
+                Ok(())
+            }
+            Err(e) if is_device_lost(&e) => {
+                if self.retry_on_device_lost {
+                    self.lost.push((id, source));
+                }
+                Err(Error::DeviceLost.into())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn try_submit(&mut self, source: &ReadbackSource) -> Result<(Buffer<A>, Pooled<Fence>)> {
+        let staging = Buffer::new(self.device.clone(), &mut self.allocator, source.size()?, MemoryType::GpuToCpu)?;
+        let dst = staging.view_full();
+        let cmd = self.exec.on_domain::<domain::Graphics>()?;
+        let cmd = match source {
+            ReadbackSource::Image {
+                view,
+                layout,
+                ..
+            } => cmd
+                .transition_image(
+                    view,
+                    PipelineStage::ALL_COMMANDS,
+                    PipelineStage::TRANSFER,
+                    *layout,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    vk::AccessFlags2::MEMORY_WRITE,
+                    vk::AccessFlags2::TRANSFER_READ,
+                )
+                .copy_image_to_buffer(view, &dst)?
+                .transition_image(
+                    view,
+                    PipelineStage::TRANSFER,
+                    PipelineStage::ALL_COMMANDS,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    *layout,
+                    vk::AccessFlags2::NONE,
+                    vk::AccessFlags2::NONE,
+                ),
+            ReadbackSource::Buffer(buffer) => cmd
+                .memory_barrier(
+                    PipelineStage::ALL_COMMANDS,
+                    vk::AccessFlags2::MEMORY_WRITE,
+                    PipelineStage::TRANSFER,
+                    vk::AccessFlags2::TRANSFER_READ,
+                )
+                .copy_buffer(buffer, &dst)?,
+        };
+        let cmd = cmd
+            .memory_barrier(
+                PipelineStage::TRANSFER,
+                vk::AccessFlags2::TRANSFER_WRITE,
+                PipelineStage::HOST,
+                vk::AccessFlags2::HOST_READ,
+            )
+            .finish()?;
+        let fence = self.exec.submit(cmd)?;
+        Ok((staging, fence))
+    }
+
+    /// Check all pending readbacks without blocking, and return the ones that finished. Failed readbacks release their staging
+    /// buffer before they are returned.
+    pub fn poll(&mut self) -> Vec<CompletedReadback> {
+        let mut completed = Vec::new();
+        let mut pending = Vec::with_capacity(self.pending.len());
+        for mut readback in self.pending.drain(..) {
+            let result = match readback.fence.poll_status() {
+                Ok(false) => {
+                    pending.push(readback);
+                    continue;
+                }
+                // Waiting on a signaled fence returns immediately, this runs the cleanup that deletes the command buffer.
+                Ok(true) => readback
+                    .fence
+                    .wait()
+                    .and_then(|_| Ok(readback.staging.view_full().mapped_slice::<u8>()?.to_vec()))
+                    .map_err(|e| {
+                        if is_device_lost(&e) {
+                            Error::DeviceLost
+                        } else {
+                            Error::Uncategorized("Failed to read staging buffer.")
+                        }
+                    }),
+                Err(e) => {
+                    // The wait fails immediately as well, but still releases the command buffer.
+                    let _ = readback.fence.wait();
+                    Err(if e == vk::Result::ERROR_DEVICE_LOST {
+                        Error::DeviceLost
+                    } else {
+                        Error::VkError(e)
+                    })
+                }
+            };
+            if matches!(result, Err(Error::DeviceLost)) && self.retry_on_device_lost {
+                self.lost.push((readback.id, readback.source.clone()));
+            }
+            completed.push(CompletedReadback {
+                id: readback.id,
+                result,
+            });
+        }
+        self.pending = pending;
+        completed
+    }
+
+    /// Get the number of readbacks that are still executing.
+    pub fn num_pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Get all readbacks that failed because the device was lost, and are waiting to be retried.
+    pub fn lost_requests(&self) -> impl Iterator<Item = (ReadbackId, &ReadbackSource)> {
+        self.lost.iter().map(|(id, source)| (*id, source))
+    }
+
+    /// Continue on a newly created device after the previous one was lost. Readbacks that are still pending on the old device
+    /// are abandoned. If retrying is enabled, every lost readback is then submitted again with the same id. Since the resources
+    /// of the old device are no longer usable, `remap` is called to obtain the equivalent source on the new device. Returning
+    /// `None` drops the request.
+    /// # Errors
+    /// * Fails if submitting a readback fails. The failed readback and the ones that were not submitted yet are kept for the next
+    ///   call, unless it failed because the device was lost again and retrying is disabled.
+    pub fn reinitialize(
+        &mut self,
+        device: Device,
+        exec: ExecutionManager<A>,
+        allocator: A,
+        mut remap: impl FnMut(ReadbackId, &ReadbackSource) -> Option<ReadbackSource>,
+    ) -> Result<()> {
+        for mut readback in self.pending.drain(..) {
+            let _ = readback.fence.wait();
+            if self.retry_on_device_lost {
+                self.lost.push((readback.id, readback.source));
+            }
+        }
+        self.device = device;
+        self.exec = exec;
+        self.allocator = allocator;
+
+        let mut lost = std::mem::take(&mut self.lost).into_iter();
+        while let Some((id, source)) = lost.next() {
+            let Some(remapped) = remap(id, &source) else { continue; };
+            if let Err(e) = self.submit(id, remapped) {
+                // A lost device already queued this request again if retrying is enabled. Other errors keep the original
+                // request, so it is remapped again on the next call.
+                if !is_device_lost(&e) {
+                    self.lost.push((id, source));
+                }
+                self.lost.extend(lost);
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Make the results of the copy into `staging` visible to the host, submit `cmd` and return a future that resolves to the contents
+/// of `staging` once it has executed.
+fn submit_readback<D: ExecutionDomain + 'static, A: Allocator + 'static>(
+    exec: &ExecutionManager<A>,
+    cmd: IncompleteCommandBuffer<'_, D, A>,
+    staging: Buffer<A>,
+) -> Result<GpuFuture<Vec<u8>>> {
+    let cmd = cmd
+        .memory_barrier(
+            PipelineStage::TRANSFER,
+            vk::AccessFlags2::TRANSFER_WRITE,
+            PipelineStage::HOST,
+            vk::AccessFlags2::HOST_READ,
+        )
+        .finish()?;
+    exec.submit_with_value(cmd, move || {
+        // The staging buffer is always allocated in host visible memory.
+        staging
+            .view_full()
+            .mapped_slice::<u8>()
+            .map(|data| data.to_vec())
+            .unwrap_or_default()
+    })
+}
+
+/// Record a copy of `buffer` at the end of `cmd` and submit it. Returns a future that resolves to the contents of the buffer once
+/// the copy has executed. Writes to the buffer by earlier commands and submissions are made visible to the copy.
+///
+/// The copy executes on the queue of `cmd`. Buffers created by phobos are shared between all queue families when the device has
+/// more than one queue, so they can be read back on any domain that supports transfer operations.
+/// # Errors
+/// * Fails if allocating the staging buffer fails.
+/// * Fails if submitting the command buffer fails.
+/// # Example
+/// ```
+/// # use anyhow::Result;
+/// # use phobos::prelude::*;
+/// # use phobos::util::readback::readback_buffer;
+/// async fn read_histogram(exec: ExecutionManager, mut allocator: DefaultAllocator, histogram: &BufferView) -> Result<Vec<u32>> {
+///     let cmd = exec.on_domain::<domain::Transfer>()?;
+///     let data = readback_buffer(&exec, cmd, &mut allocator, histogram)?.await.unwrap();
+///     Ok(data.chunks_exact(4).map(|bin| u32::from_le_bytes([bin[0], bin[1], bin[2], bin[3]])).collect())
+/// }
+/// ```
+pub fn readback_buffer<D: TransferSupport + ExecutionDomain + 'static, A: Allocator + 'static>(
+    exec: &ExecutionManager<A>,
+    cmd: IncompleteCommandBuffer<'_, D, A>,
+    allocator: &mut A,
+    buffer: &BufferView,
+) -> Result<GpuFuture<Vec<u8>>> {
+    let staging = Buffer::new(cmd.device().clone(), allocator, buffer.size(), MemoryType::GpuToCpu)?;
+    let cmd = cmd
+        .memory_barrier(
+            PipelineStage::ALL_COMMANDS,
+            vk::AccessFlags2::MEMORY_WRITE,
+            PipelineStage::TRANSFER,
+            vk::AccessFlags2::TRANSFER_READ,
+        )
+        .copy_buffer(buffer, &staging.view_full())?;
+    submit_readback(exec, cmd, staging)
+}
+
+/// Record a copy of a region of the base mip level of `image` at the end of `cmd` and submit it. Returns a future that resolves
+/// to the texels in the region once the copy has executed. Texels are tightly packed, with the data for all layers of the view
+/// after each other.
+///
+/// `layout` is the layout the image is in at the end of `cmd`. The image is transitioned to [`vk::ImageLayout::TRANSFER_SRC_OPTIMAL`]
+/// for the copy, and back to `layout` afterwards. The image must have been created with [`vk::ImageUsageFlags::TRANSFER_SRC`].
+///
+/// The copy executes on the queue of `cmd`. Sampled and storage images created by phobos are shared between all queue families
+/// when the device has more than one queue, but attachments and swapchain images have [`vk::SharingMode::EXCLUSIVE`] and are owned
+/// by a single queue family, `owner_family`, usually the family of [`domain::Graphics`]. If `cmd` is on another queue family,
+/// the image is acquired from `owner_family` before the copy and released back to it afterwards. The owning queue must then
+/// release the image first with [`IncompleteCommandBuffer::release_image()`], with `layout` and
+/// [`vk::ImageLayout::TRANSFER_SRC_OPTIMAL`] as layouts, and acquire it again after the readback with
+/// [`IncompleteCommandBuffer::acquire_image()`], with [`vk::ImageLayout::TRANSFER_SRC_OPTIMAL`] and `layout` as layouts.
+/// Nothing extra is recorded if the image is shared, or `cmd` is on `owner_family`.
+///
+/// The size of a texel is derived from the format and aspect of `image`. Depth and stencil views must have a single aspect.
+/// # Errors
+/// * Fails with [`Error::ImageRegionOutOfRange`] if the region is not inside the base mip level of the image.
+/// * Fails if the format or aspect of `image` cannot be read back, for example because it is block-compressed.
+/// * Fails if allocating the staging buffer fails.
+/// * Fails if submitting the command buffer fails.
+/// # Example
+/// ```
+/// # use anyhow::Result;
+/// # use phobos::prelude::*;
+/// # use phobos::util::readback::readback_image_region;
+/// // Read the object id under the cursor from an R32_UINT id buffer.
+/// async fn pick(exec: ExecutionManager, mut allocator: DefaultAllocator, ids: &ImageView, x: i32, y: i32) -> Result<u32> {
+///     let region = ImageRegion {
+///         offset: vk::Offset3D { x, y, z: 0 },
+///         extent: vk::Extent3D { width: 1, height: 1, depth: 1 },
+///     };
+///     let cmd = exec.on_domain::<domain::Graphics>()?;
+///     // The id buffer is an attachment, so it is owned by the graphics queue the copy is recorded on.
+///     let owner = cmd.queue_family();
+///     let data = readback_image_region(&exec, cmd, &mut allocator, ids, region, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL, owner)?
+///         .await
+///         .unwrap();
+///     Ok(u32::from_le_bytes([data[0], data[1], data[2], data[3]]))
+/// }
+/// ```
+pub fn readback_image_region<D: TransferSupport + ExecutionDomain + 'static, A: Allocator + 'static>(
+    exec: &ExecutionManager<A>,
+    cmd: IncompleteCommandBuffer<'_, D, A>,
+    allocator: &mut A,
+    image: &ImageView,
+    region: ImageRegion,
+    layout: vk::ImageLayout,
+    owner_family: u32,
+) -> Result<GpuFuture<Vec<u8>>> {
+    region.validate(image)?;
+    let texel_size = aspect_texel_size(image.format(), image.aspect()).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Cannot read back aspect {:?} of images with format {:?}",
+            image.aspect(),
+            image.format()
+        )
+    })?;
+    let size = region.extent.width as vk::DeviceSize
+        * region.extent.height as vk::DeviceSize
+        * region.extent.depth as vk::DeviceSize
+        * image.subresource_range().layer_count as vk::DeviceSize
+        * texel_size as vk::DeviceSize;
+    let staging = Buffer::new(cmd.device().clone(), allocator, size, MemoryType::GpuToCpu)?;
+    let transfer = image.sharing_mode() == vk::SharingMode::EXCLUSIVE && owner_family != cmd.queue_family();
+    let cmd = if transfer {
+        cmd.acquire_image(
+            image,
+            owner_family,
+            PipelineStage::TRANSFER,
+            vk::AccessFlags2::TRANSFER_READ,
+            layout,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+        )
+    } else {
+        cmd.transition_image(
+            image,
+            PipelineStage::ALL_COMMANDS,
+            PipelineStage::TRANSFER,
+            layout,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            vk::AccessFlags2::MEMORY_WRITE,
+            vk::AccessFlags2::TRANSFER_READ,
+        )
+    };
+    // Transitions back to `layout` if no ownership transfer is needed.
+    let cmd = cmd
+        .copy_image_region_to_buffer(image, &region, &staging.view_full())?
+        .release_image(
+            image,
+            owner_family,
+            PipelineStage::TRANSFER,
+            vk::AccessFlags2::NONE,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            layout,
+        );
+    submit_readback(exec, cmd, staging)
+}
+
+/// Pixel data of an image read back with [`readback_image()`], converted to RGBA8.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageData {
+    /// Width of the image in pixels.
+    pub width: u32,
+    /// Height of the image in pixels.
+    pub height: u32,
+    /// Tightly packed RGBA8 pixels, row by row. Each row is `width * 4` bytes.
+    pub pixels: Vec<u8>,
+}
+
+/// Owns the `VkEvent` that signals the end of a frame readback.
+#[derive(Debug)]
+struct ReadbackEvent {
+    device: Device,
+    handle: vk::Event,
+}
+
+impl ReadbackEvent {
+    fn is_set(&self) -> VkResult<bool> {
+        self.device
+            .check_device_lost(unsafe { self.device.get_event_status(self.handle) })
+    }
+}
+
+impl Drop for ReadbackEvent {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_event(self.handle, None);
+        }
+    }
+}
+
+/// A copy of an image recorded with [`readback_image()`]. This is a future that resolves to the pixel data once the command
+/// buffer it was recorded in has executed. It can also be waited on with [`FrameReadback::wait()`].
+///
+/// Events can not be waited on from the host. Instead, waiting submits a fence to the queue of the command buffer, which is
+/// signaled once all work submitted to that queue so far has completed. If the copy has not executed by then, because the
+/// command buffer was not submitted yet, a new fence is submitted.
+///
+/// Dropping a readback before it completed waits for the device to be idle, since the GPU may still write to its staging buffer.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct FrameReadback<A: Allocator = DefaultAllocator> {
+    event: ReadbackEvent,
+    staging: Buffer<A>,
+    format: vk::Format,
+    extent: vk::Extent2D,
+    completed: bool,
+    #[derivative(Debug = "ignore")]
+    queue: Arc<Mutex<DeviceQueue>>,
+    /// Fence submitted to `queue` by the last poll of the future.
+    fence: Option<Fence>,
+}
+
+/// Record a copy of `image` into a host-visible buffer at the end of `cmd`, typically the last command buffer of a frame. The
+/// returned [`FrameReadback`] resolves to the pixels of the image once that command buffer has executed, converted to RGBA8.
+///
+/// `layout` is the layout the image is in at this point in the command buffer. The image is transitioned to
+/// [`vk::ImageLayout::TRANSFER_SRC_OPTIMAL`] for the copy, and back to `layout` afterwards. This means a swapchain image can be read
+/// back after it was transitioned to [`vk::ImageLayout::PRESENT_SRC_KHR`], for example by a present pass.
+///
+/// The image must have been created with [`vk::ImageUsageFlags::TRANSFER_SRC`]. Swapchain images have this usage if the surface
+/// supports it. Only the base mip level and first layer of the view are read back. Color space conversions are not applied, so pixels of sRGB
+/// images are returned as sRGB and floating point formats are clamped to `[0, 1]`.
+/// # Errors
+/// * Fails with [`Error::UnsupportedReadbackFormat`] if the image format can not be converted to RGBA8.
+/// * Fails if allocating the staging buffer or creating the event fails.
+/// # Example
+/// ```
+/// # use anyhow::Result;
+/// # use phobos::prelude::*;
+/// # use phobos::util::readback::{readback_image, FrameReadback};
+/// fn screenshot<'q>(
+///     cmd: IncompleteCommandBuffer<'q, domain::Graphics>,
+///     allocator: &mut DefaultAllocator,
+///     ifc: &InFlightContext,
+/// ) -> Result<(IncompleteCommandBuffer<'q, domain::Graphics>, FrameReadback)> {
+///     // The present pass of this frame already transitioned the swapchain image for presentation.
+///     readback_image(cmd, allocator, &ifc.swapchain_image, vk::ImageLayout::PRESENT_SRC_KHR)
+/// }
+///
+/// async fn save(readback: FrameReadback) -> Result<()> {
+///     let image = readback.await?;
+///     println!("Screenshot of {}x{} pixels", image.width, image.height);
+///     Ok(())
+/// }
+/// ```
+pub fn readback_image<'q, D: TransferSupport + ExecutionDomain, A: Allocator>(
+    cmd: IncompleteCommandBuffer<'q, D, A>,
+    allocator: &mut A,
+    image: &ImageView,
+    layout: vk::ImageLayout,
+) -> Result<(IncompleteCommandBuffer<'q, D, A>, FrameReadback<A>)> {
+    let format = image.format();
+    let texel_size = readback_texel_size(format).ok_or(Error::UnsupportedReadbackFormat(format))?;
+    let size = image.base_level_size();
+    let extent = vk::Extent2D {
+        width: size.width,
+        height: size.height,
+    };
+    let staging = Buffer::new(
+        cmd.device().clone(),
+        allocator,
+        extent.width as vk::DeviceSize * extent.height as vk::DeviceSize * texel_size as vk::DeviceSize,
+        MemoryType::GpuToCpu,
+    )?;
+    let device = cmd.device().clone();
+    let queue = cmd.device_queue();
+    // The event status is read from the host, so it can not be created with DEVICE_ONLY.
+    let event = ReadbackEvent {
+        handle: unsafe { device.create_event(&vk::EventCreateInfo::default(), None)? },
+        device,
+    };
+
+    let cmd = cmd.transition_image(
+        image,
+        PipelineStage::ALL_COMMANDS,
+        PipelineStage::TRANSFER,
+        layout,
+        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+        vk::AccessFlags2::MEMORY_WRITE,
+        vk::AccessFlags2::TRANSFER_READ,
+    );
+    // Only copy the first layer, tightly packed.
+    let copy = vk::BufferImageCopy {
+        buffer_offset: 0,
+        buffer_row_length: extent.width,
+        buffer_image_height: extent.height,
+        image_subresource: vk::ImageSubresourceLayers {
+            aspect_mask: image.aspect(),
+            mip_level: image.base_level(),
+            base_array_layer: image.subresource_range().base_array_layer,
+            layer_count: 1,
+        },
+        image_offset: Default::default(),
+        image_extent: size,
+    };
+    unsafe {
+        event.device.cmd_copy_image_to_buffer(
+            cmd.handle(),
+            image.image(),
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            staging.handle(),
+            std::slice::from_ref(&copy),
+        );
+    }
+    let cmd = cmd.transition_image(
+        image,
+        PipelineStage::TRANSFER,
+        PipelineStage::ALL_COMMANDS,
+        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+        layout,
+        vk::AccessFlags2::NONE,
+        vk::AccessFlags2::NONE,
+    );
+    // The event is set once the copy is done, and makes the copied data available to the host.
+    let barrier = vk::MemoryBarrier2 {
+        src_stage_mask: PipelineStage::TRANSFER,
+        src_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+        dst_stage_mask: PipelineStage::HOST,
+        dst_access_mask: vk::AccessFlags2::HOST_READ,
+        ..Default::default()
+    };
+    let dependency = vk::DependencyInfo {
+        memory_barrier_count: 1,
+        p_memory_barriers: &barrier,
+        ..Default::default()
+    };
+    unsafe {
+        event
+            .device
+            .cmd_set_event2(cmd.handle(), event.handle, &dependency);
+    }
+
+    Ok((
+        cmd,
+        FrameReadback {
+            event,
+            staging,
+            format,
+            extent,
+            completed: false,
+            queue,
+            fence: None,
+        },
+    ))
+}
+
+impl<A: Allocator> Unpin for FrameReadback<A> {}
+
+impl<A: Allocator> FrameReadback<A> {
+    /// Check whether the copy has finished without blocking.
+    /// # Errors
+    /// * Fails with [`vk::Result::ERROR_DEVICE_LOST`] if the device was lost.
+    pub fn is_ready(&self) -> Result<bool> {
+        Ok(self.event.is_set()?)
+    }
+
+    /// Block until the copy has finished and return the pixel data. This must only be called after the command buffer the
+    /// readback was recorded in was submitted, otherwise it never returns.
+    /// # Errors
+    /// * Fails with [`vk::Result::ERROR_DEVICE_LOST`] if the device was lost.
+    pub fn wait(mut self) -> Result<ImageData> {
+        while !self.event.is_set()? {
+            self.submit_fence()?.wait()?;
+        }
+        self.read()
+    }
+
+    /// Submit a fence that is signaled once the work submitted to the queue of the readback so far has completed.
+    fn submit_fence(&self) -> Result<Fence> {
+        let queue = self.queue.lock().map_err(|_| Error::PoisonError)?;
+        queue.submitted_work_fence(&self.event.device)
+    }
+
+    fn read(&mut self) -> Result<ImageData> {
+        self.completed = true;
+        let mut view = self.staging.view_full();
+        Ok(ImageData {
+            width: self.extent.width,
+            height: self.extent.height,
+            pixels: convert_to_rgba8(self.format, view.mapped_slice::<u8>()?)?,
+        })
+    }
+}
+
+impl<A: Allocator> std::future::Future for FrameReadback<A> {
+    type Output = Result<ImageData>;
+
+    fn poll(mut self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.event.is_set() {
+            Ok(true) => return Poll::Ready(self.read()),
+            Ok(false) => {}
+            Err(e) => return Poll::Ready(Err(e.into())),
+        }
+        if self.fence.is_none() {
+            match self.submit_fence() {
+                Ok(fence) => self.fence = Some(fence),
+                Err(e) => return Poll::Ready(Err(e)),
+            }
+        }
+        let fence = self.fence.as_mut().unwrap();
+        if Pin::new(fence).poll(ctx).is_ready() {
+            // The fence was submitted before the command buffer with the copy, so check again with a new fence.
+            self.fence = None;
+            ctx.waker().wake_by_ref();
+        }
+        Poll::Pending
+    }
+}
+
+impl<A: Allocator> Drop for FrameReadback<A> {
+    fn drop(&mut self) {
+        // The copy may still be pending, and would write to the staging buffer after it is freed.
+        if !self.completed && !matches!(self.event.is_set(), Ok(true)) {
+            self.event.device.wait_for_submitted_work();
+        }
+    }
+}
+
+/// Size in bytes of a single texel of a format supported by [`convert_to_rgba8()`].
+fn readback_texel_size(format: vk::Format) -> Option<u32> {
+    match format {
+        vk::Format::R8_UNORM | vk::Format::R8_SRGB => Some(1),
+        vk::Format::R8G8B8A8_UNORM
+        | vk::Format::R8G8B8A8_SRGB
+        | vk::Format::B8G8R8A8_UNORM
+        | vk::Format::B8G8R8A8_SRGB
+        | vk::Format::A2B10G10R10_UNORM_PACK32
+        | vk::Format::A2R10G10B10_UNORM_PACK32 => Some(4),
+        vk::Format::R16G16B16A16_UNORM | vk::Format::R16G16B16A16_SFLOAT => Some(8),
+        vk::Format::R32G32B32A32_SFLOAT => Some(16),
+        _ => None,
+    }
+}
+
+/// Convert tightly packed texels of `format` to RGBA8, the same way [`readback_image()`] does. Incomplete texels at the end of
+/// `data` are ignored.
+/// # Errors
+/// * Fails with [`Error::UnsupportedReadbackFormat`] if the format can not be converted to RGBA8.
+pub fn convert_to_rgba8(format: vk::Format, data: &[u8]) -> Result<Vec<u8>> {
+    let texel_size = readback_texel_size(format).ok_or(Error::UnsupportedReadbackFormat(format))? as usize;
+    let unorm = |value: f32| (value.clamp(0.0, 1.0) * 255.0 + 0.5) as u8;
+    let mut pixels = Vec::with_capacity(data.len() / texel_size * 4);
+    for texel in data.chunks_exact(texel_size) {
+        let rgba = match format {
+            vk::Format::R8_UNORM | vk::Format::R8_SRGB => [texel[0], texel[0], texel[0], 255],
+            vk::Format::B8G8R8A8_UNORM | vk::Format::B8G8R8A8_SRGB => [texel[2], texel[1], texel[0], texel[3]],
+            vk::Format::A2B10G10R10_UNORM_PACK32 | vk::Format::A2R10G10B10_UNORM_PACK32 => {
+                let packed = u32::from_le_bytes([texel[0], texel[1], texel[2], texel[3]]);
+                let channel = |shift: u32| (((packed >> shift) & 0x3ff) >> 2) as u8;
+                let alpha = ((packed >> 30) * 255 / 3) as u8;
+                if format == vk::Format::A2B10G10R10_UNORM_PACK32 {
+                    [channel(0), channel(10), channel(20), alpha]
+                } else {
+                    [channel(20), channel(10), channel(0), alpha]
+                }
+            }
+            vk::Format::R16G16B16A16_UNORM => {
+                let channel = |i: usize| texel[i * 2 + 1];
+                [channel(0), channel(1), channel(2), channel(3)]
+            }
+            vk::Format::R16G16B16A16_SFLOAT => {
+                let channel = |i: usize| unorm(f16_to_f32(u16::from_le_bytes([texel[i * 2], texel[i * 2 + 1]])));
+                [channel(0), channel(1), channel(2), channel(3)]
+            }
+            vk::Format::R32G32B32A32_SFLOAT => {
+                let channel = |i: usize| {
+                    unorm(f32::from_le_bytes([texel[i * 4], texel[i * 4 + 1], texel[i * 4 + 2], texel[i * 4 + 3]]))
+                };
+                [channel(0), channel(1), channel(2), channel(3)]
+            }
+            _ => [texel[0], texel[1], texel[2], texel[3]],
+        };
+        pixels.extend_from_slice(&rgba);
+    }
+    Ok(pixels)
+}
+
+/// Convert a half precision float to single precision.
+pub fn f16_to_f32(half: u16) -> f32 {
+    let sign = if half & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((half >> 10) & 0x1f) as i32;
+    let mantissa = (half & 0x3ff) as f32;
+    match exponent {
+        0 => sign * mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => sign * (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
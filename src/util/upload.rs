@@ -0,0 +1,567 @@
+//! Create device local buffers and images with initial data from the CPU.
+//!
+//! [`Buffer::from_data()`] and [`Image::from_data()`] allocate the resource, write the data to a staging buffer and submit
+//! the copy on the transfer domain. They return a [`PendingUpload`] future that resolves to the resource once the copy completed.
+//!
+//! To upload as part of a larger command buffer instead, use [`Buffer::from_data_cmd()`] and [`Image::from_data_cmd()`]. These
+//! record the copy into an existing command buffer and return the staging buffer, which must be kept alive until the command buffer
+//! finished executing.
+//!
+//! Images can be uploaded with any number of mip levels, and each [`MipLevelData`] can cover a region of a mip level.
+//! # Example
+//! ```
+//! # use anyhow::Result;
+//! # use phobos::prelude::*;
+//! async fn upload_resources(
+//!     mut allocator: DefaultAllocator,
+//!     exec: ExecutionManager,
+//!     pixels: &[u8],
+//!     half_size_pixels: &[u8],
+//! ) -> Result<(Buffer, Image)> {
+//!     let vertices: [f32; 6] = [0.0, -0.5, 0.5, 0.5, -0.5, 0.5];
+//!     let vertices = Buffer::from_data(&mut allocator, &exec, &vertices)?;
+//!     let info = ImageCreateInfo {
+//!         width: 256,
+//!         height: 256,
+//!         depth: 1,
+//!         usage: vk::ImageUsageFlags::SAMPLED,
+//!         format: vk::Format::R8G8B8A8_SRGB,
+//!         samples: vk::SampleCountFlags::TYPE_1,
+//!         mip_levels: 2,
+//!         layers: 1,
+//!         flags: vk::ImageCreateFlags::empty(),
+//!         memory_type: MemoryType::GpuOnly,
+//!     };
+//!     let size = vk::Extent3D { width: 256, height: 256, depth: 1 };
+//!     let levels = [MipLevelData::full(size, 0, pixels), MipLevelData::full(size, 1, half_size_pixels)];
+//!     let image = Image::from_data(&mut allocator, &exec, info, &levels, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)?;
+//!     Ok((vertices.await?, image.await?))
+//! }
+//! ```
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use anyhow::{ensure, Result};
+use ash::vk;
+use bytemuck::Pod;
+
+use crate::command_buffer::IncompleteCommandBuffer;
+use crate::command_buffer::traits::*;
+use crate::pool::Pooled;
+use crate::sync::domain;
+use crate::sync::domain::ExecutionDomain;
+use crate::sync::fence::Fence;
+use crate::util::align::align;
+use crate::resource::image::image_sharing_mode;
+use crate::util::compressed::{compressed_block, validate_block_region};
+use crate::util::format::{texel_region_size, texel_size};
+use crate::util::host_image_copy::supports_host_upload;
+use crate::{
+    Allocator, Buffer, DefaultAllocator, Device, Error, ExecutionManager, Image, ImageCreateInfo, ImageRegion, MemoryType,
+    PipelineStage,
+};
+
+/// Offsets of buffer data and image regions in staging buffers are aligned to this. Image regions of formats with a texel size
+/// that does not divide this are aligned further, see [`region_alignment()`].
+pub(crate) const REGION_ALIGNMENT: u64 = 16;
+
+/// Data for a region of a single mip level of an image. Texels (or blocks, for compressed formats) must be tightly packed,
+/// with the data for all array layers of the image after each other. For compressed formats, see also the
+/// [`compressed`](crate::util::compressed) module.
+#[derive(Debug, Copy, Clone)]
+pub struct MipLevelData<'a> {
+    /// Mip level to upload to.
+    pub mip_level: u32,
+    /// Region inside the mip level to upload to.
+    pub region: ImageRegion,
+    /// Texel data of the region.
+    pub data: &'a [u8],
+}
+
+impl<'a> MipLevelData<'a> {
+    /// Data covering an entire mip level of an image with base size `size`.
+    pub fn full(size: vk::Extent3D, mip_level: u32, data: &'a [u8]) -> Self {
+        Self {
+            mip_level,
+            region: ImageRegion {
+                offset: vk::Offset3D::default(),
+                extent: mip_level_size(size, mip_level),
+            },
+            data,
+        }
+    }
+}
+
+/// Get the alignment of image regions of `format` in a staging buffer. Buffer offsets of copies must be a multiple of the texel
+/// size (or block size, for compressed formats), so this is the least common multiple of that and [`REGION_ALIGNMENT`].
+pub(crate) fn region_alignment(format: vk::Format) -> u64 {
+    let texel = match compressed_block(format) {
+        Some(block) => block.bytes,
+        None => texel_size(format).unwrap_or(1),
+    } as u64;
+    let gcd = |mut a: u64, mut b: u64| {
+        while b != 0 {
+            (a, b) = (b, a % b);
+        }
+        a
+    };
+    REGION_ALIGNMENT / gcd(REGION_ALIGNMENT, texel) * texel
+}
+
+/// Get the size of a mip level of an image with base size `size`.
+pub(crate) fn mip_level_size(size: vk::Extent3D, level: u32) -> vk::Extent3D {
+    let shrink = |extent: u32| extent.checked_shr(level).unwrap_or(0).max(1);
+    vk::Extent3D {
+        width: shrink(size.width),
+        height: shrink(size.height),
+        depth: shrink(size.depth),
+    }
+}
+
+/// A resource upload submitted with [`Buffer::from_data()`] or [`Image::from_data()`]. This is a future that resolves to the
+/// resource once the copy has executed. It can also be waited on with [`PendingUpload::wait()`].
+///
+/// Dropping an upload before it completed waits for the copy to finish, since the GPU may still read from its staging buffer.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct PendingUpload<R, A: Allocator = DefaultAllocator> {
+    // None if the upload was done on the host, and has already completed.
+    #[derivative(Debug = "ignore")]
+    fence: Option<Pooled<Fence>>,
+    #[derivative(Debug = "ignore")]
+    resource: Option<R>,
+    staging: Option<Buffer<A>>,
+}
+
+// SAFETY: None of the fields are self-referential, so the upload can be moved while pinned.
+impl<R, A: Allocator> Unpin for PendingUpload<R, A> {}
+
+impl<R, A: Allocator> PendingUpload<R, A> {
+    /// Block until the upload completed, and return the resource.
+    /// # Errors
+    /// * Fails if waiting on the fence fails, for example because the device was lost.
+    pub fn wait(mut self) -> Result<R> {
+        if let Some(fence) = &mut self.fence {
+            fence.wait()?;
+        }
+        self.staging = None;
+        Ok(self.resource.take().unwrap())
+    }
+}
+
+impl<R, A: Allocator> Future for PendingUpload<R, A> {
+    type Output = Result<R>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let status = match &mut self.fence {
+            Some(fence) => Pin::new(fence).poll(cx),
+            None => Poll::Ready(None),
+        };
+        match status {
+            Poll::Ready(_) => {
+                self.staging = None;
+                Poll::Ready(Ok(self.resource.take().unwrap()))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<R, A: Allocator> Drop for PendingUpload<R, A> {
+    fn drop(&mut self) {
+        if let (Some(fence), Some(_)) = (&mut self.fence, &self.staging) {
+            if let Err(e) = fence.wait() {
+                error!("Failed to wait for upload before dropping its staging buffer: {e}");
+            }
+        }
+    }
+}
+
+/// Submit a command buffer that uploads a resource, and wrap it in a [`PendingUpload`].
+fn submit_upload<R, A: Allocator + 'static>(
+    exec: &ExecutionManager<A>,
+    cmd: IncompleteCommandBuffer<'_, domain::Transfer, A>,
+    resource: R,
+    staging: Buffer<A>,
+) -> Result<PendingUpload<R, A>> {
+    let fence = exec.submit(cmd.finish()?)?;
+    Ok(PendingUpload {
+        fence: Some(fence),
+        resource: Some(resource),
+        staging: Some(staging),
+    })
+}
+
+/// Allocate a staging buffer and fill it with `data`.
+fn staging_buffer<A: Allocator>(device: Device, allocator: &mut A, data: &[u8]) -> Result<Buffer<A>> {
+    let staging = Buffer::new(device, allocator, data.len() as vk::DeviceSize, MemoryType::CpuToGpu)?;
+    staging.view_full().mapped_slice::<u8>()?.copy_from_slice(data);
+    Ok(staging)
+}
+
+impl<A: Allocator> Buffer<A> {
+    /// Create a device local buffer holding `data`. The data is uploaded through a staging buffer on the transfer domain.
+    /// Returns a future that resolves to the buffer once the upload completed.
+    /// # Errors
+    /// * Fails if `data` is empty.
+    /// * Fails if allocating the buffer or staging buffer fails.
+    /// * Fails if there is no queue capable of transfer operations, or submitting the copy fails.
+    pub fn from_data<T: Pod>(
+        allocator: &mut A,
+        exec: &ExecutionManager<A>,
+        data: &[T],
+    ) -> Result<PendingUpload<Self, A>>
+    where
+        A: 'static, {
+        let cmd = exec.on_domain::<domain::Transfer>()?;
+        let (cmd, buffer, staging) = Self::from_data_cmd(cmd, allocator, data)?;
+        submit_upload(exec, cmd, buffer, staging)
+    }
+
+    /// Create a device local buffer holding `data`, and record the upload from a staging buffer into `cmd`. Commands recorded
+    /// after this can read the buffer.
+    ///
+    /// Returns the command buffer, the new buffer and the staging buffer. The staging buffer must be kept alive until
+    /// `cmd` finished executing.
+    /// # Errors
+    /// * Fails if `data` is empty.
+    /// * Fails if allocating the buffer or staging buffer fails.
+    pub fn from_data_cmd<'q, D: ExecutionDomain + TransferSupport, T: Pod>(
+        cmd: IncompleteCommandBuffer<'q, D, A>,
+        allocator: &mut A,
+        data: &[T],
+    ) -> Result<(IncompleteCommandBuffer<'q, D, A>, Self, Buffer<A>)> {
+        ensure!(!data.is_empty(), "Cannot create a buffer from empty data");
+        let device = cmd.device().clone();
+        let bytes: &[u8] = bytemuck::cast_slice(data);
+        let staging = staging_buffer(device.clone(), allocator, bytes)?;
+        let buffer = Buffer::new_device_local(device, allocator, bytes.len() as vk::DeviceSize)?;
+        let cmd = cmd
+            .copy_buffer(&staging.view_full(), &buffer.view_full())?
+            .memory_barrier(
+                PipelineStage::TRANSFER,
+                vk::AccessFlags2::TRANSFER_WRITE,
+                PipelineStage::ALL_COMMANDS,
+                vk::AccessFlags2::MEMORY_READ,
+            );
+        Ok((cmd, buffer, staging))
+    }
+}
+
+impl<A: Allocator> Image<A> {
+    /// Create an image and upload regions of its mip levels. The data is uploaded through a staging buffer on the transfer domain,
+    /// after which the image is transitioned to `layout`. Mip levels that are not uploaded are left undefined.
+    /// `TRANSFER_DST` usage is always added to `info.usage`.
+    ///
+    /// Images with [`vk::SharingMode::EXCLUSIVE`] are released to the graphics queue family after the upload, if the transfer
+    /// queue is on another family. Acquire them on the graphics queue before use with
+    /// [`IncompleteCommandBuffer::acquire_image()`], with the transfer queue family as the source family and `layout` as both
+    /// layouts. This records nothing if no ownership transfer was needed.
+    ///
+    /// If [`ExtensionID::HostImageCopy`](crate::ExtensionID::HostImageCopy) is enabled, the format of the image supports host
+    /// transfers and `layout` can be used for host copies, the image is uploaded with a host copy instead, see
+    /// [`Image::from_data_host()`]. Exclusive images that would need an ownership transfer are always uploaded on the transfer
+    /// queue, so acquiring them as described above stays valid.
+    ///
+    /// Returns a future that resolves to the image once the upload completed.
+    /// # Errors
+    /// * Fails if `levels` is empty, a region is outside of its mip level, or the data of a region does not have the size of the region.
+    /// * Fails if a region of a block-compressed image is not aligned to its blocks.
+    /// * Fails if the format is not a color format.
+    /// * Fails if creating the image or allocating the staging buffer fails.
+    /// * Fails if there is no queue capable of transfer operations, or submitting the copy fails.
+    pub fn from_data(
+        allocator: &mut A,
+        exec: &ExecutionManager<A>,
+        info: ImageCreateInfo,
+        levels: &[MipLevelData],
+        layout: vk::ImageLayout,
+    ) -> Result<PendingUpload<Self, A>>
+    where
+        A: 'static, {
+        // Look up the queue families before locking the transfer queue, since this locks every queue it checks.
+        let graphics_family = exec
+            .get_queue::<domain::Graphics>()
+            .map(|queue| queue.info().family_index);
+        let transfer_family = exec
+            .get_queue::<domain::Transfer>()
+            .map(|queue| queue.info().family_index);
+        let device = exec.device();
+        let needs_release = image_sharing_mode(device, &info) == vk::SharingMode::EXCLUSIVE
+            && graphics_family.is_some()
+            && graphics_family != transfer_family;
+        if !needs_release && supports_host_upload(device, info.format, layout) {
+            let image = Self::from_data_host(device.clone(), allocator, info, levels, layout)?;
+            return Ok(PendingUpload {
+                fence: None,
+                resource: Some(image),
+                staging: None,
+            });
+        }
+        let cmd = exec.on_domain::<domain::Transfer>()?;
+        let (mut cmd, image, staging) = Self::from_data_cmd(cmd, allocator, info, levels, layout)?;
+        let release_to = graphics_family.filter(|&family| family != cmd.queue_family());
+        if let Some(family) = release_to.filter(|_| image.sharing_mode() == vk::SharingMode::EXCLUSIVE) {
+            let range = vk::ImageSubresourceRange {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                base_mip_level: 0,
+                level_count: image.mip_levels(),
+                base_array_layer: 0,
+                layer_count: image.layers(),
+            };
+            cmd = release_uploaded_image(cmd, unsafe { image.handle() }, range, layout, family);
+        }
+        submit_upload(exec, cmd, image, staging)
+    }
+
+    /// Create an image, and record the upload of regions of its mip levels into `cmd`. After the upload, the image is transitioned
+    /// to `layout`, so commands recorded after this can use it. Mip levels that are not uploaded are left undefined.
+    /// `TRANSFER_DST` usage is always added to `info.usage`.
+    ///
+    /// Returns the command buffer, the new image and the staging buffer. The staging buffer must be kept alive until
+    /// `cmd` finished executing.
+    /// # Errors
+    /// * Fails if `levels` is empty, a region is outside of its mip level, or the data of a region does not have the size of the region.
+    /// * Fails if a region of a block-compressed image is not aligned to its blocks.
+    /// * Fails if the format is not a color format.
+    /// * Fails if creating the image or allocating the staging buffer fails.
+    pub fn from_data_cmd<'q, D: ExecutionDomain + TransferSupport>(
+        cmd: IncompleteCommandBuffer<'q, D, A>,
+        allocator: &mut A,
+        mut info: ImageCreateInfo,
+        levels: &[MipLevelData],
+        layout: vk::ImageLayout,
+    ) -> Result<(IncompleteCommandBuffer<'q, D, A>, Self, Buffer<A>)> {
+        ensure!(!levels.is_empty(), "Cannot create an image from empty data");
+        let size = vk::Extent3D {
+            width: info.width,
+            height: info.height,
+            depth: info.depth,
+        };
+        let (offsets, staging_size) = pack_mip_levels(size, info.format, info.layers, 0, info.mip_levels, levels)?;
+        let mut data = vec![0u8; staging_size as usize];
+        write_mip_levels(&mut data, levels, &offsets);
+
+        let device = cmd.device().clone();
+        let staging = staging_buffer(device.clone(), allocator, &data)?;
+        info.usage |= vk::ImageUsageFlags::TRANSFER_DST;
+        let image = Image::new(device.clone(), allocator, info)?;
+        let range = vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: info.mip_levels,
+            base_array_layer: 0,
+            layer_count: info.layers,
+        };
+        unsafe {
+            record_image_upload(
+                &device,
+                cmd.handle(),
+                staging.handle(),
+                image.handle(),
+                range,
+                &image_copies(levels, &offsets, 0, range),
+                vk::ImageLayout::UNDEFINED,
+                layout,
+            );
+        }
+        Ok((cmd, image, staging))
+    }
+}
+
+/// Validate a region of mip level `base_level + mip_level` of an image with base size `size` and `level_count` mip levels
+/// starting at `base_level`. For block-compressed formats, the region must also be aligned to the block size.
+pub(crate) fn validate_region(
+    size: vk::Extent3D,
+    format: vk::Format,
+    base_level: u32,
+    level_count: u32,
+    mip_level: u32,
+    region: &ImageRegion,
+) -> Result<()> {
+    ensure!(
+        mip_level < level_count,
+        "Mip level {} does not exist in an image with {} mip levels",
+        mip_level,
+        level_count
+    );
+    let level_size = mip_level_size(size, base_level + mip_level);
+    let fits = |offset: i32, extent: u32, size: u32| offset >= 0 && (offset as u64) + (extent as u64) <= size as u64;
+    if !(fits(region.offset.x, region.extent.width, level_size.width)
+        && fits(region.offset.y, region.extent.height, level_size.height)
+        && fits(region.offset.z, region.extent.depth, level_size.depth))
+    {
+        return Err(Error::ImageRegionOutOfRange.into());
+    }
+    if let Some(block) = compressed_block(format) {
+        validate_block_region(block, region, level_size)?;
+    }
+    Ok(())
+}
+
+/// Validate the regions in `levels` against mip levels `base_level..base_level + level_count` of an image with base size `size`,
+/// where each [`MipLevelData::mip_level`] is relative to `base_level`. Returns the offset of each region when all regions are packed
+/// after each other, and the total size. The data of each region must have the size of the region for `layers` array layers,
+/// which is the compressed size for block-compressed formats. Offsets are aligned with [`region_alignment()`].
+pub(crate) fn pack_mip_levels(
+    size: vk::Extent3D,
+    format: vk::Format,
+    layers: u32,
+    base_level: u32,
+    level_count: u32,
+    levels: &[MipLevelData],
+) -> Result<(Vec<u64>, u64)> {
+    let alignment = region_alignment(format);
+    let mut offsets = Vec::with_capacity(levels.len());
+    let mut total_size = 0;
+    for level in levels {
+        validate_region(size, format, base_level, level_count, level.mip_level, &level.region)?;
+        if let Some(block) = compressed_block(format) {
+            let expected = block.region_size(level.region.extent, layers)?;
+            ensure!(
+                level.data.len() as u64 == expected,
+                "Compressed data for mip level {} has {} bytes, but the region needs {expected} bytes",
+                level.mip_level,
+                level.data.len()
+            );
+        } else {
+            let texel = texel_size(format).ok_or_else(|| anyhow::anyhow!("Cannot upload images with format {format:?}"))?;
+            let extent = level.region.extent;
+            let expected = texel_region_size(extent, layers, texel)
+                .ok_or_else(|| anyhow::anyhow!("Region with extent {extent:?} and {layers} layers is too large"))?;
+            ensure!(
+                level.data.len() as u64 == expected,
+                "Data for mip level {} has {} bytes, but the region needs {expected} bytes",
+                level.mip_level,
+                level.data.len()
+            );
+        }
+        total_size = align(total_size, alignment);
+        offsets.push(total_size);
+        total_size += level.data.len() as u64;
+    }
+    Ok((offsets, total_size))
+}
+
+/// Copy the data of each level into `dst` at the offsets returned from [`pack_mip_levels()`].
+pub(crate) fn write_mip_levels(dst: &mut [u8], levels: &[MipLevelData], offsets: &[u64]) {
+    for (level, offset) in levels.iter().zip(offsets) {
+        let offset = *offset as usize;
+        dst[offset..offset + level.data.len()].copy_from_slice(level.data);
+    }
+}
+
+/// Get the copy regions for mip level data at `offsets` relative to `src_offset` in a staging buffer, into the subresources in `range`.
+/// Mip levels in `levels` are relative to the base mip level of `range`.
+pub(crate) fn image_copies(
+    levels: &[MipLevelData],
+    offsets: &[u64],
+    src_offset: u64,
+    range: vk::ImageSubresourceRange,
+) -> Vec<vk::BufferImageCopy> {
+    levels
+        .iter()
+        .zip(offsets)
+        .map(|(level, offset)| vk::BufferImageCopy {
+            buffer_offset: src_offset + *offset,
+            buffer_row_length: 0,
+            buffer_image_height: 0,
+            image_subresource: vk::ImageSubresourceLayers {
+                aspect_mask: range.aspect_mask,
+                mip_level: range.base_mip_level + level.mip_level,
+                base_array_layer: range.base_array_layer,
+                layer_count: range.layer_count,
+            },
+            image_offset: level.region.offset,
+            image_extent: level.region.extent,
+        })
+        .collect()
+}
+
+/// Record `copies` from `src` to `image`. The subresources in `range` are transitioned from `old_layout` to
+/// [`vk::ImageLayout::TRANSFER_DST_OPTIMAL`] for the copy, and to `new_layout` afterwards.
+/// # Safety
+/// `cmd` must be in the recording state, and `src` and `image` must be valid until it finished executing.
+#[allow(clippy::too_many_arguments)]
+pub(crate) unsafe fn record_image_upload(
+    device: &Device,
+    cmd: vk::CommandBuffer,
+    src: vk::Buffer,
+    image: vk::Image,
+    range: vk::ImageSubresourceRange,
+    copies: &[vk::BufferImageCopy],
+    old_layout: vk::ImageLayout,
+    new_layout: vk::ImageLayout,
+) {
+    let to_transfer = vk::ImageMemoryBarrier2 {
+        src_stage_mask: PipelineStage::ALL_COMMANDS,
+        src_access_mask: vk::AccessFlags2::MEMORY_WRITE,
+        dst_stage_mask: PipelineStage::TRANSFER,
+        dst_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+        old_layout,
+        new_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        image,
+        subresource_range: range,
+        ..Default::default()
+    };
+    let to_layout = vk::ImageMemoryBarrier2 {
+        src_stage_mask: PipelineStage::TRANSFER,
+        src_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+        dst_stage_mask: PipelineStage::ALL_COMMANDS,
+        dst_access_mask: vk::AccessFlags2::MEMORY_READ,
+        old_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        new_layout,
+        ..to_transfer
+    };
+    device.cmd_pipeline_barrier2(
+        cmd,
+        &vk::DependencyInfo {
+            image_memory_barrier_count: 1,
+            p_image_memory_barriers: &to_transfer,
+            ..Default::default()
+        },
+    );
+    device.cmd_copy_buffer_to_image(cmd, src, image, vk::ImageLayout::TRANSFER_DST_OPTIMAL, copies);
+    device.cmd_pipeline_barrier2(
+        cmd,
+        &vk::DependencyInfo {
+            image_memory_barrier_count: 1,
+            p_image_memory_barriers: &to_layout,
+            ..Default::default()
+        },
+    );
+}
+
+/// Release ownership of an uploaded image to the queue family `dst_family`. The image was already transitioned to `layout` after
+/// the copy, so the release keeps it.
+pub(crate) fn release_uploaded_image<'q, D: ExecutionDomain, A: Allocator>(
+    cmd: IncompleteCommandBuffer<'q, D, A>,
+    image: vk::Image,
+    range: vk::ImageSubresourceRange,
+    layout: vk::ImageLayout,
+    dst_family: u32,
+) -> IncompleteCommandBuffer<'q, D, A> {
+    let barrier = vk::ImageMemoryBarrier2 {
+        src_stage_mask: PipelineStage::TRANSFER,
+        src_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+        // The destination scope of a release is ignored, the acquire on the other queue defines it.
+        dst_stage_mask: PipelineStage::NONE,
+        dst_access_mask: vk::AccessFlags2::NONE,
+        old_layout: layout,
+        new_layout: layout,
+        src_queue_family_index: cmd.queue_family(),
+        dst_queue_family_index: dst_family,
+        image,
+        subresource_range: range,
+        ..Default::default()
+    };
+    cmd.pipeline_barrier(&vk::DependencyInfo {
+        image_memory_barrier_count: 1,
+        p_image_memory_barriers: &barrier,
+        ..Default::default()
+    })
+}
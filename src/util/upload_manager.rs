@@ -0,0 +1,770 @@
+//! Contains the [`UploadManager`], which batches uploads from any thread through a ring of staging buffers.
+//!
+//! [`Buffer::from_data()`] allocates a staging buffer and submits a command buffer for every upload. When many uploads are made,
+//! for example while streaming in assets, this quickly becomes expensive. The upload manager instead owns a ring of fixed size
+//! staging buffers, and a background thread that submits all uploads queued since its previous submission in a single transfer
+//! command buffer. Once the fence of a submission is signaled, its staging buffers are returned to the ring and the
+//! [`QueuedUpload`] futures of its uploads resolve.
+//!
+//! Queued uploads are submitted every time [`UploadManager::flush()`] is called, which is usually done once per frame. Uploads
+//! that are not flushed within the flush interval are submitted automatically, so threads that load assets outside of the frame loop
+//! do not depend on it. Uploads that do not fit in a single staging buffer of the ring get a dedicated staging buffer, which is freed
+//! once the upload completed.
+//!
+//! All methods take `&self`, so the upload manager can be shared between threads in an [`Arc`].
+//! # Example
+//! ```
+//! # use anyhow::Result;
+//! # use phobos::prelude::*;
+//! # use std::sync::Arc;
+//! fn load_texture(uploads: Arc<UploadManager>, pixels: Vec<u8>) -> Result<QueuedUpload<Image>> {
+//!     let info = ImageCreateInfo {
+//!         width: 512,
+//!         height: 512,
+//!         depth: 1,
+//!         usage: vk::ImageUsageFlags::SAMPLED,
+//!         format: vk::Format::R8G8B8A8_SRGB,
+//!         samples: vk::SampleCountFlags::TYPE_1,
+//!         mip_levels: 1,
+//!         layers: 1,
+//!         flags: vk::ImageCreateFlags::empty(),
+//!         memory_type: MemoryType::GpuOnly,
+//!     };
+//!     let size = vk::Extent3D { width: 512, height: 512, depth: 1 };
+//!     uploads.create_image(info, &[MipLevelData::full(size, 0, &pixels)], vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+//! }
+//!
+//! fn run(device: Device, exec: ExecutionManager, allocator: DefaultAllocator) -> Result<()> {
+//!     // Staging buffers of 16 MiB each.
+//!     let uploads = Arc::new(UploadManager::new(device, exec, allocator, 16 * 1024 * 1024)?);
+//!     let loader = {
+//!         let uploads = uploads.clone();
+//!         std::thread::spawn(move || load_texture(uploads, vec![255; 512 * 512 * 4]))
+//!     };
+//!     let texture = loader.join().unwrap()?;
+//!     // Once per frame, submit everything that was queued.
+//!     uploads.flush();
+//!     let _image = texture.wait()?;
+//!     Ok(())
+//! }
+//! ```
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use anyhow::{ensure, Result};
+use ash::vk;
+use bytemuck::Pod;
+
+use crate::command_buffer::traits::*;
+use crate::pool::Pooled;
+use crate::sync::domain;
+use crate::sync::fence::Fence;
+use crate::util::align::align;
+use crate::util::upload::{
+    image_copies, pack_mip_levels, record_image_upload, region_alignment, release_uploaded_image, write_mip_levels,
+    MipLevelData, REGION_ALIGNMENT,
+};
+use crate::{
+    Allocator, Buffer, BufferView, DefaultAllocator, Device, Error, ExecutionManager, Image, ImageCreateInfo, ImageView,
+    MemoryType,
+};
+
+/// Default time after which queued uploads are submitted without a call to [`UploadManager::flush()`].
+const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_millis(16);
+
+/// Default number of unused staging buffers that are kept in the ring.
+const DEFAULT_MAX_FREE_BLOCKS: usize = 4;
+
+/// Interval at which the submission thread checks whether submitted uploads have completed.
+const FENCE_POLL_INTERVAL: Duration = Duration::from_micros(500);
+
+/// Completion state of a queued upload, shared between the [`QueuedUpload`] and the submission thread.
+#[derive(Debug, Default)]
+struct UploadSignal {
+    state: Mutex<SignalState>,
+    done: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct SignalState {
+    result: Option<Result<(), Error>>,
+    completed: bool,
+    waker: Option<Waker>,
+}
+
+impl UploadSignal {
+    fn complete(&self, result: Result<(), Error>) {
+        let mut state = self.state.lock().unwrap();
+        state.result = Some(result);
+        state.completed = true;
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+        self.done.notify_all();
+    }
+}
+
+/// An upload queued in an [`UploadManager`]. This is a future that resolves to the uploaded resource (or `()` for uploads
+/// to existing resources) once the copy has executed. It can also be waited on with [`QueuedUpload::wait()`].
+///
+/// Dropping an upload that owns its resource before it completed waits for the copy to finish, since the GPU may still write to it.
+/// Uploads to existing resources can be dropped without blocking, the caller keeps the destination alive instead.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct QueuedUpload<R = ()> {
+    signal: Arc<UploadSignal>,
+    #[derivative(Debug = "ignore")]
+    resource: Option<R>,
+    /// Whether the resource was created by this upload, so dropping the upload must not free it while the copy is in flight.
+    owns_resource: bool,
+}
+
+// SAFETY: None of the fields are self-referential, so the upload can be moved while pinned.
+impl<R> Unpin for QueuedUpload<R> {}
+
+impl<R> QueuedUpload<R> {
+    /// Check whether the upload completed without blocking.
+    pub fn is_complete(&self) -> bool {
+        self.signal.state.lock().unwrap().completed
+    }
+
+    /// Block until the upload completed, and return the resource. This does not submit the upload, so it waits for at most the
+    /// flush interval of the upload manager, unless [`UploadManager::flush()`] is called.
+    /// # Errors
+    /// * Fails with [`Error::DeviceLost`] if the device was lost.
+    /// * Fails if recording or submitting the batch this upload was part of failed.
+    pub fn wait(mut self) -> Result<R> {
+        let mut state = self.signal.state.lock().unwrap();
+        while !state.completed {
+            state = self.signal.done.wait(state).unwrap();
+        }
+        let result = state.result.take().unwrap();
+        drop(state);
+        result?;
+        Ok(self.resource.take().unwrap())
+    }
+
+    /// Transform the resource this upload resolves to, for example to attach views to an uploaded image. `f` is called
+    /// immediately, and the returned upload completes together with this one.
+    pub fn map<U>(mut self, f: impl FnOnce(R) -> U) -> QueuedUpload<U> {
+        QueuedUpload {
+            signal: self.signal.clone(),
+            resource: self.resource.take().map(f),
+            owns_resource: self.owns_resource,
+        }
+    }
+}
+
+impl<R> Future for QueuedUpload<R> {
+    type Output = Result<R>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.signal.state.lock().unwrap();
+        if !state.completed {
+            state.waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+        let result = state.result.take().unwrap();
+        drop(state);
+        Poll::Ready(result.map(|_| self.resource.take().unwrap()).map_err(Into::into))
+    }
+}
+
+impl<R> Drop for QueuedUpload<R> {
+    fn drop(&mut self) {
+        if self.owns_resource && self.resource.is_some() {
+            let mut state = self.signal.state.lock().unwrap();
+            while !state.completed {
+                state = self.signal.done.wait(state).unwrap();
+            }
+        }
+    }
+}
+
+/// A staging buffer used by a batch of uploads.
+#[derive(Derivative)]
+#[derivative(Debug)]
+struct StagingBlock<A: Allocator> {
+    buffer: Buffer<A>,
+    /// Number of bytes written to this block.
+    used: u64,
+    /// Dedicated blocks hold a single upload that was too large for the ring, and are not returned to it.
+    dedicated: bool,
+}
+
+/// A copy from a staging block to a resource.
+#[derive(Debug)]
+enum UploadCopy {
+    Buffer {
+        block: usize,
+        offset: u64,
+        dst: BufferView,
+    },
+    Image {
+        block: usize,
+        image: vk::Image,
+        range: vk::ImageSubresourceRange,
+        copies: Vec<vk::BufferImageCopy>,
+        old_layout: vk::ImageLayout,
+        new_layout: vk::ImageLayout,
+        /// Whether the image has [`vk::SharingMode::EXCLUSIVE`], so it must be released to the graphics queue family.
+        exclusive: bool,
+    },
+}
+
+/// Uploads that are submitted together.
+#[derive(Derivative)]
+#[derivative(Debug)]
+struct UploadBatch<A: Allocator> {
+    blocks: Vec<StagingBlock<A>>,
+    /// Index of the block in `blocks` that new uploads are written to.
+    current: Option<usize>,
+    copies: Vec<UploadCopy>,
+    signals: Vec<Arc<UploadSignal>>,
+    /// Time at which the first upload of this batch was queued.
+    first_queued: Option<Instant>,
+}
+
+impl<A: Allocator> Default for UploadBatch<A> {
+    fn default() -> Self {
+        Self {
+            blocks: vec![],
+            current: None,
+            copies: vec![],
+            signals: vec![],
+            first_queued: None,
+        }
+    }
+}
+
+/// A batch of uploads that was submitted, and the fence that signals its completion.
+#[derive(Derivative)]
+#[derivative(Debug)]
+struct SubmittedBatch<A: Allocator> {
+    #[derivative(Debug = "ignore")]
+    fence: Pooled<Fence>,
+    blocks: Vec<StagingBlock<A>>,
+    signals: Vec<Arc<UploadSignal>>,
+}
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+struct UploadState<A: Allocator> {
+    #[derivative(Debug = "ignore")]
+    device: Device,
+    #[derivative(Debug = "ignore")]
+    allocator: A,
+    block_size: u64,
+    max_free_blocks: usize,
+    flush_interval: Duration,
+    free_blocks: VecDeque<Buffer<A>>,
+    batch: UploadBatch<A>,
+    flush_requested: bool,
+    shutdown: bool,
+}
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+struct Shared<A: Allocator> {
+    state: Mutex<UploadState<A>>,
+    wake: Condvar,
+}
+
+/// The execution manager used by the submission thread.
+struct SubmitContext<A: Allocator> {
+    exec: ExecutionManager<A>,
+    /// Queue family that exclusive images are released to after their upload.
+    graphics_family: Option<u32>,
+}
+
+/// Batches uploads from any thread and submits them on the transfer domain. See the [module-level documentation](self).
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct UploadManager<A: Allocator + 'static = DefaultAllocator> {
+    shared: Arc<Shared<A>>,
+    thread: Option<JoinHandle<()>>,
+    transfer_family: u32,
+}
+
+impl<A: Allocator> UploadState<A> {
+    /// Reserve `size` bytes of staging memory aligned to `alignment` in the current batch, and return the block and offset.
+    fn stage(&mut self, size: u64, alignment: u64) -> Result<(usize, u64)> {
+        ensure!(size > 0, "Cannot upload empty data");
+        if size > self.block_size {
+            let buffer = Buffer::new(self.device.clone(), &mut self.allocator, size, MemoryType::CpuToGpu)?;
+            self.batch.blocks.push(StagingBlock {
+                buffer,
+                used: size,
+                dedicated: true,
+            });
+            return Ok((self.batch.blocks.len() - 1, 0));
+        }
+        if let Some(index) = self.batch.current {
+            let block = &mut self.batch.blocks[index];
+            let offset = align(block.used, alignment);
+            if offset + size <= self.block_size {
+                block.used = offset + size;
+                return Ok((index, offset));
+            }
+        }
+        let buffer = match self.free_blocks.pop_front() {
+            Some(buffer) => buffer,
+            None => Buffer::new(self.device.clone(), &mut self.allocator, self.block_size, MemoryType::CpuToGpu)?,
+        };
+        self.batch.blocks.push(StagingBlock {
+            buffer,
+            used: size,
+            dedicated: false,
+        });
+        let index = self.batch.blocks.len() - 1;
+        self.batch.current = Some(index);
+        Ok((index, 0))
+    }
+
+    fn staging_slice(&mut self, block: usize, offset: u64, size: u64) -> Result<&mut [u8]> {
+        let mut view = self.batch.blocks[block].buffer.view(offset, size)?;
+        let slice = view.mapped_slice::<u8>()?;
+        // SAFETY: The memory is owned by the staging block, and the mutable borrow of self ensures it is not aliased.
+        Ok(unsafe { std::slice::from_raw_parts_mut(slice.as_mut_ptr(), slice.len()) })
+    }
+
+    fn queue(&mut self, copy: UploadCopy) -> Arc<UploadSignal> {
+        let signal = Arc::new(UploadSignal::default());
+        self.batch.copies.push(copy);
+        self.batch.signals.push(signal.clone());
+        self.batch.first_queued.get_or_insert_with(Instant::now);
+        signal
+    }
+
+    fn queue_buffer(&mut self, dst: BufferView, data: &[u8]) -> Result<Arc<UploadSignal>> {
+        if data.len() as u64 != dst.size() {
+            return Err(Error::InvalidBufferCopy {
+                src_size: data.len() as u64,
+                dst_size: dst.size(),
+            }
+            .into());
+        }
+        let (block, offset) = self.stage(dst.size(), REGION_ALIGNMENT)?;
+        self.staging_slice(block, offset, dst.size())?.copy_from_slice(data);
+        Ok(self.queue(UploadCopy::Buffer {
+            block,
+            offset,
+            dst,
+        }))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn queue_image(
+        &mut self,
+        image: vk::Image,
+        size: vk::Extent3D,
+        format: vk::Format,
+        range: vk::ImageSubresourceRange,
+        levels: &[MipLevelData],
+        old_layout: vk::ImageLayout,
+        new_layout: vk::ImageLayout,
+        exclusive: bool,
+    ) -> Result<Arc<UploadSignal>> {
+        ensure!(!levels.is_empty(), "Cannot upload empty data");
+        let (offsets, staging_size) = pack_mip_levels(
+            size,
+            format,
+            range.layer_count,
+            range.base_mip_level,
+            range.level_count,
+            levels,
+        )?;
+        let (block, offset) = self.stage(staging_size, region_alignment(format))?;
+        write_mip_levels(self.staging_slice(block, offset, staging_size)?, levels, &offsets);
+        Ok(self.queue(UploadCopy::Image {
+            block,
+            image,
+            range,
+            copies: image_copies(levels, &offsets, offset, range),
+            old_layout,
+            new_layout,
+            exclusive,
+        }))
+    }
+}
+
+impl<A: Allocator + 'static> UploadManager<A> {
+    /// Create a new upload manager with staging buffers of `block_size` bytes, and start its submission thread.
+    /// # Errors
+    /// * Fails if `block_size` is zero.
+    /// * Fails with [`Error::NoCapableQueue`] if there is no queue that supports transfer operations.
+    pub fn new(device: Device, exec: ExecutionManager<A>, allocator: A, block_size: u64) -> Result<Self> {
+        ensure!(block_size > 0, "Staging buffer size must not be zero");
+        let transfer_family = exec
+            .get_queue::<domain::Transfer>()
+            .ok_or(Error::NoCapableQueue)?
+            .info()
+            .family_index;
+        let graphics_family = exec
+            .get_queue::<domain::Graphics>()
+            .map(|queue| queue.info().family_index);
+        let shared = Arc::new(Shared {
+            state: Mutex::new(UploadState {
+                device,
+                allocator,
+                block_size,
+                max_free_blocks: DEFAULT_MAX_FREE_BLOCKS,
+                flush_interval: DEFAULT_FLUSH_INTERVAL,
+                free_blocks: VecDeque::new(),
+                batch: UploadBatch::default(),
+                flush_requested: false,
+                shutdown: false,
+            }),
+            wake: Condvar::new(),
+        });
+        let thread = {
+            let shared = shared.clone();
+            let context = SubmitContext {
+                exec,
+                graphics_family,
+            };
+            std::thread::Builder::new()
+                .name("phobos upload manager".to_string())
+                .spawn(move || run_submit_loop(shared, context))?
+        };
+        Ok(Self {
+            shared,
+            thread: Some(thread),
+            transfer_family,
+        })
+    }
+
+    /// Set the time after which queued uploads are submitted without a call to [`UploadManager::flush()`]. Defaults to 16 ms.
+    pub fn flush_interval(self, interval: Duration) -> Self {
+        self.shared.state.lock().unwrap().flush_interval = interval;
+        self
+    }
+
+    /// Set the maximum number of unused staging buffers kept in the ring. Staging buffers beyond this are freed once their uploads
+    /// completed. Defaults to 4.
+    pub fn max_free_blocks(self, count: usize) -> Self {
+        self.shared.state.lock().unwrap().max_free_blocks = count;
+        self
+    }
+
+    /// Get the index of the queue family uploads are submitted to. Exclusive images are released from this family after their
+    /// upload, see [`UploadManager::upload_image()`].
+    pub fn queue_family(&self) -> u32 {
+        self.transfer_family
+    }
+
+    /// Queue an upload of `data` into `dst`. The buffer must be kept alive until the upload completed.
+    /// # Errors
+    /// * Fails if `data` is empty, or its size is not equal to the size of `dst`.
+    /// * Fails if allocating a staging buffer fails.
+    pub fn upload_buffer<T: Pod>(&self, dst: &BufferView, data: &[T]) -> Result<QueuedUpload> {
+        let signal = self
+            .shared
+            .state
+            .lock()
+            .unwrap()
+            .queue_buffer(*dst, bytemuck::cast_slice(data))?;
+        self.shared.wake.notify_one();
+        Ok(QueuedUpload {
+            signal,
+            resource: Some(()),
+            owns_resource: false,
+        })
+    }
+
+    /// Queue an upload of regions of the mip levels of `image`. Mip levels in `levels` are relative to the base mip level of the view,
+    /// and each region covers all layers of the view.
+    ///
+    /// The subresources of the view are transitioned from `old_layout` to `new_layout`. Use [`vk::ImageLayout::UNDEFINED`] for
+    /// `old_layout` if the previous contents of the image can be discarded. The image must have been created with
+    /// [`vk::ImageUsageFlags::TRANSFER_DST`], and must be kept alive until the upload completed.
+    ///
+    /// Images with [`vk::SharingMode::EXCLUSIVE`] are owned by a single queue family. If uploads are submitted to another family
+    /// than the graphics queue, these images are released to the graphics queue family after the upload. Before using such an
+    /// image, acquire it on the graphics queue with
+    /// [`IncompleteCommandBuffer::acquire_image()`](crate::IncompleteCommandBuffer::acquire_image), with
+    /// [`UploadManager::queue_family()`] as the source family and `new_layout` as both layouts. This records nothing if no
+    /// ownership transfer was needed.
+    /// # Errors
+    /// * Fails if `levels` is empty, or a region is outside of its mip level.
+    /// * Fails if a region of a block-compressed image is not aligned to its blocks, or its data does not have the compressed size.
+    /// * Fails if allocating a staging buffer fails.
+    pub fn upload_image(
+        &self,
+        image: &ImageView,
+        levels: &[MipLevelData],
+        old_layout: vk::ImageLayout,
+        new_layout: vk::ImageLayout,
+    ) -> Result<QueuedUpload> {
+        let signal = self.shared.state.lock().unwrap().queue_image(
+            unsafe { image.image() },
+            image.size(),
+            image.format(),
+            image.subresource_range(),
+            levels,
+            old_layout,
+            new_layout,
+            image.sharing_mode() == vk::SharingMode::EXCLUSIVE,
+        )?;
+        self.shared.wake.notify_one();
+        Ok(QueuedUpload {
+            signal,
+            resource: Some(()),
+            owns_resource: false,
+        })
+    }
+
+    /// Create a device local buffer and queue an upload of `data` into it. The returned future resolves to the buffer.
+    /// # Errors
+    /// * Fails if `data` is empty.
+    /// * Fails if allocating the buffer or a staging buffer fails.
+    pub fn create_buffer<T: Pod>(&self, data: &[T]) -> Result<QueuedUpload<Buffer<A>>> {
+        let bytes: &[u8] = bytemuck::cast_slice(data);
+        ensure!(!bytes.is_empty(), "Cannot upload empty data");
+        let mut state = self.shared.state.lock().unwrap();
+        let buffer = Buffer::new_device_local(state.device.clone(), &mut state.allocator, bytes.len() as vk::DeviceSize)?;
+        let signal = state.queue_buffer(buffer.view_full(), bytes)?;
+        drop(state);
+        self.shared.wake.notify_one();
+        Ok(QueuedUpload {
+            signal,
+            resource: Some(buffer),
+            owns_resource: true,
+        })
+    }
+
+    /// Create an image and queue an upload of regions of its mip levels. After the upload, the image is transitioned to `layout`.
+    /// Mip levels that are not uploaded are left undefined. `TRANSFER_DST` usage is always added to `info.usage`.
+    /// The returned future resolves to the image. Exclusive images must be acquired before use, see [`UploadManager::upload_image()`].
+    /// # Errors
+    /// * Fails if `levels` is empty, or a region is outside of its mip level.
+    /// * Fails if a region of a block-compressed image is not aligned to its blocks, or its data does not have the compressed size.
+    /// * Fails if creating the image or allocating a staging buffer fails.
+    pub fn create_image(
+        &self,
+        mut info: ImageCreateInfo,
+        levels: &[MipLevelData],
+        layout: vk::ImageLayout,
+    ) -> Result<QueuedUpload<Image<A>>> {
+        info.usage |= vk::ImageUsageFlags::TRANSFER_DST;
+        let mut state = self.shared.state.lock().unwrap();
+        let image = Image::new(state.device.clone(), &mut state.allocator, info)?;
+        let range = vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: info.mip_levels,
+            base_array_layer: 0,
+            layer_count: info.layers,
+        };
+        let signal = state.queue_image(
+            unsafe { image.handle() },
+            image.size(),
+            image.format(),
+            range,
+            levels,
+            vk::ImageLayout::UNDEFINED,
+            layout,
+            image.sharing_mode() == vk::SharingMode::EXCLUSIVE,
+        )?;
+        drop(state);
+        self.shared.wake.notify_one();
+        Ok(QueuedUpload {
+            signal,
+            resource: Some(image),
+            owns_resource: true,
+        })
+    }
+
+    /// Submit all queued uploads in a single command buffer. This does not block, the submission happens on the submission thread.
+    /// Usually called once per frame.
+    pub fn flush(&self) {
+        self.shared.state.lock().unwrap().flush_requested = true;
+        self.shared.wake.notify_one();
+    }
+}
+
+impl<A: Allocator + 'static> Drop for UploadManager<A> {
+    fn drop(&mut self) {
+        // The submission thread submits any remaining uploads and waits for them before exiting.
+        self.shared.state.lock().unwrap().shutdown = true;
+        self.shared.wake.notify_one();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Convert an error that occurred while submitting a batch into an error for each of its uploads.
+fn batch_error(error: &anyhow::Error) -> Error {
+    match error.downcast_ref::<Error>() {
+        Some(Error::DeviceLost | Error::VkError(vk::Result::ERROR_DEVICE_LOST)) => Error::DeviceLost,
+        Some(Error::VkError(result)) => Error::VkError(*result),
+        _ => match error.downcast_ref::<vk::Result>() {
+            Some(&vk::Result::ERROR_DEVICE_LOST) => Error::DeviceLost,
+            Some(result) => Error::VkError(*result),
+            None => Error::Uncategorized("Failed to submit uploads."),
+        },
+    }
+}
+
+/// Copy an error returned from [`batch_error()`] for each upload of the batch.
+fn duplicate_error(error: &Error) -> Error {
+    match error {
+        Error::DeviceLost => Error::DeviceLost,
+        Error::VkError(result) => Error::VkError(*result),
+        _ => Error::Uncategorized("Failed to submit uploads."),
+    }
+}
+
+/// Record all copies of a batch into a single transfer command buffer and submit it.
+fn submit_batch<A: Allocator + 'static>(context: &SubmitContext<A>, batch: &UploadBatch<A>) -> Result<Pooled<Fence>> {
+    let exec = &context.exec;
+    let mut cmd = exec.on_domain::<domain::Transfer>()?;
+    // Exclusive images are released to the graphics queue family if uploads are not submitted on it.
+    let release_to = context
+        .graphics_family
+        .filter(|&family| family != cmd.queue_family());
+    for copy in &batch.copies {
+        cmd = match copy {
+            UploadCopy::Buffer {
+                block,
+                offset,
+                dst,
+            } => {
+                let src = batch.blocks[*block].buffer.view(*offset, dst.size())?;
+                cmd.copy_buffer(&src, dst)?
+            }
+            UploadCopy::Image {
+                block,
+                image,
+                range,
+                copies,
+                old_layout,
+                new_layout,
+                exclusive,
+            } => {
+                unsafe {
+                    record_image_upload(
+                        cmd.device(),
+                        cmd.handle(),
+                        batch.blocks[*block].buffer.handle(),
+                        *image,
+                        *range,
+                        copies,
+                        *old_layout,
+                        *new_layout,
+                    );
+                }
+                match release_to.filter(|_| *exclusive) {
+                    Some(family) => release_uploaded_image(cmd, *image, *range, *new_layout, family),
+                    None => cmd,
+                }
+            }
+        };
+    }
+    exec.submit(cmd.finish()?)
+}
+
+/// Body of the submission thread. Submits queued batches when they are flushed or due, and completes submitted batches once
+/// their fence is signaled.
+fn run_submit_loop<A: Allocator + 'static>(shared: Arc<Shared<A>>, context: SubmitContext<A>) {
+    let mut submitted: Vec<SubmittedBatch<A>> = vec![];
+    loop {
+        let mut state = shared.state.lock().unwrap();
+        let is_due = |state: &UploadState<A>| {
+            state.flush_requested
+                || state.shutdown
+                || state
+                    .batch
+                    .first_queued
+                    .is_some_and(|time| time.elapsed() >= state.flush_interval)
+        };
+        if !is_due(&state) {
+            let flush_timeout = state
+                .batch
+                .first_queued
+                .map(|time| state.flush_interval.saturating_sub(time.elapsed()));
+            let timeout = if submitted.is_empty() {
+                flush_timeout
+            } else {
+                Some(flush_timeout.map_or(FENCE_POLL_INTERVAL, |timeout| timeout.min(FENCE_POLL_INTERVAL)))
+            };
+            state = match timeout {
+                Some(timeout) => shared.wake.wait_timeout(state, timeout).unwrap().0,
+                None => shared.wake.wait(state).unwrap(),
+            };
+        }
+        let batch = if is_due(&state) {
+            state.flush_requested = false;
+            Some(std::mem::take(&mut state.batch))
+        } else {
+            None
+        };
+        let shutdown = state.shutdown;
+        drop(state);
+
+        let mut finished_blocks = vec![];
+        if let Some(batch) = batch.filter(|batch| !batch.copies.is_empty()) {
+            match submit_batch(&context, &batch) {
+                Ok(fence) => submitted.push(SubmittedBatch {
+                    fence,
+                    blocks: batch.blocks,
+                    signals: batch.signals,
+                }),
+                Err(e) => {
+                    error!("Failed to submit uploads: {e}");
+                    let error = batch_error(&e);
+                    for signal in &batch.signals {
+                        signal.complete(Err(duplicate_error(&error)));
+                    }
+                    // Nothing was submitted, so the staging buffers can be reused immediately.
+                    finished_blocks.extend(batch.blocks);
+                }
+            }
+        }
+
+        let mut pending = Vec::with_capacity(submitted.len());
+        for mut batch in submitted.drain(..) {
+            let result = match batch.fence.poll_status() {
+                Ok(false) => {
+                    pending.push(batch);
+                    continue;
+                }
+                // Waiting on a signaled fence returns immediately, this runs the cleanup that deletes the command buffer.
+                Ok(true) => batch.fence.wait().map(|_| ()).map_err(|e| batch_error(&e)),
+                Err(e) => {
+                    let _ = batch.fence.wait();
+                    Err(if e == vk::Result::ERROR_DEVICE_LOST {
+                        Error::DeviceLost
+                    } else {
+                        Error::VkError(e)
+                    })
+                }
+            };
+            for signal in &batch.signals {
+                signal.complete(result.as_ref().map(|_| ()).map_err(duplicate_error));
+            }
+            finished_blocks.extend(batch.blocks);
+        }
+        submitted = pending;
+
+        if !finished_blocks.is_empty() {
+            let mut state = shared.state.lock().unwrap();
+            for block in finished_blocks {
+                if !block.dedicated && state.free_blocks.len() < state.max_free_blocks {
+                    state.free_blocks.push_back(block.buffer);
+                }
+            }
+        }
+
+        if shutdown && submitted.is_empty() {
+            break;
+        }
+    }
+}
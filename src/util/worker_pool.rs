@@ -0,0 +1,63 @@
+//! A small pool of worker threads for running background jobs.
+
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::JoinHandle;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Runs jobs on a fixed number of worker threads, in the order they were submitted. Dropping the pool waits until all
+/// submitted jobs have finished.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub(crate) struct WorkerPool {
+    #[derivative(Debug = "ignore")]
+    sender: Option<Mutex<mpsc::Sender<Job>>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl WorkerPool {
+    /// Spawn a new pool with `threads` worker threads, named `{name} {index}`.
+    pub fn new(name: &str, threads: usize) -> Self {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let workers = (0..threads.max(1))
+            .map(|index| {
+                let receiver = receiver.clone();
+                std::thread::Builder::new()
+                    .name(format!("{name} {index}"))
+                    .spawn(move || loop {
+                        // Only hold the lock while waiting for a job, so other workers can pick up jobs while this one runs.
+                        let job = receiver.lock().unwrap().recv();
+                        match job {
+                            Ok(job) => job(),
+                            // The pool was dropped.
+                            Err(_) => break,
+                        }
+                    })
+                    .expect("Failed to spawn worker thread")
+            })
+            .collect();
+        Self {
+            sender: Some(Mutex::new(sender)),
+            workers,
+        }
+    }
+
+    /// Run a job on the next available worker thread.
+    pub fn spawn(&self, job: impl FnOnce() + Send + 'static) {
+        if let Some(sender) = &self.sender {
+            // Sending can only fail if all workers exited, which only happens once the pool is dropped.
+            let _ = sender.lock().unwrap().send(Box::new(job));
+        }
+    }
+}
+
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        // Closing the channel makes the workers exit once all remaining jobs are done.
+        self.sender = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
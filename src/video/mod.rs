@@ -0,0 +1,726 @@
+//! Hardware accelerated video decoding through Vulkan Video (`VK_KHR_video_queue`, `VK_KHR_video_decode_queue` and
+//! `VK_KHR_video_decode_h264`).
+//!
+//! Video decoding must be enabled with [`AppBuilder::video_decode()`](crate::AppBuilder::video_decode), and a queue must be
+//! requested with [`QueueType::VideoDecode`](crate::QueueType::VideoDecode). Decode commands are recorded on command buffers of the
+//! [`VideoDecode`](crate::domain::VideoDecode) domain, through the [`VideoDecodeCmdBuffer`](crate::VideoDecodeCmdBuffer) trait.
+//!
+//! Phobos does not parse bitstreams. The application is responsible for extracting parameter sets, slice data and
+//! reference picture information from the stream, and for deciding which pictures are kept as references. Decoding a stream needs:
+//! - A [`VideoProfile`] describing the codec. Query what the implementation supports for this profile with
+//!   [`VideoProfile::decode_capabilities()`] and [`VideoProfile::formats()`].
+//! - A [`VideoSession`], which holds the decoder state and its memory.
+//! - [`VideoSessionParameters`] holding the sequence and picture parameter sets of the stream.
+//! - A [`DecodedPictureBuffer`], holding the reconstructed pictures used as references by later pictures.
+//! - Output images, created with [`VideoProfile::create_image()`]. These are regular [`Image`]s, so they can be sampled or copied
+//!   from in a pass graph after decoding. Import them with [`DECODE_OUTPUT_STATE`] so the graph knows how they were last used.
+//! - A bitstream buffer created with [`VideoProfile::create_bitstream_buffer()`], holding the slice data of each picture.
+//!
+//! # Example
+//! ```
+//! # use anyhow::Result;
+//! # use phobos::prelude::*;
+//! # use phobos::video::*;
+//! fn decode_idr<'q>(
+//!     cmd: IncompleteCommandBuffer<'q, domain::VideoDecode>,
+//!     session: &VideoSession,
+//!     parameters: &VideoSessionParameters,
+//!     dpb: &mut DecodedPictureBuffer,
+//!     output: &ImageView,
+//!     bitstream: BufferView,
+//!     picture_info: vk::native::StdVideoDecodeH264PictureInfo,
+//!     reference_info: vk::native::StdVideoDecodeH264ReferenceInfo,
+//! ) -> Result<IncompleteCommandBuffer<'q, domain::VideoDecode>> {
+//!     // An IDR picture does not use any references, so all slots can be reused.
+//!     dpb.release_all();
+//!     let slot = dpb.acquire_slot().unwrap();
+//!     let setup = dpb.picture(slot);
+//!     let output = VideoPicture::new(output.clone(), dpb.extent());
+//!     cmd.transition_image(
+//!             dpb.view(),
+//!             PipelineStage::TOP_OF_PIPE,
+//!             PipelineStage::VIDEO_DECODE_KHR,
+//!             vk::ImageLayout::UNDEFINED,
+//!             vk::ImageLayout::VIDEO_DECODE_DPB_KHR,
+//!             vk::AccessFlags2::NONE,
+//!             vk::AccessFlags2::VIDEO_DECODE_READ_KHR | vk::AccessFlags2::VIDEO_DECODE_WRITE_KHR,
+//!         )
+//!         .transition_image(
+//!             &output.view,
+//!             PipelineStage::TOP_OF_PIPE,
+//!             PipelineStage::VIDEO_DECODE_KHR,
+//!             vk::ImageLayout::UNDEFINED,
+//!             vk::ImageLayout::VIDEO_DECODE_DST_KHR,
+//!             vk::AccessFlags2::NONE,
+//!             vk::AccessFlags2::VIDEO_DECODE_WRITE_KHR,
+//!         )
+//!         // The slot being set up is not active yet, so it is bound without a slot index.
+//!         .begin_video_coding(session, Some(parameters), &[VideoReferenceSlot { slot_index: -1, picture: setup.clone() }])?
+//!         .reset_video_coding()?
+//!         .decode_h264(&H264DecodeInfo {
+//!             bitstream,
+//!             slice_offsets: vec![0],
+//!             std_picture_info: picture_info,
+//!             output,
+//!             setup_reference: Some(H264ReferencePicture {
+//!                 slot_index: slot as i32,
+//!                 picture: setup,
+//!                 std_reference_info: reference_info,
+//!             }),
+//!             references: vec![],
+//!         })?
+//!         .end_video_coding()
+//! }
+//! ```
+
+use std::ffi::c_void;
+
+use anyhow::{anyhow, ensure, Result};
+use ash::vk;
+use ash::vk::Handle;
+use ash::vk::native::{StdVideoDecodeH264PictureInfo, StdVideoDecodeH264ReferenceInfo, StdVideoH264LevelIdc,
+                      StdVideoH264PictureParameterSet, StdVideoH264ProfileIdc, StdVideoH264SequenceParameterSet};
+
+use crate::{
+    Allocation, Allocator, Buffer, BufferView, DefaultAllocator, Device, GraphResourceState, Image, ImageCreateInfo, ImageView,
+    MemoryType, PhysicalDevice, PipelineStage, QueueType,
+};
+use crate::core::device::ExtensionID;
+use crate::core::traits::{AsRaw, Nameable};
+use crate::resource::image::ImageViewCreateInfo;
+
+/// State of an image directly after it was written to by [`VideoDecodeCmdBuffer::decode_h264()`](crate::VideoDecodeCmdBuffer::decode_h264).
+/// Use this with [`PassGraph::import()`](crate::PassGraph::import) to use a decoded image in a pass graph.
+pub const DECODE_OUTPUT_STATE: GraphResourceState = GraphResourceState {
+    layout: vk::ImageLayout::VIDEO_DECODE_DST_KHR,
+    stage: PipelineStage::VIDEO_DECODE_KHR,
+    access: vk::AccessFlags2::VIDEO_DECODE_WRITE_KHR,
+};
+
+/// Describes the codec and format of a video stream. Images, buffers and sessions used for decoding are all created
+/// for a specific profile.
+#[derive(Debug)]
+pub struct VideoProfile {
+    // These are boxed so the pointers between them stay valid when the profile is moved.
+    codec: Box<vk::VideoDecodeH264ProfileInfoKHR>,
+    info: Box<vk::VideoProfileInfoKHR>,
+    list: Box<vk::VideoProfileListInfoKHR>,
+}
+
+// SAFETY: The raw pointers only point to the boxed structures owned by this profile, which are never mutated after creation.
+unsafe impl Send for VideoProfile {}
+
+unsafe impl Sync for VideoProfile {}
+
+impl VideoProfile {
+    /// Create a profile for decoding H.264 streams with 4:2:0 chroma subsampling and 8 bits per component, which covers
+    /// almost all H.264 content.
+    /// * `profile_idc` - The H.264 profile of the stream, such as `vk::native::StdVideoH264ProfileIdc_STD_VIDEO_H264_PROFILE_IDC_HIGH`.
+    /// * `picture_layout` - How interlaced pictures are laid out. Use [`vk::VideoDecodeH264PictureLayoutFlagsKHR::PROGRESSIVE`] for progressive content.
+    pub fn h264(profile_idc: StdVideoH264ProfileIdc, picture_layout: vk::VideoDecodeH264PictureLayoutFlagsKHR) -> Self {
+        let codec = Box::new(vk::VideoDecodeH264ProfileInfoKHR {
+            std_profile_idc: profile_idc,
+            picture_layout,
+            ..Default::default()
+        });
+        let info = Box::new(vk::VideoProfileInfoKHR {
+            p_next: codec.as_ref() as *const _ as *const c_void,
+            video_codec_operation: vk::VideoCodecOperationFlagsKHR::DECODE_H264,
+            chroma_subsampling: vk::VideoChromaSubsamplingFlagsKHR::TYPE_420,
+            luma_bit_depth: vk::VideoComponentBitDepthFlagsKHR::TYPE_8,
+            chroma_bit_depth: vk::VideoComponentBitDepthFlagsKHR::TYPE_8,
+            ..Default::default()
+        });
+        let list = Box::new(vk::VideoProfileListInfoKHR {
+            profile_count: 1,
+            p_profiles: info.as_ref(),
+            ..Default::default()
+        });
+        Self {
+            codec,
+            info,
+            list,
+        }
+    }
+
+    /// Get the `VkVideoProfileInfoKHR` structure of this profile, including the codec specific profile in its `pNext` chain.
+    pub fn info(&self) -> &vk::VideoProfileInfoKHR {
+        &self.info
+    }
+
+    /// Get a `VkVideoProfileListInfoKHR` containing only this profile. This must be in the `pNext` chain of images and buffers used
+    /// for decoding with this profile.
+    pub fn list(&self) -> &vk::VideoProfileListInfoKHR {
+        &self.list
+    }
+
+    /// Query the decode capabilities of the implementation for this profile.
+    /// # Errors
+    /// * Fails if [`ExtensionID::VideoDecodeH264`] is not enabled.
+    /// * Fails with [`vk::Result::ERROR_VIDEO_PROFILE_OPERATION_NOT_SUPPORTED_KHR`] or a similar error if the profile is not supported.
+    pub fn decode_capabilities(&self, device: &Device, physical_device: &PhysicalDevice) -> Result<VideoDecodeCapabilities> {
+        device.require_extension(ExtensionID::VideoDecodeH264)?;
+        let fns = device.video_queue().unwrap();
+        let mut h264 = vk::VideoDecodeH264CapabilitiesKHR::default();
+        let mut decode = vk::VideoDecodeCapabilitiesKHR {
+            p_next: &mut h264 as *mut _ as *mut c_void,
+            ..Default::default()
+        };
+        let mut capabilities = vk::VideoCapabilitiesKHR {
+            p_next: &mut decode as *mut _ as *mut c_void,
+            ..Default::default()
+        };
+        unsafe {
+            (fns.get_physical_device_video_capabilities_khr)(physical_device.handle(), self.info(), &mut capabilities)
+                .result()?;
+        }
+        Ok(VideoDecodeCapabilities {
+            flags: capabilities.flags,
+            decode_flags: decode.flags,
+            min_bitstream_buffer_offset_alignment: capabilities.min_bitstream_buffer_offset_alignment,
+            min_bitstream_buffer_size_alignment: capabilities.min_bitstream_buffer_size_alignment,
+            picture_access_granularity: capabilities.picture_access_granularity,
+            min_coded_extent: capabilities.min_coded_extent,
+            max_coded_extent: capabilities.max_coded_extent,
+            max_dpb_slots: capabilities.max_dpb_slots,
+            max_active_reference_pictures: capabilities.max_active_reference_pictures,
+            std_header_version: capabilities.std_header_version,
+            h264_max_level_idc: h264.max_level_idc,
+        })
+    }
+
+    /// Query the image formats that can be used for this profile with the given usage. Use
+    /// [`vk::ImageUsageFlags::VIDEO_DECODE_DST_KHR`] for output images, and [`vk::ImageUsageFlags::VIDEO_DECODE_DPB_KHR`]
+    /// for the decoded picture buffer.
+    /// # Errors
+    /// * Fails if [`ExtensionID::VideoQueue`] is not enabled.
+    /// * Fails if the profile is not supported.
+    pub fn formats(
+        &self,
+        device: &Device,
+        physical_device: &PhysicalDevice,
+        usage: vk::ImageUsageFlags,
+    ) -> Result<Vec<vk::VideoFormatPropertiesKHR>> {
+        device.require_extension(ExtensionID::VideoQueue)?;
+        let fns = device.video_queue().unwrap();
+        let info = vk::PhysicalDeviceVideoFormatInfoKHR {
+            p_next: self.list() as *const _ as *const c_void,
+            image_usage: usage,
+            ..Default::default()
+        };
+        let query = fns.get_physical_device_video_format_properties_khr;
+        let mut count = 0;
+        unsafe {
+            query(physical_device.handle(), &info, &mut count, std::ptr::null_mut()).result()?;
+        }
+        let mut formats = vec![vk::VideoFormatPropertiesKHR::default(); count as usize];
+        unsafe {
+            query(physical_device.handle(), &info, &mut count, formats.as_mut_ptr()).result()?;
+        }
+        formats.truncate(count as usize);
+        Ok(formats)
+    }
+
+    /// Create an image that can be used with this profile, for example a decode output image with
+    /// [`vk::ImageUsageFlags::VIDEO_DECODE_DST_KHR`] and [`vk::ImageUsageFlags::SAMPLED`] usage. The format must be one of the
+    /// formats returned by [`VideoProfile::formats()`] for this usage.
+    /// # Errors
+    /// * Fails if allocating the image fails.
+    pub fn create_image<A: Allocator>(&self, device: Device, allocator: &mut A, info: ImageCreateInfo) -> Result<Image<A>> {
+        Image::new_with_p_next(device, allocator, info, self.list() as *const _ as *const c_void)
+    }
+
+    /// Create a host visible buffer that bitstream data can be written to and decoded from with this profile. The offset and
+    /// size of the buffer views used for decoding must be aligned to the bitstream alignment in [`VideoDecodeCapabilities`].
+    /// # Errors
+    /// * Fails if allocating the buffer fails.
+    pub fn create_bitstream_buffer<A: Allocator>(
+        &self,
+        device: Device,
+        allocator: &mut A,
+        size: impl Into<vk::DeviceSize>,
+    ) -> Result<Buffer<A>> {
+        Buffer::new_with_p_next(
+            device,
+            allocator,
+            size,
+            MemoryType::CpuToGpu,
+            vk::BufferUsageFlags::VIDEO_DECODE_SRC_KHR,
+            self.list() as *const _ as *const c_void,
+        )
+    }
+}
+
+impl Clone for VideoProfile {
+    fn clone(&self) -> Self {
+        Self::h264(self.codec.std_profile_idc, self.codec.picture_layout)
+    }
+}
+
+/// Decode capabilities of the implementation for a [`VideoProfile`].
+#[derive(Debug, Copy, Clone)]
+pub struct VideoDecodeCapabilities {
+    /// General video capability flags
+    pub flags: vk::VideoCapabilityFlagsKHR,
+    /// Decode capability flags. These describe whether the decode output and the reconstructed picture in the DPB can or must
+    /// be the same image.
+    pub decode_flags: vk::VideoDecodeCapabilityFlagsKHR,
+    /// Required alignment of the offset of bitstream data
+    pub min_bitstream_buffer_offset_alignment: vk::DeviceSize,
+    /// Required alignment of the size of bitstream data
+    pub min_bitstream_buffer_size_alignment: vk::DeviceSize,
+    /// Granularity at which the implementation accesses picture data
+    pub picture_access_granularity: vk::Extent2D,
+    /// Smallest supported coded extent
+    pub min_coded_extent: vk::Extent2D,
+    /// Largest supported coded extent
+    pub max_coded_extent: vk::Extent2D,
+    /// Maximum number of DPB slots a session can be created with
+    pub max_dpb_slots: u32,
+    /// Maximum number of reference pictures a single decode can use
+    pub max_active_reference_pictures: u32,
+    /// Version of the codec standard headers supported by the implementation
+    pub std_header_version: vk::ExtensionProperties,
+    /// Highest supported H.264 level
+    pub h264_max_level_idc: StdVideoH264LevelIdc,
+}
+
+/// Settings for creating a [`VideoSession`]. All values must be within the limits of the [`VideoDecodeCapabilities`] of the profile.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct VideoSessionCreateInfo {
+    /// Format of the decode output images
+    pub picture_format: vk::Format,
+    /// Format of the decoded picture buffer
+    pub reference_picture_format: vk::Format,
+    /// Largest coded extent of pictures decoded with this session
+    pub max_coded_extent: vk::Extent2D,
+    /// Number of slots in the decoded picture buffer
+    pub max_dpb_slots: u32,
+    /// Maximum number of reference pictures a single decode can use
+    pub max_active_reference_pictures: u32,
+}
+
+/// Wrapper around a [`VkVideoSessionKHR`](vk::VideoSessionKHR), which holds the state of a decoder. The memory of the session
+/// is allocated and bound on creation.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct VideoSession<A: Allocator = DefaultAllocator> {
+    device: Device,
+    handle: vk::VideoSessionKHR,
+    #[derivative(Debug = "ignore")]
+    #[allow(dead_code)]
+    memory: Vec<A::Allocation>,
+    info: VideoSessionCreateInfo,
+}
+
+impl<A: Allocator> VideoSession<A> {
+    /// Create a new video session for decoding with a profile, and allocate its memory.
+    /// # Errors
+    /// * Fails if [`ExtensionID::VideoDecodeH264`] is not enabled.
+    /// * Fails if no [`QueueType::VideoDecode`] queue was requested.
+    /// * Fails if the profile is not supported, or `info` is outside the capabilities of the profile.
+    /// * Fails if allocating memory for the session fails.
+    pub fn new(
+        device: Device,
+        physical_device: &PhysicalDevice,
+        allocator: &mut A,
+        profile: &VideoProfile,
+        info: VideoSessionCreateInfo,
+    ) -> Result<Self> {
+        let capabilities = profile.decode_capabilities(&device, physical_device)?;
+        let queue = physical_device
+            .queues()
+            .iter()
+            .find(|queue| queue.queue_type == QueueType::VideoDecode)
+            .ok_or_else(|| anyhow!("Creating a video session requires a video decode queue"))?;
+        let fns = device.video_queue().unwrap();
+        let create_info = vk::VideoSessionCreateInfoKHR {
+            queue_family_index: queue.family_index,
+            p_video_profile: profile.info(),
+            picture_format: info.picture_format,
+            max_coded_extent: info.max_coded_extent,
+            reference_picture_format: info.reference_picture_format,
+            max_dpb_slots: info.max_dpb_slots,
+            max_active_reference_pictures: info.max_active_reference_pictures,
+            p_std_header_version: &capabilities.std_header_version,
+            ..Default::default()
+        };
+        let mut handle = vk::VideoSessionKHR::null();
+        unsafe {
+            (fns.create_video_session_khr)(device.handle().handle(), &create_info, std::ptr::null(), &mut handle).result()?;
+        }
+        #[cfg(feature = "log-objects")]
+        trace!("Created new VkVideoSessionKHR {handle:p}");
+
+        // Construct the session before allocating memory, so it is destroyed if allocating fails.
+        let mut session = Self {
+            device,
+            handle,
+            memory: vec![],
+            info,
+        };
+        session.bind_memory(allocator)?;
+        Ok(session)
+    }
+
+    fn bind_memory(&mut self, allocator: &mut A) -> Result<()> {
+        let fns = self.device.video_queue().unwrap();
+        let device = unsafe { self.device.handle().handle() };
+        let mut count = 0;
+        unsafe {
+            (fns.get_video_session_memory_requirements_khr)(device, self.handle, &mut count, std::ptr::null_mut()).result()?;
+        }
+        let mut requirements = vec![vk::VideoSessionMemoryRequirementsKHR::default(); count as usize];
+        unsafe {
+            (fns.get_video_session_memory_requirements_khr)(device, self.handle, &mut count, requirements.as_mut_ptr())
+                .result()?;
+        }
+
+        let mut binds = Vec::with_capacity(requirements.len());
+        for requirement in requirements.iter().take(count as usize) {
+            let memory = allocator.allocate("video_session", &requirement.memory_requirements, MemoryType::GpuOnly)?;
+            binds.push(vk::BindVideoSessionMemoryInfoKHR {
+                memory_bind_index: requirement.memory_bind_index,
+                memory: unsafe { memory.memory() },
+                memory_offset: memory.offset(),
+                memory_size: requirement.memory_requirements.size,
+                ..Default::default()
+            });
+            self.memory.push(memory);
+        }
+        unsafe {
+            (fns.bind_video_session_memory_khr)(device, self.handle, binds.len() as u32, binds.as_ptr()).result()?;
+        }
+        Ok(())
+    }
+
+    /// Get the settings this session was created with.
+    pub fn info(&self) -> &VideoSessionCreateInfo {
+        &self.info
+    }
+
+    /// Get unsafe access to the underlying `VkVideoSessionKHR` handle.
+    /// # Safety
+    /// Any vulkan calls that mutate the session may put the system in an undefined state.
+    pub unsafe fn handle(&self) -> vk::VideoSessionKHR {
+        self.handle
+    }
+}
+
+unsafe impl<A: Allocator> AsRaw for VideoSession<A> {
+    unsafe fn as_raw(&self) -> u64 {
+        self.handle().as_raw()
+    }
+}
+
+impl<A: Allocator> Nameable for VideoSession<A> {
+    const OBJECT_TYPE: vk::ObjectType = vk::ObjectType::VIDEO_SESSION_KHR;
+}
+
+impl<A: Allocator> Drop for VideoSession<A> {
+    fn drop(&mut self) {
+        #[cfg(feature = "log-objects")]
+        trace!("Destroying VkVideoSessionKHR {:p}", self.handle);
+        unsafe {
+            // Since we created this object successfully surely the extension is supported
+            let fns = self.device.video_queue().unwrap();
+            (fns.destroy_video_session_khr)(self.device.handle().handle(), self.handle, std::ptr::null());
+        }
+    }
+}
+
+/// Wrapper around a [`VkVideoSessionParametersKHR`](vk::VideoSessionParametersKHR), which holds the H.264 sequence and
+/// picture parameter sets used by a [`VideoSession`]. These must be dropped before the session they were created for.
+#[derive(Debug)]
+pub struct VideoSessionParameters {
+    device: Device,
+    handle: vk::VideoSessionParametersKHR,
+    update_sequence_count: u32,
+}
+
+impl VideoSessionParameters {
+    /// Create H.264 session parameters with an initial set of parameter sets. Any pointers inside the parameter sets, such as
+    /// scaling lists and VUI parameters, only need to be valid for the duration of this call.
+    /// * `max_sps_count` - Maximum number of sequence parameter sets these parameters can hold, including ones added later.
+    /// * `max_pps_count` - Maximum number of picture parameter sets these parameters can hold, including ones added later.
+    /// # Errors
+    /// * Fails if [`ExtensionID::VideoDecodeH264`] is not enabled.
+    /// * Fails if more parameter sets are given than the maximum counts.
+    pub fn h264<A: Allocator>(
+        device: Device,
+        session: &VideoSession<A>,
+        max_sps_count: u32,
+        max_pps_count: u32,
+        sps: &[StdVideoH264SequenceParameterSet],
+        pps: &[StdVideoH264PictureParameterSet],
+    ) -> Result<Self> {
+        device.require_extension(ExtensionID::VideoDecodeH264)?;
+        ensure!(sps.len() as u32 <= max_sps_count, "Too many H.264 sequence parameter sets");
+        ensure!(pps.len() as u32 <= max_pps_count, "Too many H.264 picture parameter sets");
+        let fns = device.video_queue().unwrap();
+        let add_info = vk::VideoDecodeH264SessionParametersAddInfoKHR {
+            std_sps_count: sps.len() as u32,
+            p_std_sp_ss: sps.as_ptr(),
+            std_pps_count: pps.len() as u32,
+            p_std_pp_ss: pps.as_ptr(),
+            ..Default::default()
+        };
+        let h264_info = vk::VideoDecodeH264SessionParametersCreateInfoKHR {
+            max_std_sps_count: max_sps_count,
+            max_std_pps_count: max_pps_count,
+            p_parameters_add_info: &add_info,
+            ..Default::default()
+        };
+        let info = vk::VideoSessionParametersCreateInfoKHR {
+            p_next: &h264_info as *const _ as *const c_void,
+            video_session: unsafe { session.handle() },
+            ..Default::default()
+        };
+        let mut handle = vk::VideoSessionParametersKHR::null();
+        unsafe {
+            (fns.create_video_session_parameters_khr)(device.handle().handle(), &info, std::ptr::null(), &mut handle)
+                .result()?;
+        }
+        #[cfg(feature = "log-objects")]
+        trace!("Created new VkVideoSessionParametersKHR {handle:p}");
+
+        Ok(Self {
+            device,
+            handle,
+            update_sequence_count: 0,
+        })
+    }
+
+    /// Add new H.264 parameter sets, for example when they change in the middle of a stream. Parameter sets with an ID that
+    /// was already added can not be replaced, create new session parameters instead.
+    /// # Errors
+    /// * Fails if the maximum number of parameter sets would be exceeded, or a parameter set ID already exists.
+    pub fn add_h264(&mut self, sps: &[StdVideoH264SequenceParameterSet], pps: &[StdVideoH264PictureParameterSet]) -> Result<()> {
+        let fns = self.device.video_queue().unwrap();
+        let add_info = vk::VideoDecodeH264SessionParametersAddInfoKHR {
+            std_sps_count: sps.len() as u32,
+            p_std_sp_ss: sps.as_ptr(),
+            std_pps_count: pps.len() as u32,
+            p_std_pp_ss: pps.as_ptr(),
+            ..Default::default()
+        };
+        let info = vk::VideoSessionParametersUpdateInfoKHR {
+            p_next: &add_info as *const _ as *const c_void,
+            update_sequence_count: self.update_sequence_count + 1,
+            ..Default::default()
+        };
+        unsafe {
+            (fns.update_video_session_parameters_khr)(self.device.handle().handle(), self.handle, &info).result()?;
+        }
+        self.update_sequence_count += 1;
+        Ok(())
+    }
+
+    /// Get unsafe access to the underlying `VkVideoSessionParametersKHR` handle.
+    /// # Safety
+    /// Any vulkan calls that mutate the session parameters may put the system in an undefined state.
+    pub unsafe fn handle(&self) -> vk::VideoSessionParametersKHR {
+        self.handle
+    }
+}
+
+impl Drop for VideoSessionParameters {
+    fn drop(&mut self) {
+        #[cfg(feature = "log-objects")]
+        trace!("Destroying VkVideoSessionParametersKHR {:p}", self.handle);
+        unsafe {
+            let fns = self.device.video_queue().unwrap();
+            (fns.destroy_video_session_parameters_khr)(self.device.handle().handle(), self.handle, std::ptr::null());
+        }
+    }
+}
+
+/// A picture inside an image, used as decode output or as a reference picture.
+#[derive(Debug, Clone)]
+pub struct VideoPicture {
+    /// Image view containing the picture
+    pub view: ImageView,
+    /// Array layer of the picture, relative to the base layer of the view
+    pub base_array_layer: u32,
+    /// Offset of the picture inside the image
+    pub coded_offset: vk::Offset2D,
+    /// Coded size of the picture
+    pub coded_extent: vk::Extent2D,
+}
+
+impl VideoPicture {
+    /// Create a picture covering the top left `coded_extent` pixels of the first layer of a view.
+    pub fn new(view: ImageView, coded_extent: vk::Extent2D) -> Self {
+        Self {
+            view,
+            base_array_layer: 0,
+            coded_offset: vk::Offset2D::default(),
+            coded_extent,
+        }
+    }
+
+    pub(crate) fn to_vk(&self) -> vk::VideoPictureResourceInfoKHR {
+        vk::VideoPictureResourceInfoKHR {
+            coded_offset: self.coded_offset,
+            coded_extent: self.coded_extent,
+            base_array_layer: self.base_array_layer,
+            image_view_binding: unsafe { self.view.handle() },
+            ..Default::default()
+        }
+    }
+}
+
+/// A picture bound to a slot of the decoded picture buffer for the duration of a video coding scope.
+#[derive(Debug, Clone)]
+pub struct VideoReferenceSlot {
+    /// Index of the DPB slot, or `-1` to bind a picture that will be set up as a new reference in this scope.
+    pub slot_index: i32,
+    /// The picture associated with the slot
+    pub picture: VideoPicture,
+}
+
+/// An H.264 reference picture in the decoded picture buffer.
+#[derive(Debug, Clone)]
+pub struct H264ReferencePicture {
+    /// Index of the DPB slot
+    pub slot_index: i32,
+    /// The picture associated with the slot
+    pub picture: VideoPicture,
+    /// H.264 specific reference information, such as the frame number and picture order count
+    pub std_reference_info: StdVideoDecodeH264ReferenceInfo,
+}
+
+/// All information needed to decode a single H.264 picture.
+#[derive(Debug, Clone)]
+pub struct H264DecodeInfo {
+    /// Bitstream data of the picture. The offset and size must be aligned to the bitstream alignment in [`VideoDecodeCapabilities`].
+    pub bitstream: BufferView,
+    /// Byte offsets of each slice of the picture, relative to the start of `bitstream`
+    pub slice_offsets: Vec<u32>,
+    /// H.264 specific picture information, such as the parameter set IDs and picture order count
+    pub std_picture_info: StdVideoDecodeH264PictureInfo,
+    /// Picture the decoded output is written to
+    pub output: VideoPicture,
+    /// DPB slot the reconstructed picture is stored in, if it is used as a reference by later pictures.
+    pub setup_reference: Option<H264ReferencePicture>,
+    /// Reference pictures used to decode this picture
+    pub references: Vec<H264ReferencePicture>,
+}
+
+/// The decoded picture buffer (DPB) holds reconstructed pictures that later pictures are predicted from. This is a single
+/// array image with a layer for each slot, so it works on implementations that do not support separate reference images.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct DecodedPictureBuffer<A: Allocator = DefaultAllocator> {
+    #[derivative(Debug = "ignore")]
+    image: Image<A>,
+    view: ImageView,
+    active: Vec<bool>,
+    extent: vk::Extent2D,
+}
+
+impl<A: Allocator> DecodedPictureBuffer<A> {
+    /// Create a new decoded picture buffer for a profile.
+    /// * `format` - Must be one of the formats returned by [`VideoProfile::formats()`] with [`vk::ImageUsageFlags::VIDEO_DECODE_DPB_KHR`].
+    /// * `usage` - Extra usage flags, for example [`vk::ImageUsageFlags::VIDEO_DECODE_DST_KHR`] if the output and reconstructed pictures coincide.
+    /// # Errors
+    /// * Fails if allocating the image fails.
+    pub fn new(
+        device: Device,
+        allocator: &mut A,
+        profile: &VideoProfile,
+        format: vk::Format,
+        extent: vk::Extent2D,
+        slots: u32,
+        usage: vk::ImageUsageFlags,
+    ) -> Result<Self> {
+        let image = profile.create_image(
+            device,
+            allocator,
+            ImageCreateInfo {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+                usage: usage | vk::ImageUsageFlags::VIDEO_DECODE_DPB_KHR,
+                format,
+                samples: vk::SampleCountFlags::TYPE_1,
+                mip_levels: 1,
+                layers: slots,
+                flags: vk::ImageCreateFlags::empty(),
+                memory_type: MemoryType::GpuOnly,
+            },
+        )?;
+        let view = image.view(ImageViewCreateInfo {
+            aspect: vk::ImageAspectFlags::COLOR,
+            view_type: vk::ImageViewType::TYPE_2D_ARRAY,
+            base_mip_level: 0,
+            level_count: None,
+            base_layer: 0,
+            layers: None,
+            format: None,
+            swizzle: Default::default(),
+            usage: None,
+        })?;
+        Ok(Self {
+            image,
+            view,
+            active: vec![false; slots as usize],
+            extent,
+        })
+    }
+
+    /// Get the underlying image.
+    pub fn image(&self) -> &Image<A> {
+        &self.image
+    }
+
+    /// Get a view of all slots of the DPB. Use this to transition the DPB to [`vk::ImageLayout::VIDEO_DECODE_DPB_KHR`] before
+    /// its first use.
+    pub fn view(&self) -> &ImageView {
+        &self.view
+    }
+
+    /// Get the size of the pictures in the DPB.
+    pub fn extent(&self) -> vk::Extent2D {
+        self.extent
+    }
+
+    /// Get the number of slots in the DPB.
+    pub fn slots(&self) -> u32 {
+        self.active.len() as u32
+    }
+
+    /// Get the picture of a slot.
+    pub fn picture(&self, slot: u32) -> VideoPicture {
+        VideoPicture {
+            view: self.view.clone(),
+            base_array_layer: slot,
+            coded_offset: vk::Offset2D::default(),
+            coded_extent: self.extent,
+        }
+    }
+
+    /// Find a slot that is not holding a reference picture, and mark it as used. Returns `None` if all slots are in use.
+    pub fn acquire_slot(&mut self) -> Option<u32> {
+        let slot = self.active.iter().position(|active| !active)?;
+        self.active[slot] = true;
+        Some(slot as u32)
+    }
+
+    /// Mark a slot as no longer holding a reference picture, so it can be reused.
+    pub fn release_slot(&mut self, slot: u32) {
+        if let Some(active) = self.active.get_mut(slot as usize) {
+            *active = false;
+        }
+    }
+
+    /// Mark all slots as unused, for example when decoding an IDR picture.
+    pub fn release_all(&mut self) {
+        self.active.fill(false);
+    }
+
+    /// Whether a slot is currently holding a reference picture.
+    pub fn is_active(&self, slot: u32) -> bool {
+        self.active.get(slot as usize).copied().unwrap_or(false)
+    }
+}
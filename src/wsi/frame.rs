@@ -63,26 +63,97 @@
 //!         }
 //! });
 //! ```
+//!
+//! # Swapchain recreation
+//!
+//! The swapchain is recreated automatically when it goes out of date or becomes suboptimal, for example because the window was resized.
+//! While the window is minimized, [`FrameManager::new_frame`] skips the frame without calling its callback. Resources that depend on
+//! the swapchain, such as attachments with the same size, can be recreated in a callback registered with
+//! [`FrameManager::on_swapchain_recreated()`].
+//!
+//! # Multiple windows
+//!
+//! Each window is driven by its own [`FrameManager`], with its own surface, swapchain and per-frame synchronization. All frame managers
+//! share the same device, [`ExecutionManager`] and resource pool. Additional windows can be created at any time after initialization
+//! with [`FrameManager::new_for_window()`], as long as the context was initialized with a window so swapchain support is enabled.
+//! ```
+//! # use anyhow::Result;
+//! # use phobos::prelude::*;
+//! fn open_window<W: WindowInterface>(
+//!     instance: &Instance,
+//!     physical_device: &PhysicalDevice,
+//!     device: &Device,
+//!     pool: &ResourcePool,
+//!     settings: &AppSettings<W>,
+//!     window: &W,
+//! ) -> Result<(Surface, FrameManager)> {
+//!     // The surface must be kept alive and passed to FrameManager::new_frame() for this window.
+//!     FrameManager::new_for_window(instance, physical_device, device.clone(), pool.clone(), settings, window)
+//! }
+//! ```
+//!
+//! # Frame pacing
+//!
+//! By default, a new frame is started as soon as the previous one was submitted, so a main loop using `ControlFlow::Poll` keeps a CPU
+//! core busy. A [`FramePacer`] set with [`FrameManager::set_frame_pacer()`] limits the frame rate, and if present timing is enabled
+//! through [`AppBuilder::present_timing()`](crate::AppBuilder::present_timing) it also limits the number of queued frames and measures
+//! present latency.
+//! ```
+//! # use phobos::prelude::*;
+//! fn limit_frame_rate(frame: &mut FrameManager) {
+//!     frame.set_frame_pacer(Some(FramePacer::new().target_fps(Some(60.0)).max_queued_frames(1)));
+//! }
+//!
+//! fn report_latency(frame: &FrameManager) {
+//!     if let Some(latency) = frame.frame_pacer().and_then(|pacer| pacer.present_latency()) {
+//!         println!("Present latency: {latency:?}");
+//!     }
+//! }
+//! ```
+//!
+//! With present timing enabled, every present also gets an identifier. [`FrameManager::wait_for_present()`] blocks until a specific
+//! frame is displayed, and [`FrameManager::wait_for_last_present()`] can be called before starting a new frame so its input is
+//! sampled as late as possible.
+//!
+//! # Exclusive fullscreen
+//!
+//! On Windows, `VK_EXT_full_screen_exclusive` can be enabled with [`AppBuilder::full_screen_exclusive()`](crate::AppBuilder::full_screen_exclusive).
+//! With [`vk::FullScreenExclusiveEXT::APPLICATION_CONTROLLED`], exclusive mode is entered with [`FrameManager::acquire_full_screen_exclusive()`]
+//! once the window is fullscreen, and left with [`FrameManager::release_full_screen_exclusive()`]. When exclusive mode is lost, for example
+//! because the window lost focus, the swapchain is recreated and exclusive mode must be acquired again.
+//!
+//! # Frame generation
+//!
+//! Frame generation needs to pace frames and present interpolated frames in between real frames. This is done by a [`PresentHook`]
+//! registered with [`FrameManager::set_present_hook()`]. With the `fsr3` feature, [`Fsr3FrameGenerator`](crate::fsr3::Fsr3FrameGenerator)
+//! implements this hook using the frame generation of the FidelityFX SDK. Other libraries can be integrated by implementing
+//! [`PresentHook`] directly. Upscaling is separate from this, see the [`upscaler`](crate::upscaler) module.
 
+use std::collections::VecDeque;
+use std::ffi::c_void;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use anyhow::Result;
+use anyhow::{ensure, Result};
+use ash::prelude::VkResult;
 use ash::vk;
 
 use crate::{
     Allocator, AppSettings, CmdBuffer, DefaultAllocator, Device, Error, ExecutionManager, Fence,
-    Image, ImageView, Instance, Semaphore, Surface, Swapchain, WindowInterface,
+    Image, ImageView, Instance, PhysicalDevice, Semaphore, Surface, Swapchain, WindowInterface,
 };
+use crate::core::device::ExtensionID;
 use crate::pool::{Poolable, Pooled, ResourcePool};
 use crate::sync::domain::ExecutionDomain;
 use crate::sync::submit_batch::SubmitBatch;
 use crate::util::deferred_delete::DeletionQueue;
-use crate::wsi::swapchain::SwapchainImage;
+use crate::wsi::swapchain;
+use crate::wsi::swapchain::{FullScreenExclusive, SwapchainImage};
 
 /// Information stored for each in-flight frame.
 #[derive(Derivative)]
 #[derivative(Debug)]
-struct PerFrame<A> {
+struct FrameData<A> {
     #[derivative(Debug = "ignore")]
     pub fence: Pooled<Fence<()>>,
     /// Signaled by the GPU when a swapchain image is ready.
@@ -113,8 +184,23 @@ struct PerFrame<A> {
 pub struct InFlightContext {
     /// The current frame's swapchain image
     pub swapchain_image: ImageView,
-    pub(crate) wait_semaphore: Arc<Semaphore>,
-    pub(crate) signal_semaphore: Arc<Semaphore>,
+    // Both semaphores are `None` for headless frames, which have no swapchain image to wait on or present.
+    pub(crate) wait_semaphore: Option<Arc<Semaphore>>,
+    pub(crate) signal_semaphore: Option<Arc<Semaphore>>,
+    pub(crate) frame_index: usize,
+    pub(crate) image_index: usize,
+}
+
+impl InFlightContext {
+    /// Index of the current in-flight frame, in the range `[0, FRAMES_IN_FLIGHT)`.
+    pub fn frame_index(&self) -> usize {
+        self.frame_index
+    }
+
+    /// Index of the current swapchain image, in the range `[0, FrameManager::image_count())`.
+    pub fn image_index(&self) -> usize {
+        self.image_index
+    }
 }
 
 /// The number of frames in flight. A frame in-flight is a frame that is rendering on the GPU or scheduled to do so.
@@ -122,25 +208,500 @@ pub struct InFlightContext {
 /// This gives a good amount of parallelization while avoiding input lag.
 pub const FRAMES_IN_FLIGHT: usize = 2;
 
+/// Container holding one value of `T` for each frame in flight. This replaces manually indexed `[T; 2]` arrays for CPU-side data
+/// that is used by the GPU during a frame, such as staging data or per-frame descriptor contents.
+///
+/// Slots can only be mutably accessed through an [`InFlightContext`]. Since the [`FrameManager`] waits on the fence of a frame
+/// before handing out its context, the GPU is guaranteed to be done with the slot of the current frame, while the slots of other frames
+/// may still be in use.
+/// # Example
+/// ```
+/// # use phobos::prelude::*;
+/// # use phobos::wsi::frame::PerFrame;
+/// let mut uniforms = PerFrame::new(|_| [0.0f32; 16]);
+/// frame.new_frame(exec.clone(), window, &surface, |ifc| {
+///     // The GPU is no longer reading the values for this frame, so we can safely overwrite them.
+///     let data = uniforms.current_mut(&ifc);
+///     data[0] = 1.0;
+///     // Record commands using data
+/// })?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct PerFrame<T> {
+    slots: [T; FRAMES_IN_FLIGHT],
+}
+
+impl<T> PerFrame<T> {
+    /// Create a new per-frame container, calling `f` with the index of each frame in flight to initialize its slot.
+    pub fn new(f: impl FnMut(usize) -> T) -> Self {
+        Self {
+            slots: std::array::from_fn(f),
+        }
+    }
+
+    /// Get the value for the frame that is currently being recorded.
+    pub fn current(&self, ifc: &InFlightContext) -> &T {
+        &self.slots[ifc.frame_index()]
+    }
+
+    /// Get mutable access to the value for the frame that is currently being recorded. The GPU is guaranteed to be
+    /// done with this value.
+    pub fn current_mut(&mut self, ifc: &InFlightContext) -> &mut T {
+        &mut self.slots[ifc.frame_index()]
+    }
+
+    /// Iterate over the values of all frames in flight. Values other than the current one may still be in use by the GPU,
+    /// so this only hands out shared references.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.slots.iter()
+    }
+}
+
+impl<T: Default> Default for PerFrame<T> {
+    fn default() -> Self {
+        Self::new(|_| T::default())
+    }
+}
+
+type RebuildFn<T> = Box<dyn FnMut(usize, &ImageView) -> Result<T>>;
+
+/// Container holding one value of `T` for each swapchain image, for resources that refer to a specific swapchain image such as
+/// framebuffers or per-image descriptor sets. Values are built by a rebuild callback the first time their image is used.
+///
+/// When the swapchain is recreated, for example after a resize, all values are dropped and rebuilt for the new swapchain images.
+/// Frames in flight may still be using the old values, so they are kept alive for a few more frames before they are dropped.
+/// # Example
+/// ```
+/// # use anyhow::Result;
+/// # use phobos::prelude::*;
+/// # use phobos::wsi::frame::PerImage;
+/// struct Target {
+///     view: ImageView,
+/// }
+///
+/// fn render(targets: &mut PerImage<Target>, ifc: &InFlightContext) -> Result<()> {
+///     // Built the first time this swapchain image is used, and again after the swapchain was recreated.
+///     let target = targets.current_mut(ifc)?;
+///     // Record commands using target.view
+///     Ok(())
+/// }
+///
+/// let targets = PerImage::new(|_index, image: &ImageView| Ok(Target { view: image.clone() }));
+/// ```
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct PerImage<T> {
+    /// Values for each swapchain image, together with the ID of the image view they were built for.
+    slots: Vec<Option<(u64, T)>>,
+    #[derivative(Debug = "ignore")]
+    rebuild: RebuildFn<T>,
+    /// Values of previous swapchains, which may still be in use by frames in flight.
+    #[derivative(Debug = "ignore")]
+    retired: DeletionQueue<Vec<T>>,
+    last_frame: Option<usize>,
+}
+
+impl<T> PerImage<T> {
+    /// Create a new per-image container. `rebuild` is called with the swapchain image index and the swapchain image to build the
+    /// value for that image.
+    pub fn new(rebuild: impl FnMut(usize, &ImageView) -> Result<T> + 'static) -> Self {
+        Self {
+            slots: vec![],
+            rebuild: Box::new(rebuild),
+            // Same lifetime as old swapchains in the frame manager, so old values outlive the images they refer to.
+            retired: DeletionQueue::new((FRAMES_IN_FLIGHT + 2) as u32),
+            last_frame: None,
+        }
+    }
+
+    /// Get mutable access to the value for the swapchain image of the current frame, building it first if needed.
+    /// The swapchain image is only acquired once the GPU is done with it, so the GPU is not using this value.
+    /// # Errors
+    /// * Fails if the rebuild callback fails.
+    pub fn current_mut(&mut self, ifc: &InFlightContext) -> Result<&mut T> {
+        // Frames in flight are used in turn, so a different frame index means a new frame started.
+        if self.last_frame != Some(ifc.frame_index()) {
+            self.last_frame = Some(ifc.frame_index());
+            self.retired.next_frame();
+        }
+        let id = ifc.swapchain_image.id();
+        let index = ifc.image_index();
+        // Swapchain images get new views when the swapchain is recreated, so a different view means all values are outdated.
+        if matches!(self.slots.get(index), Some(Some((built_for, _))) if *built_for != id) {
+            self.invalidate();
+        }
+        if self.slots.len() <= index {
+            self.slots.resize_with(index + 1, || None);
+        }
+        let slot = &mut self.slots[index];
+        if slot.is_none() {
+            *slot = Some((id, (self.rebuild)(index, &ifc.swapchain_image)?));
+        }
+        Ok(&mut slot.as_mut().unwrap().1)
+    }
+
+    /// Drop all values, so they are rebuilt the next time they are used. This is done automatically when the swapchain is
+    /// recreated. Values that may still be in use by frames in flight are dropped a few frames later.
+    pub fn invalidate(&mut self) {
+        let values = self.slots.drain(..).flatten().map(|(_, value)| value).collect();
+        self.retired.push(values);
+    }
+
+    /// Iterate over the values that are currently built, together with their swapchain image index. Values other than the
+    /// current one may still be in use by the GPU, so this only hands out shared references.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &T)> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(index, slot)| slot.as_ref().map(|(_, value)| (index, value)))
+    }
+}
+
+/// Information about a frame that is about to be presented, passed to [`PresentHook::present()`].
+pub struct PresentContext<'a> {
+    /// The queue to present on
+    pub queue: vk::Queue,
+    /// Family index of the queue to present on. Command pools for work submitted to this queue must be created for this family.
+    pub queue_family_index: u32,
+    /// The swapchain the image belongs to. This also gives access to the `VK_KHR_swapchain` functions.
+    pub swapchain: &'a Swapchain,
+    /// Index of the swapchain image to present
+    pub image_index: u32,
+    /// Semaphore that is signaled when all commands of this frame have completed.
+    pub wait_semaphore: vk::Semaphore,
+    /// Index of the frame in flight
+    pub frame_index: usize,
+    /// Extension structures to chain into `VkPresentInfoKHR`, such as the present identifier used by a [`FramePacer`].
+    /// May be null.
+    pub(crate) p_next: *const c_void,
+}
+
+impl PresentContext<'_> {
+    /// Get the present info to present this frame with. The extension structures phobos needs, such as the present identifier
+    /// used by a [`FramePacer`], are already chained in. The returned structure points into this context, so it must not
+    /// outlive it.
+    pub fn present_info(&self) -> vk::PresentInfoKHR {
+        vk::PresentInfoKHR {
+            s_type: vk::StructureType::PRESENT_INFO_KHR,
+            p_next: self.p_next,
+            wait_semaphore_count: 1,
+            p_wait_semaphores: &self.wait_semaphore,
+            swapchain_count: 1,
+            p_swapchains: &self.swapchain.handle,
+            p_image_indices: &self.image_index,
+            p_results: std::ptr::null_mut(),
+        }
+    }
+}
+
+/// Hook into frame presentation, used to implement frame pacing and frame generation. See [`FrameManager::set_present_hook()`].
+pub trait PresentHook {
+    /// Called before a swapchain image is acquired for a new frame. Frame pacing can be implemented by blocking here.
+    /// # Errors
+    /// Errors are propagated to [`FrameManager::new_frame()`].
+    fn before_acquire(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called after the swapchain was recreated because it went out of date, and when the hook is set with
+    /// [`FrameManager::set_present_hook()`]. Any resources depending on the swapchain images must be (re)created here.
+    /// # Errors
+    /// Errors are propagated to [`FrameManager::new_frame()`].
+    fn swapchain_recreated(&mut self, _swapchain: &Swapchain) -> Result<()> {
+        Ok(())
+    }
+
+    /// Present a frame. Frame generation implementations can present interpolated frames before the real frame here.
+    /// The default implementation presents the image once. Returns whether the swapchain is suboptimal.
+    /// # Errors
+    /// Errors are propagated to [`FrameManager::new_frame()`], except for `VK_ERROR_OUT_OF_DATE_KHR`, which causes the swapchain
+    /// to be recreated before the next frame.
+    fn present(&mut self, ctx: &PresentContext) -> VkResult<bool> {
+        unsafe { ctx.swapchain.queue_present(ctx.queue, &ctx.present_info()) }
+    }
+}
+
+/// Maximum time in nanoseconds to block waiting for a previous frame to be presented, so a window that is not presented to
+/// (for example because it is occluded) does not stall the application.
+const PRESENT_WAIT_TIMEOUT: u64 = 100_000_000;
+
+/// Optional frame pacing for a [`FrameManager`], see [`FrameManager::set_frame_pacer()`].
+///
+/// The frame pacer limits the frame rate to a target FPS by sleeping before a new frame is started, instead of rendering as
+/// many frames as possible. If present timing is enabled through [`AppBuilder::present_timing()`](crate::AppBuilder::present_timing),
+/// it additionally uses:
+/// - `VK_KHR_present_wait` to limit the number of frames queued for presentation, and to measure present latency.
+/// - `VK_GOOGLE_display_timing` to query the refresh rate and past presentation timings, and to schedule presents at the target frame rate.
+#[derive(Debug, Clone)]
+pub struct FramePacer {
+    target_frame_time: Option<Duration>,
+    max_queued_frames: u64,
+    next_deadline: Option<Instant>,
+    last_frame: Option<Instant>,
+    frame_time: Duration,
+    // Present identifiers are only valid for the swapchain they were presented to, so we never wait on identifiers
+    // up to and including this one.
+    swapchain_first_id: u64,
+    // Host time at which each present identifier was submitted, used to measure present latency.
+    submit_times: VecDeque<(u64, Instant)>,
+    present_latency: Option<Duration>,
+    refresh_duration: Option<Duration>,
+    last_presentation_timing: Option<vk::PastPresentationTimingGOOGLE>,
+}
+
+impl FramePacer {
+    /// Create a frame pacer without a frame rate limit, that allows a single frame to be queued for presentation.
+    pub fn new() -> Self {
+        Self {
+            target_frame_time: None,
+            max_queued_frames: 1,
+            next_deadline: None,
+            last_frame: None,
+            frame_time: Duration::ZERO,
+            swapchain_first_id: 0,
+            submit_times: VecDeque::new(),
+            present_latency: None,
+            refresh_duration: None,
+            last_presentation_timing: None,
+        }
+    }
+
+    /// Limit the frame rate to `fps` frames per second. Pass `None` to render frames as fast as possible.
+    pub fn target_fps(mut self, fps: Option<f64>) -> Self {
+        self.set_target_fps(fps);
+        self
+    }
+
+    /// Set the maximum number of presented frames that may still be waiting to be displayed when a new frame is started.
+    /// Lower values reduce latency, higher values make it less likely to miss a refresh. Values lower than one are clamped to one.
+    /// This only has an effect if [`ExtensionID::PresentWait`](crate::core::device::ExtensionID::PresentWait) is enabled.
+    pub fn max_queued_frames(mut self, frames: u32) -> Self {
+        self.max_queued_frames = frames.max(1) as u64;
+        self
+    }
+
+    /// Change the frame rate limit. Pass `None` to render frames as fast as possible.
+    pub fn set_target_fps(&mut self, fps: Option<f64>) {
+        self.target_frame_time = fps
+            .filter(|fps| *fps > 0.0)
+            .map(|fps| Duration::from_secs_f64(1.0 / fps));
+        self.next_deadline = None;
+    }
+
+    /// Get the current frame rate limit, or `None` if there is no limit.
+    pub fn get_target_fps(&self) -> Option<f64> {
+        self.target_frame_time
+            .map(|frame_time| 1.0 / frame_time.as_secs_f64())
+    }
+
+    /// Get the time between the start of the two most recent frames.
+    pub fn frame_time(&self) -> Duration {
+        self.frame_time
+    }
+
+    /// Get the time between submitting the most recently completed present and it being displayed. Since this is measured
+    /// when the next frame waits on it, this is an upper bound.
+    ///
+    /// Returns `None` if [`ExtensionID::PresentWait`](crate::core::device::ExtensionID::PresentWait) is not enabled, or no
+    /// present was completed yet.
+    pub fn present_latency(&self) -> Option<Duration> {
+        self.present_latency
+    }
+
+    /// Get the duration of a single refresh cycle of the display the window is on.
+    ///
+    /// Returns `None` if [`ExtensionID::DisplayTiming`](crate::core::device::ExtensionID::DisplayTiming) is not enabled.
+    pub fn refresh_duration(&self) -> Option<Duration> {
+        self.refresh_duration
+    }
+
+    /// Get the timing information of the most recent present reported by the presentation engine.
+    ///
+    /// Returns `None` if [`ExtensionID::DisplayTiming`](crate::core::device::ExtensionID::DisplayTiming) is not enabled, or no
+    /// timing information is available yet.
+    pub fn last_presentation_timing(&self) -> Option<&vk::PastPresentationTimingGOOGLE> {
+        self.last_presentation_timing.as_ref()
+    }
+
+    /// Called when a new swapchain is used, after `last_present_id` was presented to the old one.
+    fn swapchain_recreated(&mut self, last_present_id: u64) {
+        self.swapchain_first_id = last_present_id;
+        self.submit_times.clear();
+        self.refresh_duration = None;
+        self.last_presentation_timing = None;
+    }
+
+    /// Block until a new frame may be started. `present_id` is the identifier of the last present operation.
+    fn wait(&mut self, device: &Device, swapchain: &Swapchain, present_id: u64) -> Result<()> {
+        self.wait_for_present(device, swapchain, present_id)?;
+        self.query_display_timing(device, swapchain)?;
+
+        if let Some(target) = self.target_frame_time {
+            let now = Instant::now();
+            let deadline = self.next_deadline.unwrap_or(now);
+            if deadline > now {
+                std::thread::sleep(deadline - now);
+            }
+            // Do not try to catch up if we are more than a frame late, as that would cause a burst of frames.
+            let now = Instant::now();
+            self.next_deadline = if now > deadline + target {
+                Some(now + target)
+            } else {
+                Some(deadline + target)
+            };
+        }
+
+        let now = Instant::now();
+        if let Some(last) = self.last_frame {
+            self.frame_time = now - last;
+        }
+        self.last_frame = Some(now);
+        Ok(())
+    }
+
+    fn wait_for_present(&mut self, device: &Device, swapchain: &Swapchain, present_id: u64) -> Result<()> {
+        let Some(fns) = device.present_wait() else {
+            return Ok(());
+        };
+        let target = present_id.saturating_sub(self.max_queued_frames);
+        if target <= self.swapchain_first_id {
+            return Ok(());
+        }
+
+        let result = unsafe { fns.wait_for_present(swapchain.handle(), target, PRESENT_WAIT_TIMEOUT) };
+        match device.check_device_lost(result) {
+            Ok(()) | Err(vk::Result::SUBOPTIMAL_KHR) => {}
+            // Timed out presents are measured later, and an out of date swapchain is recreated before the next frame.
+            Err(vk::Result::TIMEOUT) | Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => return Ok(()),
+            Err(e) => return Err(e.into()),
+        }
+
+        let now = Instant::now();
+        while let Some(&(id, submitted)) = self.submit_times.front() {
+            if id > target {
+                break;
+            }
+            self.submit_times.pop_front();
+            if id == target {
+                self.present_latency = Some(now - submitted);
+            }
+        }
+        Ok(())
+    }
+
+    fn query_display_timing(&mut self, device: &Device, swapchain: &Swapchain) -> Result<()> {
+        let Some(fns) = device.display_timing() else {
+            return Ok(());
+        };
+        let device_handle = unsafe { device.handle().handle() };
+        let swapchain_handle = unsafe { swapchain.handle() };
+
+        if self.refresh_duration.is_none() {
+            let mut properties = vk::RefreshCycleDurationGOOGLE::default();
+            let result =
+                unsafe { (fns.get_refresh_cycle_duration_google)(device_handle, swapchain_handle, &mut properties) };
+            match device.check_device_lost(result.result()) {
+                Ok(()) => self.refresh_duration = Some(Duration::from_nanos(properties.refresh_duration)),
+                Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => return Ok(()),
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let mut count = 0;
+        let result = unsafe {
+            (fns.get_past_presentation_timing_google)(device_handle, swapchain_handle, &mut count, std::ptr::null_mut())
+        };
+        match device.check_device_lost(result.result()) {
+            Ok(()) => {}
+            Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => return Ok(()),
+            Err(e) => return Err(e.into()),
+        }
+        if count == 0 {
+            return Ok(());
+        }
+        let mut timings = vec![vk::PastPresentationTimingGOOGLE::default(); count as usize];
+        let result = unsafe {
+            (fns.get_past_presentation_timing_google)(device_handle, swapchain_handle, &mut count, timings.as_mut_ptr())
+        };
+        // VK_INCOMPLETE only means more presents completed in the meantime, those are reported next frame.
+        match device.check_device_lost(result.result()) {
+            Ok(()) | Err(vk::Result::INCOMPLETE) => {}
+            Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => return Ok(()),
+            Err(e) => return Err(e.into()),
+        }
+        if let Some(timing) = timings.get(count as usize - 1) {
+            self.last_presentation_timing = Some(*timing);
+        }
+        Ok(())
+    }
+
+    /// Record that present `id` is about to be submitted, and get the requested present time to attach to it.
+    fn begin_present(&mut self, device: &Device, id: u64) -> vk::PresentTimeGOOGLE {
+        if device.present_wait().is_some() {
+            self.submit_times.push_back((id, Instant::now()));
+        }
+        // Schedule the present relative to the last known present, so frames are displayed at the target frame rate.
+        // A desired present time of zero presents as soon as possible.
+        let desired_present_time = match (self.target_frame_time, &self.last_presentation_timing) {
+            (Some(target), Some(last)) => {
+                let frames = (id as u32).wrapping_sub(last.present_id) as u64;
+                last.actual_present_time + target.as_nanos() as u64 * frames
+            }
+            _ => 0,
+        };
+        vk::PresentTimeGOOGLE {
+            present_id: id as u32,
+            desired_present_time,
+        }
+    }
+}
+
+impl Default for FramePacer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Responsible for presentation, frame-frame synchronization and per-frame resources.
 #[derive(Derivative)]
 #[derivative(Debug)]
 pub struct FrameManager<A: Allocator = DefaultAllocator> {
     device: Device,
-    per_frame: [PerFrame<A>; FRAMES_IN_FLIGHT],
+    per_frame: [FrameData<A>; FRAMES_IN_FLIGHT],
     current_frame: u32,
     current_image: u32,
     swapchain: Swapchain,
     swapchain_delete: DeletionQueue<Swapchain>,
     pool: ResourcePool<A>,
+    #[derivative(Debug = "ignore")]
+    present_hook: Option<Box<dyn PresentHook + Send>>,
+    // Set when the swapchain was reported suboptimal or out of date, so it is recreated before the next frame.
+    recreate_required: bool,
+    #[derivative(Debug = "ignore")]
+    swapchain_recreated_callbacks: Vec<SwapchainRecreatedFn>,
+    frame_pacer: Option<FramePacer>,
+    full_screen_exclusive_acquired: bool,
+    // Identifier of the last present operation. Present identifiers must keep increasing for the same swapchain.
+    present_id: u64,
+    // Identifier of the last present operation before the current swapchain was created.
+    swapchain_first_present_id: u64,
 }
 
+type SwapchainRecreatedFn = Box<dyn FnMut(&Swapchain) -> Result<()> + Send>;
+
 #[derive(Debug, Copy, Clone)]
-struct AcquiredImage {
-    pub index: u32,
-    pub resize_required: bool,
+enum AcquiredImage {
+    Image {
+        index: u32,
+        suboptimal: bool,
+    },
+    OutOfDate,
 }
 
+/// Maximum number of times the swapchain is recreated while acquiring a single frame, in case the window keeps
+/// changing size while we recreate it.
+const MAX_ACQUIRE_ATTEMPTS: u32 = 4;
+
 impl<A: Allocator> FrameManager<A> {
     fn acquire_image(&mut self) -> Result<AcquiredImage> {
         let frame = &mut self.per_frame[self.current_frame as usize];
@@ -156,54 +717,121 @@ impl<A: Allocator> FrameManager<A> {
         };
 
         match result {
-            // We ignore the flag for suboptimal swapchain images for now
-            Ok((index, _)) => Ok(AcquiredImage {
+            Ok((index, suboptimal)) => Ok(AcquiredImage::Image {
                 index,
-                resize_required: false,
-            }),
-            Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => Ok(AcquiredImage {
-                index: 0,
-                resize_required: true,
+                suboptimal,
             }),
+            Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => Ok(AcquiredImage::OutOfDate),
+            // Exclusive fullscreen was lost, for example because the window lost focus. The recreated swapchain is not in
+            // exclusive mode until it is acquired again.
+            Err(vk::Result::ERROR_FULL_SCREEN_EXCLUSIVE_MODE_LOST_EXT) => {
+                self.full_screen_exclusive_acquired = false;
+                Ok(AcquiredImage::OutOfDate)
+            }
             Err(err) => Err(err.into()),
         }
     }
 
+    /// Get the size a new swapchain for this surface should have. This is zero while the window is minimized.
+    fn surface_extent<Window: WindowInterface>(
+        window: &Window,
+        capabilities: &vk::SurfaceCapabilitiesKHR,
+    ) -> vk::Extent2D {
+        if capabilities.current_extent.width != u32::MAX {
+            return capabilities.current_extent;
+        }
+        vk::Extent2D {
+            width: window
+                .width()
+                .clamp(capabilities.min_image_extent.width, capabilities.max_image_extent.width),
+            height: window
+                .height()
+                .clamp(capabilities.min_image_extent.height, capabilities.max_image_extent.height),
+        }
+    }
+
+    /// Recreate the swapchain if the surface has a non-zero size. Returns `false` if the swapchain could not be recreated because
+    /// the window is minimized.
+    fn recreate_swapchain<Window: WindowInterface>(&mut self, window: &Window, surface: &Surface) -> Result<bool> {
+        let capabilities = surface.current_capabilities()?;
+        let extent = Self::surface_extent(window, &capabilities);
+        if extent.width == 0 || extent.height == 0 {
+            self.recreate_required = true;
+            return Ok(false);
+        }
+
+        let mut new_swapchain = self.resize_swapchain(window, surface, &capabilities, extent)?;
+        std::mem::swap(&mut new_swapchain, &mut self.swapchain);
+        self.swapchain_delete.push(new_swapchain); // now old swapchain after swapping.
+        self.recreate_required = false;
+        self.swapchain_first_present_id = self.present_id;
+
+        if let Some(pacer) = &mut self.frame_pacer {
+            pacer.swapchain_recreated(self.present_id);
+        }
+        // Exclusive fullscreen mode is tied to the swapchain, so enter it again on the new one.
+        if self.full_screen_exclusive_acquired {
+            if let Err(e) = self.acquire_full_screen_exclusive() {
+                warn!("Failed to acquire exclusive fullscreen mode for the recreated swapchain: {e}");
+            }
+        }
+        if let Some(hook) = &mut self.present_hook {
+            hook.swapchain_recreated(&self.swapchain)?;
+        }
+        for callback in &mut self.swapchain_recreated_callbacks {
+            callback(&self.swapchain)?;
+        }
+        Ok(true)
+    }
+
     fn resize_swapchain<Window: WindowInterface>(
         &mut self,
         window: &Window,
         surface: &Surface,
+        capabilities: &vk::SurfaceCapabilitiesKHR,
+        extent: vk::Extent2D,
     ) -> Result<Swapchain> {
         let mut new_swapchain = Swapchain {
             handle: vk::SwapchainKHR::null(),
             images: vec![],
             format: self.swapchain.format(),
             present_mode: self.swapchain.present_mode(),
-            extent: vk::Extent2D {
-                width: window.width(),
-                height: window.height(),
-            },
+            extent,
+            min_image_count: swapchain::clamp_image_count(self.swapchain.min_image_count(), capabilities),
+            composite_alpha: self.swapchain.composite_alpha(),
+            image_usage: swapchain::image_usage(capabilities),
             functions: self.swapchain.functions.clone(),
+            // The window may have moved to a different monitor.
+            full_screen_exclusive: self
+                .swapchain
+                .full_screen_exclusive
+                .map(|exclusive| FullScreenExclusive {
+                    monitor: swapchain::monitor_for_window(window),
+                    ..exclusive
+                }),
         };
-
-        let image_count = self.swapchain.images.len();
+        let full_screen_exclusive_chain = new_swapchain
+            .full_screen_exclusive
+            .map(|exclusive| exclusive.chain());
 
         let info = vk::SwapchainCreateInfoKHR {
             s_type: vk::StructureType::SWAPCHAIN_CREATE_INFO_KHR,
-            p_next: std::ptr::null(),
+            p_next: full_screen_exclusive_chain
+                .as_ref()
+                .map_or(std::ptr::null(), |chain| chain.p_next()),
             flags: Default::default(),
             surface: unsafe { surface.handle() },
-            min_image_count: image_count as u32,
+            min_image_count: new_swapchain.min_image_count,
             image_format: self.swapchain.format().format,
             image_color_space: self.swapchain.format().color_space,
             image_extent: *new_swapchain.extent(),
             image_array_layers: 1,
-            image_usage: vk::ImageUsageFlags::COLOR_ATTACHMENT,
+            image_usage: new_swapchain.image_usage,
             image_sharing_mode: vk::SharingMode::EXCLUSIVE,
             queue_family_index_count: 0,
             p_queue_family_indices: std::ptr::null(),
-            pre_transform: surface.capabilities().current_transform,
-            composite_alpha: vk::CompositeAlphaFlagsKHR::OPAQUE,
+            pre_transform: capabilities.current_transform,
+            composite_alpha: new_swapchain.composite_alpha,
             present_mode: self.swapchain.present_mode(),
             clipped: vk::TRUE,
             old_swapchain: unsafe { self.swapchain.handle() },
@@ -253,28 +881,77 @@ impl<A: Allocator> FrameManager<A> {
     }
 
     /// Present a frame to the swapchain. This is the same as calling
-    /// `glfwSwapBuffers()` in OpenGL code.
-    fn present(&self, exec: ExecutionManager<A>) -> Result<()> {
+    /// `glfwSwapBuffers()` in OpenGL code. Returns whether the swapchain must be recreated before the next frame.
+    fn present(&mut self, exec: ExecutionManager<A>) -> Result<bool> {
         let per_frame = &self.per_frame[self.current_frame as usize];
         let functions = &self.swapchain.functions;
         let queue = exec.get_present_queue();
         if let Some(queue) = queue {
             let gpu_finished = unsafe { per_frame.gpu_finished.handle() };
-            let info = vk::PresentInfoKHR {
-                s_type: vk::StructureType::PRESENT_INFO_KHR,
-                p_next: std::ptr::null(),
-                wait_semaphore_count: 1,
-                p_wait_semaphores: &gpu_finished,
-                swapchain_count: 1,
-                p_swapchains: &self.swapchain.handle,
-                p_image_indices: &self.current_image,
-                p_results: std::ptr::null_mut(),
+            // Every present gets an identifier, so frames can be waited on with `FrameManager::wait_for_present()`.
+            // These must stay alive until the image is presented, since they are chained into the present info.
+            self.present_id += 1;
+            let present_id = self.present_id;
+            let present_time = self
+                .frame_pacer
+                .as_mut()
+                .map(|pacer| pacer.begin_present(&self.device, present_id));
+            let present_id_info = self
+                .device
+                .is_extension_enabled(ExtensionID::PresentId)
+                .then(|| vk::PresentIdKHR {
+                    swapchain_count: 1,
+                    p_present_ids: &present_id,
+                    ..Default::default()
+                });
+            let mut present_times_info = present_time
+                .as_ref()
+                .filter(|_| self.device.is_extension_enabled(ExtensionID::DisplayTiming))
+                .map(|time| vk::PresentTimesInfoGOOGLE {
+                    swapchain_count: 1,
+                    p_times: time,
+                    ..Default::default()
+                });
+            let mut p_next = std::ptr::null();
+            if let Some(info) = &present_id_info {
+                p_next = info as *const _ as *const c_void;
+            }
+            if let Some(info) = &mut present_times_info {
+                info.p_next = p_next;
+                p_next = info as *const _ as *const c_void;
+            }
+
+            let result = if let Some(hook) = &mut self.present_hook {
+                hook.present(&PresentContext {
+                    queue: unsafe { queue.handle() },
+                    queue_family_index: queue.info().family_index,
+                    swapchain: &self.swapchain,
+                    image_index: self.current_image,
+                    wait_semaphore: gpu_finished,
+                    frame_index: self.current_frame as usize,
+                    p_next,
+                })
+            } else {
+                let info = vk::PresentInfoKHR {
+                    s_type: vk::StructureType::PRESENT_INFO_KHR,
+                    p_next,
+                    wait_semaphore_count: 1,
+                    p_wait_semaphores: &gpu_finished,
+                    swapchain_count: 1,
+                    p_swapchains: &self.swapchain.handle,
+                    p_image_indices: &self.current_image,
+                    p_results: std::ptr::null_mut(),
+                };
+                unsafe { functions.queue_present(queue.handle(), &info) }
             };
-            let result = unsafe { functions.queue_present(queue.handle(), &info).map(|_| ()) };
-            // We will ignore OUT_OF_DATE_KHR here
-            match result {
-                Ok(_) => Ok(()),
-                Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => Ok(()),
+            // The frame was still presented if the swapchain is out of date, so we only need to recreate it.
+            match self.device.check_device_lost(result) {
+                Ok(suboptimal) => Ok(suboptimal),
+                Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => Ok(true),
+                Err(vk::Result::ERROR_FULL_SCREEN_EXCLUSIVE_MODE_LOST_EXT) => {
+                    self.full_screen_exclusive_acquired = false;
+                    Ok(true)
+                }
                 Err(e) => Err(e.into()),
             }
         } else {
@@ -298,15 +975,15 @@ impl<A: Allocator> FrameManager<A> {
         Ok(FrameManager {
             device: device.clone(),
             per_frame: (0..FRAMES_IN_FLIGHT)
-                .map(|_| -> Result<PerFrame<A>> {
-                    Ok(PerFrame {
+                .map(|_| -> Result<FrameData<A>> {
+                    Ok(FrameData {
                         fence: Fence::new(device.clone(), true)?.into_pooled(&pool.fences, ()),
                         image_ready: Arc::new(Semaphore::new(device.clone())?),
                         gpu_finished: Arc::new(Semaphore::new(device.clone())?),
                         command_buffer: None,
                     })
                 })
-                .collect::<Result<Vec<PerFrame<A>>>>()?
+                .collect::<Result<Vec<FrameData<A>>>>()?
                 .try_into()
                 .map_err(|_| Error::Uncategorized("Conversion to slice failed"))?,
             current_frame: 0,
@@ -314,6 +991,13 @@ impl<A: Allocator> FrameManager<A> {
             swapchain,
             swapchain_delete: DeletionQueue::<Swapchain>::new((FRAMES_IN_FLIGHT + 2) as u32),
             pool,
+            present_hook: None,
+            recreate_required: false,
+            swapchain_recreated_callbacks: vec![],
+            frame_pacer: None,
+            present_id: 0,
+            swapchain_first_present_id: 0,
+            full_screen_exclusive_acquired: false,
         })
     }
 
@@ -329,9 +1013,42 @@ impl<A: Allocator> FrameManager<A> {
         FrameManager::new(device, pool, swapchain)
     }
 
+    /// Create a surface and a frame manager for an additional window, sharing the device and resource pool with other frame managers.
+    /// The returned surface must outlive the frame manager, and must be passed to [`FrameManager::new_frame()`] together with `window`.
+    /// # Errors
+    /// * Fails if creating the surface or swapchain fails.
+    /// * Fails if the queue used for presenting can not present to the new surface.
+    pub fn new_for_window<W: WindowInterface, Window: WindowInterface>(
+        instance: &Instance,
+        physical_device: &PhysicalDevice,
+        device: Device,
+        pool: ResourcePool<A>,
+        settings: &AppSettings<W>,
+        window: &Window,
+    ) -> Result<(Surface, Self)> {
+        let mut surface = Surface::from_window(instance, window)?;
+        surface.query_details(physical_device)?;
+        // Frames of all windows are presented on the same queue, which was selected for the surface of the main window.
+        let present_family = physical_device
+            .queues()
+            .iter()
+            .find(|queue| queue.can_present)
+            .map(|queue| queue.family_index)
+            .ok_or(Error::NoPresentQueue)?;
+        if !surface.supports_queue_family(present_family)? {
+            return Err(Error::NoPresentQueue.into());
+        }
+        let swapchain = Swapchain::new_for_window(instance, device.clone(), settings, window, &surface)?;
+        let frame = FrameManager::new(device, pool, swapchain)?;
+        Ok((surface, frame))
+    }
+
     /// Obtain a new frame context to run commands in.
     /// This will call the provided callback function to obtain a [`SubmitBatch`](crate::sync::submit_batch::SubmitBatch)
     /// which contains the commands to be submitted for this frame.
+    ///
+    /// If the swapchain is out of date or suboptimal, it is recreated first. If the window is minimized, the frame is skipped
+    /// and the callback is not called.
     pub async fn new_frame<Window, D, F>(
         &mut self,
         exec: ExecutionManager<A>,
@@ -343,29 +1060,50 @@ impl<A: Allocator> FrameManager<A> {
         Window: WindowInterface,
         D: ExecutionDomain + 'static,
         F: FnOnce(InFlightContext) -> Result<SubmitBatch<D>>, {
+        // Pace frames before anything else, so skipped frames while minimized are limited too.
+        if let Some(pacer) = &mut self.frame_pacer {
+            pacer.wait(&self.device, &self.swapchain, self.present_id)?;
+        }
+
+        // Recreate the swapchain if the previous frame reported it as suboptimal. This also skips frames while minimized.
+        if self.recreate_required && !self.recreate_swapchain(window, surface)? {
+            return Ok(());
+        }
+
         // Advance deletion queue by one frame
         self.swapchain_delete.next_frame();
 
         // Increment frame index.
         self.current_frame = (self.current_frame + 1) % self.per_frame.len() as u32;
 
-        let AcquiredImage {
-            index,
-            resize_required,
-        } = self.acquire_image()?;
-        self.current_image = index;
-
-        if resize_required {
-            let mut new_swapchain = self.resize_swapchain(window, surface)?;
-            std::mem::swap(&mut new_swapchain, &mut self.swapchain);
-            self.swapchain_delete.push(new_swapchain); // now old swapchain after swapping.
+        if let Some(hook) = &mut self.present_hook {
+            hook.before_acquire()?;
+        }
 
-            // Acquire image again. Note that this won't wait on the same fence again is it is never reset.
-            let AcquiredImage {
-                index,
-                ..
-            } = self.acquire_image()?;
-            self.current_image = index;
+        // Note that acquiring again won't wait on the same fence again, as it is never reset.
+        let mut attempts = 0;
+        loop {
+            match self.acquire_image()? {
+                AcquiredImage::Image {
+                    index,
+                    suboptimal,
+                } => {
+                    // The image is still acquired, so it must be presented before the swapchain can be recreated.
+                    self.current_image = index;
+                    self.recreate_required = suboptimal;
+                    break;
+                }
+                AcquiredImage::OutOfDate => {
+                    attempts += 1;
+                    if attempts > MAX_ACQUIRE_ATTEMPTS {
+                        return Err(vk::Result::ERROR_OUT_OF_DATE_KHR.into());
+                    }
+                    // Nothing was acquired, so the frame can be skipped entirely while minimized.
+                    if !self.recreate_swapchain(window, surface)? {
+                        return Ok(());
+                    }
+                }
+            }
         }
 
         let submission = {
@@ -381,13 +1119,149 @@ impl<A: Allocator> FrameManager<A> {
 
             let ifc = InFlightContext {
                 swapchain_image: image,
-                wait_semaphore: per_frame.image_ready.clone(),
-                signal_semaphore: per_frame.gpu_finished.clone(),
+                wait_semaphore: Some(per_frame.image_ready.clone()),
+                signal_semaphore: Some(per_frame.gpu_finished.clone()),
+                frame_index: self.current_frame as usize,
+                image_index: self.current_image as usize,
             };
             f(ifc)?
         };
         self.submit(submission)?;
-        self.present(exec)
+        if self.present(exec)? {
+            self.recreate_required = true;
+        }
+        Ok(())
+    }
+
+    /// Register a callback that is called after the swapchain was recreated, for example because the window was resized.
+    /// Resources that depend on the swapchain, such as attachments with the same size, should be recreated here.
+    /// Callbacks are called in the order they were registered, after [`PresentHook::swapchain_recreated()`].
+    pub fn on_swapchain_recreated(&mut self, callback: impl FnMut(&Swapchain) -> Result<()> + Send + 'static) {
+        self.swapchain_recreated_callbacks.push(Box::new(callback));
+    }
+
+    /// Get the number of images in the swapchain. This may be more than requested, and may change when the swapchain is recreated.
+    pub fn image_count(&self) -> usize {
+        self.swapchain.image_count()
+    }
+
+    /// Set a hook that is called around presentation, for example to implement frame generation.
+    /// Pass `None` to remove the hook and present frames directly. The new hook is passed the current swapchain through
+    /// [`PresentHook::swapchain_recreated()`].
+    /// # Errors
+    /// * Fails if the hook fails to create its swapchain resources. The hook is not set in this case.
+    pub fn set_present_hook(&mut self, mut hook: Option<Box<dyn PresentHook + Send>>) -> Result<()> {
+        if let Some(hook) = &mut hook {
+            hook.swapchain_recreated(&self.swapchain)?;
+        }
+        self.present_hook = hook;
+        Ok(())
+    }
+
+    /// Set a frame pacer to limit the frame rate and measure present latency. Pass `None` to remove the frame pacer and start
+    /// new frames as soon as possible. See [`FramePacer`].
+    pub fn set_frame_pacer(&mut self, pacer: Option<FramePacer>) {
+        self.frame_pacer = pacer.map(|mut pacer| {
+            // The new pacer has not seen any of the presents to the current swapchain.
+            pacer.swapchain_recreated(self.present_id);
+            pacer
+        });
+    }
+
+    /// Get the current frame pacer, if any.
+    pub fn frame_pacer(&self) -> Option<&FramePacer> {
+        self.frame_pacer.as_ref()
+    }
+
+    /// Get mutable access to the current frame pacer, for example to change the target frame rate.
+    pub fn frame_pacer_mut(&mut self) -> Option<&mut FramePacer> {
+        self.frame_pacer.as_mut()
+    }
+
+    /// Get the identifier of the most recent present operation, to pass to [`FrameManager::wait_for_present()`] later.
+    ///
+    /// Returns `None` if [`ExtensionID::PresentId`] is not enabled, or no frame was presented yet.
+    pub fn last_present_id(&self) -> Option<u64> {
+        if !self.device.is_extension_enabled(ExtensionID::PresentId) || self.present_id == 0 {
+            return None;
+        }
+        Some(self.present_id)
+    }
+
+    /// Block until the frame with the given present identifier is displayed, or until `timeout` has passed. Returns `false`
+    /// if the wait timed out. Frames presented to a swapchain that was since recreated are treated as displayed.
+    ///
+    /// This can be used to measure display latency, or to sample input as late as possible by waiting until the previous frame
+    /// is displayed before starting a new one.
+    /// # Errors
+    /// * Fails if [`ExtensionID::PresentWait`] is not enabled.
+    /// * Fails if `present_id` was not presented yet.
+    pub fn wait_for_present(&self, present_id: u64, timeout: Duration) -> Result<bool> {
+        self.device.require_extension(ExtensionID::PresentWait)?;
+        ensure!(present_id <= self.present_id, "Cannot wait for present {present_id}, it was not presented yet.");
+        if present_id <= self.swapchain_first_present_id {
+            return Ok(true);
+        }
+        let fns = self.device.present_wait().unwrap();
+        let timeout = timeout.as_nanos().min(u64::MAX as u128) as u64;
+        let result = unsafe { fns.wait_for_present(self.swapchain.handle(), present_id, timeout) };
+        match self.device.check_device_lost(result) {
+            // The swapchain is recreated before the next frame, so the present will not be displayed anymore.
+            Ok(()) | Err(vk::Result::SUBOPTIMAL_KHR) | Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => Ok(true),
+            Err(vk::Result::TIMEOUT) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Block until the most recently presented frame is displayed, or until `timeout` has passed. Returns `false` if the wait
+    /// timed out. Calling this right before [`FrameManager::new_frame()`] ensures input sampled for the new frame is as recent
+    /// as possible. See also [`FrameManager::wait_for_present()`].
+    /// # Errors
+    /// * Fails if [`ExtensionID::PresentWait`] is not enabled.
+    pub fn wait_for_last_present(&self, timeout: Duration) -> Result<bool> {
+        self.wait_for_present(self.present_id, timeout)
+    }
+
+    /// Enter exclusive fullscreen mode, for the lowest latency presentation. The swapchain must have been created with
+    /// [`vk::FullScreenExclusiveEXT::APPLICATION_CONTROLLED`], see [`AppBuilder::full_screen_exclusive()`](crate::AppBuilder::full_screen_exclusive).
+    /// Exclusive mode is kept when the swapchain is recreated. It can be lost at any time, for example when the window loses focus,
+    /// after which the swapchain is recreated and this must be called again.
+    /// # Errors
+    /// * Fails if [`ExtensionID::FullScreenExclusive`] is not enabled.
+    /// * Fails if the swapchain was not created with [`vk::FullScreenExclusiveEXT::APPLICATION_CONTROLLED`].
+    /// * Fails with `VK_ERROR_INITIALIZATION_FAILED` if exclusive mode could not be entered, for example because the window is not
+    ///   fullscreen.
+    pub fn acquire_full_screen_exclusive(&mut self) -> Result<()> {
+        self.device
+            .require_extension(ExtensionID::FullScreenExclusive)?;
+        ensure!(
+            self.swapchain.full_screen_exclusive() == Some(vk::FullScreenExclusiveEXT::APPLICATION_CONTROLLED),
+            "Exclusive fullscreen can only be acquired for swapchains created with APPLICATION_CONTROLLED."
+        );
+        let fns = self.device.full_screen_exclusive().unwrap();
+        let result = unsafe { fns.acquire_full_screen_exclusive_mode(self.swapchain.handle()) };
+        self.full_screen_exclusive_acquired = result.is_ok();
+        Ok(self.device.check_device_lost(result)?)
+    }
+
+    /// Leave exclusive fullscreen mode entered with [`FrameManager::acquire_full_screen_exclusive()`].
+    /// # Errors
+    /// * Fails if [`ExtensionID::FullScreenExclusive`] is not enabled.
+    pub fn release_full_screen_exclusive(&mut self) -> Result<()> {
+        self.device
+            .require_extension(ExtensionID::FullScreenExclusive)?;
+        if !self.full_screen_exclusive_acquired {
+            return Ok(());
+        }
+        self.full_screen_exclusive_acquired = false;
+        let fns = self.device.full_screen_exclusive().unwrap();
+        let result = unsafe { fns.release_full_screen_exclusive_mode(self.swapchain.handle()) };
+        Ok(self.device.check_device_lost(result)?)
+    }
+
+    /// Whether exclusive fullscreen mode is currently acquired, see [`FrameManager::acquire_full_screen_exclusive()`].
+    pub fn is_full_screen_exclusive(&self) -> bool {
+        self.full_screen_exclusive_acquired
     }
 
     /// Unsafe access to the underlying swapchain.
@@ -397,3 +1271,15 @@ impl<A: Allocator> FrameManager<A> {
         &self.swapchain
     }
 }
+
+impl<A: Allocator> Drop for FrameManager<A> {
+    fn drop(&mut self) {
+        // Frames may still be in flight. Wait for their fences before destroying per-frame data and the swapchains in the
+        // deletion queue. This also deletes the command buffers of these frames.
+        for frame in &mut self.per_frame {
+            if let Err(e) = frame.fence.wait() {
+                error!("Failed to wait for frame in flight during teardown: {e}");
+            }
+        }
+    }
+}
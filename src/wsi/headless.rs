@@ -0,0 +1,238 @@
+//! Contains the [`HeadlessFrameManager`], which runs a frame loop that renders to offscreen images instead of a swapchain.
+//!
+//! The headless frame manager hands out the same [`InFlightContext`] as the [`FrameManager`](crate::FrameManager), with
+//! [`InFlightContext::swapchain_image`] set to the current offscreen image. This allows the same frame code to be used for a
+//! window and for batch renders or CI, where no window or surface is available.
+//!
+//! Since there is no swapchain, frame code must not transition the image to `VK_IMAGE_LAYOUT_PRESENT_SRC_KHR` (for example with
+//! [`Pass::present()`](crate::graph::pass::Pass::present)) unless `VK_KHR_swapchain` is enabled. Leaving the image in a layout suitable
+//! for reading it back, such as `VK_IMAGE_LAYOUT_TRANSFER_SRC_OPTIMAL`, is usually the better choice.
+//!
+//! # Example
+//! ```
+//! # use anyhow::Result;
+//! # use phobos::prelude::*;
+//! use phobos::wsi::frame::FRAMES_IN_FLIGHT;
+//!
+//! fn render_frames(
+//!     device: Device,
+//!     mut allocator: DefaultAllocator,
+//!     pool: ResourcePool,
+//!     exec: ExecutionManager,
+//! ) -> Result<()> {
+//!     let extent = vk::Extent2D { width: 1920, height: 1080 };
+//!     let mut frame = HeadlessFrameManager::new_with_images(
+//!         device,
+//!         &mut allocator,
+//!         pool.clone(),
+//!         vk::Format::R8G8B8A8_SRGB,
+//!         extent,
+//!         vk::ImageUsageFlags::empty(),
+//!         FRAMES_IN_FLIGHT,
+//!     )?;
+//!     for _ in 0..100 {
+//!         futures::executor::block_on(frame.new_frame(exec.clone(), |ifc| {
+//!             let cmd = exec.on_domain::<domain::Graphics>()?
+//!                 .transition_image(&ifc.swapchain_image,
+//!                     PipelineStage::TOP_OF_PIPE, PipelineStage::BOTTOM_OF_PIPE,
+//!                     vk::ImageLayout::UNDEFINED, vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+//!                     vk::AccessFlags2::NONE, vk::AccessFlags2::NONE)
+//!                 .finish()?;
+//!             let mut batch = exec.start_submit_batch()?;
+//!             batch.submit_for_present(cmd, ifc, LocalPool::new(pool.clone())?)?;
+//!             Ok(batch)
+//!         }))?;
+//!     }
+//!     // Wait for the last frame before reading back its image.
+//!     frame.wait_for_frames()?;
+//!     Ok(())
+//! }
+//! ```
+
+use anyhow::{ensure, Result};
+use ash::vk;
+
+use crate::{
+    Allocator, CmdBuffer, DefaultAllocator, Device, Error, ExecutionManager, Fence, Image, ImageCreateInfo,
+    ImageView, InFlightContext, MemoryType,
+};
+use crate::pool::{Poolable, Pooled, ResourcePool};
+use crate::sync::domain::ExecutionDomain;
+use crate::sync::submit_batch::SubmitBatch;
+use crate::wsi::frame::FRAMES_IN_FLIGHT;
+
+/// Information stored for each in-flight headless frame.
+#[derive(Derivative)]
+#[derivative(Debug)]
+struct HeadlessFrameData<A> {
+    #[derivative(Debug = "ignore")]
+    fence: Pooled<Fence<()>>,
+    /// Command buffer that was submitted this frame.
+    /// Can be deleted once this frame's data is used again.
+    #[derivative(Debug = "ignore")]
+    command_buffer: Option<Box<dyn CmdBuffer<A>>>,
+}
+
+/// Responsible for frame-frame synchronization and per-frame resources when rendering to offscreen images.
+/// See the [module-level documentation](self) for more information.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct HeadlessFrameManager<A: Allocator = DefaultAllocator> {
+    device: Device,
+    per_frame: [HeadlessFrameData<A>; FRAMES_IN_FLIGHT],
+    current_frame: u32,
+    current_image: u32,
+    views: Vec<ImageView>,
+    // Frame that last rendered to each image, so we can wait for it before rendering to the image again.
+    image_frames: Vec<Option<usize>>,
+    // Images owned by the frame manager, if it allocated its own images.
+    images: Vec<Image<A>>,
+    pool: ResourcePool<A>,
+}
+
+impl<A: Allocator> HeadlessFrameManager<A> {
+    /// Initialize a headless frame manager that renders to the given images, in order. Any number of images can be used,
+    /// but with fewer than [`FRAMES_IN_FLIGHT`] images a frame must wait for the previous frame using the same image.
+    /// # Errors
+    /// * Fails if `images` is empty.
+    pub fn new(device: Device, pool: ResourcePool<A>, images: Vec<ImageView>) -> Result<Self> {
+        ensure!(!images.is_empty(), "A headless frame manager needs at least one image");
+        Ok(HeadlessFrameManager {
+            device: device.clone(),
+            per_frame: (0..FRAMES_IN_FLIGHT)
+                .map(|_| -> Result<HeadlessFrameData<A>> {
+                    Ok(HeadlessFrameData {
+                        fence: Fence::new(device.clone(), true)?.into_pooled(&pool.fences, ()),
+                        command_buffer: None,
+                    })
+                })
+                .collect::<Result<Vec<HeadlessFrameData<A>>>>()?
+                .try_into()
+                .map_err(|_| Error::Uncategorized("Conversion to slice failed"))?,
+            current_frame: 0,
+            // The first frame renders to the first image.
+            current_image: images.len() as u32 - 1,
+            image_frames: vec![None; images.len()],
+            views: images,
+            images: vec![],
+            pool,
+        })
+    }
+
+    /// Initialize a headless frame manager and allocate `count` images to render to. The images can always be used as color
+    /// attachment and as transfer source, additional usage flags can be given in `usage`.
+    /// # Errors
+    /// * Fails if `count` is zero.
+    /// * Fails if allocating the images fails.
+    pub fn new_with_images(
+        device: Device,
+        allocator: &mut A,
+        pool: ResourcePool<A>,
+        format: vk::Format,
+        extent: vk::Extent2D,
+        usage: vk::ImageUsageFlags,
+        count: usize,
+    ) -> Result<Self> {
+        let images = (0..count)
+            .map(|_| {
+                Image::new(
+                    device.clone(),
+                    allocator,
+                    ImageCreateInfo {
+                        width: extent.width,
+                        height: extent.height,
+                        depth: 1,
+                        usage: usage | vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC,
+                        format,
+                        samples: vk::SampleCountFlags::TYPE_1,
+                        mip_levels: 1,
+                        layers: 1,
+                        flags: vk::ImageCreateFlags::empty(),
+                        memory_type: MemoryType::GpuOnly,
+                    },
+                )
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let views = images
+            .iter()
+            .map(|image| image.whole_view(vk::ImageAspectFlags::COLOR))
+            .collect::<Result<Vec<_>>>()?;
+        let mut frame = Self::new(device, pool, views)?;
+        frame.images = images;
+        Ok(frame)
+    }
+
+    /// Obtain a new frame context to run commands in.
+    /// This will call the provided callback function to obtain a [`SubmitBatch`](crate::sync::submit_batch::SubmitBatch)
+    /// which contains the commands to be submitted for this frame. The frame is rendered to the next image in order, after
+    /// waiting for any previous frame that used the same image or the same per-frame resources.
+    pub async fn new_frame<D, F>(&mut self, exec: ExecutionManager<A>, f: F) -> Result<()>
+    where
+        D: ExecutionDomain + 'static,
+        F: FnOnce(InFlightContext) -> Result<SubmitBatch<D>>, {
+        // Increment frame and image index.
+        self.current_frame = (self.current_frame + 1) % self.per_frame.len() as u32;
+        self.current_image = (self.current_image + 1) % self.views.len() as u32;
+        let frame_index = self.current_frame as usize;
+        let image_index = self.current_image as usize;
+
+        // Note that waiting on a fence that was already waited on won't wait again, as it is never reset.
+        self.per_frame[frame_index].fence.wait()?;
+        // With fewer images than frames in flight, another frame may still be rendering to this image.
+        if let Some(previous) = self.image_frames[image_index] {
+            if previous != frame_index {
+                self.per_frame[previous].fence.wait()?;
+            }
+        }
+
+        let submission = {
+            let per_frame = &mut self.per_frame[frame_index];
+            // Delete the command buffer used the previous time this frame was allocated.
+            if let Some(cmd) = &mut per_frame.command_buffer {
+                unsafe { cmd.delete(exec.clone())? }
+            }
+            per_frame.command_buffer = None;
+
+            let ifc = InFlightContext {
+                swapchain_image: self.views[image_index].clone(),
+                wait_semaphore: None,
+                signal_semaphore: None,
+                frame_index,
+                image_index,
+            };
+            f(ifc)?
+        };
+        self.per_frame[frame_index].fence = submission.finish()?;
+        self.image_frames[image_index] = Some(frame_index);
+        Ok(())
+    }
+
+    /// Block until all submitted frames have finished rendering, for example before reading back the most recent image.
+    /// # Errors
+    /// * Fails if waiting on a frame's fence fails, for example because the device was lost.
+    pub fn wait_for_frames(&mut self) -> Result<()> {
+        for frame in &mut self.per_frame {
+            frame.fence.wait()?;
+        }
+        Ok(())
+    }
+
+    /// Get the image the most recent frame was rendered to. Before the first frame, this is the last image.
+    pub fn image(&self) -> &ImageView {
+        &self.views[self.current_image as usize]
+    }
+
+    /// Get all images this frame manager renders to, in the order they are used.
+    pub fn images(&self) -> &[ImageView] {
+        &self.views
+    }
+}
+
+impl<A: Allocator> Drop for HeadlessFrameManager<A> {
+    fn drop(&mut self) {
+        // Frames may still be in flight, wait for them to finish before destroying per-frame data and images.
+        if let Err(e) = self.wait_for_frames() {
+            error!("Failed to wait for frames in flight during teardown: {e}");
+        }
+    }
+}
@@ -1,7 +1,9 @@
 //! Provides utilities for interacting with the window and rendering frames.
-//! If you are using a headless context, you can largely ignore this module.
+//! If you are using a headless context, you can still run the same frame loop on offscreen images with the
+//! [`HeadlessFrameManager`](headless::HeadlessFrameManager).
 
 pub mod frame;
+pub mod headless;
 pub mod surface;
 pub mod swapchain;
 pub mod window;
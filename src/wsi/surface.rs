@@ -19,6 +19,8 @@ pub struct Surface {
     formats: Vec<vk::SurfaceFormatKHR>,
     /// List of [`VkPresentModeKHR`](vk::PresentModeKHR) with all present modes this surface supports.
     present_modes: Vec<vk::PresentModeKHR>,
+    /// The physical device the details were queried for.
+    physical_device: vk::PhysicalDevice,
     /// Vulkan extension functions for surface handling.
     #[derivative(Debug = "ignore")]
     functions: ash::extensions::khr::Surface,
@@ -31,35 +33,52 @@ impl Surface {
         settings: &AppSettings<Window>,
     ) -> Result<Self> {
         if let Some(window) = settings.window {
-            let functions =
-                ash::extensions::khr::Surface::new(unsafe { instance.loader() }, instance);
-            let handle = unsafe {
-                ash_window::create_surface(
-                    instance.loader(),
-                    instance,
-                    window.raw_display_handle(),
-                    window.raw_window_handle(),
-                    None,
-                )?
-            };
-            #[cfg(feature = "log-objects")]
-            trace!("Created new VkSurfaceKHR {handle:p}");
-            Ok(Surface {
-                handle,
-                functions,
-                capabilities: Default::default(),
-                formats: vec![],
-                present_modes: vec![],
-            })
+            Self::from_window(instance, window)
         } else {
             Err(anyhow::Error::from(Error::NoWindow))
         }
     }
 
+    /// Create a new surface for a window other than the one in the [`AppSettings`], for example to drive additional windows
+    /// after initialization. Call [`Surface::query_details()`] before using it.
+    pub fn from_window<Window: WindowInterface>(instance: &Instance, window: &Window) -> Result<Self> {
+        let functions = ash::extensions::khr::Surface::new(unsafe { instance.loader() }, instance);
+        let handle = unsafe {
+            ash_window::create_surface(
+                instance.loader(),
+                instance,
+                window.raw_display_handle(),
+                window.raw_window_handle(),
+                None,
+            )?
+        };
+        #[cfg(feature = "log-objects")]
+        trace!("Created new VkSurfaceKHR {handle:p}");
+        Ok(Surface {
+            handle,
+            functions,
+            capabilities: Default::default(),
+            formats: vec![],
+            present_modes: vec![],
+            physical_device: vk::PhysicalDevice::null(),
+        })
+    }
+
+    /// Whether a queue family of the physical device the details were queried for can present to this surface.
+    /// # Errors
+    /// * Fails if [`Surface::query_details()`] was not called yet.
+    pub fn supports_queue_family(&self, family_index: u32) -> Result<bool> {
+        if self.physical_device == vk::PhysicalDevice::null() {
+            return Err(anyhow::anyhow!("Surface details were not queried yet"));
+        }
+        unsafe { Ok(self.get_physical_device_surface_support(self.physical_device, family_index, self.handle)?) }
+    }
+
     /// Query support for features, capabilities and formats for this surface.
     /// Because surface support varies per physical device, this function requires one to be selected.
     pub fn query_details(&mut self, physical_device: &PhysicalDevice) -> Result<()> {
         unsafe {
+            self.physical_device = physical_device.handle();
             self.capabilities = self
                 .get_physical_device_surface_capabilities(physical_device.handle(), self.handle)?;
             self.formats =
@@ -82,6 +101,17 @@ impl Surface {
         &self.capabilities
     }
 
+    /// Query the current surface capabilities. Unlike [`Surface::capabilities()`], this reflects changes to the window after the
+    /// surface was created, such as its current size.
+    /// # Errors
+    /// * Fails if [`Surface::query_details()`] was not called yet.
+    pub fn current_capabilities(&self) -> Result<vk::SurfaceCapabilitiesKHR> {
+        if self.physical_device == vk::PhysicalDevice::null() {
+            return Err(anyhow::anyhow!("Surface details were not queried yet"));
+        }
+        unsafe { Ok(self.get_physical_device_surface_capabilities(self.physical_device, self.handle)?) }
+    }
+
     /// Get the available surface formats.
     pub fn formats(&self) -> &[vk::SurfaceFormatKHR] {
         self.formats.as_slice()
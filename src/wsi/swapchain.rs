@@ -1,5 +1,6 @@
 //! Wrappers around a `VkSwapchainKHR`
 
+use std::ffi::c_void;
 use std::ops::Deref;
 
 use anyhow::Result;
@@ -8,6 +9,15 @@ use ash::vk;
 use crate::{AppSettings, Device, Error, Instance, Surface, WindowInterface};
 use crate::image::*;
 
+#[cfg(windows)]
+#[link(name = "user32")]
+extern "system" {
+    fn MonitorFromWindow(hwnd: *mut c_void, flags: u32) -> *mut c_void;
+}
+
+#[cfg(windows)]
+const MONITOR_DEFAULTTONEAREST: u32 = 2;
+
 #[derive(Debug)]
 pub(crate) struct SwapchainImage {
     #[allow(dead_code)]
@@ -30,9 +40,86 @@ pub struct Swapchain {
     pub(super) present_mode: vk::PresentModeKHR,
     /// Size of the swapchain images. This is effectively the window render area.
     pub(super) extent: vk::Extent2D,
+    /// Minimum number of images requested at creation. The implementation may create more images than this.
+    pub(super) min_image_count: u32,
+    /// How the swapchain images are composited with other surfaces.
+    pub(super) composite_alpha: vk::CompositeAlphaFlagsKHR,
+    /// Usage flags the swapchain images were created with.
+    pub(super) image_usage: vk::ImageUsageFlags,
     /// Vulkan extension functions operating on the swapchain.
     #[derivative(Debug = "ignore")]
     pub(super) functions: ash::extensions::khr::Swapchain,
+    /// Exclusive fullscreen settings, if `VK_EXT_full_screen_exclusive` is enabled.
+    pub(super) full_screen_exclusive: Option<FullScreenExclusive>,
+}
+
+/// Exclusive fullscreen settings of a swapchain, see [`ExtensionID::FullScreenExclusive`](crate::core::device::ExtensionID::FullScreenExclusive).
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct FullScreenExclusive {
+    pub mode: vk::FullScreenExclusiveEXT,
+    // HMONITOR of the monitor the window is on, or zero if this is not a Win32 window.
+    pub monitor: isize,
+}
+
+/// Structures to chain into `VkSwapchainCreateInfoKHR` to request exclusive fullscreen. This is boxed, so the pointer
+/// from the first structure to the second stays valid.
+pub(crate) struct FullScreenExclusiveChain {
+    info: vk::SurfaceFullScreenExclusiveInfoEXT,
+    win32_info: vk::SurfaceFullScreenExclusiveWin32InfoEXT,
+}
+
+impl FullScreenExclusive {
+    /// Get the exclusive fullscreen settings for a swapchain on `window`. Returns `None` if the extension is not enabled.
+    pub fn new<Window: WindowInterface, W: WindowInterface>(
+        device: &Device,
+        settings: &AppSettings<Window>,
+        window: &W,
+    ) -> Option<Self> {
+        device.full_screen_exclusive()?;
+        Some(Self {
+            mode: settings.full_screen_exclusive?,
+            monitor: monitor_for_window(window),
+        })
+    }
+
+    /// Build the structures to chain into `VkSwapchainCreateInfoKHR`. Use [`FullScreenExclusiveChain::p_next()`] to obtain
+    /// the pointer to chain.
+    pub fn chain(&self) -> Box<FullScreenExclusiveChain> {
+        let mut chain = Box::new(FullScreenExclusiveChain {
+            info: vk::SurfaceFullScreenExclusiveInfoEXT {
+                full_screen_exclusive: self.mode,
+                ..Default::default()
+            },
+            win32_info: vk::SurfaceFullScreenExclusiveWin32InfoEXT {
+                hmonitor: self.monitor as vk::HMONITOR,
+                ..Default::default()
+            },
+        });
+        // The Win32 structure is required for application controlled exclusive mode on Win32 surfaces.
+        if self.monitor != 0 {
+            chain.info.p_next = &mut chain.win32_info as *mut _ as *mut c_void;
+        }
+        chain
+    }
+}
+
+impl FullScreenExclusiveChain {
+    /// Pointer to the first structure in the chain.
+    pub fn p_next(&self) -> *const c_void {
+        &self.info as *const _ as *const c_void
+    }
+}
+
+/// Get the monitor a Win32 window is on, or zero for other windows.
+pub(crate) fn monitor_for_window<W: WindowInterface>(window: &W) -> isize {
+    #[cfg(windows)]
+    if let raw_window_handle::RawWindowHandle::Win32(handle) = window.raw_window_handle() {
+        // SAFETY: The window handle is valid as long as the window is alive, which it is during this call.
+        return unsafe { MonitorFromWindow(handle.hwnd, MONITOR_DEFAULTTONEAREST) as isize };
+    }
+    #[cfg(not(windows))]
+    let _ = window;
+    0
 }
 
 impl Swapchain {
@@ -42,34 +129,48 @@ impl Swapchain {
         device: Device,
         settings: &AppSettings<Window>,
         surface: &Surface,
+    ) -> Result<Self> {
+        let window = settings.window.ok_or(Error::NoWindow)?;
+        Self::new_for_window(instance, device, settings, window, surface)
+    }
+
+    /// Create a new swapchain for a window other than the one in the [`AppSettings`]. The surface format and present mode
+    /// are still chosen based on the settings.
+    pub fn new_for_window<Window: WindowInterface, W: WindowInterface>(
+        instance: &Instance,
+        device: Device,
+        settings: &AppSettings<Window>,
+        window: &W,
+        surface: &Surface,
     ) -> Result<Self> {
         let format = choose_surface_format(settings, surface)?;
         let present_mode = choose_present_mode(settings, surface);
-        let extent = choose_swapchain_extent(settings, surface);
-
-        let image_count = {
-            let mut count = surface.capabilities().min_image_count + 1;
-            // If a maximum is set, clamp to it
-            if surface.capabilities().max_image_count != 0 {
-                count = count.clamp(0, surface.capabilities().max_image_count);
-            }
-            count
-        };
-
-        let info = vk::SwapchainCreateInfoKHR::builder()
+        let extent = choose_swapchain_extent(window, surface);
+
+        let min_image_count = choose_image_count(settings, surface.capabilities());
+        let composite_alpha = choose_composite_alpha(settings, surface.capabilities());
+
+        let full_screen_exclusive = FullScreenExclusive::new(&device, settings, window);
+        let full_screen_exclusive_chain = full_screen_exclusive.map(|exclusive| exclusive.chain());
+
+        let usage = image_usage(surface.capabilities());
+        let mut info = vk::SwapchainCreateInfoKHR::builder()
             .surface(unsafe { surface.handle() })
             .image_format(format.format)
             .image_color_space(format.color_space)
             .image_extent(extent)
             .image_sharing_mode(vk::SharingMode::EXCLUSIVE)
             .image_array_layers(1)
-            .image_usage(vk::ImageUsageFlags::COLOR_ATTACHMENT)
+            .image_usage(usage)
             .present_mode(present_mode)
-            .min_image_count(image_count)
+            .min_image_count(min_image_count)
             .clipped(true)
             .pre_transform(surface.capabilities().current_transform)
-            .composite_alpha(vk::CompositeAlphaFlagsKHR::OPAQUE)
+            .composite_alpha(composite_alpha)
             .build();
+        if let Some(chain) = &full_screen_exclusive_chain {
+            info.p_next = chain.p_next();
+        }
 
         let functions = ash::extensions::khr::Swapchain::new(instance, unsafe { &device.handle() });
         let swapchain = unsafe { functions.create_swapchain(&info, None)? };
@@ -107,8 +208,12 @@ impl Swapchain {
             format,
             present_mode,
             extent,
+            min_image_count,
+            composite_alpha,
+            image_usage: usage,
             images,
             functions,
+            full_screen_exclusive,
         })
     }
 
@@ -123,6 +228,11 @@ impl Swapchain {
         self.images.as_slice()
     }
 
+    /// Get the number of images in this swapchain
+    pub fn image_count(&self) -> usize {
+        self.images.len()
+    }
+
     /// Get the available present modes of this swapchain
     pub fn present_mode(&self) -> vk::PresentModeKHR {
         self.present_mode
@@ -137,6 +247,28 @@ impl Swapchain {
     pub fn format(&self) -> vk::SurfaceFormatKHR {
         self.format
     }
+
+    /// Get the minimum number of images requested for this swapchain. The actual number of images may be higher.
+    pub fn min_image_count(&self) -> u32 {
+        self.min_image_count
+    }
+
+    /// Get the composite alpha mode of this swapchain
+    pub fn composite_alpha(&self) -> vk::CompositeAlphaFlagsKHR {
+        self.composite_alpha
+    }
+
+    /// Get the usage flags the swapchain images were created with. This always contains
+    /// [`vk::ImageUsageFlags::COLOR_ATTACHMENT`], and transfer usage if the surface supports it.
+    pub fn image_usage(&self) -> vk::ImageUsageFlags {
+        self.image_usage
+    }
+
+    /// Get the exclusive fullscreen behaviour this swapchain was created with. Returns `None` if
+    /// [`ExtensionID::FullScreenExclusive`](crate::core::device::ExtensionID::FullScreenExclusive) is not enabled.
+    pub fn full_screen_exclusive(&self) -> Option<vk::FullScreenExclusiveEXT> {
+        self.full_screen_exclusive.map(|exclusive| exclusive.mode)
+    }
 }
 
 impl Deref for Swapchain {
@@ -170,6 +302,15 @@ fn choose_surface_format<Window: WindowInterface>(
         color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
     };
 
+    if let Some(selector) = &settings.surface_format_selector {
+        let ranked = selector(surface.formats());
+        if let Some(format) = ranked
+            .into_iter()
+            .find(|format| surface.formats().contains(format))
+        {
+            return Ok(format);
+        }
+    }
     if let Some(preferred_format) = settings.surface_format {
         if surface.formats().contains(&preferred_format) {
             return Ok(preferred_format);
@@ -199,8 +340,46 @@ fn choose_present_mode<Window: WindowInterface>(
     vk::PresentModeKHR::FIFO
 }
 
-fn choose_swapchain_extent<Window: WindowInterface>(
+fn choose_image_count<Window: WindowInterface>(
     settings: &AppSettings<Window>,
+    capabilities: &vk::SurfaceCapabilitiesKHR,
+) -> u32 {
+    let count = settings
+        .min_image_count
+        .unwrap_or(capabilities.min_image_count + 1);
+    clamp_image_count(count, capabilities)
+}
+
+/// Clamp a requested image count to the limits of a surface.
+pub(crate) fn clamp_image_count(count: u32, capabilities: &vk::SurfaceCapabilitiesKHR) -> u32 {
+    let count = count.max(capabilities.min_image_count);
+    // If a maximum is set, clamp to it
+    if capabilities.max_image_count != 0 {
+        count.min(capabilities.max_image_count)
+    } else {
+        count
+    }
+}
+
+fn choose_composite_alpha<Window: WindowInterface>(
+    settings: &AppSettings<Window>,
+    capabilities: &vk::SurfaceCapabilitiesKHR,
+) -> vk::CompositeAlphaFlagsKHR {
+    let supported = capabilities.supported_composite_alpha;
+    if let Some(mode) = settings.composite_alpha {
+        if supported.contains(mode) {
+            return mode;
+        }
+    }
+    if supported.contains(vk::CompositeAlphaFlagsKHR::OPAQUE) {
+        return vk::CompositeAlphaFlagsKHR::OPAQUE;
+    }
+    // At least one bit is guaranteed to be set, pick the lowest one.
+    vk::CompositeAlphaFlagsKHR::from_raw(supported.as_raw() & supported.as_raw().wrapping_neg())
+}
+
+fn choose_swapchain_extent<Window: WindowInterface>(
+    window: &Window,
     surface: &Surface,
 ) -> vk::Extent2D {
     if surface.capabilities().current_extent.width != u32::MAX {
@@ -208,13 +387,21 @@ fn choose_swapchain_extent<Window: WindowInterface>(
     }
 
     vk::Extent2D {
-        width: settings.window.unwrap().width().clamp(
+        width: window.width().clamp(
             surface.capabilities().min_image_extent.width,
             surface.capabilities().max_image_extent.width,
         ),
-        height: settings.window.unwrap().height().clamp(
+        height: window.height().clamp(
             surface.capabilities().min_image_extent.height,
             surface.capabilities().max_image_extent.height,
         ),
     }
 }
+
+/// Usage flags for swapchain images. Images are also created with transfer source usage if supported, so they can be read back
+/// for screenshots, see [`readback_image()`](crate::util::readback::readback_image), and with transfer destination usage if
+/// supported, so generated frames can be copied into them by a [`PresentHook`](crate::wsi::frame::PresentHook).
+pub(crate) fn image_usage(capabilities: &vk::SurfaceCapabilitiesKHR) -> vk::ImageUsageFlags {
+    vk::ImageUsageFlags::COLOR_ATTACHMENT
+        | (capabilities.supported_usage_flags & (vk::ImageUsageFlags::TRANSFER_SRC | vk::ImageUsageFlags::TRANSFER_DST))
+}
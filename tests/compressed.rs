@@ -0,0 +1,34 @@
+use ash::vk;
+
+use phobos::util::upload::MipLevelData;
+use phobos::{compressed_block, compressed_level_size};
+
+#[test]
+pub fn compressed_levels_round_up_to_blocks() {
+    let size = vk::Extent3D {
+        width: 10,
+        height: 6,
+        depth: 1,
+    };
+    // 3x2 blocks of 16 bytes, then 2x1 blocks, then a single block for each smaller level.
+    assert_eq!(compressed_level_size(vk::Format::BC7_UNORM_BLOCK, size, 0, 1).unwrap(), 96);
+    assert_eq!(compressed_level_size(vk::Format::BC7_UNORM_BLOCK, size, 1, 1).unwrap(), 32);
+    assert_eq!(compressed_level_size(vk::Format::BC7_UNORM_BLOCK, size, 3, 2).unwrap(), 32);
+    assert_eq!(compressed_block(vk::Format::ASTC_10X8_SRGB_BLOCK).unwrap().block_count(size), (1, 1));
+    assert!(compressed_level_size(vk::Format::R8G8B8A8_UNORM, size, 0, 1).is_err());
+}
+
+#[test]
+pub fn compressed_chain_matches_data_size() {
+    let size = vk::Extent3D {
+        width: 16,
+        height: 16,
+        depth: 1,
+    };
+    // BC1 levels of 4x4, 2x2, 1x1 and 1x1 blocks.
+    let data = vec![0u8; (16 + 4 + 1 + 1) * 8];
+    let levels = MipLevelData::compressed_chain(vk::Format::BC1_RGB_UNORM_BLOCK, size, 4, 1, &data).unwrap();
+    assert_eq!(levels.len(), 4);
+    assert!(MipLevelData::compressed_chain(vk::Format::BC1_RGB_UNORM_BLOCK, size, 4, 1, &data[1..]).is_err());
+    assert!(MipLevelData::compressed_chain(vk::Format::BC1_RGB_UNORM_BLOCK, size, 3, 1, &data).is_err());
+}
@@ -0,0 +1,24 @@
+use phobos::util::device_size::DeviceSize;
+
+#[test]
+pub fn checked_arithmetic() {
+    let size = DeviceSize::new(64);
+    assert_eq!(size.checked_add(32u64).unwrap().get(), 96);
+    assert_eq!(size.checked_sub(32u64).unwrap().get(), 32);
+    assert_eq!(size.checked_mul(4u64).unwrap().get(), 256);
+    size.checked_sub(65u64).expect_err("Subtraction should underflow");
+    DeviceSize::new(u64::MAX).checked_add(1u64).expect_err("Addition should overflow");
+    DeviceSize::new(u64::MAX / 2).checked_mul(3u64).expect_err("Multiplication should overflow");
+}
+
+#[test]
+pub fn alignment_and_ranges() {
+    assert_eq!(DeviceSize::new(100).align_up(64u64).unwrap().get(), 128);
+    assert_eq!(DeviceSize::new(128).align_up(64u64).unwrap().get(), 128, "Aligned sizes should not change");
+    DeviceSize::new(u64::MAX - 1).align_up(64u64).expect_err("Alignment should overflow");
+
+    let buffer = DeviceSize::new(1024);
+    assert!(buffer.contains_range(512u64, 512u64).unwrap());
+    assert!(!buffer.contains_range(512u64, 513u64).unwrap());
+    buffer.contains_range(u64::MAX, 2u64).expect_err("Range end should overflow");
+}
@@ -48,6 +48,7 @@ pub fn make_context_with_queues(
             features_1_2: Default::default(),
             features_1_3: Default::default(),
             device_extensions: vec![],
+            ray_query: false,
         })
         .build();
     let (instance, phys_device, None, device, allocator, pool, exec, None, None) =
@@ -88,6 +89,7 @@ pub fn make_context_with_settings<
             features_1_2: Default::default(),
             features_1_3: Default::default(),
             device_extensions: vec![],
+            ray_query: false,
         });
 
     let settings = callback(builder).build();
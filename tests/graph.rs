@@ -0,0 +1,229 @@
+use anyhow::Result;
+use ash::vk;
+
+use phobos::{domain, CubeFace};
+use phobos::graph::pass::{Pass, PassBuilder, PassConstants};
+use phobos::graph::pass_graph::PassGraph;
+use phobos::graph::physical_resource::PhysicalResourceBindings;
+use phobos::graph::virtual_resource::VirtualResource;
+use phobos::pipeline::PipelineStage;
+
+fn color_pass<'cb>(name: &str, color: &VirtualResource, op: vk::AttachmentLoadOp) -> Result<Pass<'cb, domain::Graphics>> {
+    Ok(PassBuilder::render(name)
+        .color_attachment(color, op, Some(vk::ClearColorValue { float32: [0.0; 4] }))?
+        .build())
+}
+
+#[test]
+pub fn export_final_color_output() -> Result<()> {
+    let color = VirtualResource::image("color");
+    let main = PassGraph::<domain::Graphics>::new()
+        .add_pass(color_pass("main", &color, vk::AttachmentLoadOp::CLEAR)?)?
+        .build()?;
+
+    let state = main.export(&color).expect("Color output should be exported.");
+    assert_eq!(state.layout, vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
+    assert_eq!(state.stage, PipelineStage::COLOR_ATTACHMENT_OUTPUT);
+    assert_eq!(state.access, vk::AccessFlags2::COLOR_ATTACHMENT_WRITE);
+    assert!(main.export(&VirtualResource::image("unused")).is_none());
+
+    // The overlay graph should be able to consume the imported output.
+    let overlay = PassGraph::<domain::Graphics>::new()
+        .import(&color, state)
+        .add_pass(color_pass("overlay", &color, vk::AttachmentLoadOp::LOAD)?)?
+        .build()?;
+    assert!(overlay.export(&color).is_some());
+    Ok(())
+}
+
+#[test]
+pub fn clear_buffer_before_compute() -> Result<()> {
+    let counters = VirtualResource::buffer("counters");
+    let clear: Pass<domain::Compute> = PassBuilder::clear_buffer(&counters, 0);
+    let cleared = clear.output(&counters).unwrap().clone();
+    let cull = PassBuilder::<domain::Compute>::new("cull")
+        .write_buffer(&cleared, PipelineStage::COMPUTE_SHADER)
+        .build();
+    let graph = PassGraph::<domain::Compute>::new()
+        .add_pass(clear)?
+        .add_pass(cull)?
+        .build()?;
+
+    let state = graph.export(&counters).expect("Counter buffer should be exported.");
+    assert_eq!(state.stage, PipelineStage::COMPUTE_SHADER);
+    assert_eq!(state.access, vk::AccessFlags2::SHADER_WRITE);
+    Ok(())
+}
+
+#[test]
+pub fn separate_depth_stencil_layouts() -> Result<()> {
+    let depth = VirtualResource::image("depth");
+    let stencil = VirtualResource::image("stencil");
+    let mask: Pass<domain::Graphics> = PassBuilder::render("mask")
+        .clear_stencil_attachment(&stencil, 0)?
+        .build();
+    let written = mask.output(&stencil).unwrap().clone();
+    let main: Pass<domain::Graphics> = PassBuilder::render("main")
+        .clear_depth_attachment(&depth, phobos::ClearDepthStencil { depth: 1.0, stencil: 0 })?
+        .sample_stencil(&written, PipelineStage::FRAGMENT_SHADER)
+        .build();
+    let graph = PassGraph::<domain::Graphics>::new()
+        .add_pass(mask)?
+        .add_pass(main)?
+        .build()?;
+
+    let depth_state = graph.export(&depth).expect("Depth output should be exported.");
+    assert_eq!(depth_state.layout, vk::ImageLayout::DEPTH_ATTACHMENT_OPTIMAL);
+    let stencil_state = graph.export(&stencil).expect("Stencil output should be exported.");
+    assert_eq!(stencil_state.layout, vk::ImageLayout::STENCIL_READ_ONLY_OPTIMAL);
+    assert!(stencil_state.access.contains(vk::AccessFlags2::SHADER_READ));
+    Ok(())
+}
+
+#[test]
+pub fn dry_run_without_bindings() -> Result<()> {
+    let color = VirtualResource::image("color");
+    let main = color_pass("main", &color, vk::AttachmentLoadOp::CLEAR)?;
+    let written = main.output(&color).unwrap().clone();
+    let post: Pass<domain::Graphics> = PassBuilder::new("post")
+        .sample_image(&written, PipelineStage::FRAGMENT_SHADER)
+        .build();
+    let graph = PassGraph::<domain::Graphics>::new()
+        .add_pass(main)?
+        .add_pass(post)?
+        .build()?;
+
+    let dry_run = graph.dry_run(&PhysicalResourceBindings::new())?;
+    assert_eq!(dry_run.unbound, vec!["color".to_owned()]);
+    let passes = dry_run.passes().map(|pass| pass.name.as_str()).collect::<Vec<_>>();
+    let main_index = passes.iter().position(|&name| name == "main").unwrap();
+    let post_index = passes.iter().position(|&name| name == "post").unwrap();
+    assert!(main_index < post_index);
+    let barrier = dry_run
+        .barriers()
+        .find(|barrier| barrier.new_layout == vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+        .expect("Sampling the color output should transition it.");
+    assert_eq!(barrier.resource, "color");
+    assert_eq!(barrier.old_layout, vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
+    assert!(barrier.aspect.is_none());
+    Ok(())
+}
+
+#[test]
+pub fn sample_after_generate_mipmaps() -> Result<()> {
+    let color = VirtualResource::image("color");
+    let main = color_pass("main", &color, vk::AttachmentLoadOp::CLEAR)?;
+    let written = main.output(&color).unwrap().clone();
+    let mips: Pass<domain::Graphics> = PassBuilder::generate_mipmaps(&written, vk::Filter::LINEAR);
+    let mipmapped = mips.output(&written).unwrap().clone();
+    let post: Pass<domain::Graphics> = PassBuilder::new("post")
+        .sample_image(&mipmapped, PipelineStage::FRAGMENT_SHADER)
+        .build();
+    let graph = PassGraph::<domain::Graphics>::new()
+        .add_pass(main)?
+        .add_pass(mips)?
+        .add_pass(post)?
+        .build()?;
+
+    let dry_run = graph.dry_run(&PhysicalResourceBindings::new())?;
+    let to_blit = dry_run
+        .barriers()
+        .find(|barrier| barrier.new_layout == vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+        .expect("Generating mipmaps should transition the color output.");
+    assert_eq!(to_blit.old_layout, vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
+    let to_sample = dry_run
+        .barriers()
+        .find(|barrier| barrier.new_layout == vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+        .expect("Sampling the mipmapped image should transition it.");
+    assert_eq!(to_sample.old_layout, vk::ImageLayout::TRANSFER_SRC_OPTIMAL);
+    assert_eq!(to_sample.src_stage, PipelineStage::BLIT);
+    Ok(())
+}
+
+#[test]
+pub fn sample_after_rendering_cube_faces() -> Result<()> {
+    let mut graph = PassGraph::<domain::Graphics>::new();
+    let mut sample = PassBuilder::new("sample_environment");
+    for face in CubeFace::ALL {
+        let resource = VirtualResource::cube_face("environment", face);
+        let pass = color_pass(&format!("render_{}", face.suffix()), &resource, vk::AttachmentLoadOp::CLEAR)?;
+        sample = sample.sample_image(pass.output(&resource).unwrap(), PipelineStage::FRAGMENT_SHADER);
+        graph = graph.add_pass(pass)?;
+    }
+    let graph = graph.add_pass(sample.build())?.build()?;
+
+    let dry_run = graph.dry_run(&PhysicalResourceBindings::new())?;
+    for face in CubeFace::ALL {
+        let resource = VirtualResource::cube_face("environment", face);
+        let barrier = dry_run
+            .barriers()
+            .find(|barrier| barrier.resource == resource.name() && barrier.new_layout == vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .expect("Sampling the cube map should transition every face.");
+        assert_eq!(barrier.old_layout, vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
+    }
+    Ok(())
+}
+
+/// Build a TLAS in one pass and read it in `stage` in the next, and check that the read waits for the build.
+fn assert_waits_for_tlas_build(tlas: VirtualResource, stage: PipelineStage) -> Result<()> {
+    let build: Pass<domain::Compute> = PassBuilder::new("build_tlas")
+        .write_acceleration_structure(&tlas)
+        .build();
+    let built = build.output(&tlas).unwrap().clone();
+    let read: Pass<domain::Compute> = PassBuilder::new("read_tlas")
+        .read_acceleration_structure(&built, stage)
+        .build();
+    let graph = PassGraph::<domain::Compute>::new()
+        .add_pass(build)?
+        .add_pass(read)?
+        .build()?;
+
+    let dry_run = graph.dry_run(&PhysicalResourceBindings::new())?;
+    let barrier = dry_run
+        .barriers()
+        .find(|barrier| barrier.dst_stage == stage)
+        .expect("Reading the TLAS should wait for its build.");
+    assert_eq!(barrier.resource, "tlas");
+    assert_eq!(barrier.src_stage, PipelineStage::ACCELERATION_STRUCTURE_BUILD_KHR);
+    assert_eq!(barrier.src_access, vk::AccessFlags2::ACCELERATION_STRUCTURE_WRITE_KHR);
+    assert_eq!(barrier.dst_access, vk::AccessFlags2::ACCELERATION_STRUCTURE_READ_KHR);
+    assert_eq!(barrier.aspect, None);
+    Ok(())
+}
+
+#[test]
+pub fn ray_query_waits_for_tlas_build() -> Result<()> {
+    // Acceleration structures used to be tracked as buffers, which must keep working.
+    assert_waits_for_tlas_build(VirtualResource::buffer("tlas"), PipelineStage::COMPUTE_SHADER)
+}
+
+#[test]
+pub fn trace_rays_waits_for_tlas_build() -> Result<()> {
+    assert_waits_for_tlas_build(VirtualResource::acceleration_structure("tlas"), PipelineStage::RAY_TRACING_SHADER_KHR)
+}
+
+#[test]
+pub fn multiview_pass() -> Result<()> {
+    let color = VirtualResource::image("color");
+    let stereo: Pass<domain::Graphics> = PassBuilder::render("stereo")
+        .color_attachment(&color, vk::AttachmentLoadOp::CLEAR, Some(vk::ClearColorValue { float32: [0.0; 4] }))?
+        .view_mask(0b11)?
+        .build();
+    assert!(PassBuilder::<domain::Graphics>::new("compute").view_mask(0b11).is_err());
+    let graph = PassGraph::<domain::Graphics>::new().add_pass(stereo)?.build()?;
+
+    let dry_run = graph.dry_run(&PhysicalResourceBindings::new())?;
+    let pass = dry_run.passes().find(|pass| pass.name == "stereo").unwrap();
+    assert_eq!(pass.view_mask, 0b11);
+    Ok(())
+}
+
+#[test]
+pub fn pass_constants_are_deterministic() {
+    let first = PassConstants::new(10, 0);
+    assert_eq!(first, PassConstants::new(10, 0));
+    let second = PassConstants::new(10, 1);
+    assert_eq!(first.frame_seed, second.frame_seed);
+    assert_ne!(first.pass_seed, second.pass_seed);
+    assert_ne!(first.frame_seed, PassConstants::new(11, 0).frame_seed);
+}
@@ -0,0 +1,63 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use ash::vk;
+
+use phobos::impl_vertex_input;
+use phobos::pipeline::vertex::{VertexAttribute, VertexInput};
+use phobos::ShaderCreateInfo;
+
+fn hash(shader: &ShaderCreateInfo) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    shader.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[test]
+pub fn specialization_constants_are_hashed() {
+    let shader = ShaderCreateInfo::from_spirv(vk::ShaderStageFlags::COMPUTE, vec![0x07230203, 0x00010000]);
+    let small = shader.clone().specialization_constant(0, &32u32);
+    let large = shader.clone().specialization_constant(0, &64u32);
+    assert_ne!(small, large);
+    assert_ne!(hash(&small), hash(&large));
+    assert_ne!(shader, small);
+    assert_eq!(small, shader.specialization_constant(0, &32u32));
+    assert_eq!(small.specialization_constants().collect::<Vec<_>>(), vec![(0, 32u32.to_ne_bytes().as_slice())]);
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct Vertex {
+    position: [f32; 3],
+    color: [u8; 4],
+    uv: [f32; 2],
+    index: u32,
+}
+
+impl_vertex_input!(Vertex { position, color, uv, index });
+
+#[test]
+pub fn vertex_layout_from_struct() {
+    assert_eq!(Vertex::STRIDE, 28);
+    assert_eq!(
+        Vertex::attributes(),
+        vec![
+            VertexAttribute {
+                format: vk::Format::R32G32B32_SFLOAT,
+                offset: 0,
+            },
+            VertexAttribute {
+                format: vk::Format::R8G8B8A8_UINT,
+                offset: 12,
+            },
+            VertexAttribute {
+                format: vk::Format::R32G32_SFLOAT,
+                offset: 16,
+            },
+            VertexAttribute {
+                format: vk::Format::R32_UINT,
+                offset: 24,
+            },
+        ]
+    );
+}
@@ -0,0 +1,35 @@
+use ash::vk;
+
+use phobos::util::readback::{convert_to_rgba8, f16_to_f32};
+
+#[test]
+pub fn half_floats() {
+    assert_eq!(f16_to_f32(0x3c00), 1.0);
+    assert_eq!(f16_to_f32(0x3800), 0.5);
+    assert_eq!(f16_to_f32(0xc000), -2.0);
+    assert_eq!(f16_to_f32(0x0000), 0.0);
+    assert_eq!(f16_to_f32(0x0001), 2f32.powi(-24), "Subnormals should be converted");
+    assert_eq!(f16_to_f32(0x7c00), f32::INFINITY);
+    assert_eq!(f16_to_f32(0xfc00), f32::NEG_INFINITY);
+    assert!(f16_to_f32(0x7e00).is_nan());
+}
+
+#[test]
+pub fn rgba8_conversion() {
+    assert_eq!(convert_to_rgba8(vk::Format::R8_UNORM, &[7, 9]).unwrap(), [7, 7, 7, 255, 9, 9, 9, 255]);
+    assert_eq!(convert_to_rgba8(vk::Format::B8G8R8A8_SRGB, &[1, 2, 3, 4]).unwrap(), [3, 2, 1, 4]);
+    // Red is fully set, green is zero, blue is half and alpha is fully set.
+    let packed = (0x3ffu32 | (0x200 << 20) | (3 << 30)).to_le_bytes();
+    assert_eq!(convert_to_rgba8(vk::Format::A2B10G10R10_UNORM_PACK32, &packed).unwrap(), [255, 0, 128, 255]);
+    let halves = [0x3c00u16, 0x3800, 0xbc00, 0x4000]
+        .iter()
+        .flat_map(|half| half.to_le_bytes())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        convert_to_rgba8(vk::Format::R16G16B16A16_SFLOAT, &halves).unwrap(),
+        [255, 128, 0, 255],
+        "Floats should be clamped to [0, 1]"
+    );
+    assert_eq!(convert_to_rgba8(vk::Format::R8G8B8A8_UNORM, &[1, 2, 3, 4, 5]).unwrap(), [1, 2, 3, 4]);
+    convert_to_rgba8(vk::Format::BC1_RGB_UNORM_BLOCK, &[0; 8]).expect_err("Compressed formats should be unsupported");
+}
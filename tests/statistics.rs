@@ -0,0 +1,36 @@
+use phobos::{PipelineStatistics, PipelineStatisticsAggregator};
+
+fn sample(fragments: u64) -> PipelineStatistics {
+    PipelineStatistics {
+        fragment_shader_invocations: Some(fragments),
+        ..Default::default()
+    }
+}
+
+#[test]
+pub fn aggregator_keeps_sliding_window() {
+    let mut aggregator = PipelineStatisticsAggregator::new(4);
+    for value in 1..=6 {
+        aggregator.record("main", sample(value));
+    }
+
+    let summary = aggregator
+        .summarize("main", |stats| stats.fragment_shader_invocations)
+        .expect("Pass should have samples.");
+    assert_eq!(summary.samples, 4, "Only the last 4 samples should be kept.");
+    assert_eq!(summary.min, 3);
+    assert_eq!(summary.max, 6);
+    assert_eq!(summary.average, 4.5);
+    assert_eq!(summary.p50, 4);
+}
+
+#[test]
+pub fn aggregator_skips_missing_statistics() {
+    let mut aggregator = PipelineStatisticsAggregator::new(8);
+    aggregator.record("main", sample(10));
+
+    let summary = aggregator.summary("main").expect("Pass should have samples.");
+    assert!(summary.fragment_shader_invocations.is_some());
+    assert!(summary.compute_shader_invocations.is_none());
+    assert!(aggregator.summary("unknown").is_none(), "Unknown pass should have no summary.");
+}
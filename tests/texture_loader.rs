@@ -0,0 +1,191 @@
+#![cfg(feature = "texture-loader")]
+
+use ash::vk;
+
+use phobos::texture_loader::{BasisEncoding, BasisInfo, Supercompression, TextureEncoding, TextureFile};
+
+fn put_u32(bytes: &mut [u8], offset: usize, value: u32) {
+    bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+fn put_u64(bytes: &mut [u8], offset: usize, value: u64) {
+    bytes[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+}
+
+/// Build the headers of a 2D DX10 DDS file with the given DXGI format, without any texel data.
+fn dx10_dds(width: u32, height: u32, mip_levels: u32, dxgi_format: u32, array_size: u32) -> Vec<u8> {
+    let mut bytes = vec![0u8; 148];
+    bytes[..4].copy_from_slice(b"DDS ");
+    put_u32(&mut bytes, 4, 124);
+    put_u32(&mut bytes, 12, height);
+    put_u32(&mut bytes, 16, width);
+    put_u32(&mut bytes, 28, mip_levels);
+    put_u32(&mut bytes, 80, 0x4);
+    bytes[84..88].copy_from_slice(b"DX10");
+    put_u32(&mut bytes, 128, dxgi_format);
+    put_u32(&mut bytes, 132, 3);
+    put_u32(&mut bytes, 140, array_size);
+    bytes
+}
+
+#[test]
+pub fn dds_layers_are_grouped_per_mip_level() {
+    // A DX10 DDS file with 2 array layers of an 8x8 BC1 texture with 2 mip levels.
+    let mut bytes = dx10_dds(8, 8, 2, 71, 2);
+    // Layer 0 levels, then layer 1 levels. The base level has 2x2 blocks of 8 bytes, the next level a single block.
+    for (layer, level_blocks) in [(0u8, [4, 1]), (1u8, [4, 1])] {
+        for (level, blocks) in level_blocks.into_iter().enumerate() {
+            bytes.extend(std::iter::repeat_n(layer * 16 + level as u8, blocks * 8));
+        }
+    }
+
+    let file = TextureFile::parse(&bytes).unwrap();
+    assert_eq!(file.encoding, TextureEncoding::Format(vk::Format::BC1_RGBA_UNORM_BLOCK));
+    assert_eq!(file.image_layers(), 2);
+    assert_eq!(file.mip_levels(), 2);
+    assert_eq!(file.levels[0].data.len(), 64);
+    assert_eq!(file.levels[1].data[..8], [1; 8]);
+    assert_eq!(file.levels[1].data[8..], [17; 8]);
+    assert!(TextureFile::parse(&bytes[..bytes.len() - 1]).is_err());
+}
+
+#[test]
+pub fn ktx2_cubemap_levels_are_borrowed() {
+    let identifier = [0xAB, b'K', b'T', b'X', b' ', b'2', b'0', 0xBB, b'\r', b'\n', 0x1A, b'\n'];
+    // A 4x4 RGBA8 cubemap with 2 mip levels, stored after the level index.
+    let mut bytes = vec![0u8; 80 + 2 * 24];
+    bytes[..12].copy_from_slice(&identifier);
+    put_u32(&mut bytes, 12, vk::Format::R8G8B8A8_UNORM.as_raw() as u32);
+    put_u32(&mut bytes, 20, 4);
+    put_u32(&mut bytes, 24, 4);
+    put_u32(&mut bytes, 36, 6);
+    put_u32(&mut bytes, 40, 2);
+    let level_sizes = [4 * 4 * 4 * 6, 2 * 2 * 4 * 6];
+    let mut offset = bytes.len() as u64;
+    for (level, size) in level_sizes.into_iter().enumerate() {
+        put_u64(&mut bytes, 80 + level * 24, offset);
+        put_u64(&mut bytes, 88 + level * 24, size);
+        put_u64(&mut bytes, 96 + level * 24, size);
+        offset += size;
+    }
+    bytes.resize(offset as usize, 0);
+
+    let file = TextureFile::parse(&bytes).unwrap();
+    assert_eq!(file.encoding, TextureEncoding::Format(vk::Format::R8G8B8A8_UNORM));
+    assert_eq!(file.supercompression, Supercompression::None);
+    assert!(file.is_cube());
+    assert_eq!(file.image_layers(), 6);
+    assert_eq!(file.size.depth, 1);
+    assert_eq!(file.levels[1].data.len(), 96);
+}
+
+/// Build a KTX2 file with a single 1x1 RGBA8 mip level, stored with `scheme` supercompression.
+fn supercompressed_ktx2(scheme: u32, data: &[u8]) -> Vec<u8> {
+    let identifier = [0xAB, b'K', b'T', b'X', b' ', b'2', b'0', 0xBB, b'\r', b'\n', 0x1A, b'\n'];
+    let mut bytes = vec![0u8; 80 + 24];
+    bytes[..12].copy_from_slice(&identifier);
+    put_u32(&mut bytes, 12, vk::Format::R8G8B8A8_UNORM.as_raw() as u32);
+    put_u32(&mut bytes, 20, 1);
+    put_u32(&mut bytes, 36, 1);
+    put_u32(&mut bytes, 40, 1);
+    put_u32(&mut bytes, 44, scheme);
+    let offset = bytes.len() as u64;
+    put_u64(&mut bytes, 80, offset);
+    put_u64(&mut bytes, 88, data.len() as u64);
+    put_u64(&mut bytes, 96, 4);
+    bytes.extend_from_slice(data);
+    bytes
+}
+
+#[test]
+pub fn ktx2_zstd_levels_are_decompressed() {
+    // A single segment Zstandard frame with a content size of 4, holding one raw block.
+    let frame = [0x28, 0xB5, 0x2F, 0xFD, 0x20, 0x04, 0x21, 0x00, 0x00, 1, 2, 3, 4];
+    let bytes = supercompressed_ktx2(2, &frame);
+    let file = TextureFile::parse(&bytes).unwrap();
+    assert_eq!(file.supercompression, Supercompression::Zstandard);
+    assert_eq!(file.level_data(0).unwrap().as_ref(), &[1, 2, 3, 4]);
+}
+
+#[test]
+pub fn ktx2_zlib_levels_are_decompressed() {
+    // A ZLIB stream with a single stored deflate block, followed by the Adler-32 checksum of the data.
+    let stream = [0x78, 0x01, 0x01, 0x04, 0x00, 0xFB, 0xFF, 1, 2, 3, 4, 0x00, 0x18, 0x00, 0x0B];
+    let bytes = supercompressed_ktx2(3, &stream);
+    let file = TextureFile::parse(&bytes).unwrap();
+    assert_eq!(file.supercompression, Supercompression::Zlib);
+    assert_eq!(file.level_data(0).unwrap().as_ref(), &[1, 2, 3, 4]);
+    // The decompressed size must match the size in the level index.
+    let truncated = supercompressed_ktx2(3, &stream[..stream.len() - 1]);
+    assert!(TextureFile::parse(&truncated).unwrap().level_data(0).is_err());
+}
+
+#[test]
+pub fn malformed_dds_files_are_rejected() {
+    TextureFile::parse(&dx10_dds(8, 8, 1, 71, 1)[..100]).expect_err("Truncated header should be rejected");
+    // Cubemap array with so many layers that the number of images overflows.
+    let mut cube = dx10_dds(4, 4, 1, 71, u32::MAX);
+    put_u32(&mut cube, 136, 0x4);
+    TextureFile::parse(&cube).expect_err("Huge array size should be rejected");
+    TextureFile::parse(&dx10_dds(4, 4, 1, 71, 1 << 20)).expect_err("Array layers missing from the file should be rejected");
+    TextureFile::parse(&dx10_dds(u32::MAX, u32::MAX, 1, 2, 1)).expect_err("Huge dimensions should be rejected");
+    TextureFile::parse(&dx10_dds(8, 8, u32::MAX, 71, 1)).expect_err("Huge mip count should be rejected");
+    // An 8x8 texture has at most 4 mip levels.
+    let mut bytes = dx10_dds(8, 8, 5, 71, 1);
+    bytes.resize(bytes.len() + 4 * 8 + 3 * 8, 0);
+    TextureFile::parse(&bytes).expect_err("More mip levels than the size allows should be rejected");
+}
+
+#[test]
+pub fn malformed_ktx2_files_are_rejected() {
+    let frame = [0x28, 0xB5, 0x2F, 0xFD, 0x20, 0x04, 0x21, 0x00, 0x00, 1, 2, 3, 4];
+    let bytes = supercompressed_ktx2(2, &frame);
+    TextureFile::parse(&bytes[..60]).expect_err("Truncated header should be rejected");
+    let mut huge = bytes.clone();
+    put_u64(&mut huge, 96, u64::MAX);
+    TextureFile::parse(&huge).expect_err("Huge uncompressed size should be rejected");
+    let mut levels = bytes.clone();
+    put_u32(&mut levels, 40, u32::MAX);
+    TextureFile::parse(&levels).expect_err("Huge mip count should be rejected");
+    let mut layers = bytes;
+    put_u32(&mut layers, 32, u32::MAX);
+    put_u32(&mut layers, 36, 6);
+    TextureFile::parse(&layers).expect_err("Huge array size should be rejected");
+}
+
+#[test]
+pub fn ktx2_uastc_zstd_levels_are_decompressed() {
+    let identifier = [0xAB, b'K', b'T', b'X', b' ', b'2', b'0', 0xBB, b'\r', b'\n', 0x1A, b'\n'];
+    // A 4x4 UASTC texture with a single block, supercompressed with Zstandard.
+    let mut bytes = vec![0u8; 80 + 24 + 44];
+    bytes[..12].copy_from_slice(&identifier);
+    put_u32(&mut bytes, 20, 4);
+    put_u32(&mut bytes, 24, 4);
+    put_u32(&mut bytes, 36, 1);
+    put_u32(&mut bytes, 40, 1);
+    put_u32(&mut bytes, 44, 2);
+    // The data format descriptor has a basic block with the UASTC color model and a single RGB sample.
+    put_u32(&mut bytes, 48, 104);
+    put_u32(&mut bytes, 104, 44);
+    put_u32(&mut bytes, 112, 40 << 16);
+    put_u32(&mut bytes, 116, 166);
+    let mut frame = vec![0x28, 0xB5, 0x2F, 0xFD, 0x20, 0x10, 0x81, 0x00, 0x00];
+    frame.extend(1..=16u8);
+    let offset = bytes.len() as u64;
+    put_u64(&mut bytes, 80, offset);
+    put_u64(&mut bytes, 88, frame.len() as u64);
+    put_u64(&mut bytes, 96, 16);
+    bytes.extend_from_slice(&frame);
+
+    let file = TextureFile::parse(&bytes).unwrap();
+    assert_eq!(
+        file.encoding,
+        TextureEncoding::Basis(BasisInfo {
+            encoding: BasisEncoding::Uastc,
+            srgb: false,
+            has_alpha: false,
+        })
+    );
+    assert_eq!(file.supercompression, Supercompression::Zstandard);
+    assert_eq!(file.level_data(0).unwrap().as_ref(), (1..=16u8).collect::<Vec<_>>());
+}